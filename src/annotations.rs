@@ -0,0 +1,81 @@
+use crate::message_cached::MessageCached;
+use chrono::{DateTime, Local};
+use core::fmt;
+use std::collections::BTreeSet;
+use tokio::time::Instant;
+
+/// Maximum number of user markers kept, the oldest ones are dropped first.
+const MAX_MARKERS: usize = 1000;
+
+/// Row inserted by the user in the stream of messages, e.g. where the e-stop was pressed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UserMarker {
+    pub number: u32,
+    /// Index of the first message received after the marker.
+    pub index: u64,
+    pub time: Instant,
+    pub wall: DateTime<Local>,
+    pub note: String,
+}
+
+impl fmt::Display for UserMarker {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Marker {} — {}",
+            self.number,
+            self.wall.format("%H:%M:%S")
+        )?;
+        if !self.note.is_empty() {
+            write!(f, " — {}", self.note)?;
+        }
+        Ok(())
+    }
+}
+
+/// Bookmarked messages and user markers, kept when the messages leave the buffer.
+#[derive(Debug, Clone, Default)]
+pub struct Annotations {
+    /// Indices of the bookmarked messages.
+    pub bookmarks: BTreeSet<u64>,
+    /// Markers, oldest first.
+    pub markers: Vec<UserMarker>,
+    /// Index of the next message received, where a marker added now goes.
+    next_index: u64,
+    next_number: u32,
+}
+
+impl Annotations {
+    pub fn on_message(&mut self, msg: &MessageCached) {
+        self.next_index = msg.index + 1;
+    }
+
+    /// Inserts a marker before the next message received.
+    pub fn add_marker(&mut self, note: &str) {
+        self.next_number += 1;
+        self.markers.push(UserMarker {
+            number: self.next_number,
+            index: self.next_index,
+            time: Instant::now(),
+            wall: Local::now(),
+            note: note.trim().to_owned(),
+        });
+        if self.markers.len() > MAX_MARKERS {
+            self.markers.remove(0);
+        }
+    }
+
+    pub fn toggle_bookmark(&mut self, index: u64) {
+        if !self.bookmarks.remove(&index) {
+            self.bookmarks.insert(index);
+        }
+    }
+
+    pub fn is_bookmarked(&self, index: u64) -> bool {
+        self.bookmarks.contains(&index)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bookmarks.is_empty() && self.markers.is_empty()
+    }
+}
@@ -0,0 +1,217 @@
+use crate::filter::NodeSet;
+use egui::{Color32, Ui};
+use oze_canopen::canopen::{NodeId, RxMessage};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Nodes the driver puts in operational when it sees them boot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AutoStart {
+    /// Nodes started, every node if empty.
+    pub nodes: NodeSet,
+    /// Time between the boot-up and the NMT Start.
+    pub delay: Duration,
+}
+
+impl AutoStart {
+    pub fn applies(&self, node_id: NodeId) -> bool {
+        self.nodes.is_empty() || self.nodes.contains(node_id)
+    }
+}
+
+/// Node announcing its boot-up, a heartbeat carrying the `0x00` state.
+pub fn boot_up(msg: &RxMessage) -> Option<NodeId> {
+    let node_id = msg.cob_id.checked_sub(0x700)?;
+    (msg.dlc == 1 && msg.data[0] == 0x00 && (1..=127).contains(&node_id))
+        .then(|| NodeId::try_from(node_id).ok())
+        .flatten()
+}
+
+/// Boot-ups waiting for the delay before their NMT Start, run by the driver.
+#[derive(Debug, Default)]
+pub struct AutoStarter {
+    pending: Vec<(NodeId, Instant)>,
+}
+
+impl AutoStarter {
+    /// Schedules the start of a node seen booting at `now`, returns the node if it is started.
+    /// A node booting again restarts its delay.
+    pub fn on_frame(
+        &mut self,
+        config: Option<&AutoStart>,
+        msg: &RxMessage,
+        now: Instant,
+    ) -> Option<NodeId> {
+        let config = config?;
+        let node_id = boot_up(msg).filter(|node_id| config.applies(*node_id))?;
+        self.pending.retain(|(pending, _)| *pending != node_id);
+        self.pending.push((node_id, now + config.delay));
+        Some(node_id)
+    }
+
+    /// Time of the next start.
+    pub fn next_due(&self) -> Option<Instant> {
+        self.pending.iter().map(|(_, due)| *due).min()
+    }
+
+    /// Nodes to start at `now`.
+    pub fn take_due(&mut self, now: Instant) -> Vec<NodeId> {
+        let (due, pending) = self.pending.iter().partition(|(_, due)| *due <= now);
+        self.pending = pending;
+        due.into_iter().map(|(node_id, _)| node_id).collect()
+    }
+
+    /// Forgets the scheduled starts, e.g. when the feature is turned off.
+    pub fn clear(&mut self) {
+        self.pending.clear();
+    }
+}
+
+/// Auto-start as set in the NMT tab of the sender.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutoStartEditor {
+    /// Not saved with the settings, the viewer only writes to the bus once enabled in the session.
+    #[serde(skip)]
+    pub enabled: bool,
+    /// Node IDs and ranges, see [`NodeSet::parse`], every node if empty.
+    pub nodes: String,
+    pub delay_ms: u64,
+}
+
+impl Default for AutoStartEditor {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            nodes: String::new(),
+            delay_ms: 500,
+        }
+    }
+}
+
+impl AutoStartEditor {
+    /// Configuration handed to the driver, `None` if disabled or the node list is invalid.
+    pub fn config(&self) -> Option<AutoStart> {
+        if !self.enabled {
+            return None;
+        }
+        Some(AutoStart {
+            nodes: NodeSet::parse(&self.nodes).ok()?,
+            delay: Duration::from_millis(self.delay_ms),
+        })
+    }
+
+    /// Nodes started and the delay, for the indicator of the top bar.
+    pub fn summary(&self) -> String {
+        let nodes = match self.nodes.trim() {
+            "" => "every node",
+            nodes => nodes,
+        };
+        format!(
+            "NMT Start sent to {nodes} {} ms after its boot-up",
+            self.delay_ms
+        )
+    }
+
+    pub fn ui(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.enabled, "Auto-start on boot-up")
+                .on_hover_text(
+                "Send NMT Start to the nodes seen booting, the viewer writes to the bus on its own. \
+                Turned off on every start of the viewer",
+            );
+            ui.add(
+                egui::DragValue::new(&mut self.delay_ms)
+                    .range(0..=60_000)
+                    .prefix("after ")
+                    .suffix(" ms"),
+            );
+        });
+        ui.horizontal(|ui| {
+            ui.label("Nodes:");
+            ui.add(
+                egui::TextEdit::singleline(&mut self.nodes)
+                    .hint_text("all, or 1-4 7")
+                    .desired_width(120.0),
+            );
+            if let Err(e) = NodeSet::parse(&self.nodes) {
+                ui.colored_label(Color32::RED, e);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{boot_up, AutoStart, AutoStartEditor, AutoStarter};
+    use crate::filter::NodeSet;
+    use oze_canopen::canopen::RxMessage;
+    use std::time::Duration;
+    use tokio::time::Instant;
+
+    #[test]
+    fn test_auto_start() {
+        let start = Instant::now();
+        let heartbeat = |cob_id, state, dlc| RxMessage {
+            timestamp: start,
+            cob_id,
+            data: [state, 0, 0, 0, 0, 0, 0, 0],
+            dlc,
+        };
+        assert_eq!(boot_up(&heartbeat(0x705, 0x00, 1)), Some(5));
+        assert_eq!(boot_up(&heartbeat(0x705, 0x7F, 1)), None);
+        assert_eq!(boot_up(&heartbeat(0x700, 0x00, 1)), None);
+        assert_eq!(boot_up(&heartbeat(0x185, 0x00, 1)), None);
+
+        let config = AutoStart {
+            nodes: NodeSet::parse("1-5").unwrap(),
+            delay: Duration::from_millis(100),
+        };
+        let mut starter = AutoStarter::default();
+        assert_eq!(starter.on_frame(None, &heartbeat(0x705, 0, 1), start), None);
+        assert_eq!(
+            starter.on_frame(Some(&config), &heartbeat(0x706, 0, 1), start),
+            None
+        );
+        assert_eq!(
+            starter.on_frame(Some(&config), &heartbeat(0x705, 0, 1), start),
+            Some(5)
+        );
+        let later = start + Duration::from_millis(50);
+        assert_eq!(
+            starter.on_frame(Some(&config), &heartbeat(0x702, 0, 1), later),
+            Some(2)
+        );
+        // Node 5 boots again, its delay starts over.
+        assert_eq!(
+            starter.on_frame(Some(&config), &heartbeat(0x705, 0, 1), later),
+            Some(5)
+        );
+        assert_eq!(starter.next_due(), Some(later + Duration::from_millis(100)));
+        assert!(starter
+            .take_due(start + Duration::from_millis(100))
+            .is_empty());
+        assert_eq!(starter.take_due(later + Duration::from_millis(100)), [2, 5]);
+        assert_eq!(starter.next_due(), None);
+
+        let mut editor = AutoStartEditor::default();
+        assert_eq!(editor.config(), None);
+        editor.enabled = true;
+        assert!(editor.config().is_some_and(|config| config.applies(42)));
+        editor.nodes = "0".to_owned();
+        assert_eq!(editor.config(), None);
+
+        // Saved settings keep the nodes and delay but never turn it on.
+        editor.nodes = "1-4".to_owned();
+        editor.delay_ms = 200;
+        let saved = serde_json::to_string(&editor).unwrap();
+        let loaded: AutoStartEditor = serde_json::from_str(&saved).unwrap();
+        assert!(!loaded.enabled);
+        assert_eq!(loaded.nodes, "1-4");
+        assert_eq!(loaded.delay_ms, 200);
+        let loaded: AutoStartEditor =
+            serde_json::from_str(r#"{"enabled":true,"nodes":"","delay_ms":500}"#).unwrap();
+        assert!(!loaded.enabled);
+    }
+}
@@ -1,64 +1,138 @@
 use oze_canopen::interface::CanOpenInfo;
 use std::{
     collections::VecDeque,
-    sync::Arc,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
     time::{Duration, Instant},
 };
-use tokio::{sync::Mutex, time::sleep};
+use tokio::{
+    sync::{watch, Mutex},
+    time::sleep,
+};
 
-pub const RATES_LEN: usize = 1024;
+/// Points kept per series, an hour at one point per 10 ms.
+pub const RATES_LEN: usize = 360_000;
 pub const RATE_PERIOD: usize = 4;
 
-pub type RatesData = Vec<[f64; 2]>;
+/// Bits of a frame as counted by the `oze_canopen` receiver, without stuff bits.
+pub fn counted_bits(dlc: usize) -> usize {
+    dlc * 8 + 46
+}
+
+/// Bitrates over the time since start, in bits per second.
+#[derive(Debug, Clone, Default)]
+pub struct RatesData {
+    /// Frames of the other nodes.
+    pub rx: VecDeque<[f64; 2]>,
+    /// Frames sent by the viewer.
+    pub tx: VecDeque<[f64; 2]>,
+    /// Every frame on the bus, what the bus load is computed from.
+    pub total: VecDeque<[f64; 2]>,
+    /// Time 0 of the series.
+    pub started: Option<Instant>,
+}
+
+/// Bitrates offered when the bus load has no bitrate to rely on.
+pub const STANDARD_BITRATES: [u32; 4] = [125_000, 250_000, 500_000, 1_000_000];
+
+/// Where the bitrate used for the bus load comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitrateSource {
+    /// Typed in the bitrate field.
+    Configured,
+    /// Read from the interface over netlink.
+    Interface,
+    /// Picked by the user among [`STANDARD_BITRATES`].
+    Assumed,
+}
+
+impl BitrateSource {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Configured => "configured",
+            Self::Interface => "read from interface",
+            Self::Assumed => "assumed",
+        }
+    }
+}
 
 #[derive(Clone, Debug)]
 pub struct Bitrate {
-    rates: Arc<Mutex<RatesData>>,
-    data: VecDeque<(f64, usize)>,
+    /// Rates published to the GUI, updated in place so they are never copied.
+    rates: watch::Sender<RatesData>,
+    /// Last samples of the time, bits received and bits sent by the viewer.
+    data: VecDeque<(f64, usize, usize)>,
     canopen_info: Arc<Mutex<CanOpenInfo>>,
+    /// Bits of the frames sent by the viewer, counted by the driver when they come back from
+    /// the bus. They are part of `rx_bits` as well.
+    tx_bits: Arc<AtomicUsize>,
 }
 
 impl Bitrate {
-    pub fn new(canopen_info: Arc<Mutex<CanOpenInfo>>, output: Arc<Mutex<RatesData>>) -> Self {
+    pub fn new(
+        canopen_info: Arc<Mutex<CanOpenInfo>>,
+        tx_bits: Arc<AtomicUsize>,
+        output: watch::Sender<RatesData>,
+    ) -> Self {
         Self {
             data: VecDeque::new(),
             canopen_info,
+            tx_bits,
             rates: output,
         }
     }
 
-    async fn calculate_rate(&self) {
-        let mut rates = self.rates.lock().await;
-        rates.clear();
-        for (i, &(current_time, current_bits)) in self.data.iter().enumerate() {
-            if i < RATE_PERIOD {
-                continue;
-            }
-
-            let (prev_time, prev_bits) = self.data[i - RATE_PERIOD];
-            let Ok(bits_diff) = i32::try_from(current_bits - prev_bits) else {
-                continue;
-            };
-            let bits_diff = f64::from(bits_diff);
-            let duration_secs = current_time - prev_time;
-            if duration_secs > 0.0 {
-                let rate = bits_diff / duration_secs;
-                rates.push([current_time, rate]);
-            }
+    /// Appends the rates over the last [`RATE_PERIOD`] samples.
+    fn calculate_rate(&self, started: Instant) {
+        let (Some(&(current_time, current_bits, current_tx)), Some(&(prev_time, prev_bits, prev_tx))) =
+            (self.data.back(), self.data.front())
+        else {
+            return;
+        };
+        if self.data.len() <= RATE_PERIOD {
+            return;
+        }
+        let (Ok(bits_diff), Ok(tx_diff)) = (
+            i32::try_from(current_bits - prev_bits),
+            i32::try_from(current_tx - prev_tx),
+        ) else {
+            return;
+        };
+        let duration_secs = current_time - prev_time;
+        if duration_secs <= 0.0 {
+            return;
         }
+        let total = f64::from(bits_diff) / duration_secs;
+        // The echo of a frame sent may be counted by the driver before the receiver.
+        let tx = (f64::from(tx_diff) / duration_secs).min(total);
+        self.rates.send_modify(|rates| {
+            rates.started = Some(started);
+            rates.total.push_back([current_time, total]);
+            rates.tx.push_back([current_time, tx]);
+            rates.rx.push_back([current_time, total - tx]);
+            while rates.total.len() > RATES_LEN {
+                rates.total.pop_front();
+                rates.tx.pop_front();
+                rates.rx.pop_front();
+            }
+        });
     }
 
     pub fn start_thread(mut self) {
         tokio::spawn(async move {
             let started = Instant::now();
-            loop {
+            // Until the GUI drops the rates with its session.
+            while !self.rates.is_closed() {
                 let b = self.canopen_info.lock().await.rx_bits;
-                if self.data.len() > RATES_LEN + RATE_PERIOD {
+                let tx = self.tx_bits.load(Ordering::Relaxed);
+                if self.data.len() > RATE_PERIOD {
                     self.data.pop_front();
                 }
 
-                self.data.push_back((started.elapsed().as_secs_f64(), b));
-                self.calculate_rate().await;
+                self.data.push_back((started.elapsed().as_secs_f64(), b, tx));
+                self.calculate_rate(started);
                 sleep(Duration::from_millis(10)).await;
             }
         });
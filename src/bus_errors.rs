@@ -0,0 +1,288 @@
+use crate::theme::Status;
+use egui::Color32;
+use socketcan::{nl::CanState, CanInterface};
+
+/// Error classes in the CAN ID of an error frame, see `linux/can/error.h`.
+const ERR_TX_TIMEOUT: u32 = 0x0001;
+const ERR_LOSTARB: u32 = 0x0002;
+const ERR_CRTL: u32 = 0x0004;
+const ERR_PROT: u32 = 0x0008;
+const ERR_TRX: u32 = 0x0010;
+const ERR_ACK: u32 = 0x0020;
+const ERR_BUSOFF: u32 = 0x0040;
+const ERR_BUSERROR: u32 = 0x0080;
+const ERR_RESTARTED: u32 = 0x0100;
+
+/// Controller status in `data[1]` of an error frame of class `ERR_CRTL`.
+const CRTL_RX_OVERFLOW: u8 = 0x01;
+const CRTL_TX_OVERFLOW: u8 = 0x02;
+const CRTL_RX_WARNING: u8 = 0x04;
+const CRTL_TX_WARNING: u8 = 0x08;
+const CRTL_RX_PASSIVE: u8 = 0x10;
+const CRTL_TX_PASSIVE: u8 = 0x20;
+const CRTL_ACTIVE: u8 = 0x40;
+
+/// Error frame generated by the CAN controller, as received on the receive socket.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ErrorFrame {
+    /// Error classes, the CAN ID without the error flag.
+    pub bits: u32,
+    /// Details of the classes, e.g. the controller status in `data[1]`.
+    pub data: [u8; 8],
+}
+
+/// Kind of error counted in the statistics, an error frame can report several of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorClass {
+    /// Bit, stuff, form or CRC error seen by the controller.
+    Protocol,
+    /// No node acknowledged a frame sent by the interface.
+    NoAck,
+    ArbitrationLost,
+    TxTimeout,
+    Transceiver,
+    /// Receive or transmit buffer overflow of the controller.
+    Overflow,
+    /// Error counter of the controller reached the warning level (96).
+    Warning,
+    /// Error counter of the controller reached the error passive level (128).
+    Passive,
+    BusOff,
+    /// The controller was restarted after a bus off.
+    Restarted,
+}
+
+impl ErrorClass {
+    pub const ALL: [Self; 10] = [
+        Self::Protocol,
+        Self::NoAck,
+        Self::ArbitrationLost,
+        Self::TxTimeout,
+        Self::Transceiver,
+        Self::Overflow,
+        Self::Warning,
+        Self::Passive,
+        Self::BusOff,
+        Self::Restarted,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Protocol => "Protocol",
+            Self::NoAck => "No ACK",
+            Self::ArbitrationLost => "Arbitration lost",
+            Self::TxTimeout => "TX timeout",
+            Self::Transceiver => "Transceiver",
+            Self::Overflow => "Overflow",
+            Self::Warning => "Error warning",
+            Self::Passive => "Error passive",
+            Self::BusOff => "Bus off",
+            Self::Restarted => "Restarted",
+        }
+    }
+
+    /// Classes reported by an error frame.
+    pub fn of(frame: &ErrorFrame) -> Vec<Self> {
+        let crtl = if frame.bits & ERR_CRTL == 0 {
+            0
+        } else {
+            frame.data[1]
+        };
+        [
+            (frame.bits & (ERR_PROT | ERR_BUSERROR) != 0, Self::Protocol),
+            (frame.bits & ERR_ACK != 0, Self::NoAck),
+            (frame.bits & ERR_LOSTARB != 0, Self::ArbitrationLost),
+            (frame.bits & ERR_TX_TIMEOUT != 0, Self::TxTimeout),
+            (frame.bits & ERR_TRX != 0, Self::Transceiver),
+            (
+                crtl & (CRTL_RX_OVERFLOW | CRTL_TX_OVERFLOW) != 0,
+                Self::Overflow,
+            ),
+            (
+                crtl & (CRTL_RX_WARNING | CRTL_TX_WARNING) != 0,
+                Self::Warning,
+            ),
+            (
+                crtl & (CRTL_RX_PASSIVE | CRTL_TX_PASSIVE) != 0,
+                Self::Passive,
+            ),
+            (frame.bits & ERR_BUSOFF != 0, Self::BusOff),
+            (frame.bits & ERR_RESTARTED != 0, Self::Restarted),
+        ]
+        .into_iter()
+        .filter_map(|(reported, class)| reported.then_some(class))
+        .collect()
+    }
+}
+
+/// Error state of the CAN controller, as last reported by an error frame.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ControllerState {
+    #[default]
+    Active,
+    Warning,
+    Passive,
+    BusOff,
+}
+
+impl ControllerState {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Active => "error active",
+            Self::Warning => "error warning",
+            Self::Passive => "error passive",
+            Self::BusOff => "bus off",
+        }
+    }
+
+    pub fn color(self) -> Color32 {
+        match self {
+            Self::Active | Self::Warning => Status::Ok.color(),
+            Self::Passive => Status::Warning.color(),
+            Self::BusOff => Status::Error.color(),
+        }
+    }
+
+    /// State reported over netlink, `None` while the controller is stopped or sleeping.
+    fn from_can_state(state: CanState) -> Option<Self> {
+        match state {
+            CanState::ErrorActive => Some(Self::Active),
+            CanState::ErrorWarning => Some(Self::Warning),
+            CanState::ErrorPassive => Some(Self::Passive),
+            CanState::BusOff => Some(Self::BusOff),
+            CanState::Stopped | CanState::Sleeping => None,
+        }
+    }
+}
+
+/// State and error counters of the controller, read over netlink.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct InterfaceHealth {
+    /// `None` while the controller is stopped or sleeping.
+    pub state: Option<ControllerState>,
+    /// Transmit and receive error counters, `None` if the driver doesn't report them.
+    pub counters: Option<(u16, u16)>,
+}
+
+impl InterfaceHealth {
+    /// Reads the interface, `None` if it doesn't exist or reports neither, e.g. on vcan.
+    pub fn read(can_name: &str) -> Option<Self> {
+        if can_name.is_empty() {
+            return None;
+        }
+        let interface = CanInterface::open(can_name).ok()?;
+        let state = interface.state().ok().flatten();
+        let counters = interface.berr_counter().ok().flatten();
+        if state.is_none() && counters.is_none() {
+            return None;
+        }
+        Some(Self {
+            state: state.and_then(ControllerState::from_can_state),
+            counters: counters.map(|counters| (counters.txerr, counters.rxerr)),
+        })
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.state.map_or("stopped", ControllerState::name)
+    }
+
+    pub fn color(&self) -> Color32 {
+        self.state
+            .map_or(Status::Warning.color(), ControllerState::color)
+    }
+}
+
+/// Error frames received, per class.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ErrorCounts {
+    /// Error frames, each one counts in one class or more.
+    pub frames: u64,
+    classes: [u64; ErrorClass::ALL.len()],
+    pub state: ControllerState,
+}
+
+impl ErrorCounts {
+    pub fn on_error_frame(&mut self, frame: &ErrorFrame) {
+        self.frames += 1;
+        for class in ErrorClass::of(frame) {
+            self.classes[class as usize] += 1;
+        }
+        if frame.bits & ERR_BUSOFF != 0 {
+            self.state = ControllerState::BusOff;
+        } else if frame.bits & ERR_RESTARTED != 0 {
+            self.state = ControllerState::Active;
+        } else if frame.bits & ERR_CRTL != 0 {
+            let crtl = frame.data[1];
+            if crtl & (CRTL_RX_PASSIVE | CRTL_TX_PASSIVE) != 0 {
+                self.state = ControllerState::Passive;
+            } else if crtl & (CRTL_RX_WARNING | CRTL_TX_WARNING) != 0 {
+                self.state = ControllerState::Warning;
+            } else if crtl & CRTL_ACTIVE != 0 {
+                self.state = ControllerState::Active;
+            }
+        }
+    }
+
+    pub fn get(&self, class: ErrorClass) -> u64 {
+        self.classes[class as usize]
+    }
+
+    /// Counts since `base`, taken earlier from the same counters, with the current state.
+    #[must_use]
+    pub fn since(&self, base: &Self) -> Self {
+        let mut classes = self.classes;
+        for (count, base) in classes.iter_mut().zip(base.classes) {
+            *count = count.saturating_sub(base);
+        }
+        Self {
+            frames: self.frames.saturating_sub(base.frames),
+            classes,
+            state: self.state,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ControllerState, ErrorClass, ErrorCounts, ErrorFrame};
+    use socketcan::nl::CanState;
+
+    #[test]
+    fn test_error_counts() {
+        let frame = |bits, crtl| ErrorFrame {
+            bits,
+            data: [0, crtl, 0, 0, 0, 0, 0, 0],
+        };
+        let mut counts = ErrorCounts::default();
+        // Stuff error, reported as protocol violation and bus error.
+        counts.on_error_frame(&frame(0x0088, 0));
+        counts.on_error_frame(&frame(0x0020, 0));
+        let base = counts;
+        counts.on_error_frame(&frame(0x0004, 0x08));
+        assert_eq!(counts.state, ControllerState::Warning);
+        counts.on_error_frame(&frame(0x0004 | 0x0008, 0x20));
+        assert_eq!(counts.state, ControllerState::Passive);
+        counts.on_error_frame(&frame(0x0040, 0));
+        assert_eq!(counts.state, ControllerState::BusOff);
+        counts.on_error_frame(&frame(0x0100, 0));
+        assert_eq!(counts.state, ControllerState::Active);
+
+        assert_eq!(counts.frames, 6);
+        assert_eq!(counts.get(ErrorClass::Protocol), 2);
+        assert_eq!(counts.get(ErrorClass::NoAck), 1);
+        assert_eq!(counts.get(ErrorClass::Passive), 1);
+        assert_eq!(counts.get(ErrorClass::Overflow), 0);
+
+        let since = counts.since(&base);
+        assert_eq!(since.frames, 4);
+        assert_eq!(since.get(ErrorClass::Protocol), 1);
+        assert_eq!(since.get(ErrorClass::NoAck), 0);
+        assert_eq!(since.get(ErrorClass::BusOff), 1);
+
+        assert_eq!(
+            ControllerState::from_can_state(CanState::ErrorPassive),
+            Some(ControllerState::Passive)
+        );
+        assert_eq!(ControllerState::from_can_state(CanState::Stopped), None);
+    }
+}
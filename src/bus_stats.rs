@@ -1,18 +1,271 @@
-use std::collections::{HashMap, VecDeque};
+use crate::{
+    bus_errors::{ErrorClass, ErrorCounts},
+    deadlines::{DeadlineSnapshot, Deadlines},
+    emcy::Emergency,
+    rolling_max::RollingMax,
+    sync_stats::{SyncSnapshot, SyncStats},
+};
+use chrono::{DateTime, Local};
+use oze_canopen::canopen::NodeId;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    ops::RangeInclusive,
+    time::Duration,
+};
 use tokio::time::Instant;
 
+/// Default window of the COB-ID rates.
+pub const RATE_WINDOW: Duration = Duration::from_secs(2);
+
+/// Allowed window of the COB-ID rates, in seconds.
+pub const RATE_WINDOW_RANGE: RangeInclusive<f64> = 0.5..=60.0;
+
+/// Default window of the peak load and peak message rate.
+pub const PEAK_WINDOW: Duration = Duration::from_secs(60);
+
+/// Allowed window of the peaks, in seconds.
+pub const PEAK_WINDOW_RANGE: RangeInclusive<f64> = 5.0..=3600.0;
+
+/// Default number of COB-IDs with their own statistics, the others are counted together.
+pub const MAX_COB_IDS: usize = 512;
+
+/// Allowed number of COB-IDs with their own statistics.
+pub const MAX_COB_IDS_RANGE: RangeInclusive<usize> = 16..=4096;
+
+/// Window of the current message rate.
+const MSG_COUNT_WINDOW: Duration = Duration::from_secs(5);
+
+/// Shortest interval between two samples of the message count, which bounds their number.
+const MSG_COUNT_SAMPLE_PERIOD: Duration = Duration::from_millis(10);
+
+/// Samples of the message count over its window, and the newest one.
+const MSG_COUNT_HISTORY: usize = 502;
+
+/// Inter-frame gaps kept for the jitter.
+const GAP_HISTORY: usize = 1000;
+
+/// Load samples kept for the average load.
+const LOAD_HISTORY: usize = 100;
+
+/// Interval between two samples of the message rate history.
+const MSG_RATE_SAMPLE_PERIOD: Duration = Duration::from_secs(1);
+
+/// Samples kept in the message rate history, 10 minutes.
+const MSG_RATE_HISTORY: usize = 600;
+
+/// Inter-arrival times kept per COB-ID for its period statistics.
+///
+/// At most [`MAX_COB_IDS`] tracked COB-IDs by default, so at most 0.8 MB of history.
+const PERIOD_HISTORY: usize = 200;
+
+/// Window of the bus load computed from the frames.
+const LOAD_WINDOW: Duration = Duration::from_secs(1);
+
+/// Bits of a classic CAN base frame on the wire, with the worst case of bit stuffing and the
+/// interframe space.
+///
+/// SOF, identifier, RTR, IDE, r0, DLC, data and CRC are subject to stuffing, one stuff bit every
+/// 4 bits at worst. CRC delimiter, ACK, EOF and interframe space add 13 fixed bits.
+pub fn frame_bits(dlc: usize) -> u32 {
+    let stuffed = 34 + 8 * u32::try_from(dlc.min(8)).unwrap_or(8);
+    stuffed + (stuffed - 1) / 4 + 13
+}
+
+/// Bus load in percent, see [`BusStats::calculate_frame_load`] for the two estimations.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoadSnapshot {
+    pub current: f64,
+    /// Over the peak window, `peak` is over the whole run.
+    pub peak_window: Option<f64>,
+    pub peak: f64,
+    pub average: f64,
+    pub from_frames: Option<f64>,
+    pub from_counter: Option<f64>,
+}
+
+/// Inter-frame gaps in milliseconds.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GapSnapshot {
+    pub min_ms: Option<f64>,
+    pub max_ms: Option<f64>,
+    pub avg_ms: Option<f64>,
+    pub jitter_ms: Option<f64>,
+}
+
+/// Messages per second.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RateSnapshot {
+    pub current: f64,
+    /// Over the peak window, `peak` is over the whole run.
+    pub peak_window: Option<f64>,
+    pub peak: f64,
+    pub average: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ErrorSnapshot {
+    pub frames: u64,
+    pub per_second: f64,
+    /// Share of error frames in the frames received lately, in percent.
+    pub ratio: f64,
+    pub controller_state: String,
+    pub classes: BTreeMap<String, u64>,
+}
+
+/// Inter-arrival statistics of a COB-ID over its last arrivals, in milliseconds.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PeriodStats {
+    pub mean: f64,
+    pub std_dev: f64,
+    pub min: f64,
+    pub max: f64,
+    /// Number of intervals the statistics are computed from.
+    pub samples: usize,
+}
+
+impl PeriodStats {
+    #[allow(clippy::cast_precision_loss)]
+    fn of(gaps: &VecDeque<f64>) -> Option<Self> {
+        if gaps.is_empty() {
+            return None;
+        }
+        let count = gaps.len() as f64;
+        let mean = gaps.iter().sum::<f64>() / count;
+        let variance = gaps.iter().map(|gap| (gap - mean).powi(2)).sum::<f64>() / count;
+        Some(Self {
+            mean,
+            std_dev: variance.sqrt(),
+            min: gaps.iter().copied().fold(f64::INFINITY, f64::min),
+            max: gaps.iter().copied().fold(0.0, f64::max),
+            samples: gaps.len(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CobIdSnapshot {
+    pub cob_id: String,
+    pub count: u64,
+    /// Rate over the rate window, `None` if not seen within it.
+    pub rate_hz: Option<f64>,
+    /// Seconds between the last frame and the snapshot.
+    pub last_seen_ago_s: f64,
+    pub period_ms: Option<PeriodStats>,
+}
+
+/// Emergencies sent by a node.
+#[derive(Debug, Clone)]
+pub struct NodeEmcyStats {
+    pub total: u64,
+    /// Number of emergencies per error code.
+    pub codes: BTreeMap<u16, u64>,
+    pub last_code: u16,
+    /// Message index of the last emergency.
+    pub last_index: u64,
+    pub last_time: DateTime<Local>,
+}
+
+/// Payload sizes of the frames.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PayloadStats {
+    /// Number of frames per DLC, 0 to 8.
+    pub dlc_counts: [u64; 9],
+    pub payload_bytes: u64,
+    /// Bytes of the frames besides the payload, with the worst case of bit stuffing.
+    pub overhead_bytes: f64,
+}
+
+impl PayloadStats {
+    fn on_frame(&mut self, dlc: usize) {
+        let dlc = dlc.min(8);
+        self.dlc_counts[dlc] += 1;
+        self.payload_bytes += dlc as u64;
+        let payload_bits = 8 * u32::try_from(dlc).unwrap_or(8);
+        self.overhead_bytes += f64::from(frame_bits(dlc) - payload_bits) / 8.0;
+    }
+    
+    pub fn frames(&self) -> u64 {
+        self.dlc_counts.iter().sum()
+    }
+    
+    /// Share of the frame bytes that are payload, in percent
+    #[allow(clippy::cast_precision_loss)]
+    pub fn efficiency(&self) -> f64 {
+        let total = self.payload_bytes as f64 + self.overhead_bytes;
+        if total > 0.0 {
+            self.payload_bytes as f64 / total * 100.0
+        } else {
+            0.0
+        }
+    }
+    
+    /// Mean DLC of the frames
+    #[allow(clippy::cast_precision_loss)]
+    pub fn avg_dlc(&self) -> f64 {
+        match self.frames() {
+            0 => 0.0,
+            frames => self.payload_bytes as f64 / frames as f64,
+        }
+    }
+}
+
+/// COB-IDs evicted from the per-COB-ID statistics, counted together.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OtherCobIds {
+    /// Frames of the COB-IDs while they were not tracked.
+    pub count: u64,
+    /// Number of evictions.
+    pub evicted: u64,
+}
+
+/// Appends to a history allocated once with `capacity`, the oldest value makes room.
+fn push_bounded<T>(history: &mut VecDeque<T>, value: T, capacity: usize) {
+    if history.len() >= capacity {
+        history.pop_front();
+    }
+    history.push_back(value);
+}
+
+/// Statistics at a point in time, without the opaque instants.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BusStatsSnapshot {
+    /// Seconds since the start of the statistics or their last reset.
+    pub duration_s: f64,
+    pub rate_window_s: f64,
+    pub peak_window_s: f64,
+    pub total_messages: u64,
+    pub load: LoadSnapshot,
+    pub gaps: GapSnapshot,
+    pub message_rate: RateSnapshot,
+    pub errors: ErrorSnapshot,
+    pub payload: PayloadStats,
+    pub payload_per_node: BTreeMap<NodeId, PayloadStats>,
+    pub sync: SyncSnapshot,
+    pub deadlines: Vec<DeadlineSnapshot>,
+    /// Ordered by COB-ID.
+    pub cob_ids: Vec<CobIdSnapshot>,
+    #[serde(default)]
+    pub other_cob_ids: OtherCobIds,
+}
+
 /// Detailed bus statistics tracker
 #[derive(Debug, Clone)]
 pub struct BusStats {
     // Message counting
     total_messages: u64,
-    messages_history: VecDeque<(Instant, u64)>, // (timestamp, message_count)
+    messages_history: VecDeque<(Instant, u64)>, // (timestamp, message_count), at most MSG_COUNT_HISTORY
     
     // Bus load tracking
     current_load: f64,
     peak_load: f64,
+    window_peak_load: RollingMax,
     avg_load: f64,
     load_samples: VecDeque<f64>,
+    frame_bits: VecDeque<(Instant, u32)>, // (timestamp, bits) within the load window
+    frame_bits_sum: u64,
+    frame_load: Option<f64>,   // from the frames, percent
+    counter_load: Option<f64>, // from the bit counter of the CANopen stack, percent
     
     // Timing analysis
     last_message_time: Option<Instant>,
@@ -22,15 +275,43 @@ pub struct BusStats {
     gap_count: u64,
     gap_history: VecDeque<f64>,
     
-    // COB-ID frequency tracking
+    // COB-ID frequency tracking, of at most max_cob_ids COB-IDs
+    max_cob_ids: usize,
+    other_cob_ids: OtherCobIds,
     cob_id_counts: HashMap<u16, u64>,
     cob_id_last_seen: HashMap<u16, Instant>,
-    cob_id_rates: HashMap<u16, f64>, // Hz
+    cob_id_times: HashMap<u16, VecDeque<Instant>>, // receptions within the rate window
+    cob_id_gaps: HashMap<u16, VecDeque<f64>>, // last inter-arrival times, milliseconds
+    cob_id_rates: HashMap<u16, f64>, // Hz, over the rate window
+    rate_window: Duration,
+    cob_id_rate_history: BTreeMap<u16, VecDeque<(Instant, f64)>>, // plotted COB-IDs, one sample per second
+    last_rate_sample: Option<Instant>,
     
     // Message rate
     current_msg_rate: f64, // messages per second
     peak_msg_rate: f64,
+    window_peak_msg_rate: RollingMax,
     avg_msg_rate: f64,
+    msg_rate_history: VecDeque<(Instant, f64)>, // one sample per second
+    
+    // Payload sizes, in total and per node
+    payload: PayloadStats,
+    payload_per_node: BTreeMap<NodeId, PayloadStats>,
+    
+    // SYNC timing
+    sync: SyncStats,
+    
+    // Expected periods and their violations
+    deadlines: Deadlines,
+    
+    // Emergencies per node
+    emcy: BTreeMap<NodeId, NodeEmcyStats>,
+    
+    // Error frames, counted by the driver since its start
+    errors_base: ErrorCounts,
+    errors: ErrorCounts,
+    errors_history: VecDeque<(Instant, u64)>, // (timestamp, error_frames)
+    current_error_rate: f64, // error frames per second
     
     // Start time for calculations
     start_time: Instant,
@@ -42,37 +323,69 @@ impl Default for BusStats {
     }
 }
 
+#[allow(clippy::cast_precision_loss)]
 impl BusStats {
     pub fn new() -> Self {
         Self {
             total_messages: 0,
-            messages_history: VecDeque::new(),
+            messages_history: VecDeque::with_capacity(MSG_COUNT_HISTORY),
             current_load: 0.0,
             peak_load: 0.0,
+            window_peak_load: RollingMax::new(PEAK_WINDOW),
             avg_load: 0.0,
-            load_samples: VecDeque::new(),
+            load_samples: VecDeque::with_capacity(LOAD_HISTORY),
+            frame_bits: VecDeque::new(),
+            frame_bits_sum: 0,
+            frame_load: None,
+            counter_load: None,
             last_message_time: None,
             min_gap: None,
             max_gap: None,
             gap_sum: 0.0,
             gap_count: 0,
-            gap_history: VecDeque::new(),
+            gap_history: VecDeque::with_capacity(GAP_HISTORY),
+            max_cob_ids: MAX_COB_IDS,
+            other_cob_ids: OtherCobIds::default(),
             cob_id_counts: HashMap::new(),
             cob_id_last_seen: HashMap::new(),
+            cob_id_times: HashMap::new(),
+            cob_id_gaps: HashMap::new(),
             cob_id_rates: HashMap::new(),
+            rate_window: RATE_WINDOW,
+            cob_id_rate_history: BTreeMap::new(),
+            last_rate_sample: None,
             current_msg_rate: 0.0,
             peak_msg_rate: 0.0,
+            window_peak_msg_rate: RollingMax::new(PEAK_WINDOW),
             avg_msg_rate: 0.0,
+            msg_rate_history: VecDeque::with_capacity(MSG_RATE_HISTORY),
+            payload: PayloadStats::default(),
+            payload_per_node: BTreeMap::new(),
+            sync: SyncStats::default(),
+            deadlines: Deadlines::default(),
+            emcy: BTreeMap::new(),
+            errors_base: ErrorCounts::default(),
+            errors: ErrorCounts::default(),
+            errors_history: VecDeque::new(),
+            current_error_rate: 0.0,
             start_time: Instant::now(),
         }
     }
     
     /// Update statistics with a new message
-    pub fn on_message(&mut self, cob_id: u16, timestamp: Instant) {
+    pub fn on_message(&mut self, cob_id: u16, node_id: Option<NodeId>, dlc: usize, timestamp: Instant) {
         self.total_messages += 1;
         
-        // Update COB-ID count
-        *self.cob_id_counts.entry(cob_id).or_insert(0) += 1;
+        self.payload.on_frame(dlc);
+        if let Some(node_id) = node_id {
+            self.payload_per_node.entry(node_id).or_default().on_frame(dlc);
+        }
+        self.sync.on_frame(cob_id, node_id, timestamp);
+        self.deadlines.on_frame(cob_id, timestamp);
+        
+        let bits = frame_bits(dlc);
+        self.frame_bits.push_back((timestamp, bits));
+        self.frame_bits_sum += u64::from(bits);
         
         // Calculate inter-frame gap
         if let Some(last_time) = self.last_message_time {
@@ -84,21 +397,39 @@ impl BusStats {
             self.gap_sum += gap_ms;
             self.gap_count += 1;
             
-            // Keep gap history (last 1000 samples)
-            self.gap_history.push_back(gap_ms);
-            if self.gap_history.len() > 1000 {
-                self.gap_history.pop_front();
-            }
+            push_bounded(&mut self.gap_history, gap_ms, GAP_HISTORY);
         }
         
         self.last_message_time = Some(timestamp);
-        self.cob_id_last_seen.insert(cob_id, timestamp);
         
-        // Update message history for rate calculation
-        self.messages_history.push_back((timestamp, self.total_messages));
-        // Keep only last 5 seconds of history
+        // Update the COB-ID statistics, if tracked
+        let tracked = self.cob_id_counts.contains_key(&cob_id)
+            || self.cob_id_counts.len() < self.max_cob_ids
+            || self.evict_cob_id();
+        if tracked {
+            *self.cob_id_counts.entry(cob_id).or_insert(0) += 1;
+            if let Some(last_seen) = self.cob_id_last_seen.insert(cob_id, timestamp) {
+                let gap_ms = timestamp.saturating_duration_since(last_seen).as_secs_f64() * 1000.0;
+                let gaps = self.cob_id_gaps.entry(cob_id).or_insert_with(|| VecDeque::with_capacity(PERIOD_HISTORY));
+                push_bounded(gaps, gap_ms, PERIOD_HISTORY);
+            }
+            self.cob_id_times.entry(cob_id).or_default().push_back(timestamp);
+        } else {
+            self.other_cob_ids.count += 1;
+        }
+        
+        // Update message history for rate calculation, the newest sample follows the
+        // last message and the others are at least a sample period apart
+        let len = self.messages_history.len();
+        let spaced = len < 2
+            || timestamp.saturating_duration_since(self.messages_history[len - 2].0) >= MSG_COUNT_SAMPLE_PERIOD;
+        match self.messages_history.back_mut() {
+            Some(newest) if !spaced => *newest = (timestamp, self.total_messages),
+            _ => push_bounded(&mut self.messages_history, (timestamp, self.total_messages), MSG_COUNT_HISTORY),
+        }
+        // Keep only the window of history
         while let Some((old_time, _)) = self.messages_history.front() {
-            if timestamp.duration_since(*old_time).as_secs_f64() > 5.0 {
+            if timestamp.duration_since(*old_time) > MSG_COUNT_WINDOW {
                 self.messages_history.pop_front();
             } else {
                 break;
@@ -106,23 +437,87 @@ impl BusStats {
         }
     }
     
+    /// Drop the statistics of the least recently seen COB-ID into the other bucket, the
+    /// plotted ones are kept. Returns false if all the tracked COB-IDs are plotted.
+    fn evict_cob_id(&mut self) -> bool {
+        let oldest = self.cob_id_last_seen.iter()
+            .filter(|(cob_id, _)| !self.cob_id_rate_history.contains_key(cob_id))
+            .min_by_key(|(_, time)| **time)
+            .map(|(cob_id, _)| *cob_id);
+        let Some(cob_id) = oldest else {
+            return false;
+        };
+        self.other_cob_ids.count += self.cob_id_counts.remove(&cob_id).unwrap_or(0);
+        self.other_cob_ids.evicted += 1;
+        self.cob_id_last_seen.remove(&cob_id);
+        self.cob_id_times.remove(&cob_id);
+        self.cob_id_gaps.remove(&cob_id);
+        self.cob_id_rates.remove(&cob_id);
+        true
+    }
+    
+    /// Number of COB-IDs with their own statistics
+    pub fn tracked_cob_ids(&self) -> usize { self.cob_id_counts.len() }
+    
+    pub fn max_cob_ids(&self) -> usize { self.max_cob_ids }
+    
+    /// Cap the COB-IDs with their own statistics, evicting the least recently seen ones
+    pub fn set_max_cob_ids(&mut self, max: usize) {
+        self.max_cob_ids = max;
+        while self.cob_id_counts.len() > max && self.evict_cob_id() {}
+    }
+    
+    /// Frames of the evicted COB-IDs
+    pub fn other_cob_ids(&self) -> OtherCobIds { self.other_cob_ids }
+    
     /// Update bus load value
-    pub fn update_load(&mut self, load: f64) {
+    pub fn update_load(&mut self, load: f64, now: Instant) {
         self.current_load = load;
         self.peak_load = self.peak_load.max(load);
+        self.window_peak_load.push(now, load);
         
         // Update average load
-        self.load_samples.push_back(load);
-        if self.load_samples.len() > 100 {
-            self.load_samples.pop_front();
-        }
+        push_bounded(&mut self.load_samples, load, LOAD_HISTORY);
         if !self.load_samples.is_empty() {
             self.avg_load = self.load_samples.iter().sum::<f64>() / self.load_samples.len() as f64;
         }
     }
     
+    /// Calculate the bus load from the bits of the frames received within the load window
+    pub fn calculate_frame_load(&mut self, bitrate: u32, now: Instant) -> f64 {
+        while let Some((time, bits)) = self.frame_bits.front() {
+            if now.duration_since(*time) > LOAD_WINDOW {
+                self.frame_bits_sum -= u64::from(*bits);
+                self.frame_bits.pop_front();
+            } else {
+                break;
+            }
+        }
+        // Right after a reset the window is not full yet
+        let window = LOAD_WINDOW.min(now.duration_since(self.start_time)).as_secs_f64().max(0.1);
+        let load = (self.frame_bits_sum as f64 / (f64::from(bitrate) * window) * 100.0).min(100.0);
+        self.frame_load = Some(load);
+        load
+    }
+    
+    /// Set the bus load estimated from the bit counter of the `oze_canopen` stack
+    pub fn set_counter_load(&mut self, load: Option<f64>) {
+        self.counter_load = load;
+    }
+    
     /// Calculate current message rate
-    pub fn calculate_msg_rate(&mut self) {
+    pub fn calculate_msg_rate(&mut self, now: Instant) {
+        self.update_msg_rate(now);
+        
+        // Sample the rate for its history
+        if self.msg_rate_history.back()
+            .map_or(true, |(time, _)| now.saturating_duration_since(*time) >= MSG_RATE_SAMPLE_PERIOD)
+        {
+            push_bounded(&mut self.msg_rate_history, (now, self.current_msg_rate), MSG_RATE_HISTORY);
+        }
+    }
+    
+    fn update_msg_rate(&mut self, now: Instant) {
         if self.messages_history.len() < 2 {
             self.current_msg_rate = 0.0;
             return;
@@ -135,9 +530,10 @@ impl BusStats {
                 let msg_diff = last_count - first_count;
                 self.current_msg_rate = msg_diff as f64 / duration;
                 self.peak_msg_rate = self.peak_msg_rate.max(self.current_msg_rate);
+                self.window_peak_msg_rate.push(now, self.current_msg_rate);
                 
                 // Calculate average rate
-                let total_duration = Instant::now().duration_since(self.start_time).as_secs_f64();
+                let total_duration = now.duration_since(self.start_time).as_secs_f64();
                 if total_duration > 0.0 {
                     self.avg_msg_rate = self.total_messages as f64 / total_duration;
                 }
@@ -145,34 +541,209 @@ impl BusStats {
         }
     }
     
-    /// Calculate rates for each COB-ID
+    /// Calculate rates for each COB-ID over the rate window
+    ///
+    /// COB-IDs not seen within the window get no rate, they are stale.
     pub fn calculate_cob_id_rates(&mut self, now: Instant) {
-        for (cob_id, _last_seen) in &self.cob_id_last_seen {
-            if let Some(count) = self.cob_id_counts.get(cob_id) {
-                let duration = now.duration_since(self.start_time).as_secs_f64();
-                if duration > 1.0 {
-                    let rate = *count as f64 / duration;
-                    self.cob_id_rates.insert(*cob_id, rate);
-                }
+        // Right after a reset the window is not full yet
+        let window = self.rate_window.min(now.duration_since(self.start_time));
+        let window_secs = window.as_secs_f64();
+        self.cob_id_rates.clear();
+        for (cob_id, times) in &mut self.cob_id_times {
+            while times.front().is_some_and(|time| now.duration_since(*time) > self.rate_window) {
+                times.pop_front();
+            }
+            if !times.is_empty() && window_secs > 0.1 {
+                self.cob_id_rates.insert(*cob_id, times.len() as f64 / window_secs);
+            }
+        }
+        
+        // Sample the rates of the plotted COB-IDs for their history
+        if self.last_rate_sample
+            .map_or(true, |time| now.saturating_duration_since(time) >= MSG_RATE_SAMPLE_PERIOD)
+        {
+            self.last_rate_sample = Some(now);
+            for (cob_id, history) in &mut self.cob_id_rate_history {
+                push_bounded(history, (now, self.cob_id_rates.get(cob_id).copied().unwrap_or(0.0)), MSG_RATE_HISTORY);
+            }
+        }
+    }
+    
+    /// Keep a rate history of a COB-ID, sampled once per second
+    pub fn track_cob_id(&mut self, cob_id: u16) {
+        self.cob_id_rate_history.entry(cob_id).or_insert_with(|| VecDeque::with_capacity(MSG_RATE_HISTORY));
+    }
+    
+    /// Stop the rate history of a COB-ID and drop it
+    pub fn untrack_cob_id(&mut self, cob_id: u16) {
+        self.cob_id_rate_history.remove(&cob_id);
+    }
+    
+    pub fn is_tracked(&self, cob_id: u16) -> bool {
+        self.cob_id_rate_history.contains_key(&cob_id)
+    }
+    
+    /// Rate histories of the tracked COB-IDs
+    pub fn cob_id_rate_history(&self) -> &BTreeMap<u16, VecDeque<(Instant, f64)>> {
+        &self.cob_id_rate_history
+    }
+    
+    pub fn rate_window(&self) -> Duration { self.rate_window }
+    
+    pub fn set_rate_window(&mut self, window: Duration) {
+        self.rate_window = window;
+    }
+    
+    /// Window of the windowed peaks, the all-time peaks are kept as well
+    pub fn peak_window(&self) -> Duration { self.window_peak_load.window() }
+    
+    pub fn set_peak_window(&mut self, window: Duration) {
+        self.window_peak_load.set_window(window);
+        self.window_peak_msg_rate.set_window(window);
+    }
+    
+    /// Count an emergency of a node
+    pub fn on_emcy(&mut self, emcy: &Emergency) {
+        let stats = self.emcy.entry(emcy.node_id).or_insert_with(|| NodeEmcyStats {
+            total: 0,
+            codes: BTreeMap::new(),
+            last_code: emcy.code,
+            last_index: emcy.index,
+            last_time: emcy.time,
+        });
+        stats.total += 1;
+        *stats.codes.entry(emcy.code).or_insert(0) += 1;
+        stats.last_code = emcy.code;
+        stats.last_index = emcy.index;
+        stats.last_time = emcy.time;
+    }
+    
+    /// Ignore the error frames counted so far by the driver, e.g. after a reset
+    pub fn set_errors_base(&mut self, total: &ErrorCounts) {
+        self.errors_base = *total;
+        self.errors = total.since(total);
+        self.errors_history.clear();
+    }
+    
+    /// Update the error counters with the totals of the driver
+    pub fn on_errors(&mut self, total: &ErrorCounts, now: Instant) {
+        self.errors = total.since(&self.errors_base);
+        
+        // One sample every 100 ms is enough for the rate
+        let due = self.errors_history.back()
+            .map_or(true, |(time, _)| now.duration_since(*time).as_secs_f64() >= 0.1);
+        if due {
+            self.errors_history.push_back((now, self.errors.frames));
+        }
+        // Keep only last 5 seconds of history
+        while let Some((old_time, _)) = self.errors_history.front() {
+            if now.duration_since(*old_time).as_secs_f64() > 5.0 {
+                self.errors_history.pop_front();
+            } else {
+                break;
             }
         }
+        
+        self.current_error_rate = match self.errors_history.front() {
+            Some((first_time, first_count)) if now > *first_time => {
+                self.errors.frames.saturating_sub(*first_count) as f64 / now.duration_since(*first_time).as_secs_f64()
+            }
+            _ => 0.0,
+        };
     }
     
-    /// Get top N most frequent COB-IDs
-    pub fn get_top_cob_ids(&self, n: usize) -> Vec<(u16, f64)> {
+    /// Get top N most frequent COB-IDs over the rate window
+    ///
+    /// Stale COB-IDs have no rate and come last, the most recently seen first.
+    pub fn get_top_cob_ids(&self, n: usize) -> Vec<(u16, Option<f64>)> {
         let mut rates: Vec<_> = self.cob_id_rates.iter()
             .map(|(cob_id, rate)| (*cob_id, *rate))
             .collect();
         rates.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
-        rates.truncate(n);
-        rates
+        let mut top: Vec<_> = rates.into_iter()
+            .map(|(cob_id, rate)| (cob_id, Some(rate)))
+            .collect();
+        if top.len() < n {
+            let mut stale: Vec<_> = self.cob_id_last_seen.iter()
+                .filter(|(cob_id, _)| !self.cob_id_rates.contains_key(cob_id))
+                .collect();
+            stale.sort_by(|a, b| b.1.cmp(a.1));
+            top.extend(stale.into_iter().map(|(cob_id, _)| (*cob_id, None)));
+        }
+        top.truncate(n);
+        top
+    }
+    
+    /// Current statistics, the instants are converted to durations before `now`
+    pub fn snapshot(&self, now: Instant) -> BusStatsSnapshot {
+        let mut cob_ids: Vec<_> = self.cob_id_counts.iter()
+            .map(|(cob_id, count)| CobIdSnapshot {
+                cob_id: format!("0x{cob_id:03X}"),
+                count: *count,
+                rate_hz: self.cob_id_rates.get(cob_id).copied(),
+                last_seen_ago_s: self.cob_id_last_seen.get(cob_id)
+                    .map_or(0.0, |time| now.saturating_duration_since(*time).as_secs_f64()),
+                period_ms: self.period_stats(*cob_id),
+            })
+            .collect();
+        cob_ids.sort_by(|a, b| a.cob_id.cmp(&b.cob_id));
+        
+        BusStatsSnapshot {
+            duration_s: now.saturating_duration_since(self.start_time).as_secs_f64(),
+            rate_window_s: self.rate_window.as_secs_f64(),
+            peak_window_s: self.peak_window().as_secs_f64(),
+            total_messages: self.total_messages,
+            load: LoadSnapshot {
+                current: self.current_load,
+                peak_window: self.window_peak_load(),
+                peak: self.peak_load,
+                average: self.avg_load,
+                from_frames: self.frame_load,
+                from_counter: self.counter_load,
+            },
+            gaps: GapSnapshot {
+                min_ms: self.min_gap,
+                max_ms: self.max_gap,
+                avg_ms: self.avg_gap(),
+                jitter_ms: self.jitter(),
+            },
+            message_rate: RateSnapshot {
+                current: self.current_msg_rate,
+                peak_window: self.window_peak_msg_rate(),
+                peak: self.peak_msg_rate,
+                average: self.avg_msg_rate,
+            },
+            errors: ErrorSnapshot {
+                frames: self.errors.frames,
+                per_second: self.current_error_rate,
+                ratio: self.error_ratio(),
+                controller_state: self.errors.state.name().to_owned(),
+                classes: ErrorClass::ALL.into_iter()
+                    .map(|class| (class.name().to_owned(), self.errors.get(class)))
+                    .collect(),
+            },
+            payload: self.payload.clone(),
+            payload_per_node: self.payload_per_node.clone(),
+            sync: self.sync.snapshot(),
+            deadlines: self.deadlines.snapshot(),
+            cob_ids,
+            other_cob_ids: self.other_cob_ids,
+        }
+    }
+    
+    /// Period statistics of a COB-ID over its last arrivals, `None` before its second frame
+    pub fn period_stats(&self, cob_id: u16) -> Option<PeriodStats> {
+        self.cob_id_gaps.get(&cob_id).and_then(PeriodStats::of)
     }
     
     // Getters
     pub fn total_messages(&self) -> u64 { self.total_messages }
     pub fn current_load(&self) -> f64 { self.current_load }
     pub fn peak_load(&self) -> f64 { self.peak_load }
+    pub fn window_peak_load(&self) -> Option<f64> { self.window_peak_load.max() }
     pub fn avg_load(&self) -> f64 { self.avg_load }
+    pub fn frame_load(&self) -> Option<f64> { self.frame_load }
+    pub fn counter_load(&self) -> Option<f64> { self.counter_load }
     pub fn min_gap(&self) -> Option<f64> { self.min_gap }
     pub fn max_gap(&self) -> Option<f64> { self.max_gap }
     pub fn avg_gap(&self) -> Option<f64> {
@@ -182,6 +753,8 @@ impl BusStats {
             None
         }
     }
+    /// Last inter-frame gaps, in milliseconds
+    pub fn gap_history(&self) -> impl Iterator<Item = f64> + '_ { self.gap_history.iter().copied() }
     pub fn jitter(&self) -> Option<f64> {
         if self.gap_history.len() < 2 {
             return None;
@@ -194,6 +767,221 @@ impl BusStats {
     }
     pub fn current_msg_rate(&self) -> f64 { self.current_msg_rate }
     pub fn peak_msg_rate(&self) -> f64 { self.peak_msg_rate }
+    pub fn window_peak_msg_rate(&self) -> Option<f64> { self.window_peak_msg_rate.max() }
     pub fn avg_msg_rate(&self) -> f64 { self.avg_msg_rate }
+    pub fn msg_rate_history(&self) -> impl Iterator<Item = (Instant, f64)> + '_ { self.msg_rate_history.iter().copied() }
+    pub fn emcy(&self) -> &BTreeMap<NodeId, NodeEmcyStats> { &self.emcy }
+    pub fn payload(&self) -> &PayloadStats { &self.payload }
+    pub fn sync(&self) -> &SyncStats { &self.sync }
+    pub fn sync_mut(&mut self) -> &mut SyncStats { &mut self.sync }
+    pub fn deadlines(&self) -> &Deadlines { &self.deadlines }
+    pub fn deadlines_mut(&mut self) -> &mut Deadlines { &mut self.deadlines }
+    pub fn payload_per_node(&self) -> &BTreeMap<NodeId, PayloadStats> { &self.payload_per_node }
+    pub fn errors(&self) -> &ErrorCounts { &self.errors }
+    pub fn current_error_rate(&self) -> f64 { self.current_error_rate }
+    /// Share of error frames in the frames received lately, in percent
+    pub fn error_ratio(&self) -> f64 {
+        let total = self.current_error_rate + self.current_msg_rate;
+        if total > 0.0 {
+            self.current_error_rate / total * 100.0
+        } else {
+            0.0
+        }
+    }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::{frame_bits, BusStats, OtherCobIds, GAP_HISTORY, MSG_COUNT_HISTORY, PERIOD_HISTORY};
+    use crate::emcy::Emergency;
+    use chrono::Local;
+    use std::time::Duration;
+    use tokio::time::Instant;
+
+    #[test]
+    fn test_frame_load() {
+        assert_eq!(frame_bits(0), 55);
+        assert_eq!(frame_bits(8), 135);
+
+        let mut stats = BusStats::new();
+        let start = Instant::now();
+        // 1000 frames of 8 bytes in the last second, 135 kbit on a 250 kbit/s bus
+        for i in 0..1000 {
+            stats.on_message(0x181, Some(1), 8, start + Duration::from_millis(1000 + i));
+        }
+        let load = stats.calculate_frame_load(250_000, start + Duration::from_secs(2));
+        assert!((load - 54.0).abs() < 0.5, "{load}");
+    }
+
+    #[test]
+    fn test_payload_sizes() {
+        let mut stats = BusStats::new();
+        let start = Instant::now();
+        stats.on_message(0x181, Some(1), 8, start);
+        stats.on_message(0x181, Some(1), 8, start);
+        stats.on_message(0x701, Some(1), 1, start);
+        stats.on_message(0x080, None, 0, start);
+
+        let payload = stats.payload();
+        assert_eq!(payload.dlc_counts, [1, 1, 0, 0, 0, 0, 0, 0, 2]);
+        assert_eq!(payload.payload_bytes, 17);
+        // 2 × (135 - 64) + (65 - 8) + 55 bits
+        assert!((payload.overhead_bytes - 254.0 / 8.0).abs() < 1e-9);
+        assert_eq!(stats.payload_per_node()[&1].frames(), 3);
+        assert!((stats.payload_per_node()[&1].avg_dlc() - 17.0 / 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_emcy_per_node() {
+        let emcy = |index, node_id, code| Emergency {
+            index,
+            time: Local::now(),
+            node_id,
+            code,
+            register: 0,
+            data: [0; 5],
+        };
+        let mut stats = BusStats::new();
+        stats.on_emcy(&emcy(10, 3, 0x8130));
+        stats.on_emcy(&emcy(20, 3, 0x8130));
+        stats.on_emcy(&emcy(30, 5, 0x2310));
+        stats.on_emcy(&emcy(40, 3, 0x0000));
+
+        let node = &stats.emcy()[&3];
+        assert_eq!(node.total, 3);
+        assert_eq!(node.codes[&0x8130], 2);
+        assert_eq!(node.codes[&0x0000], 1);
+        assert_eq!(node.last_code, 0);
+        assert_eq!(node.last_index, 40);
+        assert_eq!(stats.emcy()[&5].last_code, 0x2310);
+    }
+
+    #[test]
+    fn test_period_stats() {
+        let mut stats = BusStats::new();
+        let start = Instant::now();
+        // 0x181 alternates 9 ms and 11 ms gaps, 0x701 is interleaved and must not disturb it
+        let mut time = start;
+        for i in 0..(PERIOD_HISTORY + 11) {
+            time += Duration::from_millis(if i % 2 == 0 { 9 } else { 11 });
+            stats.on_message(0x181, Some(1), 8, time);
+            stats.on_message(0x701, Some(1), 1, time + Duration::from_millis(1));
+        }
+        assert!(stats.period_stats(0x281).is_none());
+
+        let period = stats.period_stats(0x181).unwrap();
+        assert_eq!(period.samples, PERIOD_HISTORY);
+        assert!((period.mean - 10.0).abs() < 1e-6, "{period:?}");
+        assert!((period.std_dev - 1.0).abs() < 1e-6, "{period:?}");
+        assert!((period.min - 9.0).abs() < 1e-6);
+        assert!((period.max - 11.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_cob_id_rates_decay() {
+        let mut stats = BusStats::new();
+        let start = Instant::now();
+        // 0x181 at 50 Hz for 4 s, 0x201 at 10 Hz for the first second only
+        for i in 0..200 {
+            stats.on_message(0x181, Some(1), 8, start + Duration::from_millis(i * 20));
+        }
+        for i in 0..10 {
+            stats.on_message(0x201, Some(1), 8, start + Duration::from_millis(i * 100));
+        }
+        let now = start + Duration::from_secs(4);
+        stats.calculate_cob_id_rates(now);
+
+        let top = stats.get_top_cob_ids(10);
+        assert_eq!(top.len(), 2);
+        assert_eq!(top[0].0, 0x181);
+        let rate = top[0].1.unwrap();
+        assert!((rate - 50.0).abs() < 1.0, "{rate}");
+        assert_eq!(top[1], (0x201, None));
+    }
+
+    #[test]
+    fn test_cob_id_rate_history() {
+        let mut stats = BusStats::new();
+        let start = Instant::now();
+        stats.track_cob_id(0x201);
+        // 0x201 at 10 Hz, silent from 2 s to 6 s
+        for second in 0..8 {
+            let now = start + Duration::from_secs(second);
+            if !(2..6).contains(&second) {
+                for i in 0..10 {
+                    stats.on_message(0x201, Some(1), 8, now + Duration::from_millis(i * 100));
+                }
+            }
+            stats.calculate_cob_id_rates(now + Duration::from_secs(1));
+            // Sampled once per second only
+            stats.calculate_cob_id_rates(now + Duration::from_millis(1500));
+        }
+        let rates: Vec<f64> = stats.cob_id_rate_history()[&0x201].iter().map(|(_, rate)| *rate).collect();
+        assert_eq!(rates.len(), 8);
+        assert!(rates[1] > 4.0 && rates[6] > 4.0, "{rates:?}");
+        assert!(rates[4] == 0.0, "{rates:?}");
+
+        stats.untrack_cob_id(0x201);
+        assert!(stats.cob_id_rate_history().is_empty());
+    }
+
+    #[test]
+    fn test_cob_id_eviction() {
+        let mut stats = BusStats::new();
+        let start = Instant::now();
+        let mut time = start;
+        let mut frame = |stats: &mut BusStats, cob_id| {
+            time += Duration::from_millis(1);
+            stats.on_message(cob_id, None, 8, time);
+        };
+        // A few dozen COB-IDs stay under the default cap
+        for cob_id in 0x181..0x1A1 {
+            frame(&mut stats, cob_id);
+        }
+        assert_eq!(stats.tracked_cob_ids(), 32);
+        assert_eq!(stats.other_cob_ids(), OtherCobIds::default());
+
+        stats.set_max_cob_ids(3);
+        assert_eq!(stats.tracked_cob_ids(), 3);
+        assert_eq!(stats.other_cob_ids().count, 29);
+        stats.track_cob_id(0x19E);
+        for cob_id in [0x19E, 0x19E, 0x19F, 0x1A0] {
+            frame(&mut stats, cob_id);
+        }
+        // 0x19E is plotted, so 0x19F is the least recently seen one that can go
+        frame(&mut stats, 0x281);
+        let other = stats.other_cob_ids();
+        assert_eq!(other.evicted, 30);
+        assert_eq!(other.count, 31);
+        let snapshot = stats.snapshot(start + Duration::from_secs(1));
+        let counts: Vec<_> = snapshot.cob_ids.iter().map(|cob| (cob.cob_id.as_str(), cob.count)).collect();
+        assert_eq!(counts, [("0x19E", 3), ("0x1A0", 2), ("0x281", 1)]);
+        assert_eq!(snapshot.total_messages, 37);
+
+        // Only plotted COB-IDs left, the new ones are counted as other
+        stats.track_cob_id(0x1A0);
+        stats.track_cob_id(0x281);
+        frame(&mut stats, 0x701);
+        assert_eq!(stats.other_cob_ids().count, 32);
+        assert_eq!(stats.tracked_cob_ids(), 3);
+        assert!(stats.get_top_cob_ids(10).iter().all(|(cob_id, _)| *cob_id != 0x701));
+    }
+
+    #[test]
+    fn test_histories_bounded() {
+        let mut stats = BusStats::new();
+        let capacities = (stats.messages_history.capacity(), stats.gap_history.capacity());
+        let start = Instant::now();
+        // 10 kHz for 10 s, no reallocation
+        for i in 0..100_000 {
+            stats.on_message(0x181, Some(1), 8, start + Duration::from_micros(i * 100));
+        }
+        assert!(stats.messages_history.len() <= MSG_COUNT_HISTORY);
+        assert_eq!(stats.gap_history.len(), GAP_HISTORY);
+        assert_eq!((stats.messages_history.capacity(), stats.gap_history.capacity()), capacities);
+        stats.calculate_msg_rate(start + Duration::from_secs(10));
+        let rate = stats.current_msg_rate();
+        assert!((rate - 10_000.0).abs() < 10.0, "{rate}");
+    }
+}
@@ -0,0 +1,206 @@
+use crate::{data_format::DataFormat, message_cached::MessageCached, theme::OZON_PINK};
+use egui::{text::LayoutJob, DragValue, TextFormat, TextStyle};
+use oze_canopen::canopen::RxMessageToStringFormat;
+use std::{collections::HashMap, time::Duration};
+use tokio::time::Instant;
+
+/// Default time a changed byte takes to fade back to the normal colour.
+const DEFAULT_FADE: Duration = Duration::from_millis(500);
+
+/// Last payload of a COB-ID and when each of its bytes last changed.
+#[derive(Debug, Clone)]
+struct LastData {
+    dlc: usize,
+    data: [u8; 8],
+    changed_at: [Option<Instant>; 8],
+}
+
+/// Tracks which bytes changed between consecutive frames of the same COB-ID.
+#[derive(Debug)]
+pub struct ByteChanges {
+    last: HashMap<u16, LastData>,
+    /// Time a changed byte stays highlighted.
+    pub fade: Duration,
+    /// Also highlight changed bytes in the message list.
+    pub in_list: bool,
+}
+
+impl Default for ByteChanges {
+    fn default() -> Self {
+        Self {
+            last: HashMap::new(),
+            fade: DEFAULT_FADE,
+            in_list: false,
+        }
+    }
+}
+
+impl ByteChanges {
+    /// Compares the message with the previous frame of its COB-ID and stores the
+    /// changed bytes in [`MessageCached::changed_bytes`].
+    pub fn on_message(&mut self, msg: &mut MessageCached) {
+        let frame = &msg.msg.msg;
+        let time = msg.get_timestamp();
+        let dlc = frame.dlc.min(8);
+        msg.changed_bytes = 0;
+
+        match self.last.get_mut(&frame.cob_id) {
+            // A new length is a new layout, bytes can't be compared.
+            Some(last) if last.dlc == dlc => {
+                for i in 0..dlc {
+                    if last.data[i] != frame.data[i] {
+                        msg.changed_bytes |= 1 << i;
+                        last.changed_at[i] = Some(time);
+                    }
+                }
+                last.data = frame.data;
+            }
+            _ => {
+                self.last.insert(
+                    frame.cob_id,
+                    LastData {
+                        dlc,
+                        data: frame.data,
+                        changed_at: [None; 8],
+                    },
+                );
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.last.clear();
+    }
+
+    /// Data of the latest frame of a COB-ID with every byte fading since its last change.
+    pub fn latest_job(&self, ui: &egui::Ui, msg: &MessageCached, format: DataFormat) -> LayoutJob {
+        let now = Instant::now();
+        let changed_at = self
+            .last
+            .get(&msg.msg.msg.cob_id)
+            .map(|last| last.changed_at)
+            .unwrap_or_default();
+        data_job(ui, msg, format, |i| {
+            changed_at[i].map_or(0.0, |t| {
+                fade_intensity(now.saturating_duration_since(t), self.fade)
+            })
+        })
+    }
+
+    /// Fade of the highlight in the message list, `None` if disabled there.
+    pub fn list_fade(&self) -> Option<Duration> {
+        self.in_list.then_some(self.fade)
+    }
+
+    pub fn settings_ui(&mut self, ui: &mut egui::Ui) {
+        ui.label("Changed bytes");
+        let mut fade_ms = u64::try_from(self.fade.as_millis()).unwrap_or(u64::MAX);
+        ui.horizontal(|ui| {
+            ui.label("Fade out");
+            if ui
+                .add(
+                    DragValue::new(&mut fade_ms)
+                        .range(100..=10_000)
+                        .speed(100)
+                        .suffix(" ms"),
+                )
+                .changed()
+            {
+                self.fade = Duration::from_millis(fade_ms);
+            }
+        });
+        ui.checkbox(&mut self.in_list, "Highlight in the message list");
+    }
+}
+
+/// Highlight of a byte changed `age` ago, from 1 just after the change to 0 once faded.
+pub fn fade_intensity(age: Duration, fade: Duration) -> f32 {
+    if fade.is_zero() {
+        return 0.0;
+    }
+    (1.0 - age.as_secs_f32() / fade.as_secs_f32()).max(0.0)
+}
+
+/// Lays out the data of a message, byte `i` is tinted by `intensity(i)` in 0..=1. The typed
+/// formats are tinted as a whole after their most changed byte.
+pub fn data_job(
+    ui: &egui::Ui,
+    msg: &MessageCached,
+    format: DataFormat,
+    intensity: impl Fn(usize) -> f32,
+) -> LayoutJob {
+    let font_id = TextStyle::Body.resolve(ui.style());
+    let color = ui.visuals().text_color();
+    let Some(format) = format.bytes() else {
+        let t = (0..msg.msg.msg.dlc.min(8))
+            .map(&intensity)
+            .fold(0.0, f32::max);
+        return LayoutJob::simple_singleline(
+            format.format(msg).into_owned(),
+            font_id,
+            color.lerp_to_gamma(OZON_PINK, t),
+        );
+    };
+    let text = msg.get_by_format(format);
+    let bytes: Vec<&str> = match format {
+        RxMessageToStringFormat::Ascii => text
+            .char_indices()
+            .map(|(i, c)| &text[i..i + c.len_utf8()])
+            .collect(),
+        _ => text.split(' ').collect(),
+    };
+
+    let mut job = LayoutJob::default();
+    for (i, byte) in bytes.iter().enumerate() {
+        if i > 0 && format != RxMessageToStringFormat::Ascii {
+            job.append(" ", 0.0, TextFormat::simple(font_id.clone(), color));
+        }
+        let t = if i < 8 { intensity(i) } else { 0.0 };
+        job.append(
+            byte,
+            0.0,
+            TextFormat::simple(font_id.clone(), color.lerp_to_gamma(OZON_PINK, t)),
+        );
+    }
+    job
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ByteChanges;
+    use crate::message_cached::MessageCached;
+    use oze_canopen::canopen::RxMessage;
+    use tokio::time::Instant;
+
+    fn message(dlc: usize, data: [u8; 8]) -> MessageCached {
+        MessageCached::new(
+            0,
+            RxMessage {
+                timestamp: Instant::now(),
+                cob_id: 0x181,
+                data,
+                dlc,
+            },
+        )
+    }
+
+    #[test]
+    fn test_byte_changes() {
+        let mut changes = ByteChanges::default();
+        let mut first = message(4, [1, 2, 3, 4, 0, 0, 0, 0]);
+        changes.on_message(&mut first);
+        assert_eq!(first.changed_bytes, 0);
+
+        let mut second = message(4, [1, 9, 3, 8, 0, 0, 0, 0]);
+        changes.on_message(&mut second);
+        assert_eq!(second.changed_bytes, 0b1010);
+
+        // The comparison restarts when the DLC changes.
+        let mut third = message(2, [7, 7, 0, 0, 0, 0, 0, 0]);
+        changes.on_message(&mut third);
+        assert_eq!(third.changed_bytes, 0);
+        let mut fourth = message(2, [7, 6, 0, 0, 0, 0, 0, 0]);
+        changes.on_message(&mut fourth);
+        assert_eq!(fourth.changed_bytes, 0b10);
+    }
+}
@@ -0,0 +1,238 @@
+use crate::message_cached::MessageCached;
+use chrono::Local;
+use std::{collections::VecDeque, time::Duration};
+use tokio::time::Instant;
+
+/// File a triggered capture is saved to.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum CaptureFormat {
+    /// `candump -l` log.
+    #[default]
+    Log,
+    /// Session file, reopened offline.
+    Session,
+}
+
+impl CaptureFormat {
+    pub const ALL: [Self; 2] = [Self::Log, Self::Session];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Log => "log",
+            Self::Session => "session",
+        }
+    }
+
+    /// Timestamped name of the capture of a trigger, [`crate::triggers::Triggers::save`] adds a
+    /// suffix if a capture of the same second already exists.
+    pub fn file_name(self, trigger: &str) -> String {
+        let trigger: String = trigger
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() {
+                    c.to_ascii_lowercase()
+                } else {
+                    '-'
+                }
+            })
+            .collect();
+        let date = Local::now().format("%Y%m%d-%H%M%S");
+        match self {
+            Self::Log => format!("{trigger}-{date}.log"),
+            Self::Session => format!("{trigger}-{date}.json"),
+        }
+    }
+}
+
+/// Frames kept around the time a trigger fired.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CaptureWindow {
+    /// Frames received before the trigger.
+    pub pre: Duration,
+    /// Frames received after the trigger.
+    pub post: Duration,
+    /// The trigger is armed again once the capture is saved, otherwise it is disabled.
+    pub rearm: bool,
+}
+
+impl Default for CaptureWindow {
+    fn default() -> Self {
+        Self {
+            pre: Duration::from_secs(5),
+            post: Duration::from_secs(5),
+            rearm: true,
+        }
+    }
+}
+
+impl CaptureWindow {
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        let seconds = |ui: &mut egui::Ui, duration: &mut Duration, prefix, hover| {
+            let mut secs = duration.as_secs_f64();
+            if ui
+                .add(
+                    egui::DragValue::new(&mut secs)
+                        .range(0.0..=600.0)
+                        .speed(0.1)
+                        .prefix(prefix)
+                        .suffix(" s"),
+                )
+                .on_hover_text(hover)
+                .changed()
+            {
+                *duration = Duration::from_secs_f64(secs);
+            }
+        };
+        seconds(
+            ui,
+            &mut self.pre,
+            "pre ",
+            "Frames kept from before the trigger",
+        );
+        seconds(
+            ui,
+            &mut self.post,
+            "post ",
+            "Frames recorded after the trigger",
+        );
+        ui.checkbox(&mut self.rearm, "re-arm")
+            .on_hover_text("Arm the trigger again once the capture is saved");
+    }
+}
+
+/// Capture recording the frames after its trigger.
+#[derive(Debug)]
+struct Running {
+    trigger: String,
+    until: Instant,
+    frames: Vec<MessageCached>,
+}
+
+/// Capture whose post-trigger window is over, ready to be saved.
+#[derive(Debug)]
+pub struct Finished {
+    pub trigger: String,
+    /// Frames of the pre-trigger ring followed by the ones received after the trigger.
+    pub frames: Vec<MessageCached>,
+}
+
+/// Ring of the last received frames and the captures started from it by the triggers.
+#[derive(Debug, Default)]
+pub struct CaptureBuffer {
+    ring: VecDeque<MessageCached>,
+    /// How long the frames stay in the ring, the longest pre-trigger window of the triggers.
+    keep: Duration,
+    running: Vec<Running>,
+}
+
+impl CaptureBuffer {
+    pub fn set_keep(&mut self, keep: Duration) {
+        self.keep = keep;
+        if keep.is_zero() {
+            self.ring.clear();
+        }
+    }
+
+    pub fn on_message(&mut self, msg: &MessageCached) {
+        for running in &mut self.running {
+            running.frames.push(msg.clone());
+        }
+        if self.keep.is_zero() {
+            return;
+        }
+        let time = msg.get_timestamp();
+        while self.ring.front().is_some_and(|oldest| {
+            time.saturating_duration_since(oldest.get_timestamp()) > self.keep
+        }) {
+            self.ring.pop_front();
+        }
+        self.ring.push_back(msg.clone());
+    }
+
+    /// Starts the capture of a trigger fired at `now`, with the frames of the ring received in
+    /// the pre-trigger window.
+    pub fn start(&mut self, trigger: &str, window: CaptureWindow, now: Instant) {
+        let frames = self
+            .ring
+            .iter()
+            .filter(|msg| now.saturating_duration_since(msg.get_timestamp()) <= window.pre)
+            .cloned()
+            .collect();
+        self.running.push(Running {
+            trigger: trigger.to_owned(),
+            until: now + window.post,
+            frames,
+        });
+    }
+
+    /// Captures whose post-trigger window ended before `now`.
+    pub fn take_finished(&mut self, now: Instant) -> Vec<Finished> {
+        let (finished, running) = std::mem::take(&mut self.running)
+            .into_iter()
+            .partition(|running| running.until <= now);
+        self.running = running;
+        finished
+            .into_iter()
+            .map(|running: Running| Finished {
+                trigger: running.trigger,
+                frames: running.frames,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CaptureBuffer, CaptureFormat, CaptureWindow};
+    use crate::message_cached::MessageCached;
+    use oze_canopen::canopen::RxMessage;
+    use std::time::Duration;
+    use tokio::time::Instant;
+
+    #[test]
+    fn test_capture() {
+        let start = Instant::now();
+        let message = |index: u64| {
+            MessageCached::new(
+                index,
+                RxMessage {
+                    timestamp: start + Duration::from_secs(index),
+                    cob_id: 0x181,
+                    data: [0; 8],
+                    dlc: 8,
+                },
+            )
+        };
+        let mut buffer = CaptureBuffer::default();
+        buffer.set_keep(Duration::from_secs(3));
+        for index in 0..10 {
+            buffer.on_message(&message(index));
+        }
+        // Frames older than the longest pre-trigger window are dropped.
+        assert_eq!(buffer.ring.len(), 4);
+
+        let window = CaptureWindow {
+            pre: Duration::from_secs(2),
+            post: Duration::from_secs(2),
+            rearm: true,
+        };
+        buffer.start("Trigger 1", window, start + Duration::from_secs(9));
+        buffer.on_message(&message(10));
+        assert!(buffer
+            .take_finished(start + Duration::from_secs(10))
+            .is_empty());
+        buffer.on_message(&message(11));
+        let finished = buffer.take_finished(start + Duration::from_secs(11));
+        assert_eq!(finished.len(), 1);
+        let indexes: Vec<u64> = finished[0].frames.iter().map(|msg| msg.index).collect();
+        assert_eq!(indexes, [7, 8, 9, 10, 11]);
+        assert!(buffer.running.is_empty());
+
+        assert!(CaptureFormat::Log
+            .file_name("Trigger 1")
+            .starts_with("trigger-1-"));
+        assert!(CaptureFormat::Session
+            .file_name("EMCY")
+            .starts_with("emcy-"));
+    }
+}
@@ -1,27 +1,490 @@
-use std::sync::Arc;
+use std::{
+    collections::{BTreeSet, VecDeque},
+    ops::RangeInclusive,
+    time::Duration,
+};
 
-use crate::{bitrate::RatesData, theme::OZON_PINK};
-use egui::Vec2b;
-use egui_plot::{Line, Plot, PlotPoints};
-use tokio::{runtime::Handle, sync::Mutex};
+use crate::{
+    bitrate::RatesData,
+    bus_stats::BusStats,
+    config,
+    export::ExportJob,
+    overlay_plot::to_csv,
+    theme::{Status, OZON_BLUE_ACTIVE, OZON_PINK},
+};
+use catppuccin_egui::FRAPPE;
+use chrono::Local;
+use egui::{Color32, DragValue, Id, TextEdit, Vec2b};
+use egui_plot::{HLine, Legend, Line, Plot, PlotBounds, PlotMemory, PlotPoints};
+use serde::{Deserialize, Serialize};
+use tokio::{sync::watch, time::Instant};
+
+/// File of the COB-IDs whose rate is plotted, in the config directory.
+const CONFIG_FILE: &str = "rate_plots.bin";
+
+/// File of the chart settings in the config directory.
+const SETTINGS_FILE: &str = "chart.bin";
+
+/// File of the selected chart tab in the config directory.
+const TAB_FILE: &str = "chart_tab.bin";
+
+/// Tab of the chart area, each with its own series and axes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChartTab {
+    /// Bitrates and message rates.
+    #[default]
+    Bandwidth,
+    /// Bus load.
+    Load,
+    /// Values of the pinned filters overlaid.
+    Signals,
+}
+
+impl ChartTab {
+    pub const ALL: [Self; 3] = [Self::Bandwidth, Self::Load, Self::Signals];
+
+    /// Tab selected in a previous run.
+    pub fn load() -> Self {
+        config::load(TAB_FILE).unwrap_or_default()
+    }
+
+    pub fn save(self) {
+        if let Err(e) = config::save(TAB_FILE, &self) {
+            log::warn!("Can't save the chart tab: {e}");
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Bandwidth => "📶 Bandwidth",
+            Self::Load => "📉 Load",
+            Self::Signals => "📈 Signals",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Self::Bandwidth => "Received and sent bitrates, message rate and rates of COB-IDs",
+            Self::Load => "Bus load over the last minutes",
+            Self::Signals => "Values plotted by the pinned filters, on one time axis",
+        }
+    }
+}
+
+/// Points drawn per series at most, longer windows are decimated.
+const MAX_POINTS: usize = 2000;
+
+/// Manual Y max offered when it is first enabled, in bits per second.
+const DEFAULT_Y_MAX: f64 = 500_000.0;
+
+/// Time span shown by the rate plots.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TimeWindow {
+    TenSeconds,
+    Minute,
+    FiveMinutes,
+    All,
+}
+
+impl TimeWindow {
+    pub const ALL: [Self; 4] = [Self::TenSeconds, Self::Minute, Self::FiveMinutes, Self::All];
+
+    /// Length of the window, `None` for all the history kept.
+    pub fn seconds(self) -> Option<f64> {
+        match self {
+            Self::TenSeconds => Some(10.0),
+            Self::Minute => Some(60.0),
+            Self::FiveMinutes => Some(300.0),
+            Self::All => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::TenSeconds => "10 s",
+            Self::Minute => "1 min",
+            Self::FiveMinutes => "5 min",
+            Self::All => "All",
+        }
+    }
+}
+
+/// Lowest bitrate on the logarithmic scale, lower rates are drawn at it.
+const LOG_FLOOR: f64 = 1.0;
+
+/// Bitrate in the unit fitting its magnitude, e.g. `480 kbit/s`.
+pub fn format_bitrate(bps: f64) -> String {
+    if bps.abs() >= 1_000_000.0 {
+        format!("{} Mbit/s", (bps / 10_000.0).round() / 100.0)
+    } else if bps.abs() >= 1000.0 {
+        format!("{} kbit/s", (bps / 100.0).round() / 10.0)
+    } else {
+        format!("{bps:.0} bps")
+    }
+}
+
+/// Series hidden by a click on their name in the legend of a plot.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HiddenSeries(BTreeSet<String>);
+
+impl HiddenSeries {
+    pub fn contains(&self, name: &str) -> bool {
+        self.0.contains(name)
+    }
+
+    /// Legend with the series hidden, a click on a name toggles it.
+    pub fn legend(&self) -> Legend {
+        Legend::default().hidden_items(self.0.iter().cloned())
+    }
+
+    /// Takes the series toggled in the legend of the plot `id` once it is shown, `names` being
+    /// the series drawn in it. The hidden series not drawn stay hidden. Returns whether they
+    /// changed.
+    pub fn update<'a>(
+        &mut self,
+        ctx: &egui::Context,
+        id: Id,
+        names: impl IntoIterator<Item = &'a str>,
+    ) -> bool {
+        PlotMemory::load(ctx, id).is_some_and(|memory| self.merge(names, memory.hidden_items))
+    }
+
+    fn merge<'a>(
+        &mut self,
+        names: impl IntoIterator<Item = &'a str>,
+        hidden_in_plot: impl IntoIterator<Item = String>,
+    ) -> bool {
+        let mut hidden = self.0.clone();
+        for name in names {
+            hidden.remove(name);
+        }
+        hidden.extend(hidden_in_plot);
+        let changed = hidden != self.0;
+        self.0 = hidden;
+        changed
+    }
+}
+
+impl<const N: usize> From<[&str; N]> for HiddenSeries {
+    fn from(names: [&str; N]) -> Self {
+        Self(names.into_iter().map(str::to_owned).collect())
+    }
+}
+
+/// Time window, Y range, scale and series of the rate plots, saved for the next run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChartSettings {
+    pub window: TimeWindow,
+    /// Top of the bitrate plot in bits per second, fitted to the rates if `None`.
+    pub y_max: Option<f64>,
+    /// Top of the bitrate plot at the bitrate of the bus, 100 % load, when it is known.
+    pub fit_bitrate: bool,
+    /// Logarithmic Y axis for the bitrates.
+    pub log_scale: bool,
+    pub hidden_bitrates: HiddenSeries,
+    pub hidden_rates: HiddenSeries,
+}
+
+impl Default for ChartSettings {
+    fn default() -> Self {
+        Self {
+            window: TimeWindow::TenSeconds,
+            y_max: None,
+            fit_bitrate: false,
+            log_scale: false,
+            hidden_bitrates: HiddenSeries::from(["Total"]),
+            hidden_rates: HiddenSeries::default(),
+        }
+    }
+}
+
+impl ChartSettings {
+    /// Top of the bitrate plot, `None` to fit it to the rates.
+    pub fn y_max(&self, bitrate: Option<u32>) -> Option<f64> {
+        bitrate
+            .filter(|_| self.fit_bitrate)
+            .map(f64::from)
+            .or(self.y_max)
+    }
+
+    /// Position of a bitrate on the Y axis of the bitrate plot.
+    pub fn to_axis(&self, bps: f64) -> f64 {
+        if self.log_scale {
+            bps.max(LOG_FLOOR).log10()
+        } else {
+            bps
+        }
+    }
+
+    /// Bitrate at a position on the Y axis of the bitrate plot.
+    pub fn from_axis(&self, y: f64) -> f64 {
+        if self.log_scale {
+            10f64.powf(y)
+        } else {
+            y
+        }
+    }
+
+    /// Points of a bitrate series on the Y axis.
+    fn scaled(&self, points: &[[f64; 2]]) -> Vec<[f64; 2]> {
+        points
+            .iter()
+            .map(|[time, bps]| [*time, self.to_axis(*bps)])
+            .collect()
+    }
+}
+
+/// At most [`MAX_POINTS`] points, keeping the largest value of each run of consecutive points
+/// so the peaks stay visible.
+fn decimate<'a>(points: impl ExactSizeIterator<Item = &'a [f64; 2]>) -> Vec<[f64; 2]> {
+    let step = points.len().div_ceil(MAX_POINTS).max(1);
+    let mut decimated: Vec<[f64; 2]> = Vec::with_capacity(points.len() / step + 1);
+    for (i, point) in points.enumerate() {
+        match decimated.last_mut() {
+            Some(last) if i % step != 0 => {
+                if point[1] > last[1] {
+                    *last = *point;
+                }
+            }
+            _ => decimated.push(*point),
+        }
+    }
+    decimated
+}
+
+/// Points of a series within `span`.
+fn within<'a>(
+    points: &'a VecDeque<[f64; 2]>,
+    span: &RangeInclusive<f64>,
+) -> std::collections::vec_deque::Iter<'a, [f64; 2]> {
+    let start = points.partition_point(|[time, _]| time < span.start());
+    let end = points.partition_point(|[time, _]| time <= span.end());
+    points.range(start..end.max(start))
+}
+
+/// Rates of a history in seconds since `started`, within `span`.
+fn since(
+    history: impl Iterator<Item = (Instant, f64)>,
+    started: Option<std::time::Instant>,
+    span: &RangeInclusive<f64>,
+) -> Vec<[f64; 2]> {
+    let Some(started) = started else {
+        return Vec::new();
+    };
+    history
+        .map(|(time, rate)| {
+            let time = time.into_std().saturating_duration_since(started);
+            [time.as_secs_f64(), rate]
+        })
+        .filter(|[time, _]| span.contains(time))
+        .collect()
+}
+
+/// Bitrates within the window of the chart.
+struct WindowedRates {
+    rx: Vec<[f64; 2]>,
+    tx: Vec<[f64; 2]>,
+    total: Vec<[f64; 2]>,
+    /// Time span of the points, seconds since `started`.
+    span: RangeInclusive<f64>,
+    started: Option<std::time::Instant>,
+}
+
+/// Colours of the rates of the plotted COB-IDs, in their order.
+const COB_ID_PALETTE: [Color32; 6] = [
+    FRAPPE.green,
+    FRAPPE.yellow,
+    FRAPPE.sapphire,
+    FRAPPE.mauve,
+    FRAPPE.peach,
+    FRAPPE.teal,
+];
+
+/// Plots the rate of the COB-IDs saved by a previous run.
+pub fn load_rate_plots(stats: &mut BusStats) {
+    for cob_id in config::load::<Vec<u16>>(CONFIG_FILE).unwrap_or_default() {
+        stats.track_cob_id(cob_id);
+    }
+}
+
+/// Starts or stops plotting the rate of a COB-ID, the plotted ones are saved for the next run.
+pub fn toggle_rate_plot(stats: &mut BusStats, cob_id: u16) {
+    if stats.is_tracked(cob_id) {
+        stats.untrack_cob_id(cob_id);
+    } else {
+        stats.track_cob_id(cob_id);
+    }
+    let cob_ids: Vec<u16> = stats.cob_id_rate_history().keys().copied().collect();
+    if let Err(e) = config::save(CONFIG_FILE, &cob_ids) {
+        log::warn!("Can't save the plotted COB-IDs: {e}");
+    }
+}
 
 #[derive(Debug)]
 pub struct Chart {
-    channel: Arc<Mutex<RatesData>>,
+    channel: watch::Receiver<RatesData>,
+    /// COB-ID typed to plot its rate.
+    cob_raw: String,
+    settings: ChartSettings,
+    /// Range shown when the plots were paused, they follow the newest rates otherwise.
+    paused: Option<PlotBounds>,
+    /// Range to show on the next frame.
+    pending_bounds: Option<PlotBounds>,
+    /// Range of the bitrate plot in the last frame.
+    visible: Option<PlotBounds>,
+    export: ExportJob,
 }
 
 impl Chart {
-    pub fn new(channel: Arc<Mutex<RatesData>>) -> Chart {
-        Chart { channel }
+    pub fn new(channel: watch::Receiver<RatesData>) -> Chart {
+        Chart {
+            channel,
+            cob_raw: String::new(),
+            settings: config::load(SETTINGS_FILE).unwrap_or_default(),
+            paused: None,
+            pending_bounds: None,
+            visible: None,
+            export: ExportJob::new("points"),
+        }
     }
 
-    pub fn ui(&mut self, ui: &mut egui::Ui) {
-        // Display Y-axis label manually on the left with spacing
+    /// Copies the bitrates to plot, decimated, so the bitrate task isn't held up while drawing:
+    /// the window up to the newest ones, or around the shown range when paused.
+    fn windowed_rates(&self) -> WindowedRates {
+        let data = self.channel.borrow();
+        let shown = self.pending_bounds.or(self.visible);
+        let span = if let (Some(_), Some(bounds)) = (self.paused, shown) {
+            // The range may be dragged by up to its width before the next frame.
+            let [min, max] = [bounds.min()[0], bounds.max()[0]];
+            let width = max - min;
+            (min - width)..=(max + width)
+        } else {
+            let latest = data.total.back().map_or(0.0, |[time, _]| *time);
+            let first = data.total.front().map_or(0.0, |[time, _]| *time);
+            let from = self
+                .settings
+                .window
+                .seconds()
+                .map_or(first, |window| first.max(latest - window));
+            from..=latest
+        };
+        WindowedRates {
+            rx: decimate(within(&data.rx, &span)),
+            tx: decimate(within(&data.tx, &span)),
+            total: decimate(within(&data.total, &span)),
+            span,
+            started: data.started,
+        }
+    }
+
+    /// Pause, reset and export buttons.
+    fn show_view_controls(&mut self, ui: &mut egui::Ui, stats: &BusStats) {
+        if self.paused.is_some() {
+            if ui
+                .button("▶ Live")
+                .on_hover_text("Follow the newest rates again")
+                .clicked()
+            {
+                self.paused = None;
+                self.pending_bounds = None;
+            }
+        } else if ui
+            .button("⏸ Pause")
+            .on_hover_text("Freeze the shown range to drag it and zoom with the right button, the rates keep being recorded")
+            .clicked()
+        {
+            self.paused = self.visible;
+            self.pending_bounds = self.visible;
+        }
+        if ui
+            .add_enabled(self.paused.is_some(), egui::Button::new("Reset view"))
+            .on_hover_text("Back to the range shown when paused")
+            .clicked()
+        {
+            self.pending_bounds = self.paused;
+        }
+        if ui
+            .add_enabled(
+                self.export.is_idle() && self.visible.is_some(),
+                egui::Button::new("Export CSV…"),
+            )
+            .on_hover_text("Write the shown series within the visible time span to a CSV file in the working directory")
+            .clicked()
+        {
+            self.export_visible(stats);
+        }
+        self.export.ui(ui);
+    }
+
+    /// Writes every shown series within the visible time span, at full resolution.
+    fn export_visible(&mut self, stats: &BusStats) {
+        let Some(visible) = self.visible else {
+            return;
+        };
+        let span = visible.min()[0]..=visible.max()[0];
+        let data = self.channel.borrow();
+        let mut names = Vec::new();
+        let mut series: Vec<Vec<[f64; 2]>> = Vec::new();
+        for (name, points) in [("RX", &data.rx), ("TX", &data.tx), ("Total", &data.total)] {
+            if !self.settings.hidden_bitrates.contains(name) {
+                names.push(format!("{name} (bits/s)"));
+                series.push(within(points, &span).copied().collect());
+            }
+        }
+        let started = data.started;
+        drop(data);
+        let hidden = &self.settings.hidden_rates;
+        if !hidden.contains("Messages") {
+            names.push("Messages (msg/s)".to_owned());
+            series.push(since(stats.msg_rate_history(), started, &span));
+        }
+        for (cob_id, history) in stats.cob_id_rate_history() {
+            let name = format!("{cob_id:03X}");
+            if !hidden.contains(&name) {
+                names.push(format!("{name} (msg/s)"));
+                series.push(since(history.iter().copied(), started, &span));
+            }
+        }
+        let rows = series.iter().map(Vec::len).sum();
+        let path = format!("rates-{}.csv", Local::now().format("%Y%m%d-%H%M%S"));
+        self.export.start(path, rows, move || to_csv(&names, &series));
+    }
+
+    /// Bitrate plot and, below it on the same time axis, the message rate plot with the rates
+    /// of the plotted COB-IDs. `bitrate` is the one of the bus, to fit the Y axis to, `height`
+    /// the one of the bitrate plot, the message rate plot is half as high.
+    pub fn ui(&mut self, ui: &mut egui::Ui, stats: &mut BusStats, bitrate: Option<u32>, height: f32) {
         ui.horizontal(|ui| {
+            ui.menu_button("⚙", |ui| self.show_settings(ui, bitrate))
+                .response
+                .on_hover_text("Time window, Y range and scale of the plots");
+            ui.separator();
+            self.show_view_controls(ui, stats);
+        });
+        ui.horizontal(|ui| self.show_rate_plots(ui, stats));
+
+        let data = self.windowed_rates();
+        let span = data.span.clone();
+        let live = self.paused.is_none();
+        let settings = &self.settings;
+        let y_max = settings.y_max(bitrate);
+        let pending_bounds = self.pending_bounds.take();
+        let msg_rates = since(stats.msg_rate_history(), data.started, &span);
+        let cob_id_rates: Vec<(u16, Vec<[f64; 2]>)> = stats
+            .cob_id_rate_history()
+            .iter()
+            .map(|(cob_id, history)| (*cob_id, since(history.iter().copied(), data.started, &span)))
+            .collect();
+        let total = &data.total;
+        let rates = &msg_rates;
+
+        // Display Y-axis label manually on the left with spacing
+        self.visible = Some(ui.horizontal(|ui| {
             ui.vertical(|ui| {
-                ui.add_space(120.0); // Center the label vertically
+                ui.add_space(height / 2.0 - 5.0); // Center the label vertically
+                let label = if settings.log_scale { "Bitrate\n(log)" } else { "Bitrate" };
                 ui.label(
-                    egui::RichText::new("Bitrate\n(bits/s)")
+                    egui::RichText::new(label)
                         .size(11.0)
                 );
             });
@@ -29,28 +492,449 @@ impl Chart {
             ui.add_space(5.0); // Space between label and plot
             
             let plot = Plot::new("plot")
-                .height(250.0)
-                .allow_drag(false)
-                .allow_boxed_zoom(false)
+                .id(Id::new(BITRATE_PLOT))
+                .height(height)
+                .allow_drag(Vec2b::new(!live, false))
+                .allow_boxed_zoom(!live)
                 .allow_scroll(false)
                 .allow_zoom(false)
+                .allow_double_click_reset(false)
                 .show_axes(Vec2b::new(true, true))
                 .x_axis_label("Time (s)")
-                .label_formatter(|name, value| {
-                    if !name.is_empty() {
-                        format!("{}: {:.1} s, {:.0} bps", name, value.x, value.y)
-                    } else {
-                        format!("Time: {:.1} s\nBitrate: {:.0} bps", value.x, value.y)
+                .y_axis_formatter(move |mark, _| format_bitrate(settings.from_axis(mark.value)))
+                .legend(settings.hidden_bitrates.legend())
+                .link_axis(LINK_GROUP, true, false)
+                .link_cursor(LINK_GROUP, true, false)
+                .label_formatter(|_, value| hover_text(value.x, total, rates));
+
+            // There is no Borrowed PlotPoints so we need to copy every time
+            let response = plot.show(ui, |plot_ui| {
+                if let Some(bounds) = pending_bounds {
+                    plot_ui.set_plot_bounds(bounds);
+                } else if let (true, Some(y_max)) = (live, y_max) {
+                    plot_ui.set_plot_bounds(PlotBounds::from_min_max(
+                        [*span.start(), settings.to_axis(0.0)],
+                        [*span.end(), settings.to_axis(y_max)],
+                    ));
+                } else if live {
+                    plot_ui.set_auto_bounds(Vec2b::TRUE);
+                }
+                plot_ui.line(Line::new(PlotPoints::new(settings.scaled(&data.rx))).color(OZON_PINK).name("RX"));
+                plot_ui.line(Line::new(PlotPoints::new(settings.scaled(&data.tx))).color(OZON_BLUE_ACTIVE).name("TX"));
+                plot_ui.line(Line::new(PlotPoints::new(settings.scaled(total))).color(Color32::GRAY).name("Total"));
+            });
+            *response.transform.bounds()
+        }).inner);
+
+        let names: Vec<String> = cob_id_rates
+            .iter()
+            .map(|(cob_id, _)| format!("{cob_id:03X}"))
+            .collect();
+        let legend = self.settings.hidden_rates.legend();
+        Self::show_msg_rate_plot(ui, live, legend, total, rates, cob_id_rates, height / 2.0);
+
+        let ctx = ui.ctx();
+        let bitrates_changed =
+            self.settings
+                .hidden_bitrates
+                .update(ctx, Id::new(BITRATE_PLOT), ["RX", "TX", "Total"]);
+        let names = names.iter().map(String::as_str).chain(["Messages"]);
+        let rates_changed = self
+            .settings
+            .hidden_rates
+            .update(ctx, Id::new(MSG_RATE_PLOT), names);
+        if bitrates_changed || rates_changed {
+            self.save_settings();
+        }
+    }
+
+    fn save_settings(&self) {
+        if let Err(e) = config::save(SETTINGS_FILE, &self.settings) {
+            log::warn!("Can't save the chart settings: {e}");
+        }
+    }
+
+    /// Message rate and rates of the plotted COB-IDs, on the time axis of the bitrate plot.
+    fn show_msg_rate_plot(
+        ui: &mut egui::Ui,
+        live: bool,
+        legend: Legend,
+        total: &[[f64; 2]],
+        rates: &[[f64; 2]],
+        cob_id_rates: Vec<(u16, Vec<[f64; 2]>)>,
+        height: f32,
+    ) {
+        ui.horizontal(|ui| {
+            ui.vertical(|ui| {
+                ui.add_space(height / 2.0 - 10.0);
+                ui.label(
+                    egui::RichText::new("Rate\n(msg/s)")
+                        .size(11.0)
+                );
+            });
+            
+            ui.add_space(5.0);
+            
+            Plot::new("msg_rate_plot")
+                .id(Id::new(MSG_RATE_PLOT))
+                .height(height)
+                .allow_drag(Vec2b::new(!live, false))
+                .allow_boxed_zoom(!live)
+                .allow_scroll(false)
+                .allow_zoom(false)
+                .allow_double_click_reset(false)
+                .include_y(0.0)
+                .legend(legend)
+                .link_axis(LINK_GROUP, true, false)
+                .link_cursor(LINK_GROUP, true, false)
+                .label_formatter(|_, value| hover_text(value.x, total, rates))
+                .show(ui, |plot_ui| {
+                    if live {
+                        plot_ui.set_auto_bounds(Vec2b::TRUE);
+                    }
+                    plot_ui.line(Line::new(PlotPoints::new(rates.to_vec())).color(OZON_PINK).name("Messages"));
+                    for (i, (cob_id, points)) in cob_id_rates.into_iter().enumerate() {
+                        plot_ui.line(
+                            Line::new(PlotPoints::new(points))
+                                .color(COB_ID_PALETTE[i % COB_ID_PALETTE.len()])
+                                .name(format!("{cob_id:03X}")),
+                        );
                     }
                 });
+        });
+    }
 
-            Handle::current().block_on(async {
-                let data: Vec<[f64; 2]> = self.channel.lock().await.clone();
-                // There is no Borrowed PlotPoints so we need to copy every time
-                plot.show(ui, |plot_ui| {
-                    plot_ui.line(Line::new(PlotPoints::new(data)).color(OZON_PINK).name("CAN Bitrate"));
-                })
-            });
+    /// Time window, Y range and scale, saved when changed.
+    fn show_settings(&mut self, ui: &mut egui::Ui, bitrate: Option<u32>) {
+        let before = self.settings.clone();
+        ui.horizontal(|ui| {
+            ui.label("Window:");
+            for window in TimeWindow::ALL {
+                ui.selectable_value(&mut self.settings.window, window, window.name());
+            }
         });
+        ui.horizontal(|ui| {
+            let mut fixed = self.settings.y_max.is_some();
+            let mut y_max = self.settings.y_max.unwrap_or(DEFAULT_Y_MAX);
+            ui.checkbox(&mut fixed, "Y max")
+                .on_hover_text("Fix the top of the bitrate plot instead of fitting it to the rates");
+            ui.add_enabled(
+                fixed,
+                DragValue::new(&mut y_max)
+                    .range(1000.0..=10_000_000.0)
+                    .speed(1000.0)
+                    .suffix(" bits/s"),
+            );
+            self.settings.y_max = fixed.then_some(y_max);
+        });
+        let hover = match bitrate {
+            Some(bitrate) => format!("The top of the bitrate plot is {bitrate} bits/s, 100 % bus load"),
+            None => "No bitrate configured or reported by the interface".to_owned(),
+        };
+        ui.checkbox(&mut self.settings.fit_bitrate, "Fit Y to the bitrate")
+            .on_hover_text(hover);
+        ui.checkbox(&mut self.settings.log_scale, "Logarithmic Y")
+            .on_hover_text("Idle and busy bitrates readable on the same plot");
+
+        if self.settings != before {
+            self.save_settings();
+        }
+    }
+
+    /// Plotted COB-IDs, a click removes one, and the field to add one.
+    fn show_rate_plots(&mut self, ui: &mut egui::Ui, stats: &mut BusStats) {
+        ui.label("Rate of:");
+        let plotted: Vec<u16> = stats.cob_id_rate_history().keys().copied().collect();
+        for (i, cob_id) in plotted.into_iter().enumerate() {
+            let text = egui::RichText::new(format!("{cob_id:03X} ❌"))
+                .color(COB_ID_PALETTE[i % COB_ID_PALETTE.len()]);
+            if ui
+                .small_button(text)
+                .on_hover_text("Stop plotting the rate of this COB-ID")
+                .clicked()
+            {
+                toggle_rate_plot(stats, cob_id);
+            }
+        }
+        ui.add(
+            TextEdit::singleline(&mut self.cob_raw)
+                .hint_text("COB-ID")
+                .desired_width(60.0),
+        );
+        let cob_id = u16::from_str_radix(self.cob_raw.trim(), 16)
+            .ok()
+            .filter(|cob_id| *cob_id <= 0x7FF && !stats.is_tracked(*cob_id));
+        if ui
+            .add_enabled(cob_id.is_some(), egui::Button::new("➕"))
+            .on_hover_text("Plot the rate of this COB-ID, e.g. 181")
+            .clicked()
+        {
+            if let Some(cob_id) = cob_id {
+                toggle_rate_plot(stats, cob_id);
+                self.cob_raw.clear();
+            }
+        }
+    }
+}
+
+/// Plots whose time axis and cursor move together.
+const LINK_GROUP: &str = "rates";
+
+/// Id of the bitrate plot, to read its legend.
+const BITRATE_PLOT: &str = "bitrate_plot";
+
+/// Id of the message rate plot, to read its legend.
+const MSG_RATE_PLOT: &str = "msg_rate_plot";
+
+/// Value of the point of a series nearest to `time`.
+pub fn value_at(points: &[[f64; 2]], time: f64) -> Option<f64> {
+    let after = points.partition_point(|[x, _]| *x < time);
+    let before = after.checked_sub(1).and_then(|i| points.get(i));
+    match (before, points.get(after)) {
+        (Some(before), Some(after)) if time - before[0] < after[0] - time => Some(before[1]),
+        (_, Some(after)) => Some(after[1]),
+        (Some(before), None) => Some(before[1]),
+        (None, None) => None,
+    }
+}
+
+/// Bitrate and message rate at the time under the cursor.
+fn hover_text(time: f64, bitrates: &[[f64; 2]], msg_rates: &[[f64; 2]]) -> String {
+    let bitrate = value_at(bitrates, time).map_or_else(|| "--".to_owned(), format_bitrate);
+    let msg_rate = value_at(msg_rates, time).map_or_else(|| "--".to_owned(), |rate| format!("{rate:.0} msg/s"));
+    format!("Time: {time:.1} s\nBitrate: {bitrate}\nRate: {msg_rate}")
+}
+
+/// Bus load above which it is shown in yellow, in percent.
+pub const LOAD_WARNING: f64 = 50.0;
+
+/// Bus load above which it is shown in red, in percent.
+pub const LOAD_CRITICAL: f64 = 80.0;
+
+/// Default window of the bus load chart.
+const LOAD_WINDOW: Duration = Duration::from_secs(300);
+
+/// Allowed window of the bus load chart, in seconds.
+pub const LOAD_WINDOW_RANGE: RangeInclusive<f64> = 10.0..=3600.0;
+
+/// Interval between two points of the bus load chart, the load is computed every frame.
+const LOAD_SAMPLE_PERIOD: Duration = Duration::from_millis(100);
+
+/// Colour of a bus load in percent.
+pub fn load_color(load: f64) -> Color32 {
+    if load > LOAD_CRITICAL {
+        Status::Error.color()
+    } else if load > LOAD_WARNING {
+        Status::Warning.color()
+    } else {
+        Status::Ok.color()
+    }
+}
+
+/// File of the bus load chart settings in the config directory.
+const LOAD_CHART_FILE: &str = "load_chart.bin";
+
+/// Id of the bus load plot, to read its legend.
+const LOAD_PLOT: &str = "load_plot";
+
+/// Window and series of the bus load chart, saved for the next run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct LoadChartSettings {
+    window: Duration,
+    hidden: HiddenSeries,
+}
+
+impl Default for LoadChartSettings {
+    fn default() -> Self {
+        Self {
+            window: LOAD_WINDOW,
+            hidden: HiddenSeries::default(),
+        }
+    }
+}
+
+/// Bus load over the last minutes.
+#[derive(Debug)]
+pub struct LoadChart {
+    start: Instant,
+    settings: LoadChartSettings,
+    /// Seconds since `start` and load in percent, oldest first.
+    points: VecDeque<[f64; 2]>,
+    last_sample: Option<Instant>,
+}
+
+impl Default for LoadChart {
+    fn default() -> Self {
+        Self {
+            start: Instant::now(),
+            settings: LoadChartSettings::default(),
+            points: VecDeque::new(),
+            last_sample: None,
+        }
+    }
+}
+
+impl LoadChart {
+    /// Chart with the settings saved by a previous run.
+    pub fn load() -> Self {
+        Self {
+            settings: config::load(LOAD_CHART_FILE).unwrap_or_default(),
+            ..Self::default()
+        }
+    }
+
+    fn save_settings(&self) {
+        if let Err(e) = config::save(LOAD_CHART_FILE, &self.settings) {
+            log::warn!("Can't save the bus load chart settings: {e}");
+        }
+    }
+
+    /// Adds the load at `now`, at most one point per sample period.
+    pub fn push(&mut self, now: Instant, load: f64) {
+        if self
+            .last_sample
+            .is_some_and(|last| now.saturating_duration_since(last) < LOAD_SAMPLE_PERIOD)
+        {
+            return;
+        }
+        self.last_sample = Some(now);
+        let time = now.saturating_duration_since(self.start).as_secs_f64();
+        self.points.push_back([time, load]);
+        let oldest = time - self.settings.window.as_secs_f64();
+        while self.points.front().is_some_and(|[time, _]| *time < oldest) {
+            self.points.pop_front();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.points.clear();
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui, height: f32) {
+        ui.horizontal(|ui| {
+            ui.label("Window:");
+            let mut window = self.settings.window.as_secs_f64();
+            if ui
+                .add(
+                    DragValue::new(&mut window)
+                        .range(LOAD_WINDOW_RANGE)
+                        .suffix(" s"),
+                )
+                .on_hover_text("Time span of the bus load plot, older points are dropped")
+                .changed()
+            {
+                self.settings.window = Duration::from_secs_f64(window);
+                self.save_settings();
+            }
+        });
+
+        let points: Vec<[f64; 2]> = self.points.iter().copied().collect();
+        let latest = points.last().map_or(0.0, |[time, _]| *time);
+        Plot::new("load_plot")
+            .id(Id::new(LOAD_PLOT))
+            .height(height)
+            .allow_drag(false)
+            .allow_boxed_zoom(false)
+            .allow_scroll(false)
+            .allow_zoom(false)
+            .include_y(0.0)
+            .include_y(100.0)
+            .include_x(latest - self.settings.window.as_secs_f64())
+            .legend(self.settings.hidden.legend())
+            .x_axis_label("Time (s)")
+            .y_axis_label("Bus load (%)")
+            .label_formatter(|_, value| format!("{:.1} s\n{:.1}%", value.x, value.y))
+            .show(ui, |plot_ui| {
+                plot_ui.hline(HLine::new(LOAD_WARNING).color(Status::Warning.color()).name("Warning"));
+                plot_ui.hline(HLine::new(LOAD_CRITICAL).color(Status::Error.color()).name("Critical"));
+                plot_ui.line(Line::new(PlotPoints::new(points)).color(OZON_PINK).name("Bus load"));
+            });
+        if self.settings.hidden.update(
+            ui.ctx(),
+            Id::new(LOAD_PLOT),
+            ["Warning", "Critical", "Bus load"],
+        ) {
+            self.save_settings();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        decimate, format_bitrate, value_at, within, ChartSettings, HiddenSeries, LoadChart,
+        MAX_POINTS,
+    };
+    use std::{collections::VecDeque, time::Duration};
+    use tokio::time::Instant;
+
+    #[test]
+    fn test_load_chart() {
+        let mut chart = LoadChart::default();
+        let start = Instant::now();
+        // A point per 100 ms at most, points older than the window dropped.
+        for millis in (0..400_000).step_by(20) {
+            chart.push(start + Duration::from_millis(millis), 10.0);
+        }
+        // The point on the edge of the window may be rounded out.
+        assert!((3000..=3001).contains(&chart.points.len()), "{}", chart.points.len());
+        assert!(chart.points[0][0] >= 99.9);
+    }
+
+    #[test]
+    fn test_decimate() {
+        let points: Vec<[f64; 2]> = (0..10_000).map(|i| [f64::from(i), f64::from(i % 7)]).collect();
+        let decimated = decimate(points.iter());
+        assert_eq!(decimated.len(), MAX_POINTS);
+        // The largest value of each run of 5 points is kept with its time.
+        assert!((decimated[1][0] - 6.0).abs() < 1e-9, "{:?}", decimated[1]);
+        assert!(decimated.iter().all(|[_, value]| *value >= 4.0));
+        assert_eq!(decimate(points[..10].iter()), &points[..10]);
+        let points: VecDeque<[f64; 2]> = points.into_iter().collect();
+        assert_eq!(within(&points, &(2.5..=5.0)).count(), 3);
+        assert_eq!(within(&points, &(20_000.0..=30_000.0)).count(), 0);
+
+        let settings = ChartSettings {
+            y_max: Some(100_000.0),
+            fit_bitrate: true,
+            ..ChartSettings::default()
+        };
+        assert_eq!(settings.y_max(Some(250_000)), Some(250_000.0));
+        assert_eq!(settings.y_max(None), Some(100_000.0));
+    }
+
+    #[test]
+    fn test_bitrate_units_and_scale() {
+        assert_eq!(format_bitrate(480.0), "480 bps");
+        assert_eq!(format_bitrate(480_000.0), "480 kbit/s");
+        assert_eq!(format_bitrate(12_480.0), "12.5 kbit/s");
+        assert_eq!(format_bitrate(1_250_000.0), "1.25 Mbit/s");
+
+        let settings = ChartSettings {
+            log_scale: true,
+            ..ChartSettings::default()
+        };
+        assert!((settings.to_axis(100_000.0) - 5.0).abs() < 1e-9);
+        assert!(settings.to_axis(0.0).abs() < 1e-9);
+        assert!((settings.from_axis(3.0) - 1000.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_hidden_series() {
+        let mut hidden = HiddenSeries::from(["Total", "181"]);
+        // 181 isn't plotted: it stays hidden while RX is hidden and Total shown.
+        assert!(hidden.merge(["RX", "TX", "Total"], ["RX".to_owned()]));
+        assert_eq!(hidden, HiddenSeries::from(["RX", "181"]));
+        assert!(!hidden.merge(["RX", "TX", "Total"], ["RX".to_owned()]));
+    }
+
+    #[test]
+    fn test_value_at() {
+        let points = [[1.0, 10.0], [2.0, 20.0], [3.0, 30.0]];
+        assert_eq!(value_at(&points, 0.0), Some(10.0));
+        assert_eq!(value_at(&points, 1.4), Some(10.0));
+        assert_eq!(value_at(&points, 1.6), Some(20.0));
+        assert_eq!(value_at(&points, 9.0), Some(30.0));
+        assert_eq!(value_at(&[], 1.0), None);
     }
 }
@@ -0,0 +1,374 @@
+use crate::{
+    interpreter::{DecodedView, MessageInterpreter},
+    message_cached::{MessageCached, RxMessageAdditional},
+    theme::{Status, OZON_GRAY},
+};
+use core::fmt;
+use egui::Color32;
+use oze_canopen::canopen::{NodeId, RxMessage};
+use std::{
+    collections::BTreeMap,
+    sync::{PoisonError, RwLock},
+};
+
+/// Index of the statusword of a drive.
+pub const STATUSWORD: u16 = 0x6041;
+
+static PDOS: RwLock<StatuswordPdos> = RwLock::new(StatuswordPdos {
+    declared: BTreeMap::new(),
+    learned: BTreeMap::new(),
+    tpdos: BTreeMap::new(),
+});
+
+/// State of the power drive state machine of the drive profile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DriveState {
+    NotReadyToSwitchOn,
+    SwitchOnDisabled,
+    ReadyToSwitchOn,
+    SwitchedOn,
+    OperationEnabled,
+    QuickStopActive,
+    FaultReactionActive,
+    Fault,
+    /// Combination of bits the state machine doesn't define.
+    Unknown,
+}
+
+impl DriveState {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::NotReadyToSwitchOn => "Not ready to switch on",
+            Self::SwitchOnDisabled => "Switch on disabled",
+            Self::ReadyToSwitchOn => "Ready to switch on",
+            Self::SwitchedOn => "Switched on",
+            Self::OperationEnabled => "Operation enabled",
+            Self::QuickStopActive => "Quick stop active",
+            Self::FaultReactionActive => "Fault reaction active",
+            Self::Fault => "Fault",
+            Self::Unknown => "Unknown",
+        }
+    }
+
+    pub fn color(self) -> Color32 {
+        match self {
+            Self::OperationEnabled => Status::Ok.color(),
+            Self::QuickStopActive => Status::Warning.color(),
+            Self::FaultReactionActive | Self::Fault => Status::Error.color(),
+            _ => OZON_GRAY,
+        }
+    }
+}
+
+impl fmt::Display for DriveState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Statusword (0x6041) of a drive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Statusword(pub u16);
+
+impl Statusword {
+    /// Flags shown next to the state, with their bit.
+    const FLAGS: [(u16, &'static str); 4] = [
+        (4, "voltage enabled"),
+        (7, "warning"),
+        (10, "target reached"),
+        (11, "internal limit"),
+    ];
+
+    /// State from the bits 0 to 3, 5 and 6.
+    pub fn state(self) -> DriveState {
+        let word = self.0;
+        match (word & 0x4F, word & 0x6F) {
+            (0x00, _) => DriveState::NotReadyToSwitchOn,
+            (0x40, _) => DriveState::SwitchOnDisabled,
+            (_, 0x21) => DriveState::ReadyToSwitchOn,
+            (_, 0x23) => DriveState::SwitchedOn,
+            (_, 0x27) => DriveState::OperationEnabled,
+            (_, 0x07) => DriveState::QuickStopActive,
+            (0x0F, _) => DriveState::FaultReactionActive,
+            (0x08, _) => DriveState::Fault,
+            _ => DriveState::Unknown,
+        }
+    }
+
+    fn bit(self, bit: u16) -> bool {
+        self.0 & (1 << bit) != 0
+    }
+
+    pub fn voltage_enabled(self) -> bool {
+        self.bit(4)
+    }
+
+    pub fn warning(self) -> bool {
+        self.bit(7)
+    }
+
+    pub fn target_reached(self) -> bool {
+        self.bit(10)
+    }
+
+    pub fn internal_limit(self) -> bool {
+        self.bit(11)
+    }
+
+    /// Names of the flags set.
+    pub fn flags(self) -> Vec<&'static str> {
+        Self::FLAGS
+            .iter()
+            .filter(|(bit, _)| self.bit(*bit))
+            .map(|(_, name)| *name)
+            .collect()
+    }
+}
+
+impl fmt::Display for Statusword {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Statusword 0x{:04X} {}", self.0, self.state())?;
+        let flags = self.flags();
+        if !flags.is_empty() {
+            write!(f, " ({})", flags.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Mapping of a TPDO as written over SDO.
+#[derive(Debug, Clone, Default)]
+struct Tpdo {
+    /// COB-ID written to the communication parameters, the default one of the TPDO if `None`.
+    cob_id: Option<u16>,
+    count: u8,
+    entries: [u32; 8],
+}
+
+impl Tpdo {
+    /// Byte offset of the statusword in the frames of the TPDO, if it is mapped on a byte.
+    fn statusword_offset(&self) -> Option<usize> {
+        let mut bits = 0;
+        for entry in self.entries.iter().take(usize::from(self.count)) {
+            if entry >> 16 == u32::from(STATUSWORD) {
+                return (bits % 8 == 0).then_some(bits / 8);
+            }
+            bits += (entry & 0xFF) as usize;
+        }
+        None
+    }
+}
+
+/// COB-IDs of the PDOs carrying a statusword with its byte offset.
+#[derive(Debug)]
+struct StatuswordPdos {
+    declared: BTreeMap<u16, usize>,
+    /// From the mappings written over SDO, see `learn`.
+    learned: BTreeMap<u16, usize>,
+    tpdos: BTreeMap<(NodeId, u8), Tpdo>,
+}
+
+impl StatuswordPdos {
+    fn on_download(&mut self, node_id: NodeId, index: u16, subindex: u8, value: u32) {
+        let (tpdo, mapping) = match index {
+            0x1800..=0x1803 if subindex == 1 => (index - 0x1800, false),
+            0x1A00..=0x1A03 if subindex <= 8 => (index - 0x1A00, true),
+            _ => return,
+        };
+        #[allow(clippy::cast_possible_truncation)]
+        let tpdo_number = tpdo as u8;
+        let entry = self.tpdos.entry((node_id, tpdo_number)).or_default();
+        match (mapping, subindex) {
+            #[allow(clippy::cast_possible_truncation)]
+            (false, _) => entry.cob_id = Some((value & 0x7FF) as u16),
+            #[allow(clippy::cast_possible_truncation)]
+            (true, 0) => entry.count = (value as u8).min(8),
+            (true, subindex) => entry.entries[usize::from(subindex - 1)] = value,
+        }
+        self.learned = self
+            .tpdos
+            .iter()
+            .filter_map(|((node_id, tpdo), entry)| {
+                let cob_id = entry
+                    .cob_id
+                    .unwrap_or(0x180 + 0x100 * u16::from(*tpdo) + u16::from(*node_id));
+                Some((cob_id, entry.statusword_offset()?))
+            })
+            .collect();
+    }
+
+    fn offset(&self, cob_id: u16) -> Option<usize> {
+        self.declared
+            .get(&cob_id)
+            .or_else(|| self.learned.get(&cob_id))
+            .copied()
+    }
+}
+
+/// Declares that the frames of `cob_id` carry a statusword at the byte `offset`.
+pub fn declare(cob_id: u16, offset: usize) {
+    PDOS.write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .declared
+        .insert(cob_id, offset);
+}
+
+/// Follows the TPDO mappings written by the expedited SDO downloads of the frame, so the
+/// statuswords of the PDOs are found without being declared.
+pub fn learn(msg: &RxMessage) {
+    let data = msg.data;
+    // Initiate download request, expedited.
+    let download = (0x601..=0x67F).contains(&msg.cob_id) && data[0] & 0xE2 == 0x22;
+    if !download {
+        return;
+    }
+    let index = u16::from_le_bytes([data[1], data[2]]);
+    if !matches!(index, 0x1800..=0x1803 | 0x1A00..=0x1A03) {
+        return;
+    }
+    #[allow(clippy::cast_possible_truncation)]
+    let node_id = (msg.cob_id - 0x600) as NodeId;
+    let value = u32::from_le_bytes([data[4], data[5], data[6], data[7]]);
+    PDOS.write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .on_download(node_id, index, data[3], value);
+}
+
+/// Statusword carried by the frame, an expedited SDO upload of 0x6041 or a PDO declared or
+/// mapped with it.
+pub fn statusword(msg: &RxMessage) -> Option<Statusword> {
+    let data = msg.data;
+    let upload = (0x581..=0x5FF).contains(&msg.cob_id) && data[0] & 0xE2 == 0x42;
+    if upload {
+        let index = u16::from_le_bytes([data[1], data[2]]);
+        return (index == STATUSWORD && data[3] == 0)
+            .then(|| Statusword(u16::from_le_bytes([data[4], data[5]])));
+    }
+    let offset = PDOS
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .offset(msg.cob_id)?;
+    let bytes = data
+        .get(offset..offset + 2)
+        .filter(|_| offset + 2 <= msg.dlc)?;
+    Some(Statusword(u16::from_le_bytes([bytes[0], bytes[1]])))
+}
+
+/// Statuswords of the drives, after the protocol decode of their SDO or PDO frame.
+pub struct Cia402;
+
+impl MessageInterpreter for Cia402 {
+    fn name(&self) -> &'static str {
+        "CiA 402"
+    }
+
+    fn matches(&self, msg: &MessageCached) -> bool {
+        statusword(&msg.msg.msg).is_some()
+    }
+
+    fn decode(&self, msg: &MessageCached) -> DecodedView {
+        let Some(word) = statusword(&msg.msg.msg) else {
+            return DecodedView::default();
+        };
+        let summary = match &msg.additional {
+            RxMessageAdditional::None => word.to_string(),
+            additional => format!("{additional}, {word}"),
+        };
+        let yes_no = |set: bool| if set { "yes" } else { "no" }.to_owned();
+        DecodedView {
+            summary,
+            fields: [
+                ("state", word.state().to_string()),
+                ("statusword", format!("0x{:04X}", word.0)),
+                ("voltage enabled", yes_no(word.voltage_enabled())),
+                ("warning", yes_no(word.warning())),
+                ("target reached", yes_no(word.target_reached())),
+                ("internal limit", yes_no(word.internal_limit())),
+            ]
+            .into_iter()
+            .map(|(name, value)| (name.to_owned(), value))
+            .collect(),
+        }
+    }
+
+    fn columns(&self) -> &'static [&'static str] {
+        &[
+            "state",
+            "statusword",
+            "voltage enabled",
+            "warning",
+            "target reached",
+            "internal limit",
+        ]
+    }
+
+    fn stats_contribution(&self, msg: &MessageCached) -> Option<String> {
+        statusword(&msg.msg.msg).map(|word| word.state().to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{declare, learn, statusword, DriveState, Statusword};
+    use crate::{interpreter::InterpreterSettings, message_cached::MessageCached};
+    use oze_canopen::canopen::RxMessage;
+    use tokio::time::Instant;
+
+    fn frame(cob_id: u16, data: [u8; 8]) -> RxMessage {
+        RxMessage {
+            timestamp: Instant::now(),
+            cob_id,
+            data,
+            dlc: 8,
+        }
+    }
+
+    #[test]
+    fn test_statusword() {
+        assert_eq!(Statusword(0x0250).state(), DriveState::SwitchOnDisabled);
+        assert_eq!(Statusword(0x0231).state(), DriveState::ReadyToSwitchOn);
+        assert_eq!(Statusword(0x0233).state(), DriveState::SwitchedOn);
+        assert_eq!(Statusword(0x0637).state(), DriveState::OperationEnabled);
+        assert_eq!(Statusword(0x0217).state(), DriveState::QuickStopActive);
+        assert_eq!(Statusword(0x021F).state(), DriveState::FaultReactionActive);
+        assert_eq!(Statusword(0x0218).state(), DriveState::Fault);
+        assert_eq!(Statusword(0x0000).state(), DriveState::NotReadyToSwitchOn);
+        assert_eq!(
+            Statusword(0x0C37).to_string(),
+            "Statusword 0x0C37 Operation enabled (voltage enabled, target reached, internal limit)"
+        );
+
+        let upload = frame(0x583, [0x4B, 0x41, 0x60, 0x00, 0x37, 0x06, 0, 0]);
+        assert_eq!(statusword(&upload), Some(Statusword(0x0637)));
+        // The upload keeps its SDO decode with the drive states turned off.
+        let cached = MessageCached::new(0, upload);
+        assert_eq!(cached.interpreter, Some("CiA 402"));
+        let mut settings = InterpreterSettings::default();
+        settings.disabled.insert("CiA 402".to_owned());
+        assert_eq!(settings.view(&cached).map(|(name, _)| name), Some("SDO"));
+        let info = settings.info(&cached);
+        assert!(!info.is_empty() && !info.contains("Statusword"), "{info}");
+        let other = frame(0x583, [0x4B, 0x40, 0x60, 0x00, 0x37, 0x06, 0, 0]);
+        assert_eq!(statusword(&other), None);
+
+        // TPDO2 of node 0x33 remapped to a u16 then the statusword.
+        let pdo = frame(0x2B3, [0xAA, 0xBB, 0x08, 0x02, 0, 0, 0, 0]);
+        assert_eq!(statusword(&pdo), None);
+        for data in [
+            [0x2F, 0x01, 0x1A, 0x00, 0x00, 0, 0, 0],
+            [0x23, 0x01, 0x1A, 0x01, 0x10, 0x00, 0x00, 0x20],
+            [0x23, 0x01, 0x1A, 0x02, 0x10, 0x00, 0x41, 0x60],
+            [0x2F, 0x01, 0x1A, 0x00, 0x02, 0, 0, 0],
+        ] {
+            learn(&frame(0x633, data));
+        }
+        assert_eq!(statusword(&pdo), Some(Statusword(0x0208)));
+
+        declare(0x1F0, 6);
+        let declared = frame(0x1F0, [0, 0, 0, 0, 0, 0, 0x27, 0x00]);
+        assert_eq!(
+            statusword(&declared).map(Statusword::state),
+            Some(DriveState::OperationEnabled)
+        );
+    }
+}
@@ -0,0 +1,209 @@
+use crate::config;
+use egui::DragValue;
+use serde::{Deserialize, Serialize};
+
+/// File of the column settings in the config directory.
+const CONFIG_FILE: &str = "columns.bin";
+
+/// Column of the message tables.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Column {
+    Index,
+    Timestamp,
+    CobId,
+    Dlc,
+    /// Gateway side the message was received on and TX for the frames sent by the viewer.
+    Direction,
+    Data,
+    Type,
+    NodeId,
+    Info,
+}
+
+impl Column {
+    pub const ALL: [Self; 9] = [
+        Self::Index,
+        Self::Timestamp,
+        Self::CobId,
+        Self::Dlc,
+        Self::Direction,
+        Self::Data,
+        Self::Type,
+        Self::NodeId,
+        Self::Info,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Index => "Index",
+            Self::Timestamp => "Timestamp",
+            Self::CobId => "COB ID",
+            Self::Dlc => "DLC",
+            Self::Direction => "Direction",
+            Self::Data => "Data",
+            Self::Type => "Packet type",
+            Self::NodeId => "Node ID",
+            Self::Info => "Info",
+        }
+    }
+
+    /// Hidden by default to keep the rows as they always were.
+    fn default_visible(self) -> bool {
+        !matches!(self, Self::Index | Self::Dlc | Self::Direction)
+    }
+}
+
+/// Visibility and minimum width of a column.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ColumnSetting {
+    pub column: Column,
+    pub visible: bool,
+    /// Minimum width in points, 0 to fit the content.
+    pub width: f32,
+}
+
+/// Columns of the message tables in display order.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Columns {
+    settings: Vec<ColumnSetting>,
+}
+
+impl Default for Columns {
+    fn default() -> Self {
+        Self {
+            settings: Column::ALL
+                .into_iter()
+                .map(|column| ColumnSetting {
+                    column,
+                    visible: column.default_visible(),
+                    width: 0.0,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Columns {
+    /// Settings saved by a previous run, or the default columns.
+    pub fn load() -> Self {
+        config::load::<Self>(CONFIG_FILE).map_or_else(Self::default, Self::normalized)
+    }
+
+    /// Drops unknown or repeated columns and appends the missing ones.
+    fn normalized(mut self) -> Self {
+        let mut seen = Vec::new();
+        self.settings.retain(|s| {
+            let first = !seen.contains(&s.column);
+            seen.push(s.column);
+            first
+        });
+        for column in Column::ALL {
+            if !seen.contains(&column) {
+                self.settings.push(ColumnSetting {
+                    column,
+                    visible: column.default_visible(),
+                    width: 0.0,
+                });
+            }
+        }
+        self
+    }
+
+    /// Visible columns in display order.
+    pub fn visible(&self) -> impl Iterator<Item = &ColumnSetting> {
+        self.settings.iter().filter(|s| s.visible)
+    }
+
+    pub fn is_visible(&self, column: Column) -> bool {
+        self.visible().any(|s| s.column == column)
+    }
+
+    /// Column chooser, returns true if the columns changed.
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> bool {
+        let before = self.clone();
+        ui.label("Columns");
+        let mut swap = None;
+        let last = self.settings.len() - 1;
+        egui::Grid::new("columns").show(ui, |ui| {
+            for (i, setting) in self.settings.iter_mut().enumerate() {
+                ui.checkbox(&mut setting.visible, setting.column.name());
+                if ui.add_enabled(i > 0, egui::Button::new("⏶")).clicked() {
+                    swap = Some(i - 1);
+                }
+                if ui.add_enabled(i < last, egui::Button::new("⏷")).clicked() {
+                    swap = Some(i);
+                }
+                ui.add(
+                    DragValue::new(&mut setting.width)
+                        .range(0.0..=600.0)
+                        .suffix(" px"),
+                )
+                .on_hover_text("Minimum width, 0 fits the content");
+                ui.end_row();
+            }
+        });
+        if let Some(i) = swap {
+            self.settings.swap(i, i + 1);
+        }
+        if ui.button("Reset columns").clicked() {
+            *self = Self::default();
+        }
+
+        let changed = *self != before;
+        if changed {
+            self.save();
+        }
+        changed
+    }
+
+    pub fn save(&self) {
+        if let Err(e) = config::save(CONFIG_FILE, self) {
+            log::warn!("Can't save the columns: {e}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Column, ColumnSetting, Columns};
+
+    #[test]
+    fn test_normalized() {
+        let saved = Columns {
+            settings: vec![
+                ColumnSetting {
+                    column: Column::Info,
+                    visible: true,
+                    width: 100.0,
+                },
+                ColumnSetting {
+                    column: Column::CobId,
+                    visible: false,
+                    width: 0.0,
+                },
+                ColumnSetting {
+                    column: Column::Info,
+                    visible: false,
+                    width: 0.0,
+                },
+            ],
+        };
+        let bytes = bincode::serialize(&saved).unwrap();
+        let columns = bincode::deserialize::<Columns>(&bytes)
+            .unwrap()
+            .normalized();
+        assert_eq!(columns.settings.len(), Column::ALL.len());
+        assert_eq!(columns.settings[0].width.to_bits(), 100.0f32.to_bits());
+        assert!(!columns.is_visible(Column::CobId));
+        assert_eq!(
+            columns.visible().map(|s| s.column).collect::<Vec<_>>(),
+            [
+                Column::Info,
+                Column::Timestamp,
+                Column::Data,
+                Column::Type,
+                Column::NodeId
+            ]
+        );
+    }
+}
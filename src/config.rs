@@ -0,0 +1,49 @@
+use serde::{de::DeserializeOwned, Serialize};
+use std::path::PathBuf;
+
+/// Directory of the settings kept between runs, `$XDG_CONFIG_HOME/oze-canopen-viewer` or
+/// `~/.config/oze-canopen-viewer`.
+pub fn dir() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(base.join("oze-canopen-viewer"))
+}
+
+/// Reads the settings saved under `name`, `None` if there are none or they can't be read.
+pub fn load<T: DeserializeOwned>(name: &str) -> Option<T> {
+    let bytes = std::fs::read(dir()?.join(name)).ok()?;
+    bincode::deserialize(&bytes).ok()
+}
+
+/// Saves settings under `name`.
+///
+/// # Errors
+/// If there is no config directory or it can't be written.
+pub fn save<T: Serialize>(name: &str, value: &T) -> Result<(), String> {
+    let bytes = bincode::serialize(value).map_err(|e| e.to_string())?;
+    write(name, &bytes)
+}
+
+/// Reads the JSON settings saved under `name`, `None` if there are none or they can't be read.
+pub fn load_json<T: DeserializeOwned>(name: &str) -> Option<T> {
+    let bytes = std::fs::read(dir()?.join(name)).ok()?;
+    serde_json::from_slice(&bytes).ok()
+}
+
+/// Saves settings under `name` as JSON, readable by the versions with other fields.
+///
+/// # Errors
+/// If there is no config directory or it can't be written.
+pub fn save_json<T: Serialize>(name: &str, value: &T) -> Result<(), String> {
+    let bytes = serde_json::to_vec_pretty(value).map_err(|e| e.to_string())?;
+    write(name, &bytes)
+}
+
+fn write(name: &str, bytes: &[u8]) -> Result<(), String> {
+    let dir = dir().ok_or("no config directory")?;
+    std::fs::create_dir_all(&dir).map_err(|e| format!("{}: {e}", dir.display()))?;
+    let path = dir.join(name);
+    std::fs::write(&path, bytes).map_err(|e| format!("{}: {e}", path.display()))
+}
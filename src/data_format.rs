@@ -0,0 +1,253 @@
+use crate::message_cached::MessageCached;
+use oze_canopen::canopen::RxMessageToStringFormat;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+/// Serialization of [`RxMessageToStringFormat`], which has none.
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "RxMessageToStringFormat")]
+enum ByteFormat {
+    Binary,
+    Hex,
+    Ascii,
+    Utf8,
+}
+
+/// Byte order of the typed values.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Endian {
+    /// Least significant byte first, the byte order of `CANopen`.
+    #[default]
+    Little,
+    Big,
+}
+
+impl Endian {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Little => "LE",
+            Self::Big => "BE",
+        }
+    }
+
+    /// Puts `bytes` in little endian order.
+    fn le<const N: usize>(self, mut bytes: [u8; N]) -> [u8; N] {
+        if self == Self::Big {
+            bytes.reverse();
+        }
+        bytes
+    }
+}
+
+/// Type of the value read from the first bytes of the payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ValueType {
+    U16,
+    I16,
+    U32,
+    I32,
+    F32,
+}
+
+impl ValueType {
+    pub const ALL: [Self; 5] = [Self::U16, Self::I16, Self::U32, Self::I32, Self::F32];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::U16 => "u16",
+            Self::I16 => "i16",
+            Self::U32 => "u32",
+            Self::I32 => "i32",
+            Self::F32 => "f32",
+        }
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Self::U16 | Self::I16 => 2,
+            Self::U32 | Self::I32 | Self::F32 => 4,
+        }
+    }
+
+    /// Formats the value of `bytes`, exactly `size()` long.
+    fn format(self, bytes: &[u8], endian: Endian) -> String {
+        let b = |i: usize| bytes[i];
+        match self {
+            Self::U16 => u16::from_le_bytes(endian.le([b(0), b(1)])).to_string(),
+            Self::I16 => i16::from_le_bytes(endian.le([b(0), b(1)])).to_string(),
+            Self::U32 => u32::from_le_bytes(endian.le([b(0), b(1), b(2), b(3)])).to_string(),
+            Self::I32 => i32::from_le_bytes(endian.le([b(0), b(1), b(2), b(3)])).to_string(),
+            Self::F32 => f32::from_le_bytes(endian.le([b(0), b(1), b(2), b(3)])).to_string(),
+        }
+    }
+}
+
+/// How the data column shows the payload of the messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DataFormat {
+    /// Every byte in hex, binary or ASCII.
+    Bytes(#[serde(with = "ByteFormat")] RxMessageToStringFormat),
+    /// The first bytes as one value, the remaining ones in hex.
+    Value(ValueType, Endian),
+    /// Every pair of bytes as a u16, an odd last byte in hex.
+    U16List(Endian),
+}
+
+impl Default for DataFormat {
+    fn default() -> Self {
+        Self::Bytes(RxMessageToStringFormat::Hex)
+    }
+}
+
+impl DataFormat {
+    /// Payload of a message in this format.
+    pub fn format(self, d: &MessageCached) -> Cow<'_, str> {
+        match self {
+            Self::Bytes(format) => Cow::Borrowed(d.get_by_format(format)),
+            _ => Cow::Owned(self.interpret(&d.msg.msg.data[..d.msg.msg.dlc.min(8)])),
+        }
+    }
+
+    /// Typed values of `data` followed by the bytes left over in hex, after a `|` if there are
+    /// values. Only the hex of the bytes for the byte formats.
+    fn interpret(self, data: &[u8]) -> String {
+        let (values, rest) = match self {
+            Self::Value(value_type, endian) if data.len() >= value_type.size() => {
+                let (value, rest) = data.split_at(value_type.size());
+                (vec![value_type.format(value, endian)], rest)
+            }
+            Self::Bytes(_) | Self::Value(..) => (Vec::new(), data),
+            Self::U16List(endian) => {
+                let words = data.chunks_exact(2);
+                let rest = words.remainder();
+                let values = words
+                    .map(|word| ValueType::U16.format(word, endian))
+                    .collect();
+                (values, rest)
+            }
+        };
+        let rest = rest
+            .iter()
+            .map(|byte| format!("{byte:02X}"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        match (values.is_empty(), rest.is_empty()) {
+            (true, _) => rest,
+            (false, true) => values.join(" "),
+            (false, false) => format!("{} | {rest}", values.join(" ")),
+        }
+    }
+
+    /// Byte format if every byte is shown on its own.
+    pub fn bytes(self) -> Option<RxMessageToStringFormat> {
+        match self {
+            Self::Bytes(format) => Some(format),
+            _ => None,
+        }
+    }
+
+    /// Title of the data column, padded to the width of the values.
+    pub fn header(self) -> String {
+        match self {
+            Self::Bytes(RxMessageToStringFormat::Binary) => {
+                " Binary data                                                            "
+                    .to_owned()
+                //00000000 00000000 00000000 00000000 00000000 00000000 00000000 00000000
+            }
+            Self::Bytes(RxMessageToStringFormat::Hex) => {
+                " Hex data               ".to_owned()
+                //00 00 00 00 00 00 00 00
+            }
+            Self::Bytes(RxMessageToStringFormat::Ascii) => "ASCII data".to_owned(),
+            Self::Bytes(RxMessageToStringFormat::Utf8) => "UTF8 data".to_owned(),
+            Self::Value(value_type, endian) => {
+                format!(" {} {} data", value_type.as_str(), endian.as_str())
+            }
+            Self::U16List(endian) => format!(" u16 list {} data", endian.as_str()),
+        }
+    }
+
+    fn endian(self) -> Option<Endian> {
+        match self {
+            Self::Bytes(_) => None,
+            Self::Value(_, endian) | Self::U16List(endian) => Some(endian),
+        }
+    }
+
+    /// Selector of the typed formats, next to the byte formats of the top bar.
+    pub fn typed_ui(&mut self, ui: &mut egui::Ui) {
+        let endian = self.endian().unwrap_or_default();
+        let selected = match *self {
+            Self::Bytes(_) => "typed",
+            Self::Value(value_type, _) => value_type.as_str(),
+            Self::U16List(_) => "u16 list",
+        };
+        egui::ComboBox::from_id_salt("typed_format")
+            .selected_text(selected)
+            .width(70.0)
+            .show_ui(ui, |ui| {
+                for value_type in ValueType::ALL {
+                    ui.selectable_value(self, Self::Value(value_type, endian), value_type.as_str())
+                        .on_hover_text(format!(
+                            "Show the first {} bytes as {}, the others in hex",
+                            value_type.size(),
+                            value_type.as_str()
+                        ));
+                }
+                ui.selectable_value(self, Self::U16List(endian), "u16 list")
+                    .on_hover_text("Show every pair of bytes as u16, an odd last byte in hex");
+            })
+            .response
+            .on_hover_text("Interpret the data as typed values");
+
+        if let Some(endian) = self.endian() {
+            let other = match endian {
+                Endian::Little => Endian::Big,
+                Endian::Big => Endian::Little,
+            };
+            if ui
+                .button(endian.as_str())
+                .on_hover_text(format!("Byte order, click for {}", other.as_str()))
+                .clicked()
+            {
+                *self = match *self {
+                    Self::Value(value_type, _) => Self::Value(value_type, other),
+                    Self::U16List(_) => Self::U16List(other),
+                    bytes @ Self::Bytes(_) => bytes,
+                };
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DataFormat, Endian, ValueType};
+
+    #[test]
+    fn test_interpret() {
+        let data = [0xE8, 0x03, 0xFF, 0xFF, 0x05];
+        let value = |value_type, endian| DataFormat::Value(value_type, endian).interpret(&data);
+        assert_eq!(value(ValueType::U16, Endian::Little), "1000 | FF FF 05");
+        assert_eq!(value(ValueType::U16, Endian::Big), "59395 | FF FF 05");
+        assert_eq!(value(ValueType::I32, Endian::Little), "-64536 | 05");
+        assert_eq!(
+            DataFormat::Value(ValueType::F32, Endian::Big).interpret(&[0x3F, 0xC0, 0, 0]),
+            "1.5"
+        );
+        assert_eq!(
+            DataFormat::Value(ValueType::I16, Endian::Little).interpret(&[0xFF, 0xFF]),
+            "-1"
+        );
+        // Too short for the value, only the bytes.
+        assert_eq!(
+            DataFormat::Value(ValueType::U32, Endian::Little).interpret(&[1, 2, 3]),
+            "01 02 03"
+        );
+
+        let list = DataFormat::U16List(Endian::Little);
+        assert_eq!(list.interpret(&data), "1000 65535 | 05");
+        assert_eq!(list.interpret(&data[..4]), "1000 65535");
+        assert_eq!(list.interpret(&[]), "");
+    }
+}
@@ -0,0 +1,658 @@
+use core::fmt;
+use oze_canopen::canopen::RxMessage;
+use regex::Regex;
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, PoisonError, RwLock},
+};
+
+/// Keywords of the DBC format that don't change how the frames are decoded.
+const IGNORED_KEYWORDS: [&str; 24] = [
+    "VERSION",
+    "NS_",
+    "BS_",
+    "BU_",
+    "CM_",
+    "BA_DEF_",
+    "BA_DEF_REL_",
+    "BA_",
+    "BA_REL_",
+    "BA_DEF_DEF_",
+    "BA_DEF_DEF_REL_",
+    "BA_DEF_SGTYPE_",
+    "BA_SGTYPE_",
+    "VAL_TABLE_",
+    "BO_TX_BU_",
+    "BU_SG_REL_",
+    "BU_EV_REL_",
+    "BU_BO_REL_",
+    "EV_",
+    "ENVVAR_DATA_",
+    "SIG_GROUP_",
+    "SGTYPE_",
+    "SIG_TYPE_REF_",
+    "CAT_DEF_",
+];
+
+/// DBC loaded for decoding, see `set`.
+static LOADED: RwLock<Option<Arc<Dbc>>> = RwLock::new(None);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ByteOrder {
+    /// Intel, `@1` in the DBC, the start bit is the least significant one.
+    LittleEndian,
+    /// Motorola, `@0` in the DBC, the start bit is the most significant one.
+    BigEndian,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ValueKind {
+    Integer { signed: bool },
+    Float,
+    Double,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Multiplex {
+    None,
+    /// Selects which multiplexed signals are present.
+    Multiplexor,
+    /// Present when the multiplexor has this value.
+    Multiplexed(u64),
+}
+
+#[derive(Debug, Clone)]
+struct SignalDef {
+    name: String,
+    start: u16,
+    len: u16,
+    order: ByteOrder,
+    kind: ValueKind,
+    factor: f64,
+    offset: f64,
+    /// Decimals of the factor and offset, the precision the values are shown with.
+    decimals: usize,
+    unit: String,
+    multiplex: Multiplex,
+    /// Value descriptions from `VAL_`, by raw value.
+    values: BTreeMap<i64, String>,
+}
+
+impl SignalDef {
+    /// Raw bits of the signal, `None` if the frame is too short.
+    fn raw(&self, data: &[u8]) -> Option<u64> {
+        let start = usize::from(self.start);
+        let len = usize::from(self.len);
+        match self.order {
+            ByteOrder::LittleEndian => {
+                if start + len > data.len() * 8 {
+                    return None;
+                }
+                let mut bytes = [0; 8];
+                bytes[..data.len()].copy_from_slice(data);
+                let mask = if len == 64 { u64::MAX } else { (1 << len) - 1 };
+                Some((u64::from_le_bytes(bytes) >> start) & mask)
+            }
+            ByteOrder::BigEndian => {
+                // Bits are numbered from the LSB of every byte, a big endian signal goes down
+                // from its start bit then continues at the MSB of the next byte.
+                let mut bit = start;
+                let mut raw = 0;
+                for _ in 0..len {
+                    let byte = data.get(bit / 8)?;
+                    raw = (raw << 1) | u64::from((byte >> (bit % 8)) & 1);
+                    bit = if bit % 8 == 0 { bit + 15 } else { bit - 1 };
+                }
+                Some(raw)
+            }
+        }
+    }
+
+    /// Raw value as an integer, sign extended if the signal is signed.
+    #[allow(clippy::cast_possible_wrap)]
+    fn raw_integer(&self, raw: u64) -> i64 {
+        match self.kind {
+            ValueKind::Integer { signed: true } if self.len < 64 => {
+                let shift = 64 - u32::from(self.len);
+                ((raw << shift) as i64) >> shift
+            }
+            _ => raw as i64,
+        }
+    }
+
+    #[allow(clippy::cast_precision_loss, clippy::cast_possible_truncation)]
+    fn decode(&self, data: &[u8]) -> Option<SignalValue> {
+        let raw = self.raw(data)?;
+        let value = match self.kind {
+            ValueKind::Integer { signed: false } => raw as f64,
+            ValueKind::Integer { signed: true } => self.raw_integer(raw) as f64,
+            ValueKind::Float => f64::from(f32::from_bits(raw as u32)),
+            ValueKind::Double => f64::from_bits(raw),
+        };
+        Some(SignalValue {
+            name: self.name.clone(),
+            raw,
+            value: value * self.factor + self.offset,
+            unit: self.unit.clone(),
+            text: self.values.get(&self.raw_integer(raw)).cloned(),
+            decimals: match self.kind {
+                ValueKind::Integer { .. } => Some(self.decimals),
+                ValueKind::Float | ValueKind::Double => None,
+            },
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+struct MessageDef {
+    name: String,
+    signals: Vec<SignalDef>,
+}
+
+/// Value of a signal decoded from a frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignalValue {
+    pub name: String,
+    pub raw: u64,
+    /// Physical value, after scaling and offset.
+    pub value: f64,
+    pub unit: String,
+    /// Description of the raw value from the DBC, if any.
+    pub text: Option<String>,
+    /// Decimals to show, `None` for floating point signals.
+    decimals: Option<usize>,
+}
+
+impl SignalValue {
+    /// Value with its unit, or its description from the DBC.
+    pub fn value_text(&self) -> String {
+        if let Some(text) = &self.text {
+            return text.clone();
+        }
+        let value = match self.decimals {
+            Some(decimals) => format!("{:.decimals$}", self.value),
+            None => self.value.to_string(),
+        };
+        if self.unit.is_empty() {
+            value
+        } else {
+            format!("{value} {}", self.unit)
+        }
+    }
+}
+
+impl fmt::Display for SignalValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} = {}", self.name, self.value_text())
+    }
+}
+
+/// Signals of a frame matching a message of the DBC.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Decoded {
+    pub message: String,
+    pub signals: Vec<SignalValue>,
+}
+
+impl fmt::Display for Decoded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:", self.message)?;
+        for (i, signal) in self.signals.iter().enumerate() {
+            let separator = if i == 0 { " " } else { ", " };
+            write!(f, "{separator}{signal}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Messages and signals of a DBC file.
+#[derive(Debug, Clone, Default)]
+pub struct Dbc {
+    /// File the DBC was read from.
+    pub source: String,
+    messages: BTreeMap<u16, MessageDef>,
+    /// Constructs that were skipped, one per line.
+    pub warnings: Vec<String>,
+}
+
+impl Dbc {
+    pub fn message_count(&self) -> usize {
+        self.messages.len()
+    }
+
+    pub fn signal_count(&self) -> usize {
+        self.messages.values().map(|m| m.signals.len()).sum()
+    }
+
+    /// Decodes the signals of a frame whose COB-ID is a message of the DBC. Multiplexed
+    /// signals are only decoded when the multiplexor selects them.
+    pub fn decode(&self, msg: &RxMessage) -> Option<Decoded> {
+        let def = self.messages.get(&msg.cob_id)?;
+        let data = &msg.data[..msg.dlc.min(8)];
+        let selector = def
+            .signals
+            .iter()
+            .find(|s| s.multiplex == Multiplex::Multiplexor)
+            .and_then(|s| s.raw(data));
+        let signals = def
+            .signals
+            .iter()
+            .filter(|s| match s.multiplex {
+                Multiplex::Multiplexed(value) => selector == Some(value),
+                Multiplex::None | Multiplex::Multiplexor => true,
+            })
+            .filter_map(|s| s.decode(data))
+            .collect();
+        Some(Decoded {
+            message: def.name.clone(),
+            signals,
+        })
+    }
+
+    /// Parses a DBC file. Lines that can't be parsed or that use constructs this viewer doesn't
+    /// support are skipped and listed in `warnings`.
+    pub fn parse(text: &str) -> Self {
+        Parser::new().parse(text)
+    }
+}
+
+/// Reads and parses a DBC file.
+///
+/// # Errors
+/// If the file can't be read or has no message.
+pub fn load(path: &str) -> Result<Dbc, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+    let mut dbc = Dbc::parse(&text);
+    if dbc.messages.is_empty() {
+        return Err(format!("{path}: no message definition (BO_) found"));
+    }
+    path.clone_into(&mut dbc.source);
+    Ok(dbc)
+}
+
+/// Sets the DBC used to decode the frames, `None` to stop decoding them.
+pub fn set(dbc: Option<Dbc>) {
+    *LOADED.write().unwrap_or_else(PoisonError::into_inner) = dbc.map(Arc::new);
+}
+
+/// DBC used to decode the frames.
+pub fn loaded() -> Option<Arc<Dbc>> {
+    LOADED
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+}
+
+/// Decodes a frame with the loaded DBC.
+pub fn decode(msg: &RxMessage) -> Option<Decoded> {
+    LOADED
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .as_ref()?
+        .decode(msg)
+}
+
+/// Number of decimals written in a number, e.g. 2 for `0.25`.
+fn decimals(number: &str) -> usize {
+    if number.contains(['e', 'E']) {
+        return 6;
+    }
+    number
+        .split_once('.')
+        .map_or(0, |(_, fraction)| fraction.trim_end_matches('0').len())
+}
+
+struct Parser {
+    message: Regex,
+    signal: Regex,
+    values: Regex,
+    value: Regex,
+    value_type: Regex,
+    dbc: Dbc,
+    /// Message the following signals belong to, `None` if it was skipped.
+    current: Option<u16>,
+}
+
+impl Parser {
+    fn new() -> Self {
+        Self {
+            message: Regex::new(r"^BO_\s+(\d+)\s+(\w+)\s*:\s*(\d+)\s+(\w+)").unwrap(),
+            signal: Regex::new(
+                r#"^SG_\s+(\w+)(?:\s+(M|m\d+M?))?\s*:\s*(\d+)\|(\d+)@([01])([+-])\s*\(([^,]+),([^)]+)\)\s*\[[^\]]*\]\s*"([^"]*)""#,
+            )
+            .unwrap(),
+            values: Regex::new(r"^VAL_\s+(\d+)\s+(\w+)\s+(.*);$").unwrap(),
+            value: Regex::new(r#"(-?\d+)\s+"([^"]*)""#).unwrap(),
+            value_type: Regex::new(r"^SIG_VALTYPE_\s+(\d+)\s+(\w+)\s*:?\s*([0-3])\s*;$").unwrap(),
+            dbc: Dbc::default(),
+            current: None,
+        }
+    }
+
+    fn parse(mut self, text: &str) -> Dbc {
+        // A statement ends with its line unless a string goes on over the next lines.
+        let mut statement = String::new();
+        let mut first_line = 0;
+        for (number, line) in text.lines().enumerate() {
+            if statement.is_empty() {
+                first_line = number + 1;
+            } else {
+                statement.push('\n');
+            }
+            statement.push_str(line);
+            if statement.matches('"').count() % 2 == 0 {
+                self.statement(first_line, statement.trim());
+                statement.clear();
+            }
+        }
+        if !statement.trim().is_empty() {
+            self.warn(first_line, "unterminated string");
+        }
+
+        for message in self.dbc.messages.values() {
+            let multiplexors = message
+                .signals
+                .iter()
+                .filter(|s| s.multiplex == Multiplex::Multiplexor)
+                .count();
+            let multiplexed = message
+                .signals
+                .iter()
+                .any(|s| matches!(s.multiplex, Multiplex::Multiplexed(_)));
+            if multiplexed && multiplexors != 1 {
+                self.dbc.warnings.push(format!(
+                    "{}: multiplexed signals need exactly one multiplexor, they are not decoded",
+                    message.name
+                ));
+            }
+        }
+        self.dbc
+    }
+
+    fn warn(&mut self, line: usize, text: &str) {
+        self.dbc.warnings.push(format!("line {line}: {text}"));
+    }
+
+    fn statement(&mut self, line: usize, statement: &str) {
+        let keyword = statement.split_whitespace().next().unwrap_or_default();
+        // Lone keywords are the list of the `NS_` section.
+        if keyword.is_empty() || keyword == statement || keyword.starts_with("//") {
+            return;
+        }
+        match keyword {
+            "BO_" => self.message(line, statement),
+            "SG_" => self.signal(line, statement),
+            "VAL_" => self.values(line, statement),
+            "SIG_VALTYPE_" => self.value_type(line, statement),
+            "SG_MUL_VAL_" => {
+                self.warn(line, "extended multiplexing (SG_MUL_VAL_) is not supported");
+            }
+            keyword if IGNORED_KEYWORDS.contains(&keyword.trim_end_matches(':')) => {}
+            keyword if keyword.chars().all(|c| c.is_ascii_uppercase() || c == '_') => {
+                self.warn(line, &format!("{keyword} is not supported"));
+            }
+            _ => self.warn(line, "can't parse the line"),
+        }
+    }
+
+    fn message(&mut self, line: usize, statement: &str) {
+        self.current = None;
+        let Some(caps) = self.message.captures(statement) else {
+            self.warn(line, "can't parse the message definition");
+            return;
+        };
+        let name = caps[2].to_owned();
+        let id = caps[1]
+            .parse::<u32>()
+            .ok()
+            .and_then(|id| u16::try_from(id).ok());
+        match id {
+            Some(id) if id <= 0x7FF => {
+                self.current = Some(id);
+                self.dbc.messages.insert(
+                    id,
+                    MessageDef {
+                        name,
+                        signals: Vec::new(),
+                    },
+                );
+            }
+            // The "VECTOR__INDEPENDENT_SIG_MSG" pseudo message holds unused signals.
+            _ if name == "VECTOR__INDEPENDENT_SIG_MSG" => {}
+            _ => self.warn(
+                line,
+                &format!("{name}: 29-bit identifiers are not supported, message skipped"),
+            ),
+        }
+    }
+
+    fn signal(&mut self, line: usize, statement: &str) {
+        let Some(caps) = self.signal.captures(statement) else {
+            self.warn(line, "can't parse the signal definition");
+            return;
+        };
+        let name = caps[1].to_owned();
+        let multiplex = match caps.get(2).map(|m| m.as_str()) {
+            None => Multiplex::None,
+            Some("M") => Multiplex::Multiplexor,
+            Some(m) if m.ends_with('M') => {
+                self.warn(
+                    line,
+                    &format!("{name}: nested multiplexing is not supported, signal skipped"),
+                );
+                return;
+            }
+            Some(m) => {
+                let Ok(value) = m[1..].parse() else {
+                    self.warn(line, &format!("{name}: invalid multiplexer value {m}"));
+                    return;
+                };
+                Multiplex::Multiplexed(value)
+            }
+        };
+        let (Ok(start), Ok(len), Ok(factor), Ok(offset)) = (
+            caps[3].parse::<u16>(),
+            caps[4].parse::<u16>(),
+            caps[7].trim().parse::<f64>(),
+            caps[8].trim().parse::<f64>(),
+        ) else {
+            self.warn(line, &format!("{name}: invalid number in the definition"));
+            return;
+        };
+        let order = if &caps[5] == "1" {
+            ByteOrder::LittleEndian
+        } else {
+            ByteOrder::BigEndian
+        };
+        // Big endian signals go down from the start bit, they can't be checked this simply.
+        let too_long = order == ByteOrder::LittleEndian && start + len > 64;
+        if len == 0 || len > 64 || start > 63 || too_long {
+            self.warn(
+                line,
+                &format!("{name}: bits {start}|{len} don't fit in 8 bytes, signal skipped"),
+            );
+            return;
+        }
+
+        let Some(message) = self.current.and_then(|id| self.dbc.messages.get_mut(&id)) else {
+            // Signals of skipped messages were reported with them.
+            return;
+        };
+        message.signals.push(SignalDef {
+            name,
+            start,
+            len,
+            order,
+            kind: ValueKind::Integer {
+                signed: &caps[6] == "-",
+            },
+            factor,
+            offset,
+            decimals: decimals(caps[7].trim()).max(decimals(caps[8].trim())),
+            unit: caps[9].to_owned(),
+            multiplex,
+            values: BTreeMap::new(),
+        });
+    }
+
+    fn find_signal(&mut self, id: &str, name: &str) -> Option<&mut SignalDef> {
+        let id = id.parse::<u16>().ok()?;
+        self.dbc
+            .messages
+            .get_mut(&id)?
+            .signals
+            .iter_mut()
+            .find(|s| s.name == name)
+    }
+
+    fn values(&mut self, line: usize, statement: &str) {
+        let Some(caps) = self.values.captures(statement) else {
+            self.warn(line, "can't parse the value descriptions");
+            return;
+        };
+        let values: BTreeMap<i64, String> = self
+            .value
+            .captures_iter(&caps[3])
+            .filter_map(|value| Some((value[1].parse().ok()?, value[2].to_owned())))
+            .collect();
+        // Descriptions of skipped messages or signals are dropped with them.
+        if let Some(signal) = self.find_signal(&caps[1], &caps[2]) {
+            signal.values = values;
+        }
+    }
+
+    fn value_type(&mut self, line: usize, statement: &str) {
+        let Some(caps) = self.value_type.captures(statement) else {
+            self.warn(line, "can't parse the signal value type");
+            return;
+        };
+        let kind = &caps[3];
+        let name = caps[2].to_owned();
+        let Some(signal) = self.find_signal(&caps[1], &name) else {
+            return;
+        };
+        let (kind, len) = match kind {
+            "1" => (ValueKind::Float, 32),
+            "2" => (ValueKind::Double, 64),
+            _ => return,
+        };
+        if signal.len == len {
+            signal.kind = kind;
+        } else {
+            self.warn(
+                line,
+                &format!("{name}: a floating point signal must be {len} bits long"),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Dbc;
+    use oze_canopen::canopen::RxMessage;
+    use tokio::time::Instant;
+
+    const DBC: &str = r#"VERSION ""
+
+NS_ :
+    CM_
+    SG_MUL_VAL_
+
+BS_:
+
+BU_: Drive PLC
+
+BO_ 385 TPDO1_Drive: 8 Drive
+ SG_ StatusWord : 0|16@1+ (1,0) [0|65535] "" PLC
+ SG_ ActualVelocity : 16|32@1- (1,0) [-100000|100000] "rpm" PLC
+ SG_ Mode : 48|8@1- (1,0) [-128|127] "" PLC
+
+BO_ 641 TPDO2_Drive: 8 Drive
+ SG_ Temperature : 7|16@0+ (0.1,-40) [-40|200] "degC" PLC
+ SG_ Page M : 16|8@1+ (1,0) [0|255] "" PLC
+ SG_ Voltage m0 : 24|8@1+ (0.5,0) [0|127] "V" PLC
+ SG_ Current m1 : 24|8@1- (0.1,0) [-12.8|12.7] "A" PLC
+ SG_ Torque : 32|32@1- (1,0) [0|0] "Nm" PLC
+
+BO_ 2147484000 Extended: 8 Drive
+ SG_ Ignored : 0|8@1+ (1,0) [0|255] "" PLC
+
+CM_ SG_ 385 StatusWord "CiA 402 status word,
+spanning two lines";
+VAL_ 385 Mode 1 "Profile position" 3 "Profile velocity" -1 "Vendor" ;
+SIG_VALTYPE_ 641 Torque : 1;
+SG_MUL_VAL_ 641 Voltage Page 0-0;
+"#;
+
+    fn frame(cob_id: u16, data: [u8; 8]) -> RxMessage {
+        RxMessage {
+            timestamp: Instant::now(),
+            cob_id,
+            data,
+            dlc: 8,
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        let dbc = Dbc::parse(DBC);
+        assert_eq!(dbc.message_count(), 2);
+        assert_eq!(dbc.signal_count(), 8);
+        assert_eq!(dbc.warnings.len(), 2, "{:?}", dbc.warnings);
+        assert!(dbc.warnings[0].contains("29-bit"));
+        assert!(dbc.warnings[1].contains("SG_MUL_VAL_"));
+    }
+
+    #[test]
+    fn test_decode() {
+        let dbc = Dbc::parse(DBC);
+        let velocity = (-1234i32).to_le_bytes();
+        let decoded = dbc
+            .decode(&frame(
+                0x181,
+                [
+                    0x37,
+                    0x06,
+                    velocity[0],
+                    velocity[1],
+                    velocity[2],
+                    velocity[3],
+                    0xFF,
+                    0,
+                ],
+            ))
+            .unwrap();
+        assert_eq!(
+            decoded.to_string(),
+            "TPDO1_Drive: StatusWord = 1591, ActualVelocity = -1234 rpm, Mode = Vendor"
+        );
+
+        // Temperature is big endian: 0x0384 = 900 -> 50.0 degC.
+        let mut data = [0x03, 0x84, 1, 0xF6, 0, 0, 0, 0];
+        data[4..].copy_from_slice(&2.5f32.to_le_bytes());
+        let decoded = dbc.decode(&frame(0x281, data)).unwrap();
+        let signals: Vec<String> = decoded.signals.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            signals,
+            [
+                "Temperature = 50.0 degC",
+                "Page = 1",
+                "Current = -1.0 A",
+                "Torque = 2.5 Nm"
+            ]
+        );
+
+        // Signals past the DLC are left out.
+        let mut short = frame(0x281, [0x03, 0x84, 0, 200, 0, 0, 0, 0]);
+        short.dlc = 5;
+        let decoded = dbc.decode(&short).unwrap();
+        let signals: Vec<String> = decoded.signals.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            signals,
+            ["Temperature = 50.0 degC", "Page = 0", "Voltage = 100.0 V"]
+        );
+
+        assert_eq!(dbc.decode(&frame(0x182, [0; 8])), None);
+    }
+}
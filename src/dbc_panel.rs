@@ -0,0 +1,73 @@
+use crate::{dbc, theme::OZON_PINK};
+use egui::TextEdit;
+
+/// Loads the DBC file decoding the PDOs into signals.
+#[derive(Debug, Default)]
+pub struct DbcPanel {
+    path_raw: String,
+    /// Error of the last load.
+    error: Option<String>,
+}
+
+impl DbcPanel {
+    /// Shows the panel, returns `true` if the DBC was loaded or unloaded and the messages must
+    /// be decoded again.
+    pub fn update(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut changed = false;
+        let loaded = dbc::loaded();
+        ui.horizontal(|ui| {
+            ui.add(
+                TextEdit::singleline(&mut self.path_raw)
+                    .hint_text("path/to/file.dbc")
+                    .desired_width(250.0),
+            );
+            if ui
+                .add_enabled(
+                    !self.path_raw.trim().is_empty(),
+                    egui::Button::new("Load DBC…"),
+                )
+                .on_hover_text("Decode the frames of the messages of this file into signals")
+                .clicked()
+            {
+                match dbc::load(self.path_raw.trim()) {
+                    Ok(dbc) => {
+                        dbc::set(Some(dbc));
+                        self.error = None;
+                        changed = true;
+                    }
+                    Err(e) => self.error = Some(e),
+                }
+            }
+            if loaded.is_some() && ui.button("Unload").clicked() {
+                dbc::set(None);
+                changed = true;
+            }
+        });
+
+        if let Some(error) = &self.error {
+            ui.colored_label(egui::Color32::RED, error);
+        }
+        let Some(dbc) = loaded else {
+            ui.label("No DBC loaded");
+            return changed;
+        };
+        ui.label(format!(
+            "{}: {} messages, {} signals",
+            dbc.source,
+            dbc.message_count(),
+            dbc.signal_count()
+        ));
+        if !dbc.warnings.is_empty() {
+            ui.collapsing(
+                egui::RichText::new(format!("{} constructs skipped", dbc.warnings.len()))
+                    .color(OZON_PINK),
+                |ui| {
+                    for warning in &dbc.warnings {
+                        ui.label(warning);
+                    }
+                },
+            );
+        }
+        changed
+    }
+}
@@ -0,0 +1,361 @@
+use crate::{config, theme::Status};
+use chrono::{DateTime, Local};
+use egui::{DragValue, TextEdit};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fmt};
+use tokio::time::Instant;
+
+/// File of the deadline table in the config directory.
+const CONFIG_FILE: &str = "deadlines.bin";
+
+/// Milliseconds from `earlier` to `later`.
+fn millis(earlier: Instant, later: Instant) -> f64 {
+    later.saturating_duration_since(earlier).as_secs_f64() * 1000.0
+}
+
+/// Expected period of a COB-ID, in milliseconds.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Deadline {
+    pub period_ms: f64,
+    pub tolerance_ms: f64,
+}
+
+impl Default for Deadline {
+    fn default() -> Self {
+        Self {
+            period_ms: 10.0,
+            tolerance_ms: 2.0,
+        }
+    }
+}
+
+impl Deadline {
+    /// Whole periods without a frame in a gap, the next frame may still come within the
+    /// tolerance of the last one.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    fn missing(&self, gap: f64) -> u64 {
+        (((gap + self.tolerance_ms) / self.period_ms).floor() as u64).saturating_sub(1)
+    }
+
+    fn is_late(&self, gap: f64) -> bool {
+        gap > self.period_ms + self.tolerance_ms
+    }
+
+    fn is_early(&self, gap: f64) -> bool {
+        gap < self.period_ms - self.tolerance_ms
+    }
+}
+
+/// Violations of the deadline of a COB-ID.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Violations {
+    /// Frames after the period and its tolerance.
+    pub late: u64,
+    /// Frames before the period minus its tolerance.
+    pub early: u64,
+    /// Periods without any frame.
+    pub missing: u64,
+}
+
+impl Violations {
+    pub fn total(&self) -> u64 {
+        self.late + self.early + self.missing
+    }
+}
+
+/// Arrivals of a COB-ID with a deadline.
+#[derive(Debug, Clone, Copy, Default)]
+struct Tracking {
+    last: Option<Instant>,
+    violations: Violations,
+    /// Missing periods already counted since `last`.
+    missing_counted: u64,
+    /// The deadline is violated until a frame arrives within it.
+    violated: bool,
+}
+
+/// A deadline started or stopped being violated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeadlineEvent {
+    Violated,
+    Restored,
+}
+
+#[derive(Debug, Clone)]
+pub struct DeadlineAlert {
+    pub time: DateTime<Local>,
+    pub cob_id: u16,
+    pub event: DeadlineEvent,
+    /// Gap since the previous frame, milliseconds.
+    pub gap_ms: f64,
+    pub deadline: Deadline,
+}
+
+impl fmt::Display for DeadlineAlert {
+    /// E.g. `COB-ID 181 deadline violated, 16.2 ms since the last frame (10 ± 2 ms)`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let event = match self.event {
+            DeadlineEvent::Violated => "violated",
+            DeadlineEvent::Restored => "met again",
+        };
+        write!(
+            f,
+            "COB-ID {:03X} deadline {event}, {:.1} ms since the last frame ({} ± {} ms)",
+            self.cob_id, self.gap_ms, self.deadline.period_ms, self.deadline.tolerance_ms
+        )
+    }
+}
+
+/// Deadline and violations of a COB-ID in a statistics snapshot.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeadlineSnapshot {
+    pub cob_id: String,
+    pub period_ms: f64,
+    pub tolerance_ms: f64,
+    pub violations: Violations,
+    pub violated: bool,
+}
+
+/// Expected periods of COB-IDs and their violations.
+#[derive(Debug, Clone, Default)]
+pub struct Deadlines {
+    table: BTreeMap<u16, Deadline>,
+    tracking: BTreeMap<u16, Tracking>,
+    /// Alerts raised by the arrivals since the last tick.
+    pending: Vec<DeadlineAlert>,
+    /// COB-ID typed to add a deadline.
+    cob_raw: String,
+}
+
+impl Deadlines {
+    /// Deadlines saved by a previous run.
+    pub fn load() -> Self {
+        Self {
+            table: config::load(CONFIG_FILE).unwrap_or_default(),
+            ..Self::default()
+        }
+    }
+
+    pub fn table(&self) -> &BTreeMap<u16, Deadline> {
+        &self.table
+    }
+
+    /// Replaces the deadlines, the violations of the removed ones are dropped.
+    pub fn set_table(&mut self, table: BTreeMap<u16, Deadline>) {
+        self.tracking.retain(|cob_id, _| table.contains_key(cob_id));
+        self.table = table;
+    }
+
+    pub fn violations(&self, cob_id: u16) -> Violations {
+        self.tracking
+            .get(&cob_id)
+            .map(|tracking| tracking.violations)
+            .unwrap_or_default()
+    }
+
+    /// Checks the gap of a frame against the deadline of its COB-ID.
+    pub fn on_frame(&mut self, cob_id: u16, timestamp: Instant) {
+        let Some(deadline) = self.table.get(&cob_id).copied() else {
+            return;
+        };
+        let tracking = self.tracking.entry(cob_id).or_default();
+        let Some(last) = tracking.last.replace(timestamp) else {
+            return;
+        };
+        let gap = millis(last, timestamp);
+        let missing = deadline.missing(gap);
+        tracking.violations.missing += missing.saturating_sub(tracking.missing_counted);
+        tracking.missing_counted = 0;
+        let violated = if missing > 0 {
+            true
+        } else if deadline.is_late(gap) {
+            tracking.violations.late += 1;
+            true
+        } else if deadline.is_early(gap) {
+            tracking.violations.early += 1;
+            true
+        } else {
+            false
+        };
+        if violated != tracking.violated {
+            tracking.violated = violated;
+            self.pending.push(DeadlineAlert {
+                time: Local::now(),
+                cob_id,
+                event: if violated {
+                    DeadlineEvent::Violated
+                } else {
+                    DeadlineEvent::Restored
+                },
+                gap_ms: gap,
+                deadline,
+            });
+        }
+    }
+
+    /// Counts the periods missed so far, returns the alerts raised since the last tick.
+    pub fn on_tick(&mut self, now: Instant) -> Vec<DeadlineAlert> {
+        for (cob_id, tracking) in &mut self.tracking {
+            let (Some(deadline), Some(last)) = (self.table.get(cob_id), tracking.last) else {
+                continue;
+            };
+            let gap = millis(last, now);
+            let missing = deadline.missing(gap);
+            tracking.violations.missing += missing.saturating_sub(tracking.missing_counted);
+            tracking.missing_counted = tracking.missing_counted.max(missing);
+            if !tracking.violated && deadline.is_late(gap) {
+                tracking.violated = true;
+                self.pending.push(DeadlineAlert {
+                    time: Local::now(),
+                    cob_id: *cob_id,
+                    event: DeadlineEvent::Violated,
+                    gap_ms: gap,
+                    deadline: *deadline,
+                });
+            }
+        }
+        std::mem::take(&mut self.pending)
+    }
+
+    pub fn snapshot(&self) -> Vec<DeadlineSnapshot> {
+        self.table
+            .iter()
+            .map(|(cob_id, deadline)| DeadlineSnapshot {
+                cob_id: format!("0x{cob_id:03X}"),
+                period_ms: deadline.period_ms,
+                tolerance_ms: deadline.tolerance_ms,
+                violations: self.violations(*cob_id),
+                violated: self
+                    .tracking
+                    .get(cob_id)
+                    .is_some_and(|tracking| tracking.violated),
+            })
+            .collect()
+    }
+
+    /// Table of the deadlines with their violations, saved when edited.
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        let before = self.table.clone();
+        let mut remove = None;
+        if !self.table.is_empty() {
+            egui::Grid::new("deadlines").striped(true).show(ui, |ui| {
+                ui.label("COB-ID");
+                ui.label("Period");
+                ui.label("±");
+                ui.label("Late");
+                ui.label("Early");
+                ui.label("Missing");
+                ui.end_row();
+                for (cob_id, deadline) in &mut self.table {
+                    let tracking = self.tracking.get(cob_id).copied().unwrap_or_default();
+                    let text = format!("{cob_id:03X}");
+                    if tracking.violated {
+                        ui.colored_label(Status::Error.color(), text);
+                    } else {
+                        ui.label(text);
+                    }
+                    ui.add(
+                        DragValue::new(&mut deadline.period_ms)
+                            .range(0.1..=60_000.0)
+                            .suffix(" ms"),
+                    );
+                    ui.add(
+                        DragValue::new(&mut deadline.tolerance_ms)
+                            .range(0.0..=60_000.0)
+                            .speed(0.1)
+                            .suffix(" ms"),
+                    );
+                    ui.label(tracking.violations.late.to_string());
+                    ui.label(tracking.violations.early.to_string());
+                    ui.label(tracking.violations.missing.to_string());
+                    if ui.small_button("❌").clicked() {
+                        remove = Some(*cob_id);
+                    }
+                    ui.end_row();
+                }
+            });
+        }
+        if let Some(cob_id) = remove {
+            self.table.remove(&cob_id);
+            self.tracking.remove(&cob_id);
+        }
+        ui.horizontal(|ui| {
+            ui.add(
+                TextEdit::singleline(&mut self.cob_raw)
+                    .hint_text("COB-ID")
+                    .desired_width(60.0),
+            );
+            let cob_id = u16::from_str_radix(self.cob_raw.trim(), 16)
+                .ok()
+                .filter(|cob_id| *cob_id <= 0x7FF && !self.table.contains_key(cob_id));
+            if ui
+                .add_enabled(cob_id.is_some(), egui::Button::new("➕"))
+                .on_hover_text("Expect this COB-ID periodically, e.g. 181")
+                .clicked()
+            {
+                if let Some(cob_id) = cob_id {
+                    self.table.insert(cob_id, Deadline::default());
+                    self.cob_raw.clear();
+                }
+            }
+        });
+
+        if self.table != before {
+            if let Err(e) = config::save(CONFIG_FILE, &self.table) {
+                log::warn!("Can't save the deadlines: {e}");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Deadline, DeadlineEvent, Deadlines, Violations};
+    use std::time::Duration;
+    use tokio::time::Instant;
+
+    #[test]
+    fn test_deadlines() {
+        let mut deadlines = Deadlines::default();
+        deadlines.set_table([(0x181, Deadline::default())].into_iter().collect());
+        let start = Instant::now();
+        let ms = |ms| start + Duration::from_millis(ms);
+        let events = |alerts: Vec<super::DeadlineAlert>| -> Vec<_> {
+            alerts.iter().map(|alert| alert.event).collect()
+        };
+
+        // Within 10 ± 2 ms, other COB-IDs aren't checked.
+        for time in [0, 10, 21, 30] {
+            deadlines.on_frame(0x181, ms(time));
+            deadlines.on_frame(0x182, ms(time));
+        }
+        assert!(deadlines.on_tick(ms(31)).is_empty());
+
+        // Late, then early, then back in time.
+        deadlines.on_frame(0x181, ms(43));
+        deadlines.on_frame(0x181, ms(45));
+        deadlines.on_frame(0x181, ms(55));
+        assert_eq!(
+            events(deadlines.on_tick(ms(56))),
+            [DeadlineEvent::Violated, DeadlineEvent::Restored]
+        );
+
+        // Silent: overdue, then two periods missing counted on the ticks and the arrival.
+        assert_eq!(events(deadlines.on_tick(ms(68))), [DeadlineEvent::Violated]);
+        assert!(deadlines.on_tick(ms(77)).is_empty());
+        assert_eq!(deadlines.violations(0x181).missing, 1);
+        deadlines.on_frame(0x181, ms(85));
+        deadlines.on_frame(0x181, ms(95));
+        assert_eq!(
+            deadlines.violations(0x181),
+            Violations {
+                late: 1,
+                early: 1,
+                missing: 2,
+            }
+        );
+        assert_eq!(events(deadlines.on_tick(ms(96))), [DeadlineEvent::Restored]);
+        assert_eq!(deadlines.violations(0x182), Violations::default());
+        assert_eq!(deadlines.snapshot()[0].violations.total(), 4);
+    }
+}
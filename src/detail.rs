@@ -0,0 +1,271 @@
+use crate::{
+    bus_stats::PeriodStats,
+    interpreter::InterpreterSettings,
+    message_cached::{MessageCached, RxMessageAdditional},
+};
+use egui::{Grid, RichText};
+
+/// A value read from the data at some offset, little and big endian.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Endian<T> {
+    pub le: T,
+    pub be: T,
+}
+
+/// Interpretations of the bytes starting at an offset, `None` past the end of the data.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Values {
+    pub u16: Option<Endian<u16>>,
+    pub u32: Option<Endian<u32>>,
+    pub i32: Option<Endian<i32>>,
+    pub f32: Option<Endian<f32>>,
+}
+
+impl Values {
+    pub fn at(data: &[u8], offset: usize) -> Self {
+        let two: Option<[u8; 2]> = data.get(offset..offset + 2).and_then(|b| b.try_into().ok());
+        let four: Option<[u8; 4]> = data.get(offset..offset + 4).and_then(|b| b.try_into().ok());
+        Self {
+            u16: two.map(|b| Endian {
+                le: u16::from_le_bytes(b),
+                be: u16::from_be_bytes(b),
+            }),
+            u32: four.map(|b| Endian {
+                le: u32::from_le_bytes(b),
+                be: u32::from_be_bytes(b),
+            }),
+            i32: four.map(|b| Endian {
+                le: i32::from_le_bytes(b),
+                be: i32::from_be_bytes(b),
+            }),
+            f32: four.map(|b| Endian {
+                le: f32::from_le_bytes(b),
+                be: f32::from_be_bytes(b),
+            }),
+        }
+    }
+}
+
+/// Full breakdown of the selected frame, kept even when it leaves the buffer.
+#[derive(Debug, Default)]
+pub struct DetailPane {
+    msg: Option<MessageCached>,
+}
+
+impl DetailPane {
+    pub fn set(&mut self, msg: Option<MessageCached>) {
+        self.msg = msg;
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.msg.is_some()
+    }
+
+    /// COB-ID of the selected frame.
+    pub fn cob_id(&self) -> Option<u16> {
+        self.msg.as_ref().map(|msg| msg.msg.msg.cob_id)
+    }
+
+    /// `period` are the inter-arrival statistics of the COB-ID of the frame.
+    pub fn update(
+        &mut self,
+        ui: &mut egui::Ui,
+        period: Option<PeriodStats>,
+        interpreters: &InterpreterSettings,
+    ) {
+        let Some(msg) = &self.msg else {
+            return;
+        };
+        let mut close = false;
+        ui.horizontal(|ui| {
+            ui.heading(format!("Message #{}", msg.index));
+            if ui.button("❌").on_hover_text("Close").clicked() {
+                close = true;
+            }
+        });
+        egui::ScrollArea::both().show(ui, |ui| {
+            ui.horizontal_top(|ui| {
+                ui.vertical(|ui| {
+                    Self::show_cob_id(ui, msg);
+                    Self::show_period(ui, period);
+                });
+                ui.separator();
+                ui.vertical(|ui| Self::show_bytes(ui, msg));
+                ui.separator();
+                ui.vertical(|ui| Self::show_values(ui, msg));
+                ui.separator();
+                ui.vertical(|ui| Self::show_decode(ui, msg, interpreters));
+            });
+        });
+        if close {
+            self.msg = None;
+        }
+    }
+
+    fn show_cob_id(ui: &mut egui::Ui, msg: &MessageCached) {
+        let cob_id = msg.msg.msg.cob_id;
+        ui.label(RichText::new("COB-ID").strong());
+        Grid::new("detail_cob_id").show(ui, |ui| {
+            ui.label("hex");
+            ui.label(format!("0x{cob_id:03X}"));
+            ui.end_row();
+            ui.label("bin");
+            ui.label(format!("{:04b} {:07b}", cob_id >> 7, cob_id & 0x7F));
+            ui.end_row();
+            ui.label("function");
+            ui.label(format!(
+                "0x{:X} {}",
+                cob_id >> 7,
+                msg.msg.parsed_type.to_string()
+            ));
+            ui.end_row();
+            ui.label("node ID");
+            ui.label(
+                msg.msg
+                    .parsed_node_id
+                    .map_or_else(|| "-".to_owned(), |n| format!("{n} (0x{n:02X})")),
+            );
+            ui.end_row();
+            ui.label("DLC");
+            ui.label(msg.msg.msg.dlc.to_string());
+            ui.end_row();
+            if let Some(side) = msg.origin {
+                ui.label("gateway side");
+                ui.label(side.to_string());
+                ui.end_row();
+            }
+        });
+    }
+
+    fn show_period(ui: &mut egui::Ui, period: Option<PeriodStats>) {
+        ui.label(RichText::new("Period").strong());
+        let Some(period) = period else {
+            ui.label("-");
+            return;
+        };
+        Grid::new("detail_period").show(ui, |ui| {
+            ui.label("mean");
+            ui.label(format!("{:.3} ms", period.mean));
+            ui.end_row();
+            ui.label("jitter σ");
+            ui.label(format!("±{:.3} ms", period.std_dev));
+            ui.end_row();
+            ui.label("min / max");
+            ui.label(format!("{:.3} / {:.3} ms", period.min, period.max));
+            ui.end_row();
+            ui.label("samples");
+            ui.label(period.samples.to_string());
+            ui.end_row();
+        });
+    }
+
+    fn data(msg: &MessageCached) -> &[u8] {
+        &msg.msg.msg.data[..msg.msg.msg.dlc.min(8)]
+    }
+
+    fn show_bytes(ui: &mut egui::Ui, msg: &MessageCached) {
+        ui.label(RichText::new("Bytes").strong());
+        Grid::new("detail_bytes").striped(true).show(ui, |ui| {
+            for title in ["offset", "hex", "dec", "bin", "ascii"] {
+                ui.label(title);
+            }
+            ui.end_row();
+            for (offset, byte) in Self::data(msg).iter().enumerate() {
+                ui.label(offset.to_string());
+                ui.label(format!("{byte:02X}"));
+                ui.label(byte.to_string());
+                ui.label(format!("{byte:08b}"));
+                ui.label(if byte.is_ascii_graphic() {
+                    char::from(*byte).to_string()
+                } else {
+                    ".".to_owned()
+                });
+                ui.end_row();
+            }
+        });
+    }
+
+    fn show_values(ui: &mut egui::Ui, msg: &MessageCached) {
+        fn cell<T: ToString>(ui: &mut egui::Ui, value: Option<Endian<T>>, le: bool) {
+            ui.label(value.map_or_else(String::new, |v| if le { v.le } else { v.be }.to_string()));
+        }
+
+        ui.label(RichText::new("Values (LE / BE)").strong());
+        let data = Self::data(msg);
+        Grid::new("detail_values").striped(true).show(ui, |ui| {
+            for title in [
+                "offset", "u16 LE", "u16 BE", "u32 LE", "u32 BE", "i32 LE", "i32 BE", "f32 LE",
+                "f32 BE",
+            ] {
+                ui.label(title);
+            }
+            ui.end_row();
+            for offset in 0..data.len().saturating_sub(1) {
+                let values = Values::at(data, offset);
+                ui.label(offset.to_string());
+                cell(ui, values.u16, true);
+                cell(ui, values.u16, false);
+                cell(ui, values.u32, true);
+                cell(ui, values.u32, false);
+                cell(ui, values.i32, true);
+                cell(ui, values.i32, false);
+                cell(ui, values.f32, true);
+                cell(ui, values.f32, false);
+                ui.end_row();
+            }
+        });
+    }
+
+    /// Summary and fields of the first enabled interpreter matching the frame.
+    fn show_decode(ui: &mut egui::Ui, msg: &MessageCached, interpreters: &InterpreterSettings) {
+        ui.label(RichText::new("Decode").strong());
+        let Some((name, view)) = interpreters.view(msg) else {
+            match msg.interpreter {
+                Some(name) => ui.weak(format!("{name} interpreter turned off in the settings")),
+                None => ui.label("No protocol decode"),
+            };
+            return;
+        };
+        ui.label(format!("{name}: {}", view.summary));
+        if !view.fields.is_empty() {
+            Grid::new("detail_decode").striped(true).show(ui, |ui| {
+                for (field, value) in &view.fields {
+                    ui.label(field);
+                    ui.label(value);
+                    ui.end_row();
+                }
+            });
+        }
+        if !matches!(msg.additional, RxMessageAdditional::None) {
+            ui.collapsing("Fields", |ui| {
+                ui.monospace(format!("{:#?}", msg.additional))
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Endian, Values};
+
+    #[test]
+    fn test_values() {
+        let data = [0x01, 0x02, 0x00, 0x00, 0x80, 0x3F];
+        let values = Values::at(&data, 0);
+        assert_eq!(
+            values.u16,
+            Some(Endian {
+                le: 0x0201,
+                be: 0x0102
+            })
+        );
+        assert_eq!(values.u32.unwrap().le, 0x0201);
+        assert_eq!(
+            Values::at(&data, 2).f32.unwrap().le.to_bits(),
+            1.0f32.to_bits()
+        );
+        assert_eq!(Values::at(&data, 2).i32.unwrap().be, 0x0000_803F);
+        assert_eq!(Values::at(&data, 3).u32, None);
+        assert_eq!(Values::at(&data, 5).u16, None);
+    }
+}
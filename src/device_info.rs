@@ -0,0 +1,239 @@
+use crate::nodes::NodeIdentity;
+use egui::{Color32, RichText};
+use oze_canopen::canopen::NodeId;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fmt};
+
+/// Device profiles of `CiA`, by number.
+const PROFILES: &[(u16, &str)] = &[
+    (401, "Generic I/O modules"),
+    (402, "Drives and motion control"),
+    (404, "Measuring devices and closed-loop controllers"),
+    (405, "IEC 61131-3 programmable devices"),
+    (406, "Encoders"),
+    (408, "Fluid power technology"),
+    (410, "Inclinometers"),
+    (412, "Medical devices"),
+    (413, "Truck gateways"),
+    (415, "Road construction machinery"),
+    (417, "Lift control systems"),
+    (418, "Battery modules"),
+    (419, "Battery chargers"),
+    (443, "SIIS level-2 devices"),
+    (447, "Special-purpose car add-on devices"),
+];
+
+/// Device type, identity and name strings of a node read over SDO.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviceInfo {
+    /// Device type (0x1000), the profile number in the low word.
+    pub device_type: u32,
+    /// Identity object (0x1018), `None` if the node doesn't answer it.
+    pub identity: Option<NodeIdentity>,
+    /// Manufacturer device name (0x1008).
+    pub name: Option<String>,
+    /// Manufacturer hardware version (0x1009).
+    pub hardware_version: Option<String>,
+    /// Manufacturer software version (0x100A).
+    pub software_version: Option<String>,
+    /// Local time of the read, RFC 3339.
+    pub read_at: String,
+}
+
+impl DeviceInfo {
+    /// Device profile number, 0 if the device follows none.
+    pub fn profile(&self) -> u16 {
+        (self.device_type & 0xFFFF) as u16
+    }
+
+    /// Additional information of the profile, e.g. the I/O functionality of a `CiA 401` device.
+    pub fn additional(&self) -> u16 {
+        (self.device_type >> 16) as u16
+    }
+
+    /// Profile as `CiA 402 Drives and motion control`.
+    pub fn profile_text(&self) -> String {
+        match self.profile() {
+            0 => "no standard profile".to_owned(),
+            profile => match PROFILES.binary_search_by_key(&profile, |(p, _)| *p) {
+                Ok(i) => format!("CiA {profile} {}", PROFILES[i].1),
+                Err(_) => format!("CiA {profile}"),
+            },
+        }
+    }
+}
+
+impl fmt::Display for DeviceInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(name) = &self.name {
+            write!(f, "{name}, ")?;
+        }
+        write!(f, "{}", self.profile_text())?;
+        if let Some(identity) = &self.identity {
+            write!(f, ", {identity}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Text of a `VISIBLE_STRING` object, without the padding some devices add.
+pub fn visible_string(data: &[u8]) -> String {
+    String::from_utf8_lossy(data)
+        .trim_end_matches(['\0', ' '])
+        .to_owned()
+}
+
+/// Window with the device information of the node picked in the inventory.
+#[derive(Debug, Default)]
+pub struct DeviceInfoPanel {
+    node_id: Option<NodeId>,
+}
+
+impl DeviceInfoPanel {
+    pub fn open(&mut self, node_id: NodeId) {
+        self.node_id = Some(node_id);
+    }
+
+    /// Node shown, `None` when the window is closed.
+    pub fn node_id(&self) -> Option<NodeId> {
+        self.node_id
+    }
+
+    /// Shows the information of the node, `reading` while a read is in progress. Returns the
+    /// node to read again when Refresh is clicked, which `can_read` allows.
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        infos: &BTreeMap<NodeId, Result<DeviceInfo, String>>,
+        reading: bool,
+        can_read: bool,
+    ) -> Option<NodeId> {
+        let node_id = self.node_id?;
+        let mut open = true;
+        let mut refresh = None;
+        egui::Window::new(format!("Device info — node {node_id}"))
+            .id(egui::Id::new("device_info"))
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                match infos.get(&node_id) {
+                    Some(Ok(info)) => Self::show_info(ui, info),
+                    Some(Err(e)) => {
+                        ui.colored_label(Color32::RED, e);
+                    }
+                    None if reading => {}
+                    None => {
+                        ui.label("Not read yet");
+                    }
+                }
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(can_read && !reading, egui::Button::new("⟳ Refresh"))
+                        .on_hover_text("Read 0x1000, 0x1018 and 0x1008-0x100A again")
+                        .clicked()
+                    {
+                        refresh = Some(node_id);
+                    }
+                    if reading {
+                        ui.spinner();
+                    }
+                });
+            });
+        if !open {
+            self.node_id = None;
+        }
+        refresh
+    }
+
+    fn show_info(ui: &mut egui::Ui, info: &DeviceInfo) {
+        let missing = || RichText::new("--").weak();
+        let text = |value: &Option<String>| value.as_deref().map_or_else(missing, RichText::new);
+        let hex = |value: Option<u32>| {
+            value.map_or_else(missing, |value| {
+                RichText::new(format!("0x{value:08X}")).monospace()
+            })
+        };
+        egui::Grid::new("device_info_grid")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("Name");
+                ui.label(text(&info.name).strong());
+                ui.end_row();
+                ui.label("Profile");
+                ui.label(info.profile_text()).on_hover_text(format!(
+                    "Additional information 0x{:04X}",
+                    info.additional()
+                ));
+                ui.end_row();
+                ui.label("Device type");
+                ui.label(hex(Some(info.device_type)));
+                ui.end_row();
+                let identity = info.identity.as_ref();
+                ui.label("Vendor ID");
+                ui.label(hex(identity.map(|identity| identity.vendor_id)));
+                ui.end_row();
+                ui.label("Product code");
+                ui.label(hex(identity.and_then(|identity| identity.product_code)));
+                ui.end_row();
+                ui.label("Revision");
+                ui.label(hex(identity.and_then(|identity| identity.revision)));
+                ui.end_row();
+                ui.label("Serial number");
+                ui.label(hex(identity.and_then(|identity| identity.serial)));
+                ui.end_row();
+                ui.label("Hardware version");
+                ui.label(text(&info.hardware_version));
+                ui.end_row();
+                ui.label("Software version");
+                ui.label(text(&info.software_version));
+                ui.end_row();
+            });
+        ui.label(RichText::new(format!("Read at {}", info.read_at)).weak());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{visible_string, DeviceInfo, PROFILES};
+    use crate::nodes::NodeIdentity;
+
+    #[test]
+    fn test_device_info() {
+        assert!(PROFILES.windows(2).all(|w| w[0].0 < w[1].0));
+        let info = DeviceInfo {
+            device_type: 0x0002_0192,
+            identity: Some(NodeIdentity {
+                vendor_id: 0x9A,
+                product_code: Some(0x0003_0924),
+                revision: None,
+                serial: None,
+            }),
+            name: Some(visible_string(b"Drive 42 \0\0")),
+            hardware_version: None,
+            software_version: None,
+            read_at: String::new(),
+        };
+        assert_eq!(info.profile(), 402);
+        assert_eq!(info.additional(), 2);
+        assert_eq!(
+            info.to_string(),
+            "Drive 42, CiA 402 Drives and motion control, vendor 0x0000009A, product 0x00030924"
+        );
+        let custom = DeviceInfo {
+            device_type: 0x0000_01F4,
+            identity: None,
+            name: None,
+            ..info.clone()
+        };
+        assert_eq!(custom.to_string(), "CiA 500");
+        assert_eq!(
+            DeviceInfo {
+                device_type: 0,
+                ..custom
+            }
+            .profile_text(),
+            "no standard profile"
+        );
+    }
+}
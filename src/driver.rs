@@ -1,21 +1,66 @@
-use crate::message_cached::MessageCached;
+use crate::{
+    auto_start::{AutoStart, AutoStarter},
+    bitrate::{counted_bits, Bitrate, RatesData},
+    bus_errors::{ControllerState, ErrorCounts, ErrorFrame, InterfaceHealth},
+    device_info::{self, DeviceInfo},
+    drops::InterfaceDrops,
+    emcy::Emergency,
+    gateway::{Gateway, GatewayConfig, GatewaySide, GatewayStats},
+    message_cached::{Direction, Heartbeat, MessageCached, NmtState},
+    notifications::{Notification, Severity},
+    nodes::NodeIdentity,
+    pacer::{BulkThroughput, TxPacer},
+    pdo_config::{self, MappedObject, PdoConfig, PdoId, PdoParameters, PDO_DISABLED},
+    periodic::{PeriodicEntry, PeriodicTable},
+    recorder::Recorder,
+    rx_socket::{KernelFilter, RxEvent, RxSocket},
+    sdo::{self, SdoReply, SegmentReply},
+    tx_log::TxLog,
+};
+use chrono::{DateTime, Local, SecondsFormat};
 use oze_canopen::{
-    canopen::{self, JoinHandles},
+    canopen::{self, JoinHandles, NodeId, RxMessage},
     interface::{CanOpenInfo, CanOpenInterface, Connection},
     proto::nmt::{NmtCommand, NmtCommandSpecifier},
     transmitter::TxPacket,
 };
-use std::{collections::VecDeque, time::Duration};
-use tokio::{signal::ctrl_c, sync::{watch, mpsc}, task::JoinHandle, time::sleep};
+use socketcan::CanInterface;
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fmt, io,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        OnceLock,
+        Arc,
+    },
+    time::Duration,
+};
+use tokio::{
+    runtime::Handle,
+    signal::ctrl_c,
+    sync::{broadcast, mpsc, oneshot, watch},
+    task::JoinHandle,
+    time::{sleep_until, Instant},
+};
 
 /// Enum representing different control commands that can be sent to the driver.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ControlCommand {
+    /// The viewer is paused, messages are still delivered for statistics and node tracking.
     Stop,
+    /// Received messages are dropped by the driver.
+    Disconnect,
     Kill,
     Process,
 }
 
+impl ControlCommand {
+    /// Whether the driver keeps its periodic transmissions, pausing the viewer doesn't stop them.
+    pub fn transmits(&self) -> bool {
+        matches!(self, ControlCommand::Stop | ControlCommand::Process)
+    }
+}
+
 /// Enum representing different write commands for sending CAN messages.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum WriteCommand {
@@ -29,18 +74,306 @@ pub enum WriteCommand {
     SendPdo { cob_id: u32, data: Vec<u8> },
     /// Send an SDO Download (write to object dictionary)
     SendSdoDownload { node_id: u8, index: u16, subindex: u8, data: Vec<u8> },
+    /// Send an SDO Upload (read from object dictionary)
+    SendSdoUpload { node_id: u8, index: u16, subindex: u8 },
+    /// SDO Upload of an object dictionary scan, paced like the other bulk operations
+    ScanUpload { node_id: u8, index: u16, subindex: u8 },
     /// Configure TPDO1 for Statusword on SYNC
     ConfigureTpdo1Statusword { node_id: u8 },
+    /// Read the identity object (0x1018) of a node, the result is also kept in [`State::identities`]
+    ReadIdentity { node_id: u8 },
+    /// Read the device type, identity and name strings of a node, the result is also kept in
+    /// [`State::device_infos`]
+    ReadDeviceInfo { node_id: u8 },
+    /// Read the communication and mapping parameters of the first four RPDOs and TPDOs of a
+    /// node, the result is also kept in [`State::pdo_configs`]
+    ReadPdoConfig { node_id: u8 },
+    /// Write the communication and mapping parameters of a PDO, disabling it while it is
+    /// remapped
+    WritePdoConfig { node_id: u8, parameters: PdoParameters },
+    /// Start sending a frame every `period`, replacing the periodic entry with the same id
+    AddPeriodic { id: u32, cob_id: u32, data: Vec<u8>, period: Duration },
+    /// Stop a periodic entry
+    RemovePeriodic { id: u32 },
+}
+
+impl WriteCommand {
+    /// Whether the command sends a burst of frames, which is paced by the driver.
+    pub fn is_bulk(&self) -> bool {
+        matches!(
+            self,
+            WriteCommand::ConfigureTpdo1Statusword { .. }
+                | WriteCommand::ReadIdentity { .. }
+                | WriteCommand::ReadDeviceInfo { .. }
+                | WriteCommand::ReadPdoConfig { .. }
+                | WriteCommand::WritePdoConfig { .. }
+                | WriteCommand::ScanUpload { .. }
+        )
+    }
+}
+
+/// Successful outcome of a write command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WriteOutcome {
+    /// The frames were queued for transmission.
+    Sent,
+    /// The SDO server confirmed the download.
+    SdoConfirmed,
+    /// Data uploaded from the SDO server.
+    SdoData(Vec<u8>),
+    /// The identity object of the node.
+    Identity(NodeIdentity),
+    /// The device information of the node.
+    DeviceInfo(Box<DeviceInfo>),
+    /// The PDO configuration of the node.
+    PdoConfig(Box<PdoConfig>),
+    /// The command was applied by the driver.
+    Done,
+}
+
+impl fmt::Display for WriteOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WriteOutcome::Sent => write!(f, "sent"),
+            WriteOutcome::SdoConfirmed => write!(f, "SDO confirmed"),
+            WriteOutcome::SdoData(data) => {
+                write!(f, "SDO data")?;
+                data.iter().try_for_each(|byte| write!(f, " {byte:02X}"))
+            }
+            WriteOutcome::Identity(identity) => write!(f, "{identity}"),
+            WriteOutcome::DeviceInfo(info) => write!(f, "{info}"),
+            WriteOutcome::PdoConfig(config) => write!(f, "{config}"),
+            WriteOutcome::Done => write!(f, "done"),
+        }
+    }
+}
+
+/// Failure of a write command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WriteError {
+    /// The frame couldn't be queued for transmission.
+    Transmit(String),
+    /// The SDO server didn't answer in time.
+    Timeout,
+    /// The SDO server aborted the transfer.
+    SdoAbort { index: u16, subindex: u8, code: u32 },
+    /// The command or the answer to it is not supported.
+    Unsupported(String),
+    /// No heartbeat reported the expected NMT state in time.
+    NmtState(NmtState),
+    /// A step of a multi-step command failed.
+    Step(u8, Box<WriteError>),
+}
+
+impl fmt::Display for WriteError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WriteError::Transmit(e) => write!(f, "transmit failed: {e}"),
+            WriteError::Timeout => write!(f, "no SDO response"),
+            WriteError::SdoAbort {
+                index,
+                subindex,
+                code,
+            } => write!(f, "{}", sdo::abort_text(*index, *subindex, *code)),
+            WriteError::Unsupported(e) => write!(f, "{e}"),
+            WriteError::NmtState(state) => write!(
+                f,
+                "no heartbeat reporting {state} within {} s",
+                HEARTBEAT_TIMEOUT.as_secs()
+            ),
+            WriteError::Step(step, e) => write!(f, "step {step}: {e}"),
+        }
+    }
 }
 
-/// Struct representing the state of the CAN interface and received messages.
+pub type WriteResult = Result<WriteOutcome, WriteError>;
+
+/// Time to wait for a heartbeat confirming an NMT state change.
+const HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Write command queued for the driver, with an optional channel for its result.
+#[derive(Debug)]
+pub struct WriteRequest {
+    pub command: WriteCommand,
+    reply: Option<oneshot::Sender<WriteResult>>,
+}
+
+impl WriteRequest {
+    /// Creates a request whose result is delivered to the returned receiver.
+    pub fn new(command: WriteCommand) -> (Self, oneshot::Receiver<WriteResult>) {
+        let (reply, result) = oneshot::channel();
+        (
+            Self {
+                command,
+                reply: Some(reply),
+            },
+            result,
+        )
+    }
+
+    /// Delivers the result to the requester, if one waits for it.
+    pub fn reply(self, result: WriteResult) {
+        if let Some(reply) = self.reply {
+            let _ = reply.send(result);
+        }
+    }
+}
+
+impl From<WriteCommand> for WriteRequest {
+    fn from(command: WriteCommand) -> Self {
+        Self {
+            command,
+            reply: None,
+        }
+    }
+}
+
+/// State of the link to the CAN interface as seen by the driver.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LinkState {
+    /// No interface name is configured.
+    #[default]
+    Disconnected,
+    /// Waiting for the interface to be opened for the first time.
+    Connecting,
+    /// The receive socket is open.
+    Connected,
+    /// The interface disappeared, the driver retries to open it with backoff.
+    Reconnecting,
+}
+
+impl LinkState {
+    pub fn as_str(&self) -> &str {
+        match self {
+            LinkState::Disconnected => "disconnected",
+            LinkState::Connecting => "connecting",
+            LinkState::Connected => "connected",
+            LinkState::Reconnecting => "reconnecting",
+        }
+    }
+}
+
+/// Time without a received frame after which a connection is shown as idle.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// State of the connection shown to the user, from the link, the traffic and the controller.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionStatus {
+    /// No interface configured, or disconnected by the user.
+    #[default]
+    Disconnected,
+    /// Waiting for the interface to be opened for the first time.
+    Connecting,
+    /// The interface is open but no frame was received lately.
+    Idle,
+    /// Frames were received lately.
+    Active,
+    /// The interface was lost or the controller is bus off.
+    Error,
+}
+
+impl ConnectionStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Disconnected => "disconnected",
+            Self::Connecting => "connecting",
+            Self::Idle => "connected, idle",
+            Self::Active => "connected",
+            Self::Error => "error",
+        }
+    }
+}
+
+/// Record of the interface being lost and, possibly, recovered.
+#[derive(Debug, Clone)]
+pub struct LinkEvent {
+    pub can_name: String,
+    pub reason: String,
+    pub lost: DateTime<Local>,
+    pub recovered: Option<DateTime<Local>>,
+}
+
+impl fmt::Display for LinkEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} lost {}",
+            self.can_name,
+            self.lost.format("%H:%M:%S")
+        )?;
+        if let Some(recovered) = self.recovered {
+            write!(f, ", recovered {}", recovered.format("%H:%M:%S"))?;
+        }
+        write!(f, " ({})", self.reason)
+    }
+}
+
+/// Maximum number of link events kept in the state.
+const MAX_LINK_EVENTS: usize = 32;
+
+/// Boundary between two sessions in the stream of received messages, or messages lost
+/// because the GUI couldn't keep up.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionMarker {
+    /// Index of the first message of the new session.
+    pub index: u64,
+    pub text: String,
+}
+
+/// Maximum number of session markers kept in the state.
+const MAX_SESSION_MARKERS: usize = 64;
+
+/// Maximum number of emergencies kept in the state.
+const MAX_EMERGENCIES: usize = 256;
+
+/// Struct representing the state of the CAN interface.
+///
+/// Received messages are not part of the state, they are delivered over a separate
+/// bounded channel so that none of them is lost between two GUI frames.
 #[derive(Default, Debug, Clone)]
 pub struct State {
     pub can_name: String,
     pub bitrate: Option<u32>,
-    pub data: VecDeque<MessageCached>,
+    /// Bitrate reported by the interface when it was opened, `None` if unknown, e.g. on vcan.
+    pub interface_bitrate: Option<u32>,
     pub info: CanOpenInfo,
     pub exit_signal: bool,
+    /// Number of messages dropped because a channel on the way to the GUI was full.
+    pub dropped: u64,
+    /// Receive drop counters of the interface, `None` if they can't be read.
+    pub interface_drops: Option<InterfaceDrops>,
+    pub link: LinkState,
+    pub status: ConnectionStatus,
+    /// History of interface losses, oldest first.
+    pub link_events: VecDeque<LinkEvent>,
+    /// Connection changes, recoveries and losses in the message stream, oldest first.
+    ///
+    /// Message indexes keep increasing across sessions.
+    pub sessions: VecDeque<SessionMarker>,
+    /// Results of the last identity read of every node.
+    pub identities: BTreeMap<NodeId, Result<NodeIdentity, String>>,
+    /// Results of the last device information read of every node.
+    pub device_infos: BTreeMap<NodeId, Result<DeviceInfo, String>>,
+    /// Results of the last PDO configuration read of every node.
+    pub pdo_configs: BTreeMap<NodeId, Result<PdoConfig, String>>,
+    /// Active periodic transmissions.
+    pub periodic: Vec<PeriodicEntry>,
+    /// File the received frames are recorded to.
+    pub recording: Option<String>,
+    /// Throughput of the last bulk operation.
+    pub last_bulk: Option<BulkThroughput>,
+    /// Last emergencies received, oldest first, independent of the message buffers.
+    pub emergencies: VecDeque<Emergency>,
+    /// Number of emergencies received since the start.
+    pub emergency_count: u64,
+    /// Error frames of the interface since the start, per class.
+    pub bus_errors: ErrorCounts,
+    /// Controller state and error counters, `None` if the interface doesn't report them.
+    pub health: Option<InterfaceHealth>,
+    /// Last change of the controller state seen since the connection.
+    pub health_changed: Option<DateTime<Local>>,
+    /// Counters of the gateway, `None` when it is not running.
+    pub gateway: Option<GatewayStats>,
 }
 
 /// Struct representing control data including the command and connection details.
@@ -48,65 +381,399 @@ pub struct State {
 pub struct Control {
     pub command: ControlCommand,
     pub connection: Connection,
+    /// Acceptance filters pushed down to the receive socket, empty accepts everything.
+    pub kernel_filters: Vec<KernelFilter>,
+    /// Maximum rate of bulk transmissions in frames per second, `None` is unlimited.
+    pub tx_rate: Option<u32>,
+    /// Forward frames between the interface of the connection and a peer interface.
+    pub gateway: Option<GatewayConfig>,
+    /// Sent to every node when the driver is killed, e.g. to stop the drives.
+    pub exit_nmt: Option<NmtCommandSpecifier>,
+    /// Sends NMT Start to the nodes seen booting.
+    pub auto_start: Option<AutoStart>,
+}
+
+impl Control {
+    /// Whether the periodic transmissions stop when the connection was `previous`: on a
+    /// disconnect, a kill or another connection, not when the viewer is paused.
+    pub fn stops_periodic(&self, previous: &Connection) -> bool {
+        *previous != self.connection || !self.command.transmits()
+    }
 }
 
 /// Struct representing the driver responsible for processing CAN messages and handling control commands.
 pub struct Driver {
     sender: watch::Sender<State>,
+    data_sender: mpsc::Sender<MessageCached>,
     receiver: watch::Receiver<Control>,
-    write_receiver: mpsc::Receiver<WriteCommand>,
+    write_receiver: mpsc::Receiver<WriteRequest>,
     state: State,
     pub co: CanOpenInterface,
+    /// Frames sent through `co`, to mark their copies received as `Tx`.
+    tx_log: TxLog,
+    /// Bits of the frames marked `Tx`, for the bitrate chart.
+    pub tx_bits: Arc<AtomicUsize>,
+    rx: RxSocket,
+    gateway: Option<Gateway>,
+    periodic: PeriodicTable,
+    pacer: TxPacer,
+    auto_starter: AutoStarter,
     control: Control,
     index: u64,
+    next_drops_poll: Instant,
+    /// Earliest time the state may be published again, see [`STATE_PUBLISH_PERIOD`].
+    next_publish: Instant,
+    /// The state changed since it was last published.
+    unpublished: bool,
+    /// Log every received frame is written to, see `record`.
+    recorder: Option<Recorder>,
+    /// Messages streamed to the WebSocket clients, see `stream`.
+    stream: Option<broadcast::Sender<Arc<MessageCached>>>,
+    /// Context of the GUI, repainted when a message is handed to it.
+    repaint: Arc<OnceLock<egui::Context>>,
+    /// Events reported to the GUI.
+    events: Option<mpsc::Sender<Notification>>,
+    /// Reception of the last frame, for the idle status.
+    last_frame: Option<Instant>,
+    /// Messages dropped since the last one handed to the GUI, marked in the stream once it
+    /// keeps up again.
+    lost: u64,
     handles: JoinHandles,
 }
 
-const MAX_MESSAGES_IN_STATE: usize = 512;
+/// Interval between two reads of the interface drop counters.
+const DROPS_POLL_PERIOD: Duration = Duration::from_millis(500);
+
+/// Longest wait for an event before the state is refreshed anyway.
+const IDLE_POLL_PERIOD: Duration = Duration::from_millis(100);
+
+/// Shortest interval between two publications of the state, which is cloned for the GUI.
+/// Received messages go over the data channel as they come.
+pub const STATE_PUBLISH_PERIOD: Duration = Duration::from_millis(20);
+
+/// Capacity of the channel delivering the events to the GUI.
+const EVENT_CHANNEL_CAPACITY: usize = 256;
+
+/// Capacity of the channel delivering received messages to the GUI.
+pub const DATA_CHANNEL_CAPACITY: usize = 16384;
 
 impl Driver {
     pub fn new(
         sender: watch::Sender<State>,
+        data_sender: mpsc::Sender<MessageCached>,
         receiver: watch::Receiver<Control>,
-        write_receiver: mpsc::Receiver<WriteCommand>,
+        write_receiver: mpsc::Receiver<WriteRequest>,
     ) -> Self {
         // Initialize the CANopen interface with the initial connection details.
         let initial_connection = receiver.borrow().connection.clone();
-        let (co, handles) = canopen::start(initial_connection.can_name, initial_connection.bitrate);
+        let (mut co, handles) = canopen::start(
+            initial_connection.can_name.clone(),
+            initial_connection.bitrate,
+        );
+        let tx_log = TxLog::default();
+        co.tx = tx_log.intercept(co.tx.clone());
 
         // Create the driver and start running it.
         let control = receiver.borrow().clone();
+        let state = State {
+            link: Self::initial_link_state(&initial_connection.can_name),
+            ..Default::default()
+        };
+        let mut rx = RxSocket::new(initial_connection.can_name);
+        rx.set_filters(control.kernel_filters.clone());
+        let mut pacer = TxPacer::default();
+        pacer.set_rate(control.tx_rate);
+        let gateway = Self::start_gateway(&control);
         Driver {
             co,
+            tx_log,
+            tx_bits: Arc::new(AtomicUsize::new(0)),
+            rx,
+            gateway,
+            periodic: PeriodicTable::default(),
+            pacer,
+            auto_starter: AutoStarter::default(),
             sender,
+            data_sender,
             control,
             receiver,
             write_receiver,
             index: 0,
-            state: State::default(),
+            next_drops_poll: Instant::now(),
+            next_publish: Instant::now(),
+            unpublished: false,
+            recorder: None,
+            stream: None,
+            repaint: Arc::default(),
+            events: None,
+            last_frame: None,
+            lost: 0,
+            state,
             handles,
         }
     }
 
+    /// Repaints the GUI of `repaint` as soon as a message is handed to it, the context is set
+    /// once the window is created.
+    pub fn repaint_on_data(&mut self, repaint: Arc<OnceLock<egui::Context>>) {
+        self.repaint = repaint;
+    }
+
+    /// Reports the connection changes, link losses, bus errors, EMCYs and failed commands to
+    /// `events`.
+    pub fn notify_to(&mut self, events: mpsc::Sender<Notification>) {
+        self.events = Some(events);
+    }
+
+    /// Reports an event, dropped if the GUI is behind.
+    fn notify(&self, severity: Severity, text: String) {
+        if let Some(events) = &self.events {
+            let _ = events.try_send(Notification::new(severity, text));
+        }
+    }
+
+    /// Writes every frame received from now on to a log file, both sides of the gateway.
+    pub fn record(&mut self, recorder: Recorder) {
+        log::info!("Recording the frames to {}", recorder.path());
+        self.state.recording = Some(recorder.path().to_owned());
+        self.recorder = Some(recorder);
+    }
+
+    /// Sends every received and sent message to `stream` as well, never waiting for its
+    /// receivers.
+    pub fn stream(&mut self, stream: broadcast::Sender<Arc<MessageCached>>) {
+        self.stream = Some(stream);
+    }
+
+    /// Writes a frame to the recording, which is stopped if the file can't be written.
+    fn record_frame(&mut self, msg: &MessageCached) {
+        let Some(recorder) = &mut self.recorder else {
+            return;
+        };
+        let interface = match (msg.origin, Self::gateway_setup(&self.control)) {
+            (Some(GatewaySide::B), Some((_, config))) => config.peer.as_str(),
+            _ => self.control.connection.can_name.as_str(),
+        };
+        if let Err(e) = recorder.write(msg, interface) {
+            self.stop_recording(&e);
+        }
+    }
+
+    /// Writes an error frame of the interface to the recording.
+    fn record_error(&mut self, frame: &ErrorFrame) {
+        let Some(recorder) = &mut self.recorder else {
+            return;
+        };
+        if let Err(e) = recorder.write_error(frame, &self.control.connection.can_name) {
+            self.stop_recording(&e);
+        }
+    }
+
+    /// Drops the recording after a write error.
+    fn stop_recording(&mut self, e: &io::Error) {
+        let Some(recorder) = self.recorder.take() else {
+            return;
+        };
+        let text = format!("Recording to {} stopped: {e}", recorder.path());
+        log::error!("{text}");
+        self.state.recording = None;
+        self.notify(Severity::Error, text);
+    }
+
+    fn initial_link_state(can_name: &str) -> LinkState {
+        if can_name.is_empty() {
+            LinkState::Disconnected
+        } else {
+            LinkState::Connecting
+        }
+    }
+
+    /// Interface and configuration of the gateway, `None` if it must not run.
+    fn gateway_setup(control: &Control) -> Option<(&str, &GatewayConfig)> {
+        let config = control.gateway.as_ref()?;
+        let active = matches!(
+            control.command,
+            ControlCommand::Process | ControlCommand::Stop
+        );
+        (active && !control.connection.can_name.is_empty() && !config.peer.is_empty())
+            .then_some((control.connection.can_name.as_str(), config))
+    }
+
+    fn start_gateway(control: &Control) -> Option<Gateway> {
+        Self::gateway_setup(control).map(|(can_name, config)| Gateway::start(can_name, config))
+    }
+
+    /// Updates the link state after the receive socket was opened.
+    fn on_link_opened(&mut self) {
+        if self.state.link == LinkState::Reconnecting {
+            if let Some(event) = self.state.link_events.back_mut() {
+                event.recovered = Some(Local::now());
+                let text = format!("Interface recovered: {event}");
+                log::info!("{text}");
+                self.notify(Severity::Info, text);
+            }
+            self.push_session_marker(format!(
+                "--- {} recovered ---",
+                self.control.connection.can_name
+            ));
+        }
+        self.state.link = LinkState::Connected;
+        self.state.interface_bitrate =
+            Self::read_interface_bitrate(&self.control.connection.can_name);
+    }
+
+    fn update_status(&mut self) {
+        let bus_off = match self.state.health {
+            Some(health) => health.state == Some(ControllerState::BusOff),
+            None => self.state.bus_errors.state == ControllerState::BusOff,
+        };
+        let recent = self
+            .last_frame
+            .is_some_and(|time| time.elapsed() < IDLE_TIMEOUT);
+        self.state.status = match (&self.control.command, self.state.link) {
+            (ControlCommand::Disconnect | ControlCommand::Kill, _) | (_, LinkState::Disconnected) => {
+                ConnectionStatus::Disconnected
+            }
+            (_, LinkState::Connecting) => ConnectionStatus::Connecting,
+            (_, LinkState::Reconnecting) => ConnectionStatus::Error,
+            (_, LinkState::Connected) if bus_off => ConnectionStatus::Error,
+            (_, LinkState::Connected) if recent => ConnectionStatus::Active,
+            (_, LinkState::Connected) => ConnectionStatus::Idle,
+        };
+    }
+
+    /// Reads the controller state and error counters, noting when the state changes.
+    fn poll_health(&mut self) {
+        let health = InterfaceHealth::read(&self.control.connection.can_name);
+        let state = |health: Option<InterfaceHealth>| health.map(|health| health.state);
+        if self.state.health.is_some() && state(health) != state(self.state.health) {
+            self.state.health_changed = Some(Local::now());
+        }
+        self.state.health = health;
+    }
+
+    fn read_interface_bitrate(can_name: &str) -> Option<u32> {
+        CanInterface::open(can_name).ok()?.bit_rate().ok()?
+    }
+
+    fn push_session_marker(&mut self, text: String) {
+        self.push_session_marker_at(self.index, text);
+    }
+
+    fn push_session_marker_at(&mut self, index: u64, text: String) {
+        self.state.sessions.push_back(SessionMarker { index, text });
+        while self.state.sessions.len() > MAX_SESSION_MARKERS {
+            self.state.sessions.pop_front();
+        }
+    }
+
+    /// Marks the start of a session on a new interface or bitrate.
+    fn on_connection_changed(&mut self, previous: &Connection) {
+        let connection = &self.control.connection;
+        let verb = if previous.can_name.is_empty() {
+            "connected"
+        } else {
+            "reconnected"
+        };
+        let text = match connection.bitrate {
+            _ if connection.can_name.is_empty() => "--- disconnected ---".to_owned(),
+            Some(bitrate) if bitrate % 1000 == 0 => {
+                format!("--- {verb} to {} @{}k ---", connection.can_name, bitrate / 1000)
+            }
+            Some(bitrate) => format!("--- {verb} to {} @{bitrate} ---", connection.can_name),
+            None => format!("--- {verb} to {} ---", connection.can_name),
+        };
+        self.notify(Severity::Info, text.trim_matches(|c| c == '-' || c == ' ').to_owned());
+        self.push_session_marker(text);
+        self.state.health = None;
+        self.state.health_changed = None;
+    }
+
+    /// Updates the link state after the receive socket failed.
+    fn on_link_lost(&mut self, reason: String) {
+        log::warn!("Interface {} lost: {reason}", self.control.connection.can_name);
+        self.notify(
+            Severity::Warning,
+            format!("Interface {} lost: {reason}", self.control.connection.can_name),
+        );
+        if self.state.link != LinkState::Reconnecting {
+            self.state.link_events.push_back(LinkEvent {
+                can_name: self.control.connection.can_name.clone(),
+                reason,
+                lost: Local::now(),
+                recovered: None,
+            });
+            while self.state.link_events.len() > MAX_LINK_EVENTS {
+                self.state.link_events.pop_front();
+            }
+        }
+        self.state.link = LinkState::Reconnecting;
+    }
+
     /// Asynchronously processes incoming CAN messages and control commands.
     async fn process(&mut self) {
         // Wait for a message, timeout, ctrl_c signal, or write command.
+        let wake = self.wake_time();
         let rcv = tokio::select! {
-            rcv = self.co.rx.recv() => Some(rcv),
-            () = sleep(Duration::from_millis(100)) => None,
+            rcv = self.rx.recv() => Some(rcv),
+            msg = Self::recv_peer(&mut self.gateway) => {
+                self.on_frame(msg, Some(GatewaySide::B));
+                None
+            }
+            () = sleep_until(wake) => None,
             _ = ctrl_c() => {
                 self.control.command = ControlCommand::Kill;
                 return;
             },
-            Some(write_cmd) = self.write_receiver.recv() => {
-                self.handle_write_command(write_cmd).await;
+            Some(request) = self.write_receiver.recv() => {
+                let name = format!("{:?}", request.command);
+                let result = self.handle_write_command(request.command).await;
+                match (request.reply, &result) {
+                    (Some(reply), _) => {
+                        let _ = reply.send(result);
+                    }
+                    // Nobody waits for the result, the user is told here.
+                    (None, Err(e)) => self.notify(Severity::Error, format!("{name} failed: {e}")),
+                    (None, Ok(_)) => {}
+                }
                 None
             }
         };
 
-        // Get the latest control data if it has changed.
-        if self.receiver.has_changed().unwrap() {
+        // Get the latest control data if it has changed, the GUI dropping its end kills the driver.
+        let Ok(changed) = self.receiver.has_changed() else {
+            self.control.command = ControlCommand::Kill;
+            return;
+        };
+        if changed {
+            let previous = self.control.connection.clone();
+            let previous_filters = self.control.kernel_filters.clone();
+            let previous_gateway = Self::gateway_setup(&self.control)
+                .map(|(can_name, config)| (can_name.to_owned(), config.clone()));
             self.control = self.receiver.borrow_and_update().clone();
+            if self.control.stops_periodic(&previous) {
+                self.periodic.clear();
+            }
+            if previous != self.control.connection {
+                // The new interface will be opened by the receive socket on the next poll.
+                self.rx.set_interface(self.control.connection.can_name.clone());
+                self.state.link = Self::initial_link_state(&self.control.connection.can_name);
+                self.on_connection_changed(&previous);
+            }
+            if previous_filters != self.control.kernel_filters {
+                self.rx.set_filters(self.control.kernel_filters.clone());
+            }
+            let gateway = Self::gateway_setup(&self.control)
+                .map(|(can_name, config)| (can_name.to_owned(), config.clone()));
+            if previous_gateway != gateway {
+                // Stop the running gateway before its interfaces are reopened.
+                self.gateway = None;
+                self.gateway = Self::start_gateway(&self.control);
+            }
+            self.pacer.set_rate(self.control.tx_rate);
+            if self.control.auto_start.is_none() {
+                self.auto_starter.clear();
+            }
             // Update connection details if they have changed.
             self.co
                 .connection
@@ -118,163 +785,690 @@ impl Driver {
         // Set information from the CANopen stack to the state.
         let info = self.co.info.lock().await.clone();
         self.state.info = info;
+        self.state.periodic = self.periodic.entries();
+        if Instant::now() >= self.next_drops_poll {
+            self.next_drops_poll = Instant::now() + DROPS_POLL_PERIOD;
+            self.state.interface_drops = InterfaceDrops::read(&self.control.connection.can_name);
+            self.poll_health();
+        }
+        self.update_status();
+        if let Some(recorder) = &mut self.recorder {
+            if let Err(e) = recorder.flush_if_due(Instant::now()) {
+                self.stop_recording(&e);
+            }
+        }
+        self.state.gateway = self.gateway.as_ref().map(Gateway::stats);
+        if let Some(gateway) = &self.gateway {
+            self.state.dropped += gateway.take_unseen();
+        }
+
+        if let Some(event) = rcv {
+            self.on_rx_event(event);
+        }
+        self.start_booted_nodes().await;
+    }
+
+    /// Time to stop waiting for an event: the publication of the last changes or the next
+    /// auto-start, else the idle poll.
+    fn wake_time(&self) -> Instant {
+        let idle = Instant::now() + IDLE_POLL_PERIOD;
+        let wake = self.auto_starter.next_due().map_or(idle, |due| due.min(idle));
+        if self.unpublished {
+            self.next_publish.min(wake)
+        } else {
+            wake
+        }
+    }
+
+    /// Sends NMT Start to the booted nodes whose delay is over.
+    async fn start_booted_nodes(&mut self) {
+        for node_id in self.auto_starter.take_due(Instant::now()) {
+            match self.send_nmt(node_id, NmtCommandSpecifier::StartRemoteNode).await {
+                Ok(_) => self.notify(Severity::Info, format!("Auto-start: NMT Start sent to node {node_id}")),
+                Err(e) => self.notify(Severity::Error, format!("Auto-start of node {node_id} failed: {e}")),
+            }
+        }
+    }
+
+    /// Tracks the link and hands received frames to the GUI.
+    fn on_rx_event(&mut self, event: RxEvent) {
+        match event {
+            RxEvent::Frame(msg) => {
+                let origin = self.gateway.as_ref().map(|_| GatewaySide::A);
+                self.on_frame(msg, origin);
+            }
+            RxEvent::Error(frame) => {
+                if let ControlCommand::Stop | ControlCommand::Process = self.control.command {
+                    let previous = self.state.bus_errors.state;
+                    self.state.bus_errors.on_error_frame(&frame);
+                    self.notify_controller_state(previous);
+                    self.record_error(&frame);
+                }
+            }
+            RxEvent::Opened => self.on_link_opened(),
+            RxEvent::Lost(reason) => self.on_link_lost(reason),
+        }
+    }
+
+    /// Reports a change of the controller state seen in an error frame.
+    fn notify_controller_state(&self, previous: ControllerState) {
+        let state = self.state.bus_errors.state;
+        if state == previous {
+            return;
+        }
+        let severity = match state {
+            ControllerState::BusOff => Severity::Error,
+            ControllerState::Passive => Severity::Warning,
+            ControllerState::Active | ControllerState::Warning => Severity::Info,
+        };
+        let can_name = &self.control.connection.can_name;
+        self.notify(severity, format!("{can_name} is {}", state.name()));
+    }
 
+    /// Waits for the next frame of side B of the gateway, forever if there is no gateway.
+    async fn recv_peer(gateway: &mut Option<Gateway>) -> RxMessage {
+        match gateway {
+            Some(gateway) => gateway.recv().await,
+            None => std::future::pending().await,
+        }
+    }
+
+    fn on_frame(&mut self, msg: RxMessage, origin: Option<GatewaySide>) {
         // Handle control commands.
         match self.control.command {
-            ControlCommand::Stop | ControlCommand::Kill => {
+            ControlCommand::Disconnect | ControlCommand::Kill => {
                 return;
             }
-            ControlCommand::Process => {}
+            ControlCommand::Stop | ControlCommand::Process => {}
+        }
+        if origin != Some(GatewaySide::B) {
+            self.last_frame = Some(Instant::now());
         }
 
-        // If no message has been received, return.
-        let Some(Ok(d)) = rcv else {
-            return;
-        };
+        if let Some(emergency) = Emergency::parse(self.index, &msg) {
+            let severity = if emergency.is_reset() {
+                Severity::Info
+            } else {
+                Severity::Warning
+            };
+            self.notify(
+                severity,
+                format!("Node {}: {}", emergency.node_id, emergency.description()),
+            );
+            self.state.emergencies.push_back(emergency);
+            self.state.emergency_count += 1;
+            while self.state.emergencies.len() > MAX_EMERGENCIES {
+                self.state.emergencies.pop_front();
+            }
+        }
+
+        if origin != Some(GatewaySide::B) {
+            let auto_start = self.control.auto_start;
+            if let Some(node_id) = self.auto_starter.on_frame(auto_start.as_ref(), &msg, Instant::now()) {
+                let delay = auto_start.map(|config| config.delay.as_millis()).unwrap_or_default();
+                self.notify(
+                    Severity::Info,
+                    format!("Auto-start: node {node_id} booted, NMT Start in {delay} ms"),
+                );
+            }
+        }
 
         // Parse and cache the received message.
-        let d = MessageCached::new(self.index, d);
+        let mut d = MessageCached::new(self.index, msg);
+        d.origin = origin;
+        if origin != Some(GatewaySide::B) && self.tx_log.take_echo(&msg) {
+            d.direction = Direction::Tx;
+            self.tx_bits
+                .fetch_add(counted_bits(msg.dlc), Ordering::Relaxed);
+        }
         self.index += 1;
+        self.record_frame(&d);
+        if let Some(stream) = self.stream.as_ref().filter(|stream| stream.receiver_count() > 0) {
+            let _ = stream.send(Arc::new(d.clone()));
+        }
 
-        // Add the new message to the state, ensuring the state does not exceed the max size.
-        while self.state.data.len() > MAX_MESSAGES_IN_STATE {
-            self.state.data.pop_front();
+        // Hand the message to the GUI, counting it if the GUI can't keep up.
+        let index = d.index;
+        if self.data_sender.try_send(d).is_err() {
+            self.state.dropped += 1;
+            self.lost += 1;
+            return;
+        }
+        if self.lost > 0 {
+            self.push_session_marker_at(index, format!("--- {} messages lost ---", self.lost));
+            self.lost = 0;
+        }
+        if let Some(ctx) = self.repaint.get() {
+            ctx.request_repaint();
         }
-        self.state.data.push_back(d);
     }
 
     /// Handles write commands to send CAN messages.
-    async fn handle_write_command(&mut self, cmd: WriteCommand) {
-        match cmd {
-            WriteCommand::SendSync => {
-                if let Err(e) = self.co.send_sync().await {
-                    log::error!("Failed to send SYNC message: {:?}", e);
-                } else {
-                    log::info!("SYNC message sent successfully");
-                }
+    async fn handle_write_command(&mut self, cmd: WriteCommand) -> WriteResult {
+        if cmd.is_bulk() {
+            self.pacer.begin_bulk(format!("{cmd:?}"));
+        }
+
+        let result = match &cmd {
+            WriteCommand::SendSync => self
+                .co
+                .send_sync()
+                .await
+                .map(|()| WriteOutcome::Sent)
+                .map_err(|e| WriteError::Transmit(format!("{e:?}"))),
+            WriteCommand::SendNmt { node_id, command } => self.send_nmt(*node_id, *command).await,
+            WriteCommand::SendRaw { cob_id, data } | WriteCommand::SendPdo { cob_id, data } => {
+                let cob_id_u16 = (cob_id & 0x7FF) as u16;
+                self.transmit(cob_id_u16, data.clone()).await
             }
-            WriteCommand::SendNmt { node_id, command } => {
-                let nmt_cmd = NmtCommand::new(command, node_id);
-                if let Err(e) = self.co.send_nmt(nmt_cmd).await {
-                    log::error!("Failed to send NMT message: {:?}", e);
-                } else {
-                    log::info!("NMT message sent successfully: {:?} to node {}", command, node_id);
+            WriteCommand::SendSdoDownload { node_id, index, subindex, data } => {
+                self.sdo_download(*node_id, *index, *subindex, data).await
+            }
+            WriteCommand::SendSdoUpload { node_id, index, subindex }
+            | WriteCommand::ScanUpload { node_id, index, subindex } => self
+                .sdo_upload(*node_id, *index, *subindex)
+                .await
+                .map(WriteOutcome::SdoData),
+            WriteCommand::ConfigureTpdo1Statusword { node_id } => {
+                self.configure_tpdo1_statusword(*node_id).await
+            }
+            WriteCommand::ReadIdentity { node_id } => {
+                let identity = self.read_identity(*node_id).await;
+                self.state
+                    .identities
+                    .insert(*node_id, identity.clone().map_err(|e| e.to_string()));
+                identity.map(WriteOutcome::Identity)
+            }
+            WriteCommand::ReadDeviceInfo { node_id } => {
+                let info = self.read_device_info(*node_id).await;
+                if let Ok(DeviceInfo {
+                    identity: Some(identity),
+                    ..
+                }) = &info
+                {
+                    self.state.identities.insert(*node_id, Ok(*identity));
                 }
+                self.state
+                    .device_infos
+                    .insert(*node_id, info.clone().map_err(|e| e.to_string()));
+                info.map(|info| WriteOutcome::DeviceInfo(Box::new(info)))
             }
-            WriteCommand::SendRaw { cob_id, data } => {
-                let cob_id_u16 = (cob_id & 0x7FF) as u16;
-                let packet = TxPacket { cob_id: cob_id_u16, data };
-                if let Err(e) = self.co.tx.send(packet).await {
-                    log::error!("Failed to send raw CAN message: {:?}", e);
+            WriteCommand::ReadPdoConfig { node_id } => {
+                let config = self.read_pdo_config(*node_id).await;
+                self.state
+                    .pdo_configs
+                    .insert(*node_id, config.clone().map_err(|e| e.to_string()));
+                config.map(|config| WriteOutcome::PdoConfig(Box::new(config)))
+            }
+            WriteCommand::WritePdoConfig { node_id, parameters } => {
+                self.write_pdo_config(*node_id, parameters).await
+            }
+            WriteCommand::AddPeriodic {
+                id,
+                cob_id,
+                data,
+                period,
+            } => self.add_periodic(*id, *cob_id, data, *period),
+            WriteCommand::RemovePeriodic { id } => {
+                if self.periodic.remove(*id) {
+                    Ok(WriteOutcome::Done)
                 } else {
-                    log::info!("Raw CAN message sent successfully: COB-ID=0x{:03X}", cob_id);
+                    Err(WriteError::Unsupported(format!("no periodic entry {id}")))
                 }
             }
-            WriteCommand::SendPdo { cob_id, data } => {
-                let cob_id_u16 = (cob_id & 0x7FF) as u16;
-                let packet = TxPacket { cob_id: cob_id_u16, data };
-                if let Err(e) = self.co.tx.send(packet).await {
-                    log::error!("Failed to send PDO message: {:?}", e);
-                } else {
-                    log::info!("PDO message sent successfully: COB-ID=0x{:03X}", cob_id);
+        };
+
+        match &result {
+            Ok(outcome) => log::info!("{cmd:?}: {outcome}"),
+            Err(e) => log::error!("{cmd:?} failed: {e}"),
+        }
+        if let Some(throughput) = self.pacer.end_bulk() {
+            log::info!("{throughput}");
+            self.state.last_bulk = Some(throughput);
+        }
+        result
+    }
+
+    /// Configures TPDO1 to send the Statusword on every SYNC.
+    ///
+    /// Every SDO write is checked and retried once, the sequence stops at the first failing step.
+    async fn configure_tpdo1_statusword(&mut self, node_id: u8) -> WriteResult {
+        log::info!("Configuring TPDO1 for Statusword (0x6041) on node {}", node_id);
+        let step = |step: u8| move |e: WriteError| WriteError::Step(step, Box::new(e));
+
+        // Étape 1: NMT Pre-Operational, vérifié par le heartbeat du noeud
+        self.send_nmt(node_id, NmtCommandSpecifier::EnterPreOperational)
+            .await
+            .map_err(step(1))?;
+        self.wait_for_nmt_state(node_id, NmtState::PreOperational)
+            .await
+            .map_err(step(1))?;
+
+        // Étape 2: Désactiver TPDO1 (COB-ID avec bit 31 = 1)
+        let cob_id_disabled = 0x8000_0180_u32 + u32::from(node_id);
+        self.sdo_download_retry(node_id, 0x1800, 0x01, &cob_id_disabled.to_le_bytes())
+            .await
+            .map_err(step(2))?;
+
+        // Étape 3: Effacer le mapping (mettre le nombre d'objets à 0)
+        self.sdo_download_retry(node_id, 0x1A00, 0x00, &[0x00])
+            .await
+            .map_err(step(3))?;
+
+        // Étape 4: Configurer le mapping pour Statusword (0x6041, 32 bits)
+        // Format: 0xIIIISSLL (Index + Subindex + Length en bits)
+        let mapping: u32 = 0x6041_0020; // 0x6041 subindex 0x00, 32 bits (0x20)
+        self.sdo_download_retry(node_id, 0x1A00, 0x01, &mapping.to_le_bytes())
+            .await
+            .map_err(step(4))?;
+
+        // Étape 5: Activer le mapping (1 objet mappé)
+        self.sdo_download_retry(node_id, 0x1A00, 0x00, &[0x01])
+            .await
+            .map_err(step(5))?;
+
+        // Étape 6: Activer TPDO1 (COB-ID sans bit 31)
+        let cob_id_enabled = 0x0000_0180_u32 + u32::from(node_id);
+        self.sdo_download_retry(node_id, 0x1800, 0x01, &cob_id_enabled.to_le_bytes())
+            .await
+            .map_err(step(6))?;
+
+        // Étape 7: NMT Operational
+        self.send_nmt(node_id, NmtCommandSpecifier::StartRemoteNode)
+            .await
+            .map_err(step(7))?;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        // Étape 8: Configurer le type de transmission (0x01 = SYNC cyclique à chaque SYNC)
+        self.sdo_download_retry(node_id, 0x1800, 0x02, &[0x01])
+            .await
+            .map_err(step(8))
+    }
+
+    /// Waits for a heartbeat of the node reporting the given state.
+    async fn wait_for_nmt_state(&mut self, node_id: u8, state: NmtState) -> WriteResult {
+        let heartbeat_cob_id = 0x700 + u16::from(node_id);
+        let deadline = Instant::now() + HEARTBEAT_TIMEOUT;
+        self.wait_for_frame(deadline, |msg| {
+            (msg.cob_id == heartbeat_cob_id
+                && msg.dlc >= 1
+                && Heartbeat::from_byte(msg.data[0]).state == state)
+                .then_some(WriteOutcome::Sent)
+        })
+        .await
+        .ok_or(WriteError::NmtState(state))
+    }
+
+    /// Starts sending a frame every `period`, refused for an extended COB-ID.
+    fn add_periodic(&mut self, id: u32, cob_id: u32, data: &[u8], period: Duration) -> WriteResult {
+        if !self.control.command.transmits() {
+            return Err(WriteError::Unsupported(
+                "periodic transmission is not available while disconnected".to_owned(),
+            ));
+        }
+        if period.is_zero() {
+            return Err(WriteError::Unsupported("period must not be zero".to_owned()));
+        }
+        let Some(cob_id) = u16::try_from(cob_id).ok().filter(|cob_id| *cob_id <= 0x7FF) else {
+            return Err(WriteError::Unsupported(format!(
+                "COB-ID 0x{cob_id:X} is not an 11-bit identifier"
+            )));
+        };
+        self.periodic
+            .add(self.co.tx.clone(), id, cob_id, data.to_vec(), period);
+        Ok(WriteOutcome::Done)
+    }
+
+    async fn send_nmt(&mut self, node_id: u8, command: NmtCommandSpecifier) -> WriteResult {
+        self.pacer.pace().await;
+        self.co
+            .send_nmt(NmtCommand::new(command, node_id))
+            .await
+            .map(|()| WriteOutcome::Sent)
+            .map_err(|e| WriteError::Transmit(format!("{e:?}")))
+    }
+
+    async fn transmit(&mut self, cob_id: u16, data: Vec<u8>) -> WriteResult {
+        self.pacer.pace().await;
+        self.co
+            .tx
+            .send(TxPacket { cob_id, data })
+            .await
+            .map(|()| WriteOutcome::Sent)
+            .map_err(|e| WriteError::Transmit(e.to_string()))
+    }
+
+    async fn sdo_download(&mut self, node_id: u8, index: u16, subindex: u8, data: &[u8]) -> WriteResult {
+        let Some(request) = sdo::download_request(index, subindex, data) else {
+            return Err(WriteError::Unsupported(format!(
+                "SDO segmented transfer not implemented yet. Data size: {} bytes",
+                data.len()
+            )));
+        };
+
+        match self.sdo_transfer(node_id, request, index, subindex).await? {
+            SdoReply::Downloaded => Ok(WriteOutcome::SdoConfirmed),
+            reply => Err(Self::unexpected_reply(reply, index, subindex)),
+        }
+    }
+
+    /// Same as [`Self::sdo_download`] but retries once on failure.
+    async fn sdo_download_retry(&mut self, node_id: u8, index: u16, subindex: u8, data: &[u8]) -> WriteResult {
+        match self.sdo_download(node_id, index, subindex, data).await {
+            Err(e) => {
+                log::warn!("SDO write 0x{index:04X}:{subindex:02X} to node {node_id} failed ({e}), retrying");
+                self.sdo_download(node_id, index, subindex, data).await
+            }
+            ok => ok,
+        }
+    }
+
+    async fn sdo_upload(&mut self, node_id: u8, index: u16, subindex: u8) -> Result<Vec<u8>, WriteError> {
+        let request = sdo::upload_request(index, subindex);
+        match self.sdo_transfer(node_id, request, index, subindex).await? {
+            SdoReply::Uploaded(data) => Ok(data),
+            SdoReply::Segmented => self.sdo_upload_segments(node_id, index, subindex).await,
+            reply => Err(Self::unexpected_reply(reply, index, subindex)),
+        }
+    }
+
+    /// Requests the segments of an upload until the last one, up to [`sdo::MAX_UPLOAD_LEN`].
+    async fn sdo_upload_segments(&mut self, node_id: u8, index: u16, subindex: u8) -> Result<Vec<u8>, WriteError> {
+        let response_cob_id = 0x580 + u16::from(node_id);
+        let mut data = Vec::new();
+        let mut toggle = false;
+        loop {
+            self.transmit(0x600 + u16::from(node_id), sdo::upload_segment_request(toggle))
+                .await?;
+            let deadline = Instant::now() + sdo::SDO_TIMEOUT;
+            let reply = self
+                .wait_for_frame(deadline, |msg| {
+                    if msg.cob_id == response_cob_id {
+                        sdo::parse_segment(&msg.data, toggle)
+                    } else {
+                        None
+                    }
+                })
+                .await
+                .ok_or(WriteError::Timeout)?;
+            match reply {
+                SegmentReply::Data { data: segment, last } => {
+                    data.extend_from_slice(&segment);
+                    if last {
+                        return Ok(data);
+                    }
                 }
+                SegmentReply::Abort(code) => return Err(WriteError::SdoAbort { index, subindex, code }),
             }
-            WriteCommand::SendSdoDownload { node_id, index, subindex, data } => {
-                self.send_sdo_download(node_id, index, subindex, &data).await;
+            if data.len() > sdo::MAX_UPLOAD_LEN {
+                return Err(WriteError::Unsupported(format!(
+                    "SDO upload of 0x{index:04X}:{subindex:02X} longer than {} bytes",
+                    sdo::MAX_UPLOAD_LEN
+                )));
             }
-            WriteCommand::ConfigureTpdo1Statusword { node_id } => {
-                log::info!("Configuring TPDO1 for Statusword (0x6041) on node {}", node_id);
-                
-                // Étape 1: NMT Pre-Operational
-                let nmt_pre_op = NmtCommand::new(NmtCommandSpecifier::EnterPreOperational, node_id);
-                if let Err(e) = self.co.send_nmt(nmt_pre_op).await {
-                    log::error!("Failed to send NMT Pre-Operational: {:?}", e);
+            toggle = !toggle;
+        }
+    }
+
+    fn unexpected_reply(reply: SdoReply, index: u16, subindex: u8) -> WriteError {
+        match reply {
+            SdoReply::Abort(code) => WriteError::SdoAbort { index, subindex, code },
+            reply => WriteError::Unsupported(format!("unexpected SDO response {reply:?}")),
+        }
+    }
+
+    /// Sends an SDO request and waits for the answer of the server.
+    async fn sdo_transfer(
+        &mut self,
+        node_id: u8,
+        request: Vec<u8>,
+        index: u16,
+        subindex: u8,
+    ) -> Result<SdoReply, WriteError> {
+        self.transmit(0x600 + u16::from(node_id), request).await?;
+
+        let response_cob_id = 0x580 + u16::from(node_id);
+        let deadline = Instant::now() + sdo::SDO_TIMEOUT;
+        self.wait_for_frame(deadline, |msg| {
+            if msg.cob_id == response_cob_id {
+                sdo::parse_reply(&msg.data, index, subindex)
+            } else {
+                None
+            }
+        })
+        .await
+        .ok_or(WriteError::Timeout)
+    }
+
+    /// Waits until `matches` accepts a received frame, `None` if the deadline passes first.
+    ///
+    /// Frames received in the meantime are handed to the GUI as usual.
+    async fn wait_for_frame<T>(
+        &mut self,
+        deadline: Instant,
+        mut matches: impl FnMut(&RxMessage) -> Option<T>,
+    ) -> Option<T> {
+        loop {
+            let event = tokio::select! {
+                event = self.rx.recv() => event,
+                msg = Self::recv_peer(&mut self.gateway) => {
+                    self.on_frame(msg, Some(GatewaySide::B));
+                    continue;
                 }
-                tokio::time::sleep(Duration::from_millis(50)).await;
-                
-                // Étape 2: Désactiver TPDO1 (COB-ID avec bit 31 = 1)
-                let cob_id_disabled = 0x80000180u32 + u32::from(node_id);
-                self.send_sdo_download(node_id, 0x1800, 0x01, &cob_id_disabled.to_le_bytes().to_vec()).await;
-                tokio::time::sleep(Duration::from_millis(10)).await;
-                
-                // Étape 3: Effacer le mapping (mettre le nombre d'objets à 0)
-                self.send_sdo_download(node_id, 0x1A00, 0x00, &[0x00]).await;
-                tokio::time::sleep(Duration::from_millis(10)).await;
-                
-                // Étape 4: Configurer le mapping pour Statusword (0x6041, 32 bits)
-                // Format: 0xIIIISSLL (Index + Subindex + Length en bits)
-                let mapping: u32 = 0x60410020; // 0x6041 subindex 0x00, 32 bits (0x20)
-                self.send_sdo_download(node_id, 0x1A00, 0x01, &mapping.to_le_bytes().to_vec()).await;
-                tokio::time::sleep(Duration::from_millis(10)).await;
-                
-                // Étape 5: Activer le mapping (1 objet mappé)
-                self.send_sdo_download(node_id, 0x1A00, 0x00, &[0x01]).await;
-                tokio::time::sleep(Duration::from_millis(10)).await;
-                
-                // Étape 6: Activer TPDO1 (COB-ID sans bit 31)
-                let cob_id_enabled = 0x00000180u32 + u32::from(node_id);
-                self.send_sdo_download(node_id, 0x1800, 0x01, &cob_id_enabled.to_le_bytes().to_vec()).await;
-                tokio::time::sleep(Duration::from_millis(10)).await;
-                
-                // Étape 7: NMT Operational
-                let nmt_op = NmtCommand::new(NmtCommandSpecifier::StartRemoteNode, node_id);
-                if let Err(e) = self.co.send_nmt(nmt_op).await {
-                    log::error!("Failed to send NMT Operational: {:?}", e);
+                () = sleep_until(deadline) => return None,
+            };
+
+            let found = match &event {
+                RxEvent::Frame(msg) => matches(msg),
+                _ => None,
+            };
+            self.on_rx_event(event);
+            if found.is_some() {
+                return found;
+            }
+        }
+    }
+
+    /// Reads the identity object (0x1018) of a node, only the vendor ID is mandatory.
+    async fn read_identity(&mut self, node_id: u8) -> Result<NodeIdentity, WriteError> {
+        let mut values = [None; 4];
+        for (subindex, value) in (1..).zip(&mut values) {
+            match self.sdo_upload(node_id, 0x1018, subindex).await {
+                Ok(data) => {
+                    let mut bytes = [0u8; 4];
+                    let len = data.len().min(4);
+                    bytes[..len].copy_from_slice(&data[..len]);
+                    *value = Some(u32::from_le_bytes(bytes));
                 }
-                tokio::time::sleep(Duration::from_millis(50)).await;
-                
-                // Étape 8: Configurer le type de transmission (0x01 = SYNC cyclique à chaque SYNC)
-                self.send_sdo_download(node_id, 0x1800, 0x02, &[0x01]).await;
-                
-                log::info!("TPDO1 configured successfully for node {}", node_id);
-            }
-        }
-    }
-    
-    async fn send_sdo_download(&mut self, node_id: u8, index: u16, subindex: u8, data: &[u8]) {
-        let sdo_tx_cob_id = 0x600 + u16::from(node_id);
-        let mut sdo_data = Vec::with_capacity(8);
-        
-        // SDO Download Expedited (for data <= 4 bytes)
-        if data.len() <= 4 {
-            // Command byte: 0x23 = Initiate download expedited, 4 bytes specified
-            let n = (4 - data.len()) as u8;
-            let ccs = 0x20 | (n << 2) | 0x03; // Expedited + size indicated + size
-            
-            sdo_data.push(ccs);
-            sdo_data.extend_from_slice(&index.to_le_bytes());
-            sdo_data.push(subindex);
-            sdo_data.extend_from_slice(data);
-            // Pad to 8 bytes
-            while sdo_data.len() < 8 {
-                sdo_data.push(0);
+                Err(e) if subindex == 1 => return Err(e),
+                Err(_) => {}
             }
-        } else {
-            log::error!("SDO segmented transfer not implemented yet. Data size: {} bytes", data.len());
-            return;
         }
-        
-        let packet = TxPacket { cob_id: sdo_tx_cob_id, data: sdo_data };
-        if let Err(e) = self.co.tx.send(packet).await {
-            log::error!("Failed to send SDO Download: {:?}", e);
-        } else {
-            log::info!("SDO Download sent to node {}: index=0x{:04X}, subindex=0x{:02X}, data={:02X?}", 
-                node_id, index, subindex, data);
+
+        Ok(NodeIdentity {
+            vendor_id: values[0].unwrap_or_default(),
+            product_code: values[1],
+            revision: values[2],
+            serial: values[3],
+        })
+    }
+
+    /// Reads the device type (0x1000), identity (0x1018) and name strings (0x1008-0x100A) of a
+    /// node, only the device type is mandatory.
+    async fn read_device_info(&mut self, node_id: u8) -> Result<DeviceInfo, WriteError> {
+        let data = self.sdo_upload(node_id, 0x1000, 0).await?;
+        let mut bytes = [0u8; 4];
+        let len = data.len().min(4);
+        bytes[..len].copy_from_slice(&data[..len]);
+        let identity = self.read_identity(node_id).await.ok();
+        let mut strings = [None, None, None];
+        for (index, value) in (0x1008..).zip(&mut strings) {
+            *value = self
+                .sdo_upload(node_id, index, 0)
+                .await
+                .ok()
+                .map(|data| device_info::visible_string(&data));
+        }
+        let [name, hardware_version, software_version] = strings;
+        Ok(DeviceInfo {
+            device_type: u32::from_le_bytes(bytes),
+            identity,
+            name,
+            hardware_version,
+            software_version,
+            read_at: Local::now().to_rfc3339_opts(SecondsFormat::Secs, false),
+        })
+    }
+
+    /// Reads the parameters of the first four RPDOs and TPDOs of a node. The PDOs the node
+    /// doesn't implement are reported with the abort, only a node that doesn't answer at all
+    /// fails the read.
+    async fn read_pdo_config(&mut self, node_id: u8) -> Result<PdoConfig, WriteError> {
+        let mut pdos = Vec::new();
+        for pdo in PdoId::ALL {
+            match self.read_pdo(node_id, pdo).await {
+                Ok(parameters) => pdos.push((pdo, Ok(parameters))),
+                Err(WriteError::Timeout) if pdos.is_empty() => return Err(WriteError::Timeout),
+                Err(e) => pdos.push((pdo, Err(e.to_string()))),
+            }
+        }
+        Ok(PdoConfig {
+            pdos,
+            read_at: Local::now().to_rfc3339_opts(SecondsFormat::Secs, false),
+        })
+    }
+
+    /// Reads the communication and mapping parameters of a PDO, the transmission type, inhibit
+    /// time and event timer are optional.
+    async fn read_pdo(&mut self, node_id: u8, pdo: PdoId) -> Result<PdoParameters, WriteError> {
+        let communication = pdo.communication_index();
+        let cob_id = pdo_config::le_value(&self.sdo_upload(node_id, communication, 1).await?);
+        let mut optional = [None; 3];
+        for (subindex, value) in [2, 3, 5].into_iter().zip(&mut optional) {
+            *value = self
+                .sdo_upload(node_id, communication, subindex)
+                .await
+                .ok()
+                .map(|data| pdo_config::le_value(&data));
+        }
+        let [transmission_type, inhibit_time, event_timer] = optional;
+
+        let mapping_index = pdo.mapping_index();
+        let count = self.sdo_upload(node_id, mapping_index, 0).await?;
+        let count = count.first().copied().unwrap_or_default();
+        let mut mapping = Vec::new();
+        for subindex in (1..=count).take(pdo_config::MAX_MAPPED) {
+            let entry = self.sdo_upload(node_id, mapping_index, subindex).await?;
+            mapping.push(MappedObject::from(pdo_config::le_value(&entry)));
+        }
+        #[allow(clippy::cast_possible_truncation)]
+        Ok(PdoParameters {
+            pdo,
+            cob_id,
+            transmission_type: transmission_type.map(|value| value as u8),
+            inhibit_time: inhibit_time.map(|value| value as u16),
+            event_timer: event_timer.map(|value| value as u16),
+            mapping,
+        })
+    }
+
+    /// Writes the parameters of a PDO: disables it, writes the communication parameters given,
+    /// the mapping and its count, then enables it if asked. Most nodes only accept it while
+    /// pre-operational.
+    async fn write_pdo_config(&mut self, node_id: u8, parameters: &PdoParameters) -> WriteResult {
+        let step = |step: u8| move |e: WriteError| WriteError::Step(step, Box::new(e));
+        let communication = parameters.pdo.communication_index();
+        let mapping_index = parameters.pdo.mapping_index();
+
+        let disabled = parameters.cob_id | PDO_DISABLED;
+        self.sdo_download_retry(node_id, communication, 1, &disabled.to_le_bytes())
+            .await
+            .map_err(step(1))?;
+        if let Some(transmission_type) = parameters.transmission_type {
+            self.sdo_download_retry(node_id, communication, 2, &[transmission_type])
+                .await
+                .map_err(step(2))?;
+        }
+        if let Some(inhibit_time) = parameters.inhibit_time {
+            self.sdo_download_retry(node_id, communication, 3, &inhibit_time.to_le_bytes())
+                .await
+                .map_err(step(3))?;
+        }
+        if let Some(event_timer) = parameters.event_timer {
+            self.sdo_download_retry(node_id, communication, 5, &event_timer.to_le_bytes())
+                .await
+                .map_err(step(4))?;
+        }
+
+        self.sdo_download_retry(node_id, mapping_index, 0, &[0])
+            .await
+            .map_err(step(5))?;
+        for (subindex, object) in (1..).zip(&parameters.mapping) {
+            self.sdo_download_retry(node_id, mapping_index, subindex, &object.raw().to_le_bytes())
+                .await
+                .map_err(step(6))?;
+        }
+        #[allow(clippy::cast_possible_truncation)]
+        let count = parameters.mapping.len() as u8;
+        self.sdo_download_retry(node_id, mapping_index, 0, &[count])
+            .await
+            .map_err(step(7))?;
+
+        if parameters.enabled() {
+            self.sdo_download_retry(node_id, communication, 1, &parameters.cob_id.to_le_bytes())
+                .await
+                .map_err(step(8))?;
+        }
+        Ok(WriteOutcome::SdoConfirmed)
+    }
+
+    /// Stops the periodic transmissions and the gateway, sends the exit NMT and flushes the
+    /// recording, before the driver exits.
+    async fn shutdown(&mut self) {
+        self.periodic.clear();
+        self.state.periodic.clear();
+        self.gateway = None;
+        if let Some(command) = self.control.exit_nmt {
+            if self.state.link == LinkState::Connected {
+                if let Err(e) = self.send_nmt(0, command).await {
+                    log::warn!("Can't send the exit NMT: {e}");
+                }
+            }
+        }
+        if let Some(recorder) = self.recorder.take() {
+            let path = recorder.path().to_owned();
+            if let Err(e) = recorder.finish() {
+                log::error!("Can't flush the recording to {path}: {e}");
+            }
+            self.state.recording = None;
         }
     }
 
     /// Asynchronously runs the driver, continuously processing messages and sending state updates.
     async fn run(&mut self) {
-        self.state.data.reserve_exact(MAX_MESSAGES_IN_STATE);
         loop {
             self.process().await;
-            if self.control.command == ControlCommand::Kill {
+            let killed = self.control.command == ControlCommand::Kill;
+            if killed {
+                self.shutdown().await;
                 self.state.exit_signal = true;
             }
 
-            self.sender.send(self.state.clone()).unwrap();
-            // Exit the loop if a Kill command is received.
-            if self.control.command == ControlCommand::Kill {
+            // Publish at most every `STATE_PUBLISH_PERIOD`, the exit signal right away.
+            let now = Instant::now();
+            if !killed && now < self.next_publish {
+                self.unpublished = true;
+                continue;
+            }
+            self.next_publish = now + STATE_PUBLISH_PERIOD;
+            self.unpublished = false;
+
+            // Exit the loop if a Kill command is received or the GUI is gone.
+            if self.sender.send(self.state.clone()).is_err() {
+                self.shutdown().await;
+                break;
+            }
+            if killed {
                 break;
             }
         }
@@ -288,3 +1482,95 @@ impl Driver {
         })
     }
 }
+
+/// GUI ends of the channels of a driver started by [`spawn`].
+pub struct DriverChannels {
+    pub state: watch::Receiver<State>,
+    pub data: mpsc::Receiver<MessageCached>,
+    pub control: watch::Sender<Control>,
+    pub write: mpsc::Sender<WriteRequest>,
+    pub bitrates: watch::Receiver<RatesData>,
+    pub events: mpsc::Receiver<Notification>,
+}
+
+/// Starts a driver and its bitrate sampler on `runtime`, connecting to the interface of `control`
+/// if it names one. Dropping the control sender kills the driver.
+pub fn spawn(
+    runtime: &Handle,
+    control: Control,
+    data_capacity: usize,
+    repaint: Arc<OnceLock<egui::Context>>,
+    recorder: Option<Recorder>,
+    stream: Option<broadcast::Sender<Arc<MessageCached>>>,
+) -> DriverChannels {
+    let _enter = runtime.enter();
+    let (state_snd, state) = watch::channel(State::default());
+    let (data_snd, data) = mpsc::channel(data_capacity);
+    let (control_snd, control_rcv) = watch::channel(control.clone());
+    let (write, write_rcv) = mpsc::channel(100);
+    let (bitrates_snd, bitrates) = watch::channel(RatesData::default());
+    let (events_snd, events) = mpsc::channel(EVENT_CHANNEL_CAPACITY);
+
+    let mut driver = Driver::new(state_snd, data_snd, control_rcv, write_rcv);
+    driver.repaint_on_data(repaint);
+    driver.notify_to(events_snd);
+    if let Some(recorder) = recorder {
+        driver.record(recorder);
+    }
+    if let Some(stream) = stream {
+        driver.stream(stream);
+    }
+    let bitrate = Bitrate::new(driver.co.info.clone(), driver.tx_bits.clone(), bitrates_snd);
+    driver.start_thread();
+    bitrate.start_thread();
+
+    if !control.connection.can_name.is_empty() {
+        println!(
+            "Use args: {:?} {:?}",
+            control.connection.can_name, control.connection.bitrate
+        );
+        control_snd.send_replace(control);
+    }
+    DriverChannels {
+        state,
+        data,
+        control: control_snd,
+        write,
+        bitrates,
+        events,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Control, ControlCommand};
+    use oze_canopen::interface::Connection;
+
+    #[test]
+    fn test_stops_periodic() {
+        let connection = Connection {
+            can_name: "can0".to_owned(),
+            bitrate: None,
+        };
+        let mut control = Control {
+            command: ControlCommand::Process,
+            connection: connection.clone(),
+            kernel_filters: Vec::new(),
+            tx_rate: None,
+            gateway: None,
+            exit_nmt: None,
+            auto_start: None,
+        };
+        assert!(!control.stops_periodic(&connection));
+        // Pausing the viewer keeps the periodic table.
+        control.command = ControlCommand::Stop;
+        assert!(!control.stops_periodic(&connection));
+        control.command = ControlCommand::Disconnect;
+        assert!(control.stops_periodic(&connection));
+        control.command = ControlCommand::Kill;
+        assert!(control.stops_periodic(&connection));
+        control.command = ControlCommand::Process;
+        control.connection.can_name = "can1".to_owned();
+        assert!(control.stops_periodic(&connection));
+    }
+}
@@ -0,0 +1,189 @@
+use crate::theme::Status;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// How long the drop counters stay highlighted after they increased.
+const INCREASE_HIGHLIGHT: Duration = Duration::from_secs(5);
+
+/// Receive drop counters of a network interface, from `/sys/class/net/<if>/statistics`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct InterfaceDrops {
+    /// Frames dropped by the kernel, e.g. because a socket receive queue was full.
+    pub rx_dropped: u64,
+    /// Frames lost because the controller or driver buffer overflowed.
+    pub rx_over_errors: u64,
+    /// Frames lost in the controller FIFO.
+    pub rx_fifo_errors: u64,
+}
+
+impl InterfaceDrops {
+    /// Reads the counters of the interface, `None` if it doesn't exist.
+    pub fn read(can_name: &str) -> Option<Self> {
+        if can_name.is_empty() {
+            return None;
+        }
+        let read = |name: &str| -> Option<u64> {
+            std::fs::read_to_string(format!("/sys/class/net/{can_name}/statistics/{name}"))
+                .ok()?
+                .trim()
+                .parse()
+                .ok()
+        };
+        Some(Self {
+            rx_dropped: read("rx_dropped")?,
+            rx_over_errors: read("rx_over_errors").unwrap_or_default(),
+            rx_fifo_errors: read("rx_fifo_errors").unwrap_or_default(),
+        })
+    }
+
+    pub fn total(&self) -> u64 {
+        self.rx_dropped + self.rx_over_errors + self.rx_fifo_errors
+    }
+}
+
+/// Counts the frames lost since the start of the session or the last reset.
+#[derive(Debug, Default)]
+pub struct DropMonitor {
+    kernel_base: Option<u64>,
+    kernel: Option<InterfaceDrops>,
+    channel_base: u64,
+    channel: u64,
+    /// Oldest messages dropped from the full message list since the reset.
+    trimmed: u64,
+    last_total: u64,
+    last_increase: Option<Instant>,
+}
+
+impl DropMonitor {
+    /// Updates the counters with the absolute values reported by the driver.
+    pub fn set(&mut self, kernel: Option<InterfaceDrops>, channel: u64) {
+        let kernel_total = kernel.map(|k| k.total());
+        // The kernel counters restart from zero when the interface reappears.
+        if kernel_total < self.kernel_base || self.kernel_base.is_none() {
+            self.kernel_base = kernel_total;
+        }
+        self.kernel = kernel;
+        self.channel = channel;
+
+        let total = self.kernel_delta() + self.channel_delta();
+        if total > self.last_total {
+            self.last_increase = Some(Instant::now());
+        }
+        self.last_total = total;
+    }
+
+    /// Counts messages dropped from the message list to keep it within its size.
+    pub fn add_trimmed(&mut self, count: u64) {
+        self.trimmed += count;
+    }
+
+    /// Messages lost for any reason since the reset.
+    pub fn total(&self) -> u64 {
+        self.kernel_delta() + self.channel_delta() + self.trimmed
+    }
+
+    /// Frames dropped by the kernel since the reset.
+    pub fn kernel_delta(&self) -> u64 {
+        let total = self.kernel.map(|k| k.total()).unwrap_or_default();
+        total.saturating_sub(self.kernel_base.unwrap_or(total))
+    }
+
+    /// Messages dropped on the way from the driver to the viewer since the reset.
+    pub fn channel_delta(&self) -> u64 {
+        self.channel.saturating_sub(self.channel_base)
+    }
+
+    /// Starts counting from the current values.
+    pub fn reset(&mut self) {
+        self.kernel_base = self.kernel.map(|k| k.total());
+        self.channel_base = self.channel;
+        self.trimmed = 0;
+        self.last_total = 0;
+        self.last_increase = None;
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        let recent = self
+            .last_increase
+            .is_some_and(|t| t.elapsed() < INCREASE_HIGHLIGHT);
+        // Trimming is expected once the list is full, only the losses on the way turn it red.
+        let color = if recent {
+            Status::Error.color()
+        } else if self.total() > 0 {
+            Status::Warning.color()
+        } else {
+            ui.visuals().text_color()
+        };
+
+        ui.colored_label(color, format!("drops: {}", self.total()))
+        .on_hover_ui(|ui| {
+            ui.label("Messages lost since the connection or the last reset:");
+            ui.label(format!(
+                "• kernel: {}, dropped by the CAN driver or the socket queues, reduce the bus load",
+                self.kernel_delta()
+            ));
+            ui.label(format!(
+                "• viewer: {}, the viewer couldn't keep up with the bus, raise the driver buffer",
+                self.channel_delta()
+            ));
+            ui.label(format!(
+                "• trimmed: {}, oldest messages dropped from the full list, raise the viewer messages",
+                self.trimmed
+            ));
+            ui.separator();
+            match self.kernel {
+                Some(k) => {
+                    ui.label(format!("Interface rx_dropped: {}", k.rx_dropped));
+                    ui.label(format!("Interface rx_over_errors: {}", k.rx_over_errors));
+                    ui.label(format!("Interface rx_fifo_errors: {}", k.rx_fifo_errors));
+                }
+                None => {
+                    ui.label("Interface statistics not available");
+                }
+            }
+            ui.label(format!("Viewer channel: {}", self.channel));
+        });
+        if ui
+            .small_button("↺")
+            .on_hover_text("Reset the drop counters")
+            .clicked()
+        {
+            self.reset();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DropMonitor, InterfaceDrops};
+
+    #[test]
+    fn test_drop_monitor() {
+        let drops = |rx_dropped| {
+            Some(InterfaceDrops {
+                rx_dropped,
+                ..Default::default()
+            })
+        };
+        let mut monitor = DropMonitor::default();
+        monitor.set(drops(10), 3);
+        assert_eq!(monitor.kernel_delta(), 0);
+        assert_eq!(monitor.channel_delta(), 3);
+
+        monitor.set(drops(15), 3);
+        assert_eq!(monitor.kernel_delta(), 5);
+
+        monitor.add_trimmed(100);
+        assert_eq!(monitor.total(), 108);
+
+        monitor.reset();
+        assert_eq!(monitor.kernel_delta(), 0);
+        assert_eq!(monitor.channel_delta(), 0);
+        assert_eq!(monitor.total(), 0);
+
+        // Interface recreated, its counters restart from zero.
+        monitor.set(drops(2), 4);
+        assert_eq!(monitor.kernel_delta(), 0);
+        assert_eq!(monitor.channel_delta(), 1);
+    }
+}
@@ -0,0 +1,280 @@
+use oze_canopen::canopen::NodeId;
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, PoisonError, RwLock},
+};
+
+/// EDS files loaded for naming the objects of the SDO transfers, by node ID.
+static LOADED: RwLock<BTreeMap<NodeId, Arc<Eds>>> = RwLock::new(BTreeMap::new());
+
+/// Object or sub-object of the dictionary.
+#[derive(Debug, Clone)]
+struct Entry {
+    name: String,
+    data_type: Option<u16>,
+}
+
+/// Object dictionary of a device, read from its EDS file.
+#[derive(Debug, Clone, Default)]
+pub struct Eds {
+    /// File the EDS was read from.
+    pub source: String,
+    /// Objects by index, with `None` for the object itself and the sub-index for its entries.
+    entries: BTreeMap<(u16, Option<u8>), Entry>,
+}
+
+impl Eds {
+    /// Parses the object sections (`[1017]`, `[1018sub1]`) of an EDS file, the others are
+    /// ignored.
+    pub fn parse(text: &str) -> Self {
+        let mut entries = BTreeMap::new();
+        let mut current = None;
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(';') {
+                continue;
+            }
+            if let Some(section) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                current = parse_section(section);
+                if let Some(key) = current {
+                    entries.insert(
+                        key,
+                        Entry {
+                            name: String::new(),
+                            data_type: None,
+                        },
+                    );
+                }
+                continue;
+            }
+            let (Some(key), Some((name, value))) = (current, line.split_once('=')) else {
+                continue;
+            };
+            let Some(entry) = entries.get_mut(&key) else {
+                continue;
+            };
+            let value = value.trim();
+            match name.trim().to_ascii_lowercase().as_str() {
+                "parametername" => value.clone_into(&mut entry.name),
+                "datatype" => entry.data_type = parse_number(value),
+                _ => {}
+            }
+        }
+        Self {
+            source: String::new(),
+            entries,
+        }
+    }
+
+    pub fn object_count(&self) -> usize {
+        self.entries.keys().filter(|(_, sub)| sub.is_none()).count()
+    }
+
+    /// Entry describing `index:subindex`, a variable object stands for its sub-index 0.
+    fn entry(&self, index: u16, subindex: u8) -> Option<&Entry> {
+        self.entries.get(&(index, Some(subindex))).or_else(|| {
+            (subindex == 0 && !self.has_subs(index))
+                .then(|| self.entries.get(&(index, None)))
+                .flatten()
+        })
+    }
+
+    fn has_subs(&self, index: u16) -> bool {
+        self.entries
+            .range((index, Some(0))..=(index, Some(u8::MAX)))
+            .next()
+            .is_some()
+    }
+
+    /// Name of `index:subindex`, prefixed by the name of the object for the entries of arrays
+    /// and records, e.g. `Identity Object: Vendor-ID`.
+    pub fn name(&self, index: u16, subindex: u8) -> Option<String> {
+        let entry = self.entry(index, subindex)?;
+        match self.entries.get(&(index, None)) {
+            Some(object) if self.has_subs(index) && !object.name.is_empty() => {
+                Some(format!("{}: {}", object.name, entry.name))
+            }
+            _ => Some(entry.name.clone()),
+        }
+    }
+
+    /// Formats a value of `index:subindex` after its data type, as an unsigned integer if the
+    /// type is unknown.
+    pub fn format_value(&self, index: u16, subindex: u8, data: &[u8]) -> String {
+        format_value(self.entry(index, subindex).and_then(|e| e.data_type), data)
+    }
+
+    /// Entries whose index starts with `text` or whose name contains it, as
+    /// `(index, subindex, name)`.
+    pub fn suggestions(&self, text: &str, limit: usize) -> Vec<(u16, u8, String)> {
+        let text = text.trim().to_lowercase();
+        self.entries
+            .keys()
+            .filter_map(|&(index, sub)| match sub {
+                Some(sub) => Some((index, sub)),
+                None => (!self.has_subs(index)).then_some((index, 0)),
+            })
+            .filter_map(|(index, sub)| Some((index, sub, self.name(index, sub)?)))
+            .filter(|(index, _, name)| {
+                format!("{index:04x}").starts_with(&text) || name.to_lowercase().contains(&text)
+            })
+            .take(limit)
+            .collect()
+    }
+}
+
+/// Key of an object section, `None` for the other sections.
+fn parse_section(section: &str) -> Option<(u16, Option<u8>)> {
+    let lower = section.to_ascii_lowercase();
+    let (index, sub) = match lower.split_once("sub") {
+        Some((index, sub)) => (index, Some(u8::from_str_radix(sub, 16).ok()?)),
+        None => (lower.as_str(), None),
+    };
+    if index.len() != 4 {
+        return None;
+    }
+    Some((u16::from_str_radix(index, 16).ok()?, sub))
+}
+
+/// Parses a number written in hex (`0x0007`) or decimal.
+fn parse_number(value: &str) -> Option<u16> {
+    match value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        Some(hex) => u16::from_str_radix(hex, 16).ok(),
+        None => value.parse().ok(),
+    }
+}
+
+/// Formats little endian data after a `CiA 301` data type.
+#[allow(clippy::cast_possible_wrap)]
+pub fn format_value(data_type: Option<u16>, data: &[u8]) -> String {
+    if data.is_empty() {
+        return String::new();
+    }
+    let mut bytes = [0; 8];
+    let len = data.len().min(8);
+    bytes[..len].copy_from_slice(&data[..len]);
+    let unsigned = u64::from_le_bytes(bytes);
+    match data_type {
+        // BOOLEAN
+        Some(0x0001) => (unsigned != 0).to_string(),
+        // INTEGER8, 16, 32, 24, 40, 48, 56, 64
+        Some(0x0002..=0x0004 | 0x0010 | 0x0012..=0x0015) => {
+            let shift = 64 - 8 * len;
+            (((unsigned << shift) as i64) >> shift).to_string()
+        }
+        // REAL32
+        Some(0x0008) if len == 4 => {
+            f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]).to_string()
+        }
+        // VISIBLE_STRING
+        Some(0x0009) => format!("\"{}\"", String::from_utf8_lossy(data)),
+        _ => unsigned.to_string(),
+    }
+}
+
+/// Reads and parses an EDS file.
+///
+/// # Errors
+/// If the file can't be read or has no object.
+pub fn load(path: &str) -> Result<Eds, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+    let mut eds = Eds::parse(&text);
+    if eds.entries.is_empty() {
+        return Err(format!("{path}: no object found"));
+    }
+    path.clone_into(&mut eds.source);
+    Ok(eds)
+}
+
+/// Sets the EDS of a node, `None` to forget it.
+pub fn set(node_id: NodeId, eds: Option<Eds>) {
+    let mut loaded = LOADED.write().unwrap_or_else(PoisonError::into_inner);
+    match eds {
+        Some(eds) => loaded.insert(node_id, Arc::new(eds)),
+        None => loaded.remove(&node_id),
+    };
+}
+
+/// EDS of a node.
+pub fn get(node_id: NodeId) -> Option<Arc<Eds>> {
+    LOADED
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(&node_id)
+        .cloned()
+}
+
+/// Loaded EDS files by node ID.
+pub fn loaded() -> Vec<(NodeId, Arc<Eds>)> {
+    LOADED
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .iter()
+        .map(|(node_id, eds)| (*node_id, eds.clone()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_value, Eds};
+
+    const EDS: &str = "[FileInfo]
+FileName=drive.eds
+
+[1017]
+ParameterName=Producer Heartbeat Time
+ObjectType=0x7
+DataType=0x0006
+AccessType=rw
+
+[1018]
+ParameterName=Identity Object
+ObjectType=0x9
+SubNumber=2
+
+[1018sub0]
+ParameterName=Highest sub-index supported
+DataType=0x0005
+
+[1018sub1]
+ParameterName=Vendor-ID
+DataType=0x0007
+
+[607A]
+ParameterName=Target position
+DataType=0x0004
+";
+
+    #[test]
+    fn test_eds() {
+        let eds = Eds::parse(EDS);
+        assert_eq!(eds.object_count(), 3);
+        assert_eq!(
+            eds.name(0x1017, 0).as_deref(),
+            Some("Producer Heartbeat Time")
+        );
+        assert_eq!(
+            eds.name(0x1018, 1).as_deref(),
+            Some("Identity Object: Vendor-ID")
+        );
+        assert_eq!(eds.name(0x1017, 1), None);
+        assert_eq!(eds.name(0x1018, 2), None);
+        assert_eq!(eds.name(0x2000, 0), None);
+
+        assert_eq!(eds.format_value(0x1017, 0, &[0xE8, 0x03]), "1000");
+        assert_eq!(eds.format_value(0x607A, 0, &[0xFF; 4]), "-1");
+        assert_eq!(format_value(Some(0x0008), &1.5f32.to_le_bytes()), "1.5");
+        assert_eq!(format_value(Some(0x0009), b"abc"), "\"abc\"");
+
+        let suggestions = eds.suggestions("10", 10);
+        assert_eq!(suggestions.len(), 3);
+        assert_eq!(
+            suggestions[0],
+            (0x1017, 0, "Producer Heartbeat Time".to_owned())
+        );
+        assert_eq!(eds.suggestions("vendor", 10)[0].1, 1);
+    }
+}
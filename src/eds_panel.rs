@@ -0,0 +1,78 @@
+use crate::eds;
+use egui::{DragValue, TextEdit};
+use oze_canopen::canopen::NodeId;
+
+/// Loads the EDS files naming the objects of the SDO transfers, one per node.
+#[derive(Debug)]
+pub struct EdsPanel {
+    node_id: NodeId,
+    path_raw: String,
+    /// Error of the last load.
+    error: Option<String>,
+}
+
+impl Default for EdsPanel {
+    fn default() -> Self {
+        Self {
+            node_id: 1,
+            path_raw: String::new(),
+            error: None,
+        }
+    }
+}
+
+impl EdsPanel {
+    /// Shows the panel, returns `true` if an EDS was loaded or unloaded and the messages must be
+    /// decoded again.
+    pub fn update(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            ui.label("Node:");
+            ui.add(DragValue::new(&mut self.node_id).range(1..=127));
+            ui.add(
+                TextEdit::singleline(&mut self.path_raw)
+                    .hint_text("path/to/device.eds")
+                    .desired_width(200.0),
+            );
+            if ui
+                .add_enabled(
+                    !self.path_raw.trim().is_empty(),
+                    egui::Button::new("Load EDS…"),
+                )
+                .on_hover_text("Name the objects of the SDO transfers of this node")
+                .clicked()
+            {
+                match eds::load(self.path_raw.trim()) {
+                    Ok(loaded) => {
+                        eds::set(self.node_id, Some(loaded));
+                        self.error = None;
+                        changed = true;
+                    }
+                    Err(e) => self.error = Some(e),
+                }
+            }
+        });
+        if let Some(error) = &self.error {
+            ui.colored_label(egui::Color32::RED, error);
+        }
+
+        let loaded = eds::loaded();
+        if loaded.is_empty() {
+            ui.label("No EDS loaded");
+        }
+        for (node_id, eds) in loaded {
+            ui.horizontal(|ui| {
+                if ui.button("❌").on_hover_text("Unload").clicked() {
+                    eds::set(node_id, None);
+                    changed = true;
+                }
+                ui.label(format!(
+                    "node {node_id}: {} ({} objects)",
+                    eds.source,
+                    eds.object_count()
+                ));
+            });
+        }
+        changed
+    }
+}
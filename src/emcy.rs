@@ -0,0 +1,269 @@
+use crate::{
+    emcy_codes,
+    theme::{Status, OZON_GRAY},
+};
+use chrono::{DateTime, Local};
+use egui::Color32;
+use oze_canopen::canopen::{NodeId, RxMessage};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fmt::{self, Write as _},
+};
+
+/// Emergency frame decoded by the driver.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Emergency {
+    /// Index of the message in the received stream.
+    pub index: u64,
+    pub time: DateTime<Local>,
+    pub node_id: NodeId,
+    pub code: u16,
+    /// Error register (0x1001) of the node.
+    pub register: u8,
+    /// Manufacturer specific error field.
+    pub data: [u8; 5],
+}
+
+impl Emergency {
+    /// Decodes an EMCY frame (COB-ID 0x081..=0x0FF), `None` for any other frame.
+    pub fn parse(index: u64, msg: &RxMessage) -> Option<Self> {
+        if !(0x081..=0x0FF).contains(&msg.cob_id) {
+            return None;
+        }
+
+        let mut bytes = [0u8; 8];
+        let len = msg.dlc.min(8);
+        bytes[..len].copy_from_slice(&msg.data[..len]);
+        Some(Self {
+            index,
+            time: Local::now(),
+            node_id: u8::try_from(msg.cob_id - 0x080).ok()?,
+            code: u16::from_le_bytes([bytes[0], bytes[1]]),
+            register: bytes[2],
+            data: [bytes[3], bytes[4], bytes[5], bytes[6], bytes[7]],
+        })
+    }
+
+    /// Error code 0x0000 signals that the node recovered from its errors.
+    pub fn is_reset(&self) -> bool {
+        self.code == 0
+    }
+
+    /// Text of the error code, its hexadecimal value if it is unknown.
+    pub fn description(&self) -> String {
+        emcy_codes::describe(self.code)
+            .map_or_else(|| format!("0x{:04X}", self.code), ToOwned::to_owned)
+    }
+
+    /// Names of the bits set in the error register.
+    pub fn register_text(&self) -> String {
+        emcy_codes::register_bits(self.register).join(", ")
+    }
+
+    pub fn data_hex(&self) -> String {
+        self.data
+            .iter()
+            .map(|b| format!("{b:02X}"))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+impl fmt::Display for Emergency {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "EMCY 0x{:04X}", self.code)?;
+        if let Some(text) = emcy_codes::describe(self.code) {
+            write!(f, " {text}")?;
+        }
+        write!(f, " reg 0x{:02X}", self.register)?;
+        if self.register != 0 {
+            write!(f, " ({})", self.register_text())?;
+        }
+        write!(f, " data {}", self.data_hex())
+    }
+}
+
+/// Emergencies received so far, as shown by the "Emergencies" panel.
+#[derive(Debug, Default)]
+pub struct EmcyPanel {
+    emergencies: VecDeque<Emergency>,
+    /// Total number of emergencies reported by the driver.
+    total: u64,
+    /// Value of `total` when the panel was last open.
+    seen: u64,
+    /// Emergencies with a lower message index were cleared.
+    cleared_before: u64,
+    group_by_node: bool,
+    export_status: Option<Result<String, String>>,
+}
+
+impl EmcyPanel {
+    /// Takes the history kept by the driver.
+    pub fn sync(&mut self, emergencies: &VecDeque<Emergency>, total: u64) {
+        if self.total != total {
+            self.total = total;
+            self.emergencies.clone_from(emergencies);
+        }
+    }
+
+    /// Number of emergencies received while the panel was closed.
+    pub fn unseen(&self) -> u64 {
+        self.total - self.seen
+    }
+
+    fn visible(&self) -> impl DoubleEndedIterator<Item = &Emergency> {
+        self.emergencies
+            .iter()
+            .filter(|e| e.index >= self.cleared_before)
+    }
+
+    /// Header of the panel with a badge counting the new emergencies.
+    pub fn title(&self) -> egui::RichText {
+        match self.unseen() {
+            0 => egui::RichText::new("⚠ Emergencies"),
+            n => {
+                egui::RichText::new(format!("⚠ Emergencies ({n} new)")).color(Status::Error.color())
+            }
+        }
+    }
+
+    pub fn update(&mut self, ui: &mut egui::Ui) {
+        self.seen = self.total;
+
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.group_by_node, "Group by node");
+            if ui.button("Clear").clicked() {
+                self.cleared_before = self.emergencies.back().map_or(0, |e| e.index + 1);
+            }
+            if ui
+                .button("Export")
+                .on_hover_text("Write the emergencies to a CSV file in the working directory")
+                .clicked()
+            {
+                self.export_status = Some(self.export());
+            }
+        });
+        match &self.export_status {
+            Some(Ok(path)) => {
+                ui.label(format!("Exported to {path}"));
+            }
+            Some(Err(e)) => {
+                ui.colored_label(Color32::RED, format!("Export failed: {e}"));
+            }
+            None => {}
+        }
+
+        if self.visible().next().is_none() {
+            ui.label("No emergencies");
+            return;
+        }
+
+        if self.group_by_node {
+            let mut nodes: BTreeMap<NodeId, Vec<&Emergency>> = BTreeMap::new();
+            for emergency in self.visible() {
+                nodes.entry(emergency.node_id).or_default().push(emergency);
+            }
+            for (node_id, emergencies) in nodes {
+                let last = emergencies[emergencies.len() - 1];
+                egui::CollapsingHeader::new(format!(
+                    "Node {node_id}: {} EMCY, last {}",
+                    emergencies.len(),
+                    last.description()
+                ))
+                .id_salt(("emcy_node", node_id))
+                .show(ui, |ui| {
+                    Self::show_grid(ui, ("emcy_grid", node_id), emergencies.into_iter().rev());
+                });
+            }
+        } else {
+            Self::show_grid(ui, "emcy_grid", self.visible().rev());
+        }
+    }
+
+    fn show_grid<'a>(
+        ui: &mut egui::Ui,
+        id: impl std::hash::Hash,
+        emergencies: impl Iterator<Item = &'a Emergency>,
+    ) {
+        egui::Grid::new(id).striped(true).show(ui, |ui| {
+            ui.label("Time");
+            ui.label("Node");
+            ui.label("Code");
+            ui.label("Error");
+            ui.label("Reg");
+            ui.label("Data");
+            ui.end_row();
+
+            for emergency in emergencies {
+                let color = if emergency.is_reset() {
+                    OZON_GRAY
+                } else {
+                    Status::Error.color()
+                };
+                ui.label(emergency.time.format("%H:%M:%S%.3f").to_string());
+                ui.label(format!("{:3}", emergency.node_id));
+                ui.colored_label(color, format!("0x{:04X}", emergency.code));
+                ui.colored_label(color, emergency.description());
+                ui.label(format!("0x{:02X}", emergency.register))
+                    .on_hover_text(emergency.register_text());
+                ui.label(emergency.data_hex());
+                ui.end_row();
+            }
+        });
+    }
+
+    fn to_csv(&self) -> String {
+        let mut csv = "index,time,node_id,code,description,register,data\n".to_owned();
+        for e in self.visible() {
+            let _ = writeln!(
+                csv,
+                "{},{},{},0x{:04X},\"{}\",0x{:02X},{}",
+                e.index,
+                e.time.format("%Y-%m-%dT%H:%M:%S%.3f"),
+                e.node_id,
+                e.code,
+                e.description(),
+                e.register,
+                e.data_hex()
+            );
+        }
+        csv
+    }
+
+    fn export(&self) -> Result<String, String> {
+        let path = format!("emcy-{}.csv", Local::now().format("%Y%m%d-%H%M%S"));
+        std::fs::write(&path, self.to_csv()).map_err(|e| e.to_string())?;
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Emergency;
+    use oze_canopen::canopen::RxMessage;
+    use tokio::time::Instant;
+
+    #[test]
+    fn test_parse() {
+        let msg = RxMessage {
+            timestamp: Instant::now(),
+            cob_id: 0x085,
+            data: [0x30, 0x81, 0x11, 1, 2, 3, 4, 5],
+            dlc: 8,
+        };
+        let emergency = Emergency::parse(7, &msg).unwrap();
+        assert_eq!(emergency.index, 7);
+        assert_eq!(emergency.node_id, 5);
+        assert_eq!(emergency.code, 0x8130);
+        assert_eq!(emergency.register, 0x11);
+        assert_eq!(emergency.data, [1, 2, 3, 4, 5]);
+        assert!(!emergency.is_reset());
+
+        let sync = RxMessage {
+            cob_id: 0x080,
+            dlc: 0,
+            ..msg
+        };
+        assert!(Emergency::parse(8, &sync).is_none());
+    }
+}
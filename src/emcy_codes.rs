@@ -0,0 +1,186 @@
+use std::{collections::BTreeMap, sync::OnceLock};
+
+/// Standard emergency error codes of `CiA 301` and `CiA 402`.
+const STANDARD_CODES: &[(u16, &str)] = &[
+    (0x0000, "Error reset or no error"),
+    (0x1000, "Generic error"),
+    (0x2000, "Current"),
+    (0x2100, "Current, device input side"),
+    (0x2200, "Current inside the device"),
+    (0x2300, "Current, device output side"),
+    (0x2310, "Continuous over current"),
+    (0x2320, "Short circuit / earth leakage"),
+    (0x2330, "Earth leakage"),
+    (0x2340, "Short circuit"),
+    (0x3000, "Voltage"),
+    (0x3100, "Mains voltage"),
+    (0x3110, "Mains over-voltage"),
+    (0x3120, "Mains under-voltage"),
+    (0x3130, "Phase failure"),
+    (0x3200, "Voltage inside the device"),
+    (0x3210, "DC link over-voltage"),
+    (0x3220, "DC link under-voltage"),
+    (0x3300, "Output voltage"),
+    (0x4000, "Temperature"),
+    (0x4100, "Ambient temperature"),
+    (0x4200, "Device temperature"),
+    (0x4210, "Excess temperature device"),
+    (0x4300, "Drive temperature"),
+    (0x4310, "Excess temperature drive"),
+    (0x5000, "Device hardware"),
+    (0x5100, "Supply"),
+    (0x5500, "Data storage"),
+    (0x6000, "Device software"),
+    (0x6100, "Internal software"),
+    (0x6200, "User software"),
+    (0x6300, "Data set"),
+    (0x6320, "Parameter error"),
+    (0x7000, "Additional modules"),
+    (0x7100, "Power"),
+    (0x7110, "Brake chopper"),
+    (0x7120, "Motor"),
+    (0x7121, "Motor blocked"),
+    (0x7300, "Sensor"),
+    (0x7310, "Speed"),
+    (0x7320, "Position"),
+    (0x7500, "Communication"),
+    (0x8000, "Monitoring"),
+    (0x8100, "Communication"),
+    (0x8110, "CAN overrun (objects lost)"),
+    (0x8120, "CAN in error passive mode"),
+    (0x8130, "Life guard error or heartbeat error"),
+    (0x8140, "Recovered from bus off"),
+    (0x8150, "CAN-ID collision"),
+    (0x8200, "Protocol error"),
+    (0x8210, "PDO not processed due to length error"),
+    (0x8220, "PDO length exceeded"),
+    (
+        0x8230,
+        "DAM MPDO not processed, destination object not available",
+    ),
+    (0x8240, "Unexpected SYNC data length"),
+    (0x8250, "RPDO timeout"),
+    (0x8300, "Torque control"),
+    (0x8400, "Velocity speed controller"),
+    (0x8500, "Position controller"),
+    (0x8600, "Positioning controller"),
+    (0x8611, "Following error"),
+    (0x8612, "Reference limit"),
+    (0x8700, "Sync controller"),
+    (0x8800, "Winding controller"),
+    (0x9000, "External error"),
+    (0xF000, "Additional functions"),
+    (0xF001, "Deceleration"),
+    (0xF002, "Sub-synchronous run"),
+    (0xF003, "Stroke operation"),
+    (0xF004, "Control"),
+];
+
+/// Meaning of the bits of the error register (0x1001).
+const REGISTER_BITS: [&str; 8] = [
+    "generic",
+    "current",
+    "voltage",
+    "temperature",
+    "communication",
+    "device profile specific",
+    "reserved",
+    "manufacturer specific",
+];
+
+/// Error codes of the manufacturer, loaded once at startup.
+static VENDOR_CODES: OnceLock<BTreeMap<u16, String>> = OnceLock::new();
+
+fn standard_code(code: u16) -> Option<&'static str> {
+    STANDARD_CODES
+        .binary_search_by_key(&code, |(c, _)| *c)
+        .ok()
+        .map(|i| STANDARD_CODES[i].1)
+}
+
+/// Describes an emergency error code, `None` if it is unknown.
+///
+/// Codes without an exact entry are described by their range, e.g. 0x2350 is a
+/// "Current, device output side" error. Device specific codes (0xFF00..=0xFFFF)
+/// are only known from the manufacturer table.
+pub fn describe(code: u16) -> Option<&'static str> {
+    if let Some(text) = VENDOR_CODES.get().and_then(|codes| codes.get(&code)) {
+        return Some(text);
+    }
+    if code >= 0xFF00 {
+        return None;
+    }
+    [0xFFFF, 0xFFF0, 0xFF00, 0xF000]
+        .into_iter()
+        .find_map(|mask| standard_code(code & mask))
+}
+
+/// Names of the bits set in the error register.
+pub fn register_bits(register: u8) -> Vec<&'static str> {
+    REGISTER_BITS
+        .iter()
+        .enumerate()
+        .filter(|(bit, _)| register & (1 << bit) != 0)
+        .map(|(_, name)| *name)
+        .collect()
+}
+
+/// Parses `code,text` lines, the code is hexadecimal. Lines that don't start with a
+/// code, like a header or `#` comments, are skipped.
+pub fn parse_csv(csv: &str) -> BTreeMap<u16, String> {
+    csv.lines()
+        .filter_map(|line| {
+            let (code, text) = line.split_once(',')?;
+            let code = code
+                .trim()
+                .trim_start_matches("0x")
+                .trim_start_matches("0X");
+            let code = u16::from_str_radix(code, 16).ok()?;
+            let text = text.trim().trim_matches('"').to_owned();
+            Some((code, text))
+        })
+        .collect()
+}
+
+/// Loads the manufacturer error codes from a CSV file, returns the number of codes.
+///
+/// Manufacturer codes take precedence over the standard ones.
+///
+/// # Errors
+/// If the file can't be read or the codes were already loaded.
+pub fn load_vendor_codes(path: &str) -> Result<usize, String> {
+    let csv = std::fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+    let codes = parse_csv(&csv);
+    let count = codes.len();
+    VENDOR_CODES
+        .set(codes)
+        .map_err(|_| "vendor codes already loaded".to_owned())?;
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{describe, parse_csv, register_bits, STANDARD_CODES};
+
+    #[test]
+    fn test_describe() {
+        assert!(STANDARD_CODES.windows(2).all(|w| w[0].0 < w[1].0));
+        assert_eq!(
+            describe(0x8130),
+            Some("Life guard error or heartbeat error")
+        );
+        assert_eq!(describe(0x8611), Some("Following error"));
+        assert_eq!(describe(0x2350), Some("Current, device output side"));
+        assert_eq!(describe(0x2A00), Some("Current"));
+        assert_eq!(describe(0xFF42), None);
+        assert_eq!(register_bits(0x11), ["generic", "communication"]);
+    }
+
+    #[test]
+    fn test_parse_csv() {
+        let codes = parse_csv("code,text\n# vendor\n0xFF01,\"Fan failure\"\nff02, Door open\n");
+        assert_eq!(codes.len(), 2);
+        assert_eq!(codes[&0xFF01], "Fan failure");
+        assert_eq!(codes[&0xFF02], "Door open");
+    }
+}
@@ -0,0 +1,619 @@
+use crate::{
+    annotations::{Annotations, UserMarker},
+    filter::GlobalFilter,
+    gateway::GatewaySide,
+    message_cached::{Direction, MessageCached},
+    message_row::wall_clock,
+    pcapng::{PcapFrame, PcapngWriter},
+    value_plot::Extraction,
+};
+use chrono::{DateTime, Local};
+use egui::Color32;
+use std::{
+    collections::VecDeque,
+    fmt::Write,
+    fs::File,
+    io::{self, Write as _},
+    path::Path,
+};
+use tokio::{
+    sync::oneshot::{self, error::TryRecvError},
+    time::Instant,
+};
+
+/// Creates `path`, or the first of `<stem>-1.<ext>`, `<stem>-2.<ext>`… which doesn't exist.
+fn create_new(path: &str) -> io::Result<(String, File)> {
+    let extension = Path::new(path)
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+    let stem = &path[..path.len() - extension.len()];
+    let mut candidate = path.to_owned();
+    for suffix in 1.. {
+        match File::options()
+            .write(true)
+            .create_new(true)
+            .open(&candidate)
+        {
+            Ok(file) => return Ok((candidate, file)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                candidate = format!("{stem}-{suffix}{extension}");
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    unreachable!()
+}
+
+/// Which messages of the buffer are exported.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ExportScope {
+    /// Messages passing the current filters, as shown by the viewer.
+    #[default]
+    Filtered,
+    /// Every buffered message.
+    All,
+}
+
+/// File format of the export.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// Spreadsheet friendly table with the decoded info.
+    #[default]
+    Csv,
+    /// `candump -l` log, accepted by `canplayer`.
+    Candump,
+    /// pcapng capture, dissected by Wireshark.
+    Pcapng,
+}
+
+impl ExportFormat {
+    fn file_name(self) -> String {
+        let date = Local::now().format("%Y%m%d-%H%M%S");
+        match self {
+            Self::Csv => format!("canopen-{date}.csv"),
+            Self::Candump => format!("candump-{date}.log"),
+            Self::Pcapng => format!("capture-{date}.pcapng"),
+        }
+    }
+
+    fn button(self) -> &'static str {
+        match self {
+            Self::Csv => "Export CSV…",
+            Self::Candump => "Export log…",
+            Self::Pcapng => "Export pcapng…",
+        }
+    }
+}
+
+/// Capture the exported messages come from.
+#[derive(Debug, Clone)]
+pub struct Capture {
+    /// Capture start, the CSV timestamps are relative to it.
+    pub start: Instant,
+    /// Wall-clock time at `start`.
+    pub start_wall: DateTime<Local>,
+    /// Interface of the connection, side A of the gateway.
+    pub can_name: String,
+    /// Peer interface of the gateway, side B.
+    pub peer: Option<String>,
+    /// Bookmarks and markers written with the messages.
+    pub annotations: Annotations,
+}
+
+impl Capture {
+    /// Interface a message was received on.
+    fn interface(&self, msg: &MessageCached) -> &str {
+        match (msg.origin, &self.peer) {
+            (Some(GatewaySide::B), Some(peer)) => peer,
+            _ => &self.can_name,
+        }
+    }
+}
+
+/// Result of a finished export.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Exported {
+    pub path: String,
+    pub rows: usize,
+}
+
+type ExportResult = Result<Exported, String>;
+
+/// File written on a separate thread, with the result of the last one written.
+#[derive(Debug)]
+pub struct ExportJob {
+    pending: Option<oneshot::Receiver<ExportResult>>,
+    status: Option<ExportResult>,
+    /// What the rows are, shown in the status.
+    unit: &'static str,
+}
+
+impl Default for ExportJob {
+    fn default() -> Self {
+        Self::new("messages")
+    }
+}
+
+impl ExportJob {
+    pub fn new(unit: &'static str) -> Self {
+        Self {
+            pending: None,
+            status: None,
+            unit,
+        }
+    }
+
+    /// No file is being written.
+    pub fn is_idle(&self) -> bool {
+        self.pending.is_none()
+    }
+
+    /// Formats `rows` lines with `contents` and writes them to `path` on a separate thread.
+    pub fn start<T: AsRef<[u8]>>(
+        &mut self,
+        path: String,
+        rows: usize,
+        contents: impl FnOnce() -> T + Send + 'static,
+    ) {
+        self.spawn(rows, move || {
+            std::fs::write(&path, contents()).map(|()| path)
+        });
+    }
+
+    /// Same as [`Self::start`] but never overwrites a file, a `-1`, `-2`… suffix is added to
+    /// the name of `path` if it exists.
+    pub fn start_new<T: AsRef<[u8]>>(
+        &mut self,
+        path: String,
+        rows: usize,
+        contents: impl FnOnce() -> T + Send + 'static,
+    ) {
+        self.spawn(rows, move || {
+            let (path, mut file) = create_new(&path)?;
+            file.write_all(contents().as_ref())?;
+            Ok(path)
+        });
+    }
+
+    /// Runs `write` on a separate thread, it returns the path written.
+    fn spawn(&mut self, rows: usize, write: impl FnOnce() -> io::Result<String> + Send + 'static) {
+        let (reply, result) = oneshot::channel();
+        std::thread::spawn(move || {
+            let written = write()
+                .map(|path| Exported { path, rows })
+                .map_err(|e| e.to_string());
+            let _ = reply.send(written);
+        });
+        self.pending = Some(result);
+        self.status = None;
+    }
+
+    /// Result of the file once written, taken so that the caller reports it instead of
+    /// [`Self::ui`].
+    pub fn finished(&mut self) -> Option<Result<Exported, String>> {
+        self.poll();
+        self.status.take()
+    }
+
+    fn poll(&mut self) {
+        let Some(pending) = &mut self.pending else {
+            return;
+        };
+        self.status = match pending.try_recv() {
+            Ok(result) => Some(result),
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Closed) => Some(Err("export thread stopped".to_owned())),
+        };
+        self.pending = None;
+    }
+
+    /// Spinner while writing, then the number of rows written or the error.
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        self.poll();
+        if self.pending.is_some() {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label("Exporting…");
+            });
+        }
+        match &self.status {
+            Some(Ok(done)) => {
+                ui.label(format!(
+                    "{} {} exported to {}",
+                    done.rows, self.unit, done.path
+                ));
+            }
+            Some(Err(e)) => {
+                ui.colored_label(Color32::RED, format!("Export failed: {e}"));
+            }
+            None => {}
+        }
+    }
+}
+
+/// Writes the buffered messages to a file without blocking the GUI.
+#[derive(Debug, Default)]
+pub struct MessageExport {
+    scope: ExportScope,
+    format: ExportFormat,
+    job: ExportJob,
+}
+
+impl MessageExport {
+    /// `data` is ordered newest first.
+    pub fn update(
+        &mut self,
+        ui: &mut egui::Ui,
+        data: &VecDeque<MessageCached>,
+        filter: &GlobalFilter,
+        capture: Capture,
+    ) {
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut self.format, ExportFormat::Csv, "CSV")
+                .on_hover_text("Index, timestamp, COB-ID, DLC, data and decoded info");
+            ui.radio_value(&mut self.format, ExportFormat::Candump, "candump")
+                .on_hover_text("candump log file, can be replayed with canplayer");
+            ui.radio_value(&mut self.format, ExportFormat::Pcapng, "pcapng")
+                .on_hover_text("Capture for Wireshark and its CAN and CANopen dissectors");
+        });
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut self.scope, ExportScope::Filtered, "Filtered")
+                .on_hover_text("Only the messages shown by the viewer");
+            ui.radio_value(&mut self.scope, ExportScope::All, "All")
+                .on_hover_text("Every buffered message, ignoring the filters");
+            if ui
+                .add_enabled(self.job.is_idle(), egui::Button::new(self.format.button()))
+                .on_hover_text("Write the messages to a file in the working directory")
+                .clicked()
+            {
+                let rows = self.snapshot(data, filter);
+                self.start(rows, capture);
+            }
+        });
+        self.job.ui(ui);
+    }
+
+    /// Messages to export, oldest first.
+    fn snapshot(
+        &self,
+        data: &VecDeque<MessageCached>,
+        filter: &GlobalFilter,
+    ) -> Vec<MessageCached> {
+        data.iter()
+            .rev()
+            .filter(|msg| self.scope == ExportScope::All || !filter.filter(msg))
+            .cloned()
+            .collect()
+    }
+
+    /// Formats and writes the messages on a separate thread.
+    fn start(&mut self, rows: Vec<MessageCached>, capture: Capture) {
+        let format = self.format;
+        self.job
+            .start(format.file_name(), rows.len(), move || match format {
+                ExportFormat::Csv => {
+                    to_csv(&rows, capture.start, &capture.annotations).into_bytes()
+                }
+                ExportFormat::Candump => to_candump(&rows, &capture).into_bytes(),
+                ExportFormat::Pcapng => to_pcapng(&rows, &capture),
+            });
+    }
+}
+
+/// Quotes a CSV field, doubling the quotes it contains.
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('"', "\"\""))
+}
+
+/// Line of an export.
+enum Line<'a> {
+    Message {
+        msg: &'a MessageCached,
+        bookmarked: bool,
+    },
+    Marker(&'a UserMarker),
+}
+
+/// Merges the markers placed since the first of `rows` into them, both ordered oldest first.
+fn lines<'a>(rows: &'a [MessageCached], annotations: &'a Annotations) -> Vec<Line<'a>> {
+    let Some(first) = rows.first() else {
+        return Vec::new();
+    };
+    let mut markers = annotations
+        .markers
+        .iter()
+        .filter(|marker| marker.time >= first.get_timestamp())
+        .peekable();
+    let mut lines = Vec::with_capacity(rows.len());
+    for msg in rows {
+        while let Some(marker) = markers.next_if(|marker| marker.index <= msg.index) {
+            lines.push(Line::Marker(marker));
+        }
+        lines.push(Line::Message {
+            msg,
+            bookmarked: annotations.is_bookmarked(msg.index),
+        });
+    }
+    lines.extend(markers.map(Line::Marker));
+    lines
+}
+
+/// One line per message, plus one per marker with only a timestamp and the marker text in the
+/// `annotation` column. `direction` is `tx` for the frames sent by the viewer, `rx` otherwise.
+/// Numbers are formatted by Rust, never by the locale: the timestamp is in seconds since `start`
+/// with a `.` separator, IDs and data are hexadecimal.
+pub fn to_csv(rows: &[MessageCached], start: Instant, annotations: &Annotations) -> String {
+    let mut csv = "index,timestamp,cob_id,dlc,data,decoded,annotation,direction\n".to_owned();
+    for line in lines(rows, annotations) {
+        let _ = match line {
+            Line::Message { msg, bookmarked } => writeln!(
+                csv,
+                "{},{:.6},0x{:03X},{},{},{},{},{}",
+                msg.index,
+                msg.get_timestamp().duration_since(start).as_secs_f64(),
+                msg.msg.msg.cob_id,
+                msg.msg.msg.dlc,
+                msg.hex_str,
+                quote(msg.info()),
+                if bookmarked { "bookmark" } else { "" },
+                msg.direction.as_str().to_lowercase()
+            ),
+            Line::Marker(marker) => writeln!(
+                csv,
+                ",{:.6},,,,,{},",
+                marker.time.duration_since(start).as_secs_f64(),
+                quote(&marker.to_string())
+            ),
+        };
+    }
+    csv
+}
+
+/// One `(seconds.micros) interface ID#DATA` line per message, as written by `candump -l`.
+/// Timestamps are wall-clock times derived from the capture start. Markers and bookmarks are
+/// `#` comment lines, before the bookmarked frames. The frames sent by the viewer end with ` T`
+/// as with `candump -x`, the received ones are left as is.
+pub fn to_candump(rows: &[MessageCached], capture: &Capture) -> String {
+    let mut log = String::new();
+    for line in lines(rows, &capture.annotations) {
+        let msg = match line {
+            Line::Message { msg, bookmarked } => {
+                if bookmarked {
+                    log.push_str("# bookmark\n");
+                }
+                msg
+            }
+            Line::Marker(marker) => {
+                let _ = writeln!(log, "# {marker}");
+                continue;
+            }
+        };
+        let time = wall_clock(capture.start, capture.start_wall, msg.get_timestamp());
+        candump_line(&mut log, time, capture.interface(msg), msg);
+    }
+    log
+}
+
+/// Appends the `candump -l` line of a message received at `time` on `interface`.
+pub fn candump_line(log: &mut String, time: DateTime<Local>, interface: &str, msg: &MessageCached) {
+    let frame = &msg.msg.msg;
+    let _ = write!(
+        log,
+        "({}.{:06}) {interface} {:03X}#",
+        time.timestamp(),
+        time.timestamp_subsec_micros(),
+        frame.cob_id
+    );
+    for byte in &frame.data[..frame.dlc.min(8)] {
+        let _ = write!(log, "{byte:02X}");
+    }
+    if msg.direction == Direction::Tx {
+        log.push_str(" T");
+    }
+    log.push('\n');
+}
+
+/// pcapng capture with one `SocketCAN` packet per message and one interface per side of the
+/// gateway. Timestamps are wall-clock times derived from the capture start, the frames sent by
+/// the viewer are outbound. Bookmarks and markers are comments of the packet that follows them.
+pub fn to_pcapng(rows: &[MessageCached], capture: &Capture) -> Vec<u8> {
+    let write = || {
+        let mut writer = PcapngWriter::new(Vec::new())?;
+        let mut comments = Vec::new();
+        for line in lines(rows, &capture.annotations) {
+            let msg = match line {
+                Line::Message { msg, bookmarked } => {
+                    if bookmarked {
+                        comments.push("bookmark".to_owned());
+                    }
+                    msg
+                }
+                Line::Marker(marker) => {
+                    comments.push(marker.to_string());
+                    continue;
+                }
+            };
+            let frame = &msg.msg.msg;
+            let time = wall_clock(capture.start, capture.start_wall, msg.get_timestamp());
+            let packet = PcapFrame {
+                time_us: time.timestamp_micros(),
+                can_id: u32::from(frame.cob_id),
+                data: frame.data[..frame.dlc.min(8)].to_vec(),
+                direction: msg.direction,
+                comment: (!comments.is_empty()).then(|| comments.join("\n")),
+            };
+            comments.clear();
+            writer.write(capture.interface(msg), &packet)?;
+        }
+        std::io::Result::Ok(writer.into_inner())
+    };
+    // Written to memory, can't fail.
+    write().unwrap_or_default()
+}
+
+/// File name of the export of a pinned filter, after its title.
+pub fn pinned_file_name(title: &str) -> String {
+    let name: String = title
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect();
+    let date = Local::now().format("%Y%m%d-%H%M%S");
+    format!("pinned-{name}-{date}.csv")
+}
+
+/// One line per message of a pinned filter, oldest first. `value` is the value read by
+/// `extraction`, empty without extraction or if the frame is too short. The timestamp is in
+/// seconds since `start`, formatted as in `to_csv`.
+pub fn pinned_to_csv(
+    rows: &[MessageCached],
+    start: Instant,
+    extraction: Option<&Extraction>,
+) -> String {
+    let mut csv = "timestamp,cob_id,dlc,data,decoded,value\n".to_owned();
+    for msg in rows {
+        let value = extraction
+            .and_then(|extraction| extraction.extract(msg))
+            .map(|value| value.to_string())
+            .unwrap_or_default();
+        let _ = writeln!(
+            csv,
+            "{:.6},0x{:03X},{},{},{},{}",
+            msg.get_timestamp().duration_since(start).as_secs_f64(),
+            msg.msg.msg.cob_id,
+            msg.msg.msg.dlc,
+            msg.hex_str,
+            quote(msg.info()),
+            value
+        );
+    }
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{pinned_to_csv, to_candump, to_csv, Capture};
+    use crate::{
+        annotations::{Annotations, UserMarker},
+        gateway::GatewaySide,
+        message_cached::{Direction, MessageCached},
+        value_plot::Extraction,
+    };
+    use chrono::{Local, TimeZone};
+    use oze_canopen::canopen::RxMessage;
+    use std::time::Duration;
+    use tokio::time::Instant;
+
+    #[test]
+    fn test_to_csv() {
+        let start = Instant::now();
+        let msg = MessageCached::new(
+            7,
+            RxMessage {
+                timestamp: start + Duration::from_micros(1_500_250),
+                cob_id: 0x701,
+                data: [0x05, 0, 0, 0, 0, 0, 0, 0],
+                dlc: 1,
+            },
+        );
+        let mut annotations = Annotations::default();
+        annotations.bookmarks.insert(7);
+        annotations.markers.push(UserMarker {
+            number: 1,
+            index: 8,
+            time: start + Duration::from_secs(2),
+            wall: Local.with_ymd_and_hms(2024, 5, 2, 12, 3, 45).unwrap(),
+            note: "e-stop".to_owned(),
+        });
+        let csv = to_csv(&[msg], start, &annotations);
+        let mut lines = csv.lines();
+        assert_eq!(
+            lines.next(),
+            Some("index,timestamp,cob_id,dlc,data,decoded,annotation,direction")
+        );
+        assert_eq!(
+            lines.next(),
+            Some("7,1.500250,0x701,1,05,\"State: Operational\",bookmark,rx")
+        );
+        assert_eq!(
+            lines.next(),
+            Some(",2.000000,,,,,\"Marker 1 — 12:03:45 — e-stop\",")
+        );
+        assert_eq!(lines.next(), None);
+    }
+
+    #[test]
+    fn test_to_candump() {
+        let capture = Capture {
+            start: Instant::now(),
+            start_wall: Local.timestamp_opt(1_436_509_052, 0).unwrap(),
+            can_name: "can0".to_owned(),
+            peer: Some("can1".to_owned()),
+            annotations: Annotations::default(),
+        };
+        let mut sync = MessageCached::new(
+            0,
+            RxMessage {
+                timestamp: capture.start + Duration::from_micros(249_713),
+                cob_id: 0x080,
+                data: [0; 8],
+                dlc: 0,
+            },
+        );
+        let mut pdo = sync.clone();
+        pdo.msg.msg.cob_id = 0x181;
+        pdo.msg.msg.dlc = 2;
+        pdo.msg.msg.data[..2].copy_from_slice(&[0x01, 0xAB]);
+        pdo.origin = Some(GatewaySide::B);
+        sync.origin = Some(GatewaySide::A);
+        sync.direction = Direction::Tx;
+
+        assert_eq!(
+            to_candump(&[sync, pdo], &capture),
+            "(1436509052.249713) can0 080# T\n(1436509052.249713) can1 181#01AB\n"
+        );
+    }
+
+    #[test]
+    fn test_pinned_to_csv() {
+        let start = Instant::now();
+        let position = |cob_id, data, dlc, millis| {
+            MessageCached::new(
+                0,
+                RxMessage {
+                    timestamp: start + Duration::from_millis(millis),
+                    cob_id,
+                    data,
+                    dlc,
+                },
+            )
+        };
+        let rows = [
+            position(0x281, [0x18, 0xFC, 0xFF, 0xFF, 0, 0, 0, 0], 4, 250),
+            position(0x281, [0xE8, 0x03, 0, 0, 0, 0, 0, 0], 2, 1250),
+        ];
+        let extraction = Extraction {
+            width: 4,
+            signed: true,
+            scale: 0.5,
+            ..Extraction::default()
+        };
+
+        let csv = pinned_to_csv(&rows, start, Some(&extraction));
+        let mut lines = csv.lines();
+        assert_eq!(
+            lines.next(),
+            Some("timestamp,cob_id,dlc,data,decoded,value")
+        );
+        assert!(lines
+            .next()
+            .unwrap()
+            .starts_with("0.250000,0x281,4,18 FC FF FF,"));
+        assert!(lines.next().unwrap().ends_with(','), "too short to extract");
+        assert_eq!(lines.next(), None);
+        assert!(csv.lines().nth(1).unwrap().ends_with(",-500"));
+
+        let csv = pinned_to_csv(&rows, start, None);
+        assert!(csv.lines().skip(1).all(|line| line.ends_with(',')));
+    }
+}
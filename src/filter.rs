@@ -1,7 +1,8 @@
-use crate::message_cached::MessageCached;
+use crate::message_cached::{Direction, MessageCached};
 use bitflags::bitflags;
 use oze_canopen::canopen::{NodeId, RxMessageType};
 use regex::Regex;
+use serde::{Deserialize, Serialize};
 use std::{cell::RefCell, rc::Rc};
 
 /// Represents a filter for CAN messages based on node id and regular expressions.
@@ -11,19 +12,200 @@ pub struct DataFilter {
     pub regex: Option<Regex>,
     pub node_id: Option<NodeId>,
     pub regex_cob: Option<Regex>,
+    /// Matched against the text of the row, see `MessageCached::text_str`.
+    pub regex_text: Option<Regex>,
+    /// Only the frames sent (`Tx`) or received (`Rx`) by the viewer, both if `None`.
+    pub direction: Option<Direction>,
+    /// Matches the messages the other fields don't match.
+    pub invert: bool,
 }
 
 /// Represents a global filter that includes data filters and flag-based type filters.
+///
+/// A message is shown if it matches `data`, its type isn't ignored, it matches the `show` rules
+/// if there are any and doesn't match the `hide` rules. Hide wins over show.
 #[derive(Default, Debug, Clone)]
 #[allow(clippy::module_name_repetitions)]
 pub struct GlobalFilter {
     pub ignore_type: Flags,
+    /// Classes hidden, on top of the types of `ignore_type`.
+    pub ignore_class: Classes,
     pub data: Rc<RefCell<DataFilter>>,
+    /// Show only the messages matching these rules, the empty ones are ignored.
+    pub show: RuleGroup,
+    /// Hide the messages matching these rules, the empty ones are ignored.
+    pub hide: RuleGroup,
+    /// Show only the messages of these nodes, see `node_of`.
+    pub nodes: Option<NodeSet>,
+    /// Hide the messages of no node (NMT, SYNC, TIME, LSS) when filtering by node.
+    pub hide_broadcast: bool,
+    /// Show only the messages matching one of these rules if there are any.
+    pub bytes: Vec<ByteRule>,
+}
+
+/// Copy of the rules of a global filter, to tell when they change.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilterRules {
+    ignore_type: Flags,
+    ignore_class: Classes,
+    data: DataFilter,
+    show: RuleGroup,
+    hide: RuleGroup,
+    nodes: Option<NodeSet>,
+    hide_broadcast: bool,
+    bytes: Vec<ByteRule>,
+}
+
+/// How the rules of a group combine.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Operator {
+    /// Every rule matches.
+    And,
+    /// At least one rule matches.
+    #[default]
+    Or,
+}
+
+/// Rule of a group: a data filter or a nested group.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RuleNode {
+    Rule(Rc<RefCell<DataFilter>>),
+    Group(RuleGroup),
+}
+
+/// Rules combined with an operator, e.g. `(node 3 AND PDO) OR EMCY` is an `Or` group of an
+/// `And` group and a rule. A flat list of rules is an `Or` group without nested groups.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct RuleGroup {
+    pub operator: Operator,
+    pub rules: Vec<RuleNode>,
+}
+
+impl RuleNode {
+    fn matches(&self, msg: &MessageCached) -> Option<bool> {
+        match self {
+            Self::Rule(rule) => {
+                let rule = rule.borrow();
+                (!rule.is_empty()).then(|| rule.matches(msg))
+            }
+            Self::Group(group) => group.matches(msg),
+        }
+    }
+
+    fn deep_clone(&self) -> Self {
+        match self {
+            Self::Rule(rule) => Self::Rule(Rc::new(RefCell::new(rule.borrow().clone()))),
+            Self::Group(group) => Self::Group(group.deep_clone()),
+        }
+    }
+}
+
+impl RuleGroup {
+    /// Whether the message matches the group, `None` if no rule of the group is set. Empty rules
+    /// and groups are skipped and the evaluation stops at the first rule deciding the outcome.
+    pub fn matches(&self, msg: &MessageCached) -> Option<bool> {
+        let decisive = self.operator == Operator::Or;
+        let mut active = false;
+        for rule in &self.rules {
+            match rule.matches(msg) {
+                Some(matches) if matches == decisive => return Some(decisive),
+                Some(_) => active = true,
+                None => {}
+            }
+        }
+        active.then_some(!decisive)
+    }
+
+    /// Copy with rules of its own, not shared with the panels editing them.
+    fn deep_clone(&self) -> Self {
+        Self {
+            operator: self.operator,
+            rules: self.rules.iter().map(RuleNode::deep_clone).collect(),
+        }
+    }
+}
+
+/// Matches the frames whose byte at `offset`, masked, equals `value`, e.g. the fault bit of a
+/// statusword with `offset` 0 and `mask` and `value` 0x08.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByteRule {
+    /// COB-ID the rule applies to, every frame if `None`.
+    pub cob_id: Option<u16>,
+    pub offset: usize,
+    pub mask: u8,
+    pub value: u8,
+}
+
+impl ByteRule {
+    /// The frame is long enough and its byte matches, on the COB-ID of the rule if it has one.
+    pub fn matches(&self, msg: &MessageCached) -> bool {
+        let frame = &msg.msg.msg;
+        self.cob_id.map_or(true, |cob_id| cob_id == frame.cob_id)
+            && self.offset < frame.dlc.min(8)
+            && frame.data[self.offset] & self.mask == self.value & self.mask
+    }
+}
+
+/// Set of node IDs, 1 to 127.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct NodeSet(u128);
+
+impl NodeSet {
+    /// Parses node IDs and ranges separated by commas or spaces, e.g. `3, 7` or `1-8 12`.
+    /// The IDs are decimal, or hex with `0x`.
+    ///
+    /// # Errors
+    /// If a node ID isn't a number from 1 to 127 or a range is reversed.
+    pub fn parse(raw: &str) -> Result<Self, String> {
+        let node = |raw: &str| -> Result<NodeId, String> {
+            let raw = raw.trim();
+            let node = match raw.strip_prefix("0x") {
+                Some(hex) => NodeId::from_str_radix(hex, 16),
+                None => raw.parse(),
+            };
+            node.ok()
+                .filter(|node| (1..=127).contains(node))
+                .ok_or_else(|| format!("'{raw}' isn't a node ID from 1 to 127"))
+        };
+        let mut set = Self::default();
+        for part in raw.split([',', ' ']).filter(|part| !part.trim().is_empty()) {
+            let (first, last) = match part.split_once('-') {
+                Some((first, last)) => (node(first)?, node(last)?),
+                None => (node(part)?, node(part)?),
+            };
+            if first > last {
+                return Err(format!("'{part}' is reversed"));
+            }
+            for node in first..=last {
+                set.0 |= 1 << node;
+            }
+        }
+        Ok(set)
+    }
+
+    pub fn contains(self, node: NodeId) -> bool {
+        node < 128 && self.0 & (1 << node) != 0
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+}
+
+/// Node a COB-ID belongs to after the function code layout of the predefined connection set:
+/// EMCY, the four TPDOs and RPDOs, SDO tx and rx and heartbeat. `None` for the broadcast objects
+/// (NMT, SYNC, TIME), LSS and the unassigned ranges.
+pub fn node_of(cob_id: u16) -> Option<NodeId> {
+    let node = NodeId::try_from(cob_id & 0x7F).ok()?;
+    match cob_id >> 7 {
+        0x1 | 0x3..=0xC | 0xE if node != 0 => Some(node),
+        _ => None,
+    }
 }
 
 bitflags! {
     /// Flags for different types of CAN messages.
-    #[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+    #[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
     pub struct Flags: u8 {
         const NONE = 0b0000_0000;
         const SYNC = 0b0000_0001;
@@ -38,28 +220,123 @@ bitflags! {
     }
 }
 
+bitflags! {
+    /// Classes of messages after the function code of their COB-ID, finer than `Flags`.
+    #[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+    pub struct Classes: u16 {
+        const NMT = 1 << 0;
+        const SYNC = 1 << 1;
+        const TIME = 1 << 2;
+        const EMCY = 1 << 3;
+        const TPDO1 = 1 << 4;
+        const RPDO1 = 1 << 5;
+        const TPDO2 = 1 << 6;
+        const RPDO2 = 1 << 7;
+        const TPDO3 = 1 << 8;
+        const RPDO3 = 1 << 9;
+        const TPDO4 = 1 << 10;
+        const RPDO4 = 1 << 11;
+        const SDO_TX = 1 << 12;
+        const SDO_RX = 1 << 13;
+        const HEARTBEAT = 1 << 14;
+        const OTHER = 1 << 15;
+    }
+}
+
+impl Classes {
+    /// Class of a COB-ID, one flag. Node 0 of the node ranges, LSS and the unassigned COB-IDs
+    /// are `OTHER`.
+    pub fn of(cob_id: u16) -> Self {
+        match cob_id {
+            0x000 => Self::NMT,
+            0x080 => Self::SYNC,
+            0x100 => Self::TIME,
+            _ if node_of(cob_id).is_none() => Self::OTHER,
+            _ => match cob_id >> 7 {
+                0x1 => Self::EMCY,
+                0x3 => Self::TPDO1,
+                0x4 => Self::RPDO1,
+                0x5 => Self::TPDO2,
+                0x6 => Self::RPDO2,
+                0x7 => Self::TPDO3,
+                0x8 => Self::RPDO3,
+                0x9 => Self::TPDO4,
+                0xA => Self::RPDO4,
+                0xB => Self::SDO_TX,
+                0xC => Self::SDO_RX,
+                _ => Self::HEARTBEAT,
+            },
+        }
+    }
+}
+
+impl PartialEq for DataFilter {
+    /// The regular expressions are compared by their pattern.
+    fn eq(&self, other: &Self) -> bool {
+        self.node_id == other.node_id
+            && self.direction == other.direction
+            && self.invert == other.invert
+            && self.regex.as_ref().map(Regex::as_str) == other.regex.as_ref().map(Regex::as_str)
+            && self.regex_cob.as_ref().map(Regex::as_str)
+                == other.regex_cob.as_ref().map(Regex::as_str)
+            && self.regex_text.as_ref().map(Regex::as_str)
+                == other.regex_text.as_ref().map(Regex::as_str)
+    }
+}
+
 impl DataFilter {
     /// Filters messages based on node id and regular expressions.
     ///
     /// Returns `true` if the message should be filtered out, `false` otherwise.
     pub fn filter(&self, msg: &MessageCached) -> bool {
+        !self.matches(msg)
+    }
+
+    /// Returns `true` if the message matches every field set, or none with `invert`.
+    pub fn matches(&self, msg: &MessageCached) -> bool {
+        self.matches_fields(msg) != self.invert
+    }
+
+    fn matches_fields(&self, msg: &MessageCached) -> bool {
         if self.node_id.is_some() && msg.msg.parsed_node_id != self.node_id {
-            return true;
+            return false;
+        }
+
+        if self
+            .direction
+            .is_some_and(|direction| direction != msg.direction)
+        {
+            return false;
         }
 
         if let Some(re) = &self.regex_cob {
             if !re.is_match(&msg.cob_str) {
-                return true;
+                return false;
             }
         }
 
         if let Some(re) = &self.regex {
             if !re.is_match(&msg.hex_str) {
-                return true;
+                return false;
+            }
+        }
+
+        if let Some(re) = &self.regex_text {
+            if !re.is_match(&msg.text_str) {
+                return false;
             }
         }
 
-        false
+        true
+    }
+
+    /// No field is set, the rule matches every message.
+    pub fn is_empty(&self) -> bool {
+        self.node_id.is_none()
+            && self.direction.is_none()
+            && self.regex_cob.is_none()
+            && self.regex.is_none()
+            && self.regex_text.is_none()
     }
 }
 
@@ -68,10 +345,36 @@ impl GlobalFilter {
     ///
     /// Returns `true` if the message should be filtered out, `false` otherwise.
     pub fn filter(&self, msg: &MessageCached) -> bool {
-        if self.data.borrow().filter(msg) {
+        if self.data.borrow().filter(msg) || self.ignores_type(msg) || self.ignores_node(msg) {
             return true;
         }
 
+        if self.hide.matches(msg) == Some(true) {
+            return true;
+        }
+        if !self.bytes.is_empty() && !self.bytes.iter().any(|rule| rule.matches(msg)) {
+            return true;
+        }
+        self.show.matches(msg) == Some(false)
+    }
+
+    fn ignores_node(&self, msg: &MessageCached) -> bool {
+        let Some(nodes) = self.nodes else {
+            return false;
+        };
+        match node_of(msg.msg.msg.cob_id) {
+            Some(node) => !nodes.contains(node),
+            None => self.hide_broadcast,
+        }
+    }
+
+    fn ignores_type(&self, msg: &MessageCached) -> bool {
+        if self
+            .ignore_class
+            .intersects(Classes::of(msg.msg.msg.cob_id))
+        {
+            return true;
+        }
         match msg.msg.parsed_type {
             RxMessageType::SdoTx | RxMessageType::SdoRx => self.ignore_type.contains(Flags::SDO),
             RxMessageType::Pdo => self.ignore_type.contains(Flags::PDO),
@@ -83,12 +386,29 @@ impl GlobalFilter {
             RxMessageType::Unknown => self.ignore_type.contains(Flags::UNKNOWN),
         }
     }
+
+    /// Copy of the rules, compare it to a previous one to tell if they changed.
+    pub fn rules(&self) -> FilterRules {
+        FilterRules {
+            ignore_type: self.ignore_type,
+            ignore_class: self.ignore_class,
+            data: self.data.borrow().clone(),
+            show: self.show.deep_clone(),
+            hide: self.hide.deep_clone(),
+            nodes: self.nodes,
+            hide_broadcast: self.hide_broadcast,
+            bytes: self.bytes.clone(),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{DataFilter, GlobalFilter};
-    use crate::message_cached::MessageCached;
+    use super::{
+        node_of, ByteRule, Classes, DataFilter, GlobalFilter, NodeSet, Operator, RuleGroup,
+        RuleNode,
+    };
+    use crate::message_cached::{Direction, MessageCached};
     use oze_canopen::receiver::RxMessage;
     use regex::Regex;
     use std::{cell::RefCell, rc::Rc};
@@ -119,6 +439,7 @@ mod tests {
             regex: Regex::new("^01").ok(),
             node_id: None,
             regex_cob: None,
+            ..DataFilter::default()
         };
         assert!(!filt.filter(msg183));
         assert!(filt.filter(msg585));
@@ -127,6 +448,7 @@ mod tests {
             regex: Regex::new("^01 02 03$").ok(),
             node_id: None,
             regex_cob: None,
+            ..DataFilter::default()
         };
         assert!(!filt.filter(msg183));
         assert!(filt.filter(msg585));
@@ -135,6 +457,7 @@ mod tests {
             regex: Regex::new("03").ok(),
             node_id: None,
             regex_cob: None,
+            ..DataFilter::default()
         };
         assert!(!filt.filter(msg183));
         assert!(!filt.filter(msg585));
@@ -143,6 +466,7 @@ mod tests {
             regex: Regex::new("03").ok(),
             node_id: Some(3),
             regex_cob: None,
+            ..DataFilter::default()
         };
         assert!(!filt.filter(msg183));
         assert!(filt.filter(msg585));
@@ -151,6 +475,7 @@ mod tests {
             regex: Regex::new("03").ok(),
             node_id: Some(5),
             regex_cob: None,
+            ..DataFilter::default()
         };
         assert!(filt.filter(msg183));
         assert!(!filt.filter(msg585));
@@ -159,6 +484,7 @@ mod tests {
             regex: None,
             node_id: Some(5),
             regex_cob: Regex::new("^58").ok(),
+            ..DataFilter::default()
         };
         assert!(filt.filter(msg183));
         assert!(!filt.filter(msg585));
@@ -167,6 +493,7 @@ mod tests {
             regex: None,
             node_id: Some(5),
             regex_cob: Regex::new("^18").ok(),
+            ..DataFilter::default()
         };
         assert!(filt.filter(msg183));
         assert!(filt.filter(msg585));
@@ -175,9 +502,50 @@ mod tests {
             regex: Regex::new("AB").ok(),
             node_id: Some(5),
             regex_cob: Regex::new("58").ok(),
+            ..DataFilter::default()
         };
         assert!(filt.filter(msg183));
         assert!(!filt.filter(msg585));
+
+        // The text regex runs on the COB-ID, type and data together, and with the other fields.
+        let filt = DataFilter {
+            regex_text: Regex::new("^183 PDO 01").ok(),
+            ..DataFilter::default()
+        };
+        assert!(!filt.filter(msg183));
+        assert!(filt.filter(msg585));
+
+        let filt = DataFilter {
+            regex_text: Regex::new("SDO.*AB FE").ok(),
+            node_id: Some(4),
+            ..DataFilter::default()
+        };
+        assert!(filt.filter(msg585));
+    }
+
+    #[test]
+    fn test_direction() {
+        let received = MessageCached::new(
+            0,
+            RxMessage {
+                timestamp: Instant::now(),
+                cob_id: 0x605,
+                data: [0x40, 0x18, 0x10, 0x01, 0, 0, 0, 0],
+                dlc: 8,
+            },
+        );
+        let mut sent = received.clone();
+        sent.direction = Direction::Tx;
+
+        // Frames sent by the viewer only.
+        let filt = DataFilter {
+            direction: Some(Direction::Tx),
+            ..DataFilter::default()
+        };
+        assert!(!filt.is_empty());
+        assert!(!filt.filter(&sent));
+        assert!(filt.filter(&received));
+        assert!(!DataFilter::default().filter(&sent));
     }
 
     #[test]
@@ -215,7 +583,11 @@ mod tests {
                 regex: None,
                 node_id: None,
                 regex_cob: None,
+                regex_text: None,
+                direction: None,
+                invert: false,
             })),
+            ..GlobalFilter::default()
         };
         assert!(!filt.filter(msg183));
         assert!(!filt.filter(msg585));
@@ -227,7 +599,11 @@ mod tests {
                 regex: None,
                 node_id: Some(3),
                 regex_cob: None,
+                regex_text: None,
+                direction: None,
+                invert: false,
             })),
+            ..GlobalFilter::default()
         };
         assert!(!filt.filter(msg183));
         assert!(filt.filter(msg585));
@@ -239,7 +615,11 @@ mod tests {
                 regex: None,
                 node_id: Some(5),
                 regex_cob: None,
+                regex_text: None,
+                direction: None,
+                invert: false,
             })),
+            ..GlobalFilter::default()
         };
         assert!(filt.filter(msg183));
         assert!(filt.filter(msg585));
@@ -251,7 +631,11 @@ mod tests {
                 regex: None,
                 node_id: None,
                 regex_cob: None,
+                regex_text: None,
+                direction: None,
+                invert: false,
             })),
+            ..GlobalFilter::default()
         };
         assert!(!filt.filter(msg183));
         assert!(filt.filter(msg585));
@@ -263,10 +647,264 @@ mod tests {
                 regex: None,
                 node_id: None,
                 regex_cob: None,
+                regex_text: None,
+                direction: None,
+                invert: false,
             })),
+            ..GlobalFilter::default()
         };
         assert!(filt.filter(msg183));
         assert!(filt.filter(msg585));
         assert!(filt.filter(msg80));
     }
+
+    #[test]
+    fn test_show_hide() {
+        let message = |cob_id| {
+            MessageCached::new(
+                0,
+                RxMessage {
+                    timestamp: Instant::now(),
+                    cob_id,
+                    data: [0; 8],
+                    dlc: 0,
+                },
+            )
+        };
+        let (msg183, msg185, msg283) = (message(0x183), message(0x185), message(0x283));
+        let rule = |node_id, invert| {
+            Rc::new(RefCell::new(DataFilter {
+                node_id: Some(node_id),
+                invert,
+                ..DataFilter::default()
+            }))
+        };
+
+        let mut filt = GlobalFilter::default();
+        filt.show.rules.push(RuleNode::Rule(rule(3, false)));
+        assert!(!filt.filter(&msg183));
+        assert!(filt.filter(&msg185));
+
+        // Hide wins over show.
+        filt.hide
+            .rules
+            .push(RuleNode::Rule(Rc::new(RefCell::new(DataFilter {
+                regex_cob: Regex::new("^283$").ok(),
+                regex_text: None,
+                ..DataFilter::default()
+            }))));
+        assert!(!filt.filter(&msg183));
+        assert!(filt.filter(&msg283));
+
+        // Empty rules are ignored.
+        filt.show.rules = vec![RuleNode::Rule(Rc::default())];
+        assert!(!filt.filter(&msg185));
+
+        let filt = GlobalFilter {
+            hide: RuleGroup {
+                operator: Operator::Or,
+                rules: vec![RuleNode::Rule(rule(3, true))],
+            },
+            ..GlobalFilter::default()
+        };
+        assert!(!filt.filter(&msg183));
+        assert!(filt.filter(&msg185));
+        assert_ne!(filt.rules(), GlobalFilter::default().rules());
+    }
+
+    #[test]
+    fn test_rule_groups() {
+        let message = |cob_id| {
+            MessageCached::new(
+                0,
+                RxMessage {
+                    timestamp: Instant::now(),
+                    cob_id,
+                    data: [0; 8],
+                    dlc: 0,
+                },
+            )
+        };
+        let rule = |node_id, regex_text: &str| {
+            RuleNode::Rule(Rc::new(RefCell::new(DataFilter {
+                node_id,
+                regex_text: Regex::new(regex_text).ok(),
+                ..DataFilter::default()
+            })))
+        };
+        // (node 3 AND PDO) OR EMCY
+        let filt = GlobalFilter {
+            show: RuleGroup {
+                operator: Operator::Or,
+                rules: vec![
+                    RuleNode::Group(RuleGroup {
+                        operator: Operator::And,
+                        rules: vec![rule(Some(3), ""), rule(None, "PDO")],
+                    }),
+                    rule(None, "EMCY"),
+                ],
+            },
+            ..GlobalFilter::default()
+        };
+        assert!(!filt.filter(&message(0x183)));
+        assert!(filt.filter(&message(0x583)));
+        assert!(filt.filter(&message(0x185)));
+        assert!(!filt.filter(&message(0x085)));
+
+        // Empty rules and groups don't take part.
+        let group = RuleGroup {
+            operator: Operator::And,
+            rules: vec![
+                RuleNode::Rule(Rc::default()),
+                RuleNode::Group(RuleGroup::default()),
+            ],
+        };
+        assert_eq!(group.matches(&message(0x183)), None);
+        let group = RuleGroup {
+            rules: vec![rule(Some(3), ""), RuleNode::Group(group)],
+            ..RuleGroup::default()
+        };
+        assert_eq!(group.matches(&message(0x183)), Some(true));
+        assert_eq!(group.matches(&message(0x184)), Some(false));
+
+        // The snapshot doesn't follow the rules edited afterwards.
+        let shared = Rc::new(RefCell::new(DataFilter::default()));
+        let filt = GlobalFilter {
+            show: RuleGroup {
+                operator: Operator::And,
+                rules: vec![RuleNode::Rule(shared.clone())],
+            },
+            ..GlobalFilter::default()
+        };
+        let rules = filt.rules();
+        shared.borrow_mut().node_id = Some(3);
+        assert_ne!(filt.rules(), rules);
+    }
+
+    #[test]
+    fn test_nodes() {
+        assert_eq!(node_of(0x080), None);
+        assert_eq!(node_of(0x100), None);
+        assert_eq!(node_of(0x000), None);
+        assert_eq!(node_of(0x7E5), None);
+        assert_eq!(node_of(0x180), None);
+        for cob_id in [0x085, 0x185, 0x285, 0x505, 0x585, 0x605, 0x705] {
+            assert_eq!(node_of(cob_id), Some(5), "{cob_id:03X}");
+        }
+
+        let nodes = NodeSet::parse("1-3, 7 0x10").unwrap();
+        assert!(nodes.contains(1) && nodes.contains(3) && nodes.contains(7));
+        assert!(nodes.contains(16));
+        assert!(!nodes.contains(4) && !nodes.contains(0));
+        assert!(NodeSet::parse("").unwrap().is_empty());
+        assert!(NodeSet::parse("0").is_err());
+        assert!(NodeSet::parse("128").is_err());
+        assert!(NodeSet::parse("8-1").is_err());
+        assert!(NodeSet::parse("a").is_err());
+
+        let message = |cob_id| {
+            MessageCached::new(
+                0,
+                RxMessage {
+                    timestamp: Instant::now(),
+                    cob_id,
+                    data: [0; 8],
+                    dlc: 0,
+                },
+            )
+        };
+        let mut filt = GlobalFilter {
+            nodes: Some(NodeSet::parse("3").unwrap()),
+            ..GlobalFilter::default()
+        };
+        assert!(!filt.filter(&message(0x583)));
+        assert!(!filt.filter(&message(0x703)));
+        assert!(filt.filter(&message(0x584)));
+        assert!(!filt.filter(&message(0x080)));
+        filt.hide_broadcast = true;
+        assert!(filt.filter(&message(0x080)));
+        assert!(filt.filter(&message(0x000)));
+        assert!(!filt.filter(&message(0x183)));
+    }
+
+    #[test]
+    fn test_classes() {
+        assert_eq!(Classes::of(0x000), Classes::NMT);
+        assert_eq!(Classes::of(0x080), Classes::SYNC);
+        assert_eq!(Classes::of(0x100), Classes::TIME);
+        assert_eq!(Classes::of(0x081), Classes::EMCY);
+        assert_eq!(Classes::of(0x1FF), Classes::TPDO1);
+        assert_eq!(Classes::of(0x203), Classes::RPDO1);
+        assert_eq!(Classes::of(0x483), Classes::TPDO4);
+        assert_eq!(Classes::of(0x57F), Classes::RPDO4);
+        assert_eq!(Classes::of(0x583), Classes::SDO_TX);
+        assert_eq!(Classes::of(0x603), Classes::SDO_RX);
+        assert_eq!(Classes::of(0x77F), Classes::HEARTBEAT);
+        assert_eq!(Classes::of(0x180), Classes::OTHER);
+        assert_eq!(Classes::of(0x7E5), Classes::OTHER);
+        assert_eq!(Classes::of(0x683), Classes::OTHER);
+
+        let message = |cob_id| {
+            MessageCached::new(
+                0,
+                RxMessage {
+                    timestamp: Instant::now(),
+                    cob_id,
+                    data: [0; 8],
+                    dlc: 0,
+                },
+            )
+        };
+        let filt = GlobalFilter {
+            ignore_class: Classes::HEARTBEAT | Classes::TPDO2,
+            ..GlobalFilter::default()
+        };
+        assert!(filt.filter(&message(0x703)));
+        assert!(filt.filter(&message(0x283)));
+        assert!(!filt.filter(&message(0x183)));
+        assert!(!filt.filter(&message(0x083)));
+    }
+
+    #[test]
+    fn test_byte_rules() {
+        let message = |cob_id, data: [u8; 8], dlc| {
+            MessageCached::new(
+                0,
+                RxMessage {
+                    timestamp: Instant::now(),
+                    cob_id,
+                    data,
+                    dlc,
+                },
+            )
+        };
+        let fault = ByteRule {
+            cob_id: Some(0x181),
+            offset: 0,
+            mask: 0x08,
+            value: 0x08,
+        };
+        assert!(fault.matches(&message(0x181, [0x2F, 0, 0, 0, 0, 0, 0, 0], 2)));
+        assert!(!fault.matches(&message(0x181, [0x27, 0, 0, 0, 0, 0, 0, 0], 2)));
+        assert!(!fault.matches(&message(0x182, [0x08, 0, 0, 0, 0, 0, 0, 0], 2)));
+        // The byte must be in the frame.
+        assert!(!fault.matches(&message(0x181, [0x08, 0, 0, 0, 0, 0, 0, 0], 0)));
+
+        let filt = GlobalFilter {
+            bytes: vec![
+                fault,
+                ByteRule {
+                    cob_id: None,
+                    offset: 7,
+                    mask: 0xFF,
+                    value: 0x55,
+                },
+            ],
+            ..GlobalFilter::default()
+        };
+        assert!(!filt.filter(&message(0x181, [0x08, 0, 0, 0, 0, 0, 0, 0], 1)));
+        assert!(!filt.filter(&message(0x285, [0, 0, 0, 0, 0, 0, 0, 0x55], 8)));
+        assert!(filt.filter(&message(0x285, [0, 0, 0, 0, 0, 0, 0, 0x55], 7)));
+        assert!(filt.filter(&message(0x181, [0; 8], 8)));
+    }
 }
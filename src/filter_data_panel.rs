@@ -1,5 +1,6 @@
-use crate::filter::DataFilter;
+use crate::{filter::DataFilter, filter_profile::RuleConfig, message_cached::Direction};
 use egui::TextEdit;
+use oze_canopen::canopen::NodeId;
 use regex::Regex;
 use std::{cell::RefCell, rc::Rc};
 
@@ -9,6 +10,9 @@ pub struct FilterDataPanel {
     node_raw: String,
     regex_raw: String,
     regex_cob_raw: String,
+    regex_text_raw: String,
+    /// Why the text regex doesn't compile.
+    regex_text_error: Option<String>,
 }
 
 impl FilterDataPanel {
@@ -17,10 +21,90 @@ impl FilterDataPanel {
             data_filter,
             regex_raw: String::new(),
             regex_cob_raw: String::new(),
+            regex_text_raw: String::new(),
+            regex_text_error: None,
             node_raw: String::new(),
         }
     }
 
+    /// Sets the node ID filter as if it was typed in the panel.
+    pub fn set_node_id(&mut self, node_id: Option<NodeId>) {
+        self.node_raw = node_id.map(|n| n.to_string()).unwrap_or_default();
+        self.data_filter.borrow_mut().node_id = node_id;
+    }
+
+    /// Sets the COB-ID filter to match exactly the given COB-ID.
+    pub fn set_cob_id(&mut self, cob_id: Option<u16>) {
+        self.regex_cob_raw = cob_id.map(|c| format!("^{c:03X}$")).unwrap_or_default();
+        self.data_filter.borrow_mut().regex_cob =
+            cob_id.and_then(|_| Regex::new(&self.regex_cob_raw).ok());
+    }
+
+    /// Panel with a filter of its own set from a saved rule.
+    pub fn from_config(config: &RuleConfig) -> Self {
+        let mut panel = Self::new(Rc::default());
+        panel.set_config(config);
+        panel
+    }
+
+    /// Fields as typed in the panel.
+    pub fn config(&self) -> RuleConfig {
+        RuleConfig {
+            node: self.node_raw.clone(),
+            regex: self.regex_raw.clone(),
+            regex_cob: self.regex_cob_raw.clone(),
+            regex_text: self.regex_text_raw.clone(),
+            direction: self.data_filter.borrow().direction,
+            invert: self.data_filter.borrow().invert,
+        }
+    }
+
+    /// Sets the fields as if they were typed in the panel.
+    pub fn set_config(&mut self, config: &RuleConfig) {
+        self.node_raw.clone_from(&config.node);
+        self.regex_raw.clone_from(&config.regex);
+        self.regex_cob_raw.clone_from(&config.regex_cob);
+        self.regex_text_raw.clone_from(&config.regex_text);
+        let mut data_filter = self.data_filter.borrow_mut();
+        data_filter.node_id = self.node_raw.parse().ok();
+        data_filter.regex = Regex::new(&self.regex_raw).ok();
+        data_filter.regex_cob = Regex::new(&self.regex_cob_raw).ok();
+        data_filter.regex_text =
+            Self::compile_text(&self.regex_text_raw, &mut self.regex_text_error);
+        data_filter.direction = config.direction;
+        data_filter.invert = config.invert;
+    }
+
+    /// Compiles the text regex, keeping the error to show it.
+    fn compile_text(raw: &str, error: &mut Option<String>) -> Option<Regex> {
+        *error = None;
+        if raw.is_empty() {
+            return None;
+        }
+        Regex::new(raw)
+            .map_err(|e| *error = Some(e.to_string()))
+            .ok()
+    }
+
+    /// Button cycling through both directions, RX and TX, returns true if clicked.
+    fn direction_ui(ui: &mut egui::Ui, direction: &mut Option<Direction>) -> bool {
+        let text = direction.map_or("RX+TX", Direction::as_str);
+        let clicked = ui
+            .button(text)
+            .on_hover_text(
+                "Frames received from the other nodes (RX), sent by the viewer (TX) or both, click to change",
+            )
+            .clicked();
+        if clicked {
+            *direction = match direction {
+                None => Some(Direction::Rx),
+                Some(Direction::Rx) => Some(Direction::Tx),
+                Some(Direction::Tx) => None,
+            };
+        }
+        clicked
+    }
+
     /// # Panics
     pub fn update(&mut self, ui: &mut egui::Ui) -> bool {
         let mut data_filter = self.data_filter.try_borrow_mut().unwrap();
@@ -61,6 +145,30 @@ impl FilterDataPanel {
             changed = true;
             data_filter.regex = Regex::new(&self.regex_raw).ok();
         }
+        if ui
+            .add(
+                TextEdit::singleline(&mut self.regex_text_raw)
+                    .hint_text("text regex")
+                    .desired_width(200.0),
+            )
+            .on_hover_text(
+                "Regex matched against the COB-ID, type, hex data and decoded info of the row. For exemple: 'SDO.*6041' or 'EMCY.*81[0-9A-F]{2}'",
+            )
+            .changed()
+        {
+            changed = true;
+            data_filter.regex_text =
+            Self::compile_text(&self.regex_text_raw, &mut self.regex_text_error);
+        }
+        if let Some(error) = &self.regex_text_error {
+            ui.colored_label(egui::Color32::RED, "invalid regex")
+                .on_hover_text(error);
+        }
+        changed |= Self::direction_ui(ui, &mut data_filter.direction);
+        changed |= ui
+            .checkbox(&mut data_filter.invert, "not")
+            .on_hover_text("Match the messages this rule doesn't match")
+            .changed();
         ui.separator();
         changed
     }
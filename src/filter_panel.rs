@@ -1,13 +1,25 @@
 use crate::{
-    filter::{self, GlobalFilter},
+    filter::{self, Classes, GlobalFilter, NodeSet},
     filter_data_panel::FilterDataPanel,
+    filter_profile::{ByteRuleConfig, FilterProfile, PinnedConfig},
+    message_cached::MessageCached,
+    rule_group_panel::RuleGroupPanel,
 };
-use std::{cell::RefCell, rc::Rc};
+use std::{cell::RefCell, collections::VecDeque, rc::Rc};
 
 #[derive(Debug)]
 pub struct FilterPanel {
     pub global_filter: Rc<RefCell<GlobalFilter>>,
     pub data_panel: FilterDataPanel,
+    /// Rules of `GlobalFilter::show`.
+    show: RuleGroupPanel,
+    /// Rules of `GlobalFilter::hide`.
+    hide: RuleGroupPanel,
+    /// Rules of `GlobalFilter::bytes` as typed, the invalid ones aren't applied.
+    byte_rules: Vec<ByteRuleConfig>,
+    nodes_raw: String,
+    /// Why the node list can't be parsed.
+    nodes_error: Option<String>,
     pub stop: bool,
 }
 
@@ -17,15 +29,156 @@ impl FilterPanel {
         Self {
             data_panel,
             global_filter,
+            show: RuleGroupPanel::default(),
+            hide: RuleGroupPanel::default(),
+            byte_rules: Vec::new(),
+            nodes_raw: String::new(),
+            nodes_error: None,
             stop: false,
         }
     }
 
-    pub fn update(&mut self, ui: &mut egui::Ui) -> Option<FilterDataPanel> {
+    /// Setup of the panel, the pinned filters are given by the caller.
+    pub fn profile(&self, pinned: Vec<PinnedConfig>) -> FilterProfile {
+        FilterProfile {
+            ignore_type: self.global_filter.borrow().ignore_type,
+            ignore_class: self.global_filter.borrow().ignore_class,
+            data: self.data_panel.config(),
+            show: self.show.config(),
+            hide: self.hide.config(),
+            nodes: self.nodes_raw.clone(),
+            hide_broadcast: self.global_filter.borrow().hide_broadcast,
+            bytes: self.byte_rules.clone(),
+            pinned,
+        }
+    }
+
+    /// Replaces the filters by the ones of a profile, except the pinned filters.
+    pub fn apply_profile(&mut self, profile: &FilterProfile) {
+        self.data_panel.set_config(&profile.data);
+        self.show = RuleGroupPanel::from_config(&profile.show);
+        self.hide = RuleGroupPanel::from_config(&profile.hide);
+        self.nodes_raw.clone_from(&profile.nodes);
+        self.parse_nodes();
+        self.byte_rules.clone_from(&profile.bytes);
+        self.parse_byte_rules();
+        let mut global_filter = self.global_filter.borrow_mut();
+        global_filter.ignore_type = profile.ignore_type;
+        global_filter.ignore_class = profile.ignore_class;
+        global_filter.hide_broadcast = profile.hide_broadcast;
+        global_filter.show = self.show.group();
+        global_filter.hide = self.hide.group();
+    }
+
+    /// Adds a show rule, e.g. from the menu of a row. It can be removed like the rules added here.
+    pub fn add_show_rule(&mut self, rule: FilterDataPanel) {
+        self.show.push(rule);
+        self.global_filter.borrow_mut().show = self.show.group();
+    }
+
+    /// Adds a hide rule, e.g. from the menu of a row.
+    pub fn add_hide_rule(&mut self, rule: FilterDataPanel) {
+        self.hide.push(rule);
+        self.global_filter.borrow_mut().hide = self.hide.group();
+    }
+
+    /// Sets the node filter from the node list typed, none if the list is empty or invalid.
+    fn parse_nodes(&mut self) {
+        let nodes = NodeSet::parse(&self.nodes_raw);
+        self.nodes_error = nodes.as_ref().err().cloned();
+        self.global_filter.borrow_mut().nodes = nodes.ok().filter(|nodes| !nodes.is_empty());
+    }
+
+    /// Sets the byte rules of the filter from the valid ones typed.
+    fn parse_byte_rules(&mut self) {
+        self.global_filter.borrow_mut().bytes = self
+            .byte_rules
+            .iter()
+            .filter_map(|rule| rule.parse().ok())
+            .collect();
+    }
+
+    /// List of byte rules with a button to add one and a button per rule to remove it.
+    fn byte_rules_ui(&mut self, ui: &mut egui::Ui) {
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            ui.label("Bytes").on_hover_text(
+                "Show only the frames whose byte at the offset, masked, equals the value, e.g. offset 0, mask 08 and value 08 for bit 3 of the first byte",
+            );
+            if ui.button("➕").on_hover_text("Add a rule").clicked() {
+                self.byte_rules.push(ByteRuleConfig {
+                    offset: "0".to_owned(),
+                    ..ByteRuleConfig::default()
+                });
+                changed = true;
+            }
+        });
+
+        let mut remove = None;
+        for (i, rule) in self.byte_rules.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                for (raw, hint, width) in [
+                    (&mut rule.cob_id, "COB-ID", 55.0),
+                    (&mut rule.offset, "offset", 40.0),
+                    (&mut rule.mask, "mask FF", 55.0),
+                    (&mut rule.value, "value", 40.0),
+                ] {
+                    changed |= ui
+                        .add(
+                            egui::TextEdit::singleline(raw)
+                                .hint_text(hint)
+                                .desired_width(width),
+                        )
+                        .changed();
+                }
+                if ui.button("❌").on_hover_text("Remove the rule").clicked() {
+                    remove = Some(i);
+                }
+                if let Err(e) = rule.parse() {
+                    ui.colored_label(egui::Color32::RED, e);
+                }
+            });
+        }
+        if let Some(i) = remove {
+            self.byte_rules.remove(i);
+            changed = true;
+        }
+        if changed {
+            self.parse_byte_rules();
+        }
+    }
+
+    /// Checkboxes of the function code classes with the number of buffered messages of each.
+    fn classes_ui(&mut self, ui: &mut egui::Ui, data: &VecDeque<MessageCached>) {
+        let mut counts = [0usize; 16];
+        for msg in data {
+            counts[Classes::of(msg.msg.msg.cob_id).bits().trailing_zeros() as usize] += 1;
+        }
+        ui.horizontal_wrapped(|ui| {
+            let mut global_filter = self.global_filter.borrow_mut();
+            for (name, class) in Classes::all().iter_names() {
+                let mut shown = !global_filter.ignore_class.contains(class);
+                let count = counts[class.bits().trailing_zeros() as usize];
+                if ui
+                    .checkbox(&mut shown, format!("{name} ({count})"))
+                    .changed()
+                {
+                    global_filter.ignore_class.set(class, !shown);
+                }
+            }
+        });
+    }
+
+    pub fn update(
+        &mut self,
+        ui: &mut egui::Ui,
+        data: &VecDeque<MessageCached>,
+    ) -> Option<FilterDataPanel> {
         let mut changed = false;
         ui.horizontal(|ui| {
             if ui
                 .button(if self.stop { "START" } else { "STOP " })
+                .on_hover_text("Pause the message list, statistics and node tracking keep running")
                 .clicked()
             {
                 self.stop = !self.stop;
@@ -71,6 +224,60 @@ impl FilterPanel {
             }
         });
 
+        ui.collapsing("Function codes", |ui| self.classes_ui(ui, data))
+            .header_response
+            .on_hover_text(
+                "Show or hide the messages by the function code of their COB-ID, with the number of buffered messages of each",
+            );
+
+        ui.horizontal(|ui| {
+            if ui
+                .add(
+                    egui::TextEdit::singleline(&mut self.nodes_raw)
+                        .hint_text("nodes")
+                        .desired_width(100.0),
+                )
+                .on_hover_text(
+                    "Show only the messages of these nodes, e.g. '3, 7' or '1-8'. The node is taken from the COB-ID: EMCY, PDOs, SDO and heartbeat",
+                )
+                .changed()
+            {
+                self.parse_nodes();
+            }
+            let mut global_filter = self.global_filter.borrow_mut();
+            let mut broadcast = !global_filter.hide_broadcast;
+            if ui
+                .add_enabled(
+                    global_filter.nodes.is_some(),
+                    egui::Checkbox::new(&mut broadcast, "broadcast"),
+                )
+                .on_hover_text("Also show NMT, SYNC, TIME and LSS, which belong to no node")
+                .changed()
+            {
+                global_filter.hide_broadcast = !broadcast;
+            }
+            if let Some(error) = &self.nodes_error {
+                ui.colored_label(egui::Color32::RED, error);
+            }
+        });
+
+        self.byte_rules_ui(ui);
+
+        if self.show.update(
+            ui,
+            "Show only",
+            "Show only the messages matching these rules. OR and AND combine the rules of a group, e.g. (node 3 AND PDO) OR EMCY",
+        ) {
+            self.global_filter.borrow_mut().show = self.show.group();
+        }
+        if self.hide.update(
+            ui,
+            "Hide",
+            "Hide the messages matching these rules, even if the show rules match them",
+        ) {
+            self.global_filter.borrow_mut().hide = self.hide.group();
+        }
+
         to_add_fixed_filter
     }
 }
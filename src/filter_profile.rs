@@ -0,0 +1,544 @@
+use crate::{
+    config,
+    filter::{ByteRule, Classes, Flags, Operator},
+    message_cached::Direction,
+    pinned_filter::Retention,
+};
+use bincode::Options;
+use egui::TextEdit;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// File of the saved profiles in the config directory.
+const CONFIG_FILE: &str = "filter_profiles_v2.bin";
+
+/// File of the profiles saved before the rules could be grouped, read if there is no other.
+const LEGACY_CONFIG_FILE: &str = "filter_profiles.bin";
+
+/// Fields of a data filter as typed in the filter panel.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RuleConfig {
+    pub node: String,
+    pub regex: String,
+    pub regex_cob: String,
+    pub regex_text: String,
+    pub direction: Option<Direction>,
+    pub invert: bool,
+}
+
+impl RuleConfig {
+    /// Fields set, e.g. `node 3, COB-ID ^181$`, "any message" if none.
+    pub fn summary(&self) -> String {
+        let fields: Vec<String> = [
+            ("node", &self.node),
+            ("COB-ID", &self.regex_cob),
+            ("data", &self.regex),
+            ("text", &self.regex_text),
+        ]
+        .into_iter()
+        .filter(|(_, raw)| !raw.is_empty())
+        .map(|(name, raw)| format!("{name} {raw}"))
+        .chain(
+            self.direction
+                .map(|direction| direction.as_str().to_owned()),
+        )
+        .collect();
+        let summary = if fields.is_empty() {
+            "any message".to_owned()
+        } else {
+            fields.join(", ")
+        };
+        if self.invert {
+            format!("not {summary}")
+        } else {
+            summary
+        }
+    }
+}
+
+/// Pinned filter: its rule, label, layout and retention.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PinnedConfig {
+    pub rule: RuleConfig,
+    pub label: String,
+    pub collapsed: bool,
+    pub retention: Retention,
+}
+
+/// Rule of a group as typed in the filter panel.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RuleNodeConfig {
+    Rule(RuleConfig),
+    Group(RuleGroupConfig),
+}
+
+/// Group of rules as typed in the filter panel, see `filter::RuleGroup`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RuleGroupConfig {
+    pub operator: Operator,
+    pub rules: Vec<RuleNodeConfig>,
+}
+
+impl RuleGroupConfig {
+    /// `Or` group of flat rules, as the show and hide lists were before groups.
+    fn flat(rules: Vec<LegacyRule>) -> Self {
+        Self {
+            operator: Operator::Or,
+            rules: rules
+                .into_iter()
+                .map(|rule| RuleNodeConfig::Rule(rule.into()))
+                .collect(),
+        }
+    }
+}
+
+/// Fields of a byte rule as typed in the filter panel, in hex except the offset.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ByteRuleConfig {
+    pub cob_id: String,
+    pub offset: String,
+    pub mask: String,
+    pub value: String,
+}
+
+impl ByteRuleConfig {
+    /// Rule of the fields, an empty COB-ID applies it to every frame and an empty mask is 0xFF.
+    ///
+    /// # Errors
+    /// If a field isn't a valid number for its place.
+    pub fn parse(&self) -> Result<ByteRule, String> {
+        let hex = |name: &str, raw: &str, max: u16| {
+            let raw = raw.trim();
+            u16::from_str_radix(raw.strip_prefix("0x").unwrap_or(raw), 16)
+                .ok()
+                .filter(|number| *number <= max)
+                .ok_or_else(|| format!("{name} '{raw}' isn't hex up to {max:X}"))
+        };
+        let cob_id = match self.cob_id.trim() {
+            "" => None,
+            raw => Some(hex("COB-ID", raw, 0x7FF)?),
+        };
+        let offset = self
+            .offset
+            .trim()
+            .parse()
+            .ok()
+            .filter(|offset| *offset < 8)
+            .ok_or_else(|| format!("offset '{}' isn't 0 to 7", self.offset.trim()))?;
+        let mask = match self.mask.trim() {
+            "" => 0xFF,
+            raw => hex("mask", raw, 0xFF)?,
+        };
+        let value = hex("value", &self.value, 0xFF)?;
+        Ok(ByteRule {
+            cob_id,
+            offset,
+            mask: u8::try_from(mask).unwrap_or(u8::MAX),
+            value: u8::try_from(value).unwrap_or(u8::MAX),
+        })
+    }
+}
+
+/// Filter setup saved under a name: the filter panel and the pinned filters.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FilterProfile {
+    pub ignore_type: Flags,
+    pub ignore_class: Classes,
+    pub data: RuleConfig,
+    pub show: RuleGroupConfig,
+    pub hide: RuleGroupConfig,
+    /// Node list as typed.
+    pub nodes: String,
+    pub hide_broadcast: bool,
+    pub bytes: Vec<ByteRuleConfig>,
+    /// Pinned filters in their order.
+    pub pinned: Vec<PinnedConfig>,
+}
+
+/// Rule as saved before the rules had a direction.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct LegacyRule {
+    node: String,
+    regex: String,
+    regex_cob: String,
+    regex_text: String,
+    invert: bool,
+}
+
+impl From<LegacyRule> for RuleConfig {
+    fn from(legacy: LegacyRule) -> Self {
+        Self {
+            node: legacy.node,
+            regex: legacy.regex,
+            regex_cob: legacy.regex_cob,
+            regex_text: legacy.regex_text,
+            direction: None,
+            invert: legacy.invert,
+        }
+    }
+}
+
+/// Profile as saved before the show and hide rules could be grouped.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct LegacyProfile {
+    ignore_type: Flags,
+    ignore_class: Classes,
+    data: LegacyRule,
+    show: Vec<LegacyRule>,
+    hide: Vec<LegacyRule>,
+    nodes: String,
+    hide_broadcast: bool,
+    bytes: Vec<ByteRuleConfig>,
+    pinned: Vec<LegacyRule>,
+}
+
+impl From<LegacyProfile> for FilterProfile {
+    fn from(legacy: LegacyProfile) -> Self {
+        Self {
+            ignore_type: legacy.ignore_type,
+            ignore_class: legacy.ignore_class,
+            data: legacy.data.into(),
+            show: RuleGroupConfig::flat(legacy.show),
+            hide: RuleGroupConfig::flat(legacy.hide),
+            nodes: legacy.nodes,
+            hide_broadcast: legacy.hide_broadcast,
+            bytes: legacy.bytes,
+            pinned: legacy
+                .pinned
+                .into_iter()
+                .map(|rule| PinnedConfig {
+                    rule: rule.into(),
+                    ..PinnedConfig::default()
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Decodes the whole of `bytes`, so a file of the other format isn't read by mistake.
+fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, bincode::Error> {
+    bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .reject_trailing_bytes()
+        .deserialize(bytes)
+}
+
+impl FilterProfile {
+    /// Writes the profile to a file of its own, to share it.
+    ///
+    /// # Errors
+    /// If the file can't be written.
+    pub fn export(&self, path: &str) -> Result<(), String> {
+        let bytes = bincode::serialize(self).map_err(|e| e.to_string())?;
+        std::fs::write(path, bytes).map_err(|e| format!("{path}: {e}"))
+    }
+
+    /// Reads a profile written by `export`, also by the versions without rule groups.
+    ///
+    /// # Errors
+    /// If the file can't be read or isn't a profile.
+    pub fn import(path: &str) -> Result<Self, String> {
+        let bytes = std::fs::read(path).map_err(|e| format!("{path}: {e}"))?;
+        decode(&bytes)
+            .or_else(|e| {
+                decode::<LegacyProfile>(&bytes)
+                    .map(Self::from)
+                    .map_err(|_| e)
+            })
+            .map_err(|e| format!("{path}: not a filter profile ({e})"))
+    }
+}
+
+/// Profiles saved by the user and the last one applied.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct Saved {
+    profiles: BTreeMap<String, FilterProfile>,
+    last: Option<String>,
+}
+
+impl From<LegacySaved> for Saved {
+    fn from(legacy: LegacySaved) -> Self {
+        Self {
+            profiles: legacy
+                .profiles
+                .into_iter()
+                .map(|(name, profile)| (name, profile.into()))
+                .collect(),
+            last: legacy.last,
+        }
+    }
+}
+
+/// `Saved` before the rules could be grouped.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct LegacySaved {
+    profiles: BTreeMap<String, LegacyProfile>,
+    last: Option<String>,
+}
+
+/// Named filter profiles, restored from the config directory at startup.
+#[derive(Debug, Default)]
+pub struct FilterProfiles {
+    saved: Saved,
+    name_raw: String,
+    path_raw: String,
+    /// Outcome of the last save, export or import.
+    status: Option<Result<String, String>>,
+}
+
+impl FilterProfiles {
+    /// Profiles saved by a previous run.
+    pub fn load() -> Self {
+        Self {
+            saved: config::load(CONFIG_FILE)
+                .or_else(|| config::load::<LegacySaved>(LEGACY_CONFIG_FILE).map(Saved::from))
+                .unwrap_or_default(),
+            ..Self::default()
+        }
+    }
+
+    /// Profile applied last, to restore it at startup.
+    pub fn last(&self) -> Option<&FilterProfile> {
+        self.saved.profiles.get(self.saved.last.as_ref()?)
+    }
+
+    /// Profile saved under `name`.
+    pub fn get(&self, name: &str) -> Option<&FilterProfile> {
+        self.saved.profiles.get(name)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.saved.profiles.keys().map(String::as_str)
+    }
+
+    fn save(&mut self) {
+        if let Err(e) = config::save(CONFIG_FILE, &self.saved) {
+            log::warn!("Can't save the filter profiles: {e}");
+            self.status = Some(Err(e));
+        }
+    }
+
+    /// Profile selector, save, delete, export and import, returns the profile to apply.
+    /// `current` gives the setup shown in the filter panel when saving or exporting.
+    pub fn update(
+        &mut self,
+        ui: &mut egui::Ui,
+        current: impl Fn() -> FilterProfile,
+    ) -> Option<FilterProfile> {
+        let mut apply = None;
+        ui.horizontal(|ui| {
+            let selected = self.saved.last.clone().unwrap_or_default();
+            egui::ComboBox::from_id_salt("filter_profile")
+                .selected_text(if selected.is_empty() {
+                    "profile"
+                } else {
+                    &selected
+                })
+                .show_ui(ui, |ui| {
+                    for (name, profile) in &self.saved.profiles {
+                        if ui.selectable_label(*name == selected, name).clicked() {
+                            apply = Some(profile.clone());
+                            self.saved.last = Some(name.clone());
+                            self.name_raw.clone_from(name);
+                        }
+                    }
+                })
+                .response
+                .on_hover_text("Apply a saved filter profile");
+            if apply.is_some() {
+                self.save();
+            }
+
+            ui.add(
+                TextEdit::singleline(&mut self.name_raw)
+                    .hint_text("profile name")
+                    .desired_width(120.0),
+            );
+            let name = self.name_raw.trim().to_owned();
+            if ui
+                .add_enabled(!name.is_empty(), egui::Button::new("💾 Save"))
+                .on_hover_text("Save the filters and the pinned filters under this name")
+                .clicked()
+            {
+                self.saved.profiles.insert(name.clone(), current());
+                self.saved.last = Some(name.clone());
+                self.status = Some(Ok(format!("Saved {name}")));
+                self.save();
+            }
+            if ui
+                .add_enabled(
+                    self.saved.profiles.contains_key(&name),
+                    egui::Button::new("🗑"),
+                )
+                .on_hover_text("Delete the profile of this name")
+                .clicked()
+            {
+                self.saved.profiles.remove(&name);
+                if self.saved.last.as_ref() == Some(&name) {
+                    self.saved.last = None;
+                }
+                self.status = None;
+                self.save();
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.add(
+                TextEdit::singleline(&mut self.path_raw)
+                    .hint_text("path/to/profile.bin")
+                    .desired_width(250.0),
+            );
+            let path = self.path_raw.trim().to_owned();
+            if ui
+                .add_enabled(!path.is_empty(), egui::Button::new("Export"))
+                .on_hover_text("Write the current filters to this file to share them")
+                .clicked()
+            {
+                self.status = Some(
+                    current()
+                        .export(&path)
+                        .map(|()| format!("Exported to {path}")),
+                );
+            }
+            if ui
+                .add_enabled(!path.is_empty(), egui::Button::new("Import"))
+                .on_hover_text("Apply the profile of this file, save it to keep it")
+                .clicked()
+            {
+                match FilterProfile::import(&path) {
+                    Ok(profile) => {
+                        apply = Some(profile);
+                        self.status = Some(Ok(format!("Imported {path}")));
+                    }
+                    Err(e) => self.status = Some(Err(e)),
+                }
+            }
+        });
+
+        match &self.status {
+            Some(Ok(status)) => {
+                ui.label(status);
+            }
+            Some(Err(e)) => {
+                ui.colored_label(egui::Color32::RED, e);
+            }
+            None => {}
+        }
+        apply
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        ByteRuleConfig, FilterProfile, LegacyProfile, LegacyRule, PinnedConfig, RuleConfig,
+        RuleGroupConfig, RuleNodeConfig,
+    };
+    use crate::{
+        filter::{ByteRule, Flags, Operator},
+        message_cached::Direction,
+        pinned_filter::Retention,
+    };
+
+    #[test]
+    fn test_byte_rule() {
+        let config = |cob_id: &str, offset: &str, mask: &str, value: &str| ByteRuleConfig {
+            cob_id: cob_id.to_owned(),
+            offset: offset.to_owned(),
+            mask: mask.to_owned(),
+            value: value.to_owned(),
+        };
+        assert_eq!(
+            config("181", "0", "0x08", "08").parse(),
+            Ok(ByteRule {
+                cob_id: Some(0x181),
+                offset: 0,
+                mask: 0x08,
+                value: 0x08,
+            })
+        );
+        assert_eq!(
+            config(" ", "7", "", "ff").parse(),
+            Ok(ByteRule {
+                cob_id: None,
+                offset: 7,
+                mask: 0xFF,
+                value: 0xFF,
+            })
+        );
+        assert!(config("800", "0", "", "0").parse().is_err());
+        assert!(config("", "8", "", "0").parse().is_err());
+        assert!(config("", "0", "100", "0").parse().is_err());
+        assert!(config("", "0", "", "").parse().is_err());
+        assert!(config("", "0", "", "g").parse().is_err());
+    }
+
+    #[test]
+    fn test_export_import() {
+        let profile = FilterProfile {
+            ignore_type: Flags::SYNC | Flags::GUARD,
+            show: RuleGroupConfig {
+                operator: Operator::Or,
+                rules: vec![
+                    RuleNodeConfig::Group(RuleGroupConfig {
+                        operator: Operator::And,
+                        rules: vec![
+                            RuleNodeConfig::Rule(RuleConfig {
+                                node: "3".to_owned(),
+                                ..RuleConfig::default()
+                            }),
+                            RuleNodeConfig::Rule(RuleConfig {
+                                regex_text: "PDO".to_owned(),
+                                ..RuleConfig::default()
+                            }),
+                        ],
+                    }),
+                    RuleNodeConfig::Rule(RuleConfig {
+                        node: "7".to_owned(),
+                        ..RuleConfig::default()
+                    }),
+                ],
+            },
+            pinned: vec![PinnedConfig {
+                rule: RuleConfig {
+                    regex_text: "EMCY".to_owned(),
+                    direction: Some(Direction::Rx),
+                    invert: true,
+                    ..RuleConfig::default()
+                },
+                label: "Faults".to_owned(),
+                collapsed: true,
+                retention: Retention::Seconds(3600),
+            }],
+            ..FilterProfile::default()
+        };
+        let path = std::env::temp_dir().join(format!("filter-profile-{}.bin", std::process::id()));
+        let path = path.to_str().unwrap();
+        profile.export(path).unwrap();
+        assert_eq!(FilterProfile::import(path), Ok(profile));
+        std::fs::write(path, [1, 2]).unwrap();
+        assert!(FilterProfile::import(path).is_err());
+
+        // Profiles exported before the groups read as flat `Or` groups.
+        let legacy = LegacyProfile {
+            show: vec![LegacyRule {
+                node: "3".to_owned(),
+                ..LegacyRule::default()
+            }],
+            pinned: vec![LegacyRule::default()],
+            ..LegacyProfile::default()
+        };
+        std::fs::write(path, bincode::serialize(&legacy).unwrap()).unwrap();
+        let profile = FilterProfile::import(path).unwrap();
+        assert_eq!(
+            profile.show.rules,
+            vec![RuleNodeConfig::Rule(RuleConfig {
+                node: "3".to_owned(),
+                ..RuleConfig::default()
+            })]
+        );
+        assert_eq!(profile.show.operator, Operator::Or);
+        assert_eq!(profile.pinned, vec![PinnedConfig::default()]);
+        std::fs::remove_file(path).unwrap();
+    }
+}
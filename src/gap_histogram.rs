@@ -0,0 +1,174 @@
+use crate::theme::OZON_PINK;
+use egui::{Color32, DragValue};
+use egui_plot::{Bar, BarChart, Plot};
+use std::ops::RangeInclusive;
+
+/// Smallest gap of the first bucket, in milliseconds.
+const MIN_GAP: f64 = 0.05;
+/// Largest gap of the last bucket, in milliseconds.
+const MAX_GAP: f64 = 1000.0;
+
+/// Default number of buckets between [`MIN_GAP`] and [`MAX_GAP`].
+const DEFAULT_BUCKETS: usize = 30;
+
+/// Allowed number of buckets.
+pub const BUCKETS_RANGE: RangeInclusive<usize> = 5..=100;
+
+/// Width of a bucket in decades.
+#[allow(clippy::cast_precision_loss)]
+fn bucket_width(buckets: usize) -> f64 {
+    (MAX_GAP / MIN_GAP).log10() / buckets as f64
+}
+
+/// Gaps at the edges of a bucket, in milliseconds.
+#[allow(clippy::cast_precision_loss)]
+pub fn bucket_range(bucket: usize, buckets: usize) -> (f64, f64) {
+    let width = bucket_width(buckets);
+    let low = MIN_GAP.log10() + width * bucket as f64;
+    (10f64.powf(low), 10f64.powf(low + width))
+}
+
+/// Counts the gaps, in milliseconds, in log-scaled buckets.
+///
+/// Gaps below [`MIN_GAP`] land in the first bucket, gaps above [`MAX_GAP`] in the last one.
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    clippy::cast_precision_loss
+)]
+pub fn histogram(gaps: impl IntoIterator<Item = f64>, buckets: usize) -> Vec<u64> {
+    let mut counts = vec![0; buckets];
+    let width = bucket_width(buckets);
+    for gap in gaps {
+        let position = (gap.max(MIN_GAP).log10() - MIN_GAP.log10()) / width;
+        let bucket = (position.max(0.0) as usize).min(buckets - 1);
+        counts[bucket] += 1;
+    }
+    counts
+}
+
+/// Histogram of the inter-frame gaps of the bus statistics.
+#[derive(Debug)]
+pub struct GapHistogram {
+    buckets: usize,
+    /// Keep showing `counts` instead of the live gaps.
+    frozen: bool,
+    counts: Vec<u64>,
+    selected: Option<usize>,
+}
+
+impl Default for GapHistogram {
+    fn default() -> Self {
+        Self {
+            buckets: DEFAULT_BUCKETS,
+            frozen: false,
+            counts: vec![0; DEFAULT_BUCKETS],
+            selected: None,
+        }
+    }
+}
+
+impl GapHistogram {
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        clippy::cast_precision_loss
+    )]
+    pub fn update(&mut self, ui: &mut egui::Ui, gaps: impl IntoIterator<Item = f64>) {
+        ui.horizontal(|ui| {
+            ui.label("Buckets:");
+            ui.add_enabled(
+                !self.frozen,
+                DragValue::new(&mut self.buckets).range(BUCKETS_RANGE),
+            );
+            ui.toggle_value(&mut self.frozen, "❄ Freeze")
+                .on_hover_text("Keep the current histogram while the gaps keep being measured");
+        });
+        if !self.frozen {
+            if self.counts.len() != self.buckets {
+                self.selected = None;
+            }
+            self.counts = histogram(gaps, self.buckets);
+        }
+
+        let width = bucket_width(self.counts.len());
+        let bars = self
+            .counts
+            .iter()
+            .enumerate()
+            .map(|(bucket, count)| {
+                let center = MIN_GAP.log10() + width * (bucket as f64 + 0.5);
+                let bar = Bar::new(center, *count as f64).width(width * 0.9);
+                if self.selected == Some(bucket) {
+                    bar.fill(Color32::YELLOW)
+                } else {
+                    bar.fill(OZON_PINK)
+                }
+            })
+            .collect();
+
+        let response = Plot::new("gap_histogram")
+            .height(150.0)
+            .allow_drag(false)
+            .allow_boxed_zoom(false)
+            .allow_scroll(false)
+            .allow_zoom(false)
+            .x_axis_label("Gap (ms)")
+            .x_axis_formatter(|mark, _| format!("{}", 10f64.powf(mark.value)))
+            .label_formatter(|_, value| format!("{:.3} ms", 10f64.powf(value.x)))
+            .show(ui, |plot_ui| {
+                plot_ui.bar_chart(BarChart::new(bars));
+                plot_ui
+                    .response()
+                    .clicked()
+                    .then(|| plot_ui.pointer_coordinate())
+                    .flatten()
+            });
+        if let Some(point) = response.inner {
+            let position = (point.x - MIN_GAP.log10()) / width;
+            self.selected = (0.0..self.counts.len() as f64)
+                .contains(&position)
+                .then_some(position.floor() as usize);
+        }
+
+        let total: u64 = self.counts.iter().sum();
+        match self
+            .selected
+            .and_then(|bucket| Some((bucket, *self.counts.get(bucket)?)))
+        {
+            Some((bucket, count)) => {
+                let (low, high) = bucket_range(bucket, self.counts.len());
+                let percent = if total > 0 {
+                    count as f64 / total as f64 * 100.0
+                } else {
+                    0.0
+                };
+                ui.label(format!("{low:.3}–{high:.3} ms: {count} ({percent:.1}%)"));
+            }
+            None => {
+                ui.weak(format!("{total} gaps, click a bar for its count"));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{bucket_range, histogram};
+
+    #[test]
+    fn test_histogram() {
+        // 0.05 ms to 1 s is 4.3 decades, 0.43 decade per bucket.
+        let counts = histogram([0.01, 0.06, 1.0, 1.1, 10.0, 5000.0], 10);
+        assert_eq!(counts.iter().sum::<u64>(), 6);
+        assert_eq!(counts[0], 2);
+        assert_eq!(counts[3], 2);
+        assert_eq!(counts[9], 1);
+
+        let (low, high) = bucket_range(0, 10);
+        assert!((low - 0.05).abs() < 1e-9);
+        let (_, high_last) = bucket_range(9, 10);
+        assert!((high_last - 1000.0).abs() < 1e-6);
+        assert!(high > low);
+    }
+}
@@ -0,0 +1,315 @@
+use crate::rx_socket::{KernelFilter, RxEvent, RxSocket};
+use oze_canopen::canopen::RxMessage;
+use serde::{Deserialize, Serialize};
+use socketcan::{tokio::CanSocket, CanFrame, EmbeddedFrame, SocketOptions, StandardId};
+use std::{
+    collections::VecDeque,
+    fmt,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tokio::{
+    sync::mpsc,
+    task::JoinHandle,
+    time::{sleep_until, Instant},
+};
+
+/// Maximum number of frames waiting for their artificial delay in one direction.
+const MAX_QUEUED: usize = 4096;
+
+/// Capacity of the channel delivering the frames of side B to the driver.
+const PEER_CHANNEL_CAPACITY: usize = 1024;
+
+/// Side of the gateway a frame was received on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GatewaySide {
+    /// The interface of the connection.
+    A,
+    /// The peer interface.
+    B,
+}
+
+impl fmt::Display for GatewaySide {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GatewaySide::A => write!(f, "A"),
+            GatewaySide::B => write!(f, "B"),
+        }
+    }
+}
+
+/// Forwarding rules of one direction of the gateway.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GatewayRoute {
+    /// Only frames matching one of the filters are forwarded, empty forwards everything.
+    pub filters: Vec<KernelFilter>,
+    /// Artificial delay added to every forwarded frame.
+    pub delay: Duration,
+    /// Probability of dropping a frame on purpose, in percent.
+    pub drop_percent: u8,
+}
+
+impl GatewayRoute {
+    fn accepts(&self, cob_id: u16) -> bool {
+        let cob_id = u32::from(cob_id);
+        self.filters.is_empty()
+            || self
+                .filters
+                .iter()
+                .any(|f| cob_id & f.mask == f.id & f.mask)
+    }
+
+    fn should_drop(&self) -> bool {
+        self.drop_percent > 0 && rand::random::<f64>() * 100.0 < f64::from(self.drop_percent)
+    }
+}
+
+/// Gateway configuration, side A is the interface of the connection.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GatewayConfig {
+    /// Name of the interface of side B.
+    pub peer: String,
+    pub a_to_b: GatewayRoute,
+    pub b_to_a: GatewayRoute,
+}
+
+/// Counters of one direction of the gateway.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RouteStats {
+    pub forwarded: u64,
+    /// Frames rejected by the filters of the route.
+    pub filtered: u64,
+    /// Frames dropped on purpose or because the delay queue was full.
+    pub dropped: u64,
+    /// Frames that couldn't be written to the other side.
+    pub errors: u64,
+}
+
+/// Counters of the gateway as reported to the GUI.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct GatewayStats {
+    pub peer: String,
+    pub a_to_b: RouteStats,
+    pub b_to_a: RouteStats,
+}
+
+#[derive(Debug, Default)]
+struct RouteCounters {
+    forwarded: AtomicU64,
+    filtered: AtomicU64,
+    dropped: AtomicU64,
+    errors: AtomicU64,
+}
+
+impl RouteCounters {
+    fn stats(&self) -> RouteStats {
+        RouteStats {
+            forwarded: self.forwarded.load(Ordering::Relaxed),
+            filtered: self.filtered.load(Ordering::Relaxed),
+            dropped: self.dropped.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// One direction of the gateway, read from `src` and written to `dst`.
+struct Forwarder {
+    /// Direction for logs, e.g. `can0 → can1`.
+    name: String,
+    src: RxSocket,
+    dst_name: String,
+    dst: Option<CanSocket>,
+    route: GatewayRoute,
+    counters: Arc<RouteCounters>,
+    queue: VecDeque<(Instant, RxMessage)>,
+    /// Receives a copy of every frame read from `src`.
+    seen: Option<mpsc::Sender<RxMessage>>,
+    unseen: Arc<AtomicU64>,
+}
+
+impl Forwarder {
+    async fn run(mut self) {
+        loop {
+            let due = self.queue.front().map(|(due, _)| *due);
+            tokio::select! {
+                event = self.src.recv() => self.on_event(event),
+                () = sleep_until(due.unwrap_or_else(Instant::now)), if due.is_some() => {
+                    if let Some((_, msg)) = self.queue.pop_front() {
+                        self.write(msg).await;
+                    }
+                }
+            }
+        }
+    }
+
+    fn on_event(&mut self, event: RxEvent) {
+        let msg = match event {
+            RxEvent::Frame(msg) => msg,
+            // Errors of one bus mean nothing on the other one.
+            RxEvent::Error(_) => return,
+            RxEvent::Opened => {
+                log::info!("Gateway {}: source opened", self.name);
+                return;
+            }
+            RxEvent::Lost(reason) => {
+                log::warn!("Gateway {}: source lost: {reason}", self.name);
+                return;
+            }
+        };
+
+        if let Some(seen) = &self.seen {
+            if seen.try_send(msg).is_err() {
+                self.unseen.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        if !self.route.accepts(msg.cob_id) {
+            self.counters.filtered.fetch_add(1, Ordering::Relaxed);
+        } else if self.route.should_drop() || self.queue.len() >= MAX_QUEUED {
+            self.counters.dropped.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.queue
+                .push_back((Instant::now() + self.route.delay, msg));
+        }
+    }
+
+    async fn write(&mut self, msg: RxMessage) {
+        match self.write_frame(msg).await {
+            Ok(()) => {
+                self.counters.forwarded.fetch_add(1, Ordering::Relaxed);
+            }
+            Err(e) => {
+                log::warn!("Gateway {}: write failed: {e}", self.name);
+                self.dst = None;
+                self.counters.errors.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    async fn write_frame(&mut self, msg: RxMessage) -> std::io::Result<()> {
+        let invalid = || std::io::Error::from(std::io::ErrorKind::InvalidInput);
+        let id = StandardId::new(msg.cob_id).ok_or_else(invalid)?;
+        let frame = CanFrame::new(id, &msg.data[..msg.dlc.min(8)]).ok_or_else(invalid)?;
+
+        if self.dst.is_none() {
+            let sock = CanSocket::open(&self.dst_name)?;
+            // Without loopback the frames written here are not seen by the other sockets
+            // of the interface, so they are not forwarded back nor shown twice.
+            sock.set_loopback(false)?;
+            self.dst = Some(sock);
+        }
+        self.dst.as_ref().unwrap().write_frame(frame).await
+    }
+}
+
+/// Forwards frames in both directions between the interface of the connection and a peer one.
+///
+/// Each direction runs in its own task so that forwarding is not held up by the driver.
+/// The frames of side A are read by the driver itself, the frames of side B are delivered
+/// by [`Gateway::recv`].
+pub struct Gateway {
+    peer: String,
+    a_to_b: Arc<RouteCounters>,
+    b_to_a: Arc<RouteCounters>,
+    peer_frames: mpsc::Receiver<RxMessage>,
+    unseen: Arc<AtomicU64>,
+    handles: [JoinHandle<()>; 2],
+}
+
+impl Gateway {
+    pub fn start(can_name: &str, config: &GatewayConfig) -> Self {
+        log::info!("Gateway between {can_name} and {}", config.peer);
+        let (peer_sender, peer_frames) = mpsc::channel(PEER_CHANNEL_CAPACITY);
+        let a_to_b = Arc::new(RouteCounters::default());
+        let b_to_a = Arc::new(RouteCounters::default());
+        let unseen = Arc::new(AtomicU64::new(0));
+
+        let forward_a = Forwarder {
+            name: format!("{can_name} → {}", config.peer),
+            src: RxSocket::new(can_name.to_owned()),
+            dst_name: config.peer.clone(),
+            dst: None,
+            route: config.a_to_b.clone(),
+            counters: a_to_b.clone(),
+            queue: VecDeque::new(),
+            seen: None,
+            unseen: unseen.clone(),
+        };
+        let forward_b = Forwarder {
+            name: format!("{} → {can_name}", config.peer),
+            src: RxSocket::new(config.peer.clone()),
+            dst_name: can_name.to_owned(),
+            dst: None,
+            route: config.b_to_a.clone(),
+            counters: b_to_a.clone(),
+            queue: VecDeque::new(),
+            seen: Some(peer_sender),
+            unseen: unseen.clone(),
+        };
+
+        Self {
+            peer: config.peer.clone(),
+            a_to_b,
+            b_to_a,
+            peer_frames,
+            unseen,
+            handles: [tokio::spawn(forward_a.run()), tokio::spawn(forward_b.run())],
+        }
+    }
+
+    /// Waits for the next frame received on side B. The future is cancel safe.
+    pub async fn recv(&mut self) -> RxMessage {
+        match self.peer_frames.recv().await {
+            Some(msg) => msg,
+            None => std::future::pending().await,
+        }
+    }
+
+    /// Number of side B frames lost since the last call because the driver couldn't keep up.
+    pub fn take_unseen(&self) -> u64 {
+        self.unseen.swap(0, Ordering::Relaxed)
+    }
+
+    pub fn stats(&self) -> GatewayStats {
+        GatewayStats {
+            peer: self.peer.clone(),
+            a_to_b: self.a_to_b.stats(),
+            b_to_a: self.b_to_a.stats(),
+        }
+    }
+}
+
+impl Drop for Gateway {
+    fn drop(&mut self) {
+        for handle in &self.handles {
+            handle.abort();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GatewayRoute;
+    use crate::rx_socket::KernelFilter;
+
+    #[test]
+    fn test_route_filters() {
+        let mut route = GatewayRoute::default();
+        assert!(route.accepts(0x181));
+        assert!(!route.should_drop());
+
+        route.filters.push(KernelFilter {
+            id: 0x180,
+            mask: 0x780,
+        });
+        assert!(route.accepts(0x181));
+        assert!(route.accepts(0x1FF));
+        assert!(!route.accepts(0x201));
+
+        route.drop_percent = 100;
+        assert!(route.should_drop());
+    }
+}
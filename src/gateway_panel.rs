@@ -0,0 +1,156 @@
+use crate::{
+    gateway::{GatewayConfig, GatewayRoute},
+    rx_socket::KernelFilter,
+    theme::OZON_PINK,
+};
+use egui::{DragValue, TextEdit};
+use std::time::Duration;
+
+/// Editor of the forwarding rules of one direction.
+#[derive(Debug, Default)]
+struct RouteEditor {
+    filters_raw: String,
+    delay_ms: u64,
+    drop_percent: u8,
+}
+
+impl RouteEditor {
+    fn from_route(route: &GatewayRoute) -> Self {
+        Self {
+            filters_raw: route
+                .filters
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(" "),
+            delay_ms: u64::try_from(route.delay.as_millis()).unwrap_or(u64::MAX),
+            drop_percent: route.drop_percent,
+        }
+    }
+
+    /// Parses filters written as `id[/mask]` separated by spaces or commas.
+    fn parse_filters(&self) -> Option<Vec<KernelFilter>> {
+        self.filters_raw
+            .split([' ', ','])
+            .filter(|s| !s.is_empty())
+            .map(|s| {
+                let (id, mask) = s.split_once('/').unwrap_or((s, ""));
+                KernelFilter::from_hex(id, mask)
+            })
+            .collect()
+    }
+
+    fn route(&self) -> Option<GatewayRoute> {
+        Some(GatewayRoute {
+            filters: self.parse_filters()?,
+            delay: Duration::from_millis(self.delay_ms),
+            drop_percent: self.drop_percent,
+        })
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui, name: &str) {
+        ui.label(name);
+        let valid = self.parse_filters().is_some();
+        let mut edit = TextEdit::singleline(&mut self.filters_raw)
+            .hint_text("all, or 181 180/780 ...")
+            .desired_width(140.0);
+        if !valid {
+            edit = edit.text_color(egui::Color32::RED);
+        }
+        ui.add(edit)
+            .on_hover_text("Forwarded IDs as hex id/mask, empty forwards everything");
+        ui.add(
+            DragValue::new(&mut self.delay_ms)
+                .range(0..=10_000)
+                .suffix(" ms"),
+        )
+        .on_hover_text("Artificial delay of every forwarded frame");
+        ui.add(
+            DragValue::new(&mut self.drop_percent)
+                .range(0..=100)
+                .suffix(" % drop"),
+        )
+        .on_hover_text("Probability of dropping a frame on purpose");
+        ui.end_row();
+    }
+}
+
+/// Editor of the gateway between the connected interface (side A) and a peer one (side B).
+#[derive(Debug, Default)]
+pub struct GatewayPanel {
+    enabled: bool,
+    peer_raw: String,
+    a_to_b: RouteEditor,
+    b_to_a: RouteEditor,
+    /// Configuration last sent to the driver.
+    applied: Option<GatewayConfig>,
+}
+
+impl GatewayPanel {
+    pub fn new(config: Option<&GatewayConfig>) -> Self {
+        config.map_or_else(Self::default, |config| Self {
+            enabled: true,
+            peer_raw: config.peer.clone(),
+            a_to_b: RouteEditor::from_route(&config.a_to_b),
+            b_to_a: RouteEditor::from_route(&config.b_to_a),
+            applied: Some(config.clone()),
+        })
+    }
+
+    /// Configuration to send to the driver, `None` when the gateway is disabled.
+    pub fn config(&self) -> Option<GatewayConfig> {
+        self.applied.clone()
+    }
+
+    /// Configuration being edited, `None` if it is disabled or invalid.
+    fn edited(&self) -> Option<GatewayConfig> {
+        if !self.enabled || self.peer_raw.trim().is_empty() {
+            return None;
+        }
+        Some(GatewayConfig {
+            peer: self.peer_raw.trim().to_owned(),
+            a_to_b: self.a_to_b.route()?,
+            b_to_a: self.b_to_a.route()?,
+        })
+    }
+
+    /// Shows the editor, returns `true` if the configuration must be sent to the driver.
+    pub fn update(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut changed = false;
+        ui.colored_label(
+            OZON_PINK,
+            "Every frame of side A (connected interface) is forwarded to side B and back. Messages are tagged with their side.",
+        );
+
+        ui.horizontal(|ui| {
+            ui.label("Side B:");
+            ui.add(
+                TextEdit::singleline(&mut self.peer_raw)
+                    .hint_text("can1")
+                    .desired_width(100.0),
+            );
+            if ui.checkbox(&mut self.enabled, "Enabled").changed() {
+                changed = true;
+            }
+        });
+
+        egui::Grid::new("gateway_routes").show(ui, |ui| {
+            self.a_to_b.ui(ui, "A → B");
+            self.b_to_a.ui(ui, "B → A");
+        });
+
+        let edited = self.edited();
+        if ui
+            .add_enabled(edited.is_some(), egui::Button::new("Apply"))
+            .on_hover_text("Restart the gateway with these settings")
+            .clicked()
+        {
+            changed = true;
+        }
+
+        if changed {
+            self.applied = self.edited();
+        }
+        changed
+    }
+}
@@ -1,194 +1,936 @@
 use crate::{
-    bitrate::RatesData,
-    bus_stats::BusStats,
-    chart::{self, Chart},
-    driver::{Control, ControlCommand, State, WriteCommand},
-    filter::GlobalFilter,
+    bitrate::{BitrateSource, RatesData, STANDARD_BITRATES},
+    bus_errors::{ErrorClass, InterfaceHealth},
+    bus_stats::{BusStats, BusStatsSnapshot, MAX_COB_IDS_RANGE, PEAK_WINDOW_RANGE, RATE_WINDOW_RANGE},
+    byte_changes::ByteChanges,
+    capture::{self, CaptureFormat},
+    chart::{self, Chart, ChartTab, LoadChart},
+    columns::Columns,
+    data_format::DataFormat,
+    dbc_panel::DbcPanel,
+    deadlines::Deadlines,
+    detail::DetailPane,
+    driver::{
+        self, ConnectionStatus, Control, ControlCommand, DriverChannels, LinkEvent, LinkState,
+        SessionMarker, State, WriteCommand,
+    },
+    drops::DropMonitor,
+    eds_panel::EdsPanel,
+    emcy::{EmcyPanel, Emergency},
+    emcy_codes,
+    device_info::{DeviceInfo, DeviceInfoPanel},
+    export::{self, Capture, ExportJob, MessageExport},
+    filter::{Classes, DataFilter, GlobalFilter},
+    filter_data_panel::FilterDataPanel,
     filter_panel::FilterPanel,
+    filter_profile::{FilterProfile, FilterProfiles},
+    gap_histogram::GapHistogram,
+    gateway::GatewayStats,
+    gateway_panel::GatewayPanel,
+    http_api::{ApiStatus, StatusPublisher},
+    interfaces::{self, CanInterfaceInfo},
+    interpreter::{InterpreterSettings, InterpreterStats},
+    kernel_filter_panel::KernelFilterPanel,
+    layouts::{LayoutAction, Layouts, WindowLayout},
+    live::{LiveAction, LiveTable},
+    log_import::{FileFormat, ImportJob},
     message_cached::MessageCached,
+    message_row::TimestampMode,
     message_sender::MessageSender,
+    navigation::Jump,
+    nodes::{NodeAction, NodeIdentity, NodeTable},
+    notifications::{Notification, Notifications, Severity},
+    od_browser::{OdBrowserAction, OdBrowserPanel},
+    overlay_plot::OverlayPlot,
+    pacer::{BulkThroughput, DEFAULT_TX_RATE},
+    pdo_config::{PdoConfigAction, PdoConfigPanel},
+    periodic::PeriodicEntry,
     pinned_filter::PinnedFilters,
-    theme::{theme, OZON_GRAY, OZON_PINK},
+    row_colors::RowColors,
+    script::{self, ScriptPanel},
+    session_file::{SavedMessage, SessionFile},
+    settings::{
+        AppSettings, BufferSettings, HiddenPanels, PanelSizes, CHART_HEIGHT_RANGE,
+        MESSAGES_COUNT_RANGE, PINNED_HISTORY_RANGE,
+    },
+    startup::Startup,
+    stats_export::StatsReport,
+    sync_stats::SYNC_WINDOW_RANGE,
+    theme::{theme, Status, ThemeSettings, OZON_BLUE_ACTIVE, OZON_GRAY, OZON_PINK},
+    triggers::{Actions, Alert, Triggers},
     viewer::Viewer,
+    ws_server::StatsPublisher,
+};
+use chrono::{DateTime, Local};
+use egui::{
+    emath::Numeric, Button, Key, KeyboardShortcut, Layout, Modifiers, RichText, TextEdit, Ui,
 };
-use egui::{emath::Numeric, Button, Layout, TextEdit, Ui};
 use oze_canopen::{
-    canopen::RxMessageToStringFormat,
+    canopen::{NodeId, RxMessageToStringFormat},
     interface::{CanOpenInfo, Connection},
 };
-use std::{cell::RefCell, collections::VecDeque, rc::Rc, sync::Arc};
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, VecDeque},
+    path::Path,
+    rc::Rc,
+    sync::{Arc, OnceLock},
+    time::Duration,
+};
 use tokio::{
-    sync::{watch, mpsc, Mutex},
+    runtime::Handle,
+    sync::{watch, mpsc},
     time::Instant,
 };
 
-const MESSAGES_COUNT: usize = 4096;
+/// Breakdown of the payload sizes in the statistics panel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PayloadView {
+    /// Frames per DLC.
+    Dlc,
+    /// Frames, mean DLC and efficiency per node.
+    PerNode,
+}
 
-pub struct Gui {
+/// Difference of the two bus load estimations, in points, above which both are shown.
+const LOAD_DISAGREEMENT: f64 = 5.0;
+
+/// Width of a collapsed side panel.
+const PANEL_STRIP_WIDTH: f32 = 18.0;
+
+/// Panels of the View menu, their name and the key toggling them with Ctrl.
+const VIEW_PANELS: [(HiddenPanels, &str, Key); 4] = [
+    (HiddenPanels::SENDER, "message sender", Key::Num1),
+    (HiddenPanels::STATS, "statistics", Key::Num2),
+    (HiddenPanels::DASHBOARD, "dashboard", Key::Num3),
+    (HiddenPanels::CHART, "chart", Key::Num4),
+];
+
+fn view_shortcut(key: Key) -> KeyboardShortcut {
+    KeyboardShortcut::new(Modifiers::COMMAND, key)
+}
+
+/// Interval between two repaints without new data, to refresh the clocks and statistics.
+const IDLE_REPAINT_PERIOD: Duration = Duration::from_millis(100);
+
+/// Allowed continuous repaint rates.
+const MAX_FPS_RANGE: std::ops::RangeInclusive<u32> = 1..=240;
+
+/// Window title without a connection.
+const WINDOW_TITLE: &str = "oze-canopen-viewer";
+
+/// Longest wait for the drivers to stop before the window closes anyway.
+const EXIT_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Interval between two checks of the settings to save.
+const SETTINGS_SAVE_PERIOD: Duration = Duration::from_secs(1);
+
+/// Messages of a file loaded into its offline session per frame, shown while the rest loads.
+const OFFLINE_LOAD_CHUNK: usize = 20_000;
+
+/// One connection: its driver, messages, filters, statistics and charts.
+pub struct Session {
     data: VecDeque<MessageCached>,
     driver: watch::Receiver<State>,
+    driver_data: mpsc::Receiver<MessageCached>,
+    /// Events of the driver, moved to the notifications of the window.
+    events: mpsc::Receiver<Notification>,
     pinned_filters: PinnedFilters,
     viewer: Viewer,
     chart: chart::Chart,
-    last: Instant,
-    fps: VecDeque<f64>,
+    load_chart: LoadChart,
+    chart_tab: ChartTab,
+    overlay: OverlayPlot,
     bus_load_history: VecDeque<f64>,
+    /// Bus load of the last frame, `None` without a bitrate.
+    bus_load: Option<f64>,
     bus_stats: BusStats,
+    gap_histogram: GapHistogram,
+    nodes: NodeTable,
+    live: LiveTable,
+    byte_changes: ByteChanges,
+    columns: Columns,
+    emcy: EmcyPanel,
+    triggers: Triggers,
+    export: MessageExport,
+    stats_export: ExportJob,
+    detail: DetailPane,
     global_filter: Rc<RefCell<GlobalFilter>>,
     filter_panel: FilterPanel,
+    filter_profiles: FilterProfiles,
+    kernel_filter_panel: KernelFilterPanel,
+    gateway_panel: GatewayPanel,
+    dbc_panel: DbcPanel,
+    script_panel: ScriptPanel,
+    interpreters: InterpreterSettings,
+    interpreter_stats: InterpreterStats,
+    eds_panel: EdsPanel,
     message_sender: MessageSender,
+    buffers: BufferSettings,
 
-    format: RxMessageToStringFormat,
+    format: DataFormat,
+    timestamp_mode: TimestampMode,
 
     can_name_raw: String,
     bitrate_raw: String,
+    /// CAN interfaces of the system, listed when their menu opens.
+    interfaces: Option<Vec<CanInterfaceInfo>>,
 
     info: CanOpenInfo,
+    drops: DropMonitor,
+    link: LinkState,
+    status: ConnectionStatus,
+    link_events: VecDeque<LinkEvent>,
+    sessions: VecDeque<SessionMarker>,
+    identities: BTreeMap<NodeId, Result<NodeIdentity, String>>,
+    device_infos: BTreeMap<NodeId, Result<DeviceInfo, String>>,
+    device_info: DeviceInfoPanel,
+    pdo_config: PdoConfigPanel,
+    od_browser: OdBrowserPanel,
+    periodic: Vec<PeriodicEntry>,
+    tx_rate: u32,
+    last_bulk: Option<BulkThroughput>,
+    gateway: Option<GatewayStats>,
+    health: Option<InterfaceHealth>,
+    health_changed: Option<DateTime<Local>>,
+    /// Controller restarts seen in the error frames.
+    restarts: u64,
 
     connection: Connection,
+    payload_view: PayloadView,
+    /// Bitrate reported by the interface, used for the bus load when none is configured.
+    interface_bitrate: Option<u32>,
+    /// Bitrate picked by the user for the bus load when none is configured or reported.
+    assumed_bitrate: Option<u32>,
     stopped: bool,
+    disconnected: bool,
+    clear_on_connect: bool,
     driver_ctrl: watch::Sender<Control>,
-    write_sender: mpsc::Sender<WriteCommand>,
-    bitrate: Arc<Mutex<RatesData>>,
+    bitrate: watch::Receiver<RatesData>,
+    /// Saved session shown read-only, without a connection.
+    offline: Option<SavedSource>,
+    /// Messages of the file of the offline session not loaded yet.
+    loading: Option<OfflineLoad>,
+    /// Statistics sent to the WebSocket clients, for the session whose messages are streamed.
+    stats_stream: Option<StatsPublisher>,
+    /// Statistics and nodes answered by the HTTP API and exported as metrics, for the session
+    /// of the first driver.
+    status_publishers: Vec<StatusPublisher>,
 }
 
-impl Gui {
-    pub fn new(
-        cc: &eframe::CreationContext<'_>,
-        driver: watch::Receiver<State>,
-        driver_ctrl: watch::Sender<Control>,
-        bitrate: Arc<Mutex<RatesData>>,
-        write_sender: mpsc::Sender<WriteCommand>,
-    ) -> Self {
-        theme(&cc.egui_ctx);
-
+impl Session {
+    /// Session of a started driver, with the display settings and filters of `settings`.
+    fn new(channels: DriverChannels, buffers: BufferSettings, settings: &AppSettings) -> Self {
+        let DriverChannels {
+            state: driver,
+            data: driver_data,
+            control: driver_ctrl,
+            write: write_sender,
+            bitrates: bitrate,
+            events,
+        } = channels;
         let global_filter = Rc::new(RefCell::new(GlobalFilter::default()));
         let connection_data = driver_ctrl.subscribe().borrow().connection.clone();
+        let tx_rate = driver_ctrl.subscribe().borrow().tx_rate.unwrap_or_default();
+        let gateway_panel = GatewayPanel::new(driver_ctrl.subscribe().borrow().gateway.as_ref());
         let can_name_raw = connection_data.can_name.clone();
         let bitrate_raw = connection_data
             .bitrate
             .map(|b| b.to_string())
             .unwrap_or_default();
 
-        Self {
-            fps: VecDeque::new(),
+        let mut session = Self {
             bus_load_history: VecDeque::new(),
+            bus_load: None,
             bus_stats: BusStats::new(),
+            gap_histogram: GapHistogram::default(),
+            nodes: NodeTable::default(),
+            live: LiveTable::default(),
+            byte_changes: ByteChanges::default(),
+            columns: Columns::load(),
+            emcy: EmcyPanel::default(),
+            triggers: Triggers::default(),
+            export: MessageExport::default(),
+            stats_export: ExportJob::new("COB-IDs"),
+            detail: DetailPane::default(),
             data: VecDeque::new(),
             pinned_filters: PinnedFilters::default(),
             info: CanOpenInfo::default(),
+            drops: DropMonitor::default(),
+            link: LinkState::default(),
+            status: ConnectionStatus::default(),
+            link_events: VecDeque::new(),
+            sessions: VecDeque::new(),
+            identities: BTreeMap::new(),
+            device_infos: BTreeMap::new(),
+            device_info: DeviceInfoPanel::default(),
+            pdo_config: PdoConfigPanel::default(),
+            od_browser: OdBrowserPanel::new(write_sender.clone()),
+            periodic: Vec::new(),
+            tx_rate,
+            last_bulk: None,
+            gateway: None,
+            health: None,
+            health_changed: None,
+            restarts: 0,
             connection: connection_data,
-            format: RxMessageToStringFormat::Hex,
+            payload_view: PayloadView::Dlc,
+            interface_bitrate: None,
+            assumed_bitrate: None,
+            format: DataFormat::default(),
+            timestamp_mode: TimestampMode::default(),
             viewer: Viewer::new(global_filter.clone()),
             filter_panel: FilterPanel::new(global_filter.clone()),
-            message_sender: MessageSender::new(write_sender.clone()),
-            last: Instant::now(),
+            filter_profiles: FilterProfiles::load(),
+            kernel_filter_panel: KernelFilterPanel::default(),
+            gateway_panel,
+            dbc_panel: DbcPanel::default(),
+            script_panel: ScriptPanel::default(),
+            interpreters: InterpreterSettings::default(),
+            interpreter_stats: InterpreterStats::default(),
+            eds_panel: EdsPanel::default(),
+            message_sender: MessageSender::new(write_sender),
+            buffers,
             chart: Chart::new(bitrate.clone()),
+            load_chart: LoadChart::load(),
+            chart_tab: ChartTab::load(),
+            overlay: OverlayPlot::load(),
             stopped: false,
+            disconnected: false,
+            clear_on_connect: false,
             global_filter,
             can_name_raw,
             bitrate_raw,
+            interfaces: None,
             driver_ctrl,
             driver,
-            write_sender,
+            driver_data,
+            events,
             bitrate,
+            offline: None,
+            loading: None,
+            stats_stream: None,
+            status_publishers: Vec::new(),
+        };
+        session.pinned_filters.set_history_depth(session.buffers.pinned_history);
+        if let Some(profile) = session.filter_profiles.last().cloned() {
+            session.apply_profile(&profile);
+        }
+        session.apply_settings(settings);
+        *session.bus_stats.deadlines_mut() = Deadlines::load();
+        chart::load_rate_plots(&mut session.bus_stats);
+        session
+    }
+
+    /// Name of the tab, the interface of the connection.
+    fn title(&self) -> &str {
+        if let Some(saved) = &self.offline {
+            return Path::new(&saved.path)
+                .file_name()
+                .and_then(|name| name.to_str())
+                .unwrap_or(&saved.path);
+        }
+        if self.connection.can_name.is_empty() {
+            "not connected"
+        } else {
+            &self.connection.can_name
+        }
+    }
+
+    /// Title of the window while the session is the active one, e.g.
+    /// `oze-canopen-viewer — can0 @ 500k (connected)`.
+    fn window_title(&self) -> String {
+        if let Some(saved) = &self.offline {
+            return format!("{WINDOW_TITLE} — {} (offline)", saved.path);
         }
+        if self.connection.can_name.is_empty() {
+            return WINDOW_TITLE.to_owned();
+        }
+        let bitrate = match self.connection.bitrate.or(self.interface_bitrate) {
+            Some(bitrate) if bitrate % 1000 == 0 => format!(" @ {}k", bitrate / 1000),
+            Some(bitrate) => format!(" @ {bitrate}"),
+            None => String::new(),
+        };
+        format!(
+            "{WINDOW_TITLE} — {}{bitrate} ({})",
+            self.connection.can_name,
+            self.status.as_str()
+        )
+    }
+
+    /// Receives the messages and state of the driver and updates the statistics, returns true
+    /// when the driver asks the application to exit.
+    fn poll(&mut self, window: &mut Window) -> bool {
+        while let Ok(event) = self.events.try_recv() {
+            window.notifications.push(event);
+        }
+        let exit = self.get_data_from_driver(&mut window.notifications);
+        for capture in self.triggers.take_captures(Instant::now()) {
+            self.save_capture(capture, window);
+        }
+        let alerts = self.triggers.take_saved();
+        self.on_alerts(&alerts, &mut window.notifications);
+        self.bus_load = self.calc_bus_load();
+        if let Some(stream) = &mut self.stats_stream {
+            let bus_stats = &self.bus_stats;
+            stream.publish_if_due(Instant::now(), || bus_stats.snapshot(Instant::now()));
+        }
+        let now = Instant::now();
+        for publisher in &mut self.status_publishers {
+            let (can_name, bus_stats, nodes) = (&self.connection.can_name, &self.bus_stats, &self.nodes);
+            publisher.publish_if_due(now, || ApiStatus {
+                can_name: can_name.clone(),
+                stats: bus_stats.snapshot(now),
+                nodes: nodes.snapshot(now),
+                emergencies: bus_stats
+                    .emcy()
+                    .iter()
+                    .map(|(node_id, emcy)| (*node_id, emcy.total))
+                    .collect(),
+            });
+        }
+        for error in script::loaded().map(|script| script.take_errors()).unwrap_or_default() {
+            window.notifications.push(Notification::new(Severity::Error, error));
+        }
+        for error in self.message_sender.take_errors() {
+            window.notifications.push(Notification::new(Severity::Error, error));
+        }
+        let control = self.driver_ctrl.borrow();
+        let changed = control.exit_nmt != self.message_sender.exit_nmt()
+            || control.auto_start != self.message_sender.auto_start();
+        drop(control);
+        if changed {
+            self.send_driver_control();
+        }
+        exit
+    }
+
+    /// What the driver and the sender are doing that exiting stops.
+    fn running(&self) -> Vec<String> {
+        let mut running = Vec::new();
+        if let Some(path) = &self.driver.borrow().recording {
+            running.push(format!("recording to {path}"));
+        }
+        if !self.periodic.is_empty() {
+            running.push(format!("{} periodic transmissions", self.periodic.len()));
+        }
+        if let Some(command) = self.message_sender.pending() {
+            running.push(format!("{command} in progress"));
+        }
+        if let Some(command) = self.message_sender.exit_nmt().filter(|_| self.offline.is_none()) {
+            running.push(format!("{command:?} NMT sent to every node"));
+        }
+        running
+    }
+
+    /// Whether the driver is done with its teardown, or gone.
+    fn stopped(&self) -> bool {
+        self.driver.has_changed().is_err() || self.driver.borrow().exit_signal
     }
 
     fn send_driver_control(&self) {
         let _ = self.driver_ctrl.send(Control {
-            command: if self.stopped {
+            command: if self.disconnected {
+                ControlCommand::Disconnect
+            } else if self.stopped {
                 ControlCommand::Stop
             } else {
                 ControlCommand::Process
             },
             connection: self.connection.clone(),
+            kernel_filters: self.kernel_filter_panel.filters.clone(),
+            tx_rate: (self.tx_rate > 0).then_some(self.tx_rate),
+            gateway: self.gateway_panel.config(),
+            exit_nmt: self.message_sender.exit_nmt(),
+            auto_start: self.message_sender.auto_start(),
         });
     }
 
-    fn get_data_from_driver(&mut self) -> bool {
-        let driver = self.driver.borrow();
+    fn get_data_from_driver(&mut self, notifications: &mut Notifications) -> bool {
         let now = Instant::now();
-        
-        for i in &driver.data {
-            if let Some(last) = self.data.front() {
-                if i.index <= last.index {
-                    continue;
-                }
+
+        self.load_chunk();
+        while let Ok(i) = self.driver_data.try_recv() {
+            self.on_message(i, now, notifications);
+        }
+
+        let error_frames = self.driver.borrow().bus_errors.frames;
+        let mut alerts = self.triggers.on_errors(error_frames, now);
+        alerts.extend(self.triggers.on_tick(now));
+        self.on_alerts(&alerts, notifications);
+        let deadline_alerts: Vec<_> = self
+            .bus_stats
+            .deadlines_mut()
+            .on_tick(now)
+            .iter()
+            .map(|alert| Alert {
+                time: alert.time,
+                index: None,
+                text: alert.to_string(),
+                actions: Actions::default(),
+            })
+            .collect();
+        self.triggers.record(&deadline_alerts);
+        self.nodes.on_tick(now);
+        self.trim_data();
+        self.bus_stats.calculate_cob_id_rates(now);
+
+        let driver = self.driver.borrow();
+        self.info = driver.info.clone();
+        self.drops.set(driver.interface_drops, driver.dropped);
+        self.link = driver.link;
+        self.status = driver.status;
+        self.interface_bitrate = driver.interface_bitrate;
+        if self.link_events.len() != driver.link_events.len()
+            || self.link_events.back().map(|e| e.recovered)
+                != driver.link_events.back().map(|e| e.recovered)
+        {
+            self.link_events.clone_from(&driver.link_events);
+        }
+        if self.sessions != driver.sessions {
+            self.sessions.clone_from(&driver.sessions);
+        }
+        // An offline session keeps the identities and device information it was saved with.
+        if self.offline.is_none() && self.identities != driver.identities {
+            self.identities.clone_from(&driver.identities);
+        }
+        if self.offline.is_none() && self.device_infos != driver.device_infos {
+            self.device_infos.clone_from(&driver.device_infos);
+        }
+        if self.offline.is_none() {
+            self.pdo_config.sync(&driver.pdo_configs);
+        }
+        if self.periodic != driver.periodic {
+            self.periodic.clone_from(&driver.periodic);
+        }
+        if self.last_bulk != driver.last_bulk {
+            self.last_bulk.clone_from(&driver.last_bulk);
+        }
+        self.emcy.sync(&driver.emergencies, driver.emergency_count);
+        self.bus_stats.on_errors(&driver.bus_errors, now);
+        self.health = driver.health;
+        self.health_changed = driver.health_changed;
+        self.restarts = driver.bus_errors.get(ErrorClass::Restarted);
+        if self.gateway != driver.gateway {
+            self.gateway.clone_from(&driver.gateway);
+        }
+
+        driver.exit_signal
+    }
+
+    /// Counts a message received at `now` in the statistics and adds it to the list.
+    fn on_message(&mut self, mut i: MessageCached, now: Instant, notifications: &mut Notifications) {
+        // Update bus statistics
+        self.bus_stats
+            .on_message(i.msg.msg.cob_id, i.msg.parsed_node_id, i.msg.msg.dlc, now);
+        if let Some(emcy) = Emergency::parse(i.index, &i.msg.msg) {
+            self.bus_stats.on_emcy(&emcy);
+        }
+        self.byte_changes.on_message(&mut i);
+        self.interpreter_stats.on_message(&i, &self.interpreters);
+        self.nodes.on_message(&i);
+        self.live.on_message(&i);
+        // Before the annotations, so a marker lands on the message that fired the trigger.
+        let alerts = self.triggers.on_message(&i);
+        self.on_alerts(&alerts, notifications);
+        let alerts: Vec<_> = self
+            .script_panel
+            .on_message(&i)
+            .into_iter()
+            .map(|text| Alert {
+                time: Local::now(),
+                index: Some(i.index),
+                text,
+                actions: Actions::default(),
+            })
+            .collect();
+        self.triggers.record(&alerts);
+        self.on_alerts(&alerts, notifications);
+        self.viewer.annotations.on_message(&i);
+
+        // While stopped only the statistics, node tracking and the pinned filter monitors are
+        // kept up to date.
+        if self.stopped {
+            self.pinned_filters.push_monitors(&i);
+            return;
+        }
+        self.pinned_filters.push_data(&i);
+        if !self.global_filter.borrow().filter(&i) {
+            self.data.push_front(i);
+        }
+    }
+
+    /// Messages, statistics and settings of the session, to reopen it offline.
+    fn session_file(&self, window: &Window) -> SessionFile {
+        let can_name = match &self.offline {
+            Some(saved) => saved.can_name.clone(),
+            None => self.connection.can_name.clone(),
+        };
+        let mut file = SessionFile::new(
+            can_name,
+            self.connection.bitrate,
+            self.current_settings(window),
+            self.bus_stats.snapshot(Instant::now()),
+            self.data.iter().chain(self.pinned_filters.histories()),
+            (
+                self.viewer.message_row.start_time,
+                self.viewer.message_row.start_wall,
+            ),
+        );
+        file.device_infos = self
+            .device_infos
+            .iter()
+            .filter_map(|(node_id, info)| Some((*node_id, info.clone().ok()?)))
+            .collect();
+        file
+    }
+
+    /// Receives the messages of a saved session again, the session becomes read-only.
+    fn load_saved(&mut self, file: SessionFile, path: String) {
+        let start = file.start();
+        self.viewer.message_row.start_time = start;
+        self.viewer.message_row.start_wall = file.start_wall();
+        for (node_id, info) in &file.device_infos {
+            if let Some(identity) = info.identity {
+                self.identities.insert(*node_id, Ok(identity));
             }
+            self.device_infos.insert(*node_id, Ok(info.clone()));
+        }
+        self.offline = Some(SavedSource {
+            path,
+            can_name: file.can_name,
+            saved: file.saved,
+            stats: file.stats,
+        });
+        self.loading = Some(OfflineLoad {
+            total: file.messages.len(),
+            messages: file.messages.into_iter(),
+            start,
+        });
+        self.load_chunk();
+    }
 
-            // Update bus statistics
-            self.bus_stats.on_message(i.msg.msg.cob_id, now);
-            
-            self.pinned_filters.push_data(i);
-            if !self.global_filter.borrow().filter(i) {
-                self.data.push_front(i.clone());
+    /// Loads the next messages of the file of the offline session.
+    fn load_chunk(&mut self) {
+        let Some(mut loading) = self.loading.take() else {
+            return;
+        };
+        // The alerts of the triggers were reported when the messages were received.
+        let mut notifications = Notifications::default();
+        for saved in loading.messages.by_ref().take(OFFLINE_LOAD_CHUNK) {
+            let msg = saved.decode(loading.start);
+            let time = msg.get_timestamp();
+            self.on_message(msg, time, &mut notifications);
+        }
+        self.trim_data();
+        if loading.messages.as_slice().is_empty() {
+            self.drops.reset();
+        } else {
+            self.loading = Some(loading);
+        }
+    }
+
+    /// Inserts the markers and freezes the message list as the fired triggers ask.
+    fn on_alerts(&mut self, alerts: &[Alert], notifications: &mut Notifications) {
+        for alert in alerts {
+            notifications.push(Notification::new(Severity::Warning, alert.text.clone()));
+            if alert.actions.marker {
+                self.viewer.annotations.add_marker(&alert.text);
+            }
+            if alert.actions.pause && !self.viewer.is_frozen() {
+                self.viewer.toggle_freeze(&self.data);
             }
         }
+    }
 
-        while self.data.len() > MESSAGES_COUNT {
-            self.data.pop_back();
+    /// Writes the capture of a trigger to a timestamped file on a separate thread, an alert
+    /// tells where once written.
+    fn save_capture(&mut self, capture: capture::Finished, window: &Window) {
+        let capture::Finished { trigger, frames } = capture;
+        let format = self.triggers.capture_format;
+        let path = format.file_name(&trigger);
+        let rows = frames.len();
+        match format {
+            CaptureFormat::Log => {
+                let info = self.capture();
+                self.triggers
+                    .save(trigger, path, rows, move || export::to_candump(&frames, &info));
+            }
+            CaptureFormat::Session => {
+                let file = SessionFile::new(
+                    self.connection.can_name.clone(),
+                    self.connection.bitrate,
+                    self.current_settings(window),
+                    self.bus_stats.snapshot(Instant::now()),
+                    &frames,
+                    (
+                        self.viewer.message_row.start_time,
+                        self.viewer.message_row.start_wall,
+                    ),
+                );
+                self.triggers.save(trigger, path, rows, move || file.to_json());
+            }
         }
+    }
 
-        self.info = driver.info.clone();
+    /// Capture the messages come from, for the exports.
+    fn capture(&self) -> Capture {
+        Capture {
+            start: self.viewer.message_row.start_time,
+            start_wall: self.viewer.message_row.start_wall,
+            can_name: self.connection.can_name.clone(),
+            peer: self.gateway_panel.config().map(|config| config.peer),
+            annotations: self.viewer.annotations.clone(),
+        }
+    }
 
-        driver.exit_signal
+    fn trim_data(&mut self) {
+        let excess = self.data.len().saturating_sub(self.buffers.messages);
+        self.data.truncate(self.buffers.messages);
+        self.drops.add_trimmed(excess as u64);
     }
 
-    fn calc_fps(&mut self) -> f64 {
-        let fps = 1.0 / self.last.elapsed().as_secs_f64();
-        self.last = Instant::now();
+    /// Drops the received messages and the statistics computed from them.
+    fn clear_history(&mut self) {
+        self.clear_messages();
+        self.clear_pinned_histories();
+        self.reset_statistics();
+    }
 
-        self.fps.push_back(fps);
+    /// Drops the messages of the viewer, the next ones received are shown as usual.
+    fn clear_messages(&mut self) {
+        self.data.clear();
+        self.viewer.selection.clear();
+        self.live.clear();
+        self.byte_changes.clear();
+    }
 
-        let fps = self.fps.iter().sum::<f64>() / self.fps.len().to_f64();
-        while self.fps.len() > usize::from_f64(fps.round()) * 5 {
-            self.fps.pop_front();
+    fn clear_pinned_histories(&mut self) {
+        self.pinned_filters.clear_history();
+        self.pinned_filters.selection.clear();
+        self.script_panel.clear();
+    }
+
+    /// Restarts the statistics, keeping their settings, the tracked COB-IDs and the deadlines.
+    fn reset_statistics(&mut self) {
+        let rate_window = self.bus_stats.rate_window();
+        let max_cob_ids = self.bus_stats.max_cob_ids();
+        let peak_window = self.bus_stats.peak_window();
+        let sync_window = self.bus_stats.sync().window();
+        let deadlines = self.bus_stats.deadlines().table().clone();
+        let plotted: Vec<u16> = self.bus_stats.cob_id_rate_history().keys().copied().collect();
+        self.bus_stats = BusStats::new();
+        self.bus_stats.set_rate_window(rate_window);
+        self.bus_stats.set_max_cob_ids(max_cob_ids);
+        self.bus_stats.set_peak_window(peak_window);
+        self.bus_stats.sync_mut().set_window(sync_window);
+        self.bus_stats.deadlines_mut().set_table(deadlines);
+        for cob_id in plotted {
+            self.bus_stats.track_cob_id(cob_id);
         }
+        self.bus_stats.set_errors_base(&self.driver.borrow().bus_errors);
+        self.bus_load_history.clear();
+        self.load_chart.clear();
+        self.interpreter_stats = InterpreterStats::default();
+    }
 
-        fps.round()
+    /// What to clear, the connection and the periodic transmissions are left alone.
+    fn show_clear_menu(&mut self, ui: &mut Ui) {
+        if ui
+            .button("Messages")
+            .on_hover_text("Empty the message list, the live table and the byte changes")
+            .clicked()
+        {
+            self.clear_messages();
+            ui.close_menu();
+        }
+        if ui
+            .button("Pinned filters")
+            .on_hover_text("Empty the history and statistics of every pinned filter, keeping the filters")
+            .clicked()
+        {
+            self.clear_pinned_histories();
+            ui.close_menu();
+        }
+        if ui
+            .button("Statistics")
+            .on_hover_text("Restart the bus load, rates, gaps, SYNC and deadline statistics")
+            .clicked()
+        {
+            self.reset_statistics();
+            ui.close_menu();
+        }
+        ui.separator();
+        if ui.button("All").clicked() {
+            self.clear_history();
+            ui.close_menu();
+        }
+    }
+
+    fn show_buffer_settings(&mut self, ui: &mut Ui, window: &mut Window) {
+        ui.label("Buffer sizes");
+        egui::Grid::new("buffer_settings").show(ui, |ui| {
+            ui.label("Viewer messages:");
+            if ui
+                .add(egui::DragValue::new(&mut self.buffers.messages).range(MESSAGES_COUNT_RANGE))
+                .on_hover_text("Number of messages kept in the table below")
+                .changed()
+            {
+                self.trim_data();
+            }
+            ui.end_row();
+
+            ui.label("Pinned history:");
+            let mut depth = self.pinned_filters.history_depth();
+            if ui
+                .add(egui::DragValue::new(&mut depth).range(PINNED_HISTORY_RANGE))
+                .on_hover_text("Number of messages kept per pinned filter")
+                .changed()
+            {
+                self.buffers.pinned_history = depth;
+                self.pinned_filters.set_history_depth(depth);
+            }
+            ui.end_row();
+
+            ui.label("Driver buffer:");
+            ui.label(self.buffers.driver_buffer.to_string())
+                .on_hover_text("Set with --driver-buffer, applies on restart");
+            ui.end_row();
+        });
+
+        ui.separator();
+        self.show_tx_pacing(ui);
+
+        ui.separator();
+        self.byte_changes.settings_ui(ui);
+
+        ui.separator();
+        self.columns.ui(ui);
+
+        ui.separator();
+        self.interpreters.ui(ui, &self.interpreter_stats);
+
+
+        ui.separator();
+        window.show_repaint_settings(ui);
+
+        ui.separator();
+        ui.checkbox(&mut self.clear_on_connect, "Clear history on connect")
+            .on_hover_text("Drop the messages and statistics when connecting to another interface or bitrate, otherwise a marker row separates the sessions");
+
+        ui.separator();
+        if ui
+            .button("Reset to defaults")
+            .on_hover_text("Display format, filters, pinned filters, sender values and panel sizes back to their defaults")
+            .clicked()
+        {
+            self.reset_settings(window);
+        }
+    }
+
+    fn show_tx_pacing(&mut self, ui: &mut Ui) {
+        ui.label("TX pacing");
+        ui.horizontal(|ui| {
+            ui.label("Bulk rate:");
+            if ui
+                .add(
+                    egui::DragValue::new(&mut self.tx_rate)
+                        .range(0..=10_000)
+                        .suffix(" frames/s"),
+                )
+                .on_hover_text("Maximum frame rate of bulk operations, 0 is unlimited. Single sends are not paced")
+                .changed()
+            {
+                self.send_driver_control();
+            }
+        });
+        match &self.last_bulk {
+            Some(throughput) => ui.label(format!("Last bulk: {throughput}")),
+            None => ui.label("No bulk operation yet"),
+        };
     }
 
+    /// Bus load from the bits of the received frames, or from the bit counter of the `oze_canopen`
+    /// stack while kernel filters hide part of the frames.
     fn calc_bus_load(&mut self) -> Option<f64> {
-        use tokio::runtime::Handle;
-        
-        if let Some(configured_bitrate) = self.connection.bitrate {
-            let rates = Handle::current().block_on(async {
-                self.bitrate.lock().await.clone()
-            });
-            
-            if let Some(last_rate) = rates.last() {
-                let current_bps = last_rate[1];
-                let percentage = (current_bps / f64::from(configured_bitrate)) * 100.0;
-                let clamped_percentage = percentage.min(100.0).max(0.0);
-                
-                // Ajouter à l'historique
-                self.bus_load_history.push_back(clamped_percentage);
-                
-                // Garder une fenêtre glissante de 50 échantillons
-                while self.bus_load_history.len() > 50 {
-                    self.bus_load_history.pop_front();
-                }
-                
-                // Calculer la moyenne glissante
-                if !self.bus_load_history.is_empty() {
-                    let avg = self.bus_load_history.iter().sum::<f64>() / self.bus_load_history.len() as f64;
-                    
-                    // Update bus statistics
-                    self.bus_stats.update_load(avg);
-                    self.bus_stats.calculate_msg_rate();
-                    self.bus_stats.calculate_cob_id_rates(Instant::now());
-                    
-                    return Some(avg);
+        let now = Instant::now();
+        self.bus_stats.calculate_msg_rate(now);
+        let (bitrate, _) = self.load_bitrate()?;
+        let counter_load = self.calc_counter_load(bitrate);
+        let frame_load = self.bus_stats.calculate_frame_load(bitrate, now);
+        self.bus_stats.set_counter_load(counter_load);
+
+        let load = if self.kernel_filter_panel.filters.is_empty() {
+            frame_load
+        } else {
+            counter_load.unwrap_or(frame_load)
+        };
+        self.bus_stats.update_load(load, now);
+        self.load_chart.push(now, load);
+        Some(load)
+    }
+
+    /// Bitrate of the bus load: configured, else read from the interface, else picked by the user.
+    fn load_bitrate(&self) -> Option<(u32, BitrateSource)> {
+        self.connection
+            .bitrate
+            .map(|bitrate| (bitrate, BitrateSource::Configured))
+            .or(self.interface_bitrate.map(|bitrate| (bitrate, BitrateSource::Interface)))
+            .or(self.assumed_bitrate.map(|bitrate| (bitrate, BitrateSource::Assumed)))
+    }
+
+    /// Bus load with the source of its bitrate, or a prompt for the bitrate if there is none.
+    fn show_bus_load(&mut self, ui: &mut Ui) {
+        let load = self.bus_load;
+        let source = self.load_bitrate().map(|(_, source)| source);
+        match (load, source) {
+            (Some(load), Some(source)) => {
+                ui.colored_label(chart::load_color(load), format!("Bus: {load:.1}%"));
+                let response = ui.weak(format!("({})", source.as_str()));
+                if source == BitrateSource::Assumed {
+                    response.context_menu(|ui| self.show_assumed_bitrate(ui));
                 }
             }
+            _ => {
+                ui.menu_button("Bus: ?", |ui| self.show_assumed_bitrate(ui))
+                    .response
+                    .on_hover_text("No bitrate configured or reported by the interface, pick one for the bus load");
+            }
         }
-        None
     }
-    
-    fn show_dashboard(&self, ui: &mut Ui) {
-        use egui::Color32;
+
+    fn show_assumed_bitrate(&mut self, ui: &mut Ui) {
+        ui.label("Assumed bitrate");
+        for bitrate in STANDARD_BITRATES {
+            let text = if bitrate >= 1_000_000 {
+                format!("{}M", bitrate / 1_000_000)
+            } else {
+                format!("{}k", bitrate / 1000)
+            };
+            if ui
+                .selectable_label(self.assumed_bitrate == Some(bitrate), text)
+                .clicked()
+            {
+                self.assumed_bitrate = Some(bitrate);
+                ui.close_menu();
+            }
+        }
+    }
+
+    /// Bus load from the bits per second of the bitrate chart, without stuff bits.
+    fn calc_counter_load(&mut self, bitrate: u32) -> Option<f64> {
+        let current_bps = self.bitrate.borrow().total.back().map(|rate| rate[1])?;
+        let percentage = (current_bps / f64::from(bitrate)) * 100.0;
         
+        // Sliding average over 50 samples
+        self.bus_load_history.push_back(percentage.clamp(0.0, 100.0));
+        while self.bus_load_history.len() > 50 {
+            self.bus_load_history.pop_front();
+        }
+        Some(self.bus_load_history.iter().sum::<f64>() / self.bus_load_history.len().to_f64())
+    }
+    
+    fn show_dashboard(&mut self, ui: &mut Ui) {
         ui.group(|ui| {
             ui.heading("📊 Bus Statistics");
             ui.separator();
@@ -199,17 +941,27 @@ impl Gui {
                     ui.label("🔋 Bus Occupation");
                     ui.horizontal(|ui| {
                         ui.label("Current:");
-                        let color = if self.bus_stats.current_load() > 80.0 {
-                            Color32::RED
-                        } else if self.bus_stats.current_load() > 50.0 {
-                            Color32::YELLOW
-                        } else {
-                            Color32::GREEN
-                        };
-                        ui.colored_label(color, format!("{:.1}%", self.bus_stats.current_load()));
+                        let load = self.bus_stats.current_load();
+                        ui.colored_label(chart::load_color(load), format!("{load:.1}%"));
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label(format_peak(
+                            self.bus_stats.peak_window(),
+                            self.bus_stats.window_peak_load(),
+                            self.bus_stats.peak_load(),
+                            |load| format!("{load:.1}%"),
+                        ));
+                        ui.menu_button("⚙", |ui| self.show_peak_settings(ui));
                     });
-                    ui.label(format!("Peak: {:.1}%", self.bus_stats.peak_load()));
                     ui.label(format!("Average: {:.1}%", self.bus_stats.avg_load()));
+                    if let (Some(frames), Some(counter)) =
+                        (self.bus_stats.frame_load(), self.bus_stats.counter_load())
+                    {
+                        if (frames - counter).abs() > LOAD_DISAGREEMENT {
+                            ui.colored_label(Status::Warning.color(), format!("Frames: {frames:.1}% / counter: {counter:.1}%"))
+                                .on_hover_text("Load from the frame bits with worst case stuffing vs from the bit counter of the CANopen stack");
+                        }
+                    }
                 });
                 
                 ui.separator();
@@ -218,7 +970,12 @@ impl Gui {
                 ui.vertical(|ui| {
                     ui.label("📬 Message Rate");
                     ui.label(format!("Current: {:.0} msg/s", self.bus_stats.current_msg_rate()));
-                    ui.label(format!("Peak: {:.0} msg/s", self.bus_stats.peak_msg_rate()));
+                    ui.label(format_peak(
+                        self.bus_stats.peak_window(),
+                        self.bus_stats.window_peak_msg_rate(),
+                        self.bus_stats.peak_msg_rate(),
+                        |rate| format!("{rate:.0} msg/s"),
+                    ));
                     ui.label(format!("Average: {:.1} msg/s", self.bus_stats.avg_msg_rate()));
                 });
                 
@@ -228,17 +985,17 @@ impl Gui {
                 ui.vertical(|ui| {
                     ui.label("⏱️ Inter-Frame Timing");
                     if let Some(min_gap) = self.bus_stats.min_gap() {
-                        ui.label(format!("Min: {:.2} ms", min_gap));
+                        ui.label(format!("Min: {min_gap:.2} ms"));
                     } else {
                         ui.label("Min: --");
                     }
                     if let Some(max_gap) = self.bus_stats.max_gap() {
-                        ui.label(format!("Max: {:.1} ms", max_gap));
+                        ui.label(format!("Max: {max_gap:.1} ms"));
                     } else {
                         ui.label("Max: --");
                     }
                     if let Some(avg_gap) = self.bus_stats.avg_gap() {
-                        ui.label(format!("Avg: {:.2} ms", avg_gap));
+                        ui.label(format!("Avg: {avg_gap:.2} ms"));
                     } else {
                         ui.label("Avg: --");
                     }
@@ -251,92 +1008,513 @@ impl Gui {
                     ui.label("📊 Totals");
                     ui.label(format!("Messages: {}", self.bus_stats.total_messages()));
                     if let Some(jitter) = self.bus_stats.jitter() {
-                        ui.label(format!("Jitter: ±{:.2} ms", jitter));
+                        ui.label(format!("Jitter: ±{jitter:.2} ms"));
                     } else {
                         ui.label("Jitter: --");
                     }
                 });
+                
+                ui.separator();
+                
+                self.show_sync_card(ui);
+                
+                ui.separator();
+                
+                // Error frames section
+                ui.vertical(|ui| {
+                    let errors = self.bus_stats.errors();
+                    let rate = self.bus_stats.current_error_rate();
+                    let color = if rate > 0.0 { Status::Error.color() } else { ui.visuals().text_color() };
+                    ui.colored_label(color, "⚠ Errors");
+                    ui.colored_label(color, format!("Rate: {rate:.1} err/s"))
+                        .on_hover_text("Error frames per second over the last 5 seconds");
+                    ui.colored_label(color, format!("Share: {:.2}%", self.bus_stats.error_ratio()))
+                        .on_hover_text("Error frames among all the frames received lately");
+                    ui.label(format!("Error frames: {}", errors.frames));
+                    ui.horizontal(|ui| {
+                        ui.label("State:");
+                        ui.colored_label(errors.state.color(), errors.state.name())
+                            .on_hover_text("Controller state as last reported by an error frame");
+                    });
+                });
             });
         });
     }
     
-    fn show_stats_panel(&self, ui: &mut Ui) {
-        ui.vertical(|ui| {
-            ui.heading("📈 Detailed Stats");
-            ui.separator();
-            
-            // Top COB-IDs
-            ui.label("🏆 Most Frequent COB-IDs:");
-            ui.separator();
-            
-            let top_cobs = self.bus_stats.get_top_cob_ids(10);
-            if top_cobs.is_empty() {
-                ui.label("No data yet");
-            } else {
-                egui::Grid::new("top_cob_ids")
-                    .striped(true)
-                    .show(ui, |ui| {
-                        ui.label("COB-ID");
-                        ui.label("Rate");
+    /// Writes a snapshot of the statistics on a separate thread.
+    fn export_stats(&mut self) {
+        let now = Instant::now();
+        let report = StatsReport::new(
+            Local::now(),
+            self.connection.can_name.clone(),
+            self.connection.bitrate,
+            self.interface_bitrate,
+            self.bus_stats.snapshot(now),
+            self.nodes.snapshot(now),
+        );
+        let rows = report.stats.cob_ids.len();
+        self.stats_export
+            .start(StatsReport::file_name(), rows, move || report.to_json());
+    }
+
+    /// DLC distribution and payload vs overhead bytes, in total or per node.
+    #[allow(clippy::cast_precision_loss)]
+    fn show_payload_stats(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.label("📦 Payload Sizes:");
+            ui.selectable_value(&mut self.payload_view, PayloadView::Dlc, "DLC");
+            ui.selectable_value(&mut self.payload_view, PayloadView::PerNode, "per node");
+        });
+        ui.separator();
+        let payload = self.bus_stats.payload();
+        let frames = payload.frames();
+        if frames == 0 {
+            ui.label("No data yet");
+            return;
+        }
+        if self.payload_view == PayloadView::PerNode {
+            egui::Grid::new("payload_per_node")
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.label("Node");
+                    ui.label("Frames");
+                    ui.label("Avg DLC");
+                    ui.label("Payload");
+                    ui.end_row();
+                    for (node_id, stats) in self.bus_stats.payload_per_node() {
+                        ui.label(node_id.to_string());
+                        ui.label(stats.frames().to_string())
+                            .on_hover_text(format!("Frames per DLC 0–8: {:?}", stats.dlc_counts));
+                        ui.label(format!("{:.1}", stats.avg_dlc()));
+                        ui.label(format!("{:.1}%", stats.efficiency()));
                         ui.end_row();
-                        
-                        for (cob_id, rate) in top_cobs {
-                            ui.label(format!("0x{:03X}", cob_id));
-                            if rate >= 1.0 {
-                                ui.label(format!("{:.1} Hz", rate));
-                            } else {
-                                ui.label(format!("{:.2} Hz", rate));
-                            }
-                            ui.end_row();
-                        }
-                    });
-            }
-            
-            ui.separator();
-            
-            // Bus occupation details
-            ui.label("🔋 Bus Occupation Details:");
-            ui.separator();
-            ui.label(format!("• Current: {:.2}%", self.bus_stats.current_load()));
-            ui.label(format!("• Peak: {:.2}%", self.bus_stats.peak_load()));
-            ui.label(format!("• Average: {:.2}%", self.bus_stats.avg_load()));
-            
-            ui.separator();
-            
-            // Timing details
-            ui.label("⏱️ Timing Details:");
-            ui.separator();
-            if let Some(min_gap) = self.bus_stats.min_gap() {
-                ui.label(format!("• Min gap: {:.3} ms", min_gap));
-            }
-            if let Some(max_gap) = self.bus_stats.max_gap() {
-                ui.label(format!("• Max gap: {:.1} ms", max_gap));
+                    }
+                });
+        } else {
+            egui::Grid::new("payload_dlc")
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.label("DLC");
+                    ui.label("Frames");
+                    ui.label("Share");
+                    ui.end_row();
+                    for (dlc, count) in payload.dlc_counts.iter().enumerate() {
+                        ui.label(dlc.to_string());
+                        ui.label(count.to_string());
+                        ui.label(format!("{:.1}%", *count as f64 / frames as f64 * 100.0));
+                        ui.end_row();
+                    }
+                });
+        }
+        ui.label(format!("• Payload: {} bytes", payload.payload_bytes));
+        ui.label(format!("• Overhead: {:.0} bytes", payload.overhead_bytes))
+            .on_hover_text("Arbitration, control, CRC, ACK, EOF, interframe space and worst case stuff bits");
+        ui.label(format!("• Efficiency: {:.1}%", payload.efficiency()))
+            .on_hover_text("Share of the frame bytes that are payload");
+    }
+
+    /// Rates of the bus or values of the pinned filters, in tabs.
+    fn show_charts(&mut self, ui: &mut Ui, height: f32) {
+        ui.horizontal(|ui| {
+            for tab in ChartTab::ALL {
+                if ui
+                    .selectable_value(&mut self.chart_tab, tab, tab.name())
+                    .on_hover_text(tab.description())
+                    .clicked()
+                {
+                    tab.save();
+                }
             }
-            if let Some(avg_gap) = self.bus_stats.avg_gap() {
-                ui.label(format!("• Avg gap: {:.3} ms", avg_gap));
+        });
+        match self.chart_tab {
+            ChartTab::Bandwidth => {
+                let bitrate = self.load_bitrate().map(|(bitrate, _)| bitrate);
+                self.chart.ui(ui, &mut self.bus_stats, bitrate, height);
             }
-            if let Some(jitter) = self.bus_stats.jitter() {
-                ui.label(format!("• Jitter (σ): ±{:.3} ms", jitter));
+            ChartTab::Load => self.load_chart.ui(ui, height),
+            ChartTab::Signals => self.overlay.ui(ui, &self.pinned_filters, height),
+        }
+    }
+
+    /// Window of the peak load and peak message rate.
+    fn show_peak_settings(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Peak window:");
+            let mut window = self.bus_stats.peak_window().as_secs_f64();
+            if ui
+                .add(
+                    egui::DragValue::new(&mut window)
+                        .range(PEAK_WINDOW_RANGE)
+                        .suffix(" s"),
+                )
+                .on_hover_text("Peaks are taken over this sliding window, the all-time peaks are kept too")
+                .changed()
+            {
+                self.bus_stats.set_peak_window(Duration::from_secs_f64(window));
             }
-            
-            ui.separator();
-            
-            // Message rate details
-            ui.label("📬 Message Rate Details:");
-            ui.separator();
-            ui.label(format!("• Current: {:.1} msg/s", self.bus_stats.current_msg_rate()));
-            ui.label(format!("• Peak: {:.1} msg/s", self.bus_stats.peak_msg_rate()));
-            ui.label(format!("• Average: {:.2} msg/s", self.bus_stats.avg_msg_rate()));
-            ui.label(format!("• Total: {}", self.bus_stats.total_messages()));
         });
     }
 
-    fn show_connect_ui(&mut self, ui: &mut Ui) {
-        ui.add(
-            TextEdit::singleline(&mut self.can_name_raw)
-                .hint_text("can name")
+    /// Period and jitter of the SYNC frames, with a warning when one is missing.
+    fn show_sync_card(&mut self, ui: &mut Ui) {
+        ui.vertical(|ui| {
+            let sync = self.bus_stats.sync();
+            let overdue = sync.is_overdue(Instant::now());
+            let mut window = sync.window();
+            ui.horizontal(|ui| {
+                ui.label("🕐 SYNC");
+                if overdue {
+                    ui.colored_label(Status::Error.color(), "⚠ missing")
+                        .on_hover_text("No SYNC for more than 1.5 learned periods");
+                }
+            });
+            if let Some(deviation) = sync.deviation() {
+                ui.label(format!("Period: {:.3} ms", deviation.mean));
+                ui.label(format!("Jitter: ±{:.3} ms", deviation.std_dev))
+                    .on_hover_text(format!("Worst deviation {:.3} ms", deviation.worst));
+            } else {
+                ui.label("Period: --");
+                ui.label("Jitter: --");
+            }
+            let missed = sync.missed();
+            let text = format!("Count: {} ({missed} missed)", sync.count());
+            if missed > 0 {
+                ui.colored_label(Status::Warning.color(), text);
+            } else {
+                ui.label(text);
+            }
+            ui.horizontal(|ui| {
+                ui.label("Window:");
+                if ui
+                    .add(egui::DragValue::new(&mut window).range(SYNC_WINDOW_RANGE))
+                    .on_hover_text("Number of SYNC intervals the period and jitter are computed over")
+                    .changed()
+                {
+                    self.bus_stats.sync_mut().set_window(window);
+                }
+            });
+        });
+    }
+
+    /// Latency from each SYNC to the first following TPDO of every node.
+    fn show_sync_latency(&self, ui: &mut Ui) {
+        ui.label("🕐 SYNC → TPDO Latency:");
+        ui.separator();
+        let mut latencies = self.bus_stats.sync().latencies().peekable();
+        if latencies.peek().is_none() {
+            ui.label("No TPDO after a SYNC yet");
+            return;
+        }
+        egui::Grid::new("sync_latency")
+            .striped(true)
+            .show(ui, |ui| {
+                ui.label("Node");
+                ui.label("Mean");
+                ui.label("σ");
+                ui.label("Worst");
+                ui.end_row();
+                for (node_id, latency) in latencies {
+                    ui.label(node_id.to_string());
+                    ui.label(format!("{:.3} ms", latency.mean));
+                    ui.label(format!("±{:.3}", latency.std_dev));
+                    ui.label(format!("{:.3}", latency.mean + latency.worst))
+                        .on_hover_text("Largest latency from the mean and worst deviation");
+                    ui.end_row();
+                }
+            });
+    }
+
+    /// Emergencies per node, a click on a row scrolls the viewer to the last one.
+    fn show_emcy_stats(&mut self, ui: &mut Ui) {
+        ui.label("🚨 Emergencies per Node:");
+        ui.separator();
+        if self.bus_stats.emcy().is_empty() {
+            ui.label("No emergencies");
+            return;
+        }
+        let mut jump = None;
+        egui::Grid::new("emcy_per_node")
+            .striped(true)
+            .show(ui, |ui| {
+                ui.label("Node");
+                ui.label("EMCYs");
+                ui.label("Last code");
+                ui.label("Time");
+                ui.end_row();
+
+                for (node_id, stats) in self.bus_stats.emcy() {
+                    let color = if stats.last_code == 0 {
+                        ui.visuals().text_color()
+                    } else {
+                        Status::Error.color()
+                    };
+                    let text = |text: String| egui::RichText::new(text).color(color);
+                    if ui
+                        .selectable_label(false, text(node_id.to_string()))
+                        .on_hover_text("Show the last emergency of the node in the viewer")
+                        .clicked()
+                    {
+                        jump = Some(Jump::Index(stats.last_index));
+                    }
+                    let codes: Vec<_> = stats
+                        .codes
+                        .iter()
+                        .map(|(code, count)| {
+                            let name = emcy_codes::describe(*code).unwrap_or_default();
+                            format!("0x{code:04X} {name}: {count}")
+                        })
+                        .collect();
+                    ui.label(text(stats.total.to_string()))
+                        .on_hover_text(codes.join("\n"));
+                    ui.label(text(format!("0x{:04X}", stats.last_code)))
+                        .on_hover_text(emcy_codes::describe(stats.last_code).unwrap_or("Unknown code"));
+                    ui.label(text(stats.last_time.format("%H:%M:%S").to_string()));
+                    ui.end_row();
+                }
+            });
+        if let Some(jump) = jump {
+            self.viewer.jump_to(jump);
+        }
+    }
+
+    fn show_top_cob_ids(&mut self, ui: &mut Ui) {
+        ui.label("🏆 Most Frequent COB-IDs:");
+        ui.horizontal(|ui| {
+            ui.label("Window:");
+            let mut window = self.bus_stats.rate_window().as_secs_f64();
+            if ui
+                .add(
+                    egui::DragValue::new(&mut window)
+                        .range(RATE_WINDOW_RANGE)
+                        .speed(0.1)
+                        .suffix(" s"),
+                )
+                .on_hover_text("Rates are counted over this sliding window, COB-IDs not seen within it are stale")
+                .changed()
+            {
+                self.bus_stats.set_rate_window(Duration::from_secs_f64(window));
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label(format!("Tracked: {} of", self.bus_stats.tracked_cob_ids()));
+            let mut max = self.bus_stats.max_cob_ids();
+            if ui
+                .add(egui::DragValue::new(&mut max).range(MAX_COB_IDS_RANGE))
+                .on_hover_text("COB-IDs with their own statistics, the least recently seen ones are counted as other")
+                .changed()
+            {
+                self.bus_stats.set_max_cob_ids(max);
+            }
+            let other = self.bus_stats.other_cob_ids();
+            if other.evicted > 0 {
+                ui.label(format!("Other: {} frames", other.count))
+                    .on_hover_text(format!("{} COB-IDs evicted", other.evicted));
+            }
+        });
+        ui.separator();
+        
+        let top_cobs = self.bus_stats.get_top_cob_ids(10);
+        if top_cobs.is_empty() {
+            ui.label("No data yet");
+        } else {
+            egui::Grid::new("top_cob_ids")
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.label("COB-ID");
+                    ui.label("Rate");
+                    ui.label("Period")
+                        .on_hover_text("Mean interval over the last arrivals of the COB-ID");
+                    ui.label("σ");
+                    ui.label("Min");
+                    ui.label("Max");
+                    ui.end_row();
+                    
+                    for (cob_id, rate) in top_cobs {
+                        let plotted = self.bus_stats.is_tracked(cob_id);
+                        if ui
+                            .selectable_label(plotted, format!("0x{cob_id:03X}"))
+                            .on_hover_text("Click to plot the rate of this COB-ID in the chart")
+                            .clicked()
+                        {
+                            chart::toggle_rate_plot(&mut self.bus_stats, cob_id);
+                        }
+                        match rate {
+                            Some(rate) if rate >= 1.0 => ui.label(format!("{rate:.1} Hz")),
+                            Some(rate) => ui.label(format!("{rate:.2} Hz")),
+                            None => ui
+                                .weak("– stale")
+                                .on_hover_text("Not seen within the window"),
+                        };
+                        if let Some(period) = self.bus_stats.period_stats(cob_id) {
+                            ui.label(format!("{:.2} ms", period.mean));
+                            ui.label(format!("±{:.2}", period.std_dev));
+                            ui.label(format!("{:.2}", period.min));
+                            ui.label(format!("{:.2}", period.max));
+                        }
+                        ui.end_row();
+                    }
+                });
+        }
+    }
+
+    fn show_stats_panel(&mut self, ui: &mut Ui) {
+        ui.vertical(|ui| {
+            ui.horizontal(|ui| {
+                ui.heading("📈 Detailed Stats");
+                if ui
+                    .add_enabled(self.stats_export.is_idle(), Button::new("Export stats…"))
+                    .on_hover_text("Write the statistics and the nodes to a JSON file in the working directory")
+                    .clicked()
+                {
+                    self.export_stats();
+                }
+            });
+            self.stats_export.ui(ui);
+            ui.separator();
+            
+            self.show_top_cob_ids(ui);
+
+            // Bus occupation details
+            ui.label("🔋 Bus Occupation Details:");
+            ui.separator();
+            ui.label(format!("• Current: {:.2}%", self.bus_stats.current_load()));
+            ui.label(format!("• {}", format_peak(
+                self.bus_stats.peak_window(),
+                self.bus_stats.window_peak_load(),
+                self.bus_stats.peak_load(),
+                |load| format!("{load:.2}%"),
+            )));
+            ui.label(format!("• Average: {:.2}%", self.bus_stats.avg_load()));
+            
+            ui.separator();
+            
+            // Timing details
+            ui.label("⏱️ Timing Details:");
+            ui.separator();
+            if let Some(min_gap) = self.bus_stats.min_gap() {
+                ui.label(format!("• Min gap: {min_gap:.3} ms"));
+            }
+            if let Some(max_gap) = self.bus_stats.max_gap() {
+                ui.label(format!("• Max gap: {max_gap:.1} ms"));
+            }
+            if let Some(avg_gap) = self.bus_stats.avg_gap() {
+                ui.label(format!("• Avg gap: {avg_gap:.3} ms"));
+            }
+            if let Some(jitter) = self.bus_stats.jitter() {
+                ui.label(format!("• Jitter (σ): ±{jitter:.3} ms"));
+            }
+            egui::CollapsingHeader::new("📶 Gap histogram")
+                .show(ui, |ui| self.gap_histogram.update(ui, self.bus_stats.gap_history()));
+            
+            ui.separator();
+            
+            // Message rate details
+            ui.label("📬 Message Rate Details:");
+            ui.separator();
+            ui.label(format!("• Current: {:.1} msg/s", self.bus_stats.current_msg_rate()));
+            ui.label(format!("• {}", format_peak(
+                self.bus_stats.peak_window(),
+                self.bus_stats.window_peak_msg_rate(),
+                self.bus_stats.peak_msg_rate(),
+                |rate| format!("{rate:.1} msg/s"),
+            )));
+            ui.label(format!("• Average: {:.2} msg/s", self.bus_stats.avg_msg_rate()));
+            ui.label(format!("• Total: {}", self.bus_stats.total_messages()));
+
+            ui.separator();
+
+            self.show_payload_stats(ui);
+            ui.separator();
+
+            self.show_sync_latency(ui);
+            ui.separator();
+
+            egui::CollapsingHeader::new("⏰ Deadlines")
+                .show(ui, |ui| self.bus_stats.deadlines_mut().ui(ui));
+            ui.separator();
+
+            self.show_emcy_stats(ui);
+            ui.separator();
+
+            // Error frame details
+            ui.label("⚠ Error Frames:");
+            ui.separator();
+            let errors = self.bus_stats.errors();
+            ui.label(format!("• Total: {}", errors.frames));
+            for class in ErrorClass::ALL {
+                let count = errors.get(class);
+                let text = format!("• {}: {count}", class.name());
+                if count > 0 {
+                    ui.colored_label(Status::Error.color(), text);
+                } else {
+                    ui.label(text);
+                }
+            }
+
+            ui.separator();
+
+            if let Some(gateway) = &self.gateway {
+                Self::show_gateway_stats(ui, gateway);
+                ui.separator();
+            }
+
+            // Buffer details
+            ui.label("🗄 Buffers:");
+            ui.separator();
+            let messages_memory: usize = self.data.iter().map(MessageCached::memory_usage).sum();
+            ui.label(format!(
+                "• Viewer: {}/{} ({})",
+                self.data.len(),
+                self.buffers.messages,
+                format_bytes(messages_memory)
+            ));
+            ui.label(format!(
+                "• Pinned: {}",
+                format_bytes(self.pinned_filters.memory_usage())
+            ));
+        });
+    }
+
+    fn show_gateway_stats(ui: &mut Ui, gateway: &GatewayStats) {
+        ui.label(format!("🔀 Gateway (B = {}):", gateway.peer));
+        ui.separator();
+        egui::Grid::new("gateway_stats")
+            .striped(true)
+            .show(ui, |ui| {
+                ui.label("");
+                ui.label("Fwd");
+                ui.label("Filtered");
+                ui.label("Dropped");
+                ui.label("Errors");
+                ui.end_row();
+
+                for (name, stats) in [("A → B", &gateway.a_to_b), ("B → A", &gateway.b_to_a)] {
+                    ui.label(name);
+                    ui.label(stats.forwarded.to_string());
+                    ui.label(stats.filtered.to_string());
+                    ui.label(stats.dropped.to_string());
+                    if stats.errors > 0 {
+                        ui.colored_label(Status::Error.color(), stats.errors.to_string());
+                    } else {
+                        ui.label("0");
+                    }
+                    ui.end_row();
+                }
+            });
+    }
+
+    fn show_connect_ui(&mut self, ui: &mut Ui) {
+        ui.add(
+            TextEdit::singleline(&mut self.can_name_raw)
+                .hint_text("can name")
                 .desired_width(100.0),
         );
+        let menu = ui.menu_button("⏷", |ui| self.show_interfaces(ui));
+        menu.response.on_hover_text("CAN interfaces of this system");
+        // Listed again on each opening, adapters come and go.
+        if menu.inner.is_none() {
+            self.interfaces = None;
+        }
 
         ui.add(
             TextEdit::singleline(&mut self.bitrate_raw)
@@ -353,154 +1531,1313 @@ impl Gui {
             .add_enabled(button_enbled, Button::new("🔌Connect"))
             .clicked()
         {
-            self.connection.can_name = self.can_name_raw.clone();
-            self.connection.bitrate = bitrate;
+            let connection = Connection {
+                can_name: self.can_name_raw.clone(),
+                bitrate,
+            };
+            if connection != self.connection {
+                self.drops.reset();
+                if self.clear_on_connect {
+                    self.clear_history();
+                }
+            }
+            self.connection = connection;
+            self.disconnected = false;
+            self.send_driver_control();
+        }
+
+        if ui
+            .add_enabled(!self.disconnected, Button::new("⏏Disconnect"))
+            .on_hover_text("Stop processing received messages, statistics and node tracking included")
+            .clicked()
+        {
+            self.disconnected = true;
             self.send_driver_control();
         }
     }
 
+    fn show_interfaces(&mut self, ui: &mut Ui) {
+        let interfaces = self.interfaces.get_or_insert_with(interfaces::list);
+        if interfaces.is_empty() {
+            ui.label("No CAN interface found, type its name");
+        }
+        for interface in interfaces.iter() {
+            let color = if interface.up {
+                Status::Ok.color()
+            } else {
+                OZON_GRAY
+            };
+            if ui
+                .button(egui::RichText::new(interface.to_string()).color(color))
+                .clicked()
+            {
+                self.can_name_raw.clone_from(&interface.name);
+                ui.close_menu();
+            }
+        }
+    }
+
     fn show_format_ui(&mut self, ui: &mut Ui) {
         if ui
-            .selectable_label(self.format == RxMessageToStringFormat::Hex, "hex")
+            .selectable_label(self.format == DataFormat::Bytes(RxMessageToStringFormat::Hex), "hex")
             .on_hover_text("Use HEX format to show message data")
             .clicked()
         {
-            self.format = RxMessageToStringFormat::Hex;
+            self.format = DataFormat::Bytes(RxMessageToStringFormat::Hex);
         }
         if ui
-            .selectable_label(self.format == RxMessageToStringFormat::Binary, "bin")
+            .selectable_label(self.format == DataFormat::Bytes(RxMessageToStringFormat::Binary), "bin")
             .on_hover_text("Use binary format to show message data")
             .clicked()
         {
-            self.format = RxMessageToStringFormat::Binary;
+            self.format = DataFormat::Bytes(RxMessageToStringFormat::Binary);
         }
         if ui
-            .selectable_label(self.format == RxMessageToStringFormat::Ascii, "ascii")
+            .selectable_label(self.format == DataFormat::Bytes(RxMessageToStringFormat::Ascii), "ascii")
             .on_hover_text("Use ASCII encoding to show message data")
             .clicked()
         {
-            self.format = RxMessageToStringFormat::Ascii;
+            self.format = DataFormat::Bytes(RxMessageToStringFormat::Ascii);
+        }
+        self.format.typed_ui(ui);
+        ui.separator();
+        for mode in TimestampMode::ALL {
+            ui.selectable_value(&mut self.timestamp_mode, mode, mode.as_str())
+                .on_hover_text(mode.description());
         }
     }
 
-    fn show_connection_help(ui: &mut Ui) {
-        ui.horizontal_wrapped(|ui| {
-                    ui.colored_label(OZON_PINK, "↑ You need to enter can name, i.e.");
-                    ui.colored_label(OZON_GRAY, "can0");
-                    ui.colored_label(OZON_PINK, "and optionally bitrate. If bitrate is set then link will go down, bitrate will be changed and then link will be set up.");
-                });
-        ui.colored_label(OZON_PINK, "Or your CAN interface is not connected properly");
-        ui.label("Or you can execute program with arguments default values, for help execute:");
-        ui.colored_label(OZON_GRAY, "oze-canopen-viewer --help");
+    fn on_node_action(&mut self, action: NodeAction) {
+        match action {
+            NodeAction::UseInSender(node_id) => self.message_sender.set_node_id(node_id),
+            NodeAction::ReadIdentity(node_id) => {
+                self.message_sender.send(WriteCommand::ReadIdentity { node_id });
+            }
+            NodeAction::ShowDeviceInfo(node_id) => {
+                self.device_info.open(node_id);
+                if self.offline.is_none() && !self.device_infos.contains_key(&node_id) {
+                    self.message_sender.send(WriteCommand::ReadDeviceInfo { node_id });
+                }
+            }
+            NodeAction::ShowPdoConfig(node_id) => {
+                self.pdo_config.open(node_id);
+                if self.offline.is_none() && !self.pdo_config.is_read(node_id) {
+                    self.message_sender.send(WriteCommand::ReadPdoConfig { node_id });
+                }
+            }
+            NodeAction::BrowseObjects(node_id) => {
+                self.od_browser.open(node_id);
+                if self.offline.is_none() && !self.od_browser.is_scanned(node_id) {
+                    self.od_browser.scan(node_id);
+                }
+            }
+            NodeAction::FilterTraffic(node_id) => {
+                self.filter_panel.data_panel.set_node_id(Some(node_id));
+            }
+        }
     }
-}
 
-impl eframe::App for Gui {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        let fps = self.calc_fps();
-        let connected =
-            self.info.receiver_socket || self.info.transmitter_socket || self.info.rx_bits > 0;
-        if self.get_data_from_driver() {
-            println!("Gracefull shutdown");
-            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
-            ctx.request_repaint();
-            return;
+    fn on_live_action(&mut self, action: LiveAction) {
+        let cob_id_rule = |cob_id| {
+            let mut panel = FilterDataPanel::new(Rc::new(RefCell::new(DataFilter::default())));
+            panel.set_cob_id(Some(cob_id));
+            panel
+        };
+        match action {
+            LiveAction::Pin(cob_id) => {
+                self.pinned_filters.pin_filter(cob_id_rule(cob_id), &self.data);
+            }
+            LiveAction::Filter(cob_id) => self.filter_panel.data_panel.set_cob_id(Some(cob_id)),
+            LiveAction::ShowOnly(cob_id) => self.filter_panel.add_show_rule(cob_id_rule(cob_id)),
+            LiveAction::Hide(cob_id) => self.filter_panel.add_hide_rule(cob_id_rule(cob_id)),
+            LiveAction::HideNode(node_id) => {
+                let mut panel = FilterDataPanel::new(Rc::new(RefCell::new(DataFilter::default())));
+                panel.set_node_id(Some(node_id));
+                self.filter_panel.add_hide_rule(panel);
+            }
         }
+    }
 
-        egui::TopBottomPanel::top("top").show(ctx, |ui| {
-            ui.horizontal(|ui| {
-                self.show_connect_ui(ui);
-                ui.separator();
+    /// Filter panel and the filter profiles.
+    fn show_filters(&mut self, ui: &mut Ui) {
+        let to_pin = self.filter_panel.update(ui, &self.data);
+        if self.stopped != self.filter_panel.stop {
+            self.stopped = self.filter_panel.stop;
+            self.send_driver_control();
+        }
+        if let Some(to_pin) = to_pin {
+            self.pinned_filters.pin_filter(to_pin, &self.data);
+        }
 
-                self.show_format_ui(ui);
-                ui.separator();
+        ui.collapsing("Filter profiles", |ui| {
+            let (filter_panel, pinned_filters) = (&self.filter_panel, &self.pinned_filters);
+            let current = || filter_panel.profile(pinned_filters.configs());
+            if let Some(profile) = self.filter_profiles.update(ui, current) {
+                self.apply_profile(&profile);
+            }
+        });
+    }
+
+    /// Replaces the filters and the pinned filters by the ones of a profile.
+    /// Restores the display format, filters and sender values.
+    fn apply_settings(&mut self, settings: &AppSettings) {
+        self.format = settings.format;
+        self.timestamp_mode = settings.timestamp_mode;
+        if let Some(filters) = &settings.filters {
+            self.apply_profile(filters);
+        }
+        self.message_sender.apply_settings(settings.sender.clone());
+        self.interpreters.clone_from(&settings.interpreters);
+    }
+
+    /// Applies the display and filter options of the command line over the restored settings.
+    pub fn apply_startup(&mut self, startup: &Startup) {
+        if let Some(format) = startup.format {
+            self.format = format;
+        }
+        if let Some(filters) = &startup.filters {
+            self.apply_profile(filters);
+        }
+        if startup.hide_heartbeats {
+            self.global_filter
+                .borrow_mut()
+                .ignore_class
+                .insert(Classes::HEARTBEAT);
+        }
+    }
+
+    /// Settings of the session, with the layout of `window`.
+    fn current_settings(&self, window: &Window) -> AppSettings {
+        AppSettings {
+            can_name: self.can_name_raw.clone(),
+            bitrate: self.bitrate_raw.clone(),
+            format: self.format,
+            timestamp_mode: self.timestamp_mode,
+            filters: Some(self.filter_panel.profile(self.pinned_filters.configs())),
+            sender: self.message_sender.settings(),
+            panels: window.panels,
+            hidden_panels: window.hidden_panels,
+            max_fps: window.max_fps,
+            interpreters: self.interpreters.clone(),
+        }
+    }
+
+    fn on_layout_action(&mut self, action: &LayoutAction, window: &mut Window, ctx: &egui::Context) {
+        let layout = match action {
+            LayoutAction::Apply(name) => window.layouts.select(name),
+            LayoutAction::Reset(name) => window.layouts.reset(name),
+            LayoutAction::Save(name) => {
+                let layout = WindowLayout {
+                    panels: window.panels,
+                    hidden_panels: window.hidden_panels,
+                    columns: self.columns.clone(),
+                    chart_tab: self.chart_tab,
+                };
+                window.layouts.store(name, layout);
+                return;
+            }
+        };
+        // The layout may come from a larger screen.
+        window.panels = layout.panels.fit(ctx.screen_rect().size());
+        window.forced_panels = Some(window.panels);
+        window.hidden_panels = layout.hidden_panels;
+        self.columns = layout.columns;
+        self.columns.save();
+        self.chart_tab = layout.chart_tab;
+        self.chart_tab.save();
+    }
+
+    /// Everything but the connection fields back to the defaults.
+    fn reset_settings(&mut self, window: &mut Window) {
+        let defaults = AppSettings {
+            filters: Some(FilterProfile::default()),
+            ..AppSettings::default()
+        };
+        self.apply_settings(&defaults);
+        window.apply_settings(&defaults);
+        window.forced_panels = Some(window.panels);
+    }
+
+    fn apply_profile(&mut self, profile: &FilterProfile) {
+        self.filter_panel.apply_profile(profile);
+        self.pinned_filters.set_configs(&profile.pinned, &self.data);
+    }
+
+    /// Decodes the buffered messages again with the DBC or EDS file just loaded or unloaded.
+    fn redecode(&mut self) {
+        self.data.iter_mut().for_each(MessageCached::redecode);
+        self.pinned_filters.redecode();
+        self.live.redecode();
+    }
+
+    /// Pinned filters and the viewer.
+    fn show_messages(&mut self, ui: &mut Ui, row_colors: &RowColors) {
+        self.viewer.message_row.format = self.format;
+        self.pinned_filters.message_row.format = self.format;
+        self.viewer.message_row.timestamp_mode = self.timestamp_mode;
+        self.pinned_filters.message_row.timestamp_mode = self.timestamp_mode;
+        self.viewer.message_row.highlight = self.byte_changes.list_fade();
+        self.pinned_filters.message_row.highlight = self.byte_changes.list_fade();
+        self.viewer.message_row.columns.clone_from(&self.columns);
+        self.pinned_filters.message_row.columns.clone_from(&self.columns);
+        self.viewer.message_row.interpreters.clone_from(&self.interpreters);
+        self.pinned_filters.message_row.interpreters.clone_from(&self.interpreters);
+        self.viewer.message_row.colors.clone_from(row_colors);
+        self.pinned_filters.message_row.colors.clone_from(row_colors);
+
+        self.pinned_filters.update(ui);
+        ui.separator();
+        if let Some(action) = self.viewer.show(
+            ui,
+            &self.data,
+            &self.sessions,
+            &mut self.live,
+            &self.byte_changes,
+        ) {
+            self.on_live_action(action);
+        }
+
+        // Only one table keeps a selection so that copying is unambiguous.
+        if self.pinned_filters.selection.take_clicked() {
+            self.viewer.selection.clear();
+            self.detail
+                .set(self.pinned_filters.selected_message().cloned());
+        }
+        if self.viewer.selection.take_clicked() {
+            self.pinned_filters.selection.clear();
+            self.detail
+                .set(self.viewer.selected_message(&self.data).cloned());
+        }
+
+        // Columns chosen from the header of one of the tables.
+        let viewer = self.viewer.message_row.take_edited_columns();
+        let pinned = self.pinned_filters.message_row.take_edited_columns();
+        if let Some(columns) = viewer.or(pinned) {
+            self.columns = columns;
+        }
+    }
 
-                ui.label(format!(
-                    "rx {} tx {}",
-                    self.info.receiver_socket, self.info.transmitter_socket,
+    /// Connection status, with the interface losses on hover.
+    fn show_status(&self, ui: &mut Ui) {
+        let color = match self.status {
+            ConnectionStatus::Disconnected => OZON_GRAY,
+            ConnectionStatus::Connecting => Status::Warning.color(),
+            ConnectionStatus::Idle => OZON_BLUE_ACTIVE,
+            ConnectionStatus::Active => Status::Ok.color(),
+            ConnectionStatus::Error => Status::Error.color(),
+        };
+        ui.colored_label(color, format!("● {}", self.status.as_str()))
+            .on_hover_ui(|ui| {
+                ui.label(format!("Link: {}", self.link.as_str()));
+                if self.link_events.is_empty() {
+                    ui.label("No interface losses");
+                }
+                for event in &self.link_events {
+                    ui.label(event.to_string());
+                }
+            });
+    }
+
+    /// Controller state and error counters, with the details on click.
+    fn show_health(&self, ui: &mut Ui) {
+        let Some(health) = self.health else {
+            return;
+        };
+        let text = match health.counters {
+            Some((tx, rx)) => format!("{} TX {tx} RX {rx}", health.name()),
+            None => health.name().to_owned(),
+        };
+        ui.menu_button(egui::RichText::new(text).color(health.color()), |ui| {
+            egui::Grid::new("health").show(ui, |ui| {
+                ui.label("State:");
+                ui.colored_label(health.color(), health.name());
+                ui.end_row();
+                if let Some((tx, rx)) = health.counters {
+                    ui.label("TX error counter:");
+                    ui.label(tx.to_string());
+                    ui.end_row();
+                    ui.label("RX error counter:");
+                    ui.label(rx.to_string());
+                    ui.end_row();
+                }
+                ui.label("Restarts:");
+                ui.label(self.restarts.to_string());
+                ui.end_row();
+                ui.label("Last state change:");
+                ui.label(self.health_changed.map_or_else(
+                    || "none since connected".to_owned(),
+                    |time| time.format("%H:%M:%S").to_string(),
                 ));
+                ui.end_row();
+            });
+        })
+        .response
+        .on_hover_text("Controller state and error counters of the interface");
+    }
 
-                ui.separator();
-                ui.label(format!("packets={}", self.data.len()));
+    /// File and statistics at the save of an offline session.
+    fn show_saved_source(&self, ui: &mut Ui) {
+        let Some(saved) = &self.offline else {
+            return;
+        };
+        ui.label(RichText::new(format!("📂 {} (offline)", saved.can_name)).strong())
+            .on_hover_ui(|ui| {
+                ui.label(format!("Saved session {}", saved.path));
+                ui.label(format!("Saved at {}", saved.saved));
+                if let Some(stats) = &saved.stats {
+                    ui.label(format!(
+                        "{} messages over {:.1} s, {} buffered",
+                        stats.total_messages,
+                        stats.duration_s,
+                        self.data.len()
+                    ));
+                }
+                ui.label("Read-only: sending is disabled");
+            });
+        if let Some(loading) = &self.loading {
+            ui.spinner();
+            ui.add(
+                egui::ProgressBar::new(loading.progress())
+                    .desired_width(120.0)
+                    .show_percentage(),
+            )
+            .on_hover_text(format!("Loading {} messages", loading.total));
+            ui.ctx().request_repaint();
+        }
+    }
 
+    fn show_top_bar(&mut self, ui: &mut Ui, window: &mut Window, fps: f64, connected: bool) {
+        ui.horizontal(|ui| {
+            if self.offline.is_some() {
+                self.show_saved_source(ui);
+            } else {
+                self.show_connect_ui(ui);
+            }
+            ui.separator();
+
+            self.show_format_ui(ui);
+            ui.separator();
+
+            ui.menu_button("⚙", |ui| self.show_buffer_settings(ui, window))
+                .response
+                .on_hover_text("Settings");
+            ui.menu_button("🎨", |ui| {
+                window.theme.ui(ui);
                 ui.separator();
-                if let Some(bus_load) = self.calc_bus_load() {
-                    let color = if bus_load > 80.0 {
-                        egui::Color32::RED
-                    } else if bus_load > 50.0 {
-                        egui::Color32::YELLOW
-                    } else {
-                        egui::Color32::GREEN
-                    };
-                    ui.colored_label(color, format!("Bus: {:.1}%", bus_load));
+                window.row_colors.ui(ui);
+            })
+            .response
+            .on_hover_text("Theme, scale and colours");
+            let view = ui.menu_button("View", |ui| window.show_view_menu(ui));
+            if let Some(action) = view.inner.flatten() {
+                self.on_layout_action(&action, window, ui.ctx());
+            }
+            ui.menu_button("🗑 Clear", |ui| self.show_clear_menu(ui))
+                .response
+                .on_hover_text("Start fresh without disconnecting");
+            ui.separator();
+
+            if self.offline.is_none() {
+                self.show_status(ui);
+                self.show_health(ui);
+                if self.driver_ctrl.borrow().auto_start.is_some() {
+                    ui.colored_label(ui.visuals().warn_fg_color, "⚡ Auto-start")
+                        .on_hover_text(format!(
+                            "{}, set in the NMT tab of the sender",
+                            self.message_sender.auto_start_summary()
+                        ));
                 }
+            }
+            ui.label(format!(
+                "rx {} tx {}",
+                self.info.receiver_socket, self.info.transmitter_socket,
+            ));
 
-                ui.with_layout(Layout::right_to_left(egui::Align::RIGHT), |ui| {
-                    ui.label(format!("{fps} FPS",));
-                });
+            ui.separator();
+            ui.label(format!("packets={}", self.data.len()));
+            self.drops.ui(ui);
+
+            ui.separator();
+            self.show_bus_load(ui);
+
+            ui.with_layout(Layout::right_to_left(egui::Align::RIGHT), |ui| {
+                ui.label(format!("{fps} FPS"));
             });
+        });
 
-            if !connected {
-                Self::show_connection_help(ui);
+        if !connected {
+            Self::show_connection_help(ui);
+        }
+    }
+
+    /// Sender, statistics and detail panels, at the sizes of the last run.
+    fn show_side_panels(&mut self, ctx: &egui::Context, window: &mut Window, connected: bool) {
+        let forced = window.forced_panels.take();
+        let sizes = forced.unwrap_or(window.panels);
+        let width = |panel: egui::SidePanel, width: f32| {
+            if forced.is_some() {
+                panel.exact_width(width)
+            } else {
+                panel.default_width(width)
             }
-        });
+        };
 
-        self.viewer.message_row.format = self.format;
-        self.pinned_filters.message_row.format = self.format;
-        
         // Left side panel for message sender
-        egui::SidePanel::left("message_sender_panel")
-            .resizable(true)
-            .default_width(350.0)
-            .min_width(300.0)
-            .show(ctx, |ui| {
-                ui.add_enabled_ui(connected, |ui| {
-                    egui::ScrollArea::vertical().show(ui, |ui| {
-                        self.message_sender.ui(ui);
+        if window.hidden_panels.contains(HiddenPanels::SENDER) {
+            window.show_panel_strip(ctx, egui::SidePanel::left("message_sender_strip"), HiddenPanels::SENDER);
+        } else {
+            let panel = egui::SidePanel::left("message_sender_panel")
+                .resizable(true)
+                .min_width(300.0);
+            window.panels.sender = width(panel, sizes.sender)
+                .show(ctx, |ui| {
+                    window.panel_toggle(ui, HiddenPanels::SENDER);
+                    ui.add_enabled_ui(connected && self.offline.is_none(), |ui| {
+                        egui::ScrollArea::vertical().show(ui, |ui| {
+                            self.message_sender.ui(ui, &self.periodic);
+                        });
                     });
-                });
-            });
-        
+                })
+                .response
+                .rect
+                .width();
+        }
+
         // Right side panel for detailed stats
-        egui::SidePanel::right("stats_panel")
-            .resizable(true)
-            .default_width(250.0)
-            .min_width(200.0)
-            .show(ctx, |ui| {
-                ui.add_enabled_ui(connected, |ui| {
-                    egui::ScrollArea::vertical().show(ui, |ui| {
-                        self.show_stats_panel(ui);
+        if window.hidden_panels.contains(HiddenPanels::STATS) {
+            window.show_panel_strip(ctx, egui::SidePanel::right("stats_strip"), HiddenPanels::STATS);
+        } else {
+            let panel = egui::SidePanel::right("stats_panel")
+                .resizable(true)
+                .min_width(200.0);
+            window.panels.stats = width(panel, sizes.stats)
+                .show(ctx, |ui| {
+                    window.panel_toggle(ui, HiddenPanels::STATS);
+                    ui.add_enabled_ui(connected, |ui| {
+                        egui::ScrollArea::vertical().show(ui, |ui| self.show_stats_side(ui));
                     });
-                });
+                })
+                .response
+                .rect
+                .width();
+        }
+
+        if self.detail.is_open() {
+            let panel = egui::TopBottomPanel::bottom("detail_panel").resizable(true);
+            let panel = if forced.is_some() {
+                panel.exact_height(sizes.detail)
+            } else {
+                panel.default_height(sizes.detail)
+            };
+            window.panels.detail = panel
+                .show(ctx, |ui| self.show_detail(ui))
+                .response
+                .rect
+                .height();
+        }
+    }
+
+    fn show_stats_side(&mut self, ui: &mut Ui) {
+        egui::CollapsingHeader::new(self.nodes.title())
+            .id_salt("nodes")
+            .default_open(true)
+            .show(ui, |ui| {
+                if let Some(action) = self.nodes.update(ui, &self.identities) {
+                    self.on_node_action(action);
+                }
             });
+        egui::CollapsingHeader::new(self.emcy.title())
+            .id_salt("emergencies")
+            .show(ui, |ui| self.emcy.update(ui));
+        egui::CollapsingHeader::new(self.triggers.title())
+            .id_salt("triggers")
+            .show(ui, |ui| self.triggers.update(ui));
+        ui.separator();
+        self.show_stats_panel(ui);
+    }
+
+    fn show_detail(&mut self, ui: &mut Ui) {
+        let period = self
+            .detail
+            .cob_id()
+            .and_then(|cob_id| self.bus_stats.period_stats(cob_id));
+        self.detail.update(ui, period, &self.interpreters);
+    }
+
+    fn show_connection_help(ui: &mut Ui) {
+        ui.horizontal_wrapped(|ui| {
+                    ui.colored_label(OZON_PINK, "↑ You need to enter can name, i.e.");
+                    ui.colored_label(OZON_GRAY, "can0");
+                    ui.colored_label(OZON_PINK, "and optionally bitrate. If bitrate is set then link will go down, bitrate will be changed and then link will be set up.");
+                });
+        ui.colored_label(OZON_PINK, "Or your CAN interface is not connected properly");
+        ui.label("Or you can execute program with arguments default values, for help execute:");
+        ui.colored_label(OZON_GRAY, "oze-canopen-viewer --help");
+    }
+}
+
+impl Session {
+    /// Panels of the session, in the layout of `window`.
+    fn show(&mut self, ctx: &egui::Context, window: &mut Window, fps: f64) {
+        // A saved session has no connection, everything but sending works on its messages.
+        let connected = self.offline.is_some()
+            || self.link == LinkState::Connected && self.status != ConnectionStatus::Disconnected;
+        egui::TopBottomPanel::top("top").show(ctx, |ui| self.show_top_bar(ui, window, fps, connected));
         
+        self.show_side_panels(ctx, window, connected);
+
         egui::CentralPanel::default().show(ctx, |ui| {
             ui.add_enabled_ui(connected, |ui| {
                 // Dashboard at the top
-                self.show_dashboard(ui);
-                ui.separator();
-                
+                if !window.hidden_panels.contains(HiddenPanels::DASHBOARD) {
+                    self.show_dashboard(ui);
+                    ui.separator();
+                }
+
                 // Chart in the middle
-                self.chart.ui(ui);
-                ui.separator();
+                if !window.hidden_panels.contains(HiddenPanels::CHART) {
+                    self.show_charts(ui, window.panels.chart);
+                    chart_resize_handle(ui, &mut window.panels.chart);
+                }
                 
                 // Filter panel
-                let to_pin = self.filter_panel.update(ui);
-                if self.stopped != self.filter_panel.stop {
-                    self.stopped = self.filter_panel.stop;
-                    self.send_driver_control();
+                self.show_filters(ui);
+
+                ui.collapsing("Kernel filters", |ui| {
+                    if self.kernel_filter_panel.update(ui) {
+                        self.send_driver_control();
+                    }
+                });
+
+                ui.collapsing("Gateway", |ui| {
+                    if self.gateway_panel.update(ui) {
+                        self.send_driver_control();
+                    }
+                });
+
+                ui.collapsing("DBC", |ui| {
+                    if self.dbc_panel.update(ui) {
+                        self.redecode();
+                    }
+                });
+
+                ui.collapsing("EDS", |ui| {
+                    if self.eds_panel.update(ui) {
+                        self.redecode();
+                    }
+                });
+
+                ui.collapsing("Script", |ui| {
+                    if self.script_panel.update(ui) {
+                        self.redecode();
+                    }
+                });
+
+                ui.collapsing("Export", |ui| {
+                    let capture = self.capture();
+                    self.export
+                        .update(ui, &self.data, &self.global_filter.borrow(), capture);
+                });
+
+                ui.separator();
+                self.show_messages(ui, &window.row_colors);
+            });
+        });
+
+        let reading = self.device_info.node_id().is_some_and(|node_id| {
+            self.message_sender.pending()
+                == Some(format!("{:?}", WriteCommand::ReadDeviceInfo { node_id }).as_str())
+        });
+        let can_read = connected && self.offline.is_none();
+        if let Some(node_id) = self.device_info.show(ctx, &self.device_infos, reading, can_read) {
+            self.message_sender.send(WriteCommand::ReadDeviceInfo { node_id });
+        }
+
+        let reading = self.pdo_config.node_id().is_some_and(|node_id| {
+            self.message_sender.pending()
+                == Some(format!("{:?}", WriteCommand::ReadPdoConfig { node_id }).as_str())
+        });
+        match self.pdo_config.show(ctx, reading, can_read) {
+            Some(PdoConfigAction::Read(node_id)) => {
+                self.message_sender.send(WriteCommand::ReadPdoConfig { node_id });
+            }
+            Some(PdoConfigAction::Edit(node_id, parameters)) => {
+                self.message_sender.edit_pdo(node_id, &parameters);
+            }
+            None => {}
+        }
+
+        if let Some(OdBrowserAction::UseInSender(node_id, probe, data)) = self.od_browser.show(ctx, can_read) {
+            self.message_sender.edit_sdo(node_id, probe.index, probe.subindex, &data);
+        }
+    }
+}
+
+impl Drop for Session {
+    /// Kills the driver, which closes its interfaces and stops its periodic transmissions.
+    fn drop(&mut self) {
+        self.driver_ctrl
+            .send_modify(|control| control.command = ControlCommand::Kill);
+    }
+}
+
+/// File an offline session was loaded from.
+struct SavedSource {
+    path: String,
+    /// Interface the messages were received on.
+    can_name: String,
+    /// Local time of the save, RFC 3339.
+    saved: String,
+    stats: Option<BusStatsSnapshot>,
+}
+
+/// Messages of a file still to load into its offline session, oldest first.
+struct OfflineLoad {
+    messages: std::vec::IntoIter<SavedMessage>,
+    /// Capture start the message times are relative to.
+    start: Instant,
+    total: usize,
+}
+
+impl OfflineLoad {
+    /// Share of the messages loaded, 0 to 1.
+    #[allow(clippy::cast_precision_loss)]
+    fn progress(&self) -> f32 {
+        1.0 - self.messages.len() as f32 / self.total.max(1) as f32
+    }
+}
+
+/// State shared by the sessions: theme, colours, panel layout and repaint rate.
+struct Window {
+    theme: ThemeSettings,
+    row_colors: RowColors,
+    panels: PanelSizes,
+    /// Sizes forced on the panels on the next frame.
+    forced_panels: Option<PanelSizes>,
+    hidden_panels: HiddenPanels,
+    /// Continuous repaint rate, see `AppSettings::max_fps`.
+    max_fps: Option<u32>,
+    notifications: Notifications,
+    layouts: Layouts,
+    /// Name typed to save the layout under.
+    layout_name: String,
+}
+
+impl Window {
+    /// Restores the panel layout and repaint rate.
+    fn apply_settings(&mut self, settings: &AppSettings) {
+        self.panels = settings.panels;
+        self.hidden_panels = settings.hidden_panels;
+        self.max_fps = settings.max_fps;
+    }
+
+    fn show_repaint_settings(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            let mut continuous = self.max_fps.is_some();
+            if ui
+                .checkbox(&mut continuous, "Continuous repaint")
+                .on_hover_text("Redraw at a fixed rate even on a silent bus, otherwise only when messages arrive and every 100 ms")
+                .changed()
+            {
+                self.max_fps = continuous.then_some(60);
+            }
+            if let Some(max_fps) = &mut self.max_fps {
+                ui.add(egui::DragValue::new(max_fps).range(MAX_FPS_RANGE).suffix(" FPS"));
+            }
+        });
+    }
+
+    /// Thin strip in place of a collapsed side panel, with the button to expand it.
+    fn show_panel_strip(&mut self, ctx: &egui::Context, strip: egui::SidePanel, panel: HiddenPanels) {
+        strip
+            .resizable(false)
+            .exact_width(PANEL_STRIP_WIDTH)
+            .show(ctx, |ui| self.panel_toggle(ui, panel));
+    }
+
+    /// Button collapsing or expanding a side panel, pointing the way the panel will move.
+    fn panel_toggle(&mut self, ui: &mut Ui, panel: HiddenPanels) {
+        let hidden = self.hidden_panels.contains(panel);
+        let (icon, align) = match (panel == HiddenPanels::SENDER, hidden) {
+            (true, true) | (false, false) => ("▶", egui::Align::Min),
+            (true, false) | (false, true) => ("◀", egui::Align::Max),
+        };
+        let Some((_, name, key)) = VIEW_PANELS.iter().find(|(flag, _, _)| *flag == panel) else {
+            return;
+        };
+        let shortcut = ui.ctx().format_shortcut(&view_shortcut(*key));
+        let hover = if hidden {
+            format!("Show the {name} ({shortcut})")
+        } else {
+            format!("Collapse the {name} ({shortcut})")
+        };
+        ui.with_layout(Layout::top_down(align), |ui| {
+            if ui.small_button(icon).on_hover_text(hover).clicked() {
+                self.hidden_panels.toggle(panel);
+            }
+        });
+    }
+
+    /// Panels shown or hidden, with the shortcuts to toggle them, and the layouts.
+    fn show_view_menu(&mut self, ui: &mut Ui) -> Option<LayoutAction> {
+        for (panel, name, key) in VIEW_PANELS {
+            let shown = !self.hidden_panels.contains(panel);
+            let shortcut = ui.ctx().format_shortcut(&view_shortcut(key));
+            if ui
+                .add(Button::new(name).selected(shown).shortcut_text(shortcut))
+                .clicked()
+            {
+                self.hidden_panels.toggle(panel);
+                ui.close_menu();
+            }
+        }
+        ui.separator();
+        ui.label("Layout");
+        let mut action = None;
+        let active = self.layouts.active();
+        for name in self.layouts.names() {
+            if ui.add(Button::new(name).selected(active == Some(name))).clicked() {
+                action = Some(LayoutAction::Apply(name.to_owned()));
+            }
+        }
+        ui.horizontal(|ui| {
+            if let Some(active) = active {
+                if ui
+                    .button("Save layout")
+                    .on_hover_text(format!("Save the panels, columns and chart tab as {active}"))
+                    .clicked()
+                {
+                    action = Some(LayoutAction::Save(active.to_owned()));
                 }
-                if let Some(to_pin) = to_pin {
-                    self.pinned_filters.pin_filter(to_pin, &self.data);
+                if ui
+                    .button("Reset layout")
+                    .on_hover_text(format!("Back to the layout {active} had before it was saved"))
+                    .clicked()
+                {
+                    action = Some(LayoutAction::Reset(active.to_owned()));
                 }
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.add(
+                TextEdit::singleline(&mut self.layout_name)
+                    .hint_text("layout name")
+                    .desired_width(100.0),
+            );
+            let name = self.layout_name.trim();
+            if ui
+                .add_enabled(!name.is_empty(), Button::new("Save as"))
+                .clicked()
+            {
+                action = Some(LayoutAction::Save(name.to_owned()));
+                self.layout_name.clear();
+            }
+        });
+        if action.is_some() {
+            ui.close_menu();
+        }
+        action
+    }
+
+    fn handle_view_shortcuts(&mut self, ctx: &egui::Context) {
+        for (panel, _, key) in VIEW_PANELS {
+            if ctx.input_mut(|i| i.consume_shortcut(&view_shortcut(key))) {
+                self.hidden_panels.toggle(panel);
+            }
+        }
+    }
+}
 
+/// Tabs of sessions, each connected to its own bus.
+pub struct Gui {
+    sessions: Vec<Session>,
+    active: usize,
+    window: Window,
+    /// Runtime the drivers of new sessions are started on.
+    runtime: Handle,
+    buffers: BufferSettings,
+    repaint: Arc<OnceLock<egui::Context>>,
+    /// Settings as last saved.
+    settings: AppSettings,
+    settings_checked: Instant,
+    last: Instant,
+    fps: VecDeque<f64>,
+    /// Title last set on the window.
+    title: String,
+    /// What exiting would stop, while the user is asked to confirm.
+    confirm_exit: Option<Vec<String>>,
+    /// Since when the drivers are asked to stop before the window closes.
+    exiting: Option<Instant>,
+    /// The drivers are stopped, the next close request is let through.
+    closing: bool,
+    /// Save of the active session to a file.
+    session_save: ExportJob,
+    /// File typed to open a saved session or a log.
+    session_path: String,
+    /// Files being read to open them offline.
+    imports: Vec<ImportJob>,
+}
+
+impl Gui {
+    /// Window with one session, on the driver of `channels`.
+    pub fn new(
+        cc: &eframe::CreationContext<'_>,
+        runtime: Handle,
+        channels: DriverChannels,
+        buffers: BufferSettings,
+        repaint: Arc<OnceLock<egui::Context>>,
+    ) -> Self {
+        let theme_settings = ThemeSettings::load();
+        theme(&cc.egui_ctx, &theme_settings);
+
+        let settings = AppSettings::load();
+        let mut session = Session::new(channels, buffers, &settings);
+        if session.can_name_raw.is_empty() {
+            session.can_name_raw.clone_from(&settings.can_name);
+            session.bitrate_raw.clone_from(&settings.bitrate);
+        }
+        let mut window = Window {
+            theme: theme_settings,
+            row_colors: RowColors::load(),
+            panels: PanelSizes::default(),
+            forced_panels: None,
+            hidden_panels: HiddenPanels::empty(),
+            max_fps: None,
+            notifications: Notifications::default(),
+            layouts: Layouts::load(),
+            layout_name: String::new(),
+        };
+        window.apply_settings(&settings);
+        Self {
+            sessions: vec![session],
+            active: 0,
+            window,
+            runtime,
+            buffers,
+            repaint,
+            settings,
+            settings_checked: Instant::now(),
+            last: Instant::now(),
+            fps: VecDeque::new(),
+            title: String::new(),
+            confirm_exit: None,
+            exiting: None,
+            closing: false,
+            session_save: ExportJob::new("messages"),
+            session_path: String::new(),
+            imports: Vec::new(),
+        }
+    }
+
+    /// Sends the statistics of the first session, whose driver streams its messages, to the
+    /// WebSocket clients.
+    pub fn stream_stats(&mut self, stats: StatsPublisher) {
+        if let Some(session) = self.sessions.first_mut() {
+            session.stats_stream = Some(stats);
+        }
+    }
+
+    /// Publishes the statistics and nodes of the first session, whose driver executes the
+    /// commands of the HTTP API, to the API or the metrics.
+    pub fn publish_status(&mut self, status: StatusPublisher) {
+        if let Some(session) = self.sessions.first_mut() {
+            session.status_publishers.push(status);
+        }
+    }
+
+    /// Applies the display and filter options of the command line to the first session.
+    pub fn apply_startup(&mut self, startup: &Startup) {
+        if let Some(session) = self.sessions.first_mut() {
+            session.apply_startup(startup);
+        }
+    }
+
+    /// Opens a disconnected session with the display settings of the active one.
+    fn open_session(&mut self) {
+        let control = Control {
+            command: ControlCommand::Process,
+            connection: Connection {
+                can_name: String::new(),
+                bitrate: None,
+            },
+            kernel_filters: Vec::new(),
+            tx_rate: Some(DEFAULT_TX_RATE),
+            gateway: None,
+            exit_nmt: None,
+            auto_start: None,
+        };
+        let channels = driver::spawn(
+            &self.runtime,
+            control,
+            self.buffers.driver_buffer,
+            self.repaint.clone(),
+            None,
+            None,
+        );
+        let settings = self.sessions[self.active].current_settings(&self.window);
+        self.sessions
+            .push(Session::new(channels, self.buffers, &settings));
+        self.active = self.sessions.len() - 1;
+    }
+
+    /// Writes the messages, statistics and settings of the active session on a separate thread.
+    fn save_session(&mut self) {
+        let file = self.sessions[self.active].session_file(&self.window);
+        let rows = file.messages.len();
+        self.session_save
+            .start(SessionFile::file_name(), rows, move || file.to_json());
+    }
+
+    /// Opens the offline tabs of the files read, reports those that can't be opened.
+    fn poll_imports(&mut self) {
+        let mut i = 0;
+        while i < self.imports.len() {
+            let Some(result) = self.imports[i].poll() else {
+                i += 1;
+                continue;
+            };
+            let path = self.imports.remove(i).path().to_owned();
+            match result {
+                Ok((format, mut file)) => {
+                    // Logs are shown with the settings of the active session.
+                    if format != FileFormat::Session {
+                        file.settings = self.sessions[self.active].current_settings(&self.window);
+                    }
+                    self.open_offline(file, path);
+                }
+                Err(e) => self
+                    .window
+                    .notifications
+                    .push(Notification::new(Severity::Error, format!("Can't open {path}: {e}"))),
+            }
+        }
+    }
+
+    /// Opens a saved session or a log in a new read-only tab.
+    fn open_offline(&mut self, file: SessionFile, path: String) {
+        // Never connected, its driver only idles until the tab is closed.
+        let control = Control {
+            command: ControlCommand::Disconnect,
+            connection: Connection {
+                can_name: String::new(),
+                bitrate: None,
+            },
+            kernel_filters: Vec::new(),
+            tx_rate: None,
+            gateway: None,
+            exit_nmt: None,
+            auto_start: None,
+        };
+        let channels = driver::spawn(
+            &self.runtime,
+            control,
+            self.buffers.driver_buffer,
+            self.repaint.clone(),
+            None,
+            None,
+        );
+        let buffers = BufferSettings {
+            messages: self.buffers.messages.max(file.messages.len()),
+            ..self.buffers
+        };
+        let mut session = Session::new(channels, buffers, &file.settings);
+        session.load_saved(file, path);
+        self.sessions.push(session);
+        self.active = self.sessions.len() - 1;
+    }
+
+    fn show_session_menu(&mut self, ui: &mut Ui) {
+        if ui
+            .add_enabled(self.session_save.is_idle(), Button::new("Save session…"))
+            .on_hover_text(
+                "Write the messages, statistics and settings of this session to a file in the \
+                 working directory",
+            )
+            .clicked()
+        {
+            self.save_session();
+        }
+        self.session_save.ui(ui);
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.add(
+                TextEdit::singleline(&mut self.session_path)
+                    .hint_text("session-….json")
+                    .desired_width(200.0),
+            );
+            if ui
+                .add_enabled(!self.session_path.trim().is_empty(), Button::new("Open…"))
+                .on_hover_text(
+                    "Open a saved session, a candump log, a CSV export or a pcapng capture in a new read-only tab, \
+                     files can also be dropped on the window",
+                )
+                .clicked()
+            {
+                self.imports
+                    .push(ImportJob::start(self.session_path.trim().to_owned()));
+                ui.close_menu();
+            }
+        });
+    }
+
+    /// Closes a session, its driver is killed as it is dropped.
+    fn close_session(&mut self, index: usize) {
+        self.sessions.remove(index);
+        if self.active > index || self.active == self.sessions.len() {
+            self.active = self.active.saturating_sub(1);
+        }
+    }
+
+    fn show_tabs(&mut self, ui: &mut Ui) {
+        let mut close = None;
+        ui.horizontal(|ui| {
+            let closable = self.sessions.len() > 1;
+            for (index, session) in self.sessions.iter().enumerate() {
+                if ui
+                    .selectable_label(self.active == index, session.title())
+                    .clicked()
+                {
+                    self.active = index;
+                }
+                if closable
+                    && ui
+                        .small_button("✖")
+                        .on_hover_text("Close the session and disconnect from its interface")
+                        .clicked()
+                {
+                    close = Some(index);
+                }
                 ui.separator();
-                self.pinned_filters.update(ui);
+            }
+            if ui
+                .button("➕")
+                .on_hover_text("New session, to connect to another interface")
+                .clicked()
+            {
+                self.open_session();
+            }
+            ui.menu_button("💾", |ui| self.show_session_menu(ui))
+                .response
+                .on_hover_text("Save this session, open a saved one or a log");
+            for import in &self.imports {
+                ui.spinner();
+                ui.add(
+                    egui::ProgressBar::new(import.progress())
+                        .desired_width(120.0)
+                        .show_percentage(),
+                )
+                .on_hover_text(format!("Reading {}", import.path()));
+            }
+            ui.with_layout(Layout::right_to_left(egui::Align::Center), |ui| {
+                self.window.notifications.bell_ui(ui);
+            });
+        });
+        if let Some(index) = close {
+            self.close_session(index);
+        }
+    }
+
+    fn calc_fps(&mut self) -> f64 {
+        let fps = 1.0 / self.last.elapsed().as_secs_f64();
+        self.last = Instant::now();
+
+        self.fps.push_back(fps);
+
+        let fps = self.fps.iter().sum::<f64>() / self.fps.len().to_f64();
+        while self.fps.len() > usize::from_f64(fps.round()) * 5 {
+            self.fps.pop_front();
+        }
+
+        fps.round()
+    }
+
+    /// Kills the drivers of every session, they flush their recordings and send the exit NMT.
+    fn stop_sessions(&mut self) {
+        for session in &self.sessions {
+            session
+                .driver_ctrl
+                .send_modify(|control| control.command = ControlCommand::Kill);
+        }
+        self.exiting = Some(Instant::now());
+    }
+
+    /// Holds the close requests until the drivers are stopped, after a confirmation if some of
+    /// them are busy.
+    fn on_close_request(&mut self, ctx: &egui::Context) {
+        if self.closing || !ctx.input(|i| i.viewport().close_requested()) {
+            return;
+        }
+        ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
+        if self.exiting.is_some() || self.confirm_exit.is_some() {
+            return;
+        }
+        let running: Vec<String> = self
+            .sessions
+            .iter()
+            .flat_map(|session| {
+                session
+                    .running()
+                    .into_iter()
+                    .map(|what| format!("{}: {what}", session.title()))
+            })
+            .collect();
+        if running.is_empty() {
+            self.stop_sessions();
+        } else {
+            self.confirm_exit = Some(running);
+        }
+    }
+
+    fn show_confirm_exit(&mut self, ctx: &egui::Context) {
+        let Some(running) = &self.confirm_exit else {
+            return;
+        };
+        let mut confirmed = None;
+        egui::Window::new("Exit")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label("Exiting stops:");
+                for what in running {
+                    ui.label(format!("• {what}"));
+                }
                 ui.separator();
-                self.viewer.update(ui, &self.data);
+                ui.horizontal(|ui| {
+                    if ui.button("Stop and exit").clicked() {
+                        confirmed = Some(true);
+                    }
+                    if ui.button("Cancel").clicked() {
+                        confirmed = Some(false);
+                    }
+                });
             });
+        match confirmed {
+            Some(true) => {
+                self.confirm_exit = None;
+                self.stop_sessions();
+            }
+            Some(false) => self.confirm_exit = None,
+            None => {}
+        }
+    }
+
+    /// Saves the settings of the active session if they changed since the last save.
+    fn save_settings(&mut self) {
+        let current = self.sessions[self.active].current_settings(&self.window);
+        if current != self.settings {
+            current.save();
+            self.settings = current;
+        }
+    }
+}
+
+impl eframe::App for Gui {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        let fps = self.calc_fps();
+        // Every session keeps receiving and counting, only the active one is drawn.
+        let mut exit = false;
+        for session in &mut self.sessions {
+            exit |= session.poll(&mut self.window);
+        }
+        // A driver stopped by Ctrl+C, the others are waited for.
+        if exit && self.exiting.is_none() {
+            self.stop_sessions();
+        }
+        if let Some(since) = self.exiting {
+            if self.sessions.iter().all(Session::stopped) || since.elapsed() >= EXIT_TIMEOUT {
+                println!("Gracefull shutdown");
+                self.closing = true;
+                ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                ctx.request_repaint();
+                return;
+            }
+            ctx.request_repaint_after(Duration::from_millis(50));
+        }
+        self.on_close_request(ctx);
+
+        let dropped: Vec<String> = ctx.input(|i| {
+            i.raw
+                .dropped_files
+                .iter()
+                .filter_map(|file| file.path.as_ref())
+                .map(|path| path.display().to_string())
+                .collect()
+        });
+        self.imports.extend(dropped.into_iter().map(ImportJob::start));
+        self.poll_imports();
+        if !self.imports.is_empty() {
+            ctx.request_repaint_after(Duration::from_millis(100));
+        }
+
+        let title = self.sessions[self.active].window_title();
+        if title != self.title {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Title(title.clone()));
+            self.title = title;
+        }
+
+        self.window.handle_view_shortcuts(ctx);
+        egui::TopBottomPanel::top("tabs").show(ctx, |ui| self.show_tabs(ui));
+        self.sessions[self.active].show(ctx, &mut self.window, fps);
+        self.window.notifications.toasts_ui(ctx);
+        self.show_confirm_exit(ctx);
+
+        let now = Instant::now();
+        if now.saturating_duration_since(self.settings_checked) >= SETTINGS_SAVE_PERIOD {
+            self.settings_checked = now;
+            self.save_settings();
+        }
+
+        // New messages request a repaint from the driver.
+        ctx.request_repaint_after(match self.window.max_fps {
+            Some(max_fps) => Duration::from_secs_f64(1.0 / f64::from(max_fps.max(1))),
+            None => IDLE_REPAINT_PERIOD,
         });
+    }
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        self.save_settings();
+    }
+}
+
+/// Line below the chart, dragged to change its height.
+fn chart_resize_handle(ui: &mut Ui, height: &mut f32) {
+    let response = ui
+        .separator()
+        .interact(egui::Sense::drag())
+        .on_hover_cursor(egui::CursorIcon::ResizeVertical)
+        .on_hover_text("Drag to resize the chart");
+    if response.dragged() {
+        *height = (*height + response.drag_delta().y)
+            .clamp(*CHART_HEIGHT_RANGE.start(), *CHART_HEIGHT_RANGE.end());
+    }
+}
+
+/// Peak over the window and over the whole run, e.g. `Peak (60 s): 42.0% / all-time: 97.0%`.
+fn format_peak(
+    window: Duration,
+    windowed: Option<f64>,
+    all_time: f64,
+    format: impl Fn(f64) -> String,
+) -> String {
+    let windowed = windowed.map_or_else(|| "--".to_owned(), &format);
+    format!(
+        "Peak ({} s): {windowed} / all-time: {}",
+        window.as_secs(),
+        format(all_time)
+    )
+}
 
-        ctx.request_repaint();
+/// Formats a byte count using binary units.
+#[allow(clippy::cast_precision_loss)]
+fn format_bytes(bytes: usize) -> String {
+    const KIB: f64 = 1024.0;
+    let bytes = bytes as f64;
+    if bytes < KIB {
+        format!("{bytes} B")
+    } else if bytes < KIB * KIB {
+        format!("{:.1} KiB", bytes / KIB)
+    } else {
+        format!("{:.1} MiB", bytes / KIB / KIB)
     }
 }
@@ -0,0 +1,455 @@
+use crate::{
+    bus_stats::BusStatsSnapshot,
+    driver::{WriteCommand, WriteError, WriteOutcome, WriteRequest},
+    message_sender::{self, parse_hex_data, NmtCommand},
+    nodes::NodeSnapshot,
+};
+use axum::{
+    extract::{Request, State},
+    http::{header::AUTHORIZATION, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use oze_canopen::{canopen::NodeId, proto::nmt::NmtCommandSpecifier};
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize};
+use serde_json::{json, Value};
+use std::{collections::BTreeMap, fmt::Write as _, io, net::SocketAddr, sync::Arc, time::Duration};
+use tokio::{
+    net::TcpListener,
+    runtime::Handle,
+    sync::{mpsc, watch},
+    time::{timeout, Instant},
+};
+
+/// Address listened on when `--api-listen` is given without one.
+pub const DEFAULT_API_ADDR: &str = "127.0.0.1:8080";
+
+/// Interval between two snapshots of the statistics and nodes published for the API.
+const STATUS_PERIOD: Duration = Duration::from_millis(500);
+
+/// Time the driver gets to execute a command, e.g. an SDO sequence waiting behind others.
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Statistics and nodes of the session, answered to `GET /stats` and `GET /nodes` and
+/// exported as metrics.
+#[derive(Debug, Serialize)]
+pub struct ApiStatus {
+    pub can_name: String,
+    pub stats: BusStatsSnapshot,
+    pub nodes: Vec<NodeSnapshot>,
+    /// Emergencies received per node.
+    pub emergencies: BTreeMap<NodeId, u64>,
+}
+
+/// Status of the session, published by the GUI while the API is served.
+#[derive(Debug, Clone)]
+pub struct StatusPublisher {
+    sender: watch::Sender<Option<Arc<ApiStatus>>>,
+    next: Instant,
+}
+
+impl StatusPublisher {
+    pub(crate) fn new(sender: watch::Sender<Option<Arc<ApiStatus>>>) -> Self {
+        Self {
+            sender,
+            next: Instant::now(),
+        }
+    }
+
+    /// Takes a snapshot with `status` if due.
+    pub fn publish_if_due(&mut self, now: Instant, status: impl FnOnce() -> ApiStatus) {
+        if now < self.next {
+            return;
+        }
+        self.next = now + STATUS_PERIOD;
+        self.sender.send_replace(Some(Arc::new(status())));
+    }
+}
+
+/// Number given as a JSON number or a hex string, e.g. `24640`, `"6040"` or `"0x6040"`.
+fn hex<'de, D: Deserializer<'de>, T: TryFrom<u64>>(deserializer: D) -> Result<T, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Number {
+        Number(u64),
+        Text(String),
+    }
+    let value = match Number::deserialize(deserializer)? {
+        Number::Number(value) => value,
+        Number::Text(text) => {
+            let digits = text.trim_start_matches("0x").trim_start_matches("0X");
+            u64::from_str_radix(digits, 16)
+                .map_err(|_| D::Error::custom(format!("invalid hex number '{text}'")))?
+        }
+    };
+    T::try_from(value).map_err(|_| D::Error::custom(format!("0x{value:X} out of range")))
+}
+
+#[derive(Debug, Deserialize)]
+struct NmtBody {
+    node_id: u8,
+    #[serde(with = "NmtCommand")]
+    command: NmtCommandSpecifier,
+}
+
+#[derive(Debug, Deserialize)]
+struct SdoBody {
+    node_id: u8,
+    #[serde(deserialize_with = "hex")]
+    index: u16,
+    #[serde(deserialize_with = "hex", default)]
+    subindex: u8,
+    /// Hex bytes, as in the sender panel.
+    #[serde(default)]
+    data: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawBody {
+    #[serde(deserialize_with = "hex")]
+    cob_id: u32,
+    #[serde(default)]
+    data: String,
+}
+
+/// Error answered to a request, as `{"ok": false, "error": ...}` and more fields.
+#[derive(Debug)]
+struct ApiError {
+    status: StatusCode,
+    body: Value,
+}
+
+impl ApiError {
+    fn new(status: StatusCode, error: impl Into<String>) -> Self {
+        Self {
+            status,
+            body: json!({"ok": false, "error": error.into()}),
+        }
+    }
+
+    /// Failure reported by the driver, with the abort code of an aborted SDO transfer.
+    fn driver(e: &WriteError) -> Self {
+        let status = match e {
+            WriteError::Timeout | WriteError::NmtState(_) => StatusCode::GATEWAY_TIMEOUT,
+            WriteError::Transmit(_) => StatusCode::SERVICE_UNAVAILABLE,
+            WriteError::Unsupported(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            WriteError::SdoAbort { .. } | WriteError::Step(..) => StatusCode::BAD_GATEWAY,
+        };
+        let mut error = Self::new(status, e.to_string());
+        if let WriteError::SdoAbort {
+            index,
+            subindex,
+            code,
+        } = e
+        {
+            error.body["abort_code"] = json!(format!("0x{code:08X}"));
+            error.body["index"] = json!(index);
+            error.body["subindex"] = json!(subindex);
+        }
+        error
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.status, Json(self.body)).into_response()
+    }
+}
+
+type ApiResult = Result<Json<Value>, ApiError>;
+
+/// Shared by the handlers.
+#[derive(Debug, Clone)]
+struct ApiState {
+    token: Option<Arc<str>>,
+    write: mpsc::Sender<WriteRequest>,
+    status: watch::Receiver<Option<Arc<ApiStatus>>>,
+}
+
+impl ApiState {
+    /// Validates the command like the sender panel and waits for the result of the driver.
+    async fn execute(&self, command: WriteCommand) -> ApiResult {
+        message_sender::validate(&command)
+            .map_err(|e| ApiError::new(StatusCode::BAD_REQUEST, e))?;
+        let name = format!("{command:?}");
+        let (request, result) = WriteRequest::new(command);
+        self.write
+            .try_send(request)
+            .map_err(|e| ApiError::new(StatusCode::SERVICE_UNAVAILABLE, format!("driver: {e}")))?;
+        let outcome = match timeout(COMMAND_TIMEOUT, result).await {
+            Ok(Ok(Ok(outcome))) => outcome,
+            Ok(Ok(Err(e))) => return Err(ApiError::driver(&e)),
+            Ok(Err(_)) => {
+                return Err(ApiError::new(
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    "driver stopped",
+                ))
+            }
+            Err(_) => {
+                return Err(ApiError::new(
+                    StatusCode::GATEWAY_TIMEOUT,
+                    format!("no result within {} s", COMMAND_TIMEOUT.as_secs()),
+                ))
+            }
+        };
+        log::info!("API {name}: {outcome}");
+        let mut body = json!({"ok": true, "result": outcome.to_string()});
+        if let WriteOutcome::SdoData(data) = &outcome {
+            let hex = data.iter().fold(String::new(), |mut hex, byte| {
+                let _ = write!(hex, "{byte:02X}");
+                hex
+            });
+            body["data"] = json!(hex);
+        }
+        Ok(Json(body))
+    }
+
+    fn status(&self) -> Result<Arc<ApiStatus>, ApiError> {
+        self.status
+            .borrow()
+            .clone()
+            .ok_or_else(|| ApiError::new(StatusCode::SERVICE_UNAVAILABLE, "no session yet"))
+    }
+}
+
+fn data(text: &str) -> Result<Vec<u8>, ApiError> {
+    parse_hex_data(text).map_err(|_| ApiError::new(StatusCode::BAD_REQUEST, "Invalid data format"))
+}
+
+async fn nmt(State(state): State<ApiState>, Json(body): Json<NmtBody>) -> ApiResult {
+    state
+        .execute(WriteCommand::SendNmt {
+            node_id: body.node_id,
+            command: body.command,
+        })
+        .await
+}
+
+async fn sdo_download(State(state): State<ApiState>, Json(body): Json<SdoBody>) -> ApiResult {
+    state
+        .execute(WriteCommand::SendSdoDownload {
+            node_id: body.node_id,
+            index: body.index,
+            subindex: body.subindex,
+            data: data(&body.data)?,
+        })
+        .await
+}
+
+async fn sdo_upload(State(state): State<ApiState>, Json(body): Json<SdoBody>) -> ApiResult {
+    state
+        .execute(WriteCommand::SendSdoUpload {
+            node_id: body.node_id,
+            index: body.index,
+            subindex: body.subindex,
+        })
+        .await
+}
+
+async fn raw(State(state): State<ApiState>, Json(body): Json<RawBody>) -> ApiResult {
+    state
+        .execute(WriteCommand::SendRaw {
+            cob_id: body.cob_id,
+            data: data(&body.data)?,
+        })
+        .await
+}
+
+async fn sync(State(state): State<ApiState>) -> ApiResult {
+    state.execute(WriteCommand::SendSync).await
+}
+
+async fn stats(State(state): State<ApiState>) -> ApiResult {
+    Ok(Json(json!(state.status()?.stats)))
+}
+
+async fn nodes(State(state): State<ApiState>) -> ApiResult {
+    Ok(Json(json!(state.status()?.nodes)))
+}
+
+/// Refuses the requests without `Authorization: Bearer <token>` when a token is set.
+async fn authorize(State(state): State<ApiState>, request: Request, next: Next) -> Response {
+    if let Some(token) = &state.token {
+        let given = request
+            .headers()
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+        if given != Some(token.as_ref()) {
+            return ApiError::new(StatusCode::UNAUTHORIZED, "missing or wrong token")
+                .into_response();
+        }
+    }
+    next.run(request).await
+}
+
+/// HTTP server sending the commands of its clients to a driver.
+///
+/// `POST /nmt`, `/sdo/download`, `/sdo/upload`, `/raw` and `/sync` take JSON bodies with the
+/// fields of the sender panel and answer the result of the driver, `GET /stats` and `/nodes`
+/// answer the statistics and nodes of the session.
+#[derive(Debug)]
+pub struct ApiServer {
+    status: watch::Sender<Option<Arc<ApiStatus>>>,
+    local_addr: SocketAddr,
+}
+
+impl ApiServer {
+    /// Listens on `addr` and serves the clients on `runtime`, requiring `token` if any.
+    ///
+    /// # Errors
+    /// If `addr` can't be listened on.
+    pub fn start(
+        runtime: &Handle,
+        addr: SocketAddr,
+        token: Option<String>,
+        write: mpsc::Sender<WriteRequest>,
+    ) -> io::Result<Self> {
+        let listener = std::net::TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        let local_addr = listener.local_addr()?;
+        let _enter = runtime.enter();
+        let listener = TcpListener::from_std(listener)?;
+        let (status, status_receiver) = watch::channel(None);
+        let state = ApiState {
+            token: token.map(Into::into),
+            write,
+            status: status_receiver,
+        };
+        let app = Router::new()
+            .route("/nmt", post(nmt))
+            .route("/sdo/download", post(sdo_download))
+            .route("/sdo/upload", post(sdo_upload))
+            .route("/raw", post(raw))
+            .route("/sync", post(sync))
+            .route("/stats", get(stats))
+            .route("/nodes", get(nodes))
+            .layer(middleware::from_fn_with_state(state.clone(), authorize))
+            .with_state(state);
+        tokio::spawn(async move {
+            if let Err(e) = axum::serve(listener, app).await {
+                log::error!("HTTP API stopped: {e}");
+            }
+        });
+        log::info!("Serving the HTTP API on http://{local_addr}");
+        Ok(Self { status, local_addr })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Publisher of the statistics and nodes, given to the session of the driver.
+    pub fn status(&self) -> StatusPublisher {
+        StatusPublisher::new(self.status.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ApiServer, ApiStatus};
+    use crate::{
+        bus_stats::BusStats,
+        driver::{WriteCommand, WriteError, WriteOutcome},
+    };
+    use serde_json::Value;
+    use std::{collections::BTreeMap, net::SocketAddr};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+        runtime::Handle,
+        sync::mpsc,
+        time::Instant,
+    };
+
+    async fn request(
+        addr: SocketAddr,
+        token: &str,
+        path: &str,
+        body: Option<&str>,
+    ) -> (u16, Value) {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let method = if body.is_some() { "POST" } else { "GET" };
+        let body = body.unwrap_or_default();
+        let request = format!(
+            "{method} {path} HTTP/1.1\r\nHost: test\r\nAuthorization: Bearer {token}\r\n\
+             Content-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        );
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        let status = head[9..12].parse().unwrap();
+        (status, serde_json::from_str(body).unwrap_or(Value::Null))
+    }
+
+    #[tokio::test]
+    async fn test_http_api() {
+        let (write, mut requests) = mpsc::channel(4);
+        let addr = "127.0.0.1:0".parse().unwrap();
+        let server =
+            ApiServer::start(&Handle::current(), addr, Some("secret".to_owned()), write).unwrap();
+        let addr = server.local_addr();
+        // Driver aborting the upload of a missing object.
+        tokio::spawn(async move {
+            while let Some(request) = requests.recv().await {
+                let result = match request.command {
+                    WriteCommand::SendSdoUpload {
+                        index, subindex, ..
+                    } if index == 0x2000 => Err(WriteError::SdoAbort {
+                        index,
+                        subindex,
+                        code: 0x0602_0000,
+                    }),
+                    WriteCommand::SendSdoUpload { .. } => {
+                        Ok(WriteOutcome::SdoData(vec![0x37, 0x02]))
+                    }
+                    _ => Ok(WriteOutcome::Sent),
+                };
+                request.reply(result);
+            }
+        });
+
+        assert_eq!(request(addr, "wrong", "/sync", Some("{}")).await.0, 401);
+        assert_eq!(request(addr, "secret", "/sync", Some("{}")).await.0, 200);
+        let (status, body) = request(
+            addr,
+            "secret",
+            "/nmt",
+            Some(r#"{"node_id": 130, "command": "start"}"#),
+        )
+        .await;
+        assert_eq!(status, 400);
+        assert_eq!(body["error"], "Invalid node ID: must be 0-127");
+        let upload = r#"{"node_id": 3, "index": "0x6041"}"#;
+        let (status, body) = request(addr, "secret", "/sdo/upload", Some(upload)).await;
+        assert_eq!((status, &body["data"]), (200, &Value::from("3702")));
+        let upload = r#"{"node_id": 3, "index": "2000", "subindex": 1}"#;
+        let (status, body) = request(addr, "secret", "/sdo/upload", Some(upload)).await;
+        assert_eq!(status, 502);
+        assert_eq!(body["abort_code"], "0x06020000");
+        let download = r#"{"node_id": 3, "index": 24640, "data": "06 00 00 00 00"}"#;
+        assert_eq!(
+            request(addr, "secret", "/sdo/download", Some(download))
+                .await
+                .0,
+            400
+        );
+
+        assert_eq!(request(addr, "secret", "/stats", None).await.0, 503);
+        let mut status = server.status();
+        let now = Instant::now();
+        status.publish_if_due(now, || ApiStatus {
+            can_name: "vcan0".to_owned(),
+            stats: BusStats::new().snapshot(now),
+            nodes: Vec::new(),
+            emergencies: BTreeMap::new(),
+        });
+        let (status, body) = request(addr, "secret", "/nodes", None).await;
+        assert_eq!((status, body), (200, Value::Array(Vec::new())));
+        assert_eq!(request(addr, "secret", "/stats", None).await.0, 200);
+    }
+}
@@ -0,0 +1,114 @@
+use socketcan::CanInterface;
+use std::{fmt, path::Path};
+
+/// Network interfaces, one directory each.
+const SYS_CLASS_NET: &str = "/sys/class/net";
+
+/// `ARPHRD_CAN`, the link type of the CAN interfaces.
+const ARPHRD_CAN: u32 = 280;
+
+/// `IFF_UP` of the interface flags.
+const IFF_UP: u32 = 0x1;
+
+/// CAN network interface found on the system.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CanInterfaceInfo {
+    pub name: String,
+    pub up: bool,
+    /// Bitrate configured on the interface, `None` for virtual ones.
+    pub bitrate: Option<u32>,
+}
+
+impl fmt::Display for CanInterfaceInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.name, if self.up { "up" } else { "down" })?;
+        if let Some(bitrate) = self.bitrate {
+            write!(f, " {} kbit/s", bitrate / 1000)?;
+        }
+        Ok(())
+    }
+}
+
+/// CAN interfaces of the system sorted by name, with the bitrates read over netlink.
+pub fn list() -> Vec<CanInterfaceInfo> {
+    let mut interfaces = list_in(Path::new(SYS_CLASS_NET));
+    for interface in &mut interfaces {
+        interface.bitrate = CanInterface::open(&interface.name)
+            .ok()
+            .and_then(|can| can.bit_rate().ok().flatten());
+    }
+    interfaces
+}
+
+/// CAN interfaces of a `/sys/class/net` directory sorted by name, without bitrates.
+fn list_in(root: &Path) -> Vec<CanInterfaceInfo> {
+    let Ok(entries) = std::fs::read_dir(root) else {
+        return Vec::new();
+    };
+    let read = |name: &str, file: &str| std::fs::read_to_string(root.join(name).join(file)).ok();
+    let mut interfaces: Vec<CanInterfaceInfo> = entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| {
+            read(name, "type").and_then(|kind| kind.trim().parse().ok()) == Some(ARPHRD_CAN)
+        })
+        .map(|name| {
+            let flags = read(&name, "flags").and_then(|flags| {
+                u32::from_str_radix(flags.trim().trim_start_matches("0x"), 16).ok()
+            });
+            CanInterfaceInfo {
+                up: flags.is_some_and(|flags| flags & IFF_UP != 0),
+                name,
+                bitrate: None,
+            }
+        })
+        .collect();
+    interfaces.sort_by(|a, b| a.name.cmp(&b.name));
+    interfaces
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{list_in, CanInterfaceInfo};
+    use std::fs;
+
+    #[test]
+    fn test_list_can_interfaces() {
+        let root = std::env::temp_dir().join(format!("sys-class-net-{}", std::process::id()));
+        for (name, kind, flags) in [
+            ("slcan0", "280", "0x0"),
+            ("eth0", "1", "0x1003"),
+            ("can1", "280", "0xc1"),
+        ] {
+            fs::create_dir_all(root.join(name)).unwrap();
+            fs::write(root.join(name).join("type"), format!("{kind}\n")).unwrap();
+            fs::write(root.join(name).join("flags"), format!("{flags}\n")).unwrap();
+        }
+        let interfaces = list_in(&root);
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(
+            interfaces,
+            [
+                CanInterfaceInfo {
+                    name: "can1".to_owned(),
+                    up: true,
+                    bitrate: None,
+                },
+                CanInterfaceInfo {
+                    name: "slcan0".to_owned(),
+                    up: false,
+                    bitrate: None,
+                },
+            ]
+        );
+        assert_eq!(
+            CanInterfaceInfo {
+                bitrate: Some(500_000),
+                ..interfaces[0].clone()
+            }
+            .to_string(),
+            "can1 up 500 kbit/s"
+        );
+    }
+}
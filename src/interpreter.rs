@@ -0,0 +1,609 @@
+use crate::{
+    cia402::Cia402,
+    emcy_codes,
+    message_cached::{MessageCached, RxMessageAdditional},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet},
+    sync::{Arc, OnceLock, PoisonError, RwLock},
+};
+
+/// Interpreters registered by [`register`], consulted before the built-in ones.
+static REGISTERED: RwLock<Vec<Arc<dyn MessageInterpreter>>> = RwLock::new(Vec::new());
+
+/// Decoded form of a message, the summary for the decode column and the fields for the detail
+/// pane.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DecodedView {
+    pub summary: String,
+    /// Name and value, in the order of [`MessageInterpreter::columns`] first.
+    pub fields: Vec<(String, String)>,
+}
+
+/// Decoder of a protocol, consulted in order by the decode column, the detail pane and the
+/// statistics, the first one matching a message decodes it.
+///
+/// The decoding of `CANopen` is built from these. A fork adds a protocol, e.g. J1939 or the
+/// bootloader of a vendor, by implementing this trait and calling [`register`] in `main`
+/// before the driver starts, the viewer needs no change:
+///
+/// ```ignore
+/// struct J1939;
+///
+/// impl MessageInterpreter for J1939 {
+///     fn name(&self) -> &'static str { "J1939" }
+///     fn matches(&self, msg: &MessageCached) -> bool { msg.msg.msg.cob_id >= 0x700 }
+///     fn decode(&self, msg: &MessageCached) -> DecodedView { ... }
+/// }
+///
+/// interpreter::register(Arc::new(J1939));
+/// ```
+///
+/// Interpreters run on the driver thread for every message, `matches` must be cheap.
+pub trait MessageInterpreter: Send + Sync {
+    /// Unique name, shown in the settings and saved with the disabled ones.
+    fn name(&self) -> &'static str;
+
+    fn matches(&self, msg: &MessageCached) -> bool;
+
+    fn decode(&self, msg: &MessageCached) -> DecodedView;
+
+    /// Text of the decode column, the summary of `decode` unless there is a faster way.
+    fn summary(&self, msg: &MessageCached) -> String {
+        self.decode(msg).summary
+    }
+
+    /// Names of the fields every view starts with.
+    fn columns(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    /// Category the message counts in for the statistics of the interpreter, e.g. the NMT
+    /// command, `None` to only count it.
+    fn stats_contribution(&self, _msg: &MessageCached) -> Option<String> {
+        None
+    }
+}
+
+/// Adds an interpreter consulted after the ones registered before it and before the built-in
+/// ones, messages received before aren't decoded again.
+pub fn register(interpreter: Arc<dyn MessageInterpreter>) {
+    REGISTERED
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .push(interpreter);
+}
+
+fn builtin() -> &'static [Arc<dyn MessageInterpreter>] {
+    static BUILTIN: OnceLock<Vec<Arc<dyn MessageInterpreter>>> = OnceLock::new();
+    BUILTIN.get_or_init(|| {
+        vec![
+            Arc::new(Nmt),
+            Arc::new(Cia402),
+            Arc::new(Sdo),
+            Arc::new(Emcy),
+            Arc::new(Heartbeat),
+            Arc::new(Signals),
+        ]
+    })
+}
+
+/// Every interpreter, in the order they are consulted.
+pub fn all() -> Vec<Arc<dyn MessageInterpreter>> {
+    let registered = REGISTERED.read().unwrap_or_else(PoisonError::into_inner);
+    registered.iter().chain(builtin()).cloned().collect()
+}
+
+/// Interpreter named `name`.
+pub fn find(name: &str) -> Option<Arc<dyn MessageInterpreter>> {
+    all()
+        .into_iter()
+        .find(|interpreter| interpreter.name() == name)
+}
+
+/// First interpreter matching the message.
+pub fn first_match(msg: &MessageCached) -> Option<Arc<dyn MessageInterpreter>> {
+    first_match_where(msg, |_| true)
+}
+
+/// First interpreter matching the message among the ones `enabled` accepts the name of.
+fn first_match_where(
+    msg: &MessageCached,
+    enabled: impl Fn(&str) -> bool,
+) -> Option<Arc<dyn MessageInterpreter>> {
+    let registered = REGISTERED.read().unwrap_or_else(PoisonError::into_inner);
+    registered
+        .iter()
+        .chain(builtin())
+        .find(|interpreter| enabled(interpreter.name()) && interpreter.matches(msg))
+        .cloned()
+}
+
+/// Name of the interpreter whose summary is the text of `msg.info()` if any, as decoded when
+/// the message was received.
+pub fn view(msg: &MessageCached) -> Option<(&'static str, DecodedView)> {
+    let interpreter = match msg.interpreter {
+        Some(name) => find(name)?,
+        None => first_match(msg)?,
+    };
+    Some((interpreter.name(), interpreter.decode(msg)))
+}
+
+/// Interpreters turned off in a session.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InterpreterSettings {
+    pub disabled: BTreeSet<String>,
+}
+
+impl InterpreterSettings {
+    pub fn is_enabled(&self, name: &str) -> bool {
+        !self.disabled.contains(name)
+    }
+
+    /// First enabled interpreter matching the message, the one that decoded it when it was
+    /// received unless it is turned off.
+    pub fn first_match(&self, msg: &MessageCached) -> Option<Arc<dyn MessageInterpreter>> {
+        match msg.interpreter {
+            Some(name) if self.is_enabled(name) => find(name),
+            _ => first_match_where(msg, |name| self.is_enabled(name)),
+        }
+    }
+
+    /// Text of the decode column, decoded by the next matching interpreter when the one of
+    /// the message is turned off.
+    pub fn info<'a>(&self, msg: &'a MessageCached) -> Cow<'a, str> {
+        match msg.interpreter {
+            Some(name) if !self.is_enabled(name) => {
+                let summary = self
+                    .first_match(msg)
+                    .map(|interpreter| interpreter.summary(msg))
+                    .unwrap_or_default();
+                match msg.script_info() {
+                    Some(text) if summary.is_empty() => Cow::Borrowed(text),
+                    Some(text) => Cow::Owned(format!("{summary}, {text}")),
+                    None => Cow::Owned(summary),
+                }
+            }
+            _ => Cow::Borrowed(msg.info()),
+        }
+    }
+
+    /// Name and decoded form of the message by [`Self::first_match`].
+    pub fn view(&self, msg: &MessageCached) -> Option<(&'static str, DecodedView)> {
+        let interpreter = self.first_match(msg)?;
+        Some((interpreter.name(), interpreter.decode(msg)))
+    }
+
+    /// List of the interpreters with the messages they decoded.
+    pub fn ui(&mut self, ui: &mut egui::Ui, stats: &InterpreterStats) {
+        ui.label("Interpreters");
+        for interpreter in all() {
+            let name = interpreter.name();
+            let mut enabled = self.is_enabled(name);
+            ui.horizontal(|ui| {
+                let columns = interpreter.columns();
+                let response = ui.checkbox(&mut enabled, name);
+                let response = if columns.is_empty() {
+                    response
+                } else {
+                    response.on_hover_text(format!("Fields: {}", columns.join(", ")))
+                };
+                if response.changed() {
+                    if enabled {
+                        self.disabled.remove(name);
+                    } else {
+                        self.disabled.insert(name.to_owned());
+                    }
+                }
+                if let Some(counts) = stats.counts.get(name) {
+                    ui.weak(format!("{} messages", counts.total))
+                        .on_hover_ui(|ui| {
+                            for (category, count) in &counts.categories {
+                                ui.label(format!("{category}: {count}"));
+                            }
+                        });
+                }
+            });
+        }
+    }
+}
+
+/// Messages decoded by an interpreter.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InterpreterCounts {
+    pub total: u64,
+    /// Per category of [`MessageInterpreter::stats_contribution`].
+    pub categories: BTreeMap<String, u64>,
+}
+
+/// Messages decoded by every enabled interpreter in a session.
+#[derive(Debug, Clone, Default)]
+pub struct InterpreterStats {
+    pub counts: BTreeMap<&'static str, InterpreterCounts>,
+}
+
+impl InterpreterStats {
+    pub fn on_message(&mut self, msg: &MessageCached, settings: &InterpreterSettings) {
+        let Some(interpreter) = settings.first_match(msg) else {
+            return;
+        };
+        let counts = self.counts.entry(interpreter.name()).or_default();
+        counts.total += 1;
+        if let Some(category) = interpreter.stats_contribution(msg) {
+            *counts.categories.entry(category).or_default() += 1;
+        }
+    }
+}
+
+/// Fields of `pairs`, for the views of the built-in interpreters.
+fn fields<const N: usize>(pairs: [(&str, String); N]) -> Vec<(String, String)> {
+    pairs
+        .into_iter()
+        .map(|(name, value)| (name.to_owned(), value))
+        .collect()
+}
+
+/// NMT commands of the master.
+struct Nmt;
+
+impl MessageInterpreter for Nmt {
+    fn name(&self) -> &'static str {
+        "NMT"
+    }
+
+    fn matches(&self, msg: &MessageCached) -> bool {
+        matches!(msg.additional, RxMessageAdditional::Nmt(_))
+    }
+
+    fn decode(&self, msg: &MessageCached) -> DecodedView {
+        let RxMessageAdditional::Nmt(nmt) = &msg.additional else {
+            return DecodedView::default();
+        };
+        DecodedView {
+            summary: msg.additional.to_string(),
+            fields: fields([
+                ("command", format!("{:?}", nmt.command_specifier)),
+                (
+                    "node",
+                    match nmt.node_id {
+                        0 => "all".to_owned(),
+                        node_id => node_id.to_string(),
+                    },
+                ),
+            ]),
+        }
+    }
+
+    fn summary(&self, msg: &MessageCached) -> String {
+        msg.additional.to_string()
+    }
+
+    fn columns(&self) -> &'static [&'static str] {
+        &["command", "node"]
+    }
+
+    fn stats_contribution(&self, msg: &MessageCached) -> Option<String> {
+        match &msg.additional {
+            RxMessageAdditional::Nmt(nmt) => Some(format!("{:?}", nmt.command_specifier)),
+            _ => None,
+        }
+    }
+}
+
+/// SDO requests, responses and aborts, named after the EDS of the node if there is one.
+struct Sdo;
+
+impl MessageInterpreter for Sdo {
+    fn name(&self) -> &'static str {
+        "SDO"
+    }
+
+    fn matches(&self, msg: &MessageCached) -> bool {
+        matches!(
+            msg.additional,
+            RxMessageAdditional::SdoTx(_)
+                | RxMessageAdditional::SdoRx(_)
+                | RxMessageAdditional::SdoObject(_)
+                | RxMessageAdditional::SdoAbort { .. }
+        )
+    }
+
+    fn decode(&self, msg: &MessageCached) -> DecodedView {
+        let mut view = DecodedView {
+            summary: msg.additional.to_string(),
+            fields: fields([("command", msg.additional.get_tooltip())]),
+        };
+        match &msg.additional {
+            RxMessageAdditional::SdoObject(access) => {
+                view.fields.extend(fields([
+                    (
+                        "object",
+                        format!("0x{:04X}:{:02X}", access.index, access.subindex),
+                    ),
+                    ("name", access.name.clone().unwrap_or_default()),
+                    ("value", access.value.clone().unwrap_or_default()),
+                ]));
+            }
+            RxMessageAdditional::SdoAbort {
+                index,
+                subindex,
+                code,
+            } => {
+                view.fields.extend(fields([
+                    ("object", format!("0x{index:04X}:{subindex:02X}")),
+                    ("abort code", format!("0x{code:08X}")),
+                ]));
+            }
+            _ => {}
+        }
+        view
+    }
+
+    fn summary(&self, msg: &MessageCached) -> String {
+        msg.additional.to_string()
+    }
+
+    fn columns(&self) -> &'static [&'static str] {
+        &["command"]
+    }
+
+    fn stats_contribution(&self, msg: &MessageCached) -> Option<String> {
+        let category = match &msg.additional {
+            RxMessageAdditional::SdoRx(_) => "request",
+            RxMessageAdditional::SdoTx(_) => "response",
+            RxMessageAdditional::SdoObject(access) if access.write => "download",
+            RxMessageAdditional::SdoObject(_) => "upload",
+            RxMessageAdditional::SdoAbort { .. } => "abort",
+            _ => return None,
+        };
+        Some(category.to_owned())
+    }
+}
+
+/// Emergencies, with the text of the error code.
+struct Emcy;
+
+impl MessageInterpreter for Emcy {
+    fn name(&self) -> &'static str {
+        "EMCY"
+    }
+
+    fn matches(&self, msg: &MessageCached) -> bool {
+        matches!(msg.additional, RxMessageAdditional::Emcy(_))
+    }
+
+    fn decode(&self, msg: &MessageCached) -> DecodedView {
+        let RxMessageAdditional::Emcy(e) = &msg.additional else {
+            return DecodedView::default();
+        };
+        DecodedView {
+            summary: e.to_string(),
+            fields: fields([
+                (
+                    "error code",
+                    match emcy_codes::describe(e.code) {
+                        Some(text) => format!("0x{:04X} {text}", e.code),
+                        None => format!("0x{:04X}", e.code),
+                    },
+                ),
+                (
+                    "error register",
+                    format!("0x{:02X} {}", e.register, e.register_text()),
+                ),
+                ("manufacturer data", e.data_hex()),
+            ]),
+        }
+    }
+
+    fn summary(&self, msg: &MessageCached) -> String {
+        msg.additional.to_string()
+    }
+
+    fn columns(&self) -> &'static [&'static str] {
+        &["error code", "error register", "manufacturer data"]
+    }
+
+    fn stats_contribution(&self, msg: &MessageCached) -> Option<String> {
+        match &msg.additional {
+            RxMessageAdditional::Emcy(e) => Some(format!("0x{:04X}", e.code)),
+            _ => None,
+        }
+    }
+}
+
+/// Heartbeats and boot-ups of the nodes.
+struct Heartbeat;
+
+impl MessageInterpreter for Heartbeat {
+    fn name(&self) -> &'static str {
+        "Heartbeat"
+    }
+
+    fn matches(&self, msg: &MessageCached) -> bool {
+        matches!(msg.additional, RxMessageAdditional::Heartbeat(_))
+    }
+
+    fn decode(&self, msg: &MessageCached) -> DecodedView {
+        let RxMessageAdditional::Heartbeat(heartbeat) = &msg.additional else {
+            return DecodedView::default();
+        };
+        DecodedView {
+            summary: msg.additional.to_string(),
+            fields: fields([
+                ("state", heartbeat.state.to_string()),
+                ("raw", format!("0x{:02X}", heartbeat.raw_value)),
+            ]),
+        }
+    }
+
+    fn summary(&self, msg: &MessageCached) -> String {
+        msg.additional.to_string()
+    }
+
+    fn columns(&self) -> &'static [&'static str] {
+        &["state", "raw"]
+    }
+
+    fn stats_contribution(&self, msg: &MessageCached) -> Option<String> {
+        match &msg.additional {
+            RxMessageAdditional::Heartbeat(heartbeat) => Some(heartbeat.state.to_string()),
+            _ => None,
+        }
+    }
+}
+
+/// Signals of the messages of the loaded DBC file.
+struct Signals;
+
+impl MessageInterpreter for Signals {
+    fn name(&self) -> &'static str {
+        "DBC"
+    }
+
+    fn matches(&self, msg: &MessageCached) -> bool {
+        matches!(msg.additional, RxMessageAdditional::Signals(_))
+    }
+
+    fn decode(&self, msg: &MessageCached) -> DecodedView {
+        let RxMessageAdditional::Signals(decoded) = &msg.additional else {
+            return DecodedView::default();
+        };
+        DecodedView {
+            summary: decoded.to_string(),
+            fields: decoded
+                .signals
+                .iter()
+                .map(|signal| {
+                    (
+                        signal.name.clone(),
+                        format!("{} (raw 0x{:X})", signal.value_text(), signal.raw),
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    fn summary(&self, msg: &MessageCached) -> String {
+        msg.additional.to_string()
+    }
+
+    fn stats_contribution(&self, msg: &MessageCached) -> Option<String> {
+        match &msg.additional {
+            RxMessageAdditional::Signals(decoded) => Some(decoded.message.clone()),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        all, register, view, DecodedView, InterpreterSettings, InterpreterStats, MessageInterpreter,
+    };
+    use crate::message_cached::MessageCached;
+    use oze_canopen::canopen::RxMessage;
+    use std::sync::Arc;
+    use tokio::time::Instant;
+
+    /// Bootloader of a vendor, on a COB-ID `CANopen` doesn't use.
+    struct Bootloader;
+
+    impl MessageInterpreter for Bootloader {
+        fn name(&self) -> &'static str {
+            "Bootloader"
+        }
+
+        fn matches(&self, msg: &MessageCached) -> bool {
+            msg.msg.msg.cob_id == 0x7F1
+        }
+
+        fn decode(&self, msg: &MessageCached) -> DecodedView {
+            DecodedView {
+                summary: format!("block {}", msg.msg.msg.data[0]),
+                fields: Vec::new(),
+            }
+        }
+    }
+
+    /// Tool flashing the same bootloader, consulted after it.
+    struct Flasher;
+
+    impl MessageInterpreter for Flasher {
+        fn name(&self) -> &'static str {
+            "Flasher"
+        }
+
+        fn matches(&self, msg: &MessageCached) -> bool {
+            msg.msg.msg.cob_id == 0x7F1
+        }
+
+        fn decode(&self, msg: &MessageCached) -> DecodedView {
+            DecodedView {
+                summary: format!("flashing {}", msg.msg.msg.data[0]),
+                fields: Vec::new(),
+            }
+        }
+    }
+
+    fn frame(cob_id: u16, data: &[u8]) -> MessageCached {
+        let mut raw = RxMessage {
+            timestamp: Instant::now(),
+            cob_id,
+            data: [0; 8],
+            dlc: data.len(),
+        };
+        raw.data[..data.len()].copy_from_slice(data);
+        MessageCached::new(0, raw)
+    }
+
+    #[test]
+    fn test_interpreters() {
+        let names: Vec<_> = all().iter().map(|interpreter| interpreter.name()).collect();
+        assert_eq!(
+            names[names.len() - 6..],
+            ["NMT", "CiA 402", "SDO", "EMCY", "Heartbeat", "DBC"]
+        );
+
+        let nmt = frame(0x000, &[0x01, 0x05]);
+        assert_eq!(nmt.interpreter, Some("NMT"));
+        let (name, nmt_view) = view(&nmt).unwrap();
+        assert_eq!(name, "NMT");
+        assert_eq!(nmt_view.fields[1], ("node".to_owned(), "5".to_owned()));
+        let emcy = frame(0x081, &[0x10, 0x81, 0x11, 0, 0, 0, 0, 0]);
+        assert_eq!(
+            view(&emcy).unwrap().1.fields[0].1,
+            "0x8110 CAN overrun (objects lost)"
+        );
+
+        let mut settings = InterpreterSettings::default();
+        settings.disabled.insert("NMT".to_owned());
+        assert_eq!(settings.info(&nmt), "");
+        assert_eq!(settings.info(&emcy), emcy.info().as_ref());
+        let mut stats = InterpreterStats::default();
+        stats.on_message(&nmt, &settings);
+        stats.on_message(&emcy, &settings);
+        stats.on_message(&emcy, &settings);
+        assert!(!stats.counts.contains_key("NMT"));
+        assert_eq!(stats.counts["EMCY"].total, 2);
+        assert_eq!(stats.counts["EMCY"].categories["0x8110"], 2);
+
+        register(Arc::new(Bootloader));
+        register(Arc::new(Flasher));
+        let block = frame(0x7F1, &[3]);
+        assert_eq!(block.interpreter, Some("Bootloader"));
+        assert_eq!(block.info().as_ref(), "block 3");
+
+        // Turning the interpreter off falls through to the next one matching.
+        let mut settings = InterpreterSettings::default();
+        settings.disabled.insert("Bootloader".to_owned());
+        assert_eq!(settings.view(&block).unwrap().0, "Flasher");
+        assert_eq!(settings.info(&block), "flashing 3");
+        let mut stats = InterpreterStats::default();
+        stats.on_message(&block, &settings);
+        assert!(!stats.counts.contains_key("Bootloader"));
+        assert_eq!(stats.counts["Flasher"].total, 1);
+    }
+}
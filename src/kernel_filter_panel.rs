@@ -0,0 +1,84 @@
+use crate::{rx_socket::KernelFilter, theme::OZON_PINK};
+use egui::TextEdit;
+
+/// Editor of the acceptance filters applied by the kernel to the receive socket.
+#[derive(Debug, Default)]
+pub struct KernelFilterPanel {
+    pub filters: Vec<KernelFilter>,
+    id_raw: String,
+    mask_raw: String,
+}
+
+impl KernelFilterPanel {
+    /// Shows the editor, returns `true` if the filter set was changed.
+    pub fn update(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut changed = false;
+        ui.colored_label(
+            OZON_PINK,
+            "Frames rejected here are dropped by the kernel: they never reach the viewer, statistics or pinned filters.",
+        )
+        .on_hover_text("The bus load is measured on a separate socket and still counts them");
+        ui.weak("SDO responses (580-5FF) and heartbeats (700-77F) always pass, the driver waits for them.");
+
+        ui.horizontal(|ui| {
+            ui.add(
+                TextEdit::singleline(&mut self.id_raw)
+                    .hint_text("id hex")
+                    .desired_width(70.0),
+            );
+            ui.add(
+                TextEdit::singleline(&mut self.mask_raw)
+                    .hint_text("mask hex")
+                    .desired_width(70.0),
+            )
+            .on_hover_text("Frame passes when id & mask == received & mask. Empty mask is 7FF");
+
+            let filter = KernelFilter::from_hex(&self.id_raw, &self.mask_raw);
+            if ui
+                .add_enabled(filter.is_some(), egui::Button::new("➕"))
+                .on_hover_text("Add acceptance filter")
+                .clicked()
+            {
+                if let Some(filter) = filter {
+                    if !self.filters.contains(&filter) {
+                        self.filters.push(filter);
+                        changed = true;
+                    }
+                }
+            }
+
+            if ui
+                .add_enabled(!self.filters.is_empty(), egui::Button::new("Accept all"))
+                .on_hover_text("Remove every kernel filter")
+                .clicked()
+            {
+                self.filters.clear();
+                changed = true;
+            }
+        });
+
+        ui.horizontal_wrapped(|ui| {
+            ui.label("Active:");
+            if self.filters.is_empty() {
+                ui.label("accept all");
+            }
+
+            let mut to_delete: Option<usize> = None;
+            for (index, filter) in self.filters.iter().enumerate() {
+                if ui
+                    .button(format!("{filter} ❌"))
+                    .on_hover_text("Remove filter")
+                    .clicked()
+                {
+                    to_delete = Some(index);
+                }
+            }
+            if let Some(index) = to_delete {
+                self.filters.remove(index);
+                changed = true;
+            }
+        });
+
+        changed
+    }
+}
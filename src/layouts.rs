@@ -0,0 +1,150 @@
+use crate::{
+    chart::ChartTab,
+    columns::Columns,
+    config,
+    settings::{HiddenPanels, PanelSizes},
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// File of the saved layouts in the config directory.
+const CONFIG_FILE: &str = "layouts.json";
+
+/// Layouts offered before anything is saved.
+pub const BUILTIN: [&str; 2] = ["analysis", "sending"];
+
+/// Panel sizes, shown panels, columns and chart tab of the window, saved under a name.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowLayout {
+    pub panels: PanelSizes,
+    pub hidden_panels: HiddenPanels,
+    pub columns: Columns,
+    pub chart_tab: ChartTab,
+}
+
+impl WindowLayout {
+    /// Layout of a name that was never saved: the built-in ones hide the panels they don't
+    /// need, any other name is the default layout.
+    pub fn builtin(name: &str) -> Self {
+        let hidden_panels = match name {
+            "analysis" => HiddenPanels::SENDER,
+            "sending" => HiddenPanels::STATS | HiddenPanels::DASHBOARD,
+            _ => HiddenPanels::empty(),
+        };
+        Self {
+            hidden_panels,
+            ..Self::default()
+        }
+    }
+}
+
+/// Change of layout picked in the View menu.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LayoutAction {
+    Apply(String),
+    /// Saves the current layout under the name.
+    Save(String),
+    /// Drops the saved layout, back to the built-in one.
+    Reset(String),
+}
+
+/// Layouts saved by name, with the one applied last.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Layouts {
+    active: Option<String>,
+    saved: BTreeMap<String, WindowLayout>,
+}
+
+impl Layouts {
+    pub fn load() -> Self {
+        config::load_json(CONFIG_FILE).unwrap_or_default()
+    }
+
+    fn save(&self) {
+        if let Err(e) = config::save_json(CONFIG_FILE, self) {
+            log::warn!("Can't save the layouts: {e}");
+        }
+    }
+
+    pub fn active(&self) -> Option<&str> {
+        self.active.as_deref()
+    }
+
+    /// Built-in and saved names, in order.
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = BUILTIN
+            .into_iter()
+            .chain(self.saved.keys().map(String::as_str))
+            .collect();
+        names.sort_unstable();
+        names.dedup();
+        names
+    }
+
+    /// Layout saved under `name`, else the built-in one, and makes it the active one.
+    pub fn select(&mut self, name: &str) -> WindowLayout {
+        self.active = Some(name.to_owned());
+        self.save();
+        self.get(name)
+    }
+
+    fn get(&self, name: &str) -> WindowLayout {
+        self.saved
+            .get(name)
+            .cloned()
+            .unwrap_or_else(|| WindowLayout::builtin(name))
+    }
+
+    pub fn store(&mut self, name: &str, layout: WindowLayout) {
+        self.saved.insert(name.to_owned(), layout);
+        self.active = Some(name.to_owned());
+        self.save();
+    }
+
+    /// Forgets the layout saved under `name`, returns the built-in one.
+    pub fn reset(&mut self, name: &str) -> WindowLayout {
+        self.saved.remove(name);
+        self.select(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Layouts, WindowLayout};
+    use crate::settings::{HiddenPanels, PanelSizes};
+
+    #[test]
+    fn test_layouts() {
+        let mut layouts = Layouts::default();
+        assert_eq!(layouts.names(), ["analysis", "sending"]);
+        assert_eq!(
+            layouts.get("sending").hidden_panels,
+            HiddenPanels::STATS | HiddenPanels::DASHBOARD
+        );
+
+        let wide = WindowLayout {
+            panels: PanelSizes {
+                sender: 600.0,
+                stats: 600.0,
+                detail: 500.0,
+                chart: 700.0,
+            },
+            ..WindowLayout::default()
+        };
+        layouts.saved.insert("bench".to_owned(), wide.clone());
+        layouts.saved.insert("analysis".to_owned(), wide.clone());
+        assert_eq!(layouts.names(), ["analysis", "bench", "sending"]);
+        assert_eq!(layouts.get("bench"), wide);
+
+        // Saved on a 2560x1440 screen, restored on a 1280x720 one.
+        let fitted = wide.panels.fit(egui::vec2(1280.0, 720.0));
+        assert!((fitted.sender + fitted.stats - 880.0).abs() < 0.01);
+        assert!((fitted.sender - fitted.stats).abs() < 0.01);
+        assert!((fitted.detail - 240.0).abs() < 0.01);
+        assert!((fitted.chart - 360.0).abs() < 0.01);
+        let small = PanelSizes::default();
+        assert_eq!(small.fit(egui::vec2(1920.0, 1080.0)), small);
+    }
+}
@@ -1,14 +1,74 @@
+pub mod annotations;
+pub mod auto_start;
 pub mod bitrate;
+pub mod bus_errors;
 pub mod bus_stats;
+pub mod byte_changes;
+pub mod capture;
 pub mod chart;
+pub mod cia402;
+pub mod columns;
+pub mod config;
+pub mod data_format;
+pub mod dbc;
+pub mod dbc_panel;
+pub mod deadlines;
+pub mod detail;
+pub mod device_info;
 pub mod driver;
+pub mod drops;
+pub mod eds;
+pub mod eds_panel;
+pub mod emcy;
+pub mod emcy_codes;
+pub mod export;
 pub mod filter;
 pub mod filter_data_panel;
 pub mod filter_panel;
+pub mod filter_profile;
+pub mod gap_histogram;
+pub mod gateway;
+pub mod gateway_panel;
 pub mod gui;
+pub mod http_api;
+pub mod interfaces;
+pub mod interpreter;
+pub mod kernel_filter_panel;
+pub mod layouts;
+pub mod live;
+pub mod log_import;
 pub mod message_cached;
 pub mod message_row;
 pub mod message_sender;
+pub mod metrics;
+pub mod navigation;
+pub mod nodes;
+pub mod notifications;
+pub mod od_browser;
+pub mod overlay_plot;
+pub mod pacer;
+pub mod pcapng;
+pub mod pdo_config;
+pub mod periodic;
 pub mod pinned_filter;
+pub mod pinned_stats;
+pub mod recorder;
+pub mod row_colors;
+pub mod rolling_max;
+pub mod rule_group_panel;
+pub mod rx_socket;
+pub mod script;
+pub mod sdo;
+pub mod search;
+pub mod selection;
+pub mod session_file;
+pub mod settings;
+pub mod startup;
+pub mod stats_export;
+pub mod sync_stats;
 pub mod theme;
+pub mod triggers;
+pub mod tx_log;
+pub mod value_plot;
 pub mod viewer;
+pub mod ws_server;
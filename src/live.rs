@@ -0,0 +1,177 @@
+use crate::{byte_changes::ByteChanges, data_format::DataFormat, message_cached::MessageCached};
+use oze_canopen::canopen::NodeId;
+use std::collections::BTreeMap;
+use tokio::time::Instant;
+
+/// Weight of the newest interval in the smoothed receive period.
+const PERIOD_SMOOTHING: f64 = 0.2;
+
+/// Last frame of a COB-ID with its receive statistics.
+#[derive(Debug, Clone)]
+pub struct LiveRow {
+    pub last: MessageCached,
+    pub count: u64,
+    /// Smoothed interval between two frames, in seconds.
+    pub period: Option<f64>,
+}
+
+impl LiveRow {
+    /// Receive rate in Hz.
+    pub fn rate(&self) -> f64 {
+        self.period.filter(|p| *p > 0.0).map_or(0.0, |p| 1.0 / p)
+    }
+}
+
+/// Order of the rows of the live table.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LiveSort {
+    #[default]
+    CobId,
+    Rate,
+}
+
+/// Action requested from the menu of a row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LiveAction {
+    /// Pin a filter on the COB-ID.
+    Pin(u16),
+    /// Filter the viewer on the COB-ID.
+    Filter(u16),
+    /// Add a show rule on the COB-ID.
+    ShowOnly(u16),
+    /// Add a hide rule on the COB-ID.
+    Hide(u16),
+    /// Add a hide rule on the node.
+    HideNode(NodeId),
+}
+
+/// cansniffer-like table with one row per COB-ID updated in place.
+#[derive(Debug, Default)]
+pub struct LiveTable {
+    rows: BTreeMap<u16, LiveRow>,
+    sort: LiveSort,
+}
+
+impl LiveTable {
+    /// Decodes the rows again after a DBC or EDS file changed.
+    pub fn redecode(&mut self) {
+        for row in self.rows.values_mut() {
+            row.last.redecode();
+        }
+    }
+
+    pub fn on_message(&mut self, msg: &MessageCached) {
+        let time = msg.get_timestamp();
+        let Some(row) = self.rows.get_mut(&msg.msg.msg.cob_id) else {
+            self.rows.insert(
+                msg.msg.msg.cob_id,
+                LiveRow {
+                    last: msg.clone(),
+                    count: 1,
+                    period: None,
+                },
+            );
+            return;
+        };
+
+        let interval = time
+            .saturating_duration_since(row.last.get_timestamp())
+            .as_secs_f64();
+        row.period = Some(
+            row.period
+                .map_or(interval, |p| p + PERIOD_SMOOTHING * (interval - p)),
+        );
+        row.count += 1;
+        row.last = msg.clone();
+    }
+
+    pub fn clear(&mut self) {
+        self.rows.clear();
+    }
+
+    pub fn update(
+        &mut self,
+        ui: &mut egui::Ui,
+        format: DataFormat,
+        changes: &ByteChanges,
+    ) -> Option<LiveAction> {
+        ui.horizontal(|ui| {
+            ui.label("Sort by");
+            ui.selectable_value(&mut self.sort, LiveSort::CobId, "COB-ID");
+            ui.selectable_value(&mut self.sort, LiveSort::Rate, "rate");
+            if ui.button("Clear").clicked() {
+                self.rows.clear();
+            }
+        });
+
+        if self.rows.is_empty() {
+            ui.label("No messages");
+            return None;
+        }
+
+        let mut rows: Vec<&LiveRow> = self.rows.values().collect();
+        if self.sort == LiveSort::Rate {
+            rows.sort_by(|a, b| b.rate().total_cmp(&a.rate()));
+        }
+
+        let now = Instant::now();
+        let mut action = None;
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            egui::Grid::new("live_grid")
+                .spacing([20.0, 4.0])
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.label("COB ID");
+                    ui.label("DLC");
+                    ui.label("Data");
+                    ui.label("Rate");
+                    ui.label("Age");
+                    ui.label("Count");
+                    ui.label("Info");
+                    ui.end_row();
+
+                    for row in rows {
+                        action = action.or(Self::show_row(ui, row, format, now, changes));
+                        ui.end_row();
+                    }
+                });
+        });
+        action
+    }
+
+    fn show_row(
+        ui: &mut egui::Ui,
+        row: &LiveRow,
+        format: DataFormat,
+        now: Instant,
+        changes: &ByteChanges,
+    ) -> Option<LiveAction> {
+        let mut action = None;
+        let cob_id = row.last.msg.msg.cob_id;
+        ui.menu_button(&row.last.cob_str, |ui| {
+            if ui.button("Pin filter").clicked() {
+                action = Some(LiveAction::Pin(cob_id));
+                ui.close_menu();
+            }
+            if ui.button("Filter viewer").clicked() {
+                action = Some(LiveAction::Filter(cob_id));
+                ui.close_menu();
+            }
+        });
+        ui.label(row.last.msg.msg.dlc.to_string());
+
+        ui.label(changes.latest_job(ui, &row.last, format));
+
+        let rate = row.rate();
+        ui.label(if rate >= 1.0 {
+            format!("{rate:.1} Hz")
+        } else {
+            format!("{rate:.2} Hz")
+        });
+        let age = now.saturating_duration_since(row.last.get_timestamp());
+        ui.label(format!("{:.1} s", age.as_secs_f32()));
+        ui.label(row.count.to_string());
+        ui.label(row.last.info().as_ref());
+        action
+    }
+}
@@ -0,0 +1,459 @@
+use crate::{
+    message_cached::Direction,
+    pcapng::{self, CAN_EFF_FLAG, CAN_ERR_FLAG, CAN_RTR_FLAG},
+    session_file::{SavedMessage, SessionFile},
+};
+use chrono::{Local, NaiveDateTime, SecondsFormat, TimeZone};
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+use tokio::sync::oneshot::{self, error::TryRecvError};
+
+/// Header of the CSV export of the message list.
+const CSV_HEADER: &str = "index,timestamp,cob_id,dlc,data";
+
+/// Kind of a file opened offline, told by its content.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileFormat {
+    /// `candump -l` log.
+    Candump,
+    /// CSV export of the message list.
+    Csv,
+    /// Saved session.
+    Session,
+    /// pcapng capture of `SocketCAN` frames, e.g. by Wireshark.
+    Pcapng,
+}
+
+impl FileFormat {
+    /// Format of the first line that is neither empty nor a comment.
+    pub fn sniff(text: &str) -> Option<Self> {
+        let first = text
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty() && !line.starts_with('#'))?;
+        if first.starts_with('{') {
+            Some(Self::Session)
+        } else if first.starts_with(CSV_HEADER) {
+            Some(Self::Csv)
+        } else if first.starts_with('(') {
+            Some(Self::Candump)
+        } else {
+            None
+        }
+    }
+}
+
+/// Messages of a pcapng capture, or of a log or saved session if the file is text.
+///
+/// # Errors
+/// If the format isn't known, with the first malformed line of a log or block of a capture.
+pub fn parse_bytes(
+    bytes: &[u8],
+    parsed: &AtomicUsize,
+) -> Result<(FileFormat, SessionFile), String> {
+    if pcapng::is_pcapng(bytes) {
+        let file = parse_pcapng(bytes)?;
+        parsed.store(bytes.len(), Ordering::Relaxed);
+        return Ok((FileFormat::Pcapng, file));
+    }
+    let text =
+        std::str::from_utf8(bytes).map_err(|_| "neither a text file nor a pcapng capture")?;
+    parse(text, parsed)
+}
+
+/// Messages of the CAN frames of a capture, named after the interface of the first one. The
+/// error, remote and extended frames are skipped.
+fn parse_pcapng(bytes: &[u8]) -> Result<SessionFile, String> {
+    let capture = pcapng::read(bytes)?;
+    let mut file = SessionFile {
+        saved: Local::now().to_rfc3339_opts(SecondsFormat::Millis, false),
+        ..SessionFile::default()
+    };
+    let mut start = None;
+    for (interface, frame) in capture.frames {
+        if frame.can_id & (CAN_ERR_FLAG | CAN_RTR_FLAG | CAN_EFF_FLAG) != 0 || frame.can_id > 0x7FF
+        {
+            continue;
+        }
+        let start = *start.get_or_insert_with(|| {
+            capture.interfaces[interface].clone_into(&mut file.can_name);
+            frame.time_us
+        });
+        let mut msg = saved_message(frame.can_id, frame.data, frame.direction);
+        msg.index = file.messages.len() as u64;
+        msg.time_us = u64::try_from(frame.time_us.saturating_sub(start)).unwrap_or_default();
+        file.messages.push(msg);
+    }
+    file.start_us = start.unwrap_or_default();
+    Ok(file)
+}
+
+/// Messages of a log or saved session. Logs have no settings nor statistics.
+///
+/// `parsed` is advanced to the number of bytes read, for the progress.
+///
+/// # Errors
+/// If the format isn't known, with the first malformed line of a log.
+pub fn parse(text: &str, parsed: &AtomicUsize) -> Result<(FileFormat, SessionFile), String> {
+    let format =
+        FileFormat::sniff(text).ok_or("neither a candump log, a CSV export nor a saved session")?;
+    let file = match format {
+        FileFormat::Session => SessionFile::from_json(text)?,
+        FileFormat::Candump => parse_lines(text, parsed, parse_candump_line)?,
+        FileFormat::Csv => {
+            // Times since the capture start only, the wall clock starts now.
+            let mut file = parse_lines(text, parsed, parse_csv_line)?;
+            file.start_us = Local::now().timestamp_micros();
+            file
+        }
+        FileFormat::Pcapng => parse_pcapng(text.as_bytes())?,
+    };
+    parsed.store(text.len(), Ordering::Relaxed);
+    Ok((format, file))
+}
+
+/// Frame of a log line, with its time in microseconds and its interface.
+type LogFrame<'a> = (i64, Option<&'a str>, SavedMessage);
+
+/// Frame of a log line, `None` for the lines to skip, or why the line can't be read.
+type LineResult<'a> = Result<Option<LogFrame<'a>>, &'static str>;
+
+/// Messages of the lines of a log, the comments, markers and frames the viewer doesn't
+/// handle (extended, CAN FD) are skipped.
+fn parse_lines<'a>(
+    text: &'a str,
+    parsed: &AtomicUsize,
+    parse_line: fn(&'a str) -> LineResult<'a>,
+) -> Result<SessionFile, String> {
+    let mut file = SessionFile {
+        saved: Local::now().to_rfc3339_opts(SecondsFormat::Millis, false),
+        ..SessionFile::default()
+    };
+    let mut start = None;
+    let mut bytes = 0;
+    for (number, line) in text.lines().enumerate() {
+        bytes += line.len() + 1;
+        if number % 1024 == 0 {
+            parsed.store(bytes, Ordering::Relaxed);
+        }
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let Some((time_us, interface, mut msg)) = parse_line(trimmed)
+            .map_err(|reason| format!("line {}: {reason}: {trimmed}", number + 1))?
+        else {
+            continue;
+        };
+        let start = *start.get_or_insert_with(|| {
+            interface.unwrap_or_default().clone_into(&mut file.can_name);
+            time_us
+        });
+        msg.index = file.messages.len() as u64;
+        msg.time_us = u64::try_from(time_us.saturating_sub(start)).unwrap_or_default();
+        file.messages.push(msg);
+    }
+    file.start_us = start.unwrap_or_default();
+    Ok(file)
+}
+
+/// `(1700000000.123456) can0 181#01AB` as written by `candump -l`, with ` T` for the frames
+/// sent by the viewer. Also the lines printed by `candump -ta`, `-tA` and `-x`, as
+/// `(1700000000.123456)  can0  TX - -  181   [2]  01 AB`.
+fn parse_candump_line(line: &str) -> LineResult<'_> {
+    let (time, rest) = line
+        .strip_prefix('(')
+        .and_then(|line| line.split_once(')'))
+        .ok_or("no timestamp")?;
+    let time_us = candump_time(time.trim()).ok_or("bad timestamp")?;
+    let mut fields = rest.split_whitespace();
+    let interface = fields.next().ok_or("no interface")?;
+    let frame = fields.next().ok_or("no frame")?;
+    let (id, data, direction) = if let Some((id, data)) = frame.split_once('#') {
+        let direction = match fields.next() {
+            Some("T") => Direction::Tx,
+            Some("R") | None => Direction::Rx,
+            Some(_) => return Err("unknown direction"),
+        };
+        // CAN FD frame.
+        if data.starts_with('#') {
+            return Ok(None);
+        }
+        let data = if data.starts_with('R') {
+            Vec::new()
+        } else {
+            hex_bytes(data)?
+        };
+        (id, data, direction)
+    } else {
+        // The flags of `-x` follow the direction.
+        let (id, direction) = match frame {
+            "TX" | "RX" => {
+                let id = fields.nth(2).ok_or("no CAN ID")?;
+                (
+                    id,
+                    if frame == "TX" {
+                        Direction::Tx
+                    } else {
+                        Direction::Rx
+                    },
+                )
+            }
+            id => (id, Direction::Rx),
+        };
+        let len: usize = fields
+            .next()
+            .and_then(|len| len.strip_prefix('['))
+            .and_then(|len| len.strip_suffix(']'))
+            .and_then(|len| len.parse().ok())
+            .ok_or("no length")?;
+        if len > 8 {
+            return Ok(None);
+        }
+        let mut bytes = fields.peekable();
+        let data = if bytes.peek() == Some(&"remote") {
+            Vec::new()
+        } else {
+            // The ASCII of `-a` may follow the bytes.
+            let hex: String = bytes.take(len).collect();
+            let data = hex_bytes(&hex)?;
+            if data.len() != len {
+                return Err("bad data");
+            }
+            data
+        };
+        (id, data, direction)
+    };
+    let id = u32::from_str_radix(id, 16).map_err(|_| "bad CAN ID")?;
+    // Extended and error frames.
+    if id > 0x7FF {
+        return Ok(None);
+    }
+    Ok(Some((
+        time_us,
+        Some(interface),
+        saved_message(id, data, direction),
+    )))
+}
+
+/// Microseconds since the epoch of seconds with up to 6 decimals, or of a local date as printed
+/// by `candump -tA`.
+fn candump_time(time: &str) -> Option<i64> {
+    if time.contains(' ') {
+        let date = NaiveDateTime::parse_from_str(time, "%Y-%m-%d %H:%M:%S%.f").ok()?;
+        return Some(
+            Local
+                .from_local_datetime(&date)
+                .earliest()?
+                .timestamp_micros(),
+        );
+    }
+    let (seconds, micros) = time.split_once('.')?;
+    if micros.is_empty() || !micros.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let seconds: i64 = seconds.parse().ok()?;
+    let micros: i64 = format!("{micros:0<6}")[..6].parse().ok()?;
+    seconds.checked_mul(1_000_000)?.checked_add(micros)
+}
+
+/// `index,timestamp,cob_id,dlc,data,decoded,annotation,direction`, the markers have no index.
+fn parse_csv_line(line: &str) -> LineResult<'_> {
+    if line.starts_with(CSV_HEADER) {
+        return Ok(None);
+    }
+    let mut fields = line.splitn(6, ',');
+    let index = fields.next().ok_or("no index")?;
+    let time: f64 = fields
+        .next()
+        .and_then(|time| time.parse().ok())
+        .filter(|time: &f64| time.is_finite() && *time >= 0.0)
+        .ok_or("bad timestamp")?;
+    if index.is_empty() {
+        return Ok(None);
+    }
+    let cob_id = fields.next().ok_or("no COB-ID")?;
+    let cob_id = u32::from_str_radix(cob_id.trim_start_matches("0x"), 16)
+        .ok()
+        .filter(|cob_id| *cob_id <= 0x7FF)
+        .ok_or("bad COB-ID")?;
+    let _dlc = fields.next();
+    let data = hex_bytes(&fields.next().ok_or("no data")?.replace(' ', ""))?;
+    // The decoded info is quoted and may contain commas, the direction is always last.
+    let direction = match line.rsplit(',').next() {
+        Some("tx") => Direction::Tx,
+        _ => Direction::Rx,
+    };
+    #[allow(clippy::cast_possible_truncation)]
+    let time_us = (time * 1e6).round() as i64;
+    Ok(Some((
+        time_us,
+        None,
+        saved_message(cob_id, data, direction),
+    )))
+}
+
+fn hex_bytes(hex: &str) -> Result<Vec<u8>, &'static str> {
+    if !hex.is_ascii() || hex.len() % 2 != 0 || hex.len() > 16 {
+        return Err("bad data");
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| "bad data"))
+        .collect()
+}
+
+fn saved_message(cob_id: u32, data: Vec<u8>, direction: Direction) -> SavedMessage {
+    SavedMessage {
+        index: 0,
+        time_us: 0,
+        cob_id: u16::try_from(cob_id).unwrap_or_default(),
+        data,
+        direction,
+        origin: None,
+    }
+}
+
+type ImportResult = Result<(FileFormat, SessionFile), String>;
+
+/// File read and parsed on a separate thread.
+#[derive(Debug)]
+pub struct ImportJob {
+    path: String,
+    size: usize,
+    parsed: Arc<AtomicUsize>,
+    result: oneshot::Receiver<ImportResult>,
+}
+
+impl ImportJob {
+    pub fn start(path: String) -> Self {
+        let size = std::fs::metadata(&path)
+            .map(|metadata| usize::try_from(metadata.len()).unwrap_or(usize::MAX))
+            .unwrap_or_default();
+        let parsed = Arc::new(AtomicUsize::new(0));
+        let (reply, result) = oneshot::channel();
+        let thread_path = path.clone();
+        let thread_parsed = parsed.clone();
+        std::thread::spawn(move || {
+            let imported = std::fs::read(&thread_path)
+                .map_err(|e| e.to_string())
+                .and_then(|bytes| parse_bytes(&bytes, &thread_parsed));
+            let _ = reply.send(imported);
+        });
+        Self {
+            path,
+            size,
+            parsed,
+            result,
+        }
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Share of the file parsed, 0 to 1.
+    #[allow(clippy::cast_precision_loss)]
+    pub fn progress(&self) -> f32 {
+        match self.size {
+            0 => 0.0,
+            size => self.parsed.load(Ordering::Relaxed).min(size) as f32 / size as f32,
+        }
+    }
+
+    /// The result once the file is parsed.
+    pub fn poll(&mut self) -> Option<ImportResult> {
+        match self.result.try_recv() {
+            Ok(result) => Some(result),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Closed) => Some(Err("import thread stopped".to_owned())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, FileFormat};
+    use crate::message_cached::Direction;
+    use std::sync::atomic::AtomicUsize;
+
+    #[test]
+    fn test_parse_logs() {
+        let parsed = AtomicUsize::new(0);
+        let candump = "# marker\n\
+            (1700000000.500000) can0 181#01AB\n\
+            (1700000000.750000) can0 601#40181001 T\n\
+            (1700000001.000000) can0 12345678#00\n\
+            (1700000001.250000) can0 080#\n";
+        let (format, file) = parse(candump, &parsed).unwrap();
+        assert_eq!(format, FileFormat::Candump);
+        assert_eq!(parsed.into_inner(), candump.len());
+        assert_eq!(file.can_name, "can0");
+        assert_eq!(file.start_us, 1_700_000_000_500_000);
+        assert_eq!(file.messages.len(), 3);
+        assert_eq!(file.messages[0].data, [0x01, 0xAB]);
+        assert_eq!(file.messages[1].direction, Direction::Tx);
+        assert_eq!(file.messages[1].time_us, 250_000);
+        assert_eq!(file.messages[2].index, 2);
+        assert!(file.messages[2].data.is_empty());
+
+        let csv = "index,timestamp,cob_id,dlc,data,decoded,annotation,direction\n\
+            7,0.100000,0x181,2,01 AB,\"a, b\",,rx\n\
+            ,0.150000,,,,,\"marker\",\n\
+            8,0.200000,0x601,1,40,\"\",bookmark,tx\n";
+        let (format, file) = parse(csv, &AtomicUsize::new(0)).unwrap();
+        assert_eq!(format, FileFormat::Csv);
+        assert_eq!(file.messages.len(), 2);
+        assert_eq!(file.messages[1].cob_id, 0x601);
+        assert_eq!(file.messages[1].time_us, 100_000);
+        assert_eq!(file.messages[1].direction, Direction::Tx);
+
+        let broken = "(1700000000.500000) can0 181#01AB\n(1700000000.6) can0 181#0\n";
+        assert_eq!(
+            parse(broken, &AtomicUsize::new(0)).unwrap_err(),
+            "line 2: bad data: (1700000000.6) can0 181#0"
+        );
+        let printed = " (1700000000.500000)  can0  181   [2]  01 AB   '..'\n\
+            (1700000000.750000)  can0  TX - -  601   [4]  40 18 10 01\n\
+            (1700000001.000000)  can0  RX - -  701   [0]  remote request\n\
+            (1700000001.250000)  can0  12345678   [1]  00\n";
+        let (format, file) = parse(printed, &AtomicUsize::new(0)).unwrap();
+        assert_eq!(format, FileFormat::Candump);
+        assert_eq!(file.messages.len(), 3);
+        assert_eq!(file.messages[0].data, [0x01, 0xAB]);
+        assert_eq!(file.messages[1].direction, Direction::Tx);
+        assert_eq!(file.messages[1].data, [0x40, 0x18, 0x10, 0x01]);
+        assert!(file.messages[2].data.is_empty());
+        let dated = "(2024-05-02 12:03:45.250000)  can0  181   [1]  01\n\
+            (2024-05-02 12:03:46.000000)  can0  181   [1]  02\n";
+        let file = parse(dated, &AtomicUsize::new(0)).unwrap().1;
+        assert_eq!(file.messages[1].time_us, 750_000);
+        assert_eq!(
+            parse(
+                "(1700000000.500000)  can0  181   [3]  01 AB\n",
+                &AtomicUsize::new(0)
+            )
+            .unwrap_err(),
+            "line 1: bad data: (1700000000.500000)  can0  181   [3]  01 AB"
+        );
+        assert!(parse("hello", &AtomicUsize::new(0)).is_err());
+        assert!(parse("(1.é) can0 181#é0", &AtomicUsize::new(0)).is_err());
+        assert_eq!(
+            parse("(99999999999999999.0) can0 181#01\n", &AtomicUsize::new(0)).unwrap_err(),
+            "line 1: bad timestamp: (99999999999999999.0) can0 181#01"
+        );
+        let far = "(-9000000000000.0) can0 181#01\n(9000000000000.0) can0 181#02\n";
+        assert_eq!(
+            parse(far, &AtomicUsize::new(0)).unwrap().1.messages[1].time_us,
+            u64::MAX >> 1
+        );
+        assert_eq!(
+            parse(r#"{"can_name": "can1"}"#, &AtomicUsize::new(0))
+                .unwrap()
+                .0,
+            FileFormat::Session
+        );
+    }
+}
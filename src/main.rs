@@ -1,61 +1,274 @@
-use clap::Parser;
+use clap::{error::ErrorKind, CommandFactory, Parser};
 use oze_canopen::interface::Connection;
-use oze_canopen_viewer::bitrate;
-use oze_canopen_viewer::driver::{self, Control, WriteCommand};
+use oze_canopen_viewer::cia402;
+use oze_canopen_viewer::data_format::DataFormat;
+use oze_canopen_viewer::dbc;
+use oze_canopen_viewer::eds;
+use oze_canopen_viewer::driver::{self, Control, DATA_CHANNEL_CAPACITY};
+use oze_canopen_viewer::emcy_codes;
+use oze_canopen_viewer::filter_profile::{FilterProfile, FilterProfiles, RuleConfig};
+use oze_canopen_viewer::gateway::GatewayConfig;
 use oze_canopen_viewer::gui::Gui;
-use std::sync::Arc;
+use oze_canopen_viewer::http_api::{ApiServer, DEFAULT_API_ADDR};
+use oze_canopen_viewer::metrics::MetricsServer;
+use oze_canopen_viewer::pacer::DEFAULT_TX_RATE;
+use oze_canopen_viewer::pinned_filter::PINNED_HISTORY_DEPTH;
+use oze_canopen_viewer::recorder::Recorder;
+use oze_canopen_viewer::script;
+use oze_canopen_viewer::ws_server::{WsServer, WS_CHANNEL_CAPACITY};
+use oze_canopen_viewer::settings::{AppSettings, BufferSettings, MESSAGES_COUNT};
+use oze_canopen_viewer::startup::{self, Startup};
+use std::net::SocketAddr;
+use std::sync::{Arc, OnceLock};
 use std::thread;
 use tokio::runtime::Runtime;
-use tokio::sync::{watch, mpsc, Mutex};
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 struct Args {
+    /// CAN interface to connect to, same as --can
+    #[arg(conflicts_with = "can")]
+    interface: Option<String>,
     #[arg(short, long)]
     can: Option<String>,
     #[arg(short, long)]
     bitrate: Option<u32>,
+    /// Number of messages the driver can queue for the GUI
+    #[arg(long, default_value_t = DATA_CHANNEL_CAPACITY)]
+    driver_buffer: usize,
+    /// Number of messages kept in the viewer
+    #[arg(long, default_value_t = MESSAGES_COUNT)]
+    messages: usize,
+    /// Number of messages kept per pinned filter
+    #[arg(long, default_value_t = PINNED_HISTORY_DEPTH)]
+    pinned_history: usize,
+    /// CSV file of manufacturer EMCY error codes, as `code,text` lines
+    #[arg(long)]
+    emcy_codes: Option<String>,
+    /// DBC file decoding the PDOs into signals
+    #[arg(long)]
+    dbc: Option<String>,
+    /// Rhai script decoding the frames with `decode(cob_id, data)` and running
+    /// `on_message(cob_id, data)` on every frame
+    #[arg(long)]
+    script: Option<String>,
+    /// EDS file naming the SDO objects of a node, as `node=path`, can be repeated
+    #[arg(long)]
+    eds: Vec<String>,
+    /// PDO carrying the drive statusword (0x6041), as `COB-ID` or `COB-ID:byte`, can be repeated. The
+    /// TPDO mappings written over SDO are followed without it
+    #[arg(long, value_parser = startup::parse_statusword_pdo)]
+    statusword_pdo: Vec<(u16, usize)>,
+    /// Forward frames between the CAN interface and this one
+    #[arg(long)]
+    gateway: Option<String>,
+    /// Data format: hex, bin, ascii, u16, i16, u32, i32, f32 or u16list, `be` suffix for big
+    /// endian
+    #[arg(long, value_parser = startup::parse_format)]
+    format: Option<DataFormat>,
+    /// Show only these COB-IDs, e.g. 0x181,0x581
+    #[arg(long, value_parser = startup::parse_cob_filter, conflicts_with = "profile")]
+    filter: Option<RuleConfig>,
+    /// Apply this saved filter profile
+    #[arg(long)]
+    profile: Option<String>,
+    /// Hide the heartbeats, on top of the filter or profile
+    #[arg(long)]
+    hide_heartbeats: bool,
+    /// Connect on startup, to the interface of the previous run if none is given
+    #[arg(long)]
+    auto_connect: bool,
+    /// Write every received frame to this candump log file, or pcapng capture if it ends with .pcapng
+    #[arg(long)]
+    record: Option<String>,
+    /// Stream the messages and statistics as JSON to the WebSocket clients of this address,
+    /// e.g. 127.0.0.1:9000
+    #[arg(long)]
+    ws_listen: Option<SocketAddr>,
+    /// Accept NMT, SDO, raw and SYNC commands and answer the statistics and nodes over HTTP on
+    /// this address, 127.0.0.1:8080 if none is given
+    #[arg(long, num_args = 0..=1, default_missing_value = DEFAULT_API_ADDR)]
+    api_listen: Option<SocketAddr>,
+    /// Token the HTTP API requires as `Authorization: Bearer <token>`, needed to listen on
+    /// an address other than loopback
+    #[arg(long, requires = "api_listen")]
+    api_token: Option<String>,
+    /// Export the bus load, rates, error frames and nodes as Prometheus metrics on
+    /// http://<address>/metrics, e.g. 127.0.0.1:9100
+    #[arg(long)]
+    metrics_listen: Option<SocketAddr>,
+}
+
+/// Prints a usage error and exits with the clap error code.
+fn usage_error(message: String) -> ! {
+    Args::command().error(ErrorKind::InvalidValue, message).exit()
+}
+
+/// Connection to open on startup: the interface given, else the one of the previous run with
+/// `--auto-connect`.
+fn startup_connection(args: &Args) -> Connection {
+    if let Some(can_name) = args.interface.clone().or_else(|| args.can.clone()) {
+        return Connection {
+            can_name,
+            bitrate: args.bitrate,
+        };
+    }
+    if !args.auto_connect {
+        if args.record.is_some() {
+            usage_error("--record needs an interface or --auto-connect".to_owned());
+        }
+        return Connection {
+            can_name: String::new(),
+            bitrate: args.bitrate,
+        };
+    }
+    let settings = AppSettings::load();
+    if settings.can_name.is_empty() {
+        usage_error("--auto-connect needs an interface, none given nor saved".to_owned());
+    }
+    Connection {
+        can_name: settings.can_name,
+        bitrate: args.bitrate.or_else(|| settings.bitrate.parse().ok()),
+    }
+}
+
+/// Display and filter options, the profile named must have been saved.
+fn startup_options(args: &Args) -> Startup {
+    let filters = match (&args.filter, &args.profile) {
+        (Some(rule), _) => Some(FilterProfile {
+            data: rule.clone(),
+            ..FilterProfile::default()
+        }),
+        (None, Some(name)) => {
+            let profiles = FilterProfiles::load();
+            let Some(profile) = profiles.get(name) else {
+                let names: Vec<&str> = profiles.names().collect();
+                usage_error(if names.is_empty() {
+                    format!("no filter profile {name}, none is saved")
+                } else {
+                    format!("no filter profile {name}, saved: {}", names.join(", "))
+                });
+            };
+            Some(profile.clone())
+        }
+        (None, None) => None,
+    };
+    Startup {
+        format: args.format,
+        filters,
+        hide_heartbeats: args.hide_heartbeats,
+    }
+}
+
+/// Loads the EMCY codes, DBC, script and EDS files given and declares the statusword PDOs, the
+/// files that fail are logged and skipped.
+fn load_decoding_files(args: &Args) {
+    if let Some(path) = &args.emcy_codes {
+        match emcy_codes::load_vendor_codes(path) {
+            Ok(count) => log::info!("Loaded {count} EMCY codes from {path}"),
+            Err(e) => log::error!("Failed to load EMCY codes: {e}"),
+        }
+    }
+    if let Some(path) = &args.dbc {
+        match dbc::load(path) {
+            Ok(loaded) => {
+                log::info!("Loaded {} DBC messages from {path}", loaded.message_count());
+                for warning in &loaded.warnings {
+                    log::warn!("{path}: {warning}");
+                }
+                dbc::set(Some(loaded));
+            }
+            Err(e) => log::error!("Failed to load the DBC: {e}"),
+        }
+    }
+    if let Some(path) = &args.script {
+        match script::load(path) {
+            Ok(loaded) => {
+                log::info!("Loaded the script {path}");
+                script::set(Some(loaded));
+            }
+            Err(e) => log::error!("Failed to load the script: {e}"),
+        }
+    }
+    for (cob_id, offset) in &args.statusword_pdo {
+        cia402::declare(*cob_id, *offset);
+    }
+    for arg in &args.eds {
+        let Some((node_id, path)) = arg.split_once('=') else {
+            log::error!("Invalid --eds {arg}, expected node=path");
+            continue;
+        };
+        match (node_id.parse(), eds::load(path)) {
+            (Ok(node_id), Ok(loaded)) => {
+                log::info!("Loaded {} objects of node {node_id} from {path}", loaded.object_count());
+                eds::set(node_id, Some(loaded));
+            }
+            (Err(e), _) => log::error!("Invalid node ID in --eds {arg}: {e}"),
+            (_, Err(e)) => log::error!("Failed to load the EDS: {e}"),
+        }
+    }
 }
 
 fn main() -> eframe::Result<()> {
     pretty_env_logger::init();
     let args = Args::parse();
+    let connection = startup_connection(&args);
+    let startup = startup_options(&args);
+    let recorder = args.record.as_deref().map(|path| {
+        Recorder::create(path)
+            .unwrap_or_else(|e| usage_error(format!("can't create the recording {path}: {e}")))
+    });
+    load_decoding_files(&args);
 
     let initial_control = Control {
         command: driver::ControlCommand::Process,
-        connection: Connection {
-            can_name: args.can.clone().unwrap_or_default(),
-            bitrate: args.bitrate,
-        },
+        connection,
+        kernel_filters: Vec::new(),
+        tx_rate: Some(DEFAULT_TX_RATE),
+        gateway: args.gateway.map(|peer| GatewayConfig {
+            peer,
+            ..Default::default()
+        }),
+        exit_nmt: None,
+        auto_start: None,
     };
 
-    let (state_snd, state_rcv) = watch::channel(driver::State::default());
-    let (ctrl_snd, ctrl_rcv) = watch::channel(initial_control.clone());
-    let (write_snd, write_rcv) = mpsc::channel::<WriteCommand>(100);
+    let buffers = BufferSettings {
+        driver_buffer: args.driver_buffer,
+        messages: args.messages,
+        pinned_history: args.pinned_history,
+    }
+    .clamped();
 
-    let bitrates = Arc::new(Mutex::new(Vec::new()));
-    let bitrates_thr = bitrates.clone();
-    let ctrl_snd_thr = ctrl_snd.clone();
+    let repaint = Arc::new(OnceLock::new());
     let rt = Runtime::new().expect("Unable to create Runtime");
-
-    let _enter = rt.enter();
-
-    thread::spawn(move || {
-        rt.block_on(async {
-            let drv = driver::Driver::new(state_snd, ctrl_rcv, write_rcv);
-            let br = bitrate::Bitrate::new(drv.co.info.clone(), bitrates_thr.clone());
-            drv.start_thread();
-            br.start_thread();
-
-            if let Some(can_name) = args.can {
-                println!("Use args: {can_name:?} {:?}", args.bitrate);
-                ctrl_snd_thr.send(initial_control).unwrap();
-            }
-
-            tokio::signal::ctrl_c().await
-        })
-        .unwrap();
+    let runtime = rt.handle().clone();
+    let ws_server = args.ws_listen.map(|addr| {
+        WsServer::start(&runtime, addr, WS_CHANNEL_CAPACITY)
+            .unwrap_or_else(|e| usage_error(format!("can't listen on {addr}: {e}")))
+    });
+    let channels = driver::spawn(
+        &runtime,
+        initial_control,
+        buffers.driver_buffer,
+        repaint.clone(),
+        recorder,
+        ws_server.as_ref().map(WsServer::messages),
+    );
+    let api_server = args.api_listen.map(|addr| {
+        if !addr.ip().is_loopback() && args.api_token.is_none() {
+            usage_error(format!("--api-listen {addr} isn't a loopback address, it needs --api-token"));
+        }
+        ApiServer::start(&runtime, addr, args.api_token.clone(), channels.write.clone())
+            .unwrap_or_else(|e| usage_error(format!("can't listen on {addr}: {e}")))
     });
+    let metrics_server = args.metrics_listen.map(|addr| {
+        MetricsServer::start(&runtime, addr)
+            .unwrap_or_else(|e| usage_error(format!("can't listen on {addr}: {e}")))
+    });
+
+    // Drives the tasks of every session, their drivers stop on Ctrl+C and the window follows.
+    thread::spawn(move || rt.block_on(std::future::pending::<()>()));
 
     let native_options = eframe::NativeOptions {
         viewport: {
@@ -70,6 +283,20 @@ fn main() -> eframe::Result<()> {
     eframe::run_native(
         "OZON CanOpen Viewer",
         native_options,
-        Box::new(|cc| Ok(Box::new(Gui::new(cc, state_rcv, ctrl_snd, bitrates, write_snd)))),
+        Box::new(move |cc| {
+            let _ = repaint.set(cc.egui_ctx.clone());
+            let mut gui = Gui::new(cc, runtime, channels, buffers, repaint);
+            gui.apply_startup(&startup);
+            if let Some(server) = &ws_server {
+                gui.stream_stats(server.stats());
+            }
+            if let Some(server) = &api_server {
+                gui.publish_status(server.status());
+            }
+            if let Some(server) = &metrics_server {
+                gui.publish_status(server.status());
+            }
+            Ok(Box::new(gui))
+        }),
     )
 }
@@ -1,16 +1,37 @@
+use crate::{cia402, dbc, eds, emcy::Emergency, gateway::GatewaySide, interpreter, script, sdo};
 use core::fmt;
 use oze_canopen::{
     canopen::{RxMessage, RxMessageParsed, RxMessageToStringFormat, RxMessageType},
     proto::{
-        emcy::Emcy,
         nmt::NmtCommand,
         sdo::{ResponseData, SdoRequest, SdoRequestData, SdoResponse},
     },
     BinRead,
 };
-use std::io::Cursor;
+use serde::{Deserialize, Serialize};
+use std::{
+    io::Cursor,
+    sync::{Arc, OnceLock},
+};
 use tokio::time::Instant;
 
+/// Whether a frame was sent by the viewer or received from the other nodes.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Direction {
+    #[default]
+    Rx,
+    Tx,
+}
+
+impl Direction {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Rx => "RX",
+            Self::Tx => "TX",
+        }
+    }
+}
+
 /// Heartbeat message containing NMT state
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Heartbeat {
@@ -66,9 +87,15 @@ impl fmt::Display for NmtState {
 pub enum RxMessageAdditional {
     SdoTx(SdoResponse),
     SdoRx(SdoRequest),
+    /// SDO transfer of a node with an EDS, named after its object dictionary.
+    SdoObject(sdo::SdoAccess),
+    /// Abort transfer sent by the client or the server.
+    SdoAbort { index: u16, subindex: u8, code: u32 },
     Nmt(NmtCommand),
-    Emcy(Emcy),
+    Emcy(Emergency),
     Heartbeat(Heartbeat),
+    /// Signals of a message of the loaded DBC file.
+    Signals(dbc::Decoded),
     None,
 }
 
@@ -79,8 +106,23 @@ pub struct MessageCached {
     pub additional: RxMessageAdditional,
     pub cob_str: String,
     pub hex_str: String,
-    pub bin_str: String,
-    pub ascii_str: String,
+    /// Binary and ASCII payload, formatted the first time they are shown.
+    bin_str: OnceLock<Arc<str>>,
+    ascii_str: OnceLock<Arc<str>>,
+    /// Decoded info, formatted once for `text_str` and kept for the rows.
+    info_str: Arc<str>,
+    /// Name of the interpreter whose summary starts `info_str`.
+    pub interpreter: Option<&'static str>,
+    /// Text of the script alone, shown when the interpreter is turned off.
+    script_str: Option<Arc<str>>,
+    /// COB-ID, type, hex data and decoded info as one line, what the text filters match.
+    pub text_str: String,
+    /// Side of the gateway the message was received on, `None` outside of gateway mode.
+    pub origin: Option<GatewaySide>,
+    /// `Tx` for the frames sent by the viewer, seen when they reach the bus.
+    pub direction: Direction,
+    /// Bit `i` is set if byte `i` differs from the previous frame of the same COB-ID.
+    pub changed_bytes: u8,
 }
 
 impl RxMessageAdditional {
@@ -158,15 +200,25 @@ impl RxMessageAdditional {
                 .map(|i| i.0)
                 .collect::<Vec<_>>()
                 .join(","),
+            RxMessageAdditional::SdoObject(access) => {
+                format!("Object 0x{:04X}:{:02X}", access.index, access.subindex)
+            }
+            RxMessageAdditional::SdoAbort { code, .. } => format!("Abort code 0x{code:08X}"),
             RxMessageAdditional::Nmt(n) => {
                 format!("{n:?}")
             }
-            RxMessageAdditional::Emcy(n) => {
-                format!("{n:?}")
+            RxMessageAdditional::Emcy(e) => {
+                format!("Error register: {}", e.register_text())
             }
             RxMessageAdditional::Heartbeat(h) => {
                 format!("Heartbeat: {} (0x{:02X})", h.state.as_str(), h.raw_value)
             }
+            RxMessageAdditional::Signals(d) => d
+                .signals
+                .iter()
+                .map(|s| format!("{s} (raw 0x{:X})", s.raw))
+                .collect::<Vec<_>>()
+                .join("\n"),
             RxMessageAdditional::None => String::new(),
         }
     }
@@ -181,19 +233,20 @@ impl fmt::Display for RxMessageAdditional {
             RxMessageAdditional::SdoTx(server_response) => {
                 write!(f, "{}", Self::from_server_resp_data(&server_response.resp))
             }
+            RxMessageAdditional::SdoObject(access) => write!(f, "{access}"),
+            RxMessageAdditional::SdoAbort {
+                index,
+                subindex,
+                code,
+            } => write!(f, "{}", sdo::abort_text(*index, *subindex, *code)),
             RxMessageAdditional::Nmt(n) => {
                 write!(f, "{:?} node_id: {}", n.command_specifier, n.node_id)
             }
-            RxMessageAdditional::Emcy(n) => {
-                write!(
-                    f,
-                    "{:?} {:X} reg {:X} data {:X?}",
-                    n.code, n.vendor_code, n.error_register, n.data
-                )
-            }
+            RxMessageAdditional::Emcy(e) => write!(f, "{e}"),
             RxMessageAdditional::Heartbeat(h) => {
                 write!(f, "State: {}", h.state)
             }
+            RxMessageAdditional::Signals(d) => write!(f, "{d}"),
             RxMessageAdditional::None => write!(f, ""),
         }
     }
@@ -202,7 +255,53 @@ impl fmt::Display for RxMessageAdditional {
 impl MessageCached {
     /// # Panics
     pub fn new(index: u64, msg: RxMessage) -> Self {
+        cia402::learn(&msg);
         let parsed = RxMessageParsed::new(msg);
+        let mut cached = Self {
+            index,
+            additional: Self::decode(index, &parsed),
+            msg: parsed,
+            cob_str: msg.cob_id_to_string(),
+            hex_str: msg.data_to_string(RxMessageToStringFormat::Hex),
+            bin_str: OnceLock::new(),
+            ascii_str: OnceLock::new(),
+            info_str: Arc::from(""),
+            interpreter: None,
+            script_str: None,
+            text_str: String::new(),
+            origin: None,
+            direction: Direction::Rx,
+            changed_bytes: 0,
+        };
+        cached.on_decoded();
+        cached
+    }
+
+    /// Formats the summary of the first matching interpreter, followed by the text of the
+    /// script if any, and the text matched by the filters.
+    fn on_decoded(&mut self) {
+        let interpreter = interpreter::first_match(self);
+        self.interpreter = interpreter.as_ref().map(|interpreter| interpreter.name());
+        let info = interpreter.map(|interpreter| interpreter.summary(self)).unwrap_or_default();
+        self.script_str = script::decode(self).map(Arc::from);
+        self.info_str = match &self.script_str {
+            Some(text) if info.is_empty() => text.clone(),
+            Some(text) => Arc::from(format!("{info}, {text}")),
+            None => Arc::from(info),
+        };
+        self.text_str = format!(
+            "{} {} {} {}",
+            self.cob_str,
+            self.msg.parsed_type.to_string(),
+            self.hex_str,
+            self.info_str
+        );
+    }
+
+    /// Decodes the protocol of the frame, then names the SDO objects with the EDS of the node
+    /// and decodes the other frames with the DBC file.
+    fn decode(index: u64, parsed: &RxMessageParsed) -> RxMessageAdditional {
+        let msg = parsed.msg;
         let mut dat = Cursor::new(msg.data);
 
         let additional = match parsed.parsed_type {
@@ -230,19 +329,19 @@ impl MessageCached {
                 }
             }
             RxMessageType::Emcy => {
-                if let Ok(d) = Emcy::read(&mut dat) {
-                    RxMessageAdditional::Emcy(d)
+                if let Some(e) = Emergency::parse(index, &msg) {
+                    RxMessageAdditional::Emcy(e)
                 } else {
                     RxMessageAdditional::None
                 }
             }
             RxMessageType::Guarding => {
                 // Parse Heartbeat message (1 byte containing NMT state)
-                if !msg.data.is_empty() {
+                if msg.data.is_empty() {
+                    RxMessageAdditional::None
+                } else {
                     let heartbeat = Heartbeat::from_byte(msg.data[0]);
                     RxMessageAdditional::Heartbeat(heartbeat)
-                } else {
-                    RxMessageAdditional::None
                 }
             }
             RxMessageType::Lss
@@ -251,29 +350,134 @@ impl MessageCached {
             | RxMessageType::Unknown => RxMessageAdditional::None,
         };
 
-        Self {
-            index,
-            msg: parsed,
-            additional,
-            cob_str: msg.cob_id_to_string(),
-            hex_str: msg.data_to_string(RxMessageToStringFormat::Hex),
-            bin_str: msg.data_to_string(RxMessageToStringFormat::Binary),
-            ascii_str: msg.data_to_string(RxMessageToStringFormat::Ascii),
+        // Aborts are decoded apart, the SDO parser rejects unknown abort codes.
+        let additional = match (parsed.parsed_type, sdo::parse_abort(&msg.data)) {
+            (RxMessageType::SdoTx | RxMessageType::SdoRx, Some((index, subindex, code))) => {
+                RxMessageAdditional::SdoAbort {
+                    index,
+                    subindex,
+                    code,
+                }
+            }
+            _ => additional,
+        };
+        let eds = parsed.parsed_node_id.and_then(eds::get);
+        let access = match (&additional, &eds) {
+            (RxMessageAdditional::SdoRx(request), Some(eds)) => {
+                sdo::SdoAccess::from_request(parsed.parsed_node_id.unwrap_or(0), request, eds)
+            }
+            (RxMessageAdditional::SdoTx(response), Some(eds)) => {
+                sdo::SdoAccess::from_response(parsed.parsed_node_id.unwrap_or(0), response, eds)
+            }
+            _ => None,
+        };
+        match (access, additional) {
+            (Some(access), _) => RxMessageAdditional::SdoObject(access),
+            // The DBC decodes the frames CANopen doesn't, PDOs most of all.
+            (None, RxMessageAdditional::None) => {
+                dbc::decode(&msg).map_or(RxMessageAdditional::None, RxMessageAdditional::Signals)
+            }
+            (None, additional) => additional,
         }
     }
 
+    /// Payload in `format`, the binary and ASCII ones are formatted on first use only.
+    ///
     /// # Panics
     pub fn get_by_format(&self, format: RxMessageToStringFormat) -> &str {
         assert_ne!(format, RxMessageToStringFormat::Utf8);
         match format {
-            RxMessageToStringFormat::Binary => &self.bin_str,
-            RxMessageToStringFormat::Ascii => &self.ascii_str,
+            RxMessageToStringFormat::Binary | RxMessageToStringFormat::Ascii => {
+                self.cached_format(format)
+            }
             RxMessageToStringFormat::Hex => &self.hex_str,
             RxMessageToStringFormat::Utf8 => "utf8 not supported",
         }
     }
 
+    /// Same as [`Self::get_by_format`], shared instead of borrowed.
+    ///
+    /// # Panics
+    pub fn formatted(&self, format: RxMessageToStringFormat) -> Arc<str> {
+        match format {
+            RxMessageToStringFormat::Binary | RxMessageToStringFormat::Ascii => {
+                self.cached_format(format).clone()
+            }
+            format => Arc::from(self.get_by_format(format)),
+        }
+    }
+
+    fn cached_format(&self, format: RxMessageToStringFormat) -> &Arc<str> {
+        let cache = if format == RxMessageToStringFormat::Binary {
+            &self.bin_str
+        } else {
+            &self.ascii_str
+        };
+        cache.get_or_init(|| Arc::from(self.msg.msg.data_to_string(format)))
+    }
+
+    /// Decoded info, see [`RxMessageAdditional`].
+    pub fn info(&self) -> &Arc<str> {
+        &self.info_str
+    }
+
+    /// Text of the script, without the summary of the interpreter.
+    pub fn script_info(&self) -> Option<&str> {
+        self.script_str.as_deref()
+    }
+
+    /// Decodes the message again after a DBC or EDS file was loaded or unloaded.
+    pub fn redecode(&mut self) {
+        self.additional = Self::decode(self.index, &self.msg);
+        self.on_decoded();
+    }
+
     pub fn get_timestamp(&self) -> Instant {
         self.msg.msg.timestamp
     }
+
+    /// Approximate memory occupied by the message including its cached strings, in bytes.
+    pub fn memory_usage(&self) -> usize {
+        let cached = |cache: &OnceLock<Arc<str>>| cache.get().map_or(0, |text| text.len());
+        std::mem::size_of::<Self>()
+            + self.cob_str.capacity()
+            + self.hex_str.capacity()
+            + cached(&self.bin_str)
+            + cached(&self.ascii_str)
+            + self.info_str.len()
+            + self.script_str.as_ref().map_or(0, |text| text.len())
+            + self.text_str.capacity()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MessageCached;
+    use oze_canopen::canopen::{RxMessage, RxMessageToStringFormat};
+    use std::sync::Arc;
+    use tokio::time::Instant;
+
+    #[test]
+    fn test_cached_formats() {
+        let msg = MessageCached::new(
+            0,
+            RxMessage {
+                timestamp: Instant::now(),
+                cob_id: 0x705,
+                data: [0x05, b'A', 0, 0, 0, 0, 0, 0],
+                dlc: 2,
+            },
+        );
+        assert_eq!(msg.info().as_ref(), "State: Operational");
+        assert!(msg.text_str.ends_with("05 41 State: Operational"));
+
+        // Only the formats asked for take memory.
+        let bare = msg.memory_usage();
+        let bin = msg.formatted(RxMessageToStringFormat::Binary);
+        assert_eq!(msg.memory_usage(), bare + bin.len());
+        assert!(Arc::ptr_eq(&bin, &msg.formatted(RxMessageToStringFormat::Binary)));
+        assert_eq!(msg.get_by_format(RxMessageToStringFormat::Binary), bin.as_ref());
+        assert_eq!(msg.get_by_format(RxMessageToStringFormat::Hex), msg.hex_str);
+        assert!(Arc::ptr_eq(&bin, &msg.clone().formatted(RxMessageToStringFormat::Binary)));
+    }
 }
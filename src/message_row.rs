@@ -1,76 +1,313 @@
-use crate::message_cached::MessageCached;
+use crate::{
+    annotations::UserMarker,
+    byte_changes,
+    columns::{Column, Columns},
+    data_format::DataFormat,
+    driver::SessionMarker,
+    interpreter::InterpreterSettings,
+    message_cached::{Direction, MessageCached},
+    row_colors::RowColors,
+    theme::OZON_PINK,
+};
+use chrono::{DateTime, Local};
 use oze_canopen::canopen::RxMessageToStringFormat;
+use serde::{Deserialize, Serialize};
+use std::{cell::RefCell, time::Duration};
 use tokio::time::Instant;
 
+/// What the timestamp column shows.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TimestampMode {
+    /// Wall-clock time of reception.
+    Absolute,
+    /// Seconds since the capture start.
+    #[default]
+    Relative,
+    /// Seconds since the previous row.
+    Delta,
+}
+
+impl TimestampMode {
+    pub const ALL: [Self; 3] = [Self::Absolute, Self::Relative, Self::Delta];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Absolute => "abs",
+            Self::Relative => "rel",
+            Self::Delta => "Δt",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Self::Absolute => "Show the wall-clock time of every message",
+            Self::Relative => "Show the time since the capture start",
+            Self::Delta => "Show the time since the previous row",
+        }
+    }
+
+    /// Title of the column, padded to the width of the values.
+    fn header(self) -> &'static str {
+        match self {
+            Self::Absolute => "     Time      ",
+            Self::Relative => "     Timestamp",
+            Self::Delta => "   Time delta ",
+        }
+    }
+}
+
+/// Converts a reception time to the wall clock, `start_wall` being the wall-clock time at `start`.
+pub fn wall_clock(start: Instant, start_wall: DateTime<Local>, time: Instant) -> DateTime<Local> {
+    match time.checked_duration_since(start) {
+        Some(after) => start_wall + after,
+        None => start_wall - start.duration_since(time),
+    }
+}
+
 #[derive(Debug)]
 pub struct MessageRow {
     pub start_time: Instant,
-    pub format: RxMessageToStringFormat,
+    /// Wall-clock time at `start_time`.
+    pub start_wall: DateTime<Local>,
+    pub timestamp_mode: TimestampMode,
+    pub format: DataFormat,
+    /// Fade of the bytes changed from the previous frame, `None` to not highlight them.
+    pub highlight: Option<Duration>,
+    pub columns: Columns,
+    pub colors: RowColors,
+    /// Interpreters whose summary is left out of the decode column.
+    pub interpreters: InterpreterSettings,
+    /// Columns changed from the header context menu, see `take_edited_columns`.
+    edited_columns: RefCell<Option<Columns>>,
 }
 
 impl Default for MessageRow {
     fn default() -> Self {
         Self {
             start_time: Instant::now(),
-            format: RxMessageToStringFormat::Hex,
+            start_wall: Local::now(),
+            timestamp_mode: TimestampMode::default(),
+            format: DataFormat::default(),
+            highlight: None,
+            columns: Columns::default(),
+            colors: RowColors::default(),
+            interpreters: InterpreterSettings::default(),
+            edited_columns: RefCell::default(),
         }
     }
 }
 
 impl MessageRow {
+    /// Shows the titles of the visible columns, a right click on them opens the column chooser.
     pub fn header(&self, ui: &mut egui::Ui) {
-        self.header_custom(ui, "     Timestamp");
+        for setting in self.columns.visible() {
+            let title = match setting.column {
+                Column::Timestamp => self.timestamp_mode.header().to_owned(),
+                Column::Data => self.format.header(),
+                column => column.name().to_owned(),
+            };
+            Self::cell(ui, setting.width, |ui| ui.label(title))
+                .interact(egui::Sense::click())
+                .on_hover_text("Right click to choose the columns")
+                .context_menu(|ui| {
+                    let mut columns = self.columns.clone();
+                    if columns.ui(ui) {
+                        *self.edited_columns.borrow_mut() = Some(columns);
+                    }
+                });
+        }
     }
 
-    pub fn header_custom(&self, ui: &mut egui::Ui, time: &str) {
-        ui.label(time);
-        ui.label("COB ID");
-        ui.label(match self.format {
-            RxMessageToStringFormat::Binary => {
-                " Binary data                                                            "
-                //00000000 00000000 00000000 00000000 00000000 00000000 00000000 00000000
-            }
-            RxMessageToStringFormat::Hex => {
-                " Hex data               "
-                //00 00 00 00 00 00 00 00
+    /// Columns changed from the header since the last call.
+    pub fn take_edited_columns(&self) -> Option<Columns> {
+        self.edited_columns.borrow_mut().take()
+    }
+
+    /// Shows a message, `previous` is the time of the previous row used by the delta mode.
+    /// Returns the response of the cell which selects the row, the timestamp if it is shown.
+    pub fn message(
+        &self,
+        ui: &mut egui::Ui,
+        d: &MessageCached,
+        previous: Option<Instant>,
+        selected: bool,
+    ) -> egui::Response {
+        let handle = if self.columns.is_visible(Column::Timestamp) {
+            Some(Column::Timestamp)
+        } else {
+            self.columns.visible().next().map(|s| s.column)
+        };
+        let Some(handle) = handle else {
+            return ui.selectable_label(selected, "");
+        };
+
+        // The colour of the row is the text colour of its cells, restored after the last one.
+        let text_color = ui.visuals().override_text_color;
+        if let Some(color) = self.colors.color(d) {
+            ui.visuals_mut().override_text_color = Some(color);
+        }
+        let mut response = None;
+        for setting in self.columns.visible() {
+            let column = setting.column;
+            let cell = Self::cell(ui, setting.width, |ui| {
+                if column == handle {
+                    ui.selectable_label(selected, self.cell_text(column, d, previous))
+                } else if column == Column::Data {
+                    self.data_label(ui, d).on_hover_ui(|ui| {
+                        // data in all formats on hover
+                        let bin = d.get_by_format(RxMessageToStringFormat::Binary);
+                        let ascii = d.get_by_format(RxMessageToStringFormat::Ascii);
+                        ui.label(format!("HEX:   {}", d.hex_str));
+                        ui.label(format!("BIN:   {bin}"));
+                        ui.label(format!("ASCII: {ascii}"));
+                    })
+                } else if column == Column::Info {
+                    let label = ui.label(self.cell_text(column, d, previous));
+                    if self.interpreters.first_match(d).is_some() {
+                        label.on_hover_text_at_pointer(d.additional.get_tooltip())
+                    } else {
+                        label
+                    }
+                } else {
+                    ui.label(self.cell_text(column, d, previous))
+                }
+            });
+            if column == handle {
+                response = Some(cell);
             }
-            RxMessageToStringFormat::Ascii => "ASCII data",
-            RxMessageToStringFormat::Utf8 => "UTF8 data",
-        });
+        }
+        ui.visuals_mut().override_text_color = text_color;
+        response.unwrap_or_else(|| ui.label(""))
+    }
+
+    /// The visible columns of the message as one line of text, as copied to the clipboard.
+    pub fn text(&self, d: &MessageCached, previous: Option<Instant>) -> String {
+        self.columns
+            .visible()
+            .map(|setting| self.cell_text(setting.column, d, previous))
+            .map(|text| text.trim().to_owned())
+            .filter(|text| !text.is_empty())
+            .collect::<Vec<_>>()
+            .join("  ")
+    }
 
-        ui.label("Packet type");
-        ui.label("Node ID");
-        ui.label("Info");
+    fn cell_text(&self, column: Column, d: &MessageCached, previous: Option<Instant>) -> String {
+        match column {
+            Column::Index => d.index.to_string(),
+            Column::Timestamp => self.timestamp(d.get_timestamp(), previous),
+            Column::CobId => self.cob(d),
+            Column::Dlc => d.msg.msg.dlc.to_string(),
+            Column::Direction => Self::direction(d),
+            Column::Data => self.format.format(d).into_owned(),
+            Column::Type => d.msg.parsed_type.to_string().to_owned(),
+            Column::NodeId => d
+                .msg
+                .parsed_node_id
+                .map_or_else(|| "   ".to_owned(), |node_id| format!("{node_id:3}")),
+            Column::Info => self.interpreters.info(d).into_owned(),
+        }
     }
 
-    pub fn message(&self, ui: &mut egui::Ui, d: &MessageCached) {
-        self.message_custom_timestamp(ui, d, &self.start_time);
+    /// Gateway side and `TX` for the frames sent by the viewer, empty for the frames received
+    /// outside of gateway mode.
+    fn direction(d: &MessageCached) -> String {
+        let side = d.origin.map(|side| side.to_string());
+        let tx = (d.direction == Direction::Tx).then(|| Direction::Tx.as_str().to_owned());
+        side.into_iter().chain(tx).collect::<Vec<_>>().join(" ")
     }
 
-    pub fn message_custom_timestamp(&self, ui: &mut egui::Ui, d: &MessageCached, time: &Instant) {
-        let desc = d.msg.parsed_type.to_string();
+    /// COB-ID prefixed by the direction unless it has its own column.
+    fn cob(&self, d: &MessageCached) -> String {
+        let direction = Self::direction(d);
+        if direction.is_empty() || self.columns.is_visible(Column::Direction) {
+            d.cob_str.clone()
+        } else {
+            format!("{direction} {}", d.cob_str)
+        }
+    }
 
-        let time = d.get_timestamp().duration_since(*time).as_secs_f32();
-        let time = format!("{time:.6}");
-        let cob = &d.cob_str;
-        let data = d.get_by_format(self.format);
-        let node_id = if let Some(node_id) = d.msg.parsed_node_id {
-            format!("{node_id:3}")
+    /// Adds a grid cell at least `width` wide, 0 to fit the content.
+    fn cell(
+        ui: &mut egui::Ui,
+        width: f32,
+        add: impl FnOnce(&mut egui::Ui) -> egui::Response,
+    ) -> egui::Response {
+        if width > 0.0 {
+            ui.scope(|ui| {
+                ui.set_min_width(width);
+                add(ui)
+            })
+            .inner
         } else {
-            "   ".to_owned()
-        };
+            add(ui)
+        }
+    }
+
+    fn timestamp(&self, time: Instant, previous: Option<Instant>) -> String {
+        match self.timestamp_mode {
+            TimestampMode::Absolute | TimestampMode::Relative => self.time(time),
+            TimestampMode::Delta => previous.map_or_else(
+                || "—".to_owned(),
+                |previous| format!("+{:.6}", time.duration_since(previous).as_secs_f64()),
+            ),
+        }
+    }
+
+    /// Wall-clock time in the absolute mode, otherwise the time since the capture start.
+    fn time(&self, time: Instant) -> String {
+        if self.timestamp_mode == TimestampMode::Absolute {
+            wall_clock(self.start_time, self.start_wall, time)
+                .format("%H:%M:%S%.6f")
+                .to_string()
+        } else {
+            format!("{:.6}", time.duration_since(self.start_time).as_secs_f64())
+        }
+    }
+
+    /// Counter of a run of identical frames with the time of its first and last frame.
+    pub fn repeat_text(&self, count: usize, first: Instant, last: Instant) -> String {
+        format!("×{count}  {} … {}", self.time(first), self.time(last))
+    }
+
+    /// Cell after the last column of a collapsed run of identical frames.
+    pub fn repeat(&self, ui: &mut egui::Ui, count: usize, first: Instant, last: Instant) {
+        ui.colored_label(OZON_PINK, self.repeat_text(count, first, last))
+            .on_hover_text(format!("{count} identical frames, first … last"));
+    }
+
+    fn data_label(&self, ui: &mut egui::Ui, d: &MessageCached) -> egui::Response {
+        let data = self.format.format(d);
+        match self.highlight {
+            Some(fade) if d.changed_bytes != 0 => {
+                let age = d.get_timestamp().elapsed();
+                let intensity = byte_changes::fade_intensity(age, fade);
+                ui.label(byte_changes::data_job(ui, d, self.format, |i| {
+                    if d.changed_bytes & (1 << i) == 0 {
+                        0.0
+                    } else {
+                        intensity
+                    }
+                }))
+            }
+            _ => ui.label(data),
+        }
+    }
+
+    /// Row separating two sessions.
+    pub fn session_marker(&self, ui: &mut egui::Ui, marker: &SessionMarker) {
+        self.marker(ui, OZON_PINK, &marker.text);
+    }
 
-        ui.label(time);
-        ui.label(cob);
-        ui.label(data).on_hover_ui(|ui| {
-            // data in all formats on hover
-            ui.label(format!("HEX:   {}", d.hex_str));
-            ui.label(format!("BIN:   {}", d.bin_str));
-            ui.label(format!("ASCII: {}", d.ascii_str));
-        });
-        ui.label(desc);
-        ui.label(node_id);
-        ui.label(d.additional.to_string())
-            .on_hover_text_at_pointer(d.additional.get_tooltip());
+    /// Row of a marker inserted by the user.
+    pub fn user_marker(&self, ui: &mut egui::Ui, marker: &UserMarker) {
+        self.marker(ui, ui.visuals().warn_fg_color, &marker.to_string());
+    }
+
+    /// The text goes in the last column which has no width limit.
+    fn marker(&self, ui: &mut egui::Ui, color: egui::Color32, text: &str) {
+        for _ in 1..self.columns.visible().count() {
+            ui.colored_label(color, "---");
+        }
+        ui.colored_label(color, text);
     }
 }
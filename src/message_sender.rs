@@ -1,7 +1,18 @@
-use crate::driver::WriteCommand;
+use crate::{
+    auto_start::{AutoStart, AutoStartEditor},
+    driver::{WriteCommand, WriteRequest, WriteResult},
+    eds::{self, Eds},
+    pdo_config::{PdoEditor, PdoParameters, MAX_MAPPED},
+    periodic::PeriodicEntry,
+};
 use egui::{ComboBox, TextEdit, Ui};
 use oze_canopen::proto::nmt::NmtCommandSpecifier;
-use tokio::sync::mpsc;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::sync::{
+    mpsc,
+    oneshot::{self, error::TryRecvError},
+};
 
 /// Panel for sending CAN messages
 #[derive(Debug)]
@@ -13,6 +24,10 @@ pub struct MessageSender {
     // NMT parameters
     nmt_node_id: String,
     nmt_command: NmtCommandSpecifier,
+    /// Sent to every node when the viewer exits.
+    exit_nmt: Option<NmtCommandSpecifier>,
+    /// NMT Start sent by the driver to the nodes seen booting.
+    auto_start: AutoStartEditor,
     
     // Raw/PDO parameters
     raw_cob_id: String,
@@ -27,11 +42,30 @@ pub struct MessageSender {
     
     // PDO Config parameters
     pdo_config_node_id: String,
+    pdo_editor: PdoEditor,
+
+    // Periodic transmission parameters
+    periodic_ms: String,
+    next_periodic_id: u32,
     
-    write_sender: mpsc::Sender<WriteCommand>,
+    write_sender: mpsc::Sender<WriteRequest>,
+    /// Result of the last command, until the driver reports it.
+    pending: Option<oneshot::Receiver<WriteResult>>,
+    status: SendStatus,
+    /// Invalid inputs and failed commands, until the GUI takes them.
+    errors: Vec<String>,
+}
+
+/// Status of the last command sent from the panel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum SendStatus {
+    Idle,
+    Pending(String),
+    Done(String, WriteResult),
+    Failed(String, String),
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 enum Cia402Object {
     Custom,
     Controlword,
@@ -43,7 +77,7 @@ enum Cia402Object {
     ProfileDeceleration,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 enum MessageType {
     Sync,
     Nmt,
@@ -77,12 +111,77 @@ impl MessageType {
     }
 }
 
-impl MessageSender {
-    pub fn new(write_sender: mpsc::Sender<WriteCommand>) -> Self {
+/// Serialization of [`NmtCommandSpecifier`], which has none.
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "NmtCommandSpecifier")]
+pub(crate) enum NmtCommand {
+    #[serde(alias = "start")]
+    StartRemoteNode,
+    #[serde(alias = "stop")]
+    StopRemoteNode,
+    #[serde(alias = "pre_operational")]
+    EnterPreOperational,
+    #[serde(alias = "reset_node")]
+    ResetNode,
+    #[serde(alias = "reset_communication")]
+    ResetCommunication,
+}
+
+/// Serialization of an optional [`NmtCommandSpecifier`].
+mod optional_nmt {
+    use super::NmtCommand;
+    use oze_canopen::proto::nmt::NmtCommandSpecifier;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize, Deserialize)]
+    struct Command(#[serde(with = "NmtCommand")] NmtCommandSpecifier);
+
+    // The signature serde expects.
+    #[allow(clippy::ref_option, clippy::trivially_copy_pass_by_ref)]
+    pub fn serialize<S: Serializer>(
+        command: &Option<NmtCommandSpecifier>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        command.map(Command).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<NmtCommandSpecifier>, D::Error> {
+        Ok(Option::<Command>::deserialize(deserializer)?.map(|command| command.0))
+    }
+}
+
+/// Values last used in the sender, restored at startup.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SenderSettings {
+    selected_type: MessageType,
+    nmt_node_id: String,
+    #[serde(with = "NmtCommand")]
+    nmt_command: NmtCommandSpecifier,
+    #[serde(default, with = "optional_nmt")]
+    exit_nmt: Option<NmtCommandSpecifier>,
+    #[serde(default)]
+    auto_start: AutoStartEditor,
+    raw_cob_id: String,
+    raw_data: String,
+    sdo_node_id: String,
+    sdo_index: String,
+    sdo_subindex: String,
+    sdo_data: String,
+    sdo_preset: Cia402Object,
+    pdo_config_node_id: String,
+    periodic_ms: String,
+}
+
+impl Default for SenderSettings {
+    fn default() -> Self {
         Self {
             selected_type: MessageType::Sync,
             nmt_node_id: String::from("1"),
             nmt_command: NmtCommandSpecifier::StartRemoteNode,
+            exit_nmt: None,
+            auto_start: AutoStartEditor::default(),
             raw_cob_id: String::from("180"),
             raw_data: String::from("00 00 00 00 00 00 00 00"),
             sdo_node_id: String::from("1"),
@@ -91,11 +190,219 @@ impl MessageSender {
             sdo_data: String::from("06 00"),
             sdo_preset: Cia402Object::Controlword,
             pdo_config_node_id: String::from("1"),
+            periodic_ms: String::from("100"),
+        }
+    }
+}
+
+impl MessageSender {
+    pub fn new(write_sender: mpsc::Sender<WriteRequest>) -> Self {
+        let mut sender = Self {
+            selected_type: MessageType::Sync,
+            nmt_node_id: String::new(),
+            nmt_command: NmtCommandSpecifier::StartRemoteNode,
+            exit_nmt: None,
+            auto_start: AutoStartEditor::default(),
+            raw_cob_id: String::new(),
+            raw_data: String::new(),
+            sdo_node_id: String::new(),
+            sdo_index: String::new(),
+            sdo_subindex: String::new(),
+            sdo_data: String::new(),
+            sdo_preset: Cia402Object::Controlword,
+            pdo_config_node_id: String::new(),
+            pdo_editor: PdoEditor::default(),
+            periodic_ms: String::new(),
+            next_periodic_id: 0,
             write_sender,
+            pending: None,
+            status: SendStatus::Idle,
+            errors: Vec::new(),
+        };
+        sender.apply_settings(SenderSettings::default());
+        sender
+    }
+
+    pub fn settings(&self) -> SenderSettings {
+        SenderSettings {
+            selected_type: self.selected_type,
+            nmt_node_id: self.nmt_node_id.clone(),
+            nmt_command: self.nmt_command,
+            exit_nmt: self.exit_nmt,
+            auto_start: self.auto_start.clone(),
+            raw_cob_id: self.raw_cob_id.clone(),
+            raw_data: self.raw_data.clone(),
+            sdo_node_id: self.sdo_node_id.clone(),
+            sdo_index: self.sdo_index.clone(),
+            sdo_subindex: self.sdo_subindex.clone(),
+            sdo_data: self.sdo_data.clone(),
+            sdo_preset: self.sdo_preset,
+            pdo_config_node_id: self.pdo_config_node_id.clone(),
+            periodic_ms: self.periodic_ms.clone(),
         }
     }
+
+    /// Restores the values of [`Self::settings`].
+    pub fn apply_settings(&mut self, settings: SenderSettings) {
+        let SenderSettings {
+            selected_type,
+            nmt_node_id,
+            nmt_command,
+            exit_nmt,
+            auto_start,
+            raw_cob_id,
+            raw_data,
+            sdo_node_id,
+            sdo_index,
+            sdo_subindex,
+            sdo_data,
+            sdo_preset,
+            pdo_config_node_id,
+            periodic_ms,
+        } = settings;
+        self.selected_type = selected_type;
+        self.nmt_node_id = nmt_node_id;
+        self.nmt_command = nmt_command;
+        self.exit_nmt = exit_nmt;
+        self.auto_start = auto_start;
+        self.raw_cob_id = raw_cob_id;
+        self.raw_data = raw_data;
+        self.sdo_node_id = sdo_node_id;
+        self.sdo_index = sdo_index;
+        self.sdo_subindex = sdo_subindex;
+        self.sdo_data = sdo_data;
+        self.sdo_preset = sdo_preset;
+        self.pdo_config_node_id = pdo_config_node_id;
+        self.periodic_ms = periodic_ms;
+    }
     
-    pub fn ui(&mut self, ui: &mut Ui) {
+    /// Queues a command for the driver, its result is shown at the bottom of the panel.
+    pub fn send(&mut self, cmd: WriteCommand) {
+        if let Err(e) = validate(&cmd) {
+            self.invalid(e);
+            return;
+        }
+        let name = format!("{cmd:?}");
+        let (request, result) = WriteRequest::new(cmd);
+        if let Err(e) = self.write_sender.try_send(request) {
+            self.pending = None;
+            self.errors.push(format!("{name} failed: {e}"));
+            self.status = SendStatus::Failed(name, e.to_string());
+        } else {
+            self.pending = Some(result);
+            self.status = SendStatus::Pending(name);
+        }
+    }
+
+    /// NMT sent to every node when the viewer exits, if any.
+    pub fn exit_nmt(&self) -> Option<NmtCommandSpecifier> {
+        self.exit_nmt
+    }
+
+    /// Auto-start handed to the driver, `None` when it is off.
+    pub fn auto_start(&self) -> Option<AutoStart> {
+        self.auto_start.config()
+    }
+
+    /// What the auto-start does, for the indicator of the top bar.
+    pub fn auto_start_summary(&self) -> String {
+        self.auto_start.summary()
+    }
+
+    /// Name of the command waiting for its result, e.g. an SDO sequence.
+    pub fn pending(&self) -> Option<&str> {
+        match (&self.pending, &self.status) {
+            (Some(_), SendStatus::Pending(name)) => Some(name),
+            _ => None,
+        }
+    }
+
+    /// Logs an invalid input, reported to the user by the GUI.
+    fn invalid(&mut self, text: &str) {
+        log::error!("{text}");
+        self.errors.push(text.to_owned());
+    }
+
+    /// Invalid inputs and failed commands since the last call.
+    pub fn take_errors(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.errors)
+    }
+
+    fn poll_status(&mut self) {
+        let Some(pending) = &mut self.pending else {
+            return;
+        };
+        let name = match &self.status {
+            SendStatus::Pending(name) => name.clone(),
+            _ => String::new(),
+        };
+        match pending.try_recv() {
+            Ok(result) => {
+                if let Err(e) = &result {
+                    self.errors.push(format!("{name} failed: {e}"));
+                }
+                self.status = SendStatus::Done(name, result);
+            }
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Closed) => {
+                self.errors.push(format!("{name} failed: driver stopped"));
+                self.status = SendStatus::Failed(name, "driver stopped".to_owned());
+            }
+        }
+        self.pending = None;
+    }
+
+    fn show_status(&self, ui: &mut Ui) {
+        match &self.status {
+            SendStatus::Idle => {}
+            SendStatus::Pending(name) => {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label(name);
+                });
+            }
+            SendStatus::Done(name, Ok(outcome)) => {
+                ui.colored_label(egui::Color32::GREEN, format!("✔ {outcome}"))
+                    .on_hover_text(name);
+            }
+            SendStatus::Done(name, Err(e)) => {
+                ui.colored_label(egui::Color32::RED, format!("❌ {e}"))
+                    .on_hover_text(name);
+            }
+            SendStatus::Failed(name, e) => {
+                ui.colored_label(egui::Color32::RED, format!("❌ {e}"))
+                    .on_hover_text(name);
+            }
+        }
+    }
+
+    /// Prefills the node ID of the NMT, SDO and PDO config forms.
+    pub fn set_node_id(&mut self, node_id: u8) {
+        self.nmt_node_id = node_id.to_string();
+        self.sdo_node_id = node_id.to_string();
+        self.pdo_config_node_id = node_id.to_string();
+    }
+
+    /// Prefills the SDO form with an entry of a node and its value, ready to be written.
+    pub fn edit_sdo(&mut self, node_id: u8, index: u16, subindex: u8, data: &[u8]) {
+        self.selected_type = MessageType::Sdo;
+        self.sdo_preset = Cia402Object::Custom;
+        self.sdo_node_id = node_id.to_string();
+        self.sdo_index = format!("{index:04X}");
+        self.sdo_subindex = format!("{subindex:02X}");
+        self.sdo_data = data.iter().map(|byte| format!("{byte:02X}")).collect::<Vec<_>>().join(" ");
+    }
+
+    /// Opens the mapping editor with the parameters of a PDO read from a node.
+    pub fn edit_pdo(&mut self, node_id: u8, parameters: &PdoParameters) {
+        self.selected_type = MessageType::PdoConfig;
+        self.pdo_config_node_id = node_id.to_string();
+        self.pdo_editor.load(parameters);
+    }
+
+    /// Shows the panel, `periodic` are the periodic transmissions run by the driver.
+    pub fn ui(&mut self, ui: &mut Ui, periodic: &[PeriodicEntry]) {
+        self.poll_status();
         ui.group(|ui| {
             ui.heading("📤 Send CAN Message");
             ui.separator();
@@ -135,16 +442,21 @@ impl MessageSender {
                     self.show_raw_ui(ui);
                 }
             }
+
+            self.show_periodic_table(ui, periodic);
+
+            ui.separator();
+            self.show_status(ui);
         });
     }
     
-    fn show_sync_ui(&self, ui: &mut Ui) {
+    fn show_sync_ui(&mut self, ui: &mut Ui) {
         ui.label("SYNC message (COB-ID: 0x080)");
         ui.label("No parameters required");
         ui.separator();
         
         if ui.button("📤 Send SYNC").clicked() {
-            let _ = self.write_sender.try_send(WriteCommand::SendSync);
+            self.send(WriteCommand::SendSync);
         }
     }
     
@@ -173,18 +485,30 @@ impl MessageSender {
         
         if ui.button("📤 Send NMT").clicked() {
             if let Ok(node_id) = self.nmt_node_id.parse::<u8>() {
-                if node_id <= 127 {
-                    let _ = self.write_sender.try_send(WriteCommand::SendNmt {
-                        node_id,
-                        command: self.nmt_command,
-                    });
-                } else {
-                    log::error!("Invalid node ID: must be 0-127");
-                }
+                self.send(WriteCommand::SendNmt {
+                    node_id,
+                    command: self.nmt_command,
+                });
             } else {
-                log::error!("Invalid node ID format");
+                self.invalid("Invalid node ID format");
             }
         }
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.label("On exit:");
+            ComboBox::from_id_salt("exit_nmt_combo")
+                .selected_text(self.exit_nmt.map_or("Nothing".to_owned(), |command| format!("{command:?} all")))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.exit_nmt, None, "Nothing");
+                    ui.selectable_value(&mut self.exit_nmt, Some(NmtCommandSpecifier::StopRemoteNode), "Stop all nodes (0x02)");
+                    ui.selectable_value(&mut self.exit_nmt, Some(NmtCommandSpecifier::EnterPreOperational), "Pre-operational all nodes (0x80)");
+                    ui.selectable_value(&mut self.exit_nmt, Some(NmtCommandSpecifier::ResetNode), "Reset all nodes (0x81)");
+                })
+                .response
+                .on_hover_text("NMT broadcast before the viewer exits or the session is closed");
+        });
+        self.auto_start.ui(ui);
     }
     
     fn show_pdo_ui(&mut self, ui: &mut Ui) {
@@ -208,18 +532,15 @@ impl MessageSender {
         if ui.button("📤 Send PDO").clicked() {
             if let Ok(cob_id) = u32::from_str_radix(&self.raw_cob_id, 16) {
                 if let Ok(data) = parse_hex_data(&self.raw_data) {
-                    if data.len() <= 8 {
-                        let _ = self.write_sender.try_send(WriteCommand::SendPdo { cob_id, data });
-                    } else {
-                        log::error!("Data too long: max 8 bytes");
-                    }
+                    self.send(WriteCommand::SendPdo { cob_id, data });
                 } else {
-                    log::error!("Invalid data format");
+                    self.invalid("Invalid data format");
                 }
             } else {
-                log::error!("Invalid COB-ID format");
+                self.invalid("Invalid COB-ID format");
             }
         }
+        self.show_periodic_ui(ui);
     }
     
     fn show_raw_ui(&mut self, ui: &mut Ui) {
@@ -243,20 +564,78 @@ impl MessageSender {
         if ui.button("📤 Send Raw CAN").clicked() {
             if let Ok(cob_id) = u32::from_str_radix(&self.raw_cob_id, 16) {
                 if let Ok(data) = parse_hex_data(&self.raw_data) {
-                    if data.len() <= 8 {
-                        let _ = self.write_sender.try_send(WriteCommand::SendRaw { cob_id, data });
-                    } else {
-                        log::error!("Data too long: max 8 bytes");
-                    }
+                    self.send(WriteCommand::SendRaw { cob_id, data });
                 } else {
-                    log::error!("Invalid data format");
+                    self.invalid("Invalid data format");
                 }
             } else {
-                log::error!("Invalid COB-ID format");
+                self.invalid("Invalid COB-ID format");
             }
         }
+        self.show_periodic_ui(ui);
     }
     
+    /// Starts sending the COB-ID and data of the PDO/Raw form periodically from the driver.
+    fn show_periodic_ui(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Period (ms):");
+            ui.add(TextEdit::singleline(&mut self.periodic_ms)
+                .desired_width(60.0)
+                .hint_text("100"));
+
+            if ui.button("⏱ Send periodically").clicked() {
+                let cob_id = u32::from_str_radix(&self.raw_cob_id, 16);
+                let data = parse_hex_data(&self.raw_data);
+                let period = self.periodic_ms.parse::<u64>();
+                match (cob_id, data, period) {
+                    (Ok(cob_id), Ok(data), Ok(period)) if data.len() <= 8 && period > 0 => {
+                        self.send(WriteCommand::AddPeriodic {
+                            id: self.next_periodic_id,
+                            cob_id,
+                            data,
+                            period: Duration::from_millis(period),
+                        });
+                        self.next_periodic_id += 1;
+                    }
+                    _ => self.invalid("Invalid COB-ID, data or period"),
+                }
+            }
+        });
+    }
+
+    fn show_periodic_table(&mut self, ui: &mut Ui, periodic: &[PeriodicEntry]) {
+        if periodic.is_empty() {
+            return;
+        }
+
+        ui.separator();
+        ui.label("⏱ Periodic transmissions:");
+        let mut to_remove = None;
+        egui::Grid::new("periodic_table").striped(true).show(ui, |ui| {
+            ui.label("🗑");
+            ui.label("COB-ID");
+            ui.label("Data");
+            ui.label("Period");
+            ui.label("Sent");
+            ui.end_row();
+
+            for entry in periodic {
+                if ui.button("❌").clicked() {
+                    to_remove = Some(entry.id);
+                }
+                ui.label(format!("{:03X}", entry.cob_id));
+                ui.label(format!("{:02X?}", entry.data));
+                ui.label(format!("{} ms", entry.period.as_millis()));
+                ui.label(entry.sent.to_string());
+                ui.end_row();
+            }
+        });
+
+        if let Some(id) = to_remove {
+            self.send(WriteCommand::RemovePeriodic { id });
+        }
+    }
+
     fn show_sdo_ui(&mut self, ui: &mut Ui) {
         // CIA 402 preset selector
         ui.horizontal(|ui| {
@@ -314,19 +693,7 @@ impl MessageSender {
                 .hint_text("1"));
         });
         
-        ui.horizontal(|ui| {
-            ui.label("Index (hex):");
-            ui.add(TextEdit::singleline(&mut self.sdo_index)
-                .desired_width(100.0)
-                .hint_text("6040"));
-        });
-        
-        ui.horizontal(|ui| {
-            ui.label("Subindex (hex):");
-            ui.add(TextEdit::singleline(&mut self.sdo_subindex)
-                .desired_width(60.0)
-                .hint_text("00"));
-        });
+        self.show_sdo_object_ui(ui);
         
         ui.horizontal(|ui| {
             ui.label("Data (hex, ≤4 bytes):");
@@ -338,36 +705,122 @@ impl MessageSender {
         ui.label("ℹ️ SDO TX COB-ID: 0x600 + Node ID");
         ui.separator();
         
-        if ui.button("📤 Send SDO Download").clicked() {
+        let (download, upload) = ui
+            .horizontal(|ui| {
+                let download = ui.button("📤 Send SDO Download").clicked();
+                let upload = ui
+                    .button("📥 Send SDO Upload")
+                    .on_hover_text("Read the object, the data field is ignored")
+                    .clicked();
+                (download, upload)
+            })
+            .inner;
+        if upload {
+            self.send_sdo_upload();
+        }
+        if download {
             if let Ok(node_id) = self.sdo_node_id.parse::<u8>() {
                 if let Ok(index) = u16::from_str_radix(&self.sdo_index, 16) {
                     if let Ok(subindex) = u8::from_str_radix(&self.sdo_subindex, 16) {
                         if let Ok(data) = parse_hex_data(&self.sdo_data) {
-                            if data.len() <= 4 {
-                                let _ = self.write_sender.try_send(WriteCommand::SendSdoDownload {
-                                    node_id,
-                                    index,
-                                    subindex,
-                                    data,
-                                });
-                            } else {
-                                log::error!("SDO data too long: max 4 bytes for expedited transfer");
-                            }
+                            self.send(WriteCommand::SendSdoDownload {
+                                node_id,
+                                index,
+                                subindex,
+                                data,
+                            });
                         } else {
-                            log::error!("Invalid data format");
+                            self.invalid("Invalid data format");
                         }
                     } else {
-                        log::error!("Invalid subindex format");
+                        self.invalid("Invalid subindex format");
                     }
                 } else {
-                    log::error!("Invalid index format");
+                    self.invalid("Invalid index format");
                 }
             } else {
-                log::error!("Invalid node ID format");
+                self.invalid("Invalid node ID format");
+            }
+        }
+    }
+    
+    fn send_sdo_upload(&mut self) {
+        match (
+            self.sdo_node_id.parse::<u8>(),
+            u16::from_str_radix(&self.sdo_index, 16),
+            u8::from_str_radix(&self.sdo_subindex, 16),
+        ) {
+            (Ok(node_id), Ok(index), Ok(subindex)) => {
+                self.send(WriteCommand::SendSdoUpload { node_id, index, subindex });
             }
+            (Err(_), _, _) => self.invalid("Invalid node ID format"),
+            (_, Err(_), _) => self.invalid("Invalid index format"),
+            (_, _, Err(_)) => self.invalid("Invalid subindex format"),
         }
     }
     
+    /// Index and subindex fields, named after the EDS of the node if there is one.
+    fn show_sdo_object_ui(&mut self, ui: &mut Ui) {
+        let node_eds = self.sdo_node_id.parse().ok().and_then(eds::get);
+        ui.horizontal(|ui| {
+            ui.label("Index (hex):");
+            let response = ui.add(TextEdit::singleline(&mut self.sdo_index)
+                .desired_width(100.0)
+                .hint_text("6040"));
+            if let Some(eds) = &node_eds {
+                self.show_index_suggestions(ui, &response, eds);
+            }
+        });
+        
+        ui.horizontal(|ui| {
+            ui.label("Subindex (hex):");
+            ui.add(TextEdit::singleline(&mut self.sdo_subindex)
+                .desired_width(60.0)
+                .hint_text("00"));
+        });
+
+        if let Some(eds) = &node_eds {
+            let index = u16::from_str_radix(&self.sdo_index, 16).ok();
+            let subindex = u8::from_str_radix(&self.sdo_subindex, 16).ok();
+            if let (Some(index), Some(subindex)) = (index, subindex) {
+                ui.label(eds.name(index, subindex).unwrap_or_else(|| "Not in the EDS".to_owned()));
+            }
+        }
+    }
+
+    /// Objects of the EDS of the node matching the index field, picking one fills the index
+    /// and subindex.
+    fn show_index_suggestions(&mut self, ui: &Ui, response: &egui::Response, eds: &Eds) {
+        let popup_id = ui.make_persistent_id("sdo_index_suggestions");
+        if response.gained_focus() || response.changed() {
+            ui.memory_mut(|m| m.open_popup(popup_id));
+        }
+        egui::popup_below_widget(
+            ui,
+            popup_id,
+            response,
+            egui::PopupCloseBehavior::CloseOnClickOutside,
+            |ui| {
+                ui.set_min_width(300.0);
+                let suggestions = eds.suggestions(&self.sdo_index, 12);
+                if suggestions.is_empty() {
+                    ui.label("No matching object");
+                }
+                for (index, subindex, name) in suggestions {
+                    if ui
+                        .selectable_label(false, format!("{index:04X}:{subindex:02X}  {name}"))
+                        .clicked()
+                    {
+                        self.sdo_index = format!("{index:04X}");
+                        self.sdo_subindex = format!("{subindex:02X}");
+                        self.sdo_preset = Cia402Object::Custom;
+                        ui.memory_mut(egui::Memory::close_popup);
+                    }
+                }
+            },
+        );
+    }
+
     fn show_pdo_config_ui(&mut self, ui: &mut Ui) {
         ui.label("🔧 Configuration PDO automatique");
         ui.separator();
@@ -390,7 +843,7 @@ impl MessageSender {
         ui.separator();
         
         ui.label("ℹ️ Séquence correcte envoyée:");
-        ui.label("1. NMT Pre-Operational");
+        ui.label("1. NMT Pre-Operational (attend le heartbeat)");
         ui.label("2. Disable TPDO1 (0x1800:01)");
         ui.label("3. Clear Mapping (0x1A00:00 = 0)");
         ui.label("4. Map Statusword (0x1A00:01 = 0x60410020)");
@@ -398,24 +851,85 @@ impl MessageSender {
         ui.label("6. Enable TPDO1 (0x1800:01)");
         ui.label("7. NMT Operational");
         ui.label("8. Set Transmission Type (0x1800:02 = 0x01)");
+        ui.label("Chaque écriture SDO est vérifiée et réessayée une fois, la séquence s'arrête à la première étape en échec.");
         
         ui.separator();
         
         if ui.button("🚀 Configurer TPDO1 Statusword").clicked() {
             if let Ok(node_id) = self.pdo_config_node_id.parse::<u8>() {
-                let _ = self.write_sender.try_send(WriteCommand::ConfigureTpdo1Statusword {
+                self.send(WriteCommand::ConfigureTpdo1Statusword {
                     node_id,
                 });
                 log::info!("Configuration TPDO1 lancée pour le node {}", node_id);
             } else {
-                log::error!("Invalid node ID format");
+                self.invalid("Invalid node ID format");
+            }
+        }
+
+        ui.separator();
+        self.show_pdo_editor(ui);
+    }
+
+    /// Mapping editor writing any PDO, filled by the PDO configuration read from a node.
+    fn show_pdo_editor(&mut self, ui: &mut Ui) {
+        ui.heading("Mapping editor");
+        let node_id = self.pdo_config_node_id.parse::<u8>().ok();
+        self.pdo_editor.ui(ui, node_id);
+        ui.label("ℹ️ Read the current mapping from the node menu, PDO config…, then ✏ to edit it here. Most nodes must be pre-operational.");
+        if ui.button("✏ Write PDO").clicked() {
+            let Some(node_id) = node_id else {
+                self.invalid("Invalid node ID format");
+                return;
+            };
+            match self.pdo_editor.parameters() {
+                Ok(parameters) => self.send(WriteCommand::WritePdoConfig { node_id, parameters }),
+                Err(e) => self.invalid(&e),
             }
         }
     }
 }
 
+/// Checks the values of a command entered by the user.
+///
+/// # Errors
+/// The reason the command can't be sent.
+pub fn validate(cmd: &WriteCommand) -> Result<(), &'static str> {
+    match cmd {
+        WriteCommand::SendNmt { node_id, .. } if *node_id > 127 => {
+            Err("Invalid node ID: must be 0-127")
+        }
+        WriteCommand::SendSdoDownload { node_id, .. }
+        | WriteCommand::SendSdoUpload { node_id, .. }
+        | WriteCommand::ScanUpload { node_id, .. }
+        | WriteCommand::ReadPdoConfig { node_id }
+        | WriteCommand::WritePdoConfig { node_id, .. }
+            if !(1..=127).contains(node_id) =>
+        {
+            Err("Invalid node ID: must be 1-127")
+        }
+        WriteCommand::SendSdoDownload { data, .. } if data.len() > 4 => {
+            Err("SDO data too long: max 4 bytes for expedited transfer")
+        }
+        WriteCommand::WritePdoConfig { parameters, .. } if parameters.mapping.len() > MAX_MAPPED => {
+            Err("Too many mapped objects: max 8")
+        }
+        WriteCommand::WritePdoConfig { parameters, .. } if parameters.mapped_bits() > 64 => {
+            Err("Mapping too long: max 64 bits")
+        }
+        WriteCommand::SendRaw { cob_id, .. } | WriteCommand::SendPdo { cob_id, .. }
+            if *cob_id > 0x7FF =>
+        {
+            Err("Invalid COB-ID: must be 0-7FF")
+        }
+        WriteCommand::SendRaw { data, .. } | WriteCommand::SendPdo { data, .. } if data.len() > 8 => {
+            Err("Data too long: max 8 bytes")
+        }
+        _ => Ok(()),
+    }
+}
+
 /// Parse hex data string like "00 11 22" or "001122" into Vec<u8>
-fn parse_hex_data(s: &str) -> Result<Vec<u8>, String> {
+pub(crate) fn parse_hex_data(s: &str) -> Result<Vec<u8>, String> {
     let cleaned: String = s.chars().filter(|c| !c.is_whitespace()).collect();
     
     if cleaned.len() % 2 != 0 {
@@ -426,7 +940,7 @@ fn parse_hex_data(s: &str) -> Result<Vec<u8>, String> {
     for i in (0..cleaned.len()).step_by(2) {
         match u8::from_str_radix(&cleaned[i..i+2], 16) {
             Ok(byte) => result.push(byte),
-            Err(_) => return Err(format!("Invalid hex at position {}", i)),
+            Err(_) => return Err(format!("Invalid hex at position {i}")),
         }
     }
     
@@ -436,6 +950,7 @@ fn parse_hex_data(s: &str) -> Result<Vec<u8>, String> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::pdo_config::{MappedObject, PdoId};
     
     #[test]
     fn test_parse_hex_data() {
@@ -445,5 +960,40 @@ mod tests {
         assert!(parse_hex_data("0").is_err());
         assert!(parse_hex_data("GG").is_err());
     }
+
+    #[test]
+    fn test_validate() {
+        let sdo = |node_id, data: &[u8]| WriteCommand::SendSdoDownload {
+            node_id,
+            index: 0x6040,
+            subindex: 0,
+            data: data.to_vec(),
+        };
+        assert_eq!(validate(&sdo(1, &[0x06, 0x00])), Ok(()));
+        assert!(validate(&sdo(0, &[0x06])).is_err());
+        assert!(validate(&sdo(1, &[0; 5])).is_err());
+        let nmt = |node_id| WriteCommand::SendNmt {
+            node_id,
+            command: NmtCommandSpecifier::StartRemoteNode,
+        };
+        assert_eq!(validate(&nmt(0)), Ok(()));
+        assert!(validate(&nmt(128)).is_err());
+        let raw = |cob_id, len| WriteCommand::SendRaw { cob_id, data: vec![0; len] };
+        assert_eq!(validate(&raw(0x7FF, 8)), Ok(()));
+        assert!(validate(&raw(0x800, 0)).is_err());
+        assert!(validate(&raw(0x123, 9)).is_err());
+        let mut parameters = PdoParameters {
+            pdo: PdoId::default(),
+            cob_id: 0x181,
+            transmission_type: None,
+            inhibit_time: None,
+            event_timer: None,
+            mapping: vec![MappedObject::from(0x6064_0020); 2],
+        };
+        let write = |parameters| WriteCommand::WritePdoConfig { node_id: 1, parameters };
+        assert_eq!(validate(&write(parameters.clone())), Ok(()));
+        parameters.mapping.push(MappedObject::from(0x6041_0010));
+        assert_eq!(validate(&write(parameters)), Err("Mapping too long: max 64 bits"));
+    }
 }
 
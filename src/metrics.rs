@@ -0,0 +1,302 @@
+use crate::http_api::{ApiStatus, StatusPublisher};
+use axum::{
+    extract::State,
+    http::{header::CONTENT_TYPE, StatusCode},
+    response::IntoResponse,
+    routing::get,
+    Router,
+};
+use std::{cmp::Reverse, fmt::Write as _, io, net::SocketAddr, sync::Arc};
+use tokio::{net::TcpListener, runtime::Handle, sync::watch};
+
+/// Number of COB-IDs with their own series, those with the most messages.
+pub const METRICS_COB_IDS: usize = 20;
+
+/// Content type of the Prometheus text format.
+const TEXT_FORMAT: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Writer of metric families in the Prometheus text format.
+struct Families {
+    text: String,
+    interface: String,
+}
+
+impl Families {
+    fn family(&mut self, name: &str, kind: &str, help: &str) {
+        let _ = writeln!(self.text, "# HELP {name} {help}\n# TYPE {name} {kind}");
+    }
+
+    /// Sample of the interface, with the extra `labels`.
+    fn sample(&mut self, name: &str, labels: &[(&str, &str)], value: f64) {
+        let _ = write!(self.text, "{name}{{interface=\"{}\"", self.interface);
+        for (label, text) in labels {
+            let _ = write!(self.text, ",{label}=\"{}\"", escape(text));
+        }
+        let _ = writeln!(self.text, "}} {value}");
+    }
+}
+
+/// Label value with its backslashes, quotes and newlines escaped.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Statistics and nodes of `snapshot` in the Prometheus text format.
+pub fn render(snapshot: &ApiStatus) -> String {
+    let mut out = Families {
+        text: String::new(),
+        interface: escape(&snapshot.can_name),
+    };
+    render_bus(&mut out, snapshot);
+    render_cob_ids(&mut out, snapshot);
+    render_nodes(&mut out, snapshot);
+    out.text
+}
+
+/// Load, rates and error frames of the bus.
+#[allow(clippy::cast_precision_loss)]
+fn render_bus(out: &mut Families, snapshot: &ApiStatus) {
+    let stats = &snapshot.stats;
+    out.family(
+        "canopen_bus_load_percent",
+        "gauge",
+        "Bus load over the load window.",
+    );
+    out.sample("canopen_bus_load_percent", &[], stats.load.current);
+    out.family(
+        "canopen_bus_load_peak_percent",
+        "gauge",
+        "Highest bus load since the start.",
+    );
+    out.sample("canopen_bus_load_peak_percent", &[], stats.load.peak);
+    out.family("canopen_messages_total", "counter", "Frames received.");
+    out.sample("canopen_messages_total", &[], stats.total_messages as f64);
+    out.family(
+        "canopen_message_rate_hz",
+        "gauge",
+        "Frames per second over the rate window.",
+    );
+    out.sample("canopen_message_rate_hz", &[], stats.message_rate.current);
+    out.family(
+        "canopen_error_frames_total",
+        "counter",
+        "Error frames of the interface, per class.",
+    );
+    out.sample(
+        "canopen_error_frames_total",
+        &[("class", "any")],
+        stats.errors.frames as f64,
+    );
+    for (class, count) in &stats.errors.classes {
+        out.sample(
+            "canopen_error_frames_total",
+            &[("class", class)],
+            *count as f64,
+        );
+    }
+}
+
+/// Messages and rate of the busiest COB-IDs.
+#[allow(clippy::cast_precision_loss)]
+fn render_cob_ids(out: &mut Families, snapshot: &ApiStatus) {
+    let mut cob_ids: Vec<_> = snapshot.stats.cob_ids.iter().collect();
+    cob_ids.sort_by_key(|cob_id| Reverse(cob_id.count));
+    cob_ids.truncate(METRICS_COB_IDS);
+    cob_ids.sort_by(|a, b| a.cob_id.cmp(&b.cob_id));
+    out.family(
+        "canopen_cob_id_messages_total",
+        "counter",
+        "Frames received per COB-ID, the busiest ones.",
+    );
+    for cob_id in &cob_ids {
+        out.sample(
+            "canopen_cob_id_messages_total",
+            &[("cob_id", &cob_id.cob_id)],
+            cob_id.count as f64,
+        );
+    }
+    out.family(
+        "canopen_cob_id_rate_hz",
+        "gauge",
+        "Frames per second per COB-ID, the busiest ones.",
+    );
+    for cob_id in &cob_ids {
+        let rate = cob_id.rate_hz.unwrap_or_default();
+        out.sample(
+            "canopen_cob_id_rate_hz",
+            &[("cob_id", &cob_id.cob_id)],
+            rate,
+        );
+    }
+}
+
+/// Messages, heartbeats, boot-ups and emergencies per node.
+#[allow(clippy::cast_precision_loss)]
+fn render_nodes(out: &mut Families, snapshot: &ApiStatus) {
+    out.family(
+        "canopen_node_messages_total",
+        "counter",
+        "Frames received per node.",
+    );
+    for node in &snapshot.nodes {
+        let node_id = node.node_id.to_string();
+        out.sample(
+            "canopen_node_messages_total",
+            &[("node_id", &node_id)],
+            node.messages as f64,
+        );
+    }
+    out.family(
+        "canopen_node_heartbeat_age_seconds",
+        "gauge",
+        "Time since the last heartbeat of the node.",
+    );
+    for node in &snapshot.nodes {
+        if let Some(ago) = node.heartbeat_ago_s {
+            out.sample(
+                "canopen_node_heartbeat_age_seconds",
+                &[("node_id", &node.node_id.to_string())],
+                ago,
+            );
+        }
+    }
+    out.family(
+        "canopen_node_boot_ups_total",
+        "counter",
+        "Boot-up messages sent by the node.",
+    );
+    for node in &snapshot.nodes {
+        let node_id = node.node_id.to_string();
+        out.sample(
+            "canopen_node_boot_ups_total",
+            &[("node_id", &node_id)],
+            f64::from(node.boot_ups),
+        );
+    }
+    out.family(
+        "canopen_node_emcy_total",
+        "counter",
+        "Emergencies sent by the node.",
+    );
+    for (node_id, count) in &snapshot.emergencies {
+        out.sample(
+            "canopen_node_emcy_total",
+            &[("node_id", &node_id.to_string())],
+            *count as f64,
+        );
+    }
+}
+
+async fn metrics(
+    State(status): State<watch::Receiver<Option<Arc<ApiStatus>>>>,
+) -> impl IntoResponse {
+    let status = status.borrow().clone();
+    match status {
+        Some(status) => (
+            StatusCode::OK,
+            [(CONTENT_TYPE, TEXT_FORMAT)],
+            render(&status),
+        ),
+        None => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            [(CONTENT_TYPE, TEXT_FORMAT)],
+            String::new(),
+        ),
+    }
+}
+
+/// HTTP server answering `GET /metrics` from the snapshots published by the GUI.
+#[derive(Debug)]
+pub struct MetricsServer {
+    status: watch::Sender<Option<Arc<ApiStatus>>>,
+    local_addr: SocketAddr,
+}
+
+impl MetricsServer {
+    /// Listens on `addr` and serves the scrapes on `runtime`.
+    ///
+    /// # Errors
+    /// If `addr` can't be listened on.
+    pub fn start(runtime: &Handle, addr: SocketAddr) -> io::Result<Self> {
+        let listener = std::net::TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        let local_addr = listener.local_addr()?;
+        let _enter = runtime.enter();
+        let listener = TcpListener::from_std(listener)?;
+        let (status, status_receiver) = watch::channel(None);
+        let app = Router::new()
+            .route("/metrics", get(metrics))
+            .with_state(status_receiver);
+        tokio::spawn(async move {
+            if let Err(e) = axum::serve(listener, app).await {
+                log::error!("Metrics server stopped: {e}");
+            }
+        });
+        log::info!("Serving the metrics on http://{local_addr}/metrics");
+        Ok(Self { status, local_addr })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Publisher of the statistics and nodes, given to the session of the driver.
+    pub fn status(&self) -> StatusPublisher {
+        StatusPublisher::new(self.status.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{render, METRICS_COB_IDS};
+    use crate::{
+        bus_stats::BusStats, http_api::ApiStatus, message_cached::MessageCached, nodes::NodeTable,
+    };
+    use oze_canopen::canopen::RxMessage;
+    use std::{collections::BTreeMap, time::Duration};
+    use tokio::time::Instant;
+
+    #[test]
+    fn test_render() {
+        let start = Instant::now();
+        let mut stats = BusStats::new();
+        let mut nodes = NodeTable::default();
+        for cob_id in 0x181..0x181 + 30 {
+            stats.on_message(cob_id, None, 8, start);
+        }
+        stats.on_message(0x181, Some(1), 8, start);
+        let heartbeat = RxMessage {
+            timestamp: start,
+            cob_id: 0x701,
+            data: [0x05, 0, 0, 0, 0, 0, 0, 0],
+            dlc: 1,
+        };
+        nodes.on_message(&MessageCached::new(0, heartbeat));
+        let now = start + Duration::from_secs(2);
+        let snapshot = ApiStatus {
+            can_name: "can\"0".to_owned(),
+            stats: stats.snapshot(now),
+            nodes: nodes.snapshot(now),
+            emergencies: BTreeMap::from([(1, 3)]),
+        };
+
+        let text = render(&snapshot);
+        assert!(text.contains("# TYPE canopen_messages_total counter\n"));
+        assert!(text.contains("canopen_messages_total{interface=\"can\\\"0\"} 31\n"));
+        assert!(text.contains(
+            "canopen_cob_id_messages_total{interface=\"can\\\"0\",cob_id=\"0x181\"} 2\n"
+        ));
+        let series = text
+            .lines()
+            .filter(|line| line.starts_with("canopen_cob_id_rate_hz{"))
+            .count();
+        assert_eq!(series, METRICS_COB_IDS);
+        assert!(text.contains(
+            "canopen_node_heartbeat_age_seconds{interface=\"can\\\"0\",node_id=\"1\"} 2\n"
+        ));
+        assert!(text.contains("canopen_node_emcy_total{interface=\"can\\\"0\",node_id=\"1\"} 3\n"));
+    }
+}
@@ -0,0 +1,169 @@
+use crate::message_cached::MessageCached;
+use egui::TextEdit;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Where the viewer scrolls to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Jump {
+    /// Message of this index, or the nearest one shown.
+    Index(u64),
+    /// Message nearest to this time since the capture start.
+    Time(Duration),
+    /// Next EMCY frame down the list from the selected row, or up with `forward` false.
+    Emcy { forward: bool },
+    /// Next bookmarked message down the list from the selected row, or up.
+    Bookmark { forward: bool },
+}
+
+impl Jump {
+    /// Parses a message index (`120000` or `#120000`) or a time offset in seconds (`35s`,
+    /// `t=35`, `35.2`).
+    pub fn parse(raw: &str) -> Option<Self> {
+        let raw = raw.trim();
+        if let Some(index) = raw.strip_prefix('#') {
+            return index.trim().parse().ok().map(Self::Index);
+        }
+        let time = raw
+            .strip_prefix("t=")
+            .or_else(|| raw.strip_suffix('s'))
+            .or_else(|| raw.contains('.').then_some(raw));
+        match time {
+            Some(seconds) => Duration::try_from_secs_f64(seconds.trim().parse().ok()?)
+                .ok()
+                .map(Self::Time),
+            None => raw.parse().ok().map(Self::Index),
+        }
+    }
+
+    /// How far a row standing for the messages `oldest..=newest` is from an index or time
+    /// target, `None` for the steps.
+    pub fn distance(
+        self,
+        newest: &MessageCached,
+        oldest: &MessageCached,
+        start: Instant,
+    ) -> Option<u128> {
+        match self {
+            Self::Index(index) => Some(u128::from(
+                oldest
+                    .index
+                    .saturating_sub(index)
+                    .max(index.saturating_sub(newest.index)),
+            )),
+            Self::Time(offset) => {
+                let time = start + offset;
+                let before = oldest.get_timestamp().saturating_duration_since(time);
+                let after = time.saturating_duration_since(newest.get_timestamp());
+                Some(before.max(after).as_nanos())
+            }
+            Self::Emcy { .. } | Self::Bookmark { .. } => None,
+        }
+    }
+}
+
+/// Go-to box and the EMCY and bookmark steps above the viewer.
+#[derive(Debug, Default)]
+pub struct Navigation {
+    raw: String,
+    /// The go-to box couldn't be parsed.
+    invalid: bool,
+}
+
+impl Navigation {
+    /// Draws the controls, returns where to scroll to.
+    pub fn update(&mut self, ui: &mut egui::Ui) -> Option<Jump> {
+        let mut jump = None;
+        ui.horizontal(|ui| {
+            let response = ui
+                .add(
+                    TextEdit::singleline(&mut self.raw)
+                        .hint_text("go to")
+                        .desired_width(120.0),
+                )
+                .on_hover_text(
+                    "Message index ('120000' or '#120000') or seconds since the capture start ('35s', 't=35', '35.2'), Enter scrolls to the nearest row",
+                );
+            if response.changed() {
+                self.invalid = false;
+            }
+            if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                jump = Jump::parse(&self.raw);
+                self.invalid = jump.is_none();
+            }
+            if self.invalid {
+                ui.colored_label(egui::Color32::RED, "not an index or a time");
+            }
+
+            ui.separator();
+            ui.label("EMCY");
+            if ui.button("⏶").on_hover_text("Previous EMCY frame").clicked() {
+                jump = Some(Jump::Emcy { forward: false });
+            }
+            if ui.button("⏷").on_hover_text("Next EMCY frame").clicked() {
+                jump = Some(Jump::Emcy { forward: true });
+            }
+            ui.separator();
+            ui.label("★");
+            if ui.button("⏶").on_hover_text("Previous bookmark").clicked() {
+                jump = Some(Jump::Bookmark { forward: false });
+            }
+            if ui.button("⏷").on_hover_text("Next bookmark").clicked() {
+                jump = Some(Jump::Bookmark { forward: true });
+            }
+        });
+        jump
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Jump;
+    use crate::message_cached::MessageCached;
+    use oze_canopen::canopen::RxMessage;
+    use std::time::Duration;
+    use tokio::time::Instant;
+
+    #[test]
+    fn test_jump() {
+        assert_eq!(Jump::parse("120000"), Some(Jump::Index(120_000)));
+        assert_eq!(Jump::parse(" #42 "), Some(Jump::Index(42)));
+        assert_eq!(
+            Jump::parse("35s"),
+            Some(Jump::Time(Duration::from_secs(35)))
+        );
+        assert_eq!(
+            Jump::parse("t=1.5"),
+            Some(Jump::Time(Duration::from_millis(1500)))
+        );
+        assert_eq!(
+            Jump::parse("0.25"),
+            Some(Jump::Time(Duration::from_millis(250)))
+        );
+        assert_eq!(Jump::parse("-3s"), None);
+        assert_eq!(Jump::parse("abc"), None);
+
+        let start = Instant::now();
+        let message = |index, millis| {
+            MessageCached::new(
+                index,
+                RxMessage {
+                    timestamp: start + Duration::from_millis(millis),
+                    cob_id: 0x181,
+                    data: [0; 8],
+                    dlc: 0,
+                },
+            )
+        };
+        let (oldest, newest) = (message(10, 1000), message(20, 2000));
+        let distance = |jump: Jump| jump.distance(&newest, &oldest, start);
+        assert_eq!(distance(Jump::Index(15)), Some(0));
+        assert_eq!(distance(Jump::Index(7)), Some(3));
+        assert_eq!(distance(Jump::Index(25)), Some(5));
+        assert_eq!(
+            distance(Jump::Time(Duration::from_millis(2500))),
+            Some(500_000_000)
+        );
+        assert_eq!(distance(Jump::Emcy { forward: true }), None);
+    }
+}
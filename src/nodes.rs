@@ -0,0 +1,684 @@
+use crate::{
+    cia402::{self, Statusword},
+    message_cached::{MessageCached, NmtState, RxMessageAdditional},
+    theme::{Status, OZON_GRAY, OZON_PINK},
+};
+use chrono::{DateTime, Local};
+use egui::{Color32, DragValue};
+use oze_canopen::canopen::NodeId;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fmt,
+    time::Duration,
+};
+use tokio::time::Instant;
+
+/// Weight of the newest interval in the smoothed heartbeat period.
+const PERIOD_SMOOTHING: f64 = 0.2;
+/// A heartbeat is late by default when it is overdue by more than this multiple of the period.
+const LATE_FACTOR: f64 = 1.5;
+/// A heartbeat is lost by default when it is overdue by more than this multiple of the period.
+const LOST_FACTOR: f64 = 3.0;
+/// Maximum number of heartbeat alerts kept, the oldest ones are dropped first.
+const MAX_ALERTS: usize = 1000;
+/// How long a boot-up stays highlighted.
+const BOOT_UP_HIGHLIGHT: Duration = Duration::from_secs(10);
+
+/// Heartbeat status of a node relative to its measured period.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Liveness {
+    /// Not enough heartbeats to measure the period.
+    Unknown,
+    OnTime,
+    Late,
+    Lost,
+}
+
+impl Liveness {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Liveness::Unknown => "?",
+            Liveness::OnTime => "on time",
+            Liveness::Late => "late",
+            Liveness::Lost => "lost",
+        }
+    }
+
+    pub fn color(&self) -> Color32 {
+        match self {
+            Liveness::Unknown => OZON_GRAY,
+            Liveness::OnTime => Status::Ok.color(),
+            Liveness::Late => Status::Warning.color(),
+            Liveness::Lost => Status::Error.color(),
+        }
+    }
+}
+
+/// Multiples of the heartbeat period after which a node is late or lost.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Thresholds {
+    pub late: f64,
+    pub lost: f64,
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        Self {
+            late: LATE_FACTOR,
+            lost: LOST_FACTOR,
+        }
+    }
+}
+
+/// Change of the heartbeat liveness of a node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeartbeatEvent {
+    Late,
+    Lost,
+    Recovered,
+}
+
+impl HeartbeatEvent {
+    /// Event of a liveness change, `None` if it is not worth an alert.
+    fn of(previous: Liveness, current: Liveness) -> Option<Self> {
+        match (previous, current) {
+            (Liveness::Lost, Liveness::Lost) | (Liveness::Late, Liveness::Late) => None,
+            (_, Liveness::Lost) => Some(Self::Lost),
+            (Liveness::Unknown | Liveness::OnTime, Liveness::Late) => Some(Self::Late),
+            (Liveness::Late | Liveness::Lost, Liveness::OnTime | Liveness::Unknown) => {
+                Some(Self::Recovered)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Late => "late",
+            Self::Lost => "lost",
+            Self::Recovered => "recovered",
+        }
+    }
+
+    pub fn color(self) -> Color32 {
+        match self {
+            Self::Late => Liveness::Late.color(),
+            Self::Lost => Liveness::Lost.color(),
+            Self::Recovered => Liveness::OnTime.color(),
+        }
+    }
+}
+
+/// Loss or recovery of the heartbeat of a node.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeartbeatAlert {
+    pub time: DateTime<Local>,
+    pub node_id: NodeId,
+    pub event: HeartbeatEvent,
+    /// NMT state of the last heartbeat.
+    pub state: NmtState,
+}
+
+impl fmt::Display for HeartbeatAlert {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let state = if self.event == HeartbeatEvent::Recovered {
+            "state"
+        } else {
+            "last state"
+        };
+        write!(
+            f,
+            "node {} heartbeat {} at {}, {state} {}",
+            self.node_id,
+            self.event.as_str(),
+            self.time.format("%H:%M:%S"),
+            self.state
+        )
+    }
+}
+
+/// Identity object (0x1018) of a node read over SDO.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NodeIdentity {
+    pub vendor_id: u32,
+    pub product_code: Option<u32>,
+    pub revision: Option<u32>,
+    pub serial: Option<u32>,
+}
+
+impl fmt::Display for NodeIdentity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "vendor 0x{:08X}", self.vendor_id)?;
+        for (name, value) in [
+            ("product", self.product_code),
+            ("revision", self.revision),
+            ("serial", self.serial),
+        ] {
+            if let Some(value) = value {
+                write!(f, ", {name} 0x{value:08X}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Heartbeat data of a single node.
+#[derive(Debug, Clone)]
+pub struct HeartbeatStatus {
+    pub state: NmtState,
+    pub last_heartbeat: Instant,
+    /// Smoothed interval between heartbeats, boot-up messages restart the measurement.
+    pub period: Option<Duration>,
+    pub boot_ups: u32,
+    pub last_boot_up: Option<Instant>,
+}
+
+impl HeartbeatStatus {
+    /// Liveness against the `expected` period, or the measured one if `None`.
+    pub fn liveness(
+        &self,
+        now: Instant,
+        expected: Option<Duration>,
+        thresholds: Thresholds,
+    ) -> Liveness {
+        let Some(period) = expected.or(self.period) else {
+            return Liveness::Unknown;
+        };
+
+        let elapsed = now.saturating_duration_since(self.last_heartbeat);
+        if elapsed > period.mul_f64(thresholds.lost) {
+            Liveness::Lost
+        } else if elapsed > period.mul_f64(thresholds.late) {
+            Liveness::Late
+        } else {
+            Liveness::OnTime
+        }
+    }
+
+    fn on_heartbeat(&mut self, state: NmtState, timestamp: Instant) {
+        if state == NmtState::BootUp {
+            self.period = None;
+            self.boot_ups += 1;
+            self.last_boot_up = Some(timestamp);
+        } else if self.state != NmtState::BootUp && self.last_heartbeat < timestamp {
+            let interval = timestamp - self.last_heartbeat;
+            self.period = Some(self.period.map_or(interval, |period| {
+                period.mul_f64(1.0 - PERIOD_SMOOTHING) + interval.mul_f64(PERIOD_SMOOTHING)
+            }));
+        }
+
+        self.state = state;
+        self.last_heartbeat = timestamp;
+    }
+}
+
+/// Everything known about a node seen on the bus.
+#[derive(Debug, Clone)]
+pub struct NodeStatus {
+    pub first_seen: Instant,
+    pub last_seen: Instant,
+    pub messages: u64,
+    pub heartbeat: Option<HeartbeatStatus>,
+    /// Last statusword of the node if it is a drive.
+    pub statusword: Option<Statusword>,
+}
+
+/// Node in a statistics snapshot, the instants are durations before the snapshot.
+#[derive(Debug, Clone, Serialize)]
+pub struct NodeSnapshot {
+    pub node_id: NodeId,
+    pub messages: u64,
+    pub first_seen_ago_s: f64,
+    pub last_seen_ago_s: f64,
+    /// NMT state of the last heartbeat.
+    pub state: Option<String>,
+    /// Seconds between the last heartbeat and the snapshot.
+    pub heartbeat_ago_s: Option<f64>,
+    pub heartbeat_period_ms: Option<f64>,
+    pub liveness: Option<String>,
+    pub boot_ups: u32,
+    /// Drive state of the last statusword.
+    pub drive_state: Option<String>,
+}
+
+/// Action requested from the nodes panel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeAction {
+    /// Prefill the node ID in the message sender.
+    UseInSender(NodeId),
+    /// Read the identity object of the node.
+    ReadIdentity(NodeId),
+    /// Show the device type, identity and name strings of the node.
+    ShowDeviceInfo(NodeId),
+    /// Show the PDO configuration of the node.
+    ShowPdoConfig(NodeId),
+    /// Browse the object dictionary of the node.
+    BrowseObjects(NodeId),
+    /// Show only the traffic of the node in the viewer.
+    FilterTraffic(NodeId),
+}
+
+/// Inventory of every node seen on the bus, with the NMT state reported by its heartbeats.
+///
+/// Nodes are derived from every message carrying a node ID (heartbeat, EMCY, PDO, SDO).
+/// The inventory is kept until it is cleared explicitly.
+#[derive(Debug, Default)]
+pub struct NodeTable {
+    nodes: BTreeMap<NodeId, NodeStatus>,
+    /// Heartbeat periods configured per node, used instead of the measured ones.
+    expected: BTreeMap<NodeId, Duration>,
+    pub thresholds: Thresholds,
+    /// Liveness of the nodes at the last tick.
+    liveness: BTreeMap<NodeId, Liveness>,
+    /// Heartbeat losses and recoveries, oldest first.
+    alerts: VecDeque<HeartbeatAlert>,
+    /// Alerts raised since the panel was last shown.
+    unseen: usize,
+}
+
+impl NodeTable {
+    pub fn on_message(&mut self, msg: &MessageCached) {
+        let Some(node_id) = msg.msg.parsed_node_id else {
+            return;
+        };
+
+        let timestamp = msg.get_timestamp();
+        let node = self.nodes.entry(node_id).or_insert(NodeStatus {
+            first_seen: timestamp,
+            last_seen: timestamp,
+            messages: 0,
+            heartbeat: None,
+            statusword: None,
+        });
+        node.last_seen = timestamp;
+        node.messages += 1;
+        if let Some(word) = cia402::statusword(&msg.msg.msg) {
+            node.statusword = Some(word);
+        }
+
+        if let RxMessageAdditional::Heartbeat(h) = &msg.additional {
+            node.heartbeat
+                .get_or_insert(HeartbeatStatus {
+                    state: h.state,
+                    last_heartbeat: timestamp,
+                    period: None,
+                    boot_ups: 0,
+                    last_boot_up: None,
+                })
+                .on_heartbeat(h.state, timestamp);
+        }
+    }
+
+    pub fn get(&self, node_id: NodeId) -> Option<&NodeStatus> {
+        self.nodes.get(&node_id)
+    }
+
+    /// Heartbeat liveness of a node with its configured period and the thresholds.
+    pub fn liveness(&self, node_id: NodeId, now: Instant) -> Liveness {
+        self.nodes
+            .get(&node_id)
+            .and_then(|node| node.heartbeat.as_ref())
+            .map_or(Liveness::Unknown, |h| {
+                h.liveness(now, self.expected.get(&node_id).copied(), self.thresholds)
+            })
+    }
+
+    /// Configures the heartbeat period of a node, `None` to use the measured one.
+    pub fn set_expected(&mut self, node_id: NodeId, period: Option<Duration>) {
+        match period {
+            Some(period) => self.expected.insert(node_id, period),
+            None => self.expected.remove(&node_id),
+        };
+    }
+
+    /// Checks the heartbeat timeouts, returns the alerts raised.
+    pub fn on_tick(&mut self, now: Instant) -> Vec<HeartbeatAlert> {
+        let mut alerts = Vec::new();
+        for (node_id, node) in &self.nodes {
+            let Some(heartbeat) = &node.heartbeat else {
+                continue;
+            };
+            let current = self.liveness(*node_id, now);
+            let previous = self
+                .liveness
+                .insert(*node_id, current)
+                .unwrap_or(Liveness::Unknown);
+            if let Some(event) = HeartbeatEvent::of(previous, current) {
+                alerts.push(HeartbeatAlert {
+                    time: Local::now(),
+                    node_id: *node_id,
+                    event,
+                    state: heartbeat.state,
+                });
+            }
+        }
+        self.unseen += alerts.len();
+        self.alerts.extend(alerts.iter().cloned());
+        while self.alerts.len() > MAX_ALERTS {
+            self.alerts.pop_front();
+        }
+        alerts
+    }
+
+    /// Header of the panel, red with the number of alerts raised while it was closed.
+    pub fn title(&self) -> egui::RichText {
+        match self.unseen {
+            0 => egui::RichText::new("🖧 Nodes"),
+            n => egui::RichText::new(format!("🖧 Nodes ({n} alerts)")).color(Status::Error.color()),
+        }
+    }
+
+    /// Every node ordered by ID, for a statistics export.
+    pub fn snapshot(&self, now: Instant) -> Vec<NodeSnapshot> {
+        let ago = |time: Instant| now.saturating_duration_since(time).as_secs_f64();
+        self.nodes
+            .iter()
+            .map(|(node_id, node)| {
+                let heartbeat = node.heartbeat.as_ref();
+                NodeSnapshot {
+                    node_id: *node_id,
+                    messages: node.messages,
+                    first_seen_ago_s: ago(node.first_seen),
+                    last_seen_ago_s: ago(node.last_seen),
+                    state: heartbeat.map(|h| h.state.to_string()),
+                    heartbeat_ago_s: heartbeat.map(|h| ago(h.last_heartbeat)),
+                    heartbeat_period_ms: heartbeat
+                        .and_then(|h| h.period)
+                        .map(|period| period.as_secs_f64() * 1000.0),
+                    liveness: heartbeat.map(|_| self.liveness(*node_id, now).as_str().to_owned()),
+                    boot_ups: heartbeat.map_or(0, |h| h.boot_ups),
+                    drive_state: node.statusword.map(|word| word.state().to_string()),
+                }
+            })
+            .collect()
+    }
+
+    /// Shows the inventory, `identities` are the results of identity reads per node.
+    pub fn update(
+        &mut self,
+        ui: &mut egui::Ui,
+        identities: &BTreeMap<NodeId, Result<NodeIdentity, String>>,
+    ) -> Option<NodeAction> {
+        self.unseen = 0;
+        let now = Instant::now();
+        let mut action = None;
+        if self.nodes.is_empty() {
+            ui.label("No nodes seen yet");
+        } else {
+            egui::ScrollArea::horizontal().show(ui, |ui| {
+                egui::Grid::new("nodes").striped(true).show(ui, |ui| {
+                    ui.label("Node");
+                    ui.label("State");
+                    ui.label("Period");
+                    ui.label("Boot-ups");
+                    ui.label("Drive");
+                    ui.label("Msgs");
+                    ui.label("Seen");
+                    ui.end_row();
+
+                    for (node_id, node) in &self.nodes {
+                        let liveness = self.liveness(*node_id, now);
+                        let expected = self.expected.entry(*node_id).or_insert(Duration::ZERO);
+                        action = action.or(Self::show_node(
+                            ui, now, *node_id, node, liveness, expected, identities,
+                        ));
+                        ui.end_row();
+                    }
+                });
+            });
+            self.expected.retain(|_, period| !period.is_zero());
+
+            if ui.button("Clear").clicked() {
+                self.nodes.clear();
+                self.liveness.clear();
+            }
+        }
+
+        ui.collapsing("Heartbeat alerts", |ui| self.show_alerts(ui));
+        action
+    }
+
+    fn show_alerts(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Late after");
+            ui.add(
+                DragValue::new(&mut self.thresholds.late)
+                    .range(1.0..=self.thresholds.lost)
+                    .speed(0.1)
+                    .suffix("×"),
+            );
+            ui.label("lost after");
+            ui.add(
+                DragValue::new(&mut self.thresholds.lost)
+                    .range(self.thresholds.late..=100.0)
+                    .speed(0.1)
+                    .suffix("×"),
+            )
+            .on_hover_text("Multiples of the heartbeat period, configured or measured");
+        });
+        if ui.button("Clear alerts").clicked() {
+            self.alerts.clear();
+        }
+        if self.alerts.is_empty() {
+            ui.label("No alerts");
+            return;
+        }
+        for alert in self.alerts.iter().rev() {
+            ui.colored_label(alert.event.color(), alert.to_string());
+        }
+    }
+
+    /// `expected` is the configured heartbeat period, zero to use the measured one.
+    fn show_node(
+        ui: &mut egui::Ui,
+        now: Instant,
+        node_id: NodeId,
+        node: &NodeStatus,
+        liveness: Liveness,
+        expected: &mut Duration,
+        identities: &BTreeMap<NodeId, Result<NodeIdentity, String>>,
+    ) -> Option<NodeAction> {
+        let mut action = None;
+        let label = egui::RichText::new(format!("● {node_id:3}")).color(liveness.color());
+        ui.menu_button(label, |ui| {
+            ui.label(format!("Heartbeat: {}", liveness.as_str()));
+            ui.horizontal(|ui| {
+                ui.label("Expected period:");
+                let mut millis = u64::try_from(expected.as_millis()).unwrap_or(u64::MAX);
+                if ui
+                    .add(DragValue::new(&mut millis).range(0..=65_535).suffix(" ms"))
+                    .on_hover_text("Heartbeat producer time (0x1017), 0 uses the measured period")
+                    .changed()
+                {
+                    *expected = Duration::from_millis(millis);
+                }
+            });
+            match identities.get(&node_id) {
+                Some(Ok(identity)) => ui.label(identity.to_string()),
+                Some(Err(e)) => ui.colored_label(Color32::RED, format!("Identity: {e}")),
+                None => ui.label("Identity not read"),
+            };
+            ui.separator();
+            if ui.button("Use in sender").clicked() {
+                action = Some(NodeAction::UseInSender(node_id));
+                ui.close_menu();
+            }
+            if ui.button("Read identity (0x1018)").clicked() {
+                action = Some(NodeAction::ReadIdentity(node_id));
+                ui.close_menu();
+            }
+            if ui.button("Device info…").clicked() {
+                action = Some(NodeAction::ShowDeviceInfo(node_id));
+                ui.close_menu();
+            }
+            if ui.button("PDO config…").clicked() {
+                action = Some(NodeAction::ShowPdoConfig(node_id));
+                ui.close_menu();
+            }
+            if ui.button("Object dictionary…").clicked() {
+                action = Some(NodeAction::BrowseObjects(node_id));
+                ui.close_menu();
+            }
+            if ui.button("Filter traffic").clicked() {
+                action = Some(NodeAction::FilterTraffic(node_id));
+                ui.close_menu();
+            }
+        });
+
+        if let Some(h) = &node.heartbeat {
+            ui.label(h.state.as_str());
+            if expected.is_zero() {
+                ui.label(
+                    h.period
+                        .map_or_else(|| "--".to_owned(), |p| format!("{} ms", p.as_millis())),
+                );
+            } else {
+                ui.label(format!("{} ms ⚙", expected.as_millis()))
+                    .on_hover_text("Configured period");
+            }
+            let recent_boot_up = h
+                .last_boot_up
+                .is_some_and(|t| now.saturating_duration_since(t) < BOOT_UP_HIGHLIGHT);
+            if recent_boot_up {
+                ui.colored_label(OZON_PINK, format!("⚠ {} reset", h.boot_ups))
+                    .on_hover_text("The node sent a boot-up message, it has probably just reset");
+            } else {
+                ui.label(h.boot_ups.to_string());
+            }
+        } else {
+            ui.label("--");
+            ui.label("--");
+            ui.label("--");
+        }
+
+        if let Some(word) = node.statusword {
+            let state = word.state();
+            ui.colored_label(state.color(), state.as_str())
+                .on_hover_text(word.to_string());
+        } else {
+            ui.label("--");
+        }
+        ui.label(node.messages.to_string());
+        ui.label(format!(
+            "{} - {}",
+            wall_clock(now, node.first_seen),
+            wall_clock(now, node.last_seen)
+        ));
+        action
+    }
+}
+
+/// Converts a past instant to local wall clock time.
+fn wall_clock(now: Instant, instant: Instant) -> String {
+    let age =
+        chrono::Duration::from_std(now.saturating_duration_since(instant)).unwrap_or_default();
+    (Local::now() - age).format("%H:%M:%S").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{HeartbeatEvent, Liveness, NodeTable, Thresholds};
+    use crate::{
+        cia402::DriveState,
+        message_cached::{MessageCached, NmtState},
+    };
+    use oze_canopen::canopen::RxMessage;
+    use std::time::Duration;
+    use tokio::time::Instant;
+
+    fn msg(cob_id: u16, data: u8, timestamp: Instant) -> MessageCached {
+        MessageCached::new(
+            0,
+            RxMessage {
+                timestamp,
+                cob_id,
+                data: [data, 0, 0, 0, 0, 0, 0, 0],
+                dlc: 1,
+            },
+        )
+    }
+
+    #[test]
+    fn test_node_inventory_and_heartbeat() {
+        let mut nodes = NodeTable::default();
+        let start = Instant::now();
+        let ms = Duration::from_millis;
+
+        nodes.on_message(&msg(0x705, 0x00, start));
+        let heartbeat = nodes.get(5).unwrap().heartbeat.as_ref().unwrap();
+        assert_eq!(heartbeat.boot_ups, 1);
+        assert_eq!(
+            heartbeat.liveness(start, None, Thresholds::default()),
+            Liveness::Unknown
+        );
+
+        nodes.on_message(&msg(0x705, 0x7F, start + ms(10)));
+        nodes.on_message(&msg(0x705, 0x7F, start + ms(110)));
+        nodes.on_message(&msg(0x185, 0x01, start + ms(150)));
+        nodes.on_message(&msg(0x705, 0x05, start + ms(210)));
+        let node = nodes.get(5).unwrap();
+        assert_eq!(node.messages, 5);
+        assert_eq!(node.first_seen, start);
+        assert_eq!(node.last_seen, start + ms(210));
+        let heartbeat = node.heartbeat.as_ref().unwrap();
+        assert_eq!(heartbeat.state, NmtState::Operational);
+        assert_eq!(heartbeat.period, Some(ms(100)));
+        assert_eq!(nodes.liveness(5, start + ms(300)), Liveness::OnTime);
+        assert_eq!(nodes.liveness(5, start + ms(400)), Liveness::Late);
+        assert_eq!(nodes.liveness(5, start + ms(600)), Liveness::Lost);
+
+        nodes.on_message(&msg(0x705, 0x00, start + ms(700)));
+        let heartbeat = nodes.get(5).unwrap().heartbeat.as_ref().unwrap();
+        assert_eq!(heartbeat.boot_ups, 2);
+        assert_eq!(heartbeat.period, None);
+
+        nodes.on_message(&msg(0x08A, 0x00, start));
+        assert!(nodes.get(10).unwrap().heartbeat.is_none());
+
+        let upload = RxMessage {
+            timestamp: start,
+            cob_id: 0x585,
+            data: [0x4B, 0x41, 0x60, 0x00, 0x18, 0x02, 0, 0],
+            dlc: 8,
+        };
+        nodes.on_message(&MessageCached::new(0, upload));
+        let word = nodes.get(5).unwrap().statusword.unwrap();
+        assert_eq!(word.state(), DriveState::Fault);
+    }
+
+    #[test]
+    fn test_heartbeat_alerts() {
+        let mut nodes = NodeTable::default();
+        let start = Instant::now();
+        let ms = Duration::from_millis;
+
+        nodes.on_message(&msg(0x705, 0x7F, start));
+        nodes.on_message(&msg(0x705, 0x05, start + ms(100)));
+        assert!(nodes.on_tick(start + ms(150)).is_empty());
+
+        let events = |alerts: Vec<super::HeartbeatAlert>| -> Vec<_> {
+            alerts.iter().map(|alert| alert.event).collect()
+        };
+        assert_eq!(
+            events(nodes.on_tick(start + ms(260))),
+            [HeartbeatEvent::Late]
+        );
+        assert!(nodes.on_tick(start + ms(270)).is_empty());
+        let lost = nodes.on_tick(start + ms(410));
+        assert_eq!(events(lost.clone()), [HeartbeatEvent::Lost]);
+        assert!(lost[0].to_string().ends_with("last state Operational"));
+
+        nodes.on_message(&msg(0x705, 0x05, start + ms(500)));
+        assert_eq!(
+            events(nodes.on_tick(start + ms(510))),
+            [HeartbeatEvent::Recovered]
+        );
+
+        // A configured period of 1 s overrides the measured 100 ms.
+        nodes.set_expected(5, Some(ms(1000)));
+        assert_eq!(nodes.liveness(5, start + ms(900)), Liveness::OnTime);
+        nodes.thresholds.lost = 1.2;
+        assert_eq!(nodes.liveness(5, start + ms(1800)), Liveness::Lost);
+    }
+}
@@ -0,0 +1,196 @@
+use crate::theme::{Status, OZON_GRAY};
+use chrono::{DateTime, Local};
+use egui::{Align2, Color32, Id, RichText, Ui};
+use std::{collections::VecDeque, fmt, time::Duration};
+use tokio::time::Instant;
+
+/// Notifications kept in the event log.
+const LOG_LEN: usize = 500;
+
+/// Toasts shown at once, the oldest make room for the new ones.
+const MAX_TOASTS: usize = 5;
+
+/// How long a toast stays on screen.
+const TOAST_DURATION: Duration = Duration::from_secs(6);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// Logged only, e.g. a connection change.
+    Info,
+    /// Logged and shown as a toast.
+    Warning,
+    /// Logged and shown as a toast.
+    Error,
+}
+
+impl Severity {
+    pub fn color(self) -> Color32 {
+        match self {
+            Self::Info => OZON_GRAY,
+            Self::Warning => Status::Warning.color(),
+            Self::Error => Status::Error.color(),
+        }
+    }
+
+    fn icon(self) -> &'static str {
+        match self {
+            Self::Info => "ℹ",
+            Self::Warning => "⚠",
+            Self::Error => "❌",
+        }
+    }
+}
+
+/// Event reported to the user, by the GUI or by the driver over its event channel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Notification {
+    pub time: DateTime<Local>,
+    pub severity: Severity,
+    pub text: String,
+}
+
+impl Notification {
+    pub fn new(severity: Severity, text: impl Into<String>) -> Self {
+        Self {
+            time: Local::now(),
+            severity,
+            text: text.into(),
+        }
+    }
+}
+
+impl fmt::Display for Notification {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.time.format("%H:%M:%S"), self.text)
+    }
+}
+
+/// Event log behind the bell of the top bar, with the toasts of the warnings and errors.
+#[derive(Debug, Default)]
+pub struct Notifications {
+    /// Oldest first.
+    log: VecDeque<Notification>,
+    unread: usize,
+    /// Toasts with the time they disappear, oldest first.
+    toasts: VecDeque<(Notification, Instant)>,
+}
+
+impl Notifications {
+    pub fn push(&mut self, notification: Notification) {
+        if notification.severity >= Severity::Warning {
+            self.toasts
+                .push_back((notification.clone(), Instant::now() + TOAST_DURATION));
+            while self.toasts.len() > MAX_TOASTS {
+                self.toasts.pop_front();
+            }
+        }
+        self.log.push_back(notification);
+        while self.log.len() > LOG_LEN {
+            self.log.pop_front();
+        }
+        self.unread = (self.unread + 1).min(LOG_LEN);
+    }
+
+    /// Bell with the unread count, opening the event log.
+    pub fn bell_ui(&mut self, ui: &mut Ui) {
+        let worst = self
+            .log
+            .iter()
+            .rev()
+            .take(self.unread)
+            .map(|notification| notification.severity)
+            .max();
+        let text = match worst {
+            Some(severity) => RichText::new(format!("🔔 {}", self.unread)).color(severity.color()),
+            None => RichText::new("🔔"),
+        };
+        let menu = ui.menu_button(text, |ui| self.log_ui(ui));
+        if menu.inner.is_some() {
+            self.unread = 0;
+        }
+        menu.response.on_hover_text("Event log");
+    }
+
+    fn log_ui(&mut self, ui: &mut Ui) {
+        if self.log.is_empty() {
+            ui.label("No event yet");
+            return;
+        }
+        egui::ScrollArea::vertical()
+            .max_height(400.0)
+            .show(ui, |ui| {
+                for notification in self.log.iter().rev() {
+                    ui.colored_label(
+                        notification.severity.color(),
+                        format!("{} {notification}", notification.severity.icon()),
+                    );
+                }
+            });
+        ui.separator();
+        if ui.button("Clear").clicked() {
+            self.log.clear();
+            self.toasts.clear();
+            ui.close_menu();
+        }
+    }
+
+    /// Toasts in the bottom right corner, until they expire or are closed.
+    pub fn toasts_ui(&mut self, ctx: &egui::Context) {
+        let now = Instant::now();
+        self.toasts.retain(|(_, until)| *until > now);
+        let Some((_, next)) = self.toasts.iter().min_by_key(|(_, until)| *until) else {
+            return;
+        };
+        ctx.request_repaint_after(next.saturating_duration_since(now));
+        let mut closed = None;
+        egui::Area::new(Id::new("toasts"))
+            .anchor(Align2::RIGHT_BOTTOM, [-10.0, -10.0])
+            .show(ctx, |ui| {
+                for (i, (notification, _)) in self.toasts.iter().enumerate() {
+                    egui::Frame::popup(ui.style()).show(ui, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.colored_label(
+                                notification.severity.color(),
+                                format!("{} {}", notification.severity.icon(), notification.text),
+                            );
+                            if ui.small_button("✖").clicked() {
+                                closed = Some(i);
+                            }
+                        });
+                    });
+                }
+            });
+        if let Some(i) = closed {
+            self.toasts.remove(i);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Notification, Notifications, Severity, LOG_LEN, MAX_TOASTS};
+
+    #[test]
+    fn test_notifications() {
+        let mut notifications = Notifications::default();
+        notifications.push(Notification::new(Severity::Info, "connected to can0"));
+        notifications.push(Notification::new(Severity::Error, "bus off on can0"));
+        assert_eq!(notifications.unread, 2);
+        assert_eq!(notifications.toasts.len(), 1);
+        assert_eq!(notifications.toasts[0].0.text, "bus off on can0");
+
+        for i in 0..LOG_LEN {
+            notifications.push(Notification::new(Severity::Warning, format!("EMCY {i}")));
+        }
+        assert_eq!(notifications.log.len(), LOG_LEN);
+        assert_eq!(notifications.unread, LOG_LEN);
+        assert_eq!(notifications.toasts.len(), MAX_TOASTS);
+        assert_eq!(
+            notifications
+                .toasts
+                .back()
+                .map(|(toast, _)| toast.text.as_str()),
+            Some(format!("EMCY {}", LOG_LEN - 1).as_str())
+        );
+    }
+}
@@ -0,0 +1,571 @@
+use crate::{
+    driver::{WriteCommand, WriteError, WriteOutcome, WriteRequest, WriteResult},
+    eds,
+};
+use egui::{Color32, RichText};
+use oze_canopen::canopen::NodeId;
+use std::{
+    collections::{BTreeMap, VecDeque},
+    ops::RangeInclusive,
+    time::Duration,
+};
+use tokio::sync::{
+    mpsc::{self, error::TrySendError},
+    oneshot::{self, error::TryRecvError},
+};
+
+/// Ranges scanned by default: the communication profile and the drive profile objects.
+pub const DEFAULT_RANGES: &str = "1000-1FFF, 6000-67FF";
+
+/// SDO abort codes telling an object or one of its entries doesn't exist.
+const OBJECT_MISSING: u32 = 0x0602_0000;
+const SUBINDEX_MISSING: u32 = 0x0609_0011;
+
+/// Consecutive unanswered uploads after which the scan gives up.
+const MAX_TIMEOUTS: u32 = 3;
+
+/// Parses index ranges such as `1000-1FFF, 6000-67FF`, a single index standing for itself.
+///
+/// # Errors
+/// If an index isn't hexadecimal or a range is reversed.
+pub fn parse_ranges(text: &str) -> Result<Vec<RangeInclusive<u16>>, String> {
+    let index = |text: &str| {
+        let text = text.trim();
+        let hex = text
+            .strip_prefix("0x")
+            .or_else(|| text.strip_prefix("0X"))
+            .unwrap_or(text);
+        u16::from_str_radix(hex, 16).map_err(|_| format!("Invalid index: {text}"))
+    };
+    let ranges = text
+        .split(',')
+        .filter(|range| !range.trim().is_empty())
+        .map(|range| {
+            let (start, end) = range.split_once('-').unwrap_or((range, range));
+            let (start, end) = (index(start)?, index(end)?);
+            if start > end {
+                return Err(format!("Reversed range: {}", range.trim()));
+            }
+            Ok(start..=end)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if ranges.is_empty() {
+        return Err("No index range".to_owned());
+    }
+    Ok(ranges)
+}
+
+/// Value of an entry: a string if it is long and printable, otherwise the bytes in hexadecimal
+/// and, up to 8 bytes, as a little endian unsigned integer.
+pub fn value_text(data: &[u8]) -> String {
+    if data.len() > 4
+        && data
+            .iter()
+            .all(|byte| byte.is_ascii_graphic() || *byte == b' ')
+    {
+        return format!("\"{}\"", String::from_utf8_lossy(data));
+    }
+    let hex = data
+        .iter()
+        .map(|byte| format!("{byte:02X}"))
+        .collect::<Vec<_>>()
+        .join(" ");
+    if data.is_empty() || data.len() > 8 {
+        hex
+    } else {
+        format!("{hex} ({})", eds::format_value(None, data))
+    }
+}
+
+/// Object found by a scan, with the value or the upload error of each of its entries.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OdObject {
+    pub entries: BTreeMap<u8, Result<Vec<u8>, String>>,
+}
+
+impl OdObject {
+    /// A variable has only the sub-index 0, arrays and records count their entries in it.
+    pub fn is_variable(&self) -> bool {
+        self.entries.keys().all(|subindex| *subindex == 0)
+    }
+}
+
+/// Entry of the dictionary uploaded by a scan.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Probe {
+    pub index: u16,
+    pub subindex: u8,
+}
+
+/// Uploads of a scan left to do, one at a time.
+#[derive(Debug)]
+struct Scan {
+    node_id: NodeId,
+    /// Indexes of the ranges not probed yet.
+    indexes: VecDeque<u16>,
+    /// Entries of the objects found, uploaded before the next index.
+    entries: VecDeque<Probe>,
+    total: usize,
+    /// Index probed last, for the progress.
+    current: u16,
+    timeouts: u32,
+}
+
+impl Scan {
+    fn new(node_id: NodeId, ranges: &[RangeInclusive<u16>]) -> Self {
+        let indexes: VecDeque<u16> = ranges.iter().flat_map(Clone::clone).collect();
+        Self {
+            node_id,
+            total: indexes.len(),
+            current: indexes.front().copied().unwrap_or_default(),
+            indexes,
+            entries: VecDeque::new(),
+            timeouts: 0,
+        }
+    }
+
+    fn next_probe(&mut self) -> Option<Probe> {
+        if let Some(probe) = self.entries.pop_front() {
+            return Some(probe);
+        }
+        let index = self.indexes.pop_front()?;
+        self.current = index;
+        Some(Probe { index, subindex: 0 })
+    }
+
+    /// Probes again an upload which couldn't be queued.
+    fn retry(&mut self, probe: Probe) {
+        self.entries.push_front(probe);
+    }
+
+    fn progress(&self) -> f32 {
+        #[allow(clippy::cast_precision_loss)]
+        let done = (self.total - self.indexes.len()) as f32 / self.total.max(1) as f32;
+        done
+    }
+
+    /// Records the result of an upload in `objects` and queues the entries it reveals.
+    ///
+    /// # Errors
+    /// If the node stopped answering or the driver failed, which ends the scan.
+    fn on_result(
+        &mut self,
+        probe: Probe,
+        result: &WriteResult,
+        objects: &mut BTreeMap<u16, OdObject>,
+    ) -> Result<(), String> {
+        let Probe { index, subindex } = probe;
+        if subindex == 0 {
+            objects.remove(&index);
+        }
+        let value = match result {
+            Ok(WriteOutcome::SdoData(data)) => Ok(data.clone()),
+            Ok(outcome) => return Err(format!("Unexpected outcome: {outcome}")),
+            Err(WriteError::Timeout) => {
+                self.timeouts += 1;
+                if self.timeouts >= MAX_TIMEOUTS {
+                    return Err(format!(
+                        "Node {} doesn't answer the SDO uploads",
+                        self.node_id
+                    ));
+                }
+                return Ok(());
+            }
+            Err(WriteError::SdoAbort {
+                code: OBJECT_MISSING,
+                ..
+            }) if subindex == 0 => {
+                self.timeouts = 0;
+                return Ok(());
+            }
+            Err(WriteError::SdoAbort {
+                code: SUBINDEX_MISSING | OBJECT_MISSING,
+                ..
+            }) => {
+                self.timeouts = 0;
+                // A variable has no sub-index 1 even if its value looks like a count.
+                if subindex == 1 {
+                    self.entries.retain(|probe| probe.index != index);
+                }
+                return Ok(());
+            }
+            Err(e @ WriteError::SdoAbort { .. }) => Err(e.to_string()),
+            Err(e) => return Err(e.to_string()),
+        };
+        self.timeouts = 0;
+        if let (0, Ok([count @ 1..=u8::MAX])) = (subindex, value.as_deref()) {
+            self.entries
+                .extend((1..=*count).map(|subindex| Probe { index, subindex }));
+        }
+        objects
+            .entry(index)
+            .or_default()
+            .entries
+            .insert(subindex, value);
+        Ok(())
+    }
+}
+
+/// What the user asked from the browser.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OdBrowserAction {
+    /// Prefill the SDO form of the sender with an entry and its value.
+    UseInSender(NodeId, Probe, Vec<u8>),
+}
+
+/// Window browsing the object dictionary of a node, found by uploading every index of
+/// configurable ranges.
+///
+/// The uploads are queued one at a time, so that the scan can be cancelled, and paced by the
+/// driver as bulk operations. The objects found are kept per node until they are scanned again.
+#[derive(Debug)]
+pub struct OdBrowserPanel {
+    node_id: Option<NodeId>,
+    ranges: String,
+    objects: BTreeMap<NodeId, BTreeMap<u16, OdObject>>,
+    scan: Option<Scan>,
+    pending: Option<(Probe, oneshot::Receiver<WriteResult>)>,
+    /// Why the last scan of a node stopped early.
+    errors: BTreeMap<NodeId, String>,
+    write_sender: mpsc::Sender<WriteRequest>,
+}
+
+impl OdBrowserPanel {
+    pub fn new(write_sender: mpsc::Sender<WriteRequest>) -> Self {
+        Self {
+            node_id: None,
+            ranges: DEFAULT_RANGES.to_owned(),
+            objects: BTreeMap::new(),
+            scan: None,
+            pending: None,
+            errors: BTreeMap::new(),
+            write_sender,
+        }
+    }
+
+    pub fn open(&mut self, node_id: NodeId) {
+        self.node_id = Some(node_id);
+    }
+
+    pub fn is_scanned(&self, node_id: NodeId) -> bool {
+        self.objects.contains_key(&node_id)
+    }
+
+    /// Starts scanning the ranges typed in the window, stopping the scan in progress.
+    pub fn scan(&mut self, node_id: NodeId) {
+        match parse_ranges(&self.ranges) {
+            Ok(ranges) => self.start(node_id, &ranges),
+            Err(e) => {
+                self.errors.insert(node_id, e);
+            }
+        }
+    }
+
+    fn start(&mut self, node_id: NodeId, ranges: &[RangeInclusive<u16>]) {
+        self.errors.remove(&node_id);
+        self.objects.entry(node_id).or_default();
+        self.scan = Some(Scan::new(node_id, ranges));
+        self.pending = None;
+    }
+
+    pub fn cancel(&mut self) {
+        self.scan = None;
+        self.pending = None;
+    }
+
+    /// Takes the result of the pending upload and queues the next one. Returns whether a scan
+    /// is in progress.
+    pub fn poll(&mut self) -> bool {
+        let Some(scan) = &mut self.scan else {
+            return false;
+        };
+        if let Some((probe, result)) = &mut self.pending {
+            let result = match result.try_recv() {
+                Ok(result) => scan.on_result(
+                    *probe,
+                    &result,
+                    self.objects.entry(scan.node_id).or_default(),
+                ),
+                Err(TryRecvError::Empty) => return true,
+                Err(TryRecvError::Closed) => Err("Driver stopped".to_owned()),
+            };
+            self.pending = None;
+            if let Err(e) = result {
+                self.errors.insert(scan.node_id, e);
+                self.scan = None;
+                return false;
+            }
+        }
+        let Some(probe) = scan.next_probe() else {
+            self.scan = None;
+            return false;
+        };
+        let (request, result) = WriteRequest::new(WriteCommand::ScanUpload {
+            node_id: scan.node_id,
+            index: probe.index,
+            subindex: probe.subindex,
+        });
+        match self.write_sender.try_send(request) {
+            Ok(()) => self.pending = Some((probe, result)),
+            Err(TrySendError::Full(_)) => scan.retry(probe),
+            Err(TrySendError::Closed(_)) => {
+                self.errors
+                    .insert(scan.node_id, "Driver stopped".to_owned());
+                self.scan = None;
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Shows the objects found on the node, `can_scan` while the driver can send uploads.
+    pub fn show(&mut self, ctx: &egui::Context, can_scan: bool) -> Option<OdBrowserAction> {
+        if self.poll() {
+            ctx.request_repaint_after(Duration::from_millis(10));
+        }
+        let node_id = self.node_id?;
+        let mut open = true;
+        let mut action = None;
+        egui::Window::new(format!("Object dictionary — node {node_id}"))
+            .id(egui::Id::new("od_browser"))
+            .open(&mut open)
+            .show(ctx, |ui| {
+                self.show_controls(ui, node_id, can_scan);
+                if let Some(e) = self.errors.get(&node_id) {
+                    ui.colored_label(Color32::RED, e);
+                }
+                ui.separator();
+                match self.objects.get(&node_id) {
+                    Some(objects) if !objects.is_empty() => {
+                        action = self.show_objects(ui, node_id, can_scan);
+                    }
+                    Some(_) => {
+                        ui.label("No object found yet");
+                    }
+                    None => {
+                        ui.label("Not scanned yet");
+                    }
+                }
+            });
+        if !open {
+            self.node_id = None;
+        }
+        action
+    }
+
+    fn show_controls(&mut self, ui: &mut egui::Ui, node_id: NodeId, can_scan: bool) {
+        ui.horizontal(|ui| {
+            ui.label("Ranges:");
+            ui.add(egui::TextEdit::singleline(&mut self.ranges).desired_width(160.0))
+                .on_hover_text("Hexadecimal indexes, e.g. 1000-1FFF, 6000-67FF. The uploads are paced by TX pacing in the ⚙ menu");
+        });
+        ui.horizontal(|ui| match &self.scan {
+            Some(scan) if scan.node_id == node_id => {
+                if ui.button("⏹ Cancel").clicked() {
+                    self.cancel();
+                    return;
+                }
+                ui.add(
+                    egui::ProgressBar::new(scan.progress())
+                        .desired_width(200.0)
+                        .text(format!("0x{:04X}", scan.current)),
+                );
+            }
+            scan => {
+                let busy = scan.is_some();
+                if ui
+                    .add_enabled(can_scan && !busy, egui::Button::new("🔍 Scan"))
+                    .on_hover_text("Upload every index of the ranges")
+                    .on_disabled_hover_text(if busy {
+                        "Another node is being scanned"
+                    } else {
+                        "Needs a connected live session"
+                    })
+                    .clicked()
+                {
+                    self.scan(node_id);
+                }
+            }
+        });
+    }
+
+    fn show_objects(
+        &mut self,
+        ui: &mut egui::Ui,
+        node_id: NodeId,
+        can_scan: bool,
+    ) -> Option<OdBrowserAction> {
+        let eds = eds::get(node_id);
+        let name = |index, subindex| {
+            eds.as_ref()
+                .and_then(|eds| eds.name(index, subindex))
+                .unwrap_or_default()
+        };
+        let mut action = None;
+        let mut refresh = None;
+        let objects = self.objects.get(&node_id)?;
+        ui.label(
+            RichText::new(format!(
+                "{} objects, double click an entry to use it in the sender",
+                objects.len()
+            ))
+            .weak(),
+        );
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for (index, object) in objects {
+                let entry = |ui: &mut egui::Ui, subindex: u8, text: String| {
+                    let response = ui.selectable_label(false, RichText::new(text).monospace());
+                    let value = object
+                        .entries
+                        .get(&subindex)
+                        .and_then(|value| value.clone().ok());
+                    let response = match &value {
+                        Some(value) => response.on_hover_text(format!("{} bytes", value.len())),
+                        None => response,
+                    };
+                    let mut use_in_sender = response.double_clicked();
+                    let mut refresh_object = false;
+                    response.context_menu(|ui| {
+                        if ui.button("Use in sender").clicked() {
+                            use_in_sender = true;
+                            ui.close_menu();
+                        }
+                        if ui
+                            .add_enabled(can_scan, egui::Button::new("⟳ Refresh object"))
+                            .clicked()
+                        {
+                            refresh_object = true;
+                            ui.close_menu();
+                        }
+                    });
+                    let probe = Probe {
+                        index: *index,
+                        subindex,
+                    };
+                    let use_in_sender = use_in_sender.then(|| {
+                        OdBrowserAction::UseInSender(node_id, probe, value.unwrap_or_default())
+                    });
+                    (use_in_sender, refresh_object)
+                };
+                let text = |subindex: u8| {
+                    let value = match &object.entries[&subindex] {
+                        Ok(data) => format!("{:>2} B  {}", data.len(), value_text(data)),
+                        Err(e) => e.clone(),
+                    };
+                    format!(
+                        "{index:04X}:{subindex:02X}  {value}  {}",
+                        name(*index, subindex)
+                    )
+                };
+                let results = if object.is_variable() {
+                    vec![entry(ui, 0, text(0))]
+                } else {
+                    let title = format!(
+                        "{index:04X}  [{}]  {}",
+                        object.entries.len() - 1,
+                        name(*index, 0)
+                    );
+                    egui::CollapsingHeader::new(RichText::new(title).monospace())
+                        .id_salt(("od_object", node_id, *index))
+                        .show(ui, |ui| {
+                            object
+                                .entries
+                                .keys()
+                                .map(|subindex| entry(ui, *subindex, text(*subindex)))
+                                .collect()
+                        })
+                        .body_returned
+                        .unwrap_or_default()
+                };
+                for (use_in_sender, refresh_object) in results {
+                    if use_in_sender.is_some() {
+                        action = use_in_sender;
+                    }
+                    if refresh_object {
+                        refresh = Some(*index);
+                    }
+                }
+            }
+        });
+        if let Some(index) = refresh {
+            self.start(node_id, &[index..=index]);
+        }
+        action
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        parse_ranges, value_text, OdObject, Scan, MAX_TIMEOUTS, OBJECT_MISSING, SUBINDEX_MISSING,
+    };
+    use crate::driver::{WriteError, WriteOutcome};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_scan() {
+        assert_eq!(
+            parse_ranges("1000-1FFF, 0x6041").unwrap(),
+            vec![0x1000..=0x1FFF, 0x6041..=0x6041]
+        );
+        assert!(parse_ranges("2000-1000").is_err());
+        assert!(parse_ranges(" , ").is_err());
+        assert_eq!(
+            value_text(&[0x92, 0x01, 0x02, 0x00]),
+            "92 01 02 00 (131474)"
+        );
+        assert_eq!(value_text(b"Drive X1"), "\"Drive X1\"");
+
+        let abort = |index, subindex, code| {
+            Err(WriteError::SdoAbort {
+                index,
+                subindex,
+                code,
+            })
+        };
+        let data = |data: &[u8]| Ok(WriteOutcome::SdoData(data.to_vec()));
+        let mut objects = BTreeMap::new();
+        let mut scan = Scan::new(5, &[0x1000..=0x1002]);
+        // 0x1000 is a variable holding a count like value, 0x1001 is missing and 0x1002 is an
+        // array of two entries.
+        let replies = [
+            (0x1000, 0, data(&[2])),
+            (0x1000, 1, abort(0x1000, 1, SUBINDEX_MISSING)),
+            (0x1001, 0, abort(0x1001, 0, OBJECT_MISSING)),
+            (0x1002, 0, data(&[2])),
+            (0x1002, 1, data(&[0x34, 0x12])),
+            (0x1002, 2, abort(0x1002, 2, 0x0601_0001)),
+        ];
+        for (index, subindex, reply) in replies {
+            let probe = scan.next_probe().unwrap();
+            assert_eq!((probe.index, probe.subindex), (index, subindex));
+            scan.on_result(probe, &reply, &mut objects).unwrap();
+        }
+        assert!(scan.next_probe().is_none());
+        assert_eq!(
+            objects.keys().copied().collect::<Vec<_>>(),
+            [0x1000, 0x1002]
+        );
+        assert!(objects[&0x1000].is_variable());
+        let array: &OdObject = &objects[&0x1002];
+        assert!(!array.is_variable());
+        assert_eq!(array.entries[&1], Ok(vec![0x34, 0x12]));
+        assert!(array.entries[&2].is_err());
+
+        // Rescanning an object which disappeared forgets it, a silent node ends the scan.
+        let mut scan = Scan::new(5, &[0x1002..=0x1002, 0x2000..=0x2FFF]);
+        let probe = scan.next_probe().unwrap();
+        scan.on_result(probe, &abort(0x1002, 0, OBJECT_MISSING), &mut objects)
+            .unwrap();
+        assert!(!objects.contains_key(&0x1002));
+        for _ in 1..MAX_TIMEOUTS {
+            let probe = scan.next_probe().unwrap();
+            scan.on_result(probe, &Err(WriteError::Timeout), &mut objects)
+                .unwrap();
+        }
+        let probe = scan.next_probe().unwrap();
+        assert!(scan
+            .on_result(probe, &Err(WriteError::Timeout), &mut objects)
+            .is_err());
+    }
+}
@@ -0,0 +1,301 @@
+use crate::{
+    chart::{value_at, HiddenSeries},
+    config,
+    export::ExportJob,
+    pinned_filter::PinnedFilters,
+};
+use catppuccin_egui::FRAPPE;
+use chrono::Local;
+use egui::{Button, Color32, Id};
+use egui_plot::{AxisHints, HPlacement, Line, Plot, PlotPoints};
+use std::{collections::BTreeMap, fmt::Write, ops::RangeInclusive};
+
+/// File of the series hidden in the overlay, in the config directory.
+const CONFIG_FILE: &str = "overlay.bin";
+
+/// Id of the overlay plot, to read its legend.
+const PLOT_ID: &str = "overlay_plot";
+
+/// Colours given to the series in the order they appear.
+const PALETTE: [Color32; 6] = [
+    FRAPPE.red,
+    FRAPPE.blue,
+    FRAPPE.green,
+    FRAPPE.peach,
+    FRAPPE.mauve,
+    FRAPPE.teal,
+];
+
+/// How a pinned filter value is drawn in the overlay.
+#[derive(Debug, Clone, Copy)]
+struct Style {
+    color: Color32,
+    /// Scaled to the right Y axis.
+    secondary: bool,
+}
+
+/// Maps the values of the right axis onto the range of the left one.
+#[derive(Debug, Clone, Copy)]
+struct Scale {
+    left_min: f64,
+    right_min: f64,
+    factor: f64,
+}
+
+impl Scale {
+    /// Scale fitting the range of `right` in the range of `left`, identity without either.
+    fn fit(left: Option<(f64, f64)>, right: Option<(f64, f64)>) -> Self {
+        match (left, right) {
+            (Some((left_min, left_max)), Some((right_min, right_max))) => {
+                let span = right_max - right_min;
+                Self {
+                    left_min,
+                    right_min,
+                    factor: if span > 0.0 {
+                        (left_max - left_min) / span
+                    } else {
+                        1.0
+                    },
+                }
+            }
+            _ => Self {
+                left_min: 0.0,
+                right_min: 0.0,
+                factor: 1.0,
+            },
+        }
+    }
+
+    fn to_left(self, value: f64) -> f64 {
+        self.left_min + (value - self.right_min) * self.factor
+    }
+
+    fn to_right(self, value: f64) -> f64 {
+        self.right_min + (value - self.left_min) / self.factor
+    }
+}
+
+/// Smallest and largest values of some series.
+fn value_range<'a>(series: impl Iterator<Item = &'a [[f64; 2]]>) -> Option<(f64, f64)> {
+    series
+        .flatten()
+        .map(|[_, value]| (*value, *value))
+        .reduce(|(min, max), (value, _)| (min.min(value), max.max(value)))
+}
+
+/// One line per distinct time, one column per series, a cell is empty if its series has no
+/// point at that time. Times are in seconds since the capture start.
+pub fn to_csv(names: &[String], series: &[Vec<[f64; 2]>]) -> String {
+    let mut csv = "time_s".to_owned();
+    for name in names {
+        let _ = write!(csv, ",\"{}\"", name.replace('"', "\"\""));
+    }
+    csv.push('\n');
+    let mut points: Vec<(f64, usize, f64)> = series
+        .iter()
+        .enumerate()
+        .flat_map(|(column, points)| {
+            points
+                .iter()
+                .map(move |[time, value]| (*time, column, *value))
+        })
+        .collect();
+    points.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+    let mut points = points.into_iter().peekable();
+    while let Some((time, column, value)) = points.next() {
+        let mut cells = vec![String::new(); series.len()];
+        cells[column] = value.to_string();
+        while let Some((_, column, value)) =
+            points.next_if(|(next, _, _)| next.total_cmp(&time).is_eq())
+        {
+            cells[column] = value.to_string();
+        }
+        let _ = writeln!(csv, "{time:.6},{}", cells.join(","));
+    }
+    csv
+}
+
+/// Values of the pinned filters overlaid in one plot on the time since the capture start.
+#[derive(Debug)]
+pub struct OverlayPlot {
+    /// Per pinned filter id.
+    styles: BTreeMap<u64, Style>,
+    /// Names of the values hidden in the legend.
+    hidden: HiddenSeries,
+    /// Time span shown in the last frame, what the export writes.
+    visible: Option<RangeInclusive<f64>>,
+    export: ExportJob,
+}
+
+impl Default for OverlayPlot {
+    fn default() -> Self {
+        Self {
+            styles: BTreeMap::new(),
+            hidden: HiddenSeries::default(),
+            visible: None,
+            export: ExportJob::new("points"),
+        }
+    }
+}
+
+impl OverlayPlot {
+    /// Overlay hiding the values hidden in a previous run.
+    pub fn load() -> Self {
+        Self {
+            hidden: config::load(CONFIG_FILE).unwrap_or_default(),
+            ..Self::default()
+        }
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui, pinned: &PinnedFilters, height: f32) {
+        let series: Vec<(u64, String, Vec<[f64; 2]>)> = pinned
+            .value_series()
+            .map(|(id, name, points)| (id, name, points.iter().copied().collect()))
+            .collect();
+        if series.is_empty() {
+            ui.label("No value to plot, enable 📉 on a pinned filter to overlay its value here");
+            return;
+        }
+        self.styles
+            .retain(|id, _| series.iter().any(|(other, _, _)| other == id));
+        for (i, (id, name, _)) in series.iter().enumerate() {
+            let style = self.styles.entry(*id).or_insert(Style {
+                color: PALETTE[i % PALETTE.len()],
+                secondary: false,
+            });
+            ui.horizontal(|ui| {
+                ui.label(name);
+                ui.color_edit_button_srgba(&mut style.color);
+                ui.checkbox(&mut style.secondary, "right axis")
+                    .on_hover_text("Scale this value on the right Y axis");
+            });
+        }
+        let styled: Vec<(&String, &Vec<[f64; 2]>, Style)> = series
+            .iter()
+            .filter_map(|(id, name, points)| Some((name, points, *self.styles.get(id)?)))
+            .collect();
+        let shown: Vec<(&String, &Vec<[f64; 2]>, Style)> = styled
+            .iter()
+            .copied()
+            .filter(|(name, _, _)| !self.hidden.contains(name))
+            .collect();
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(
+                    self.export.is_idle() && self.visible.is_some() && !shown.is_empty(),
+                    Button::new("Export CSV…"),
+                )
+                .on_hover_text("Write the shown values within the visible time span to a CSV file in the working directory")
+                .clicked()
+            {
+                self.export_visible(&shown);
+            }
+            self.export.ui(ui);
+        });
+
+        let on_side = |secondary: bool| {
+            value_range(
+                shown
+                    .iter()
+                    .filter(move |(_, _, style)| style.secondary == secondary)
+                    .map(|(_, points, _)| points.as_slice()),
+            )
+        };
+        let scale = Scale::fit(on_side(false), on_side(true));
+        let any_secondary = shown.iter().any(|(_, _, style)| style.secondary);
+        let mut plot = Plot::new("overlay_plot")
+            .id(Id::new(PLOT_ID))
+            .height(height)
+            .x_axis_label("Time (s)")
+            .legend(self.hidden.legend())
+            .label_formatter(|_, value| {
+                let mut text = format!("Time: {:.3} s", value.x);
+                for (name, points, _) in &shown {
+                    if let Some(value) = value_at(points, value.x) {
+                        let _ = write!(text, "\n{name}: {value}");
+                    }
+                }
+                text
+            });
+        if any_secondary {
+            plot = plot.custom_y_axes(vec![
+                AxisHints::new_y(),
+                AxisHints::new_y()
+                    .placement(HPlacement::Right)
+                    .formatter(move |mark, _| format!("{:.2}", scale.to_right(mark.value))),
+            ]);
+        }
+        // The hidden values are left out by the plot, still listed in its legend.
+        let response = plot.show(ui, |plot_ui| {
+            for (name, points, style) in &styled {
+                let points: Vec<[f64; 2]> = if style.secondary {
+                    points
+                        .iter()
+                        .map(|[time, value]| [*time, scale.to_left(*value)])
+                        .collect()
+                } else {
+                    (*points).clone()
+                };
+                plot_ui.line(
+                    Line::new(PlotPoints::new(points))
+                        .color(style.color)
+                        .name(name.as_str()),
+                );
+            }
+        });
+        self.visible = Some(response.transform.bounds().range_x());
+        let names = series.iter().map(|(_, name, _)| name.as_str());
+        if self.hidden.update(ui.ctx(), Id::new(PLOT_ID), names) {
+            if let Err(e) = config::save(CONFIG_FILE, &self.hidden) {
+                log::warn!("Can't save the hidden values: {e}");
+            }
+        }
+    }
+
+    fn export_visible(&mut self, shown: &[(&String, &Vec<[f64; 2]>, Style)]) {
+        let Some(visible) = self.visible.clone() else {
+            return;
+        };
+        let names: Vec<String> = shown.iter().map(|(name, _, _)| (*name).clone()).collect();
+        let series: Vec<Vec<[f64; 2]>> = shown
+            .iter()
+            .map(|(_, points, _)| {
+                points
+                    .iter()
+                    .copied()
+                    .filter(|[time, _]| visible.contains(time))
+                    .collect()
+            })
+            .collect();
+        let rows = series.iter().map(Vec::len).sum();
+        let path = format!("values-{}.csv", Local::now().format("%Y%m%d-%H%M%S"));
+        self.export
+            .start(path, rows, move || to_csv(&names, &series));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{to_csv, Scale};
+
+    #[test]
+    fn test_overlay_csv_and_scale() {
+        let names = ["target".to_owned(), "actual \"pos\"".to_owned()];
+        let series = [
+            vec![[0.5, 10.0], [1.0, 20.0]],
+            vec![[1.0, 19.5], [1.25, 21.0]],
+        ];
+        assert_eq!(
+            to_csv(&names, &series),
+            "time_s,\"target\",\"actual \"\"pos\"\"\"\n\
+             0.500000,10,\n\
+             1.000000,20,19.5\n\
+             1.250000,,21\n"
+        );
+
+        let scale = Scale::fit(Some((0.0, 100.0)), Some((-1.0, 1.0)));
+        assert!((scale.to_left(1.0) - 100.0).abs() < 1e-9);
+        assert!((scale.to_left(0.0) - 50.0).abs() < 1e-9);
+        assert!((scale.to_right(25.0) + 0.5).abs() < 1e-9);
+    }
+}
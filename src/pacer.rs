@@ -0,0 +1,116 @@
+use std::{fmt, time::Duration};
+use tokio::time::{sleep_until, Instant};
+
+/// Default maximum rate of bulk transmissions, in frames per second.
+pub const DEFAULT_TX_RATE: u32 = 500;
+
+/// Throughput achieved by a bulk operation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BulkThroughput {
+    pub name: String,
+    pub frames: u64,
+    pub elapsed: Duration,
+}
+
+impl BulkThroughput {
+    /// Effective rate in frames per second.
+    #[allow(clippy::cast_precision_loss)]
+    pub fn rate(&self) -> f64 {
+        let secs = self.elapsed.as_secs_f64();
+        if secs > 0.0 {
+            self.frames as f64 / secs
+        } else {
+            0.0
+        }
+    }
+}
+
+impl fmt::Display for BulkThroughput {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} frames in {} ms ({:.0} frames/s)",
+            self.name,
+            self.frames,
+            self.elapsed.as_millis(),
+            self.rate()
+        )
+    }
+}
+
+/// Limits the frame rate of bulk operations (node scan, script replay, DCF apply...).
+///
+/// Frames sent outside of a bulk operation are not delayed.
+#[derive(Debug, Default)]
+pub struct TxPacer {
+    min_gap: Option<Duration>,
+    last: Option<Instant>,
+    bulk: Option<(String, Instant, u64)>,
+}
+
+impl TxPacer {
+    /// Sets the maximum rate in frames per second, `None` disables pacing.
+    pub fn set_rate(&mut self, rate: Option<u32>) {
+        self.min_gap = rate.filter(|r| *r > 0).map(|r| Duration::from_secs(1) / r);
+    }
+
+    pub fn begin_bulk(&mut self, name: String) {
+        self.bulk = Some((name, Instant::now(), 0));
+    }
+
+    /// Ends the bulk operation and returns its throughput.
+    pub fn end_bulk(&mut self) -> Option<BulkThroughput> {
+        let (name, started, frames) = self.bulk.take()?;
+        Some(BulkThroughput {
+            name,
+            frames,
+            elapsed: started.elapsed(),
+        })
+    }
+
+    /// Time at which the next frame of a bulk operation may be sent.
+    fn next_slot(&self) -> Option<Instant> {
+        self.bulk.as_ref()?;
+        Some(self.last? + self.min_gap?)
+    }
+
+    /// Waits until the next frame may be sent and accounts for it.
+    pub async fn pace(&mut self) {
+        if let Some(slot) = self.next_slot() {
+            sleep_until(slot).await;
+        }
+        if let Some((_, _, frames)) = &mut self.bulk {
+            *frames += 1;
+            self.last = Some(Instant::now());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TxPacer;
+    use std::time::Duration;
+    use tokio::time::Instant;
+
+    #[tokio::test]
+    async fn test_pacer() {
+        let mut pacer = TxPacer::default();
+        pacer.set_rate(Some(100));
+
+        // Single sends are not delayed.
+        let start = Instant::now();
+        for _ in 0..5 {
+            pacer.pace().await;
+        }
+        assert!(start.elapsed() < Duration::from_millis(40));
+
+        pacer.begin_bulk("scan".to_owned());
+        for _ in 0..5 {
+            pacer.pace().await;
+        }
+        let throughput = pacer.end_bulk().unwrap();
+        assert_eq!(throughput.frames, 5);
+        assert!(throughput.elapsed >= Duration::from_millis(40));
+        assert!(pacer.end_bulk().is_none());
+    }
+}
@@ -0,0 +1,455 @@
+use crate::message_cached::Direction;
+use std::io::{self, Write};
+
+/// Link type of the `SocketCAN` frames, dissected by Wireshark as CAN and `CANopen`.
+pub const LINKTYPE_CAN_SOCKETCAN: u16 = 227;
+
+/// Flag of the CAN ID of an error frame, the ID holds the error classes.
+pub const CAN_ERR_FLAG: u32 = 0x2000_0000;
+
+/// Flag of the CAN ID of a remote frame.
+pub const CAN_RTR_FLAG: u32 = 0x4000_0000;
+
+/// Flag of the CAN ID of an extended frame.
+pub const CAN_EFF_FLAG: u32 = 0x8000_0000;
+
+const SECTION_HEADER: u32 = 0x0A0D_0D0A;
+const INTERFACE_DESCRIPTION: u32 = 1;
+const ENHANCED_PACKET: u32 = 6;
+const BYTE_ORDER_MAGIC: u32 = 0x1A2B_3C4D;
+
+const OPT_END: u16 = 0;
+const OPT_COMMENT: u16 = 1;
+const OPT_SHB_USERAPPL: u16 = 4;
+const OPT_IF_NAME: u16 = 2;
+const OPT_IF_TSRESOL: u16 = 9;
+const OPT_EPB_FLAGS: u16 = 2;
+
+/// Bytes of a classic `SocketCAN` frame: ID, length, 3 reserved bytes and 8 data bytes.
+const FRAME_LEN: u32 = 16;
+
+/// Inbound and outbound values of the direction bits of the packet flags.
+const FLAGS_INBOUND: u32 = 1;
+const FLAGS_OUTBOUND: u32 = 2;
+
+/// CAN frame of a pcapng capture.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PcapFrame {
+    /// Microseconds since the epoch.
+    pub time_us: i64,
+    /// CAN ID with the `SocketCAN` flags.
+    pub can_id: u32,
+    pub data: Vec<u8>,
+    pub direction: Direction,
+    /// Comment of the packet, e.g. a bookmark.
+    pub comment: Option<String>,
+}
+
+impl PcapFrame {
+    pub fn is_error(&self) -> bool {
+        self.can_id & CAN_ERR_FLAG != 0
+    }
+}
+
+/// Writes a pcapng capture of `SocketCAN` frames, opened as is by Wireshark.
+#[derive(Debug)]
+pub struct PcapngWriter<W: Write> {
+    out: W,
+    /// Interfaces described so far, by index.
+    interfaces: Vec<String>,
+    block: Vec<u8>,
+}
+
+impl<W: Write> PcapngWriter<W> {
+    /// Writes the section header to `out`.
+    ///
+    /// # Errors
+    /// If `out` can't be written.
+    pub fn new(out: W) -> io::Result<Self> {
+        let mut writer = Self {
+            out,
+            interfaces: Vec::new(),
+            block: Vec::new(),
+        };
+        writer.write_block(SECTION_HEADER, |block| {
+            block.extend_from_slice(&BYTE_ORDER_MAGIC.to_le_bytes());
+            block.extend_from_slice(&1u16.to_le_bytes());
+            block.extend_from_slice(&0u16.to_le_bytes());
+            // Section length not known in advance.
+            block.extend_from_slice(&(-1i64).to_le_bytes());
+            option(block, OPT_SHB_USERAPPL, env!("CARGO_PKG_NAME").as_bytes());
+            option(block, OPT_END, &[]);
+        })?;
+        Ok(writer)
+    }
+
+    /// Index of `name` in the capture, described by a new interface block the first time.
+    fn interface(&mut self, name: &str) -> io::Result<u32> {
+        if let Some(index) = self
+            .interfaces
+            .iter()
+            .position(|interface| interface == name)
+        {
+            return Ok(u32::try_from(index).unwrap_or(u32::MAX));
+        }
+        self.write_block(INTERFACE_DESCRIPTION, |block| {
+            block.extend_from_slice(&LINKTYPE_CAN_SOCKETCAN.to_le_bytes());
+            block.extend_from_slice(&0u16.to_le_bytes());
+            block.extend_from_slice(&FRAME_LEN.to_le_bytes());
+            option(block, OPT_IF_NAME, name.as_bytes());
+            // Microseconds, the default, written for the readers that want it.
+            option(block, OPT_IF_TSRESOL, &[6]);
+            option(block, OPT_END, &[]);
+        })?;
+        self.interfaces.push(name.to_owned());
+        let index = self.interfaces.len() - 1;
+        Ok(u32::try_from(index).unwrap_or(u32::MAX))
+    }
+
+    /// Appends a frame received or sent on `interface`.
+    ///
+    /// # Errors
+    /// If the output can't be written.
+    pub fn write(&mut self, interface: &str, frame: &PcapFrame) -> io::Result<()> {
+        let interface = self.interface(interface)?;
+        let time = u64::try_from(frame.time_us).unwrap_or_default();
+        self.write_block(ENHANCED_PACKET, |block| {
+            block.extend_from_slice(&interface.to_le_bytes());
+            block.extend_from_slice(&u32::try_from(time >> 32).unwrap_or(u32::MAX).to_le_bytes());
+            block.extend_from_slice(
+                &u32::try_from(time & 0xFFFF_FFFF)
+                    .unwrap_or_default()
+                    .to_le_bytes(),
+            );
+            block.extend_from_slice(&FRAME_LEN.to_le_bytes());
+            block.extend_from_slice(&FRAME_LEN.to_le_bytes());
+            // SocketCAN header, the ID in network byte order.
+            let len = frame.data.len().min(8);
+            block.extend_from_slice(&frame.can_id.to_be_bytes());
+            block.extend_from_slice(&[u8::try_from(len).unwrap_or(8), 0, 0, 0]);
+            block.extend_from_slice(&frame.data[..len]);
+            block.resize(block.len() + 8 - len, 0);
+            let flags = match frame.direction {
+                Direction::Rx => FLAGS_INBOUND,
+                Direction::Tx => FLAGS_OUTBOUND,
+            };
+            option(block, OPT_EPB_FLAGS, &flags.to_le_bytes());
+            if let Some(comment) = &frame.comment {
+                option(block, OPT_COMMENT, comment.as_bytes());
+            }
+            option(block, OPT_END, &[]);
+        })
+    }
+
+    /// # Errors
+    /// If the output can't be written.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+
+    /// Writes a block of `kind` with the body filled by `body`, framed by its length.
+    fn write_block(&mut self, kind: u32, body: impl FnOnce(&mut Vec<u8>)) -> io::Result<()> {
+        self.block.clear();
+        self.block.extend_from_slice(&kind.to_le_bytes());
+        self.block.extend_from_slice(&0u32.to_le_bytes());
+        body(&mut self.block);
+        let len = u32::try_from(self.block.len() + 4)
+            .unwrap_or(u32::MAX)
+            .to_le_bytes();
+        self.block[4..8].copy_from_slice(&len);
+        self.block.extend_from_slice(&len);
+        self.out.write_all(&self.block)
+    }
+}
+
+/// Appends an option, its value padded to 32 bits.
+fn option(block: &mut Vec<u8>, code: u16, value: &[u8]) {
+    block.extend_from_slice(&code.to_le_bytes());
+    block.extend_from_slice(&u16::try_from(value.len()).unwrap_or(u16::MAX).to_le_bytes());
+    block.extend_from_slice(value);
+    block.resize(block.len() + (4 - value.len() % 4) % 4, 0);
+}
+
+/// Interfaces and CAN frames of a pcapng capture.
+#[derive(Debug, Default)]
+pub struct PcapCapture {
+    /// Names of the interfaces, empty if not given.
+    pub interfaces: Vec<String>,
+    /// Frames with the index of their interface, in the order of the file.
+    pub frames: Vec<(usize, PcapFrame)>,
+}
+
+/// Tells a pcapng file by its first block, the same in both byte orders.
+pub fn is_pcapng(bytes: &[u8]) -> bool {
+    bytes.starts_with(&SECTION_HEADER.to_le_bytes())
+}
+
+/// Block fields in the byte order of their section.
+#[derive(Debug, Clone, Copy)]
+struct Fields<'a> {
+    bytes: &'a [u8],
+    big_endian: bool,
+}
+
+impl Fields<'_> {
+    fn u16(&self, at: usize) -> Result<u16, String> {
+        let bytes = self.bytes.get(at..at + 2).ok_or("truncated block")?;
+        let bytes = [bytes[0], bytes[1]];
+        Ok(if self.big_endian {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        })
+    }
+
+    fn u32(&self, at: usize) -> Result<u32, String> {
+        let bytes = self.bytes.get(at..at + 4).ok_or("truncated block")?;
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        Ok(if self.big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        })
+    }
+
+    /// Options from `at` to the end of the block body.
+    fn options(&self, mut at: usize) -> Vec<(u16, &[u8])> {
+        let mut options = Vec::new();
+        while let (Ok(code), Ok(len)) = (self.u16(at), self.u16(at + 2)) {
+            let len = usize::from(len);
+            let Some(value) = self.bytes.get(at + 4..at + 4 + len) else {
+                break;
+            };
+            if code == OPT_END {
+                break;
+            }
+            options.push((code, value));
+            at += 4 + len.next_multiple_of(4);
+        }
+        options
+    }
+}
+
+/// Interface of the section being read.
+struct Interface {
+    /// Index in the capture.
+    index: usize,
+    link_type: u16,
+    /// Timestamp units per second.
+    resolution: u64,
+}
+
+/// Reads the CAN frames of a pcapng capture, the packets of other link types and the other
+/// blocks are skipped.
+///
+/// # Errors
+/// If the file isn't a pcapng capture or is truncated.
+pub fn read(bytes: &[u8]) -> Result<PcapCapture, String> {
+    if !is_pcapng(bytes) {
+        return Err("not a pcapng file".to_owned());
+    }
+    let mut capture = PcapCapture::default();
+    let mut interfaces = Vec::new();
+    let mut big_endian = false;
+    let mut offset = 0;
+    while offset < bytes.len() {
+        let header = Fields {
+            bytes: &bytes[offset..],
+            big_endian,
+        };
+        let kind = header.u32(0)?;
+        if kind == SECTION_HEADER {
+            big_endian = match bytes.get(offset + 8..offset + 12) {
+                Some(magic) if magic == BYTE_ORDER_MAGIC.to_le_bytes() => false,
+                Some(magic) if magic == BYTE_ORDER_MAGIC.to_be_bytes() => true,
+                _ => return Err(format!("bad section header at byte {offset}")),
+            };
+            interfaces.clear();
+        }
+        let fields = Fields {
+            bytes: &bytes[offset..],
+            big_endian,
+        };
+        let len = usize::try_from(fields.u32(4)?).unwrap_or(usize::MAX);
+        if len < 12 || len % 4 != 0 || bytes.len() - offset < len {
+            return Err(format!("truncated block at byte {offset}"));
+        }
+        let body = Fields {
+            bytes: &bytes[offset + 8..offset + len - 4],
+            big_endian,
+        };
+        match kind {
+            INTERFACE_DESCRIPTION => {
+                let options = body.options(8);
+                let name =
+                    options
+                        .iter()
+                        .find(|(code, _)| *code == OPT_IF_NAME)
+                        .map(|(_, name)| {
+                            String::from_utf8_lossy(name)
+                                .trim_end_matches('\0')
+                                .to_owned()
+                        });
+                let resolution = match options.iter().find(|(code, _)| *code == OPT_IF_TSRESOL) {
+                    Some((_, [exponent, ..])) if exponent & 0x80 != 0 => {
+                        1u64.checked_shl(u32::from(exponent & 0x7F))
+                    }
+                    Some((_, [exponent, ..])) => 10u64.checked_pow(u32::from(*exponent)),
+                    _ => Some(1_000_000),
+                };
+                interfaces.push(Interface {
+                    index: capture.interfaces.len(),
+                    link_type: body.u16(0)?,
+                    resolution: resolution.ok_or("bad timestamp resolution")?,
+                });
+                capture.interfaces.push(name.unwrap_or_default());
+            }
+            ENHANCED_PACKET => {
+                let interface = usize::try_from(body.u32(0)?).unwrap_or(usize::MAX);
+                let interface = interfaces.get(interface).ok_or_else(|| {
+                    format!("packet of an undescribed interface at byte {offset}")
+                })?;
+                if interface.link_type == LINKTYPE_CAN_SOCKETCAN {
+                    let frame = enhanced_packet(body, interface.resolution)
+                        .ok_or_else(|| format!("malformed packet at byte {offset}"))?;
+                    capture.frames.push((interface.index, frame));
+                }
+            }
+            _ => {}
+        }
+        offset += len;
+    }
+    Ok(capture)
+}
+
+/// CAN frame of an enhanced packet block, `None` if malformed.
+fn enhanced_packet(body: Fields, resolution: u64) -> Option<PcapFrame> {
+    let time = u64::from(body.u32(4).ok()?) << 32 | u64::from(body.u32(8).ok()?);
+    let captured = usize::try_from(body.u32(12).ok()?).ok()?;
+    let packet = body.bytes.get(20..20 + captured)?;
+    let can_id = u32::from_be_bytes(packet.get(0..4)?.try_into().ok()?);
+    let len = usize::from(*packet.get(4)?).min(packet.len().saturating_sub(8));
+    let data = packet.get(8..8 + len)?.to_vec();
+    let options = body.options(20 + captured.next_multiple_of(4));
+    let flags = options
+        .iter()
+        .find(|(code, _)| *code == OPT_EPB_FLAGS)
+        .and_then(|(_, value)| {
+            Fields {
+                bytes: value,
+                big_endian: body.big_endian,
+            }
+            .u32(0)
+            .ok()
+        });
+    let comment = options
+        .iter()
+        .find(|(code, _)| *code == OPT_COMMENT)
+        .map(|(_, comment)| String::from_utf8_lossy(comment).into_owned());
+    let time_us = u128::from(time) * 1_000_000 / u128::from(resolution.max(1));
+    Some(PcapFrame {
+        time_us: i64::try_from(time_us).ok()?,
+        can_id,
+        data,
+        direction: match flags.map(|flags| flags & 0b11) {
+            Some(FLAGS_OUTBOUND) => Direction::Tx,
+            _ => Direction::Rx,
+        },
+        comment,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read, PcapFrame, PcapngWriter, CAN_ERR_FLAG};
+    use crate::{
+        annotations::Annotations,
+        export::{to_pcapng, Capture},
+        gateway::GatewaySide,
+        log_import::{parse_bytes, FileFormat},
+        message_cached::{Direction, MessageCached},
+    };
+    use chrono::{Local, TimeZone};
+    use oze_canopen::canopen::RxMessage;
+    use std::{sync::atomic::AtomicUsize, time::Duration};
+    use tokio::time::Instant;
+
+    #[test]
+    fn test_pcapng_round_trip() {
+        let capture = Capture {
+            start: Instant::now(),
+            start_wall: Local.timestamp_opt(1_436_509_052, 0).unwrap(),
+            can_name: "can0".to_owned(),
+            peer: Some("can1".to_owned()),
+            annotations: Annotations::default(),
+        };
+        let frame = |index, micros, cob_id, data: &[u8]| {
+            let mut raw = RxMessage {
+                timestamp: capture.start + Duration::from_micros(micros),
+                cob_id,
+                data: [0; 8],
+                dlc: data.len(),
+            };
+            raw.data[..data.len()].copy_from_slice(data);
+            MessageCached::new(index, raw)
+        };
+        let mut sent = frame(0, 1_000, 0x601, &[0x40, 0x18, 0x10, 0x01]);
+        sent.direction = Direction::Tx;
+        let mut pdo = frame(1, 249_713, 0x181, &[0x01, 0xAB]);
+        pdo.origin = Some(GatewaySide::B);
+        let sync = frame(2, 1_500_000, 0x080, &[]);
+        let rows = [sent, pdo, sync];
+
+        let bytes = to_pcapng(&rows, &capture);
+        let read_back = read(&bytes).unwrap();
+        assert_eq!(read_back.interfaces, ["can0", "can1"]);
+        let interfaces: Vec<usize> = read_back
+            .frames
+            .iter()
+            .map(|(interface, _)| *interface)
+            .collect();
+        assert_eq!(interfaces, [0, 1, 0]);
+        assert_eq!(read_back.frames[1].1.time_us, 1_436_509_052_249_713);
+
+        let (format, file) = parse_bytes(&bytes, &AtomicUsize::new(0)).unwrap();
+        assert_eq!(format, FileFormat::Pcapng);
+        assert_eq!(file.can_name, "can0");
+        assert_eq!(file.start_us, 1_436_509_052_001_000);
+        assert_eq!(file.messages.len(), rows.len());
+        for (saved, msg) in file.messages.iter().zip(&rows) {
+            assert_eq!(saved.cob_id, msg.msg.msg.cob_id);
+            assert_eq!(saved.data, msg.msg.msg.data[..msg.msg.msg.dlc]);
+            assert_eq!(saved.direction, msg.direction);
+            let time_us = msg
+                .get_timestamp()
+                .duration_since(rows[0].get_timestamp())
+                .as_micros();
+            assert_eq!(u128::from(saved.time_us), time_us);
+        }
+
+        // Error frames keep their flag and are left out of the import.
+        let error = PcapFrame {
+            time_us: 1_700_000_000_000_000,
+            can_id: CAN_ERR_FLAG | 0x0004,
+            data: vec![0, 0x08, 0, 0, 0, 0, 0, 0],
+            direction: Direction::Rx,
+            comment: Some("bus warning".to_owned()),
+        };
+        let mut writer = PcapngWriter::new(Vec::new()).unwrap();
+        writer.write("can0", &error).unwrap();
+        let bytes = writer.into_inner();
+        assert_eq!(read(&bytes).unwrap().frames, [(0, error)]);
+        assert!(parse_bytes(&bytes, &AtomicUsize::new(0))
+            .unwrap()
+            .1
+            .messages
+            .is_empty());
+        assert!(read(&bytes[..bytes.len() - 4])
+            .unwrap_err()
+            .starts_with("truncated block"));
+    }
+}
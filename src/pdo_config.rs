@@ -0,0 +1,561 @@
+use crate::eds;
+use egui::{Color32, ComboBox, RichText, TextEdit};
+use oze_canopen::canopen::NodeId;
+use std::{collections::BTreeMap, fmt, str::FromStr};
+
+/// Bit 31 of the COB-ID of a PDO, set while the PDO is disabled.
+pub const PDO_DISABLED: u32 = 0x8000_0000;
+
+/// Objects a PDO maps at most.
+pub const MAX_MAPPED: usize = 8;
+
+/// Kind and number of a PDO, the first four of each kind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PdoId {
+    pub transmit: bool,
+    /// 1 to 4.
+    pub number: u8,
+}
+
+impl Default for PdoId {
+    fn default() -> Self {
+        Self {
+            transmit: true,
+            number: 1,
+        }
+    }
+}
+
+impl PdoId {
+    pub const ALL: [Self; 8] = [
+        Self::rpdo(1),
+        Self::rpdo(2),
+        Self::rpdo(3),
+        Self::rpdo(4),
+        Self::tpdo(1),
+        Self::tpdo(2),
+        Self::tpdo(3),
+        Self::tpdo(4),
+    ];
+
+    const fn rpdo(number: u8) -> Self {
+        Self {
+            transmit: false,
+            number,
+        }
+    }
+
+    const fn tpdo(number: u8) -> Self {
+        Self {
+            transmit: true,
+            number,
+        }
+    }
+
+    /// Index of the communication parameters, 0x1400 or 0x1800 onwards.
+    pub fn communication_index(self) -> u16 {
+        let base = if self.transmit { 0x1800 } else { 0x1400 };
+        base + u16::from(self.number - 1)
+    }
+
+    /// Index of the mapping parameters, 0x1600 or 0x1A00 onwards.
+    pub fn mapping_index(self) -> u16 {
+        self.communication_index() + 0x200
+    }
+
+    /// COB-ID of the predefined connection set.
+    pub fn default_cob_id(self, node_id: NodeId) -> u16 {
+        let base = if self.transmit { 0x180 } else { 0x200 };
+        base + 0x100 * u16::from(self.number - 1) + u16::from(node_id)
+    }
+}
+
+impl fmt::Display for PdoId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let kind = if self.transmit { "TPDO" } else { "RPDO" };
+        write!(f, "{kind}{}", self.number)
+    }
+}
+
+/// Object mapped into a PDO, stored as `0xIIIISSLL` in the mapping parameters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MappedObject {
+    pub index: u16,
+    pub subindex: u8,
+    pub bits: u8,
+}
+
+impl MappedObject {
+    pub fn raw(self) -> u32 {
+        u32::from(self.index) << 16 | u32::from(self.subindex) << 8 | u32::from(self.bits)
+    }
+}
+
+impl From<u32> for MappedObject {
+    #[allow(clippy::cast_possible_truncation)]
+    fn from(raw: u32) -> Self {
+        Self {
+            index: (raw >> 16) as u16,
+            subindex: (raw >> 8) as u8,
+            bits: raw as u8,
+        }
+    }
+}
+
+/// As typed in the mapping editor, `6041:00/16`.
+impl fmt::Display for MappedObject {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04X}:{:02X}/{}", self.index, self.subindex, self.bits)
+    }
+}
+
+impl FromStr for MappedObject {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!("invalid mapped object {text}, expected index:subindex/bits e.g. 6041:00/16")
+        };
+        let (object, bits) = text.trim().split_once('/').ok_or_else(invalid)?;
+        let (index, subindex) = object.split_once(':').ok_or_else(invalid)?;
+        let index = index.trim().trim_start_matches("0x");
+        Ok(Self {
+            index: u16::from_str_radix(index, 16).map_err(|_| invalid())?,
+            subindex: u8::from_str_radix(subindex.trim(), 16).map_err(|_| invalid())?,
+            bits: bits
+                .trim()
+                .parse()
+                .ok()
+                .filter(|bits| (1..=64).contains(bits))
+                .ok_or_else(invalid)?,
+        })
+    }
+}
+
+/// Communication and mapping parameters of a PDO.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PdoParameters {
+    pub pdo: PdoId,
+    /// Sub-index 1 of the communication parameters, with [`PDO_DISABLED`].
+    pub cob_id: u32,
+    pub transmission_type: Option<u8>,
+    /// In multiples of 100 µs.
+    pub inhibit_time: Option<u16>,
+    /// In milliseconds.
+    pub event_timer: Option<u16>,
+    pub mapping: Vec<MappedObject>,
+}
+
+impl PdoParameters {
+    pub fn enabled(&self) -> bool {
+        self.cob_id & PDO_DISABLED == 0
+    }
+
+    /// Length of the mapped objects, at most 64 bits fit a frame.
+    pub fn mapped_bits(&self) -> u32 {
+        self.mapping
+            .iter()
+            .map(|object| u32::from(object.bits))
+            .sum()
+    }
+}
+
+/// Transmission type with its meaning.
+pub fn transmission_type_text(transmission_type: u8) -> String {
+    match transmission_type {
+        0 => "0 acyclic, on SYNC".to_owned(),
+        1 => "1 every SYNC".to_owned(),
+        n @ 2..=240 => format!("{n} every {n} SYNC"),
+        252 => "252 RTR, on SYNC".to_owned(),
+        253 => "253 RTR".to_owned(),
+        254 => "254 event, manufacturer".to_owned(),
+        255 => "255 event, profile".to_owned(),
+        n => format!("{n} reserved"),
+    }
+}
+
+/// Value of up to 4 bytes, little endian.
+pub fn le_value(data: &[u8]) -> u32 {
+    let mut bytes = [0u8; 4];
+    let len = data.len().min(4);
+    bytes[..len].copy_from_slice(&data[..len]);
+    u32::from_le_bytes(bytes)
+}
+
+/// PDO configuration of a node read over SDO.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PdoConfig {
+    /// Parameters of every PDO, or why they couldn't be read, e.g. the node aborts the reads of
+    /// the PDOs it doesn't implement.
+    pub pdos: Vec<(PdoId, Result<PdoParameters, String>)>,
+    /// Local time of the read, RFC 3339.
+    pub read_at: String,
+}
+
+impl fmt::Display for PdoConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let read = self.pdos.iter().filter(|(_, pdo)| pdo.is_ok()).count();
+        write!(f, "{read} of {} PDOs read", self.pdos.len())
+    }
+}
+
+/// Action requested from the PDO configuration window.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PdoConfigAction {
+    /// Read the configuration of the node again.
+    Read(NodeId),
+    /// Copy the parameters of a PDO into the mapping editor of the sender.
+    Edit(NodeId, PdoParameters),
+}
+
+/// Window with the PDO configuration of the node picked in the inventory.
+#[derive(Debug, Default)]
+pub struct PdoConfigPanel {
+    node_id: Option<NodeId>,
+    /// Results of the last read of every node, as reported by the driver.
+    configs: BTreeMap<NodeId, Result<PdoConfig, String>>,
+}
+
+impl PdoConfigPanel {
+    pub fn open(&mut self, node_id: NodeId) {
+        self.node_id = Some(node_id);
+    }
+
+    /// Takes the results of the reads reported by the driver.
+    pub fn sync(&mut self, configs: &BTreeMap<NodeId, Result<PdoConfig, String>>) {
+        if self.configs != *configs {
+            self.configs.clone_from(configs);
+        }
+    }
+
+    pub fn is_read(&self, node_id: NodeId) -> bool {
+        self.configs.contains_key(&node_id)
+    }
+
+    /// Node shown, `None` when the window is closed.
+    pub fn node_id(&self) -> Option<NodeId> {
+        self.node_id
+    }
+
+    /// Shows the configuration of the node, `reading` while a read is in progress, which
+    /// `can_read` allows.
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        reading: bool,
+        can_read: bool,
+    ) -> Option<PdoConfigAction> {
+        let node_id = self.node_id?;
+        let mut open = true;
+        let mut action = None;
+        egui::Window::new(format!("PDO configuration — node {node_id}"))
+            .id(egui::Id::new("pdo_config"))
+            .open(&mut open)
+            .show(ctx, |ui| {
+                match self.configs.get(&node_id) {
+                    Some(Ok(config)) => {
+                        action = Self::show_config(ui, node_id, config);
+                    }
+                    Some(Err(e)) => {
+                        ui.colored_label(Color32::RED, e);
+                    }
+                    None if reading => {}
+                    None => {
+                        ui.label("Not read yet");
+                    }
+                }
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(can_read && !reading, egui::Button::new("⟳ Read PDO config"))
+                        .on_hover_text(
+                            "Read 0x1400-0x1403, 0x1600-0x1603, 0x1800-0x1803 and 0x1A00-0x1A03",
+                        )
+                        .clicked()
+                    {
+                        action = Some(PdoConfigAction::Read(node_id));
+                    }
+                    if reading {
+                        ui.spinner();
+                    }
+                });
+            });
+        if !open {
+            self.node_id = None;
+        }
+        action
+    }
+
+    fn show_config(
+        ui: &mut egui::Ui,
+        node_id: NodeId,
+        config: &PdoConfig,
+    ) -> Option<PdoConfigAction> {
+        let eds = eds::get(node_id);
+        let mut action = None;
+        egui::ScrollArea::both().show(ui, |ui| {
+            egui::Grid::new("pdo_config_grid")
+                .striped(true)
+                .show(ui, |ui| {
+                    for title in [
+                        "PDO",
+                        "COB-ID",
+                        "Enabled",
+                        "Transmission",
+                        "Inhibit",
+                        "Event timer",
+                        "Mapping",
+                        "",
+                    ] {
+                        ui.label(RichText::new(title).strong());
+                    }
+                    ui.end_row();
+                    for (pdo, parameters) in &config.pdos {
+                        ui.label(pdo.to_string());
+                        let parameters = match parameters {
+                            Ok(parameters) => parameters,
+                            Err(e) => {
+                                ui.label(RichText::new(e).weak());
+                                ui.end_row();
+                                continue;
+                            }
+                        };
+                        ui.monospace(format!("0x{:03X}", parameters.cob_id & 0x7FF));
+                        ui.label(if parameters.enabled() { "✔" } else { "✖" });
+                        let missing = || RichText::new("--").weak();
+                        ui.label(parameters.transmission_type.map_or_else(
+                            missing,
+                            |transmission_type| {
+                                RichText::new(transmission_type_text(transmission_type))
+                            },
+                        ));
+                        ui.label(parameters.inhibit_time.map_or_else(missing, |inhibit| {
+                            RichText::new(format!("{:.1} ms", f64::from(inhibit) / 10.0))
+                        }));
+                        ui.label(
+                            parameters
+                                .event_timer
+                                .map_or_else(missing, |timer| RichText::new(format!("{timer} ms"))),
+                        );
+                        ui.vertical(|ui| {
+                            if parameters.mapping.is_empty() {
+                                ui.label(missing());
+                            }
+                            for object in &parameters.mapping {
+                                let name = eds
+                                    .as_ref()
+                                    .and_then(|eds| eds.name(object.index, object.subindex))
+                                    .unwrap_or_default();
+                                ui.monospace(format!(
+                                    "{:04X}:{:02X} {:>2} bits {name}",
+                                    object.index, object.subindex, object.bits
+                                ));
+                            }
+                        });
+                        if ui
+                            .button("✏")
+                            .on_hover_text("Copy into the mapping editor of the sender")
+                            .clicked()
+                        {
+                            action = Some(PdoConfigAction::Edit(node_id, parameters.clone()));
+                        }
+                        ui.end_row();
+                    }
+                });
+        });
+        ui.label(RichText::new(format!("Read at {}", config.read_at)).weak());
+        action
+    }
+}
+
+/// Parameters of a PDO as typed in the mapping editor of the sender.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PdoEditor {
+    pub pdo: PdoId,
+    cob_id: String,
+    enabled: bool,
+    transmission_type: String,
+    inhibit_time: String,
+    event_timer: String,
+    /// One mapped object per line.
+    mapping: String,
+}
+
+impl Default for PdoEditor {
+    fn default() -> Self {
+        Self {
+            pdo: PdoId::default(),
+            cob_id: String::new(),
+            enabled: true,
+            transmission_type: String::new(),
+            inhibit_time: String::new(),
+            event_timer: String::new(),
+            mapping: String::new(),
+        }
+    }
+}
+
+impl PdoEditor {
+    /// Fills the form with parameters read from a node.
+    pub fn load(&mut self, parameters: &PdoParameters) {
+        let optional =
+            |value: Option<u16>| value.map(|value| value.to_string()).unwrap_or_default();
+        self.pdo = parameters.pdo;
+        self.cob_id = format!("{:03X}", parameters.cob_id & 0x7FF);
+        self.enabled = parameters.enabled();
+        self.transmission_type = parameters
+            .transmission_type
+            .map(|transmission_type| transmission_type.to_string())
+            .unwrap_or_default();
+        self.inhibit_time = optional(parameters.inhibit_time);
+        self.event_timer = optional(parameters.event_timer);
+        self.mapping = parameters
+            .mapping
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("\n");
+    }
+
+    /// Parameters of the form, empty optional fields are left as they are on the node.
+    ///
+    /// # Errors
+    /// The first field that can't be read.
+    pub fn parameters(&self) -> Result<PdoParameters, String> {
+        let optional = |text: &str, name: &str| -> Result<Option<u16>, String> {
+            let text = text.trim();
+            if text.is_empty() {
+                return Ok(None);
+            }
+            text.parse()
+                .map(Some)
+                .map_err(|_| format!("Invalid {name}: {text}"))
+        };
+        let cob_id = u32::from_str_radix(self.cob_id.trim().trim_start_matches("0x"), 16)
+            .ok()
+            .filter(|cob_id| *cob_id <= 0x7FF)
+            .ok_or_else(|| format!("Invalid COB-ID: {}", self.cob_id.trim()))?;
+        let transmission_type = match self.transmission_type.trim() {
+            "" => None,
+            text => Some(
+                text.parse()
+                    .map_err(|_| format!("Invalid transmission type: {text}"))?,
+            ),
+        };
+        let mapping = self
+            .mapping
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(str::parse)
+            .collect::<Result<Vec<MappedObject>, _>>()?;
+        Ok(PdoParameters {
+            pdo: self.pdo,
+            cob_id: if self.enabled {
+                cob_id
+            } else {
+                cob_id | PDO_DISABLED
+            },
+            transmission_type,
+            inhibit_time: optional(&self.inhibit_time, "inhibit time")?,
+            event_timer: optional(&self.event_timer, "event timer")?,
+            mapping,
+        })
+    }
+
+    /// Shows the form, `node_id` fills the default COB-ID when another PDO is picked.
+    pub fn ui(&mut self, ui: &mut egui::Ui, node_id: Option<NodeId>) {
+        egui::Grid::new("pdo_editor").num_columns(2).show(ui, |ui| {
+            ui.label("PDO:");
+            ComboBox::from_id_salt("pdo_editor_pdo")
+                .selected_text(self.pdo.to_string())
+                .show_ui(ui, |ui| {
+                    for pdo in PdoId::ALL {
+                        if ui
+                            .selectable_value(&mut self.pdo, pdo, pdo.to_string())
+                            .clicked()
+                        {
+                            if let Some(node_id) = node_id {
+                                self.cob_id = format!("{:03X}", pdo.default_cob_id(node_id));
+                            }
+                        }
+                    }
+                });
+            ui.end_row();
+            ui.label("COB-ID (hex):");
+            ui.horizontal(|ui| {
+                ui.add(
+                    TextEdit::singleline(&mut self.cob_id)
+                        .desired_width(60.0)
+                        .hint_text("181"),
+                );
+                ui.checkbox(&mut self.enabled, "Enabled");
+            });
+            ui.end_row();
+            ui.label("Transmission type:");
+            ui.add(
+                TextEdit::singleline(&mut self.transmission_type)
+                    .desired_width(60.0)
+                    .hint_text("254"),
+            )
+            .on_hover_text("0-240 on SYNC, 254 or 255 on event, empty to leave it");
+            ui.end_row();
+            ui.label("Inhibit (100 µs):");
+            ui.add(TextEdit::singleline(&mut self.inhibit_time).desired_width(60.0));
+            ui.end_row();
+            ui.label("Event timer (ms):");
+            ui.add(TextEdit::singleline(&mut self.event_timer).desired_width(60.0));
+            ui.end_row();
+            ui.label("Mapping:");
+            ui.add(
+                TextEdit::multiline(&mut self.mapping)
+                    .desired_rows(4)
+                    .hint_text("6041:00/16\n6064:00/32"),
+            )
+            .on_hover_text("One object per line, index:subindex/bits");
+            ui.end_row();
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MappedObject, PdoEditor, PdoId, PdoParameters, PDO_DISABLED};
+
+    #[test]
+    fn test_pdo_parameters() {
+        let tpdo2 = PdoId::ALL[5];
+        assert_eq!(tpdo2.to_string(), "TPDO2");
+        assert_eq!(tpdo2.communication_index(), 0x1801);
+        assert_eq!(tpdo2.mapping_index(), 0x1A01);
+        assert_eq!(tpdo2.default_cob_id(5), 0x285);
+        assert_eq!(PdoId::ALL[0].default_cob_id(5), 0x205);
+
+        let statusword = MappedObject::from(0x6041_0010);
+        assert_eq!(statusword.to_string(), "6041:00/16");
+        assert_eq!(
+            "6064:00/32".parse::<MappedObject>().unwrap().raw(),
+            0x6064_0020
+        );
+        assert!("6064:00".parse::<MappedObject>().is_err());
+        assert!("6064:00/65".parse::<MappedObject>().is_err());
+
+        let parameters = PdoParameters {
+            pdo: tpdo2,
+            cob_id: PDO_DISABLED | 0x285,
+            transmission_type: Some(254),
+            inhibit_time: None,
+            event_timer: Some(100),
+            mapping: vec![statusword, MappedObject::from(0x6064_0020)],
+        };
+        assert!(!parameters.enabled());
+        assert_eq!(parameters.mapped_bits(), 48);
+        let mut editor = PdoEditor::default();
+        editor.load(&parameters);
+        assert_eq!(editor.parameters(), Ok(parameters));
+        editor.transmission_type = "SYNC".to_owned();
+        assert_eq!(
+            editor.parameters(),
+            Err("Invalid transmission type: SYNC".to_owned())
+        );
+    }
+}
@@ -0,0 +1,117 @@
+use oze_canopen::transmitter::TxPacket;
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tokio::{
+    sync::mpsc,
+    task::JoinHandle,
+    time::{interval, MissedTickBehavior},
+};
+
+/// Periodic transmission as reported to the GUI.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeriodicEntry {
+    pub id: u32,
+    pub cob_id: u16,
+    pub data: Vec<u8>,
+    pub period: Duration,
+    /// Number of frames queued for transmission so far.
+    pub sent: u64,
+}
+
+/// Frame sent by its own task on a fixed period.
+#[derive(Debug)]
+struct PeriodicTask {
+    cob_id: u16,
+    data: Vec<u8>,
+    period: Duration,
+    sent: Arc<AtomicU64>,
+    handle: JoinHandle<()>,
+}
+
+impl Drop for PeriodicTask {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+/// Table of the periodic transmissions run by the driver.
+#[derive(Debug, Default)]
+pub struct PeriodicTable {
+    tasks: BTreeMap<u32, PeriodicTask>,
+}
+
+impl PeriodicTable {
+    /// Starts sending the frame every `period`, replacing the entry with the same id.
+    pub fn add(
+        &mut self,
+        tx: mpsc::Sender<TxPacket>,
+        id: u32,
+        cob_id: u16,
+        data: Vec<u8>,
+        period: Duration,
+    ) {
+        let sent = Arc::new(AtomicU64::new(0));
+        let handle = {
+            let sent = sent.clone();
+            let data = data.clone();
+            tokio::spawn(async move {
+                let mut interval = interval(period);
+                interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+                loop {
+                    interval.tick().await;
+                    let packet = TxPacket {
+                        cob_id,
+                        data: data.clone(),
+                    };
+                    if tx.send(packet).await.is_err() {
+                        break;
+                    }
+                    sent.fetch_add(1, Ordering::Relaxed);
+                }
+            })
+        };
+
+        self.tasks.insert(
+            id,
+            PeriodicTask {
+                cob_id,
+                data,
+                period,
+                sent,
+                handle,
+            },
+        );
+    }
+
+    /// Stops the entry, returns `false` if there is no such entry.
+    pub fn remove(&mut self, id: u32) -> bool {
+        self.tasks.remove(&id).is_some()
+    }
+
+    /// Stops every entry.
+    pub fn clear(&mut self) {
+        if !self.tasks.is_empty() {
+            log::info!("Stopping {} periodic transmissions", self.tasks.len());
+        }
+        self.tasks.clear();
+    }
+
+    pub fn entries(&self) -> Vec<PeriodicEntry> {
+        self.tasks
+            .iter()
+            .map(|(id, task)| PeriodicEntry {
+                id: *id,
+                cob_id: task.cob_id,
+                data: task.data.clone(),
+                period: task.period,
+                sent: task.sent.load(Ordering::Relaxed),
+            })
+            .collect()
+    }
+}
@@ -1,65 +1,744 @@
 use crate::{
-    filter_data_panel::FilterDataPanel, message_cached::MessageCached, message_row::MessageRow,
+    export::{pinned_file_name, pinned_to_csv, ExportJob},
+    filter_data_panel::FilterDataPanel,
+    filter_profile::PinnedConfig,
+    message_cached::MessageCached,
+    message_row::MessageRow,
+    pinned_stats::PinnedStats,
+    selection::Selection,
+    settings::PINNED_HISTORY_RANGE,
+    value_plot::{ValuePlot, PLOT_DEPTH},
 };
-use std::{cell::RefCell, collections::VecDeque, rc::Rc};
+use serde::{Deserialize, Serialize};
+use std::{cell::RefCell, collections::VecDeque, rc::Rc, time::Duration};
 use tokio::time::Instant;
 
-#[derive(Debug, Default)]
+/// Default number of messages kept in the history of every pinned filter.
+pub const PINNED_HISTORY_DEPTH: usize = 1024;
+
+/// Allowed age of the messages of a filter keeping the last seconds, a day.
+const RETENTION_SECONDS_MAX: u64 = 86_400;
+
+/// How much of its history a pinned filter keeps. Never more than the end of
+/// `PINNED_HISTORY_RANGE`, whatever the bus rate.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Retention {
+    /// The depth of the pinned history set for every filter.
+    #[default]
+    Default,
+    /// The last messages.
+    Messages(usize),
+    /// The messages received less than this many seconds before the last one.
+    Seconds(u64),
+}
+
+impl Retention {
+    /// Number of messages kept at most, `default` being the depth of the pinned history.
+    fn depth(self, default: usize) -> usize {
+        match self {
+            Self::Default => default,
+            Self::Messages(depth) => depth,
+            Self::Seconds(_) => *PINNED_HISTORY_RANGE.end(),
+        }
+    }
+
+    /// Editor of the retention, returns true if it changed.
+    fn ui(&mut self, ui: &mut egui::Ui, default: usize) -> bool {
+        let before = *self;
+        ui.radio_value(self, Self::Default, format!("Default ({default} messages)"))
+            .on_hover_text("The pinned history depth set in ⚙");
+        ui.horizontal(|ui| {
+            let mut depth = match *self {
+                Self::Messages(depth) => depth,
+                _ => default,
+            };
+            ui.radio_value(self, Self::Messages(depth), "Last messages");
+            if ui
+                .add(egui::DragValue::new(&mut depth).range(PINNED_HISTORY_RANGE))
+                .changed()
+            {
+                *self = Self::Messages(depth);
+            }
+        });
+        ui.horizontal(|ui| {
+            let mut seconds = match *self {
+                Self::Seconds(seconds) => seconds,
+                _ => 60,
+            };
+            ui.radio_value(self, Self::Seconds(seconds), "Last seconds");
+            if ui
+                .add(
+                    egui::DragValue::new(&mut seconds)
+                        .range(1..=RETENTION_SECONDS_MAX)
+                        .suffix(" s"),
+                )
+                .on_hover_text(format!(
+                    "Before the last message, at most {} messages",
+                    PINNED_HISTORY_RANGE.end()
+                ))
+                .changed()
+            {
+                *self = Self::Seconds(seconds);
+            }
+        });
+        *self != before
+    }
+}
+
+/// A single pinned filter with the messages it matched.
+#[derive(Debug)]
+pub struct PinnedFilter {
+    /// Identifies the filter in the selection, unlike its position it doesn't change.
+    pub id: u64,
+    /// Name given by the user, e.g. "Axis 1 statusword".
+    pub label: String,
+    /// Only the label, statistics and last message are shown, not the editor nor the plot.
+    pub collapsed: bool,
+    /// Frozen: neither the history nor the statistics and plot take new messages.
+    pub paused: bool,
+    pub retention: Retention,
+    pub filter: FilterDataPanel,
+    /// Timestamp of the previous matching message, used to show the time delta.
+    pub time: Instant,
+    /// Matching messages, newest at the back.
+    pub history: VecDeque<MessageCached>,
+    /// Value extracted from the matching messages and plotted, if any.
+    pub plot: Option<ValuePlot>,
+    pub stats: PinnedStats,
+}
+
+impl PinnedFilter {
+    pub fn last(&self) -> Option<&MessageCached> {
+        self.history.back()
+    }
+
+    /// Label, or the position of the filter if it has none.
+    fn title(&self, position: usize) -> String {
+        if self.label.trim().is_empty() {
+            format!("Pinned filter {}", position + 1)
+        } else {
+            self.label.trim().to_owned()
+        }
+    }
+
+    /// Updates the statistics and the plot with a matching message.
+    fn observe(&mut self, msg: &MessageCached, origin: Instant, depth: usize) {
+        let value = self
+            .plot
+            .as_ref()
+            .and_then(|plot| plot.extraction.extract(msg));
+        self.stats.push(msg.get_timestamp(), value);
+        if let (Some(plot), Some(value)) = (&mut self.plot, value) {
+            plot.push(msg.get_timestamp(), value, origin, depth);
+        }
+    }
+
+    /// Drops the oldest messages beyond the retention, `depth` being the default depth.
+    fn trim(&mut self, depth: usize) {
+        while self.history.len() > self.retention.depth(depth) {
+            self.history.pop_front();
+        }
+        if let (Retention::Seconds(seconds), Some(last)) = (self.retention, self.last()) {
+            let oldest = last
+                .get_timestamp()
+                .checked_sub(Duration::from_secs(seconds));
+            while self
+                .history
+                .front()
+                .zip(oldest)
+                .is_some_and(|(msg, oldest)| msg.get_timestamp() < oldest)
+            {
+                self.history.pop_front();
+            }
+        }
+    }
+
+    /// Number of messages kept and the age of the oldest one.
+    fn history_summary(&self) -> String {
+        match self.history.front() {
+            Some(oldest) => format!(
+                "{} msgs, {:.1} s",
+                self.history.len(),
+                oldest.get_timestamp().elapsed().as_secs_f64()
+            ),
+            None => "0 msgs".to_owned(),
+        }
+    }
+}
+
+/// Button clicked in front of a pinned filter.
+#[derive(Debug, Clone, Copy)]
+enum RowAction {
+    Delete,
+    /// Swap with the filter at this position.
+    Move(usize),
+    Export,
+    Duplicate,
+}
+
+#[derive(Debug)]
 pub struct PinnedFilters {
-    data: Vec<(FilterDataPanel, Instant, Option<MessageCached>)>,
+    data: Vec<PinnedFilter>,
+    history_depth: usize,
+    /// Number of points kept per value plot.
+    plot_depth: usize,
+    /// Time 0 of the value plots.
+    plot_origin: Instant,
+    next_id: u64,
     pub message_row: MessageRow,
+    pub selection: Selection,
+    /// Export of the history of a filter to a CSV file.
+    export: ExportJob,
+}
+
+impl Default for PinnedFilters {
+    fn default() -> Self {
+        Self {
+            data: Vec::new(),
+            history_depth: PINNED_HISTORY_DEPTH,
+            plot_depth: PLOT_DEPTH,
+            plot_origin: Instant::now(),
+            next_id: 0,
+            message_row: MessageRow::default(),
+            selection: Selection::default(),
+            export: ExportJob::default(),
+        }
+    }
 }
 
 impl PinnedFilters {
-    pub fn pin_filter(&mut self, mut filt: FilterDataPanel, data: &VecDeque<MessageCached>) {
+    pub fn pin_filter(&mut self, filt: FilterDataPanel, data: &VecDeque<MessageCached>) {
+        self.pin(filt, Retention::Default, data);
+    }
+
+    fn pin(
+        &mut self,
+        mut filt: FilterDataPanel,
+        retention: Retention,
+        data: &VecDeque<MessageCached>,
+    ) {
         let data_filter = filt.data_filter.borrow().clone();
-        let new_data = data.iter().find(|i| !data_filter.filter(i));
+        // `data` is ordered newest first, the history is ordered oldest first.
+        let mut history: VecDeque<MessageCached> = VecDeque::new();
+        for i in data.iter().filter(|i| !data_filter.filter(i)) {
+            if history.len() >= retention.depth(self.history_depth) {
+                break;
+            }
+            history.push_front(i.clone());
+        }
 
         filt.data_filter = Rc::new(RefCell::new(data_filter));
-        self.data.push((filt, Instant::now(), new_data.cloned()));
+        self.next_id += 1;
+        let mut pinned = PinnedFilter {
+            id: self.next_id,
+            label: String::new(),
+            collapsed: false,
+            paused: false,
+            retention,
+            filter: filt,
+            time: Instant::now(),
+            history,
+            plot: None,
+            stats: PinnedStats::default(),
+        };
+        pinned.trim(self.history_depth);
+        self.data.push(pinned);
+    }
+
+    /// Fields, label and layout of every filter, in order.
+    pub fn configs(&self) -> Vec<PinnedConfig> {
+        self.data
+            .iter()
+            .map(|data| PinnedConfig {
+                rule: data.filter.config(),
+                label: data.label.clone(),
+                collapsed: data.collapsed,
+                retention: data.retention,
+            })
+            .collect()
+    }
+
+    /// Replaces the filters, their histories are matched again in `data`.
+    pub fn set_configs(&mut self, configs: &[PinnedConfig], data: &VecDeque<MessageCached>) {
+        self.data.clear();
+        self.selection.clear();
+        for config in configs {
+            self.pin(
+                FilterDataPanel::from_config(&config.rule),
+                config.retention,
+                data,
+            );
+            if let Some(pinned) = self.data.last_mut() {
+                pinned.label.clone_from(&config.label);
+                pinned.collapsed = config.collapsed;
+            }
+        }
     }
 
     pub fn push_data(&mut self, msg: &MessageCached) {
+        for data in self.data.iter_mut().filter(|data| !data.paused) {
+            if !data.filter.data_filter.borrow().filter(msg) {
+                data.time = data
+                    .last()
+                    .map_or(Instant::now(), MessageCached::get_timestamp);
+                data.history.push_back(msg.clone());
+                data.trim(self.history_depth);
+                data.observe(msg, self.plot_origin, self.plot_depth);
+            }
+        }
+    }
+
+    /// Messages of the histories of every filter, possibly repeated.
+    pub fn histories(&self) -> impl Iterator<Item = &MessageCached> {
+        self.data.iter().flat_map(|data| data.history.iter())
+    }
+
+    /// Feeds only the statistics and value plots, they keep running while the histories are
+    /// stopped.
+    pub fn push_monitors(&mut self, msg: &MessageCached) {
+        for data in self.data.iter_mut().filter(|data| !data.paused) {
+            if !data.filter.data_filter.borrow().filter(msg) {
+                data.observe(msg, self.plot_origin, self.plot_depth);
+            }
+        }
+    }
+
+    pub fn history_depth(&self) -> usize {
+        self.history_depth
+    }
+
+    /// Changes the number of messages kept per filter, dropping the oldest ones if needed.
+    pub fn set_history_depth(&mut self, depth: usize) {
+        self.history_depth = depth;
         for data in &mut self.data {
-            if !data.0.data_filter.borrow().filter(msg) {
-                let time = data.2.clone().map_or(Instant::now(), |x| x.get_timestamp());
-                data.1 = time;
-                data.2 = Some(msg.clone());
+            data.trim(depth);
+        }
+    }
+
+    /// Decodes the histories again after a DBC or EDS file changed.
+    pub fn redecode(&mut self) {
+        for data in &mut self.data {
+            data.history.iter_mut().for_each(MessageCached::redecode);
+        }
+    }
+
+    /// Drops the messages matched so far, keeping the filters.
+    pub fn clear_history(&mut self) {
+        for data in &mut self.data {
+            data.history.clear();
+            data.stats = PinnedStats::default();
+            if let Some(plot) = &mut data.plot {
+                plot.clear();
             }
         }
     }
 
+    /// Approximate memory used by the histories of all pinned filters, in bytes.
+    pub fn memory_usage(&self) -> usize {
+        self.data
+            .iter()
+            .flat_map(|i| &i.history)
+            .map(MessageCached::memory_usage)
+            .sum()
+    }
+
+    /// Shows a row per filter with its last message, only the rows in view are laid out.
     pub fn update(&mut self, ui: &mut egui::Ui) {
         let row_spacing = 4.0;
         let column_spacing = 5.0;
         let text_style = egui::TextStyle::Body;
         let text_height = ui.text_style_height(&text_style);
-        let height = text_height + row_spacing;
-        egui::Grid::new("fixed_grid")
-            .spacing([column_spacing, row_spacing])
-            .striped(true)
-            .min_row_height(height)
-            .show(ui, |ui| {
-                ui.label("🗑");
-                ui.label("Filter");
-                self.message_row.header_custom(ui, "   Time delta ");
-                ui.end_row();
-
-                let mut to_delete: Option<usize> = None;
-                for (index, (filt, time, msg)) in &mut self.data.iter_mut().enumerate() {
-                    if ui.button("❌").clicked() {
-                        to_delete = Some(index);
-                    }
-                    ui.horizontal(|ui| filt.update(ui));
-                    if let Some(msg) = msg {
-                        self.message_row.message_custom_timestamp(ui, msg, time);
-                    }
-                    ui.end_row();
+        // The filter editors are taller than a line of text.
+        let height = text_height.max(ui.spacing().interact_size.y) + row_spacing;
+
+        let mut action: Option<(usize, RowAction)> = None;
+        let mut clicked = None;
+        let mut copy = false;
+        egui::ScrollArea::vertical()
+            .id_salt("pinned_filters")
+            .max_height(ui.available_height() / 3.0)
+            .show_rows(ui, height, self.data.len() + 1, |ui, row_range| {
+                egui::Grid::new("fixed_grid")
+                    .start_row(row_range.start)
+                    .spacing([column_spacing, row_spacing])
+                    .striped(true)
+                    .min_row_height(height)
+                    .show(ui, |ui| {
+                        let data_range = if row_range.start == 0 {
+                            ui.label("🗑");
+                            ui.label("Filter");
+                            self.message_row.header(ui);
+                            ui.end_row();
+                            0..(row_range.end - 1)
+                        } else {
+                            (row_range.start - 1)..(row_range.end - 1)
+                        };
+
+                        let count = self.data.len();
+                        for index in data_range {
+                            let data = &mut self.data[index];
+                            let idle = self.export.is_idle();
+                            let depth = self.history_depth;
+                            ui.horizontal(|ui| {
+                                if let Some(clicked) =
+                                    Self::buttons_cell(ui, data, index, count, idle, depth)
+                                {
+                                    action = Some((index, clicked));
+                                }
+                            });
+                            ui.horizontal(|ui| Self::filter_cell(ui, data, index));
+                            if let Some(msg) = data.history.back() {
+                                let selected = self.selection.contains(data.id);
+                                let response =
+                                    self.message_row.message(ui, msg, Some(data.time), selected);
+                                if response.clicked() {
+                                    clicked = Some(data.id);
+                                }
+                                if response.secondary_clicked() {
+                                    self.selection.secondary_click(data.id);
+                                }
+                                response.context_menu(|ui| {
+                                    if ui.button("Copy").clicked() {
+                                        copy = true;
+                                        ui.close_menu();
+                                    }
+                                });
+                            }
+                            ui.end_row();
+                        }
+                    });
+            });
+
+        self.export.ui(ui);
+        self.plots_ui(ui);
+
+        match action {
+            Some((index, RowAction::Delete)) => {
+                let removed = self.data.remove(index);
+                self.selection.remove(removed.id);
+            }
+            Some((index, RowAction::Move(to))) => self.data.swap(index, to),
+            Some((index, RowAction::Export)) => self.export_history(index),
+            Some((index, RowAction::Duplicate)) => self.duplicate(index),
+            None => {}
+        }
+        if let Some(id) = clicked {
+            let order: Vec<u64> = self.data.iter().map(|data| data.id).collect();
+            self.selection.click(id, ui.input(|i| i.modifiers), &order);
+        }
+        if copy || self.selection.copy_requested(ui) {
+            ui.ctx().copy_text(self.selected_text());
+        }
+    }
+
+    /// Buttons in front of a filter, `idle` if no export is running and `depth` the default
+    /// depth of the histories.
+    fn buttons_cell(
+        ui: &mut egui::Ui,
+        data: &mut PinnedFilter,
+        index: usize,
+        count: usize,
+        idle: bool,
+        depth: usize,
+    ) -> Option<RowAction> {
+        let mut action = None;
+        if ui
+            .button("❌")
+            .on_hover_text("Remove this filter")
+            .clicked()
+        {
+            action = Some(RowAction::Delete);
+        }
+        if ui
+            .add_enabled(index > 0, egui::Button::new("⏶"))
+            .on_hover_text("Move up")
+            .clicked()
+        {
+            action = Some(RowAction::Move(index - 1));
+        }
+        if ui
+            .add_enabled(index + 1 < count, egui::Button::new("⏷"))
+            .on_hover_text("Move down")
+            .clicked()
+        {
+            action = Some(RowAction::Move(index + 1));
+        }
+        if ui
+            .selectable_label(data.paused, "⏸")
+            .on_hover_text(if data.paused {
+                "Frozen, click to take new messages again"
+            } else {
+                "Freeze this filter: its messages, statistics and plot stop updating"
+            })
+            .clicked()
+        {
+            data.paused = !data.paused;
+        }
+        if ui
+            .button("⧉")
+            .on_hover_text("Duplicate this filter with its history, statistics and plot")
+            .clicked()
+        {
+            action = Some(RowAction::Duplicate);
+        }
+        Self::plot_menu(ui, &mut data.plot);
+        ui.menu_button("⏳", |ui| {
+            if data.retention.ui(ui, depth) {
+                data.trim(depth);
+            }
+        })
+        .response
+        .on_hover_text("Number of messages or seconds of history kept by this filter");
+        if ui
+            .add_enabled(idle && !data.history.is_empty(), egui::Button::new("💾"))
+            .on_hover_text("Export the history of this filter and its plotted value to a CSV file")
+            .clicked()
+        {
+            action = Some(RowAction::Export);
+        }
+        action
+    }
+
+    /// Label and editor of a filter with its statistics, only the label and statistics when
+    /// collapsed.
+    fn filter_cell(ui: &mut egui::Ui, data: &mut PinnedFilter, position: usize) {
+        if ui
+            .small_button(if data.collapsed { "▶" } else { "▼" })
+            .on_hover_text(if data.collapsed {
+                "Show the filter fields and the plot"
+            } else {
+                "Show only the label, the statistics and the last message"
+            })
+            .clicked()
+        {
+            data.collapsed = !data.collapsed;
+        }
+        if data.collapsed {
+            ui.strong(data.title(position))
+                .on_hover_text(data.filter.config().summary());
+        } else {
+            ui.add(
+                egui::TextEdit::singleline(&mut data.label)
+                    .hint_text("label")
+                    .desired_width(100.0),
+            );
+            data.filter.update(ui);
+            if ui
+                .small_button("↺")
+                .on_hover_text("Reset the statistics and the plot")
+                .clicked()
+            {
+                data.stats = PinnedStats::default();
+                if let Some(plot) = &mut data.plot {
+                    plot.clear();
                 }
+            }
+        }
+        ui.label(data.history_summary())
+            .on_hover_text("Messages in the history of this filter and age of the oldest one");
+        if ui
+            .small_button("Clear")
+            .on_hover_text("Empty the history of this filter")
+            .clicked()
+        {
+            data.history.clear();
+        }
+        ui.label(data.stats.summary()).on_hover_text(
+            "Messages matched, period ± jitter, rate and the min..max and last plotted value",
+        );
+    }
+
+    /// Inserts a copy of a filter after it, running even if the original is frozen.
+    fn duplicate(&mut self, index: usize) {
+        let source = &self.data[index];
+        let label = if source.label.trim().is_empty() {
+            String::new()
+        } else {
+            format!("{} (copy)", source.label.trim())
+        };
+        let copy = PinnedFilter {
+            id: self.next_id + 1,
+            label,
+            collapsed: source.collapsed,
+            paused: false,
+            retention: source.retention,
+            filter: FilterDataPanel::from_config(&source.filter.config()),
+            time: source.time,
+            history: source.history.clone(),
+            plot: source.plot.clone(),
+            stats: source.stats.clone(),
+        };
+        self.next_id += 1;
+        self.data.insert(index + 1, copy);
+    }
+
+    /// Writes the whole history of a filter to a CSV file in the working directory, with the
+    /// value of its plot if any.
+    fn export_history(&mut self, index: usize) {
+        let data = &self.data[index];
+        let rows: Vec<MessageCached> = data.history.iter().cloned().collect();
+        let extraction = data.plot.as_ref().map(|plot| plot.extraction);
+        let start = self.message_row.start_time;
+        self.export.start(
+            pinned_file_name(&data.title(index)),
+            rows.len(),
+            move || pinned_to_csv(&rows, start, extraction.as_ref()),
+        );
+    }
 
-                if let Some(index) = to_delete {
-                    self.data.remove(index);
+    /// Button opening the settings of the value plot of a filter.
+    fn plot_menu(ui: &mut egui::Ui, plot: &mut Option<ValuePlot>) {
+        ui.menu_button(if plot.is_some() { "📈" } else { "📉" }, |ui| {
+            let mut enabled = plot.is_some();
+            if ui
+                .checkbox(&mut enabled, "Plot a value")
+                .on_hover_text("Read a value from the payload of the matching messages and plot it under the table")
+                .changed()
+            {
+                *plot = enabled.then(ValuePlot::default);
+            }
+            if let Some(plot) = plot {
+                plot.settings_ui(ui);
+            }
+        })
+        .response
+        .on_hover_text("Plot a value of the matching messages");
+    }
+
+    /// Plots of the filters extracting a value.
+    fn plots_ui(&mut self, ui: &mut egui::Ui) {
+        if self.data.iter().all(|data| data.plot.is_none()) {
+            return;
+        }
+        ui.horizontal(|ui| {
+            ui.label("Plot depth");
+            if ui
+                .add(egui::DragValue::new(&mut self.plot_depth).range(10..=1_000_000))
+                .on_hover_text("Number of points kept per plot")
+                .changed()
+            {
+                for plot in self.data.iter_mut().filter_map(|data| data.plot.as_mut()) {
+                    plot.trim(self.plot_depth);
                 }
-            });
+            }
+        });
+        for (position, data) in self.data.iter().enumerate() {
+            if let Some(plot) = data.plot.as_ref().filter(|_| !data.collapsed) {
+                plot.ui(ui, data.id, &data.title(position));
+            }
+        }
+    }
+
+    /// Id, title and points of the filters extracting a value, in their order.
+    pub fn value_series(&self) -> impl Iterator<Item = (u64, String, &VecDeque<[f64; 2]>)> {
+        self.data.iter().enumerate().filter_map(|(position, data)| {
+            let plot = data.plot.as_ref()?;
+            Some((data.id, data.title(position), plot.points()))
+        })
+    }
+
+    /// Last message of the filter last clicked in the selection.
+    pub fn selected_message(&self) -> Option<&MessageCached> {
+        let id = self.selection.current()?;
+        self.data.iter().find(|data| data.id == id)?.last()
+    }
+
+    /// Last messages of the selected filters, one per line.
+    fn selected_text(&self) -> String {
+        self.data
+            .iter()
+            .filter(|data| self.selection.contains(data.id))
+            .filter_map(|data| {
+                let msg = data.last()?;
+                Some(self.message_row.text(msg, Some(data.time)) + "\n")
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PinnedFilters, Retention};
+    use crate::{filter_data_panel::FilterDataPanel, message_cached::MessageCached};
+    use oze_canopen::canopen::RxMessage;
+    use std::{collections::VecDeque, rc::Rc, time::Duration};
+    use tokio::time::Instant;
+
+    #[test]
+    fn test_retention() {
+        let start = Instant::now();
+        let msg = |millis| {
+            MessageCached::new(
+                0,
+                RxMessage {
+                    timestamp: start + Duration::from_millis(millis),
+                    cob_id: 0x181,
+                    data: [0; 8],
+                    dlc: 2,
+                },
+            )
+        };
+        let mut pinned = PinnedFilters::default();
+        pinned.set_history_depth(3);
+        for retention in [
+            Retention::Default,
+            Retention::Messages(5),
+            Retention::Seconds(2),
+        ] {
+            pinned.pin_filter(FilterDataPanel::new(Rc::default()), &VecDeque::new());
+            pinned.data.last_mut().unwrap().retention = retention;
+        }
+        for millis in (0..10).map(|i| i * 500) {
+            pinned.push_data(&msg(millis));
+        }
+        let lengths: Vec<usize> = pinned.data.iter().map(|data| data.history.len()).collect();
+        // 2.5 s to 4.5 s for the last one.
+        assert_eq!(lengths, [3, 5, 5]);
+        assert_eq!(
+            pinned.data[2].history.front().unwrap().get_timestamp(),
+            start + Duration::from_millis(2500)
+        );
+
+        pinned.data[1].history.clear();
+        pinned.push_data(&msg(5000));
+        let lengths: Vec<usize> = pinned.data.iter().map(|data| data.history.len()).collect();
+        assert_eq!(lengths, [3, 1, 5]);
+    }
+
+    #[test]
+    fn test_pause_and_duplicate() {
+        let start = Instant::now();
+        let msg = |millis| {
+            MessageCached::new(
+                0,
+                RxMessage {
+                    timestamp: start + Duration::from_millis(millis),
+                    cob_id: 0x181,
+                    data: [0; 8],
+                    dlc: 2,
+                },
+            )
+        };
+        let mut pinned = PinnedFilters::default();
+        pinned.pin_filter(FilterDataPanel::new(Rc::default()), &VecDeque::new());
+        pinned.data[0].label = "Speed".to_owned();
+        pinned.push_data(&msg(0));
+        pinned.duplicate(0);
+        assert_eq!(pinned.data[1].label, "Speed (copy)");
+        assert_ne!(pinned.data[0].id, pinned.data[1].id);
+
+        // The original is frozen, its twin keeps updating.
+        pinned.data[0].paused = true;
+        pinned.push_data(&msg(10));
+        pinned.push_monitors(&msg(20));
+        assert_eq!(pinned.data[0].history.len(), 1);
+        assert_eq!(pinned.data[0].stats.summary(), "n=1");
+        assert_eq!(pinned.data[1].history.len(), 2);
+        assert!(pinned.data[1].stats.summary().starts_with("n=3"));
+        // The copy has a filter of its own.
+        assert!(!Rc::ptr_eq(
+            &pinned.data[0].filter.data_filter,
+            &pinned.data[1].filter.data_filter
+        ));
     }
 }
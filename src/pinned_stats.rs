@@ -0,0 +1,85 @@
+use std::fmt::Write;
+use tokio::time::Instant;
+
+/// Period, rate and value range of the messages of a pinned filter, updated message by message.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PinnedStats {
+    count: u64,
+    last_time: Option<Instant>,
+    /// Number, mean and sum of the squared deviations of the periods in seconds (Welford).
+    periods: u64,
+    mean: f64,
+    m2: f64,
+    /// Minimum, maximum and last extracted value.
+    values: Option<(f64, f64, f64)>,
+}
+
+impl PinnedStats {
+    /// Adds a message received at `time`, with its extracted value if any.
+    #[allow(clippy::cast_precision_loss)]
+    pub fn push(&mut self, time: Instant, value: Option<f64>) {
+        self.count += 1;
+        if let Some(last_time) = self.last_time.replace(time) {
+            let period = time.saturating_duration_since(last_time).as_secs_f64();
+            self.periods += 1;
+            let delta = period - self.mean;
+            self.mean += delta / self.periods as f64;
+            self.m2 += delta * (period - self.mean);
+        }
+        if let Some(value) = value {
+            self.values = Some(match self.values {
+                Some((min, max, _)) => (min.min(value), max.max(value), value),
+                None => (value, value, value),
+            });
+        }
+    }
+
+    /// Mean period and its standard deviation in seconds, once two messages were received.
+    #[allow(clippy::cast_precision_loss)]
+    pub fn period(&self) -> Option<(f64, f64)> {
+        (self.periods > 0).then(|| (self.mean, (self.m2 / self.periods as f64).sqrt()))
+    }
+
+    /// One line summary: count, period ± jitter, rate and the value range.
+    pub fn summary(&self) -> String {
+        let mut summary = format!("n={}", self.count);
+        if let Some((mean, jitter)) = self.period() {
+            let _ = write!(
+                summary,
+                "  {:.1} ± {:.1} ms",
+                mean * 1000.0,
+                jitter * 1000.0
+            );
+            if mean > 0.0 {
+                let _ = write!(summary, "  {:.1} Hz", 1.0 / mean);
+            }
+        }
+        if let Some((min, max, last)) = self.values {
+            let _ = write!(summary, "  {min}..{max} last {last}");
+        }
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PinnedStats;
+    use std::time::Duration;
+    use tokio::time::Instant;
+
+    #[test]
+    fn test_stats() {
+        let start = Instant::now();
+        let mut stats = PinnedStats::default();
+        assert_eq!(stats.summary(), "n=0");
+        stats.push(start, Some(5.0));
+        assert_eq!(stats.period(), None);
+        for (millis, value) in [(10, Some(-2.0)), (30, None), (40, Some(3.0))] {
+            stats.push(start + Duration::from_millis(millis), value);
+        }
+        let (mean, jitter) = stats.period().unwrap();
+        assert!((mean - 0.040 / 3.0).abs() < 1e-9);
+        assert!((jitter - 0.004_714).abs() < 1e-6);
+        assert_eq!(stats.summary(), "n=4  13.3 ± 4.7 ms  75.0 Hz  -2..5 last 3");
+    }
+}
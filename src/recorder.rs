@@ -0,0 +1,234 @@
+use crate::{
+    bus_errors::ErrorFrame,
+    export::candump_line,
+    message_cached::{Direction, MessageCached},
+    message_row::wall_clock,
+    pcapng::{PcapFrame, PcapngWriter, CAN_ERR_FLAG},
+};
+use chrono::{DateTime, Local};
+use std::{
+    fmt::Write as _,
+    fs::File,
+    io::{self, BufWriter, Write},
+    time::Duration,
+};
+use tokio::time::Instant;
+
+/// Interval between two flushes of the recording to the file.
+const FLUSH_PERIOD: Duration = Duration::from_millis(200);
+
+/// File format of a recording, told by the extension of its path.
+#[derive(Debug)]
+enum Output {
+    /// `candump -l` log.
+    Candump { file: BufWriter<File>, line: String },
+    /// pcapng capture, for the `.pcapng` files.
+    Pcapng(PcapngWriter<BufWriter<File>>),
+}
+
+/// Writes every received frame and error frame to a `candump -l` log or a pcapng capture as it
+/// arrives, from the driver.
+#[derive(Debug)]
+pub struct Recorder {
+    output: Output,
+    path: String,
+    /// Creation of the recorder, the timestamps are derived from it.
+    start: Instant,
+    /// Wall-clock time at `start`.
+    start_wall: DateTime<Local>,
+    next_flush: Instant,
+}
+
+impl Recorder {
+    /// Creates or truncates the file at `path`, a pcapng capture if it ends with `.pcapng`.
+    ///
+    /// # Errors
+    /// If the file can't be created.
+    pub fn create(path: &str) -> io::Result<Self> {
+        let file = BufWriter::new(File::create(path)?);
+        let output = if path.ends_with(".pcapng") {
+            Output::Pcapng(PcapngWriter::new(file)?)
+        } else {
+            Output::Candump {
+                file,
+                line: String::new(),
+            }
+        };
+        let start = Instant::now();
+        Ok(Self {
+            output,
+            path: path.to_owned(),
+            start,
+            start_wall: Local::now(),
+            next_flush: start + FLUSH_PERIOD,
+        })
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Appends a message received on `interface`.
+    ///
+    /// # Errors
+    /// If the file can't be written.
+    pub fn write(&mut self, msg: &MessageCached, interface: &str) -> io::Result<()> {
+        let time = wall_clock(self.start, self.start_wall, msg.get_timestamp());
+        match &mut self.output {
+            Output::Candump { file, line } => {
+                line.clear();
+                candump_line(line, time, interface, msg);
+                file.write_all(line.as_bytes())
+            }
+            Output::Pcapng(writer) => {
+                let frame = &msg.msg.msg;
+                let packet = PcapFrame {
+                    time_us: time.timestamp_micros(),
+                    can_id: u32::from(frame.cob_id),
+                    data: frame.data[..frame.dlc.min(8)].to_vec(),
+                    direction: msg.direction,
+                    comment: None,
+                };
+                writer.write(interface, &packet)
+            }
+        }
+    }
+
+    /// Appends an error frame received now on `interface`, with the error flag in its ID as
+    /// written by `candump`.
+    ///
+    /// # Errors
+    /// If the file can't be written.
+    pub fn write_error(&mut self, error: &ErrorFrame, interface: &str) -> io::Result<()> {
+        let time = wall_clock(self.start, self.start_wall, Instant::now());
+        let can_id = CAN_ERR_FLAG | error.bits;
+        match &mut self.output {
+            Output::Candump { file, line } => {
+                line.clear();
+                let _ = write!(
+                    line,
+                    "({}.{:06}) {interface} {can_id:08X}#",
+                    time.timestamp(),
+                    time.timestamp_subsec_micros()
+                );
+                for byte in error.data {
+                    let _ = write!(line, "{byte:02X}");
+                }
+                line.push('\n');
+                file.write_all(line.as_bytes())
+            }
+            Output::Pcapng(writer) => {
+                let packet = PcapFrame {
+                    time_us: time.timestamp_micros(),
+                    can_id,
+                    data: error.data.to_vec(),
+                    direction: Direction::Rx,
+                    comment: None,
+                };
+                writer.write(interface, &packet)
+            }
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.output {
+            Output::Candump { file, .. } => file.flush(),
+            Output::Pcapng(writer) => writer.flush(),
+        }
+    }
+
+    /// Flushes the lines written if the last flush is older than the flush period.
+    ///
+    /// # Errors
+    /// If the file can't be written.
+    pub fn flush_if_due(&mut self, now: Instant) -> io::Result<()> {
+        if now < self.next_flush {
+            return Ok(());
+        }
+        self.next_flush = now + FLUSH_PERIOD;
+        self.flush()
+    }
+
+    /// Flushes the last lines and closes the file.
+    ///
+    /// # Errors
+    /// If the file can't be written.
+    pub fn finish(mut self) -> io::Result<()> {
+        self.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Recorder;
+    use crate::{
+        bus_errors::ErrorFrame,
+        message_cached::{Direction, MessageCached},
+        pcapng::{self, CAN_ERR_FLAG},
+    };
+    use oze_canopen::canopen::RxMessage;
+    use std::time::Duration;
+    use tokio::time::Instant;
+
+    #[test]
+    fn test_recorder() {
+        let path = std::env::temp_dir().join(format!("recorder-{}.log", std::process::id()));
+        let path = path.to_str().unwrap();
+        let mut recorder = Recorder::create(path).unwrap();
+        let frame = |cob_id, data: &[u8]| {
+            let mut msg = RxMessage {
+                timestamp: Instant::now(),
+                cob_id,
+                data: [0; 8],
+                dlc: data.len(),
+            };
+            msg.data[..data.len()].copy_from_slice(data);
+            MessageCached::new(0, msg)
+        };
+        let mut sent = frame(0x601, &[0x40, 0x18, 0x10, 0x01]);
+        sent.direction = Direction::Tx;
+        recorder
+            .write(&frame(0x181, &[0x01, 0xAB]), "can0")
+            .unwrap();
+        recorder.write(&sent, "can0").unwrap();
+        recorder
+            .flush_if_due(Instant::now() + Duration::from_secs(1))
+            .unwrap();
+        // Not due yet, written by the flush of the exit.
+        recorder.write(&frame(0x701, &[0x05]), "can0").unwrap();
+        let error = ErrorFrame {
+            bits: 0x0004,
+            data: [0, 0x08, 0, 0, 0, 0, 0, 0],
+        };
+        recorder.write_error(&error, "can0").unwrap();
+        recorder.finish().unwrap();
+
+        let log = std::fs::read_to_string(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        let lines: Vec<&str> = log.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(
+            lines[0].starts_with('(') && lines[0].ends_with(") can0 181#01AB"),
+            "{log}"
+        );
+        assert!(lines[1].ends_with(" can0 601#40181001 T"), "{log}");
+        assert!(lines[2].ends_with(" can0 701#05"), "{log}");
+        assert!(
+            lines[3].ends_with(" can0 20000004#0008000000000000"),
+            "{log}"
+        );
+
+        let path = std::env::temp_dir().join(format!("recorder-{}.pcapng", std::process::id()));
+        let path = path.to_str().unwrap();
+        let mut recorder = Recorder::create(path).unwrap();
+        recorder.write(&sent, "can0").unwrap();
+        recorder.write_error(&error, "can0").unwrap();
+        recorder.finish().unwrap();
+        let capture = pcapng::read(&std::fs::read(path).unwrap()).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(capture.interfaces, ["can0"]);
+        assert_eq!(capture.frames[0].1.direction, Direction::Tx);
+        assert_eq!(capture.frames[0].1.data, [0x40, 0x18, 0x10, 0x01]);
+        assert_eq!(capture.frames[1].1.can_id, CAN_ERR_FLAG | 0x0004);
+    }
+}
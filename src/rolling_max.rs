@@ -0,0 +1,92 @@
+use std::{collections::VecDeque, time::Duration};
+use tokio::time::Instant;
+
+/// Buckets a window is split into, the window slides by one bucket at a time.
+const BUCKETS: u32 = 60;
+
+/// Maximum of the values pushed within a sliding window, in constant memory.
+///
+/// Values are folded in buckets of a sixtieth of the window, so the maximum may stay up to one
+/// bucket longer than the window.
+#[derive(Debug, Clone)]
+pub struct RollingMax {
+    window: Duration,
+    /// Start and maximum of the buckets, oldest first.
+    buckets: VecDeque<(Instant, f64)>,
+}
+
+impl RollingMax {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            buckets: VecDeque::new(),
+        }
+    }
+
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// Changes the window, the buckets already older than the new one are dropped on the next
+    /// push.
+    pub fn set_window(&mut self, window: Duration) {
+        self.window = window;
+    }
+
+    fn bucket(&self) -> Duration {
+        self.window / BUCKETS
+    }
+
+    pub fn push(&mut self, now: Instant, value: f64) {
+        let bucket = self.bucket();
+        while self
+            .buckets
+            .front()
+            .is_some_and(|(start, _)| now.saturating_duration_since(*start) > self.window + bucket)
+        {
+            self.buckets.pop_front();
+        }
+        match self.buckets.back_mut() {
+            Some((start, max)) if now.saturating_duration_since(*start) < bucket => {
+                *max = max.max(value);
+            }
+            _ => self.buckets.push_back((now, value)),
+        }
+    }
+
+    /// Maximum within the window at the last push, `None` before any.
+    pub fn max(&self) -> Option<f64> {
+        self.buckets.iter().map(|(_, max)| *max).reduce(f64::max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RollingMax;
+    use std::time::Duration;
+    use tokio::time::Instant;
+
+    #[test]
+    fn test_rolling_max() {
+        let mut peaks = RollingMax::new(Duration::from_secs(60));
+        let start = Instant::now();
+        let secs = |secs| start + Duration::from_secs(secs);
+        assert_eq!(peaks.max(), None);
+
+        // A burst at startup, then a steady 40.
+        peaks.push(start, 97.0);
+        for second in 1..=60 {
+            peaks.push(secs(second), 40.0);
+        }
+        assert_eq!(peaks.max(), Some(97.0));
+        peaks.push(secs(62), 42.0);
+        assert_eq!(peaks.max(), Some(42.0));
+        assert!(peaks.buckets.len() <= 62);
+
+        peaks.set_window(Duration::from_secs(10));
+        peaks.push(secs(70), 30.0);
+        assert_eq!(peaks.max(), Some(42.0));
+        peaks.push(secs(90), 30.0);
+        assert_eq!(peaks.max(), Some(30.0));
+    }
+}
@@ -0,0 +1,198 @@
+use crate::{config, message_cached::MessageCached};
+use catppuccin_egui::FRAPPE;
+use egui::{Color32, TextEdit};
+use oze_canopen::canopen::RxMessageType;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// File of the row colours in the config directory.
+const CONFIG_FILE: &str = "row_colors.bin";
+
+/// Colours of the nodes in the node scheme, light enough for the dark theme.
+const NODE_PALETTE: [Color32; 14] = [
+    FRAPPE.rosewater,
+    FRAPPE.flamingo,
+    FRAPPE.pink,
+    FRAPPE.mauve,
+    FRAPPE.red,
+    FRAPPE.maroon,
+    FRAPPE.peach,
+    FRAPPE.yellow,
+    FRAPPE.green,
+    FRAPPE.teal,
+    FRAPPE.sky,
+    FRAPPE.sapphire,
+    FRAPPE.blue,
+    FRAPPE.lavender,
+];
+
+/// How the rows of the message tables are coloured.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ColorScheme {
+    /// Every row in the text colour.
+    Plain,
+    /// EMCY red, NMT and heartbeat blue, SDO green, SYNC grey, the others in the text colour.
+    #[default]
+    FunctionCode,
+    /// A colour per node ID, the broadcast objects in the text colour.
+    Node,
+}
+
+impl ColorScheme {
+    const ALL: [Self; 3] = [Self::FunctionCode, Self::Node, Self::Plain];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Plain => "Plain",
+            Self::FunctionCode => "By function",
+            Self::Node => "By node",
+        }
+    }
+
+    fn color(self, d: &MessageCached) -> Option<Color32> {
+        match self {
+            Self::Plain => None,
+            Self::FunctionCode => match d.msg.parsed_type {
+                RxMessageType::Emcy => Some(FRAPPE.red),
+                RxMessageType::Nmt | RxMessageType::Guarding => Some(FRAPPE.blue),
+                RxMessageType::SdoTx | RxMessageType::SdoRx => Some(FRAPPE.green),
+                RxMessageType::Sync => Some(FRAPPE.overlay2),
+                _ => None,
+            },
+            Self::Node => d
+                .msg
+                .parsed_node_id
+                .map(|node_id| NODE_PALETTE[usize::from(node_id) % NODE_PALETTE.len()]),
+        }
+    }
+}
+
+/// Text colour of the rows of the message tables.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RowColors {
+    pub scheme: ColorScheme,
+    /// Colours of chosen COB-IDs, over the scheme.
+    overrides: BTreeMap<u16, [u8; 3]>,
+    /// COB-ID typed to add an override.
+    #[serde(skip)]
+    cob_raw: String,
+}
+
+impl PartialEq for RowColors {
+    /// The COB-ID being typed doesn't count.
+    fn eq(&self, other: &Self) -> bool {
+        self.scheme == other.scheme && self.overrides == other.overrides
+    }
+}
+
+impl RowColors {
+    /// Colours saved by a previous run, or the default scheme.
+    pub fn load() -> Self {
+        config::load(CONFIG_FILE).unwrap_or_default()
+    }
+
+    /// Colour of the row of a message, `None` for the text colour.
+    pub fn color(&self, d: &MessageCached) -> Option<Color32> {
+        match self.overrides.get(&d.msg.msg.cob_id) {
+            Some(&[r, g, b]) => Some(Color32::from_rgb(r, g, b)),
+            None => self.scheme.color(d),
+        }
+    }
+
+    /// Scheme selector and COB-ID overrides, returns true if the colours changed.
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> bool {
+        let before = self.clone();
+        ui.label("Row colours");
+        ui.horizontal(|ui| {
+            for scheme in ColorScheme::ALL {
+                ui.radio_value(&mut self.scheme, scheme, scheme.name());
+            }
+        });
+
+        let mut remove = None;
+        for (cob_id, color) in &mut self.overrides {
+            ui.horizontal(|ui| {
+                egui::color_picker::color_edit_button_srgb(ui, color);
+                ui.label(format!("{cob_id:03X}"));
+                if ui.small_button("❌").clicked() {
+                    remove = Some(*cob_id);
+                }
+            });
+        }
+        if let Some(cob_id) = remove {
+            self.overrides.remove(&cob_id);
+        }
+        ui.horizontal(|ui| {
+            ui.add(
+                TextEdit::singleline(&mut self.cob_raw)
+                    .hint_text("COB-ID")
+                    .desired_width(60.0),
+            );
+            let cob_id = u16::from_str_radix(self.cob_raw.trim(), 16)
+                .ok()
+                .filter(|cob_id| *cob_id <= 0x7FF);
+            if ui
+                .add_enabled(cob_id.is_some(), egui::Button::new("➕"))
+                .on_hover_text("Give this COB-ID its own colour, e.g. 181")
+                .clicked()
+            {
+                if let Some(cob_id) = cob_id {
+                    let [r, g, b, _] = FRAPPE.peach.to_array();
+                    self.overrides.insert(cob_id, [r, g, b]);
+                    self.cob_raw.clear();
+                }
+            }
+        });
+
+        let changed = *self != before;
+        if changed {
+            if let Err(e) = config::save(CONFIG_FILE, self) {
+                log::warn!("Can't save the row colours: {e}");
+            }
+        }
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ColorScheme, RowColors};
+    use crate::message_cached::MessageCached;
+    use catppuccin_egui::FRAPPE;
+    use egui::Color32;
+    use oze_canopen::canopen::RxMessage;
+    use tokio::time::Instant;
+
+    fn message(cob_id: u16) -> MessageCached {
+        MessageCached::new(
+            0,
+            RxMessage {
+                timestamp: Instant::now(),
+                cob_id,
+                data: [0; 8],
+                dlc: 0,
+            },
+        )
+    }
+
+    #[test]
+    fn test_color() {
+        let mut colors = RowColors::default();
+        assert_eq!(colors.color(&message(0x081)), Some(FRAPPE.red));
+        assert_eq!(colors.color(&message(0x701)), Some(FRAPPE.blue));
+        assert_eq!(colors.color(&message(0x581)), Some(FRAPPE.green));
+        assert_eq!(colors.color(&message(0x080)), Some(FRAPPE.overlay2));
+        assert_eq!(colors.color(&message(0x181)), None);
+
+        colors.overrides.insert(0x181, [1, 2, 3]);
+        assert_eq!(
+            colors.color(&message(0x181)),
+            Some(Color32::from_rgb(1, 2, 3))
+        );
+
+        colors.scheme = ColorScheme::Node;
+        assert_eq!(colors.color(&message(0x182)), colors.color(&message(0x702)));
+        assert_ne!(colors.color(&message(0x182)), colors.color(&message(0x183)));
+        assert_eq!(colors.color(&message(0x080)), None);
+    }
+}
@@ -0,0 +1,166 @@
+use crate::{
+    filter::{Operator, RuleGroup, RuleNode},
+    filter_data_panel::FilterDataPanel,
+    filter_profile::{RuleGroupConfig, RuleNodeConfig},
+};
+use std::rc::Rc;
+
+/// Groups can be nested this deep below the top group.
+const MAX_DEPTH: usize = 3;
+
+#[derive(Debug, Clone)]
+enum NodePanel {
+    Rule(FilterDataPanel),
+    Group(RuleGroupPanel),
+}
+
+/// Editor of a `RuleGroup`: operator, rules and nested groups. Its rules are shared with the
+/// group returned by `group`, which is built again when the structure changes.
+#[derive(Debug, Clone, Default)]
+pub struct RuleGroupPanel {
+    operator: Operator,
+    nodes: Vec<NodePanel>,
+}
+
+impl RuleGroupPanel {
+    pub fn from_config(config: &RuleGroupConfig) -> Self {
+        Self {
+            operator: config.operator,
+            nodes: config
+                .rules
+                .iter()
+                .map(|node| match node {
+                    RuleNodeConfig::Rule(rule) => {
+                        NodePanel::Rule(FilterDataPanel::from_config(rule))
+                    }
+                    RuleNodeConfig::Group(group) => NodePanel::Group(Self::from_config(group)),
+                })
+                .collect(),
+        }
+    }
+
+    /// Fields as typed in the panel.
+    pub fn config(&self) -> RuleGroupConfig {
+        RuleGroupConfig {
+            operator: self.operator,
+            rules: self
+                .nodes
+                .iter()
+                .map(|node| match node {
+                    NodePanel::Rule(panel) => RuleNodeConfig::Rule(panel.config()),
+                    NodePanel::Group(group) => RuleNodeConfig::Group(group.config()),
+                })
+                .collect(),
+        }
+    }
+
+    /// Group edited by the panel, sharing its rules.
+    pub fn group(&self) -> RuleGroup {
+        RuleGroup {
+            operator: self.operator,
+            rules: self
+                .nodes
+                .iter()
+                .map(|node| match node {
+                    NodePanel::Rule(panel) => RuleNode::Rule(Rc::clone(&panel.data_filter)),
+                    NodePanel::Group(group) => RuleNode::Group(group.group()),
+                })
+                .collect(),
+        }
+    }
+
+    /// Adds a rule to the top group, call `group` afterwards.
+    pub fn push(&mut self, rule: FilterDataPanel) {
+        self.nodes.push(NodePanel::Rule(rule));
+    }
+
+    /// Toggles between `OR` and `AND`, returns true if clicked.
+    fn operator_ui(&mut self, ui: &mut egui::Ui) -> bool {
+        let (text, hover) = match self.operator {
+            Operator::Or => ("OR", "Match one of the rules, click to match all of them"),
+            Operator::And => ("AND", "Match all the rules, click to match one of them"),
+        };
+        let clicked = ui.small_button(text).on_hover_text(hover).clicked();
+        if clicked {
+            self.operator = match self.operator {
+                Operator::Or => Operator::And,
+                Operator::And => Operator::Or,
+            };
+        }
+        clicked
+    }
+
+    /// Buttons to add a rule or a group, returns true if one was added.
+    fn add_ui(&mut self, ui: &mut egui::Ui, depth: usize) -> bool {
+        let mut added = false;
+        if ui.button("➕").on_hover_text("Add a rule").clicked() {
+            self.nodes
+                .push(NodePanel::Rule(FilterDataPanel::new(Rc::default())));
+            added = true;
+        }
+        if depth < MAX_DEPTH
+            && ui
+                .button("➕( )")
+                .on_hover_text("Add a group of rules with an operator of its own")
+                .clicked()
+        {
+            self.nodes.push(NodePanel::Group(Self {
+                operator: match self.operator {
+                    Operator::Or => Operator::And,
+                    Operator::And => Operator::Or,
+                },
+                nodes: vec![NodePanel::Rule(FilterDataPanel::new(Rc::default()))],
+            }));
+            added = true;
+        }
+        added
+    }
+
+    /// Top group with its label, returns true if the structure or an operator changed, the
+    /// caller then sets the filter's group to `group`.
+    pub fn update(&mut self, ui: &mut egui::Ui, label: &str, hover: &str) -> bool {
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            ui.label(label).on_hover_text(hover);
+            if self.nodes.len() > 1 {
+                changed |= self.operator_ui(ui);
+            }
+            changed |= self.add_ui(ui, 0);
+        });
+        changed | self.nodes_ui(ui, 0)
+    }
+
+    fn nodes_ui(&mut self, ui: &mut egui::Ui, depth: usize) -> bool {
+        let mut changed = false;
+        let mut remove = None;
+        for (i, node) in self.nodes.iter_mut().enumerate() {
+            match node {
+                NodePanel::Rule(panel) => {
+                    ui.horizontal(|ui| {
+                        panel.update(ui);
+                        if ui.button("❌").on_hover_text("Remove the rule").clicked() {
+                            remove = Some(i);
+                        }
+                    });
+                }
+                NodePanel::Group(group) => {
+                    egui::Frame::group(ui.style()).show(ui, |ui| {
+                        ui.horizontal(|ui| {
+                            changed |= group.operator_ui(ui);
+                            changed |= group.add_ui(ui, depth + 1);
+                            if ui.button("❌").on_hover_text("Remove the group").clicked() {
+                                remove = Some(i);
+                            }
+                        });
+                        changed |= group.nodes_ui(ui, depth + 1);
+                    });
+                }
+            }
+        }
+        if let Some(i) = remove {
+            self.nodes.remove(i);
+            changed = true;
+        }
+        changed
+    }
+}
@@ -0,0 +1,233 @@
+use crate::bus_errors::ErrorFrame;
+use futures_util::StreamExt;
+use oze_canopen::canopen::RxMessage;
+use socketcan::{
+    tokio::CanSocket, CanErrorFrame, CanFilter, CanFrame, EmbeddedFrame, SocketOptions,
+};
+use std::{fmt, time::Duration};
+use tokio::time::{sleep_until, Instant};
+
+/// Delay before the first attempt to reopen a lost interface.
+const RETRY_MIN: Duration = Duration::from_millis(100);
+/// Upper bound of the delay between attempts to reopen a lost interface.
+const RETRY_MAX: Duration = Duration::from_secs(2);
+
+/// Acceptance filter applied by the kernel to the receive socket (`CAN_RAW_FILTER`).
+///
+/// A frame passes when `received_id & mask == id & mask`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KernelFilter {
+    pub id: u32,
+    pub mask: u32,
+}
+
+impl KernelFilter {
+    /// Frames the driver waits for, accepted whatever the filters: the SDO responses
+    /// (0x580-0x5FF) and the heartbeats (0x700-0x77F).
+    pub const DRIVER: [Self; 2] = [
+        Self {
+            id: 0x580,
+            mask: 0x780,
+        },
+        Self {
+            id: 0x700,
+            mask: 0x780,
+        },
+    ];
+
+    /// Parses a filter from hexadecimal id and mask, an empty mask matches the exact id.
+    pub fn from_hex(id: &str, mask: &str) -> Option<Self> {
+        let id = u32::from_str_radix(id.trim().trim_start_matches("0x"), 16).ok()?;
+        let mask = if mask.trim().is_empty() {
+            0x7FF
+        } else {
+            u32::from_str_radix(mask.trim().trim_start_matches("0x"), 16).ok()?
+        };
+        (id <= 0x7FF && mask <= 0x7FF).then_some(Self { id, mask })
+    }
+}
+
+impl fmt::Display for KernelFilter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:03X}/{:03X}", self.id, self.mask)
+    }
+}
+
+/// Event produced by [`RxSocket::recv`].
+#[derive(Debug)]
+pub enum RxEvent {
+    /// A data frame was received.
+    Frame(RxMessage),
+    /// The controller reported an error.
+    Error(ErrorFrame),
+    /// The socket was opened.
+    Opened,
+    /// The socket failed, it will be reopened with backoff.
+    Lost(String),
+}
+
+/// Receive socket owned by the driver.
+///
+/// The socket is opened lazily and reopened with exponential backoff whenever it fails,
+/// e.g. when a USB adapter is unplugged. Bitrate configuration of the reappeared
+/// interface is done by the `oze_canopen` stack, this socket only reads frames, error frames
+/// included.
+pub struct RxSocket {
+    can_name: String,
+    filters: Vec<KernelFilter>,
+    sock: Option<CanSocket>,
+    retry: Duration,
+    next_retry: Instant,
+}
+
+impl RxSocket {
+    pub fn new(can_name: String) -> Self {
+        Self {
+            can_name,
+            filters: Vec::new(),
+            sock: None,
+            retry: RETRY_MIN,
+            next_retry: Instant::now(),
+        }
+    }
+
+    /// Switches to another interface, closing the current socket.
+    pub fn set_interface(&mut self, can_name: String) {
+        self.can_name = can_name;
+        self.sock = None;
+        self.retry = RETRY_MIN;
+        self.next_retry = Instant::now();
+    }
+
+    /// Replaces the kernel acceptance filters, an empty list accepts every frame.
+    pub fn set_filters(&mut self, filters: Vec<KernelFilter>) {
+        self.filters = filters;
+        if let Some(sock) = &self.sock {
+            if let Err(e) = Self::apply_filters(sock, &self.filters) {
+                log::error!("Failed to set kernel filters: {e}");
+            }
+        }
+    }
+
+    fn apply_filters(sock: &CanSocket, filters: &[KernelFilter]) -> std::io::Result<()> {
+        if filters.is_empty() {
+            sock.set_filter_accept_all()
+        } else {
+            let filters: Vec<CanFilter> = installed(filters)
+                .iter()
+                .map(|f| CanFilter::new(f.id, f.mask))
+                .collect();
+            sock.set_filters(&filters)
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.sock.is_some()
+    }
+
+    /// Waits for the next event. The future is cancel safe.
+    pub async fn recv(&mut self) -> RxEvent {
+        loop {
+            let Some(sock) = &mut self.sock else {
+                if self.can_name.is_empty() {
+                    std::future::pending::<()>().await;
+                }
+
+                sleep_until(self.next_retry).await;
+                match CanSocket::open(&self.can_name) {
+                    Ok(sock) => {
+                        if let Err(e) = Self::apply_filters(&sock, &self.filters) {
+                            log::error!("Failed to set kernel filters: {e}");
+                        }
+                        if let Err(e) = sock.set_error_filter_accept_all() {
+                            log::error!("Failed to enable error frames: {e}");
+                        }
+                        self.sock = Some(sock);
+                        return RxEvent::Opened;
+                    }
+                    Err(_) => self.schedule_retry(),
+                }
+                continue;
+            };
+
+            match sock.next().await {
+                Some(Ok(CanFrame::Data(frame))) => {
+                    self.retry = RETRY_MIN;
+                    if let Some(msg) = RxMessage::from(frame) {
+                        return RxEvent::Frame(msg);
+                    }
+                }
+                Some(Ok(CanFrame::Error(frame))) => return RxEvent::Error(Self::error(&frame)),
+                Some(Ok(_)) => {}
+                Some(Err(e)) => return self.lose(e.to_string()),
+                None => return self.lose("socket closed".to_owned()),
+            }
+        }
+    }
+
+    fn error(frame: &CanErrorFrame) -> ErrorFrame {
+        let mut data = [0; 8];
+        let len = frame.data().len().min(8);
+        data[..len].copy_from_slice(&frame.data()[..len]);
+        ErrorFrame {
+            bits: frame.error_bits(),
+            data,
+        }
+    }
+
+    fn lose(&mut self, reason: String) -> RxEvent {
+        self.sock = None;
+        self.schedule_retry();
+        RxEvent::Lost(reason)
+    }
+
+    fn schedule_retry(&mut self) {
+        self.next_retry = Instant::now() + self.retry;
+        self.retry = (self.retry * 2).min(RETRY_MAX);
+    }
+}
+
+/// Filters installed on the socket for the ones set by the user, with the frames the driver
+/// waits for so that the SDO commands and the node tracking keep working.
+fn installed(filters: &[KernelFilter]) -> Vec<KernelFilter> {
+    let mut installed = filters.to_vec();
+    for filter in KernelFilter::DRIVER {
+        if !installed.contains(&filter) {
+            installed.push(filter);
+        }
+    }
+    installed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{installed, KernelFilter};
+
+    #[test]
+    fn test_kernel_filter_from_hex() {
+        assert_eq!(
+            KernelFilter::from_hex("181", ""),
+            Some(KernelFilter {
+                id: 0x181,
+                mask: 0x7FF
+            })
+        );
+        assert_eq!(
+            KernelFilter::from_hex("0x180", "780"),
+            Some(KernelFilter {
+                id: 0x180,
+                mask: 0x780
+            })
+        );
+        assert_eq!(KernelFilter::from_hex("800", ""), None);
+        assert_eq!(KernelFilter::from_hex("181", "FFFF"), None);
+        assert_eq!(KernelFilter::from_hex("xyz", ""), None);
+
+        let pdo = KernelFilter::from_hex("181", "").unwrap();
+        assert_eq!(
+            installed(&[pdo]),
+            [pdo, KernelFilter::DRIVER[0], KernelFilter::DRIVER[1]]
+        );
+        assert_eq!(installed(&KernelFilter::DRIVER), KernelFilter::DRIVER);
+    }
+}
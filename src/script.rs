@@ -0,0 +1,432 @@
+use crate::{
+    message_cached::MessageCached,
+    theme::OZON_PINK,
+    value_plot::{ValuePlot, PLOT_DEPTH},
+};
+use egui::TextEdit;
+use rhai::{CallFnOptions, Dynamic, Engine, Scope, AST, INT};
+use std::{
+    cell::{Cell, RefCell},
+    collections::{BTreeMap, BTreeSet},
+    sync::{Arc, Mutex, PoisonError, RwLock},
+    time::{Duration, Instant},
+};
+
+/// Script loaded for decoding and the hooks, see `set`.
+static LOADED: RwLock<Option<Arc<Script>>> = RwLock::new(None);
+
+/// Time a call of a script function may take, so a bad script can't stall the reception.
+pub const CALL_TIME_LIMIT: Duration = Duration::from_millis(10);
+
+/// Operations between two checks of the time limit.
+const CHECK_PERIOD: u64 = 256;
+
+/// Errors kept until the GUI takes them, the others are only counted.
+const MAX_PENDING_ERRORS: usize = 16;
+
+thread_local! {
+    /// End of the call running on this thread.
+    static DEADLINE: Cell<Option<Instant>> = const { Cell::new(None) };
+    /// Outputs of the hook running on this thread.
+    static OUTPUTS: RefCell<Vec<HookOutput>> = const { RefCell::new(Vec::new()) };
+}
+
+/// What an `on_message` hook asked for.
+#[derive(Debug, Clone, PartialEq)]
+pub enum HookOutput {
+    /// `alert(text)`, raised like the alert of a trigger.
+    Alert(String),
+    /// `value(name, number)`, a point of the derived series `name`.
+    Value(String, f64),
+}
+
+/// Errors of the calls, each distinct one reported once.
+#[derive(Debug, Default)]
+struct ErrorLog {
+    pending: Vec<String>,
+    reported: BTreeSet<String>,
+    suppressed: u64,
+}
+
+/// Rhai script defining `decode(cob_id, data)`, whose text is shown in the decode column,
+/// and `on_message(cob_id, data)`, which calls `alert` and `value`.
+///
+/// The engine has no access to the file system, and every call is limited in operations
+/// and time.
+#[derive(Debug)]
+pub struct Script {
+    pub source: String,
+    engine: Engine,
+    ast: AST,
+    has_decode: bool,
+    has_on_message: bool,
+    errors: Mutex<ErrorLog>,
+}
+
+impl Script {
+    /// Compiles `text` and runs its top level once, the functions can't see its variables.
+    ///
+    /// # Errors
+    /// If the script doesn't compile, fails at the top level or defines no hook.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut engine = Engine::new();
+        engine
+            .set_max_operations(1_000_000)
+            .set_max_call_levels(32)
+            .set_max_string_size(4096)
+            .set_max_array_size(4096)
+            .set_max_map_size(4096)
+            .on_progress(|operations| {
+                if operations % CHECK_PERIOD != 0 {
+                    return None;
+                }
+                let expired = DEADLINE
+                    .get()
+                    .is_some_and(|deadline| Instant::now() > deadline);
+                expired.then(|| {
+                    Dynamic::from(format!("over the {} ms limit", CALL_TIME_LIMIT.as_millis()))
+                })
+            })
+            .on_print(|text| log::info!("script: {text}"))
+            .on_debug(|text, _, pos| log::debug!("script {pos}: {text}"))
+            .register_fn("alert", |text: &str| {
+                OUTPUTS.with_borrow_mut(|outputs| outputs.push(HookOutput::Alert(text.to_owned())));
+            })
+            .register_fn("value", |name: &str, value: f64| {
+                OUTPUTS.with_borrow_mut(|outputs| {
+                    outputs.push(HookOutput::Value(name.to_owned(), value));
+                });
+            })
+            .register_fn("value", |name: &str, value: INT| {
+                #[allow(clippy::cast_precision_loss)]
+                let value = value as f64;
+                OUTPUTS.with_borrow_mut(|outputs| {
+                    outputs.push(HookOutput::Value(name.to_owned(), value));
+                });
+            });
+        let ast = engine.compile(text).map_err(|e| e.to_string())?;
+        let defines = |name: &str| {
+            ast.iter_functions()
+                .any(|f| f.name == name && f.params.len() == 2)
+        };
+        let (has_decode, has_on_message) = (defines("decode"), defines("on_message"));
+        if !has_decode && !has_on_message {
+            return Err(
+                "defines neither decode(cob_id, data) nor on_message(cob_id, data)".to_owned(),
+            );
+        }
+        DEADLINE.set(Some(Instant::now() + CALL_TIME_LIMIT));
+        let run = engine.run_ast(&ast);
+        DEADLINE.set(None);
+        run.map_err(|e| e.to_string())?;
+        Ok(Self {
+            source: String::new(),
+            engine,
+            ast,
+            has_decode,
+            has_on_message,
+            errors: Mutex::new(ErrorLog::default()),
+        })
+    }
+
+    pub fn has_decode(&self) -> bool {
+        self.has_decode
+    }
+
+    pub fn has_on_message(&self) -> bool {
+        self.has_on_message
+    }
+
+    /// Calls `name` with the COB-ID and data of `msg`, within the limits.
+    fn call(&self, name: &str, msg: &MessageCached) -> Option<Dynamic> {
+        let frame = &msg.msg.msg;
+        let data = Dynamic::from_blob(frame.data[..frame.dlc.min(8)].to_vec());
+        let options = CallFnOptions::new().eval_ast(false);
+        DEADLINE.set(Some(Instant::now() + CALL_TIME_LIMIT));
+        let result = self.engine.call_fn_with_options::<Dynamic>(
+            options,
+            &mut Scope::new(),
+            &self.ast,
+            name,
+            (INT::from(frame.cob_id), data),
+        );
+        DEADLINE.set(None);
+        result
+            .map_err(|e| self.on_error(&format!("{name}({:#05X}): {e}", frame.cob_id)))
+            .ok()
+    }
+
+    fn on_error(&self, error: &str) {
+        let mut errors = self.errors.lock().unwrap_or_else(PoisonError::into_inner);
+        // The same error repeats on every matching frame, once is enough.
+        let key = error.split_once(": ").map_or(error, |(_, e)| e).to_owned();
+        if !errors.reported.insert(key) {
+            return;
+        }
+        if errors.pending.len() < MAX_PENDING_ERRORS {
+            errors.pending.push(format!("Script: {error}"));
+        } else {
+            errors.suppressed += 1;
+        }
+    }
+
+    /// Text of `decode`, `None` if it isn't defined or returns `()` or an empty string.
+    pub fn decode(&self, msg: &MessageCached) -> Option<String> {
+        if !self.has_decode {
+            return None;
+        }
+        let text = self.call("decode", msg)?;
+        if text.is_unit() {
+            return None;
+        }
+        let text = text.to_string();
+        (!text.is_empty()).then_some(text)
+    }
+
+    /// Alerts and values of `on_message`.
+    pub fn on_message(&self, msg: &MessageCached) -> Vec<HookOutput> {
+        if !self.has_on_message {
+            return Vec::new();
+        }
+        OUTPUTS.with_borrow_mut(Vec::clear);
+        let called = self.call("on_message", msg).is_some();
+        let outputs = OUTPUTS.take();
+        if called {
+            outputs
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Errors of the calls since the last call, for the notifications.
+    pub fn take_errors(&self) -> Vec<String> {
+        let mut errors = self.errors.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut taken = std::mem::take(&mut errors.pending);
+        if errors.suppressed > 0 {
+            taken.push(format!("Script: {} more errors", errors.suppressed));
+            errors.suppressed = 0;
+        }
+        taken
+    }
+}
+
+/// Reads and compiles a script file.
+///
+/// # Errors
+/// If the file can't be read or the script is invalid.
+pub fn load(path: &str) -> Result<Script, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+    let mut script = Script::parse(&text).map_err(|e| format!("{path}: {e}"))?;
+    path.clone_into(&mut script.source);
+    Ok(script)
+}
+
+/// Sets the script decoding the frames and running the hooks, `None` to stop.
+pub fn set(script: Option<Script>) {
+    *LOADED.write().unwrap_or_else(PoisonError::into_inner) = script.map(Arc::new);
+}
+
+/// Script decoding the frames and running the hooks.
+pub fn loaded() -> Option<Arc<Script>> {
+    LOADED
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+}
+
+/// Decodes a frame with the loaded script.
+pub fn decode(msg: &MessageCached) -> Option<String> {
+    LOADED
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .as_ref()?
+        .decode(msg)
+}
+
+/// Loads the script, shows its errors and plots the values of its hooks.
+#[derive(Debug)]
+pub struct ScriptPanel {
+    path_raw: String,
+    /// Error of the last load.
+    error: Option<String>,
+    /// Series of the `value` calls, by name.
+    series: BTreeMap<String, ValuePlot>,
+    /// Time 0 of the series.
+    origin: tokio::time::Instant,
+}
+
+impl Default for ScriptPanel {
+    fn default() -> Self {
+        Self {
+            path_raw: String::new(),
+            error: None,
+            series: BTreeMap::new(),
+            origin: tokio::time::Instant::now(),
+        }
+    }
+}
+
+impl ScriptPanel {
+    /// Runs the hooks of the loaded script on `msg`, returns the alerts raised.
+    pub fn on_message(&mut self, msg: &MessageCached) -> Vec<String> {
+        let Some(script) = loaded() else {
+            return Vec::new();
+        };
+        let mut alerts = Vec::new();
+        for output in script.on_message(msg) {
+            match output {
+                HookOutput::Alert(text) => alerts.push(text),
+                HookOutput::Value(name, value) => {
+                    let plot = self.series.entry(name).or_default();
+                    plot.push(msg.get_timestamp(), value, self.origin, PLOT_DEPTH);
+                }
+            }
+        }
+        alerts
+    }
+
+    pub fn clear(&mut self) {
+        self.series.values_mut().for_each(ValuePlot::clear);
+    }
+
+    /// Shows the panel, returns `true` if the script was loaded or unloaded and the messages
+    /// must be decoded again.
+    pub fn update(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut changed = false;
+        let loaded = loaded();
+        ui.horizontal(|ui| {
+            ui.add(
+                TextEdit::singleline(&mut self.path_raw)
+                    .hint_text("path/to/script.rhai")
+                    .desired_width(250.0),
+            );
+            if ui
+                .add_enabled(
+                    !self.path_raw.trim().is_empty(),
+                    egui::Button::new("Load script…"),
+                )
+                .on_hover_text(
+                    "Decode the frames with decode(cob_id, data) and run on_message(cob_id, data)",
+                )
+                .clicked()
+            {
+                changed = self.load(self.path_raw.trim().to_owned());
+            }
+            if let Some(script) = &loaded {
+                if ui
+                    .button("⟳ Reload")
+                    .on_hover_text("Read the file again after editing it")
+                    .clicked()
+                {
+                    changed = self.load(script.source.clone());
+                }
+                if ui.button("Unload").clicked() {
+                    set(None);
+                    self.series.clear();
+                    changed = true;
+                }
+            }
+        });
+
+        if let Some(error) = &self.error {
+            ui.colored_label(egui::Color32::RED, error);
+        }
+        let Some(script) = loaded else {
+            ui.label("No script loaded");
+            return changed;
+        };
+        let hooks: Vec<_> = [
+            (script.has_decode(), "decode"),
+            (script.has_on_message(), "on_message"),
+        ]
+        .iter()
+        .filter_map(|(defined, name)| defined.then_some(*name))
+        .collect();
+        ui.label(format!("{}: {}", script.source, hooks.join(", ")));
+        for (id, (name, plot)) in (0..).zip(&self.series) {
+            ui.label(egui::RichText::new(name).color(OZON_PINK));
+            plot.ui(ui, 1_000 + id, name);
+        }
+        changed
+    }
+
+    fn load(&mut self, path: String) -> bool {
+        match load(&path) {
+            Ok(script) => {
+                set(Some(script));
+                self.path_raw = path;
+                self.error = None;
+                self.series.clear();
+                true
+            }
+            Err(e) => {
+                self.error = Some(e);
+                false
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{HookOutput, Script};
+    use crate::message_cached::MessageCached;
+    use oze_canopen::canopen::RxMessage;
+    use tokio::time::Instant;
+
+    #[test]
+    fn test_script() {
+        let frame = |cob_id, data: &[u8]| {
+            let mut raw = RxMessage {
+                timestamp: Instant::now(),
+                cob_id,
+                data: [0; 8],
+                dlc: data.len(),
+            };
+            raw.data[..data.len()].copy_from_slice(data);
+            MessageCached::new(0, raw)
+        };
+        let script = Script::parse(
+            r#"
+            fn decode(cob_id, data) {
+                if cob_id == 0x281 { `temperature ${data[0] - 40} °C` }
+            }
+            fn on_message(cob_id, data) {
+                if cob_id == 0x281 {
+                    value("temperature", data[0] - 40);
+                    if data[0] - 40 > 100 { alert("overheat") }
+                }
+                if cob_id == 0x666 { loop {} }
+                if cob_id == 0x667 { data[9] }
+            }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            script.decode(&frame(0x281, &[65])).as_deref(),
+            Some("temperature 25 °C")
+        );
+        assert_eq!(script.decode(&frame(0x181, &[65])), None);
+        assert_eq!(
+            script.on_message(&frame(0x281, &[150])),
+            [
+                HookOutput::Value("temperature".to_owned(), 110.0),
+                HookOutput::Alert("overheat".to_owned())
+            ]
+        );
+
+        // Stopped by the limits, reported once.
+        assert!(script.on_message(&frame(0x666, &[])).is_empty());
+        assert!(script.on_message(&frame(0x666, &[])).is_empty());
+        assert!(script.on_message(&frame(0x667, &[])).is_empty());
+        let errors = script.take_errors();
+        assert_eq!(errors.len(), 2, "{errors:?}");
+        assert!(errors[0].starts_with("Script: on_message(0x666)"));
+        assert!(script.take_errors().is_empty());
+
+        assert!(Script::parse("fn other() {}")
+            .unwrap_err()
+            .contains("neither"));
+        assert!(Script::parse("fn decode(cob_id, data) {").is_err());
+    }
+}
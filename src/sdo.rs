@@ -0,0 +1,432 @@
+use crate::eds::Eds;
+use core::fmt;
+use oze_canopen::proto::sdo::{
+    ResponseData, SdoRequest, SdoRequestData, SdoResponse, UploadResponseData,
+};
+use std::time::Duration;
+
+/// SDO abort codes of `CiA 301`.
+const ABORT_CODES: &[(u32, &str)] = &[
+    (0x0503_0000, "Toggle bit not alternated"),
+    (0x0504_0000, "SDO protocol timed out"),
+    (
+        0x0504_0001,
+        "Client/server command specifier not valid or unknown",
+    ),
+    (0x0504_0002, "Invalid block size (block mode only)"),
+    (0x0504_0003, "Invalid sequence number (block mode only)"),
+    (0x0504_0004, "CRC error (block mode only)"),
+    (0x0504_0005, "Out of memory"),
+    (0x0601_0000, "Unsupported access to an object"),
+    (0x0601_0001, "Attempt to read a write only object"),
+    (0x0601_0002, "Attempt to write a read only object"),
+    (
+        0x0602_0000,
+        "Object does not exist in the object dictionary",
+    ),
+    (0x0604_0041, "Object cannot be mapped to the PDO"),
+    (
+        0x0604_0042,
+        "The number and length of the objects to be mapped would exceed PDO length",
+    ),
+    (0x0604_0043, "General parameter incompatibility reason"),
+    (
+        0x0604_0047,
+        "General internal incompatibility in the device",
+    ),
+    (0x0606_0000, "Access failed due to a hardware error"),
+    (
+        0x0607_0010,
+        "Data type does not match, length of service parameter does not match",
+    ),
+    (
+        0x0607_0012,
+        "Data type does not match, length of service parameter too high",
+    ),
+    (
+        0x0607_0013,
+        "Data type does not match, length of service parameter too low",
+    ),
+    (0x0609_0011, "Sub-index does not exist"),
+    (0x0609_0030, "Invalid value for parameter (download only)"),
+    (
+        0x0609_0031,
+        "Value of parameter written too high (download only)",
+    ),
+    (
+        0x0609_0032,
+        "Value of parameter written too low (download only)",
+    ),
+    (0x0609_0036, "Maximum value is less than minimum value"),
+    (0x060A_0023, "Resource not available: SDO connection"),
+    (0x0800_0000, "General error"),
+    (
+        0x0800_0020,
+        "Data cannot be transferred or stored to the application",
+    ),
+    (
+        0x0800_0021,
+        "Data cannot be transferred or stored (local control)",
+    ),
+    (
+        0x0800_0022,
+        "Data cannot be transferred or stored (device state)",
+    ),
+    (
+        0x0800_0023,
+        "Object dictionary dynamic generation failed or no object dictionary is present",
+    ),
+    (0x0800_0024, "No data available"),
+];
+
+/// Time to wait for the answer of an SDO server.
+pub const SDO_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Longest data read by a segmented upload, e.g. the name of a device.
+pub const MAX_UPLOAD_LEN: usize = 1024;
+
+/// Answer of an SDO server to an expedited request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SdoReply {
+    /// The download was confirmed.
+    Downloaded,
+    /// The uploaded data.
+    Uploaded(Vec<u8>),
+    /// The transfer was aborted with the given abort code.
+    Abort(u32),
+    /// The server started a segmented upload, the data follows in segments.
+    Segmented,
+}
+
+/// Answer of an SDO server to an upload segment request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SegmentReply {
+    /// Data of the segment, `last` ends the transfer.
+    Data { data: Vec<u8>, last: bool },
+    /// The transfer was aborted with the given abort code.
+    Abort(u32),
+}
+
+/// Builds an initiate upload request for the given object.
+pub fn upload_request(index: u16, subindex: u8) -> Vec<u8> {
+    let [index_lo, index_hi] = index.to_le_bytes();
+    vec![0x40, index_lo, index_hi, subindex, 0, 0, 0, 0]
+}
+
+/// Builds an upload segment request, `toggle` alternates from `false` on the first segment.
+pub fn upload_segment_request(toggle: bool) -> Vec<u8> {
+    vec![0x60 | (u8::from(toggle) << 4), 0, 0, 0, 0, 0, 0, 0]
+}
+
+/// Builds an expedited download request, `None` if the data is longer than 4 bytes.
+pub fn download_request(index: u16, subindex: u8, data: &[u8]) -> Option<Vec<u8>> {
+    let n = u8::try_from(4_usize.checked_sub(data.len())?).ok()?;
+    // Initiate download, expedited, size indicated
+    let ccs = 0x20 | (n << 2) | 0x03;
+    let [index_lo, index_hi] = index.to_le_bytes();
+
+    let mut request = vec![ccs, index_lo, index_hi, subindex];
+    request.extend_from_slice(data);
+    request.resize(8, 0);
+    Some(request)
+}
+
+/// Parses the answer of an SDO server, `None` if it is not about the given object.
+pub fn parse_reply(data: &[u8; 8], index: u16, subindex: u8) -> Option<SdoReply> {
+    if u16::from_le_bytes([data[1], data[2]]) != index || data[3] != subindex {
+        return None;
+    }
+
+    let cmd = data[0];
+    match cmd >> 5 {
+        // Initiate download response
+        3 => Some(SdoReply::Downloaded),
+        // Initiate upload response
+        2 => {
+            let expedited = cmd & 0x02 != 0;
+            let size_indicated = cmd & 0x01 != 0;
+            if !expedited {
+                return Some(SdoReply::Segmented);
+            }
+            let len = if size_indicated {
+                4 - usize::from((cmd >> 2) & 0x03)
+            } else {
+                4
+            };
+            Some(SdoReply::Uploaded(data[4..4 + len].to_vec()))
+        }
+        // Abort transfer
+        4 => Some(SdoReply::Abort(u32::from_le_bytes([
+            data[4], data[5], data[6], data[7],
+        ]))),
+        _ => None,
+    }
+}
+
+/// Parses the answer to an upload segment request, `None` if it is not a segment with the
+/// expected `toggle` nor an abort.
+pub fn parse_segment(data: &[u8; 8], toggle: bool) -> Option<SegmentReply> {
+    let cmd = data[0];
+    match cmd >> 5 {
+        // Upload segment response
+        0 if (cmd & 0x10 != 0) == toggle => {
+            let unused = usize::from((cmd >> 1) & 0x07);
+            Some(SegmentReply::Data {
+                data: data[1..8 - unused].to_vec(),
+                last: cmd & 0x01 != 0,
+            })
+        }
+        // Abort transfer
+        4 => Some(SegmentReply::Abort(u32::from_le_bytes([
+            data[4], data[5], data[6], data[7],
+        ]))),
+        _ => None,
+    }
+}
+
+/// Describes an SDO abort code, empty if the code is unknown.
+pub fn abort_description(code: u32) -> &'static str {
+    ABORT_CODES
+        .binary_search_by_key(&code, |(c, _)| *c)
+        .map_or("", |i| ABORT_CODES[i].1)
+}
+
+/// Formats an abort transfer the same way in the viewer and in the sender feedback,
+/// e.g. `SDO abort 0x6040:00 — 0x08000022 Data cannot be transferred or stored (device state)`.
+pub fn abort_text(index: u16, subindex: u8, code: u32) -> String {
+    let text = format!("SDO abort 0x{index:04X}:{subindex:02X} — 0x{code:08X}");
+    match abort_description(code) {
+        "" => text,
+        description => format!("{text} {description}"),
+    }
+}
+
+/// Decodes an abort transfer frame (command specifier 0x80) as `(index, subindex, code)`.
+pub fn parse_abort(data: &[u8; 8]) -> Option<(u16, u8, u32)> {
+    (data[0] == 0x80).then(|| {
+        (
+            u16::from_le_bytes([data[1], data[2]]),
+            data[3],
+            u32::from_le_bytes([data[4], data[5], data[6], data[7]]),
+        )
+    })
+}
+
+/// Initiate request or response of an SDO transfer named after the EDS of the node, e.g.
+/// `SDO write node 3: Producer Heartbeat Time (0x1017:00) = 1000`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SdoAccess {
+    pub node_id: u8,
+    /// Download to the server, otherwise upload from it.
+    pub write: bool,
+    pub index: u16,
+    pub subindex: u8,
+    /// Name of the object, `None` if it isn't in the EDS.
+    pub name: Option<String>,
+    /// Value carried by an expedited frame, formatted after the object data type.
+    pub value: Option<String>,
+    /// Size announced by a segmented transfer.
+    pub size: Option<u32>,
+    /// Response confirming a download.
+    pub confirmed: bool,
+}
+
+impl SdoAccess {
+    fn new(node_id: u8, write: bool, index: u16, subindex: u8, eds: &Eds) -> Self {
+        Self {
+            node_id,
+            write,
+            index,
+            subindex,
+            name: eds.name(index, subindex),
+            value: None,
+            size: None,
+            confirmed: false,
+        }
+    }
+
+    /// Names a client request, `None` for the segments which don't carry the object.
+    pub fn from_request(node_id: u8, request: &SdoRequest, eds: &Eds) -> Option<Self> {
+        Some(match &request.req {
+            SdoRequestData::InitiateUpload(r) => {
+                Self::new(node_id, false, r.index, r.subindex, eds)
+            }
+            SdoRequestData::InitiateDownload(r) => Self {
+                size: Some(r.size),
+                ..Self::new(node_id, true, r.index, r.subindex, eds)
+            },
+            SdoRequestData::InitiateDownloadExpedited(r) => Self {
+                value: Some(eds.format_value(r.index, r.subindex, &r.data)),
+                ..Self::new(node_id, true, r.index, r.subindex, eds)
+            },
+            SdoRequestData::UploadSegment(_) | SdoRequestData::DownloadSegment(_) => return None,
+        })
+    }
+
+    /// Names a server response, `None` for the segments and aborts.
+    pub fn from_response(node_id: u8, response: &SdoResponse, eds: &Eds) -> Option<Self> {
+        Some(match &response.resp {
+            ResponseData::Download(r) => Self {
+                confirmed: true,
+                ..Self::new(node_id, true, r.index, r.subindex, eds)
+            },
+            ResponseData::Upload(r) => {
+                let access = Self::new(node_id, false, r.index, r.subindex, eds);
+                match &r.data {
+                    UploadResponseData::DataExpedited(data) => Self {
+                        value: Some(eds.format_value(r.index, r.subindex, data)),
+                        ..access
+                    },
+                    UploadResponseData::Size(size) => Self {
+                        size: Some(*size),
+                        ..access
+                    },
+                    _ => access,
+                }
+            }
+            ResponseData::DownloadSegment(_)
+            | ResponseData::UploadSegment(_)
+            | ResponseData::Abort(_) => return None,
+        })
+    }
+}
+
+impl fmt::Display for SdoAccess {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let operation = if self.write { "write" } else { "read" };
+        write!(f, "SDO {operation} node {}: ", self.node_id)?;
+        let object = format!("0x{:04X}:{:02X}", self.index, self.subindex);
+        match &self.name {
+            Some(name) => write!(f, "{name} ({object})")?,
+            None => write!(f, "{object}")?,
+        }
+        if let Some(value) = &self.value {
+            write!(f, " = {value}")?;
+        } else if let Some(size) = self.size {
+            write!(f, ", {size} bytes")?;
+        } else if self.confirmed {
+            write!(f, " confirmed")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        abort_text, download_request, parse_abort, parse_reply, parse_segment, upload_request,
+        upload_segment_request, SdoAccess, SdoReply, SegmentReply, ABORT_CODES,
+    };
+    use crate::eds::Eds;
+    use oze_canopen::{
+        proto::sdo::{SdoRequest, SdoResponse},
+        BinRead,
+    };
+    use std::io::Cursor;
+
+    #[test]
+    fn test_requests() {
+        assert_eq!(upload_request(0x1018, 1), [0x40, 0x18, 0x10, 1, 0, 0, 0, 0]);
+        assert_eq!(
+            download_request(0x6040, 0, &[0x06, 0x00]),
+            Some(vec![0x2B, 0x40, 0x60, 0, 0x06, 0x00, 0, 0])
+        );
+        assert_eq!(download_request(0x6040, 0, &[0; 5]), None);
+    }
+
+    #[test]
+    fn test_parse_reply() {
+        assert_eq!(
+            parse_reply(&[0x60, 0x40, 0x60, 0, 0, 0, 0, 0], 0x6040, 0),
+            Some(SdoReply::Downloaded)
+        );
+        assert_eq!(
+            parse_reply(&[0x4B, 0x41, 0x60, 0, 0x37, 0x02, 0, 0], 0x6041, 0),
+            Some(SdoReply::Uploaded(vec![0x37, 0x02]))
+        );
+        assert_eq!(
+            parse_reply(&[0x80, 0x00, 0x1A, 1, 0x02, 0x00, 0x01, 0x06], 0x1A00, 1),
+            Some(SdoReply::Abort(0x0601_0002))
+        );
+        assert_eq!(
+            parse_reply(&[0x60, 0x40, 0x60, 0, 0, 0, 0, 0], 0x6041, 0),
+            None
+        );
+        assert_eq!(
+            parse_reply(&[0x41, 0x08, 0x10, 0, 9, 0, 0, 0], 0x1008, 0),
+            Some(SdoReply::Segmented)
+        );
+    }
+
+    #[test]
+    fn test_segments() {
+        assert_eq!(upload_segment_request(false), [0x60, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(upload_segment_request(true), [0x70, 0, 0, 0, 0, 0, 0, 0]);
+        // "Drive 42 " in two segments, the second one has 5 unused bytes.
+        assert_eq!(
+            parse_segment(b"\x00Drive 4", false),
+            Some(SegmentReply::Data {
+                data: b"Drive 4".to_vec(),
+                last: false
+            })
+        );
+        assert_eq!(
+            parse_segment(b"\x1B2 \0\0\0\0\0", true),
+            Some(SegmentReply::Data {
+                data: b"2 ".to_vec(),
+                last: true
+            })
+        );
+        assert_eq!(parse_segment(b"\x1B2 \0\0\0\0\0", false), None);
+        assert_eq!(
+            parse_segment(&[0x80, 0x08, 0x10, 0, 0, 0, 0x04, 0x05], true),
+            Some(SegmentReply::Abort(0x0504_0000))
+        );
+    }
+
+    #[test]
+    fn test_abort() {
+        assert!(ABORT_CODES.windows(2).all(|w| w[0].0 < w[1].0));
+        let (index, subindex, code) =
+            parse_abort(&[0x80, 0x40, 0x60, 0, 0x22, 0x00, 0x00, 0x08]).unwrap();
+        assert_eq!(
+            abort_text(index, subindex, code),
+            "SDO abort 0x6040:00 — 0x08000022 Data cannot be transferred or stored (device state)"
+        );
+        assert_eq!(
+            abort_text(0x1000, 0, 0x1234_5678),
+            "SDO abort 0x1000:00 — 0x12345678"
+        );
+        assert_eq!(parse_abort(&[0x60, 0x40, 0x60, 0, 0, 0, 0, 0]), None);
+    }
+
+    #[test]
+    fn test_access() {
+        let eds = Eds::parse("[1017]\nParameterName=Producer Heartbeat Time\nDataType=0x0006\n");
+        let request = download_request(0x1017, 0, &1000u16.to_le_bytes()).unwrap();
+        let request = SdoRequest::read(&mut Cursor::new(request)).unwrap();
+        assert_eq!(
+            SdoAccess::from_request(3, &request, &eds)
+                .unwrap()
+                .to_string(),
+            "SDO write node 3: Producer Heartbeat Time (0x1017:00) = 1000"
+        );
+
+        let response = [0x60, 0x17, 0x10, 0, 0, 0, 0, 0];
+        let response = SdoResponse::read(&mut Cursor::new(response)).unwrap();
+        assert_eq!(
+            SdoAccess::from_response(3, &response, &eds)
+                .unwrap()
+                .to_string(),
+            "SDO write node 3: Producer Heartbeat Time (0x1017:00) confirmed"
+        );
+
+        let request = SdoRequest::read(&mut Cursor::new(upload_request(0x1018, 1))).unwrap();
+        assert_eq!(
+            SdoAccess::from_request(3, &request, &eds)
+                .unwrap()
+                .to_string(),
+            "SDO read node 3: 0x1018:01"
+        );
+    }
+}
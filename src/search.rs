@@ -0,0 +1,209 @@
+use crate::message_cached::MessageCached;
+use egui::TextEdit;
+
+/// What the search box looks for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Query {
+    /// Exact COB-ID, typed as `id:181` or as a single 3 digit hex number.
+    CobId(u16),
+    /// Byte sequence anywhere in the data, `None` is a `??` wildcard.
+    Bytes(Vec<Option<u8>>),
+    /// Case insensitive text of the decoded info.
+    Text(String),
+}
+
+impl Query {
+    pub fn parse(raw: &str) -> Option<Self> {
+        let raw = raw.trim();
+        if raw.is_empty() {
+            return None;
+        }
+        if let Some(id) = raw.strip_prefix("id:") {
+            return parse_cob_id(id.trim()).map(Self::CobId);
+        }
+
+        let tokens: Vec<&str> = raw.split_whitespace().collect();
+        if let [token] = tokens[..] {
+            if token.len() == 3 {
+                if let Some(cob_id) = parse_cob_id(token) {
+                    return Some(Self::CobId(cob_id));
+                }
+            }
+        }
+        let bytes: Option<Vec<Option<u8>>> = tokens
+            .iter()
+            .map(|token| match *token {
+                "??" => Some(None),
+                t if t.len() == 2 => u8::from_str_radix(t, 16).ok().map(Some),
+                _ => None,
+            })
+            .collect();
+        match bytes {
+            Some(bytes) if bytes.len() <= 8 => Some(Self::Bytes(bytes)),
+            _ => Some(Self::Text(raw.to_lowercase())),
+        }
+    }
+
+    pub fn matches(&self, msg: &MessageCached) -> bool {
+        match self {
+            Self::CobId(cob_id) => msg.msg.msg.cob_id == *cob_id,
+            Self::Bytes(pattern) => {
+                let data = &msg.msg.msg.data[..msg.msg.msg.dlc.min(8)];
+                data.windows(pattern.len()).any(|window| {
+                    window
+                        .iter()
+                        .zip(pattern)
+                        .all(|(byte, expected)| expected.map_or(true, |e| e == *byte))
+                })
+            }
+            Self::Text(text) => msg.info().to_lowercase().contains(text),
+        }
+    }
+}
+
+fn parse_cob_id(raw: &str) -> Option<u16> {
+    let raw = raw.trim_start_matches("0x").trim_start_matches("0X");
+    u16::from_str_radix(raw, 16).ok().filter(|id| *id <= 0x7FF)
+}
+
+/// Search box of the viewer, the hits are the message indices in display order.
+#[derive(Debug, Default)]
+pub struct Search {
+    raw: String,
+    query: Option<Query>,
+    hits: Vec<u64>,
+    /// Position of the shown hit in `hits`.
+    current: Option<usize>,
+    /// Generation of the filtered messages and newest message the hits were computed on.
+    searched: Option<(u64, u64)>,
+}
+
+impl Search {
+    /// Updates the hits with the messages received since the last call, or recomputes them if
+    /// the query or the filtered messages changed. `messages` are the filtered messages, newest
+    /// first, `generation` changes when they are filtered again from scratch.
+    pub fn refresh(&mut self, messages: &[&MessageCached], generation: u64) {
+        let Some(query) = &self.query else {
+            self.hits.clear();
+            return;
+        };
+        let newest = messages.first().map_or(0, |msg| msg.index);
+        let current = self.current.and_then(|i| self.hits.get(i).copied());
+        match self.searched {
+            Some((searched, previous)) if searched == generation => {
+                if newest != previous {
+                    let mut hits: Vec<u64> = messages
+                        .iter()
+                        .take_while(|msg| msg.index > previous)
+                        .filter(|msg| query.matches(msg))
+                        .map(|msg| msg.index)
+                        .collect();
+                    hits.append(&mut self.hits);
+                    self.hits = hits;
+                }
+                // The oldest messages were trimmed from the buffer.
+                let oldest = messages.last().map_or(u64::MAX, |msg| msg.index);
+                while self.hits.last().is_some_and(|hit| *hit < oldest) {
+                    self.hits.pop();
+                }
+            }
+            _ => {
+                self.hits = messages
+                    .iter()
+                    .filter(|msg| query.matches(msg))
+                    .map(|msg| msg.index)
+                    .collect();
+            }
+        }
+        self.searched = Some((generation, newest));
+        // Keep showing the same message when new ones arrive.
+        self.current = current.and_then(|index| self.hits.iter().position(|i| *i == index));
+    }
+
+    /// Draws the search box, returns the message index to scroll to.
+    pub fn update(&mut self, ui: &mut egui::Ui) -> Option<u64> {
+        let mut target = None;
+        ui.horizontal(|ui| {
+            let response = ui
+                .add(
+                    TextEdit::singleline(&mut self.raw)
+                        .hint_text("search")
+                        .desired_width(200.0),
+                )
+                .on_hover_text(
+                    "Hex bytes anywhere in the data with ?? wildcards ('?? 27'), a COB-ID ('181' or 'id:181') or text of the info column",
+                );
+            if response.changed() {
+                self.query = Query::parse(&self.raw);
+                self.searched = None;
+                self.current = None;
+            }
+            let enter = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            if self.query.is_none() {
+                return;
+            }
+
+            // The hits are newest first, "next" goes down the list.
+            if ui.button("⏶").on_hover_text("Previous hit").clicked() {
+                target = self.step(false);
+            }
+            if ui.button("⏷").on_hover_text("Next hit").clicked() || enter {
+                target = self.step(true);
+            }
+            match self.current {
+                Some(current) => ui.label(format!("{} / {} hits", current + 1, self.hits.len())),
+                None => ui.label(format!("{} hits", self.hits.len())),
+            };
+        });
+        target
+    }
+
+    fn step(&mut self, forward: bool) -> Option<u64> {
+        if self.hits.is_empty() {
+            return None;
+        }
+        let len = self.hits.len();
+        let current = match (self.current, forward) {
+            (None, true) => 0,
+            (None, false) => len - 1,
+            (Some(i), true) => (i + 1) % len,
+            (Some(i), false) => (i + len - 1) % len,
+        };
+        self.current = Some(current);
+        Some(self.hits[current])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Query;
+    use crate::message_cached::MessageCached;
+    use oze_canopen::canopen::RxMessage;
+    use tokio::time::Instant;
+
+    #[test]
+    fn test_query() {
+        let msg = MessageCached::new(
+            0,
+            RxMessage {
+                timestamp: Instant::now(),
+                cob_id: 0x701,
+                data: [0x05, 0x27, 0x10, 0, 0, 0, 0, 0],
+                dlc: 3,
+            },
+        );
+        assert_eq!(Query::parse("701"), Some(Query::CobId(0x701)));
+        assert_eq!(Query::parse("id:0x701"), Some(Query::CobId(0x701)));
+        assert_eq!(
+            Query::parse("?? 27"),
+            Some(Query::Bytes(vec![None, Some(0x27)]))
+        );
+        assert!(Query::parse("?? 27").unwrap().matches(&msg));
+        assert!(Query::parse("27 10").unwrap().matches(&msg));
+        // Bytes past the DLC don't match.
+        assert!(!Query::parse("10 00").unwrap().matches(&msg));
+        assert!(Query::parse("operational").unwrap().matches(&msg));
+        assert!(!Query::parse("stopped").unwrap().matches(&msg));
+        assert_eq!(Query::parse("  "), None);
+    }
+}
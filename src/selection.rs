@@ -0,0 +1,136 @@
+use egui::Modifiers;
+use std::collections::BTreeSet;
+
+/// Rows selected in a table, identified by a key that stays valid when rows are added,
+/// e.g. the message index.
+#[derive(Debug, Default, Clone)]
+pub struct Selection {
+    keys: BTreeSet<u64>,
+    /// Row the next shift-click extends the selection from.
+    anchor: Option<u64>,
+    /// A row was clicked since the last call to `take_clicked`.
+    clicked: bool,
+}
+
+impl Selection {
+    pub fn contains(&self, key: u64) -> bool {
+        self.keys.contains(&key)
+    }
+
+    /// Last clicked row if it is still selected.
+    pub fn current(&self) -> Option<u64> {
+        self.anchor.filter(|anchor| self.keys.contains(anchor))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.keys.clear();
+        self.anchor = None;
+    }
+
+    pub fn remove(&mut self, key: u64) {
+        self.keys.remove(&key);
+        if self.anchor == Some(key) {
+            self.anchor = None;
+        }
+    }
+
+    /// Applies a click on the row `key`: a plain click selects only this row, ctrl toggles it
+    /// and shift selects the rows between the anchor and it. `order` lists the keys of the rows
+    /// as displayed.
+    pub fn click(&mut self, key: u64, modifiers: Modifiers, order: &[u64]) {
+        self.clicked = true;
+        let anchor = self
+            .anchor
+            .and_then(|anchor| order.iter().position(|k| *k == anchor));
+        let position = order.iter().position(|k| *k == key);
+        match (modifiers.shift, anchor, position) {
+            (true, Some(anchor), Some(position)) => {
+                if !modifiers.command {
+                    self.keys.clear();
+                }
+                let range = anchor.min(position)..=anchor.max(position);
+                self.keys.extend(&order[range]);
+            }
+            _ if modifiers.command => {
+                if !self.keys.remove(&key) {
+                    self.keys.insert(key);
+                }
+                self.anchor = Some(key);
+            }
+            _ => {
+                let only = self.keys.len() == 1 && self.keys.contains(&key);
+                self.keys.clear();
+                if !only {
+                    self.keys.insert(key);
+                }
+                self.anchor = Some(key);
+            }
+        }
+    }
+
+    /// Selects only the row `key`, as a plain click would.
+    pub fn select_only(&mut self, key: u64) {
+        self.clicked = true;
+        self.keys.clear();
+        self.keys.insert(key);
+        self.anchor = Some(key);
+    }
+
+    /// Selects the row under a right click unless it is already part of the selection.
+    pub fn secondary_click(&mut self, key: u64) {
+        if !self.contains(key) {
+            self.click(key, Modifiers::NONE, &[]);
+        }
+    }
+
+    /// Returns whether a row was clicked since the last call.
+    pub fn take_clicked(&mut self) -> bool {
+        std::mem::take(&mut self.clicked)
+    }
+
+    /// Whether the copy shortcut was pressed for this selection. Text fields keep the shortcut
+    /// while they have the focus.
+    pub fn copy_requested(&self, ui: &egui::Ui) -> bool {
+        !self.is_empty()
+            && ui.memory(|m| m.focused().is_none())
+            && ui.input(|i| i.events.iter().any(|e| matches!(e, egui::Event::Copy)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Selection;
+    use egui::Modifiers;
+
+    #[test]
+    fn test_click() {
+        let order = [9, 8, 7, 6, 5];
+        let mut selection = Selection::default();
+        selection.click(8, Modifiers::NONE, &order);
+        selection.click(6, Modifiers::SHIFT, &order);
+        assert_eq!(
+            selection.keys.iter().copied().collect::<Vec<_>>(),
+            [6, 7, 8]
+        );
+
+        selection.click(7, Modifiers::COMMAND, &order);
+        assert_eq!(selection.keys.iter().copied().collect::<Vec<_>>(), [6, 8]);
+
+        // New rows don't change the selection, the range extends from the anchor.
+        let order = [11, 10, 9, 8, 7, 6, 5];
+        selection.click(9, Modifiers::SHIFT | Modifiers::COMMAND, &order);
+        assert_eq!(
+            selection.keys.iter().copied().collect::<Vec<_>>(),
+            [6, 7, 8, 9]
+        );
+
+        selection.click(5, Modifiers::NONE, &order);
+        assert_eq!(selection.keys.iter().copied().collect::<Vec<_>>(), [5]);
+        selection.click(5, Modifiers::NONE, &order);
+        assert!(selection.is_empty());
+    }
+}
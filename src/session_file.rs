@@ -0,0 +1,251 @@
+use crate::{
+    bus_stats::BusStatsSnapshot,
+    device_info::DeviceInfo,
+    gateway::GatewaySide,
+    message_cached::{Direction, MessageCached},
+    settings::AppSettings,
+};
+use chrono::{DateTime, Local, SecondsFormat, Utc};
+use oze_canopen::canopen::{NodeId, RxMessage};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, time::Duration};
+use tokio::time::Instant;
+
+/// Format written by this version. Files of older formats are read with defaults for what
+/// they lack, newer ones are refused.
+pub const VERSION: u32 = 1;
+
+/// Frame of a saved session.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SavedMessage {
+    pub index: u64,
+    /// Microseconds since the start of the capture.
+    pub time_us: u64,
+    pub cob_id: u16,
+    pub data: Vec<u8>,
+    #[serde(default)]
+    pub direction: Direction,
+    #[serde(default)]
+    pub origin: Option<GatewaySide>,
+}
+
+/// Messages, statistics and settings of a session, reopened offline.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionFile {
+    pub version: u32,
+    /// Local time of the save, RFC 3339.
+    pub saved: String,
+    pub can_name: String,
+    pub bitrate: Option<u32>,
+    /// Wall-clock time of the start of the capture, microseconds since the epoch.
+    pub start_us: i64,
+    /// Filters, pinned filters and display settings.
+    pub settings: AppSettings,
+    /// Buffered messages and those kept only by the pinned filters, oldest first.
+    pub messages: Vec<SavedMessage>,
+    /// Statistics at the time of the save, counting the messages no longer buffered.
+    pub stats: Option<BusStatsSnapshot>,
+    /// Device information read from the nodes, for the hardware identification.
+    pub device_infos: BTreeMap<NodeId, DeviceInfo>,
+}
+
+impl SessionFile {
+    /// Session of `messages`, in any order and possibly repeated, captured since `start`.
+    pub fn new<'a>(
+        can_name: String,
+        bitrate: Option<u32>,
+        settings: AppSettings,
+        stats: BusStatsSnapshot,
+        messages: impl IntoIterator<Item = &'a MessageCached>,
+        start: (Instant, DateTime<Local>),
+    ) -> Self {
+        let mut messages: Vec<SavedMessage> = messages
+            .into_iter()
+            .map(|msg| SavedMessage {
+                index: msg.index,
+                time_us: duration_us(msg.get_timestamp().saturating_duration_since(start.0)),
+                cob_id: msg.msg.msg.cob_id,
+                data: msg.msg.msg.data[..msg.msg.msg.dlc.min(8)].to_vec(),
+                direction: msg.direction,
+                origin: msg.origin,
+            })
+            .collect();
+        messages.sort_unstable_by_key(|msg| msg.index);
+        messages.dedup_by_key(|msg| msg.index);
+        Self {
+            version: VERSION,
+            saved: Local::now().to_rfc3339_opts(SecondsFormat::Millis, false),
+            can_name,
+            bitrate,
+            start_us: start.1.timestamp_micros(),
+            settings,
+            messages,
+            stats: Some(stats),
+            device_infos: BTreeMap::new(),
+        }
+    }
+
+    pub fn file_name() -> String {
+        format!("session-{}.json", Local::now().format("%Y%m%d-%H%M%S"))
+    }
+
+    pub fn to_json(&self) -> String {
+        // Only strings, numbers and maps with string keys, serialization can't fail.
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    /// # Errors
+    /// If the file can't be read, isn't a session or was saved by a newer version.
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+        Self::from_json(&text).map_err(|e| format!("{path}: {e}"))
+    }
+
+    /// # Errors
+    /// If the text isn't a session or was saved by a newer version.
+    pub fn from_json(text: &str) -> Result<Self, String> {
+        let file: Self = serde_json::from_str(text).map_err(|e| e.to_string())?;
+        if file.version > VERSION {
+            return Err(format!(
+                "saved in format {}, this version reads up to {VERSION}",
+                file.version
+            ));
+        }
+        Ok(file)
+    }
+
+    /// Wall-clock time of the start of the capture.
+    pub fn start_wall(&self) -> DateTime<Local> {
+        DateTime::<Utc>::from_timestamp_micros(self.start_us)
+            .unwrap_or_default()
+            .with_timezone(&Local)
+    }
+
+    /// Start of the capture such that the last message was received now.
+    pub fn start(&self) -> Instant {
+        let span = self.messages.last().map_or(0, |msg| msg.time_us);
+        let now = Instant::now();
+        now.checked_sub(Duration::from_micros(span)).unwrap_or(now)
+    }
+
+    /// Messages decoded again, oldest first, received `time_us` after `start`.
+    pub fn messages(&self, start: Instant) -> Vec<MessageCached> {
+        self.messages
+            .iter()
+            .map(|saved| saved.decode(start))
+            .collect()
+    }
+}
+
+impl SavedMessage {
+    /// Message decoded again, received `time_us` after `start`.
+    pub fn decode(&self, start: Instant) -> MessageCached {
+        let mut data = [0; 8];
+        let dlc = self.data.len().min(8);
+        data[..dlc].copy_from_slice(&self.data[..dlc]);
+        let mut msg = MessageCached::new(
+            self.index,
+            RxMessage {
+                timestamp: start + Duration::from_micros(self.time_us),
+                cob_id: self.cob_id,
+                data,
+                dlc,
+            },
+        );
+        msg.direction = self.direction;
+        msg.origin = self.origin;
+        msg
+    }
+}
+
+fn duration_us(duration: Duration) -> u64 {
+    u64::try_from(duration.as_micros()).unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SessionFile, VERSION};
+    use crate::{
+        bus_stats::BusStats,
+        device_info::DeviceInfo,
+        message_cached::{Direction, MessageCached},
+        settings::AppSettings,
+    };
+    use chrono::Local;
+    use oze_canopen::canopen::RxMessage;
+    use std::time::Duration;
+    use tokio::time::Instant;
+
+    #[test]
+    fn test_session_file() {
+        let start = Instant::now();
+        let frame = |index, ms, cob_id, data: &[u8]| {
+            let mut raw = RxMessage {
+                timestamp: start + Duration::from_millis(ms),
+                cob_id,
+                data: [0; 8],
+                dlc: data.len(),
+            };
+            raw.data[..data.len()].copy_from_slice(data);
+            MessageCached::new(index, raw)
+        };
+        let mut sent = frame(2, 20, 0x601, &[0x40, 0x18, 0x10, 0x01]);
+        sent.direction = Direction::Tx;
+        let heartbeat = frame(1, 10, 0x701, &[0x05]);
+        // The heartbeat is also in the history of a pinned filter.
+        let messages = [&sent, &heartbeat, &heartbeat];
+
+        let settings = AppSettings {
+            can_name: "can1".to_owned(),
+            ..AppSettings::default()
+        };
+        let mut saved = SessionFile::new(
+            "can1".to_owned(),
+            Some(500_000),
+            settings.clone(),
+            BusStats::new().snapshot(start),
+            messages,
+            (start, Local::now()),
+        );
+        let drive = DeviceInfo {
+            device_type: 0x0002_0192,
+            identity: None,
+            name: Some("Drive".to_owned()),
+            hardware_version: None,
+            software_version: Some("1.2".to_owned()),
+            read_at: String::new(),
+        };
+        saved.device_infos.insert(1, drive.clone());
+        let path = std::env::temp_dir().join(format!("session-{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        std::fs::write(path, saved.to_json()).unwrap();
+        let loaded = SessionFile::load(path);
+        std::fs::write(path, r#"{"version": 99}"#).unwrap();
+        let newer = SessionFile::load(path);
+        std::fs::write(path, r#"{"can_name": "can0", "messages": []}"#).unwrap();
+        let older = SessionFile::load(path);
+        std::fs::remove_file(path).unwrap();
+
+        let loaded = loaded.unwrap();
+        assert_eq!(loaded.version, VERSION);
+        assert_eq!(loaded.settings, settings);
+        assert_eq!(loaded.device_infos.get(&1), Some(&drive));
+        assert_eq!(
+            loaded.start_wall().timestamp_micros(),
+            saved.start_wall().timestamp_micros()
+        );
+        let start = loaded.start();
+        let messages = loaded.messages(start);
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].index, 1);
+        assert_eq!(messages[0].hex_str, heartbeat.hex_str);
+        assert_eq!(messages[1].direction, Direction::Tx);
+        assert_eq!(
+            messages[1].get_timestamp().duration_since(start),
+            Duration::from_millis(20)
+        );
+        assert!(newer.unwrap_err().contains("format 99"));
+        assert_eq!(older.unwrap().can_name, "can0");
+    }
+}
@@ -0,0 +1,200 @@
+use crate::{
+    config, data_format::DataFormat, filter_profile::FilterProfile,
+    interpreter::InterpreterSettings, message_row::TimestampMode, message_sender::SenderSettings,
+    pinned_filter::PINNED_HISTORY_DEPTH,
+};
+use bitflags::bitflags;
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize};
+use std::ops::RangeInclusive;
+
+/// File of the application settings in the config directory. JSON, so a field of another
+/// version that can't be read is defaulted alone.
+const CONFIG_FILE: &str = "app.json";
+
+/// Default number of messages kept in the viewer.
+pub const MESSAGES_COUNT: usize = 100_000;
+
+/// Allowed number of messages kept in the viewer.
+pub const MESSAGES_COUNT_RANGE: RangeInclusive<usize> = 256..=1_000_000;
+
+/// Allowed number of messages kept by the driver for the GUI.
+pub const DRIVER_BUFFER_RANGE: RangeInclusive<usize> = 256..=1_000_000;
+
+/// Allowed number of messages kept per pinned filter.
+pub const PINNED_HISTORY_RANGE: RangeInclusive<usize> = 1..=100_000;
+
+/// Allowed heights of the chart, in points.
+pub const CHART_HEIGHT_RANGE: RangeInclusive<f32> = 100.0..=800.0;
+
+/// Width kept for the central panel when the side panels are fitted to a small screen.
+const MIN_CENTRAL_WIDTH: f32 = 400.0;
+
+/// Sizes of the message buffers.
+///
+/// `messages` and `pinned_history` can be changed at runtime, `driver_buffer` is the
+/// capacity of the driver to GUI channel and is fixed at startup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BufferSettings {
+    pub driver_buffer: usize,
+    pub messages: usize,
+    pub pinned_history: usize,
+}
+
+impl Default for BufferSettings {
+    fn default() -> Self {
+        Self {
+            driver_buffer: crate::driver::DATA_CHANNEL_CAPACITY,
+            messages: MESSAGES_COUNT,
+            pinned_history: PINNED_HISTORY_DEPTH,
+        }
+    }
+}
+
+impl BufferSettings {
+    /// Returns settings with every value clamped to its allowed range.
+    #[must_use]
+    pub fn clamped(self) -> Self {
+        Self {
+            driver_buffer: clamp(self.driver_buffer, &DRIVER_BUFFER_RANGE),
+            messages: clamp(self.messages, &MESSAGES_COUNT_RANGE),
+            pinned_history: clamp(self.pinned_history, &PINNED_HISTORY_RANGE),
+        }
+    }
+}
+
+fn clamp(value: usize, range: &RangeInclusive<usize>) -> usize {
+    value.clamp(*range.start(), *range.end())
+}
+
+/// Default value for a field that can't be read instead of failing the whole file.
+fn lenient<'de, D: Deserializer<'de>, T: DeserializeOwned + Default>(
+    deserializer: D,
+) -> Result<T, D::Error> {
+    let value = serde_json::Value::deserialize(deserializer)?;
+    Ok(serde_json::from_value(value).unwrap_or_default())
+}
+
+/// Widths and heights of the resizable panels and of the chart, in points.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PanelSizes {
+    pub sender: f32,
+    pub stats: f32,
+    pub detail: f32,
+    pub chart: f32,
+}
+
+impl Default for PanelSizes {
+    fn default() -> Self {
+        Self {
+            sender: 350.0,
+            stats: 250.0,
+            detail: 220.0,
+            chart: 250.0,
+        }
+    }
+}
+
+impl PanelSizes {
+    /// Sizes shrunk to fit a screen of `screen` points, e.g. smaller than the one they were
+    /// saved on, leaving room for the central panel.
+    #[must_use]
+    pub fn fit(self, screen: egui::Vec2) -> Self {
+        let sides = self.sender + self.stats;
+        let room = (screen.x - MIN_CENTRAL_WIDTH).max(0.0);
+        let scale = if sides > room { room / sides } else { 1.0 };
+        let chart_max = (screen.y / 2.0).max(*CHART_HEIGHT_RANGE.start());
+        Self {
+            sender: self.sender * scale,
+            stats: self.stats * scale,
+            detail: self.detail.min(screen.y / 3.0),
+            chart: self.chart.clamp(*CHART_HEIGHT_RANGE.start(), chart_max),
+        }
+    }
+}
+
+bitflags! {
+    /// Panels hidden from the View menu, the side ones collapsed to a strip.
+    #[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+    pub struct HiddenPanels: u8 {
+        const SENDER = 1 << 0;
+        const STATS = 1 << 1;
+        const DASHBOARD = 1 << 2;
+        const CHART = 1 << 3;
+    }
+}
+
+/// State of the GUI restored at startup.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppSettings {
+    /// Interface typed in the connection field, the command line takes precedence.
+    #[serde(deserialize_with = "lenient")]
+    pub can_name: String,
+    /// Bitrate typed in the connection field.
+    #[serde(deserialize_with = "lenient")]
+    pub bitrate: String,
+    #[serde(deserialize_with = "lenient")]
+    pub format: DataFormat,
+    #[serde(deserialize_with = "lenient")]
+    pub timestamp_mode: TimestampMode,
+    /// Filters and pinned filters as left, the last profile applied if `None`.
+    #[serde(deserialize_with = "lenient")]
+    pub filters: Option<FilterProfile>,
+    #[serde(deserialize_with = "lenient")]
+    pub sender: SenderSettings,
+    #[serde(deserialize_with = "lenient")]
+    pub panels: PanelSizes,
+    #[serde(deserialize_with = "lenient")]
+    pub hidden_panels: HiddenPanels,
+    /// Repaint continuously at this rate, if `None` only on new data and a few times a second.
+    #[serde(deserialize_with = "lenient")]
+    pub max_fps: Option<u32>,
+    #[serde(deserialize_with = "lenient")]
+    pub interpreters: InterpreterSettings,
+}
+
+impl AppSettings {
+    /// Settings of the previous run, the defaults if there are none or they can't be read.
+    pub fn load() -> Self {
+        config::load_json(CONFIG_FILE).unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        if let Err(e) = config::save_json(CONFIG_FILE, self) {
+            log::warn!("Can't save the settings: {e}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AppSettings, HiddenPanels, PanelSizes};
+    use crate::data_format::{DataFormat, Endian, ValueType};
+
+    #[test]
+    fn test_app_settings_from_other_versions() {
+        let settings = AppSettings {
+            can_name: "can1".to_owned(),
+            format: DataFormat::Value(ValueType::F32, Endian::Big),
+            hidden_panels: HiddenPanels::SENDER | HiddenPanels::CHART,
+            ..AppSettings::default()
+        };
+        let json = serde_json::to_string(&settings).unwrap();
+        assert_eq!(
+            serde_json::from_str::<AppSettings>(&json).unwrap(),
+            settings
+        );
+
+        // Unknown, missing and unreadable fields are defaulted alone.
+        let old = r#"{"can_name": "can1", "format": {"Hexa": 3}, "panels": {"stats": 300.0}, "theme": 1}"#;
+        let settings = serde_json::from_str::<AppSettings>(old).unwrap();
+        assert_eq!(settings.can_name, "can1");
+        assert_eq!(settings.format, DataFormat::default());
+        assert!((settings.panels.sender - PanelSizes::default().sender).abs() < f32::EPSILON);
+        assert!((settings.panels.stats - 300.0).abs() < f32::EPSILON);
+        assert_eq!(settings.hidden_panels, HiddenPanels::empty());
+
+        assert!(serde_json::from_str::<AppSettings>("\u{0}garbage").is_err());
+    }
+}
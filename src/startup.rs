@@ -0,0 +1,133 @@
+use crate::{
+    data_format::{DataFormat, Endian, ValueType},
+    filter_profile::{FilterProfile, RuleConfig},
+};
+use oze_canopen::canopen::RxMessageToStringFormat;
+
+/// Display and filter options of the command line, applied over the restored settings.
+#[derive(Debug, Clone, Default)]
+pub struct Startup {
+    pub format: Option<DataFormat>,
+    /// Replaces the restored filters and pinned filters.
+    pub filters: Option<FilterProfile>,
+    /// Hides the heartbeats on top of the filters.
+    pub hide_heartbeats: bool,
+}
+
+/// Data format named on the command line: `hex`, `bin`, `ascii`, a value type such as
+/// `u32`, or `u16list`, the typed ones in little endian or with a `be` suffix in big endian.
+///
+/// # Errors
+/// If the name is none of these.
+pub fn parse_format(raw: &str) -> Result<DataFormat, String> {
+    let raw = raw.trim().to_lowercase();
+    let bytes = match raw.as_str() {
+        "hex" => Some(RxMessageToStringFormat::Hex),
+        "bin" | "binary" => Some(RxMessageToStringFormat::Binary),
+        "ascii" => Some(RxMessageToStringFormat::Ascii),
+        "utf8" => return Err("utf8 isn't supported, use ascii".to_owned()),
+        _ => None,
+    };
+    if let Some(bytes) = bytes {
+        return Ok(DataFormat::Bytes(bytes));
+    }
+    let (typed, endian) = match raw.strip_suffix("be") {
+        Some(typed) => (typed, Endian::Big),
+        None => (raw.strip_suffix("le").unwrap_or(&raw), Endian::Little),
+    };
+    if typed == "u16list" {
+        return Ok(DataFormat::U16List(endian));
+    }
+    ValueType::ALL
+        .into_iter()
+        .find(|value_type| value_type.as_str() == typed)
+        .map(|value_type| DataFormat::Value(value_type, endian))
+        .ok_or_else(|| {
+            format!(
+                "unknown format {raw}, expected hex, bin, ascii, u16, i16, u32, i32, f32 or \
+                 u16list, e.g. u32be"
+            )
+        })
+}
+
+/// Rule matching a comma separated list of hexadecimal COB-IDs, e.g. `0x181,581`.
+///
+/// # Errors
+/// If the list is empty or a COB-ID isn't valid.
+pub fn parse_cob_filter(raw: &str) -> Result<RuleConfig, String> {
+    let cob_ids = raw
+        .split(',')
+        .map(str::trim)
+        .filter(|cob_id| !cob_id.is_empty())
+        .map(|cob_id| parse_cob_id(cob_id).map(|cob_id| format!("{cob_id:03X}")))
+        .collect::<Result<Vec<_>, _>>()?;
+    if cob_ids.is_empty() {
+        return Err("no COB-ID, expected a list such as 0x181,0x581".to_owned());
+    }
+    Ok(RuleConfig {
+        regex_cob: format!("^({})$", cob_ids.join("|")),
+        ..RuleConfig::default()
+    })
+}
+
+/// Hexadecimal COB-ID, with or without the `0x` prefix.
+fn parse_cob_id(raw: &str) -> Result<u16, String> {
+    let digits = raw
+        .strip_prefix("0x")
+        .or_else(|| raw.strip_prefix("0X"))
+        .unwrap_or(raw);
+    u16::from_str_radix(digits, 16)
+        .ok()
+        .filter(|cob_id| *cob_id <= 0x7FF)
+        .ok_or_else(|| format!("invalid COB-ID {raw}, expected 000 to 7FF"))
+}
+
+/// PDO carrying a statusword, as `COB-ID` or `COB-ID:byte`, e.g. `0x181:2`.
+///
+/// # Errors
+/// If the COB-ID or the byte offset isn't valid.
+pub fn parse_statusword_pdo(raw: &str) -> Result<(u16, usize), String> {
+    let (cob_id, offset) = raw.split_once(':').unwrap_or((raw, "0"));
+    let offset = offset
+        .trim()
+        .parse()
+        .ok()
+        .filter(|offset| *offset <= 6)
+        .ok_or_else(|| format!("invalid byte offset {offset}, expected 0 to 6"))?;
+    Ok((parse_cob_id(cob_id.trim())?, offset))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_cob_filter, parse_format, parse_statusword_pdo};
+    use crate::data_format::{DataFormat, Endian, ValueType};
+    use oze_canopen::canopen::RxMessageToStringFormat;
+
+    #[test]
+    fn test_startup_arguments() {
+        assert_eq!(
+            parse_format("bin"),
+            Ok(DataFormat::Bytes(RxMessageToStringFormat::Binary))
+        );
+        assert_eq!(
+            parse_format("U32BE"),
+            Ok(DataFormat::Value(ValueType::U32, Endian::Big))
+        );
+        assert_eq!(
+            parse_format("u16list"),
+            Ok(DataFormat::U16List(Endian::Little))
+        );
+        assert!(parse_format("octal").is_err());
+        assert!(parse_format("utf8").is_err());
+        assert_eq!(parse_statusword_pdo("0x181:2"), Ok((0x181, 2)));
+        assert_eq!(parse_statusword_pdo("281"), Ok((0x281, 0)));
+        assert!(parse_statusword_pdo("0x181:7").is_err());
+
+        assert_eq!(
+            parse_cob_filter("0x181, 581,80").unwrap().regex_cob,
+            "^(181|581|080)$"
+        );
+        assert!(parse_cob_filter("0x181,0x800").is_err());
+        assert!(parse_cob_filter(",").is_err());
+    }
+}
@@ -0,0 +1,88 @@
+use crate::{bus_stats::BusStatsSnapshot, nodes::NodeSnapshot};
+use chrono::{DateTime, Local, SecondsFormat};
+use serde::Serialize;
+
+/// Statistics written at the end of a test run, with the connection they were measured on.
+#[derive(Debug, Clone, Serialize)]
+pub struct StatsReport {
+    /// Local time of the snapshot, RFC 3339.
+    pub timestamp: String,
+    pub can_name: String,
+    /// Bitrate configured in the viewer.
+    pub bitrate: Option<u32>,
+    /// Bitrate reported by the interface.
+    pub interface_bitrate: Option<u32>,
+    pub stats: BusStatsSnapshot,
+    pub nodes: Vec<NodeSnapshot>,
+}
+
+impl StatsReport {
+    pub fn new(
+        time: DateTime<Local>,
+        can_name: String,
+        bitrate: Option<u32>,
+        interface_bitrate: Option<u32>,
+        stats: BusStatsSnapshot,
+        nodes: Vec<NodeSnapshot>,
+    ) -> Self {
+        Self {
+            timestamp: time.to_rfc3339_opts(SecondsFormat::Millis, false),
+            can_name,
+            bitrate,
+            interface_bitrate,
+            stats,
+            nodes,
+        }
+    }
+
+    pub fn file_name() -> String {
+        format!("bus-stats-{}.json", Local::now().format("%Y%m%d-%H%M%S"))
+    }
+
+    /// Pretty printed JSON of the report.
+    pub fn to_json(&self) -> String {
+        // Only strings, numbers and maps with string keys, serialization can't fail.
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StatsReport;
+    use crate::bus_stats::BusStats;
+    use chrono::Local;
+    use std::time::Duration;
+    use tokio::time::Instant;
+
+    #[test]
+    fn test_stats_report_json() {
+        let mut stats = BusStats::new();
+        let start = Instant::now();
+        stats.on_message(0x181, Some(1), 8, start);
+        stats.on_message(0x181, Some(1), 8, start + Duration::from_millis(10));
+        stats.on_message(0x701, Some(1), 1, start + Duration::from_millis(15));
+        let now = start + Duration::from_millis(20);
+        stats.calculate_cob_id_rates(now);
+
+        let report = StatsReport::new(
+            Local::now(),
+            "can0".to_owned(),
+            Some(250_000),
+            None,
+            stats.snapshot(now),
+            Vec::new(),
+        );
+        let json: serde_json::Value = serde_json::from_str(&report.to_json()).unwrap();
+        assert_eq!(json["can_name"], "can0");
+        assert_eq!(json["bitrate"], 250_000);
+        assert_eq!(json["stats"]["total_messages"], 3);
+        assert_eq!(json["stats"]["cob_ids"][0]["cob_id"], "0x181");
+        assert_eq!(json["stats"]["cob_ids"][0]["count"], 2);
+        let ago = json["stats"]["cob_ids"][1]["last_seen_ago_s"]
+            .as_f64()
+            .unwrap();
+        assert!((ago - 0.005).abs() < 1e-9);
+        assert_eq!(json["stats"]["errors"]["classes"]["Bus off"], 0);
+        assert!(json["stats"]["gaps"]["min_ms"].as_f64().unwrap() > 4.9);
+    }
+}
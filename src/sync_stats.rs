@@ -0,0 +1,240 @@
+use oze_canopen::canopen::NodeId;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    ops::RangeInclusive,
+};
+use tokio::time::Instant;
+
+/// COB-ID of the SYNC object.
+const SYNC_COB_ID: u16 = 0x080;
+
+/// Default number of SYNC intervals the statistics are computed over.
+pub const SYNC_WINDOW: usize = 100;
+
+/// Allowed number of SYNC intervals in the window.
+pub const SYNC_WINDOW_RANGE: RangeInclusive<usize> = 10..=10_000;
+
+/// An interval longer than this multiple of the learned period means SYNCs were missed.
+const MISSED_FACTOR: f64 = 1.5;
+
+/// Milliseconds from `earlier` to `later`.
+fn millis(earlier: Instant, later: Instant) -> f64 {
+    later.saturating_duration_since(earlier).as_secs_f64() * 1000.0
+}
+
+/// Mean, standard deviation and worst deviation from the mean of some intervals, in milliseconds.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Deviation {
+    pub mean: f64,
+    pub std_dev: f64,
+    /// Largest distance of an interval to the mean.
+    pub worst: f64,
+    pub samples: usize,
+}
+
+impl Deviation {
+    #[allow(clippy::cast_precision_loss)]
+    fn of(values: &VecDeque<f64>) -> Option<Self> {
+        if values.is_empty() {
+            return None;
+        }
+        let count = values.len() as f64;
+        let mean = values.iter().sum::<f64>() / count;
+        let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / count;
+        Some(Self {
+            mean,
+            std_dev: variance.sqrt(),
+            worst: values.iter().map(|v| (v - mean).abs()).fold(0.0, f64::max),
+            samples: values.len(),
+        })
+    }
+}
+
+/// SYNC timing in a statistics snapshot.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncSnapshot {
+    pub count: u64,
+    pub missed: u64,
+    pub period_ms: Option<Deviation>,
+    pub tpdo_latency_ms: BTreeMap<NodeId, Deviation>,
+}
+
+/// Timing of the SYNC frames and of the TPDOs answering them.
+#[derive(Debug, Clone)]
+pub struct SyncStats {
+    /// SYNC frames received.
+    count: u64,
+    last: Option<Instant>,
+    /// Last intervals between SYNCs, milliseconds, without the ones spanning missed SYNCs.
+    intervals: VecDeque<f64>,
+    window: usize,
+    /// SYNCs missing from the longer intervals.
+    missed: u64,
+    /// Nodes that already sent a TPDO since the last SYNC.
+    answered: Vec<NodeId>,
+    /// Last latencies from a SYNC to the first TPDO of each node, milliseconds.
+    latencies: BTreeMap<NodeId, VecDeque<f64>>,
+}
+
+impl Default for SyncStats {
+    fn default() -> Self {
+        Self {
+            count: 0,
+            last: None,
+            intervals: VecDeque::new(),
+            window: SYNC_WINDOW,
+            missed: 0,
+            answered: Vec::new(),
+            latencies: BTreeMap::new(),
+        }
+    }
+}
+
+impl SyncStats {
+    /// Takes every received frame, SYNCs and TPDOs are measured, the others ignored.
+    ///
+    /// The transmission type of the TPDOs is unknown, the first TPDO of a node after a SYNC
+    /// is taken as its synchronous answer.
+    pub fn on_frame(&mut self, cob_id: u16, node_id: Option<NodeId>, timestamp: Instant) {
+        if cob_id == SYNC_COB_ID {
+            self.on_sync(timestamp);
+            return;
+        }
+        let tpdo = matches!(cob_id & 0x780, 0x180 | 0x280 | 0x380 | 0x480);
+        let (Some(node_id), Some(sync), true) = (node_id, self.last, tpdo) else {
+            return;
+        };
+        if self.answered.contains(&node_id) {
+            return;
+        }
+        self.answered.push(node_id);
+        let latencies = self.latencies.entry(node_id).or_default();
+        latencies.push_back(millis(sync, timestamp));
+        while latencies.len() > self.window {
+            latencies.pop_front();
+        }
+    }
+
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        clippy::cast_precision_loss
+    )]
+    fn on_sync(&mut self, timestamp: Instant) {
+        self.count += 1;
+        self.answered.clear();
+        if let Some(last) = self.last {
+            let interval = millis(last, timestamp);
+            match self.period() {
+                Some(period) if interval > period * MISSED_FACTOR => {
+                    self.missed += ((interval / period).round() as u64)
+                        .saturating_sub(1)
+                        .max(1);
+                }
+                _ => {
+                    self.intervals.push_back(interval);
+                    while self.intervals.len() > self.window {
+                        self.intervals.pop_front();
+                    }
+                }
+            }
+        }
+        self.last = Some(timestamp);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn missed(&self) -> u64 {
+        self.missed
+    }
+
+    /// Learned SYNC period in milliseconds.
+    pub fn period(&self) -> Option<f64> {
+        self.deviation().map(|deviation| deviation.mean)
+    }
+
+    /// Period and jitter of the SYNCs over the window.
+    pub fn deviation(&self) -> Option<Deviation> {
+        Deviation::of(&self.intervals)
+    }
+
+    /// The next SYNC is overdue by more than half a period.
+    pub fn is_overdue(&self, now: Instant) -> bool {
+        match (self.last, self.period()) {
+            (Some(last), Some(period)) => millis(last, now) > period * MISSED_FACTOR,
+            _ => false,
+        }
+    }
+
+    /// Latency from a SYNC to the first following TPDO, per node.
+    pub fn latencies(&self) -> impl Iterator<Item = (NodeId, Deviation)> + '_ {
+        self.latencies
+            .iter()
+            .filter_map(|(node_id, latencies)| Some((*node_id, Deviation::of(latencies)?)))
+    }
+
+    pub fn snapshot(&self) -> SyncSnapshot {
+        SyncSnapshot {
+            count: self.count,
+            missed: self.missed,
+            period_ms: self.deviation(),
+            tpdo_latency_ms: self.latencies().collect(),
+        }
+    }
+
+    pub fn window(&self) -> usize {
+        self.window
+    }
+
+    pub fn set_window(&mut self, window: usize) {
+        self.window = window;
+        while self.intervals.len() > window {
+            self.intervals.pop_front();
+        }
+        for latencies in self.latencies.values_mut() {
+            while latencies.len() > window {
+                latencies.pop_front();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SyncStats;
+    use std::time::Duration;
+    use tokio::time::Instant;
+
+    #[test]
+    fn test_sync_jitter_and_latency() {
+        let mut sync = SyncStats::default();
+        let start = Instant::now();
+        let us = |us| start + Duration::from_micros(us);
+        // SYNC every 10 ms ± 0.5 ms, node 3 answers 1 ms later with two TPDOs.
+        for i in 0..10 {
+            let time = i * 10_000 + if i % 2 == 0 { 0 } else { 500 };
+            sync.on_frame(0x080, None, us(time));
+            sync.on_frame(0x183, Some(3), us(time + 1000));
+            sync.on_frame(0x283, Some(3), us(time + 2000));
+            sync.on_frame(0x703, Some(3), us(time + 500));
+        }
+        assert_eq!(sync.count(), 10);
+        let deviation = sync.deviation().unwrap();
+        assert_eq!(deviation.samples, 9);
+        assert!((deviation.mean - 10.0).abs() < 0.1, "{deviation:?}");
+        assert!((deviation.worst - 0.5).abs() < 0.1, "{deviation:?}");
+        let (node_id, latency) = sync.latencies().next().unwrap();
+        assert_eq!(node_id, 3);
+        assert!((latency.mean - 1.0).abs() < 1e-9, "{latency:?}");
+
+        // Two SYNCs missing after the one at 90.5 ms.
+        assert!(!sync.is_overdue(us(100_000)));
+        assert!(sync.is_overdue(us(110_000)));
+        sync.on_frame(0x080, None, us(120_500));
+        assert_eq!(sync.missed(), 2);
+        assert_eq!(sync.deviation().unwrap().samples, 9);
+    }
+}
@@ -1,6 +1,15 @@
+use crate::config;
 use catppuccin_egui::{Theme, FRAPPE};
-use egui::{style::Selection, Color32, FontFamily, FontId, TextStyle};
-use std::ops::Deref;
+use egui::{style::Selection, Color32, FontFamily, FontId, TextStyle, ThemePreference, Visuals};
+use serde::{Deserialize, Serialize};
+use std::sync::{PoisonError, RwLock};
+
+/// File of the theme settings in the config directory.
+const CONFIG_FILE: &str = "theme.bin";
+
+/// Allowed UI scales.
+const SCALE_RANGE: std::ops::RangeInclusive<f32> = 0.5..=3.0;
+
 pub const OZON_BLUE: Color32 = egui::Color32::from_rgb(0, 91, 255);
 pub const OZON_BLUE_ACTIVE: Color32 = egui::Color32::from_rgb(30, 144, 255);
 pub const OZON_PINK: Color32 = Color32::from_rgb(249, 17, 85);
@@ -15,34 +24,211 @@ pub const OZON_THEME: Theme = Theme {
     ..FRAPPE
 };
 
-pub fn theme(ctx: &egui::Context) {
+/// Colours of the status indicators, read by the widgets drawing them.
+static STATUS_COLORS: RwLock<StatusColors> = RwLock::new(StatusColors::STANDARD);
+
+/// Colour scheme of the whole window.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ThemeChoice {
+    /// Dark with the OZON blue and pink.
+    #[default]
+    Ozon,
+    Dark,
+    Light,
+    /// Dark or light after the system preference.
+    System,
+}
+
+impl ThemeChoice {
+    const ALL: [Self; 4] = [Self::Ozon, Self::Dark, Self::Light, Self::System];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Ozon => "OZON",
+            Self::Dark => "Dark",
+            Self::Light => "Light",
+            Self::System => "System",
+        }
+    }
+}
+
+/// Level shown by a status indicator: link state, bus load, node liveness…
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Ok,
+    Warning,
+    Error,
+}
+
+impl Status {
+    /// Colour of the level, as chosen in the theme settings.
+    pub fn color(self) -> Color32 {
+        let colors = *STATUS_COLORS.read().unwrap_or_else(PoisonError::into_inner);
+        let [r, g, b] = match self {
+            Self::Ok => colors.ok,
+            Self::Warning => colors.warning,
+            Self::Error => colors.error,
+        };
+        Color32::from_rgb(r, g, b)
+    }
+}
+
+/// Colours of the three status levels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StatusColors {
+    pub ok: [u8; 3],
+    pub warning: [u8; 3],
+    pub error: [u8; 3],
+}
+
+impl StatusColors {
+    /// Green, yellow and red.
+    pub const STANDARD: Self = Self {
+        ok: [0, 255, 0],
+        warning: [255, 255, 0],
+        error: [255, 0, 0],
+    };
+
+    /// Blue, orange and vermilion of the Okabe-Ito palette, told apart with any colour vision.
+    pub const COLOR_BLIND: Self = Self {
+        ok: [86, 180, 233],
+        warning: [230, 159, 0],
+        error: [213, 94, 0],
+    };
+}
+
+impl Default for StatusColors {
+    fn default() -> Self {
+        Self::STANDARD
+    }
+}
+
+/// Theme, scale and status colours, saved when changed.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ThemeSettings {
+    pub choice: ThemeChoice,
+    /// Zoom factor of the whole UI.
+    pub scale: f32,
+    pub status: StatusColors,
+}
+
+impl Default for ThemeSettings {
+    fn default() -> Self {
+        Self {
+            choice: ThemeChoice::default(),
+            scale: 1.0,
+            status: StatusColors::default(),
+        }
+    }
+}
+
+impl ThemeSettings {
+    /// Settings saved by a previous run.
+    pub fn load() -> Self {
+        config::load(CONFIG_FILE).unwrap_or_default()
+    }
+
+    /// Sets the visuals of both the dark and light modes, the mode, the scale and the status
+    /// colours.
+    pub fn apply(&self, ctx: &egui::Context) {
+        ctx.set_visuals_of(egui::Theme::Dark, Visuals::dark());
+        ctx.set_visuals_of(egui::Theme::Light, Visuals::light());
+        let preference = match self.choice {
+            ThemeChoice::Ozon => {
+                ctx.style_mut_of(egui::Theme::Dark, |style| {
+                    catppuccin_egui::set_style_theme(style, OZON_THEME);
+                    style.visuals.selection = Selection {
+                        bg_fill: OZON_BLUE,
+                        stroke: egui::Stroke {
+                            color: OZON_BLUE,
+                            width: 0.0,
+                        },
+                    };
+                });
+                ThemePreference::Dark
+            }
+            ThemeChoice::Dark => ThemePreference::Dark,
+            ThemeChoice::Light => ThemePreference::Light,
+            ThemeChoice::System => ThemePreference::System,
+        };
+        ctx.set_theme(preference);
+        ctx.set_zoom_factor(self.scale.clamp(*SCALE_RANGE.start(), *SCALE_RANGE.end()));
+        *STATUS_COLORS.write().unwrap_or_else(PoisonError::into_inner) = self.status;
+    }
+
+    /// Theme, scale and status colours, applied and saved as they are changed.
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        let before = *self;
+        // Follows the zoom of the keyboard shortcuts.
+        self.scale = ui.ctx().zoom_factor();
+        ui.label("Theme");
+        ui.horizontal(|ui| {
+            for choice in ThemeChoice::ALL {
+                ui.radio_value(&mut self.choice, choice, choice.name());
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("Scale:");
+            ui.add(
+                egui::Slider::new(&mut self.scale, SCALE_RANGE)
+                    .step_by(0.05)
+                    .fixed_decimals(2),
+            )
+            .on_hover_text("Size of the text and widgets, also changed with Ctrl + and Ctrl -");
+        });
+
+        ui.label("Status colours");
+        egui::Grid::new("status_colors").show(ui, |ui| {
+            for (color, name) in [
+                (&mut self.status.ok, "OK"),
+                (&mut self.status.warning, "Warning"),
+                (&mut self.status.error, "Error"),
+            ] {
+                egui::color_picker::color_edit_button_srgb(ui, color);
+                ui.label(name);
+                ui.end_row();
+            }
+        });
+        ui.horizontal(|ui| {
+            if ui.button("Standard").clicked() {
+                self.status = StatusColors::STANDARD;
+            }
+            if ui
+                .button("Colour-blind")
+                .on_hover_text("Blue, orange and vermilion instead of green, yellow and red")
+                .clicked()
+            {
+                self.status = StatusColors::COLOR_BLIND;
+            }
+        });
+
+        if *self != before {
+            self.apply(ui.ctx());
+            if let Err(e) = config::save(CONFIG_FILE, self) {
+                log::warn!("Can't save the theme: {e}");
+            }
+        }
+    }
+}
+
+pub fn theme(ctx: &egui::Context, settings: &ThemeSettings) {
     setup_custom_fonts(ctx);
     configure_text_styles(ctx);
-    catppuccin_egui::set_theme(ctx, OZON_THEME);
-
-    let mut style = ctx.style().deref().clone();
-    style.visuals.selection = Selection {
-        bg_fill: OZON_BLUE,
-        stroke: egui::Stroke {
-            color: OZON_BLUE,
-            width: 0.0,
-        },
-    };
-    ctx.set_style(style);
+    settings.apply(ctx);
 }
 
 fn configure_text_styles(ctx: &egui::Context) {
     use FontFamily::Monospace;
-    let mut style = (*ctx.style()).clone();
-    style.text_styles = [
-        (TextStyle::Heading, FontId::new(25.0, Monospace)),
-        (TextStyle::Body, FontId::new(16.0, Monospace)),
-        (TextStyle::Monospace, FontId::new(16.0, Monospace)),
-        (TextStyle::Button, FontId::new(16.0, Monospace)),
-        (TextStyle::Small, FontId::new(8.0, Monospace)),
-    ]
-    .into();
-    ctx.set_style(style);
+    ctx.all_styles_mut(|style| {
+        style.text_styles = [
+            (TextStyle::Heading, FontId::new(25.0, Monospace)),
+            (TextStyle::Body, FontId::new(16.0, Monospace)),
+            (TextStyle::Monospace, FontId::new(16.0, Monospace)),
+            (TextStyle::Button, FontId::new(16.0, Monospace)),
+            (TextStyle::Small, FontId::new(8.0, Monospace)),
+        ]
+        .into();
+    });
 }
 
 fn setup_custom_fonts(ctx: &egui::Context) {
@@ -73,3 +259,29 @@ fn setup_custom_fonts(ctx: &egui::Context) {
     // Tell egui to use these fonts:
     ctx.set_fonts(fonts);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Status, StatusColors, ThemeChoice, ThemeSettings};
+    use egui::Color32;
+
+    #[test]
+    fn test_apply_theme_settings() {
+        let ctx = egui::Context::default();
+        ThemeSettings {
+            choice: ThemeChoice::Light,
+            scale: 5.0,
+            status: StatusColors::COLOR_BLIND,
+        }
+        .apply(&ctx);
+        // The zoom applies from the next pass.
+        let _ = ctx.run(egui::RawInput::default(), |_| {});
+        assert!(!ctx.style().visuals.dark_mode);
+        assert!((ctx.zoom_factor() - 3.0).abs() < f32::EPSILON);
+        assert_eq!(Status::Error.color(), Color32::from_rgb(213, 94, 0));
+
+        ThemeSettings::default().apply(&ctx);
+        assert!(ctx.style().visuals.dark_mode);
+        assert_eq!(Status::Ok.color(), Color32::GREEN);
+    }
+}
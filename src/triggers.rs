@@ -0,0 +1,787 @@
+use crate::{
+    capture::{CaptureBuffer, CaptureFormat, CaptureWindow, Finished},
+    export::ExportJob,
+    message_cached::{MessageCached, RxMessageAdditional},
+    search::Query,
+    theme::{Status, OZON_PINK},
+    value_plot::Extraction,
+};
+use chrono::{DateTime, Local};
+use egui::DragValue;
+use std::{collections::VecDeque, time::Duration};
+use tokio::time::Instant;
+
+/// Maximum number of alerts kept, the oldest ones are dropped first.
+const MAX_ALERTS: usize = 1000;
+
+/// How long a trigger stays highlighted after it fired.
+const HIGHLIGHT: Duration = Duration::from_secs(3);
+
+/// What a trigger watches for.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition {
+    /// Extracted value goes above the threshold.
+    Above {
+        extraction: Extraction,
+        threshold: f64,
+    },
+    /// Extracted value goes below the threshold.
+    Below {
+        extraction: Extraction,
+        threshold: f64,
+    },
+    /// Byte at `offset`, masked, equals `value`.
+    Bytes { offset: usize, mask: u8, value: u8 },
+    /// No matching message for longer than the timeout.
+    Silence { timeout: Duration },
+    /// EMCY with an error from a node, any node if 0.
+    Emcy { node_id: u8 },
+    /// Error frame reported by the CAN controller.
+    ErrorFrame,
+    /// Message matching an expression of the search box, `None` while it is empty.
+    Expression { text: String, query: Option<Query> },
+}
+
+impl Condition {
+    const NAMES: [&'static str; 7] = [
+        "value above",
+        "value below",
+        "bytes",
+        "silence",
+        "EMCY",
+        "error frame",
+        "expression",
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Above { .. } => Self::NAMES[0],
+            Self::Below { .. } => Self::NAMES[1],
+            Self::Bytes { .. } => Self::NAMES[2],
+            Self::Silence { .. } => Self::NAMES[3],
+            Self::Emcy { .. } => Self::NAMES[4],
+            Self::ErrorFrame => Self::NAMES[5],
+            Self::Expression { .. } => Self::NAMES[6],
+        }
+    }
+
+    fn from_name(name: &str) -> Self {
+        match name {
+            "value above" => Self::Above {
+                extraction: Extraction::default(),
+                threshold: 0.0,
+            },
+            "value below" => Self::Below {
+                extraction: Extraction::default(),
+                threshold: 0.0,
+            },
+            "bytes" => Self::Bytes {
+                offset: 0,
+                mask: 0xFF,
+                value: 0,
+            },
+            "silence" => Self::Silence {
+                timeout: Duration::from_millis(500),
+            },
+            "error frame" => Self::ErrorFrame,
+            "expression" => Self::expression(""),
+            _ => Self::Emcy { node_id: 0 },
+        }
+    }
+
+    pub fn expression(text: &str) -> Self {
+        Self::Expression {
+            text: text.to_owned(),
+            query: Query::parse(text),
+        }
+    }
+
+    /// Whether a matching message meets the condition, `None` if it doesn't tell, e.g. a frame
+    /// too short for the value.
+    fn holds(&self, msg: &MessageCached) -> Option<bool> {
+        match self {
+            Self::Above {
+                extraction,
+                threshold,
+            } => Some(extraction.extract(msg)? > *threshold),
+            Self::Below {
+                extraction,
+                threshold,
+            } => Some(extraction.extract(msg)? < *threshold),
+            Self::Bytes {
+                offset,
+                mask,
+                value,
+            } => {
+                let frame = &msg.msg.msg;
+                Some(*offset < frame.dlc.min(8) && frame.data[*offset] & mask == value & mask)
+            }
+            Self::Silence { .. } | Self::ErrorFrame => None,
+            Self::Emcy { node_id } => match &msg.additional {
+                RxMessageAdditional::Emcy(e) => {
+                    Some(!e.is_reset() && (*node_id == 0 || e.node_id == *node_id))
+                }
+                _ => Some(false),
+            },
+            Self::Expression { query, .. } => Some(query.as_ref().is_some_and(|q| q.matches(msg))),
+        }
+    }
+
+    /// The COB-ID doesn't apply, the EMCY condition has its own node and an expression can
+    /// check the COB-ID itself.
+    fn uses_cob_id(&self) -> bool {
+        !matches!(
+            self,
+            Self::Emcy { .. } | Self::ErrorFrame | Self::Expression { .. }
+        )
+    }
+}
+
+/// What to do when a trigger fires, besides raising an alert.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Actions {
+    /// Insert a marker in the message list.
+    pub marker: bool,
+    /// Freeze the message list.
+    pub pause: bool,
+    /// Save the frames received around the trigger to a file.
+    pub capture: Option<CaptureWindow>,
+}
+
+/// Condition checked on every received message and what to do when it fires.
+#[derive(Debug, Clone)]
+pub struct Trigger {
+    pub name: String,
+    /// COB-ID of the messages checked, every one if `None`.
+    pub cob_id: Option<u16>,
+    pub condition: Condition,
+    pub actions: Actions,
+    pub enabled: bool,
+    /// The condition held on the last message, it fires again once it stopped holding.
+    active: bool,
+    /// Last matching message, or when the trigger was created, for the silence condition.
+    last_seen: Instant,
+    fired: Option<Instant>,
+    count: u64,
+    /// End of the capture started when the trigger fired, it doesn't fire again before.
+    capture_until: Option<Instant>,
+}
+
+impl Trigger {
+    fn new(number: usize) -> Self {
+        Self {
+            name: format!("Trigger {number}"),
+            cob_id: None,
+            condition: Condition::Emcy { node_id: 0 },
+            actions: Actions {
+                marker: true,
+                pause: false,
+                capture: None,
+            },
+            enabled: true,
+            active: false,
+            last_seen: Instant::now(),
+            fired: None,
+            count: 0,
+            capture_until: None,
+        }
+    }
+
+    fn applies(&self, msg: &MessageCached) -> bool {
+        self.condition != Condition::ErrorFrame
+            && (!self.condition.uses_cob_id()
+                || self
+                    .cob_id
+                    .map_or(true, |cob_id| cob_id == msg.msg.msg.cob_id))
+    }
+
+    /// Whether the trigger can fire at `now`, its last capture being over.
+    fn is_armed(&self, now: Instant) -> bool {
+        self.capture_until.map_or(true, |until| now >= until)
+    }
+
+    /// Returns true when the condition starts holding.
+    fn on_message(&mut self, msg: &MessageCached) -> bool {
+        if !self.enabled || !self.applies(msg) {
+            return false;
+        }
+        self.last_seen = msg.get_timestamp();
+        let Some(holds) = self.condition.holds(msg) else {
+            // A message ends the silence.
+            self.active = false;
+            return false;
+        };
+        let fired = holds && !self.active;
+        self.active = holds;
+        fired
+    }
+
+    /// Returns true when error frames arrive again, `new` if some arrived since the last call.
+    fn on_errors(&mut self, new: bool) -> bool {
+        if !self.enabled || self.condition != Condition::ErrorFrame {
+            return false;
+        }
+        let fired = new && !self.active;
+        self.active = new;
+        fired
+    }
+
+    /// Returns true when the silence exceeds the timeout.
+    fn on_tick(&mut self, now: Instant) -> bool {
+        let Condition::Silence { timeout } = self.condition else {
+            return false;
+        };
+        if !self.enabled || self.active || now.saturating_duration_since(self.last_seen) <= timeout
+        {
+            return false;
+        }
+        self.active = true;
+        true
+    }
+
+    fn description(&self) -> String {
+        let cob_id = match self.cob_id {
+            Some(cob_id) if self.condition.uses_cob_id() => format!("{cob_id:03X} "),
+            _ => String::new(),
+        };
+        let condition = match &self.condition {
+            Condition::Above { threshold, .. } => format!("value > {threshold}"),
+            Condition::Below { threshold, .. } => format!("value < {threshold}"),
+            Condition::Bytes {
+                offset,
+                mask,
+                value,
+            } => format!("byte {offset} & {mask:02X} = {value:02X}"),
+            Condition::Silence { timeout } => format!("silent for {} ms", timeout.as_millis()),
+            Condition::Emcy { node_id: 0 } => "EMCY".to_owned(),
+            Condition::Emcy { node_id } => format!("EMCY from node {node_id}"),
+            Condition::ErrorFrame => "error frame".to_owned(),
+            Condition::Expression { text, .. } => format!("matches \"{text}\""),
+        };
+        format!("{}: {cob_id}{condition}", self.name)
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.enabled, "");
+            ui.add(egui::TextEdit::singleline(&mut self.name).desired_width(90.0));
+            let mut name = self.condition.name();
+            egui::ComboBox::from_id_salt(("trigger_condition", self as *const Self as usize))
+                .selected_text(name)
+                .show_ui(ui, |ui| {
+                    for other in Condition::NAMES {
+                        ui.selectable_value(&mut name, other, other);
+                    }
+                });
+            if name != self.condition.name() {
+                self.condition = Condition::from_name(name);
+                self.active = false;
+            }
+        });
+        ui.horizontal(|ui| {
+            if self.condition.uses_cob_id() {
+                let mut any = self.cob_id.is_none();
+                if ui.checkbox(&mut any, "any COB-ID").changed() {
+                    self.cob_id = (!any).then_some(0x181);
+                }
+                if let Some(cob_id) = &mut self.cob_id {
+                    ui.add(
+                        DragValue::new(cob_id)
+                            .range(0..=0x7FF)
+                            .hexadecimal(3, false, true),
+                    );
+                }
+            }
+            match &mut self.condition {
+                Condition::Above { threshold, .. } | Condition::Below { threshold, .. } => {
+                    ui.label("threshold");
+                    ui.add(DragValue::new(threshold).speed(0.1));
+                }
+                Condition::Bytes {
+                    offset,
+                    mask,
+                    value,
+                } => {
+                    ui.add(DragValue::new(offset).range(0..=7).prefix("byte "));
+                    ui.add(
+                        DragValue::new(mask)
+                            .hexadecimal(2, false, true)
+                            .prefix("& "),
+                    );
+                    ui.add(
+                        DragValue::new(value)
+                            .hexadecimal(2, false, true)
+                            .prefix("= "),
+                    );
+                }
+                Condition::Silence { timeout } => {
+                    let mut millis = u64::try_from(timeout.as_millis()).unwrap_or(u64::MAX);
+                    if ui
+                        .add(DragValue::new(&mut millis).range(1..=600_000).suffix(" ms"))
+                        .changed()
+                    {
+                        *timeout = Duration::from_millis(millis);
+                    }
+                }
+                Condition::Emcy { node_id } => {
+                    ui.label("node");
+                    ui.add(DragValue::new(node_id).range(0..=127))
+                        .on_hover_text("0 for any node");
+                }
+                Condition::ErrorFrame => {}
+                Condition::Expression { text, query } => {
+                    if ui
+                        .add(egui::TextEdit::singleline(text).hint_text("id:181, 2B ?? 60, text"))
+                        .on_hover_text("Same syntax as the search box: a COB-ID, bytes with ?? wildcards or a text of the decoded info")
+                        .changed()
+                    {
+                        *query = Query::parse(text);
+                    }
+                }
+            }
+        });
+        if let Condition::Above { extraction, .. } | Condition::Below { extraction, .. } =
+            &mut self.condition
+        {
+            extraction.ui(ui);
+        }
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.actions.marker, "marker")
+                .on_hover_text("Insert a marker in the message list when the trigger fires");
+            ui.checkbox(&mut self.actions.pause, "freeze")
+                .on_hover_text("Freeze the message list when the trigger fires");
+            let mut capture = self.actions.capture.is_some();
+            if ui
+                .checkbox(&mut capture, "capture")
+                .on_hover_text("Save the frames received around the trigger to a file")
+                .changed()
+            {
+                self.actions.capture = capture.then(CaptureWindow::default);
+            }
+        });
+        if let Some(window) = &mut self.actions.capture {
+            ui.horizontal(|ui| window.ui(ui));
+        }
+    }
+}
+
+/// Trigger that fired, with what to do about it.
+#[derive(Debug, Clone)]
+pub struct Alert {
+    pub time: DateTime<Local>,
+    /// Index of the message that fired the trigger, `None` for a silence.
+    pub index: Option<u64>,
+    pub text: String,
+    pub actions: Actions,
+}
+
+/// Triggers evaluated on the received messages and the alerts they raised.
+#[derive(Debug, Default)]
+pub struct Triggers {
+    list: Vec<Trigger>,
+    /// Alerts, oldest first.
+    alerts: VecDeque<Alert>,
+    /// Alerts raised since the panel was last open.
+    unseen: usize,
+    /// Last frames received and the captures started by the triggers.
+    captures: CaptureBuffer,
+    /// File the captures are saved to.
+    pub capture_format: CaptureFormat,
+    /// Error frames counted by the driver at the last check.
+    error_frames: Option<u64>,
+    /// Captures being written: trigger, file and the job writing it.
+    saves: Vec<(String, String, ExportJob)>,
+}
+
+impl Triggers {
+    /// Checks a received message, returns the alerts it raised.
+    pub fn on_message(&mut self, msg: &MessageCached) -> Vec<Alert> {
+        let keep = self
+            .list
+            .iter()
+            .filter(|trigger| trigger.enabled)
+            .filter_map(|trigger| Some(trigger.actions.capture?.pre))
+            .max()
+            .unwrap_or_default();
+        self.captures.set_keep(keep);
+        self.captures.on_message(msg);
+        let now = msg.get_timestamp();
+        let mut alerts = Vec::new();
+        for trigger in &mut self.list {
+            if trigger.on_message(msg) && trigger.is_armed(now) {
+                alerts.push(Self::fire(
+                    trigger,
+                    Some(msg.index),
+                    now,
+                    &mut self.captures,
+                ));
+            }
+        }
+        self.record(&alerts);
+        alerts
+    }
+
+    /// Checks the error frames counted by the driver, returns the alerts raised.
+    pub fn on_errors(&mut self, frames: u64, now: Instant) -> Vec<Alert> {
+        let new = self.error_frames.is_some_and(|last| frames > last);
+        self.error_frames = Some(frames);
+        let mut alerts = Vec::new();
+        for trigger in &mut self.list {
+            if trigger.on_errors(new) && trigger.is_armed(now) {
+                alerts.push(Self::fire(trigger, None, now, &mut self.captures));
+            }
+        }
+        self.record(&alerts);
+        alerts
+    }
+
+    /// Checks the silence timeouts, returns the alerts raised.
+    pub fn on_tick(&mut self, now: Instant) -> Vec<Alert> {
+        let mut alerts = Vec::new();
+        for trigger in &mut self.list {
+            if trigger.on_tick(now) && trigger.is_armed(now) {
+                alerts.push(Self::fire(trigger, None, now, &mut self.captures));
+            }
+        }
+        self.record(&alerts);
+        alerts
+    }
+
+    /// Captures whose post-trigger window is over, to be saved.
+    pub fn take_captures(&mut self, now: Instant) -> Vec<Finished> {
+        self.captures.take_finished(now)
+    }
+
+    /// Writes the `rows` frames of the capture of a trigger to `path` on a separate thread, with
+    /// a suffix if the file exists, the alert telling where is returned by [`Self::take_saved`].
+    pub fn save<T: AsRef<[u8]>>(
+        &mut self,
+        trigger: String,
+        path: String,
+        rows: usize,
+        contents: impl FnOnce() -> T + Send + 'static,
+    ) {
+        let mut job = ExportJob::new("frames");
+        job.start_new(path.clone(), rows, contents);
+        self.saves.push((trigger, path, job));
+    }
+
+    /// Alerts of the captures written since the last call, added to the list.
+    pub fn take_saved(&mut self) -> Vec<Alert> {
+        let mut alerts = Vec::new();
+        self.saves.retain_mut(|(trigger, path, job)| {
+            let Some(result) = job.finished() else {
+                return true;
+            };
+            let text = match result {
+                Ok(done) => format!("{trigger}: {} frames saved to {}", done.rows, done.path),
+                Err(e) => format!("{trigger}: capture not saved to {path}: {e}"),
+            };
+            alerts.push(Alert {
+                time: Local::now(),
+                index: None,
+                text,
+                actions: Actions::default(),
+            });
+            false
+        });
+        self.record(&alerts);
+        alerts
+    }
+
+    /// Starts the capture of the trigger, a trigger which isn't re-armed is disabled.
+    fn fire(
+        trigger: &mut Trigger,
+        index: Option<u64>,
+        now: Instant,
+        captures: &mut CaptureBuffer,
+    ) -> Alert {
+        trigger.fired = Some(Instant::now());
+        trigger.count += 1;
+        if let Some(window) = trigger.actions.capture {
+            captures.start(&trigger.name, window, now);
+            trigger.capture_until = Some(now + window.post);
+            trigger.enabled &= window.rearm;
+        }
+        Alert {
+            time: Local::now(),
+            index,
+            text: trigger.description(),
+            actions: trigger.actions,
+        }
+    }
+
+    /// Adds alerts to the list, also the ones raised outside of the triggers.
+    pub fn record(&mut self, alerts: &[Alert]) {
+        self.unseen += alerts.len();
+        self.alerts.extend(alerts.iter().cloned());
+        while self.alerts.len() > MAX_ALERTS {
+            self.alerts.pop_front();
+        }
+    }
+
+    /// Header of the panel, red with the number of alerts raised while it was closed.
+    pub fn title(&self) -> egui::RichText {
+        match self.unseen {
+            0 => egui::RichText::new("🔔 Triggers"),
+            n => egui::RichText::new(format!("🔔 Triggers ({n} new)")).color(Status::Error.color()),
+        }
+    }
+
+    pub fn update(&mut self, ui: &mut egui::Ui) {
+        self.unseen = 0;
+        let now = Instant::now();
+        let mut remove = None;
+        for (i, trigger) in self.list.iter_mut().enumerate() {
+            let highlight = trigger
+                .fired
+                .is_some_and(|fired| now.saturating_duration_since(fired) < HIGHLIGHT);
+            let frame = egui::Frame::group(ui.style());
+            let frame = if highlight {
+                frame.stroke(egui::Stroke::new(2.0, OZON_PINK))
+            } else {
+                frame
+            };
+            frame.show(ui, |ui| {
+                trigger.ui(ui);
+                ui.horizontal(|ui| {
+                    ui.label(format!("fired {} times", trigger.count));
+                    if !trigger.is_armed(now) {
+                        ui.colored_label(OZON_PINK, "⏺ capturing");
+                    }
+                    if ui
+                        .button("❌")
+                        .on_hover_text("Remove the trigger")
+                        .clicked()
+                    {
+                        remove = Some(i);
+                    }
+                });
+            });
+        }
+        if let Some(i) = remove {
+            self.list.remove(i);
+        }
+        ui.horizontal(|ui| {
+            if ui.button("➕ Trigger").clicked() {
+                self.list.push(Trigger::new(self.list.len() + 1));
+            }
+            if ui.button("Clear alerts").clicked() {
+                self.alerts.clear();
+            }
+            egui::ComboBox::from_id_salt("capture_format")
+                .selected_text(format!("captures as {}", self.capture_format.as_str()))
+                .show_ui(ui, |ui| {
+                    for format in CaptureFormat::ALL {
+                        ui.selectable_value(&mut self.capture_format, format, format.as_str());
+                    }
+                })
+                .response
+                .on_hover_text("File the captures of the triggers are saved to, named after the trigger and the time");
+        });
+
+        if self.alerts.is_empty() {
+            ui.label("No alerts");
+            return;
+        }
+        egui::Grid::new("alerts_grid").striped(true).show(ui, |ui| {
+            ui.label("Time");
+            ui.label("Index");
+            ui.label("Alert");
+            ui.end_row();
+            for alert in self.alerts.iter().rev() {
+                ui.label(alert.time.format("%H:%M:%S%.3f").to_string());
+                ui.label(
+                    alert
+                        .index
+                        .map(|index| index.to_string())
+                        .unwrap_or_default(),
+                );
+                ui.label(&alert.text);
+                ui.end_row();
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Actions, Condition, Trigger, Triggers};
+    use crate::{capture::CaptureWindow, message_cached::MessageCached, value_plot::Extraction};
+    use oze_canopen::canopen::RxMessage;
+    use std::time::Duration;
+    use tokio::time::Instant;
+
+    fn message(index: u64, cob_id: u16, data: [u8; 8], time: Instant) -> MessageCached {
+        MessageCached::new(
+            index,
+            RxMessage {
+                timestamp: time,
+                cob_id,
+                data,
+                dlc: 8,
+            },
+        )
+    }
+
+    #[test]
+    fn test_triggers() {
+        let start = Instant::now();
+        let mut triggers = Triggers::default();
+        triggers.list.push(Trigger {
+            cob_id: Some(0x281),
+            condition: Condition::Above {
+                extraction: Extraction::default(),
+                threshold: 100.0,
+            },
+            ..Trigger::new(1)
+        });
+        triggers.list.push(Trigger {
+            condition: Condition::Emcy { node_id: 5 },
+            ..Trigger::new(2)
+        });
+        triggers.list.push(Trigger {
+            cob_id: Some(0x705),
+            condition: Condition::Silence {
+                timeout: Duration::from_millis(100),
+            },
+            ..Trigger::new(3)
+        });
+
+        let value = |index, cob_id, value: u16| {
+            let [low, high] = value.to_le_bytes();
+            message(index, cob_id, [low, high, 0, 0, 0, 0, 0, 0], start)
+        };
+        assert!(triggers.on_message(&value(0, 0x281, 50)).is_empty());
+        // Fires when crossing, once until the value goes back.
+        let alerts = triggers.on_message(&value(1, 0x281, 150));
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].index, Some(1));
+        assert!(alerts[0].actions.marker);
+        assert!(triggers.on_message(&value(2, 0x281, 200)).is_empty());
+        assert!(triggers.on_message(&value(3, 0x282, 500)).is_empty());
+        assert!(triggers.on_message(&value(4, 0x281, 20)).is_empty());
+        assert_eq!(triggers.on_message(&value(5, 0x281, 101)).len(), 1);
+
+        // EMCY 0x8130 of node 5, not node 6 nor a reset.
+        let emcy = |cob_id, code: u16| {
+            let [low, high] = code.to_le_bytes();
+            message(6, cob_id, [low, high, 0x11, 0, 0, 0, 0, 0], start)
+        };
+        assert!(triggers.on_message(&emcy(0x086, 0x8130)).is_empty());
+        assert!(triggers.on_message(&emcy(0x085, 0)).is_empty());
+        assert_eq!(triggers.on_message(&emcy(0x085, 0x8130)).len(), 1);
+
+        // Silent once, until a heartbeat arrives.
+        let heartbeat = message(7, 0x705, [5, 0, 0, 0, 0, 0, 0, 0], start);
+        assert!(triggers.on_message(&heartbeat).is_empty());
+        assert!(triggers
+            .on_tick(start + Duration::from_millis(50))
+            .is_empty());
+        let alerts = triggers.on_tick(start + Duration::from_millis(150));
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].index, None);
+        assert!(triggers
+            .on_tick(start + Duration::from_millis(300))
+            .is_empty());
+        triggers.on_message(&heartbeat);
+        assert_eq!(
+            triggers.on_tick(start + Duration::from_millis(150)).len(),
+            1
+        );
+        assert_eq!(triggers.alerts.len(), 5);
+    }
+
+    #[test]
+    fn test_capture_triggers() {
+        let start = Instant::now();
+        // A capturing trigger doesn't fire again before its capture is over.
+        let mut triggers = Triggers::default();
+        triggers.list.push(Trigger {
+            condition: Condition::expression("id:181"),
+            actions: Actions {
+                capture: Some(CaptureWindow {
+                    pre: Duration::from_secs(1),
+                    post: Duration::from_secs(2),
+                    rearm: true,
+                }),
+                ..Actions::default()
+            },
+            ..Trigger::new(1)
+        });
+        triggers.list.push(Trigger {
+            condition: Condition::ErrorFrame,
+            ..Trigger::new(2)
+        });
+        let pdo = |index, secs, first| {
+            message(
+                index,
+                0x181,
+                [first, 0, 0, 0, 0, 0, 0, 0],
+                start + Duration::from_secs(secs),
+            )
+        };
+        assert!(triggers
+            .on_message(&message(0, 0x282, [0; 8], start))
+            .is_empty());
+        assert_eq!(triggers.on_message(&pdo(1, 1, 0)).len(), 1);
+        assert!(triggers
+            .on_message(&message(2, 0x282, [0; 8], start))
+            .is_empty());
+        assert!(triggers.on_message(&pdo(3, 2, 0)).is_empty());
+        assert!(triggers
+            .take_captures(start + Duration::from_secs(2))
+            .is_empty());
+        let captures = triggers.take_captures(start + Duration::from_secs(3));
+        assert_eq!(captures.len(), 1);
+        assert_eq!(captures[0].frames.len(), 4);
+        triggers.on_message(&message(4, 0x282, [0; 8], start + Duration::from_secs(4)));
+        assert_eq!(triggers.on_message(&pdo(5, 4, 0)).len(), 1);
+
+        // Error frames counted since the previous check.
+        assert!(triggers.on_errors(3, start).is_empty());
+        assert_eq!(triggers.on_errors(4, start).len(), 1);
+        assert!(triggers.on_errors(5, start).is_empty());
+        assert!(triggers.on_errors(5, start).is_empty());
+        assert_eq!(triggers.on_errors(6, start).len(), 1);
+    }
+    #[test]
+    fn test_capture_save() {
+        let mut triggers = Triggers::default();
+        let path = std::env::temp_dir().join(format!("capture-{}.log", std::process::id()));
+        let path = path.to_str().unwrap();
+        triggers.save("Trigger 1".to_owned(), path.to_owned(), 4, || "181#00\n");
+        // Written in the background, the alert tells where once done.
+        let alerts = loop {
+            let alerts = triggers.take_saved();
+            if !alerts.is_empty() {
+                break alerts;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        };
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(
+            alerts[0].text,
+            format!("Trigger 1: 4 frames saved to {path}")
+        );
+        assert_eq!(std::fs::read_to_string(path).unwrap(), "181#00\n");
+        // A second capture in the same second doesn't overwrite the first.
+        triggers.save("Trigger 1".to_owned(), path.to_owned(), 1, || "182#00\n");
+        let alerts = loop {
+            let alerts = triggers.take_saved();
+            if !alerts.is_empty() {
+                break alerts;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        };
+        let second = path.replace(".log", "-1.log");
+        assert_eq!(
+            alerts[0].text,
+            format!("Trigger 1: 1 frames saved to {second}")
+        );
+        assert_eq!(std::fs::read_to_string(path).unwrap(), "181#00\n");
+        assert_eq!(std::fs::read_to_string(&second).unwrap(), "182#00\n");
+        std::fs::remove_file(path).unwrap();
+        std::fs::remove_file(second).unwrap();
+    }
+}
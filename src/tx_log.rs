@@ -0,0 +1,106 @@
+use oze_canopen::{canopen::RxMessage, transmitter::TxPacket};
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex, PoisonError},
+    time::Duration,
+};
+use tokio::{sync::mpsc, time::Instant};
+
+/// A frame sent longer ago than this never reached the bus, e.g. for lack of an ACK.
+const ECHO_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Frames sent and not seen on the bus yet are forgotten past this number.
+const MAX_PENDING: usize = 1024;
+
+/// Capacity of the channel between the senders and the transmitter.
+const CHANNEL_CAPACITY: usize = 64;
+
+/// Frames sent by the viewer, to tell their copies on the receive socket from the frames of the
+/// other nodes. The kernel loops the frames of the transmit socket back to the receive socket
+/// once they are on the bus.
+#[derive(Debug, Clone, Default)]
+pub struct TxLog {
+    pending: Arc<Mutex<VecDeque<(Instant, TxPacket)>>>,
+}
+
+impl TxLog {
+    /// Returns a sender to use instead of `tx`, which records every packet before passing it on.
+    pub fn intercept(&self, tx: mpsc::Sender<TxPacket>) -> mpsc::Sender<TxPacket> {
+        let (sender, mut receiver) = mpsc::channel::<TxPacket>(CHANNEL_CAPACITY);
+        let log = self.clone();
+        tokio::spawn(async move {
+            while let Some(packet) = receiver.recv().await {
+                log.record(Instant::now(), &packet);
+                if tx.send(packet).await.is_err() {
+                    break;
+                }
+            }
+        });
+        sender
+    }
+
+    fn record(&self, time: Instant, packet: &TxPacket) {
+        let mut pending = self.pending.lock().unwrap_or_else(PoisonError::into_inner);
+        pending.push_back((time, packet.clone()));
+        while pending.len() > MAX_PENDING {
+            pending.pop_front();
+        }
+    }
+
+    /// True if `msg` is the copy of a frame sent less than a second before, which is then
+    /// forgotten so that every frame sent is matched once.
+    pub fn take_echo(&self, msg: &RxMessage) -> bool {
+        let mut pending = self.pending.lock().unwrap_or_else(PoisonError::into_inner);
+        while pending
+            .front()
+            .is_some_and(|(time, _)| msg.timestamp.saturating_duration_since(*time) > ECHO_TIMEOUT)
+        {
+            pending.pop_front();
+        }
+        let data = &msg.data[..msg.dlc.min(8)];
+        let position = pending
+            .iter()
+            .position(|(_, packet)| packet.cob_id == msg.cob_id && packet.data == data);
+        position.and_then(|i| pending.remove(i)).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TxLog;
+    use oze_canopen::{canopen::RxMessage, transmitter::TxPacket};
+    use std::time::Duration;
+    use tokio::time::Instant;
+
+    #[test]
+    fn test_take_echo() {
+        let start = Instant::now();
+        let log = TxLog::default();
+        let packet = |cob_id, data: &[u8]| TxPacket {
+            cob_id,
+            data: data.to_vec(),
+        };
+        let frame = |cob_id, data: [u8; 8], dlc, millis| RxMessage {
+            timestamp: start + Duration::from_millis(millis),
+            cob_id,
+            data,
+            dlc,
+        };
+        log.record(start, &packet(0x605, &[0x40, 0x18, 0x10, 0x01]));
+        log.record(start, &packet(0x201, &[0x0F, 0x00]));
+
+        // Same COB-ID from another node, other data.
+        assert!(!log.take_echo(&frame(0x201, [0x06, 0, 0, 0, 0, 0, 0, 0], 2, 1)));
+        assert!(log.take_echo(&frame(0x201, [0x0F, 0, 0, 0, 0, 0, 0, 0], 2, 1)));
+        // Matched once.
+        assert!(!log.take_echo(&frame(0x201, [0x0F, 0, 0, 0, 0, 0, 0, 0], 2, 2)));
+        // Too late, the frame never made it to the bus.
+        let sdo = frame(0x605, [0x40, 0x18, 0x10, 0x01, 0, 0, 0, 0], 4, 1500);
+        assert!(!log.take_echo(&sdo));
+        log.record(
+            start + Duration::from_millis(1400),
+            &packet(0x605, &sdo.data[..4]),
+        );
+        assert!(log.take_echo(&sdo));
+    }
+}
@@ -0,0 +1,229 @@
+use crate::{data_format::Endian, message_cached::MessageCached, theme::OZON_PINK};
+use egui::DragValue;
+use egui_plot::{Line, Plot, PlotBounds, PlotPoints};
+use std::collections::VecDeque;
+use tokio::time::Instant;
+
+/// Default number of points kept per plot.
+pub const PLOT_DEPTH: usize = 10_000;
+
+/// Integer read from the payload of a frame, then scaled.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Extraction {
+    /// First byte of the value.
+    pub offset: usize,
+    /// Size of the value in bytes, 1 to 8.
+    pub width: usize,
+    pub signed: bool,
+    pub endian: Endian,
+    pub scale: f64,
+}
+
+impl Default for Extraction {
+    fn default() -> Self {
+        Self {
+            offset: 0,
+            width: 2,
+            signed: false,
+            endian: Endian::Little,
+            scale: 1.0,
+        }
+    }
+}
+
+impl Extraction {
+    /// Scaled value of the frame, `None` if the frame is too short.
+    #[allow(clippy::cast_precision_loss, clippy::cast_possible_wrap)]
+    pub fn extract(&self, msg: &MessageCached) -> Option<f64> {
+        let frame = &msg.msg.msg;
+        let bytes = frame
+            .data
+            .get(self.offset..self.offset + self.width)
+            .filter(|_| self.offset + self.width <= frame.dlc.min(8))?;
+        let mut le = [0; 8];
+        le[..self.width].copy_from_slice(bytes);
+        if self.endian == Endian::Big {
+            le[..self.width].reverse();
+        }
+        let raw = u64::from_le_bytes(le);
+        let value = if self.signed && self.width < 8 {
+            let shift = 64 - 8 * self.width;
+            (((raw << shift) as i64) >> shift) as f64
+        } else if self.signed {
+            raw as i64 as f64
+        } else {
+            raw as f64
+        };
+        Some(value * self.scale)
+    }
+
+    /// Editor of the fields, returns true if one changed.
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> bool {
+        let before = *self;
+        ui.horizontal(|ui| {
+            ui.label("offset");
+            ui.add(DragValue::new(&mut self.offset).range(0..=7));
+            ui.label("width");
+            ui.add(DragValue::new(&mut self.width).range(1..=8).suffix(" B"));
+        });
+        self.offset = self.offset.min(8 - self.width);
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.signed, "signed");
+            let other = match self.endian {
+                Endian::Little => Endian::Big,
+                Endian::Big => Endian::Little,
+            };
+            if ui
+                .button(self.endian.as_str())
+                .on_hover_text(format!("Byte order, click for {}", other.as_str()))
+                .clicked()
+            {
+                self.endian = other;
+            }
+            ui.label("scale");
+            ui.add(DragValue::new(&mut self.scale).speed(0.01));
+        });
+        *self != before
+    }
+}
+
+/// Values extracted from the messages of a pinned filter, plotted over time.
+#[derive(Debug, Clone)]
+pub struct ValuePlot {
+    pub extraction: Extraction,
+    /// Seconds since the plots' origin and value, oldest first.
+    points: VecDeque<[f64; 2]>,
+    /// Fit the Y axis to the values, else `y_min..=y_max`.
+    auto_y: bool,
+    y_min: f64,
+    y_max: f64,
+}
+
+impl Default for ValuePlot {
+    fn default() -> Self {
+        Self {
+            extraction: Extraction::default(),
+            points: VecDeque::new(),
+            auto_y: true,
+            y_min: 0.0,
+            y_max: 100.0,
+        }
+    }
+}
+
+impl ValuePlot {
+    /// Adds a value received at `time`, dropping the oldest points beyond `depth`.
+    pub fn push(&mut self, time: Instant, value: f64, origin: Instant, depth: usize) {
+        let time = time.saturating_duration_since(origin);
+        self.points.push_back([time.as_secs_f64(), value]);
+        self.trim(depth);
+    }
+
+    pub fn trim(&mut self, depth: usize) {
+        while self.points.len() > depth {
+            self.points.pop_front();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.points.clear();
+    }
+
+    /// Seconds since the plots' origin and value, oldest first.
+    pub fn points(&self) -> &VecDeque<[f64; 2]> {
+        &self.points
+    }
+
+    /// Extraction and Y axis settings, the points are dropped if the extraction changes.
+    pub fn settings_ui(&mut self, ui: &mut egui::Ui) {
+        if self.extraction.ui(ui) {
+            self.points.clear();
+        }
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.auto_y, "auto Y");
+            ui.add_enabled(!self.auto_y, DragValue::new(&mut self.y_min).prefix("min "));
+            ui.add_enabled(!self.auto_y, DragValue::new(&mut self.y_max).prefix("max "));
+        });
+    }
+
+    /// Plot of the values, `id` tells the plots apart.
+    pub fn ui(&self, ui: &mut egui::Ui, id: u64, name: &str) {
+        let points: Vec<[f64; 2]> = self.points.iter().copied().collect();
+        let (auto_y, y_min, y_max) = (self.auto_y, self.y_min, self.y_max);
+        Plot::new(("value_plot", id))
+            .height(150.0)
+            .x_axis_label("Time (s)")
+            .allow_drag(false)
+            .allow_scroll(false)
+            .allow_zoom(false)
+            .show(ui, |plot_ui| {
+                if !auto_y {
+                    let x =
+                        points.first().map_or(0.0, |p| p[0])..=points.last().map_or(1.0, |p| p[0]);
+                    plot_ui.set_plot_bounds(PlotBounds::from_min_max(
+                        [*x.start(), y_min],
+                        [x.end().max(x.start() + 1.0), y_max],
+                    ));
+                }
+                plot_ui.line(
+                    Line::new(PlotPoints::new(points))
+                        .color(OZON_PINK)
+                        .name(name),
+                );
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Extraction;
+    use crate::{data_format::Endian, message_cached::MessageCached};
+    use oze_canopen::canopen::RxMessage;
+    use tokio::time::Instant;
+
+    #[test]
+    fn test_extract() {
+        let msg = MessageCached::new(
+            0,
+            RxMessage {
+                timestamp: Instant::now(),
+                cob_id: 0x281,
+                data: [0x37, 0x00, 0x18, 0xFC, 0xFF, 0xFF, 0, 0],
+                dlc: 6,
+            },
+        );
+        let position = Extraction {
+            offset: 2,
+            width: 4,
+            signed: true,
+            ..Extraction::default()
+        };
+        assert_eq!(position.extract(&msg), Some(-1000.0));
+        let unsigned = Extraction {
+            signed: false,
+            ..position
+        };
+        assert_eq!(unsigned.extract(&msg), Some(f64::from(0xFFFF_FC18_u32)));
+        let scaled = Extraction {
+            offset: 0,
+            width: 1,
+            scale: 0.5,
+            ..Extraction::default()
+        };
+        assert_eq!(scaled.extract(&msg), Some(27.5));
+        let big = Extraction {
+            offset: 0,
+            width: 2,
+            endian: Endian::Big,
+            ..Extraction::default()
+        };
+        assert_eq!(big.extract(&msg), Some(f64::from(0x3700)));
+        // Past the DLC.
+        let short = Extraction {
+            offset: 4,
+            width: 4,
+            ..Extraction::default()
+        };
+        assert_eq!(short.extract(&msg), None);
+    }
+}
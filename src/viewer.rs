@@ -1,10 +1,305 @@
-use crate::{filter::GlobalFilter, message_cached::MessageCached, message_row::MessageRow};
-use std::{cell::RefCell, collections::VecDeque, rc::Rc};
+use crate::{
+    annotations::{Annotations, UserMarker},
+    byte_changes::ByteChanges,
+    driver::SessionMarker,
+    filter::{self, FilterRules, GlobalFilter},
+    live::{LiveAction, LiveTable},
+    message_cached::MessageCached,
+    message_row::MessageRow,
+    navigation::{Jump, Navigation},
+    search::Search,
+    selection::Selection,
+    theme::OZON_PINK,
+};
+use oze_canopen::canopen::{NodeId, RxMessageType};
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, VecDeque},
+    rc::Rc,
+    time::Duration,
+};
+use tokio::time::Instant;
+
+/// Number of messages shown under every node heading in the group mode.
+const GROUP_RECENT: usize = 100;
+
+/// How long the row scrolled to stays highlighted.
+const FLASH: Duration = Duration::from_secs(1);
+
+/// How the messages are laid out.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ViewerMode {
+    /// Flat chronological list, newest first.
+    #[default]
+    List,
+    /// Messages grouped under a heading per node.
+    ByNode,
+    /// One row per COB-ID updated in place.
+    Live,
+}
+
+/// Heading of the group mode, broadcast objects (NMT, SYNC, TIME...) belong to the bus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum Group {
+    Bus,
+    Node(NodeId),
+}
+
+/// Messages of one heading of the group mode.
+struct GroupRows<'a> {
+    count: usize,
+    /// Newest messages, newest first.
+    recent: Vec<&'a MessageCached>,
+}
+
+/// Row of the viewer table.
+enum Row<'a> {
+    Message(&'a MessageCached),
+    /// Consecutive identical frames shown as one row.
+    Repeated {
+        newest: &'a MessageCached,
+        oldest: &'a MessageCached,
+        count: usize,
+    },
+    Session(&'a SessionMarker),
+    User(&'a UserMarker),
+}
+
+impl<'a> Row<'a> {
+    /// Message shown on the row, the newest one of a run.
+    fn message(&self) -> Option<&'a MessageCached> {
+        match self {
+            Row::Message(d) | Row::Repeated { newest: d, .. } => Some(d),
+            Row::Session(_) | Row::User(_) => None,
+        }
+    }
+
+    /// Oldest message of the row, the row stays in place while a run grows.
+    fn oldest(&self) -> Option<&'a MessageCached> {
+        match self {
+            Row::Message(d) | Row::Repeated { oldest: d, .. } => Some(d),
+            Row::Session(_) | Row::User(_) => None,
+        }
+    }
+
+    fn count(&self) -> usize {
+        match self {
+            Row::Message(_) => 1,
+            Row::Repeated { count, .. } => *count,
+            Row::Session(_) | Row::User(_) => 0,
+        }
+    }
+
+    /// Adds `msg`, older than the row, to the run if it is the same frame.
+    fn collapse(&mut self, msg: &'a MessageCached) -> bool {
+        let Some(newest) = self.message() else {
+            return false;
+        };
+        let same = newest.msg.msg.cob_id == msg.msg.msg.cob_id
+            && newest.msg.msg.dlc == msg.msg.msg.dlc
+            && newest.msg.msg.data == msg.msg.msg.data
+            && newest.origin == msg.origin;
+        if same {
+            *self = Row::Repeated {
+                newest,
+                oldest: msg,
+                count: self.count() + 1,
+            };
+        }
+        same
+    }
+}
+
+/// Display frozen while the messages keep coming.
+#[derive(Debug, Clone, Copy)]
+struct Freeze {
+    /// Newest message shown, `None` if the buffer was empty.
+    newest: Option<u64>,
+}
+
+impl Freeze {
+    fn hides(self, msg: &MessageCached) -> bool {
+        self.newest.map_or(true, |newest| msg.index > newest)
+    }
+}
+
+/// Keeps the list on the newest messages, or on the ones scrolled to when new messages arrive
+/// or the filters change.
+#[derive(Debug, Clone, Copy)]
+struct AutoScroll {
+    follow: bool,
+    /// Message at the top of the view and how far it is scrolled past, when not following.
+    anchor: Option<(u64, f32)>,
+}
+
+impl Default for AutoScroll {
+    fn default() -> Self {
+        Self {
+            follow: true,
+            anchor: None,
+        }
+    }
+}
+
+impl AutoScroll {
+    /// Position of the anchor in `rows`, or of the first older message if it is gone.
+    fn anchor_row(&self, rows: &[Row]) -> Option<usize> {
+        let (index, _) = self.anchor?;
+        rows.iter()
+            .position(|row| row.oldest().is_some_and(|d| d.index <= index))
+    }
+
+    /// Offset keeping the anchor at the top of the view, the header being the first row.
+    #[allow(clippy::cast_precision_loss)]
+    fn offset(&self, rows: &[Row], row_height: f32) -> f32 {
+        if self.follow {
+            return 0.0;
+        }
+        match (self.anchor_row(rows), self.anchor) {
+            (Some(row), Some((_, past))) => (row + 1) as f32 * row_height + past,
+            _ => 0.0,
+        }
+    }
+
+    /// Floating button over the list while it doesn't follow the newest messages.
+    fn show_jump_to_latest(&mut self, ui: &egui::Ui, rows: &[Row], rect: egui::Rect) {
+        if self.follow {
+            return;
+        }
+        let new = self.anchor_row(rows).map_or(0, |row| {
+            rows[..row]
+                .iter()
+                .filter(|row| row.message().is_some())
+                .count()
+        });
+        if new == 0 {
+            return;
+        }
+        egui::Area::new(egui::Id::new("viewer_jump_to_latest"))
+            .order(egui::Order::Foreground)
+            .pivot(egui::Align2::CENTER_TOP)
+            .fixed_pos(rect.center_top() + egui::vec2(0.0, 30.0))
+            .show(ui.ctx(), |ui| {
+                if ui
+                    .button(format!("⏶ {new} new messages — jump to latest"))
+                    .clicked()
+                {
+                    *self = Self::default();
+                }
+            });
+    }
+
+    /// Follows the newest messages at the top, otherwise anchors the view on its top message.
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        clippy::cast_precision_loss
+    )]
+    fn on_scrolled(&mut self, offset: f32, rows: &[Row], row_height: f32) {
+        if offset < row_height / 2.0 {
+            *self = Self::default();
+            return;
+        }
+        self.follow = false;
+        let row = (offset / row_height).floor() as usize;
+        let past = offset - row as f32 * row_height;
+        self.anchor = rows
+            .get(row.saturating_sub(1)..)
+            .and_then(|rows| rows.iter().find_map(Row::message))
+            .map(|d| (d.index, past));
+    }
+}
+
+/// Messages passing the global filter. Only the messages received since the last frame are
+/// filtered, the whole buffer only when the filter changes.
+#[derive(Debug, Default)]
+struct Filtered {
+    /// Filter the messages were matched with.
+    filter: Option<FilterRules>,
+    /// Matching messages, newest first.
+    indices: VecDeque<u64>,
+    /// Newest message of the buffer matched so far.
+    newest: Option<u64>,
+    /// Incremented when the messages are matched again from scratch.
+    generation: u64,
+}
+
+impl Filtered {
+    /// Matches the new messages of `data` (newest first) and returns the matching ones.
+    fn update<'a>(
+        &mut self,
+        filter: &GlobalFilter,
+        data: impl Iterator<Item = &'a MessageCached> + Clone,
+    ) -> Vec<&'a MessageCached> {
+        let key = filter.rules();
+        let front = data.clone().next().map(|msg| msg.index);
+        // The buffer was cleared if its newest message is older than the ones matched.
+        if self.filter.as_ref() != Some(&key) || front < self.newest {
+            self.filter = Some(key);
+            self.indices.clear();
+            self.newest = None;
+            self.generation += 1;
+        }
+
+        let newest = self.newest;
+        let new: Vec<u64> = data
+            .clone()
+            .take_while(|msg| newest.map_or(true, |newest| msg.index > newest))
+            .filter(|msg| !filter.filter(msg))
+            .map(|msg| msg.index)
+            .collect();
+        for index in new.into_iter().rev() {
+            self.indices.push_front(index);
+        }
+        self.newest = front.or(self.newest);
+
+        // Both are ordered newest first, the oldest indices are gone once trimmed from the buffer.
+        let mut messages = Vec::with_capacity(self.indices.len());
+        let mut data = data.peekable();
+        for index in &self.indices {
+            while data.next_if(|msg| msg.index > *index).is_some() {}
+            match data.next_if(|msg| msg.index == *index) {
+                Some(msg) => messages.push(msg),
+                None => break,
+            }
+        }
+        self.indices.truncate(messages.len());
+        messages
+    }
+}
+
+/// Clicks on the rows during a frame, applied once the table is drawn.
+#[derive(Default)]
+struct RowInput {
+    clicked: Option<u64>,
+    copy: bool,
+    /// Message whose bookmark star was clicked.
+    bookmark: Option<u64>,
+    /// Chosen in the menu of a row.
+    action: Option<LiveAction>,
+}
 
 #[derive(Debug)]
 pub struct Viewer {
     global_filter: Rc<RefCell<GlobalFilter>>,
     pub message_row: MessageRow,
+    pub mode: ViewerMode,
+    /// Selected messages, by message index.
+    pub selection: Selection,
+    search: Search,
+    freeze: Option<Freeze>,
+    scroll: AutoScroll,
+    /// Fold consecutive identical frames into one row.
+    collapse: bool,
+    filtered: Filtered,
+    pub annotations: Annotations,
+    /// Note of the next marker.
+    marker_note: String,
+    navigation: Navigation,
+    /// Where to scroll to, chosen in the bookmarks menu or another panel.
+    jump: Option<Jump>,
+    /// Row scrolled to and when, highlighted for a moment.
+    flash: Option<(u64, Instant)>,
 }
 
 impl Viewer {
@@ -12,12 +307,272 @@ impl Viewer {
         Self {
             message_row: MessageRow::default(),
             global_filter,
+            mode: ViewerMode::default(),
+            selection: Selection::default(),
+            search: Search::default(),
+            freeze: None,
+            scroll: AutoScroll::default(),
+            collapse: false,
+            filtered: Filtered::default(),
+            annotations: Annotations::default(),
+            marker_note: String::new(),
+            navigation: Navigation::default(),
+            jump: None,
+            flash: None,
+        }
+    }
+
+    /// Scrolls the list to a message on the next frame.
+    pub fn jump_to(&mut self, jump: Jump) {
+        self.jump = Some(jump);
+        self.mode = ViewerMode::List;
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        self.freeze.is_some()
+    }
+
+    /// Freezes the list at the newest message or resumes showing new ones.
+    pub fn toggle_freeze(&mut self, data: &VecDeque<MessageCached>) {
+        self.freeze = match self.freeze {
+            Some(_) => None,
+            None => Some(Freeze {
+                newest: data.front().map(|msg| msg.index),
+            }),
+        };
+    }
+
+    fn show_freeze(&mut self, ui: &mut egui::Ui, data: &VecDeque<MessageCached>, hidden: usize) {
+        let toggle = match self.freeze {
+            None => ui
+                .button("⏸ Freeze")
+                .on_hover_text("Stop updating the list while the capture goes on (Space)"),
+            Some(_) => ui
+                .button(egui::RichText::new(format!("▶ Resume ({hidden} new)")).color(OZON_PINK))
+                .on_hover_text("Show the messages received while frozen (Space)"),
+        };
+        let space =
+            ui.input(|i| i.key_pressed(egui::Key::Space)) && ui.memory(|m| m.focused().is_none());
+        if toggle.clicked() || space {
+            self.toggle_freeze(data);
+        }
+    }
+
+    /// Shows the mode selector and the messages in the selected mode.
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        data: &VecDeque<MessageCached>,
+        sessions: &VecDeque<SessionMarker>,
+        live: &mut LiveTable,
+        changes: &ByteChanges,
+    ) -> Option<LiveAction> {
+        // Messages received since the display was frozen, at the front of the buffer.
+        let hidden = self.freeze.map_or(0, |freeze| {
+            data.iter().take_while(|msg| freeze.hides(msg)).count()
+        });
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut self.mode, ViewerMode::List, "List")
+                .on_hover_text("All messages, newest first");
+            ui.selectable_value(&mut self.mode, ViewerMode::ByNode, "By node")
+                .on_hover_text("Messages grouped by node, broadcast objects under \"bus\"");
+            ui.selectable_value(&mut self.mode, ViewerMode::Live, "Live")
+                .on_hover_text("One row per COB-ID with its last data, like cansniffer");
+            if self.mode != ViewerMode::Live {
+                ui.separator();
+                self.show_freeze(ui, data, hidden);
+                ui.separator();
+                self.show_annotations(ui, data);
+            }
+            if self.mode == ViewerMode::List
+                && ui
+                    .checkbox(&mut self.scroll.follow, "Auto-scroll")
+                    .on_hover_text("Keep the newest messages in view, scrolling down turns it off")
+                    .changed()
+            {
+                self.scroll.anchor = None;
+            }
+            if self.mode == ViewerMode::List {
+                ui.checkbox(&mut self.collapse, "Collapse duplicates")
+                    .on_hover_text("Show consecutive frames with the same COB-ID and data as one row with a ×N counter");
+            }
+        });
+
+        if self.mode == ViewerMode::Live {
+            return live.update(ui, self.message_row.format, changes);
+        }
+        let filter = self.global_filter.clone();
+        let messages = self.filtered.update(&filter.borrow(), data.range(hidden..));
+        if self.mode == ViewerMode::List {
+            self.update(ui, &messages, sessions)
+        } else {
+            self.update_by_node(ui, &messages)
+        }
+    }
+
+    /// Marker button and the menu of the markers and bookmarks, which scrolls to them.
+    fn show_annotations(&mut self, ui: &mut egui::Ui, data: &VecDeque<MessageCached>) {
+        let key = ui.input(|i| i.key_pressed(egui::Key::M)) && ui.memory(|m| m.focused().is_none());
+        if ui
+            .button("🚩 Marker")
+            .on_hover_text("Insert a marker row at the current time (M)")
+            .clicked()
+            || key
+        {
+            self.annotations.add_marker(&self.marker_note);
+            self.marker_note.clear();
+        }
+        ui.add(
+            egui::TextEdit::singleline(&mut self.marker_note)
+                .hint_text("marker note")
+                .desired_width(120.0),
+        );
+
+        let count = self.annotations.markers.len() + self.annotations.bookmarks.len();
+        ui.menu_button(format!("🔖 {count}"), |ui| {
+            if self.annotations.is_empty() {
+                ui.label("Click ☆ on a row or add a marker");
+            }
+            let mut jump = None;
+            for marker in self.annotations.markers.iter().rev() {
+                if ui.button(marker.to_string()).clicked() {
+                    // The newest message before the marker, its row is just below the marker.
+                    jump = Some(Jump::Index(marker.index.saturating_sub(1)));
+                }
+            }
+            if !self.annotations.markers.is_empty() && !self.annotations.bookmarks.is_empty() {
+                ui.separator();
+            }
+            let mut remove = None;
+            for &index in self.annotations.bookmarks.iter().rev() {
+                ui.horizontal(|ui| {
+                    if ui
+                        .small_button("❌")
+                        .on_hover_text("Remove the bookmark")
+                        .clicked()
+                    {
+                        remove = Some(index);
+                    }
+                    // The buffer is ordered newest first.
+                    match data.binary_search_by(|msg| index.cmp(&msg.index)) {
+                        Ok(position) => {
+                            let text = self.message_row.text(&data[position], None);
+                            if ui.button(format!("★ {text}")).clicked() {
+                                jump = Some(Jump::Index(index));
+                            }
+                        }
+                        Err(_) => {
+                            ui.weak(format!("★ message {index}, no longer buffered"));
+                        }
+                    }
+                });
+            }
+            if let Some(index) = remove {
+                self.annotations.toggle_bookmark(index);
+            }
+            if jump.is_some() {
+                self.jump = jump;
+                self.mode = ViewerMode::List;
+                ui.close_menu();
+            }
+        })
+        .response
+        .on_hover_text("Markers and bookmarks, click one to scroll to it");
+    }
+
+    /// Titles of the columns, after the bookmark column.
+    fn header(&self, ui: &mut egui::Ui) {
+        ui.label("☆").on_hover_text("Bookmarks");
+        self.message_row.header(ui);
+    }
+
+    /// Shows the filtered messages under a collapsible heading per node.
+    fn update_by_node(&mut self, ui: &mut egui::Ui, data: &[&MessageCached]) -> Option<LiveAction> {
+        let mut groups: BTreeMap<Group, GroupRows> = BTreeMap::new();
+        for &msg in data {
+            let group = msg.msg.parsed_node_id.map_or(Group::Bus, Group::Node);
+            let rows = groups.entry(group).or_insert_with(|| GroupRows {
+                count: 0,
+                recent: Vec::new(),
+            });
+            rows.count += 1;
+            if rows.recent.len() < GROUP_RECENT {
+                rows.recent.push(msg);
+            }
         }
+
+        if groups.is_empty() {
+            ui.label("No messages");
+            return None;
+        }
+
+        let mut input = RowInput::default();
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for (&group, rows) in &groups {
+                let name = match group {
+                    Group::Bus => "bus".to_owned(),
+                    Group::Node(node_id) => format!("node {node_id}"),
+                };
+                let last = rows.recent[0]
+                    .get_timestamp()
+                    .duration_since(self.message_row.start_time)
+                    .as_secs_f32();
+                egui::CollapsingHeader::new(format!(
+                    "{name}: {} messages, last at {last:.3} s",
+                    rows.count
+                ))
+                .id_salt(("viewer_group", group))
+                .show(ui, |ui| {
+                    egui::Grid::new(("viewer_group_grid", group))
+                        .spacing([20.0, 4.0])
+                        .striped(true)
+                        .show(ui, |ui| {
+                            self.header(ui);
+                            ui.end_row();
+                            // Oldest first so the group reads in time order.
+                            let mut previous = None;
+                            for msg in rows.recent.iter().rev() {
+                                self.message(ui, msg, previous, &mut input);
+                                previous = Some(msg.get_timestamp());
+                                ui.end_row();
+                            }
+                        });
+                });
+            }
+        });
+
+        Self::apply_input(&mut self.selection, &self.message_row, ui, &input, || {
+            let mut order = Vec::new();
+            for rows in groups.values() {
+                let mut previous = None;
+                for msg in rows.recent.iter().rev() {
+                    order.push((*msg, previous, 1));
+                    previous = Some(msg.get_timestamp());
+                }
+            }
+            order
+        });
+        if let Some(index) = input.bookmark {
+            self.annotations.toggle_bookmark(index);
+        }
+        input.action
     }
 
-    pub fn update(&mut self, ui: &mut egui::Ui, data: &VecDeque<MessageCached>) {
-        let filt = self.global_filter.borrow();
-        let data: VecDeque<&MessageCached> = data.iter().filter(|i| !filt.filter(i)).collect();
+    /// Shows the filtered messages (newest first) with a marker row at every session boundary.
+    /// Only the rows in view are laid out.
+    #[allow(clippy::cast_precision_loss)]
+    fn update<'a>(
+        &mut self,
+        ui: &mut egui::Ui,
+        messages: &[&'a MessageCached],
+        sessions: &'a VecDeque<SessionMarker>,
+    ) -> Option<LiveAction> {
+        let data = Self::rows(
+            messages.iter().copied(),
+            sessions,
+            &self.annotations.markers,
+            self.collapse,
+        );
 
         // let sessions: Vec<(u8, u8)> = Vec::new();
         // for i in &data {
@@ -34,11 +589,27 @@ impl Viewer {
         let text_style = egui::TextStyle::Body;
         let text_height = ui.text_style_height(&text_style);
         let height = text_height + row_spacing;
-        egui::ScrollArea::vertical().animated(true).show_rows(
-            ui,
-            height,
-            data.len() + 1,
-            |ui, row_range| {
+
+        let row_height = height + ui.spacing().item_spacing.y;
+        let mut offset = self.scroll.offset(&data, row_height);
+        self.search.refresh(messages, self.filtered.generation);
+        let hit = self.search.update(ui).map(Jump::Index);
+        let jump = hit.or(self.navigation.update(ui)).or(self.jump.take());
+        if let Some(position) = jump.and_then(|jump| self.position(jump, &data)) {
+            // A target folded into a run of duplicates selects the row of the run.
+            if let Some(d) = data[position].message() {
+                self.selection.select_only(d.index);
+                self.flash = Some((d.index, Instant::now()));
+            }
+            // The header is the first row, keep a few rows above the target.
+            offset = (position + 1).saturating_sub(3) as f32 * row_height;
+        }
+
+        let mut input = RowInput::default();
+        let output = egui::ScrollArea::vertical()
+            .animated(true)
+            .vertical_scroll_offset(offset)
+            .show_rows(ui, height, data.len() + 1, |ui, row_range| {
                 egui::Grid::new("viewer_grid")
                     .start_row(row_range.start)
                     .spacing([column_spacing, row_spacing])
@@ -46,23 +617,355 @@ impl Viewer {
                     .min_row_height(height)
                     .show(ui, |ui| {
                         let data_range = if row_range.start == 0 {
-                            self.message_row.header(ui);
+                            self.header(ui);
                             ui.end_row();
                             0..(row_range.end - 1)
                         } else {
                             (row_range.start - 1)..(row_range.end - 1)
                         };
 
-                        for d in data.range(data_range) {
-                            self.message_row.message(ui, d);
+                        for index in data_range {
+                            match &data[index] {
+                                Row::Message(d) => {
+                                    let previous = Self::previous_message(&data[index + 1..]);
+                                    self.message(ui, d, previous, &mut input);
+                                }
+                                Row::Repeated {
+                                    newest,
+                                    oldest,
+                                    count,
+                                } => {
+                                    let previous = Self::previous_message(&data[index + 1..]);
+                                    self.message(ui, newest, previous, &mut input);
+                                    self.message_row.repeat(
+                                        ui,
+                                        *count,
+                                        oldest.get_timestamp(),
+                                        newest.get_timestamp(),
+                                    );
+                                }
+                                Row::Session(marker) => {
+                                    ui.label("");
+                                    self.message_row.session_marker(ui, marker);
+                                }
+                                Row::User(marker) => {
+                                    ui.label("");
+                                    self.message_row.user_marker(ui, marker);
+                                }
+                            }
                             ui.end_row();
                         }
 
                         // Костыль нужный, чтобы выровнять ширину столбца
-                        self.message_row.header(ui);
+                        self.header(ui);
                         ui.end_row();
                     });
+            });
+
+        self.scroll
+            .on_scrolled(output.state.offset.y, &data, row_height);
+        self.scroll
+            .show_jump_to_latest(ui, &data, output.inner_rect);
+
+        Self::apply_input(&mut self.selection, &self.message_row, ui, &input, || {
+            data.iter()
+                .enumerate()
+                .filter_map(|(index, row)| {
+                    Some((
+                        row.message()?,
+                        Self::previous_message(&data[index + 1..]),
+                        row.count(),
+                    ))
+                })
+                .collect()
+        });
+        if let Some(index) = input.bookmark {
+            self.annotations.toggle_bookmark(index);
+        }
+        input.action
+    }
+
+    /// Message of `data` last clicked in the selection.
+    pub fn selected_message<'a>(
+        &self,
+        data: &'a VecDeque<MessageCached>,
+    ) -> Option<&'a MessageCached> {
+        let index = self.selection.current()?;
+        data.iter().find(|d| d.index == index)
+    }
+
+    /// Shows a message after its bookmark star and records the clicks on the star and on its
+    /// timestamp.
+    fn message(
+        &self,
+        ui: &mut egui::Ui,
+        d: &MessageCached,
+        previous: Option<Instant>,
+        input: &mut RowInput,
+    ) {
+        let star = if self.annotations.is_bookmarked(d.index) {
+            egui::RichText::new("★").color(OZON_PINK)
+        } else {
+            egui::RichText::new("☆")
+        };
+        if ui
+            .add(egui::Button::new(star).frame(false))
+            .on_hover_text("Bookmark the message")
+            .clicked()
+        {
+            input.bookmark = Some(d.index);
+        }
+        let response = self
+            .message_row
+            .message(ui, d, previous, self.selection.contains(d.index));
+        if response.clicked() {
+            input.clicked = Some(d.index);
+        }
+        if response.secondary_clicked() && !self.selection.contains(d.index) {
+            input.clicked = Some(d.index);
+        }
+        response.context_menu(|ui| {
+            let cob_id = d.msg.msg.cob_id;
+            let mut action = |ui: &mut egui::Ui, text: &str, action: LiveAction| {
+                if ui.button(text).clicked() {
+                    input.action = Some(action);
+                    ui.close_menu();
+                }
+            };
+            action(ui, "Hide this COB-ID", LiveAction::Hide(cob_id));
+            action(ui, "Show only this COB-ID", LiveAction::ShowOnly(cob_id));
+            if let Some(node_id) = filter::node_of(cob_id) {
+                action(ui, "Hide this node", LiveAction::HideNode(node_id));
+            }
+            action(ui, "Pin this COB-ID", LiveAction::Pin(cob_id));
+            ui.separator();
+            if ui.button("Copy").clicked() {
+                input.copy = true;
+                ui.close_menu();
+            }
+        });
+
+        if let Some((index, since)) = self.flash {
+            let fade = 1.0 - since.elapsed().as_secs_f32() / FLASH.as_secs_f32();
+            if index == d.index && fade > 0.0 {
+                let rect =
+                    egui::Rect::from_x_y_ranges(ui.clip_rect().x_range(), response.rect.y_range());
+                ui.painter()
+                    .rect_filled(rect, 0.0, OZON_PINK.gamma_multiply(0.4 * fade));
+            }
+        }
+    }
+
+    /// Row of `rows` to scroll to: the nearest one to an index or time, or the next matching
+    /// one from the selected row.
+    fn position(&self, jump: Jump, rows: &[Row]) -> Option<usize> {
+        let matches = |row: &Row| {
+            row.message().is_some_and(|d| match jump {
+                Jump::Emcy { .. } => d.msg.parsed_type == RxMessageType::Emcy,
+                _ => self.annotations.is_bookmarked(d.index),
+            })
+        };
+        let current = self.selection.current().and_then(|index| {
+            rows.iter()
+                .position(|row| row.message().is_some_and(|d| d.index == index))
+        });
+        match jump {
+            Jump::Emcy { forward: true } | Jump::Bookmark { forward: true } => {
+                let from = current.map_or(0, |current| current + 1);
+                rows[from..]
+                    .iter()
+                    .position(matches)
+                    .map(|position| position + from)
+            }
+            Jump::Emcy { forward: false } | Jump::Bookmark { forward: false } => rows
+                [..current.unwrap_or(rows.len())]
+                .iter()
+                .rposition(matches),
+            Jump::Index(_) | Jump::Time(_) => rows
+                .iter()
+                .enumerate()
+                .filter_map(|(position, row)| {
+                    let distance =
+                        jump.distance(row.message()?, row.oldest()?, self.message_row.start_time)?;
+                    Some((position, distance))
+                })
+                .min_by_key(|(_, distance)| *distance)
+                .map(|(position, _)| position),
+        }
+    }
+
+    /// Updates the selection and copies it if asked. `order` gives the displayed messages in
+    /// display order with the time of the row before them and the number of frames they stand
+    /// for, it is only built when needed.
+    fn apply_input<'a>(
+        selection: &mut Selection,
+        message_row: &MessageRow,
+        ui: &egui::Ui,
+        input: &RowInput,
+        order: impl FnOnce() -> Vec<(&'a MessageCached, Option<Instant>, usize)>,
+    ) {
+        let copy = input.copy || selection.copy_requested(ui);
+        if input.clicked.is_none() && !copy {
+            return;
+        }
+        let order = order();
+        if let Some(index) = input.clicked {
+            let keys: Vec<u64> = order.iter().map(|(d, _, _)| d.index).collect();
+            selection.click(index, ui.input(|i| i.modifiers), &keys);
+        }
+        if copy {
+            let text: String = order
+                .iter()
+                .filter(|(d, _, _)| selection.contains(d.index))
+                .map(|(d, previous, count)| {
+                    let text = message_row.text(d, *previous);
+                    if *count > 1 {
+                        format!("{text}  ×{count}\n")
+                    } else {
+                        text + "\n"
+                    }
+                })
+                .collect();
+            ui.ctx().copy_text(text);
+        }
+    }
+
+    /// Time of the first message of `rows`, i.e. the one displayed before in time order.
+    fn previous_message(rows: &[Row]) -> Option<Instant> {
+        rows.iter()
+            .find_map(Row::message)
+            .map(MessageCached::get_timestamp)
+    }
+
+    /// Merges the session and user markers, ordered oldest first, into the messages, ordered
+    /// newest first. With `collapse` the consecutive identical messages of `data`, i.e. after
+    /// filtering, become one row.
+    fn rows<'a>(
+        data: impl Iterator<Item = &'a MessageCached>,
+        sessions: &'a VecDeque<SessionMarker>,
+        markers: &'a [UserMarker],
+        collapse: bool,
+    ) -> Vec<Row<'a>> {
+        let mut sessions = sessions.iter().rev().peekable();
+        let mut markers = markers.iter().rev().peekable();
+        let mut rows: Vec<Row> = Vec::new();
+        for msg in data {
+            while let Some(marker) = sessions.next_if(|marker| msg.index < marker.index) {
+                rows.push(Row::Session(marker));
+            }
+            while let Some(marker) = markers.next_if(|marker| msg.index < marker.index) {
+                rows.push(Row::User(marker));
+            }
+            if collapse && rows.last_mut().is_some_and(|row| row.collapse(msg)) {
+                continue;
+            }
+            rows.push(Row::Message(msg));
+        }
+        rows
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Filtered, Row, Viewer};
+    use crate::{driver::SessionMarker, filter::GlobalFilter, message_cached::MessageCached};
+    use oze_canopen::canopen::RxMessage;
+    use std::collections::VecDeque;
+    use tokio::time::Instant;
+
+    fn message(index: u64, cob_id: u16) -> MessageCached {
+        MessageCached::new(
+            index,
+            RxMessage {
+                timestamp: Instant::now(),
+                cob_id,
+                data: [0; 8],
+                dlc: 1,
             },
+        )
+    }
+
+    #[test]
+    fn test_filtered() {
+        let filter = GlobalFilter::default();
+        filter.data.borrow_mut().node_id = Some(1);
+        let mut data: VecDeque<MessageCached> = (0..4)
+            .map(|index| message(index, [0x181, 0x182][usize::from(index % 2 == 1)]))
+            .rev()
+            .collect();
+        let mut filtered = Filtered::default();
+        let indices = |messages: Vec<&MessageCached>| -> Vec<u64> {
+            messages.iter().map(|msg| msg.index).collect()
+        };
+        assert_eq!(indices(filtered.update(&filter, data.iter())), [2, 0]);
+        let generation = filtered.generation;
+
+        // New messages are matched, trimmed ones are dropped.
+        data.push_front(message(4, 0x181));
+        data.push_front(message(5, 0x701));
+        data.pop_back();
+        assert_eq!(indices(filtered.update(&filter, data.iter())), [5, 4, 2]);
+        assert_eq!(filtered.generation, generation);
+
+        filter.data.borrow_mut().node_id = Some(2);
+        assert_eq!(indices(filtered.update(&filter, data.iter())), [3, 1]);
+        assert_ne!(filtered.generation, generation);
+
+        data.clear();
+        data.push_front(message(0, 0x182));
+        assert_eq!(indices(filtered.update(&filter, data.iter())), [0]);
+    }
+
+    #[test]
+    fn test_collapse() {
+        let mut data: Vec<MessageCached> = [1, 1, 2, 1, 1, 1]
+            .iter()
+            .zip(0..)
+            .map(|(byte, index)| {
+                MessageCached::new(
+                    index,
+                    RxMessage {
+                        timestamp: Instant::now(),
+                        cob_id: 0x181,
+                        data: [*byte, 0, 0, 0, 0, 0, 0, 0],
+                        dlc: 1,
+                    },
+                )
+            })
+            .collect();
+        // Newest first, as in the buffer.
+        data.reverse();
+        let sessions = VecDeque::from([SessionMarker {
+            index: 4,
+            text: "reconnected".to_owned(),
+        }]);
+
+        let rows = Viewer::rows(data.iter(), &sessions, &[], true);
+        let summary: Vec<_> = rows
+            .iter()
+            .map(|row| match row {
+                Row::Message(d) => (d.index, d.index, 1),
+                Row::Repeated {
+                    newest,
+                    oldest,
+                    count,
+                } => (newest.index, oldest.index, *count),
+                Row::Session(_) | Row::User(_) => (0, 0, 0),
+            })
+            .collect();
+        // The session marker breaks the run of 0x181 [01].
+        assert_eq!(
+            summary,
+            [(5, 4, 2), (0, 0, 0), (3, 3, 1), (2, 2, 1), (1, 0, 2)]
         );
+
+        // Without the frame carrying 02, the runs around it join.
+        let filtered = data.iter().filter(|d| d.index != 2);
+        let no_sessions = VecDeque::new();
+        let rows = Viewer::rows(filtered, &no_sessions, &[], true);
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].count(), 5);
+
+        assert_eq!(Viewer::rows(data.iter(), &sessions, &[], false).len(), 7);
     }
 }
@@ -0,0 +1,358 @@
+use crate::{
+    bus_stats::BusStatsSnapshot,
+    filter::{Classes, NodeSet},
+    message_cached::MessageCached,
+    message_row::wall_clock,
+    search::Query,
+};
+use chrono::{DateTime, Local, SecondsFormat};
+use futures_util::{SinkExt, StreamExt};
+use serde_json::json;
+use std::{io, net::SocketAddr, sync::Arc, time::Duration};
+use tokio::{
+    net::{TcpListener, TcpStream},
+    runtime::Handle,
+    sync::{broadcast, broadcast::error::RecvError, watch},
+    time::{timeout, Instant},
+};
+use tokio_tungstenite::tungstenite::{
+    handshake::server::{ErrorResponse, Request, Response},
+    http::StatusCode,
+    Message,
+};
+
+/// Messages queued per client, a client further behind loses the oldest ones.
+pub const WS_CHANNEL_CAPACITY: usize = 4096;
+
+/// Interval between two statistics snapshots sent to the clients.
+const STATS_PERIOD: Duration = Duration::from_secs(1);
+
+/// Time a client gets to take a message before it's disconnected.
+const SEND_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Messages a client asked for in the query of its URL, e.g. `/?filter=id:181&nodes=1-4`.
+///
+/// `filter` takes the syntax of the search box, `nodes` node IDs and ranges, and `stats=0`
+/// turns the statistics snapshots off.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientFilter {
+    query: Option<Query>,
+    nodes: Option<NodeSet>,
+    stats: bool,
+}
+
+impl Default for ClientFilter {
+    fn default() -> Self {
+        Self {
+            query: None,
+            nodes: None,
+            stats: true,
+        }
+    }
+}
+
+impl ClientFilter {
+    /// # Errors
+    /// If a parameter is unknown or its value invalid.
+    pub fn parse(query: &str) -> Result<Self, String> {
+        let mut filter = Self::default();
+        for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let value = percent_decode(value)?;
+            match key {
+                "filter" => filter.query = Query::parse(&value),
+                "nodes" => filter.nodes = Some(NodeSet::parse(&value)?),
+                "stats" => filter.stats = value != "0",
+                _ => return Err(format!("unknown parameter '{key}'")),
+            }
+        }
+        Ok(filter)
+    }
+
+    pub fn matches(&self, msg: &MessageCached) -> bool {
+        let node = msg.msg.parsed_node_id;
+        self.query.as_ref().map_or(true, |query| query.matches(msg))
+            && self
+                .nodes
+                .map_or(true, |nodes| node.is_some_and(|node| nodes.contains(node)))
+    }
+}
+
+/// Value of a URL query parameter, with `+` for spaces.
+fn percent_decode(raw: &str) -> Result<String, String> {
+    let mut bytes = Vec::with_capacity(raw.len());
+    let mut chars = raw.bytes();
+    while let Some(byte) = chars.next() {
+        bytes.push(match byte {
+            b'+' => b' ',
+            b'%' => {
+                let hex = [chars.next(), chars.next()];
+                let [Some(high), Some(low)] = hex else {
+                    return Err(format!("bad escape in '{raw}'"));
+                };
+                std::str::from_utf8(&[high, low])
+                    .ok()
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                    .ok_or_else(|| format!("bad escape in '{raw}'"))?
+            }
+            byte => byte,
+        });
+    }
+    String::from_utf8(bytes).map_err(|_| format!("'{raw}' isn't UTF-8"))
+}
+
+/// JSON object of a message received at `time`.
+pub fn message_json(msg: &MessageCached, time: DateTime<Local>) -> String {
+    let frame = &msg.msg.msg;
+    let class = Classes::of(frame.cob_id)
+        .iter_names()
+        .next()
+        .map_or("other".to_owned(), |(name, _)| name.to_lowercase());
+    json!({
+        "type": "message",
+        "index": msg.index,
+        "timestamp": time.to_rfc3339_opts(SecondsFormat::Micros, false),
+        "cob_id": frame.cob_id,
+        "node": msg.msg.parsed_node_id,
+        "class": class,
+        "direction": msg.direction.as_str().to_lowercase(),
+        "data": msg.hex_str.replace(' ', ""),
+        "decoded": msg.info().as_ref(),
+    })
+    .to_string()
+}
+
+/// Statistics of the streamed session, sent once per period while clients are connected.
+#[derive(Debug, Clone)]
+pub struct StatsPublisher {
+    sender: watch::Sender<Option<Arc<BusStatsSnapshot>>>,
+    next: Instant,
+}
+
+impl StatsPublisher {
+    /// Takes a snapshot with `snapshot` if due and a client is connected.
+    pub fn publish_if_due(&mut self, now: Instant, snapshot: impl FnOnce() -> BusStatsSnapshot) {
+        if now < self.next || self.sender.receiver_count() == 0 {
+            return;
+        }
+        self.next = now + STATS_PERIOD;
+        self.sender.send_replace(Some(Arc::new(snapshot())));
+    }
+}
+
+/// WebSocket server broadcasting the messages of a driver and the statistics to its clients.
+#[derive(Debug)]
+pub struct WsServer {
+    messages: broadcast::Sender<Arc<MessageCached>>,
+    stats: watch::Sender<Option<Arc<BusStatsSnapshot>>>,
+    local_addr: SocketAddr,
+}
+
+impl WsServer {
+    /// Listens on `addr` and serves the clients on `runtime`, each with its own queue of
+    /// `capacity` messages.
+    ///
+    /// # Errors
+    /// If `addr` can't be listened on.
+    pub fn start(runtime: &Handle, addr: SocketAddr, capacity: usize) -> io::Result<Self> {
+        let listener = std::net::TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        let local_addr = listener.local_addr()?;
+        let _enter = runtime.enter();
+        let listener = TcpListener::from_std(listener)?;
+        let (messages, _) = broadcast::channel(capacity);
+        let (stats, _) = watch::channel(None);
+        let server = Self {
+            messages: messages.clone(),
+            stats: stats.clone(),
+            local_addr,
+        };
+        // Capture start, for the wall-clock time of the messages.
+        let start = (Instant::now(), Local::now());
+        tokio::spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, peer)) => {
+                        // Subscribed now, the messages that follow are queued for the client.
+                        let client = Client {
+                            messages: messages.subscribe(),
+                            stats: stats.subscribe(),
+                            start,
+                            dropped: 0,
+                        };
+                        tokio::spawn(client.serve(stream, peer));
+                    }
+                    Err(e) => {
+                        log::warn!("Can't accept a WebSocket client: {e}");
+                        tokio::time::sleep(Duration::from_millis(100)).await;
+                    }
+                }
+            }
+        });
+        log::info!("Streaming the messages on ws://{local_addr}");
+        Ok(server)
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Sender of the messages, given to the driver.
+    pub fn messages(&self) -> broadcast::Sender<Arc<MessageCached>> {
+        self.messages.clone()
+    }
+
+    /// Publisher of the statistics, given to the session of the driver.
+    pub fn stats(&self) -> StatsPublisher {
+        StatsPublisher {
+            sender: self.stats.clone(),
+            next: Instant::now(),
+        }
+    }
+}
+
+/// Connection of a client, until it closes it or falls too far behind.
+struct Client {
+    messages: broadcast::Receiver<Arc<MessageCached>>,
+    stats: watch::Receiver<Option<Arc<BusStatsSnapshot>>>,
+    start: (Instant, DateTime<Local>),
+    /// Messages lost because the client was too slow.
+    dropped: u64,
+}
+
+impl Client {
+    // The error response of the handshake is the type of `tungstenite`.
+    #[allow(clippy::result_large_err)]
+    async fn serve(mut self, stream: TcpStream, peer: SocketAddr) {
+        let mut filter = ClientFilter::default();
+        let accepted =
+            tokio_tungstenite::accept_hdr_async(stream, |request: &Request, response: Response| {
+                match ClientFilter::parse(request.uri().query().unwrap_or_default()) {
+                    Ok(parsed) => {
+                        filter = parsed;
+                        Ok(response)
+                    }
+                    Err(e) => {
+                        let mut error = ErrorResponse::new(Some(e));
+                        *error.status_mut() = StatusCode::BAD_REQUEST;
+                        Err(error)
+                    }
+                }
+            })
+            .await;
+        let ws = match accepted {
+            Ok(ws) => ws,
+            Err(e) => {
+                log::warn!("WebSocket client {peer} refused: {e}");
+                return;
+            }
+        };
+        log::info!("WebSocket client {peer} connected");
+        let (mut sink, mut source) = ws.split();
+        loop {
+            let text = tokio::select! {
+                received = self.messages.recv() => match received {
+                    Ok(msg) if filter.matches(&msg) => {
+                        message_json(&msg, wall_clock(self.start.0, self.start.1, msg.get_timestamp()))
+                    }
+                    Ok(_) => continue,
+                    Err(RecvError::Lagged(count)) => {
+                        self.dropped += count;
+                        json!({"type": "dropped", "count": count, "total": self.dropped}).to_string()
+                    }
+                    Err(RecvError::Closed) => break,
+                },
+                Ok(()) = self.stats.changed(), if filter.stats => {
+                    let Some(stats) = self.stats.borrow_and_update().clone() else {
+                        continue;
+                    };
+                    json!({"type": "stats", "stats": stats.as_ref()}).to_string()
+                }
+                incoming = source.next() => match incoming {
+                    Some(Ok(Message::Close(_)) | Err(_)) | None => break,
+                    Some(Ok(_)) => continue,
+                },
+            };
+            match timeout(SEND_TIMEOUT, sink.send(Message::text(text))).await {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => {
+                    log::info!("WebSocket client {peer} left: {e}");
+                    return;
+                }
+                Err(_) => {
+                    log::warn!("WebSocket client {peer} disconnected, too slow");
+                    return;
+                }
+            }
+        }
+        log::info!(
+            "WebSocket client {peer} disconnected, {} messages dropped",
+            self.dropped
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ClientFilter, WsServer};
+    use crate::{bus_stats::BusStats, message_cached::MessageCached};
+    use futures_util::StreamExt;
+    use oze_canopen::canopen::RxMessage;
+    use serde_json::Value;
+    use std::sync::Arc;
+    use tokio::{runtime::Handle, time::Instant};
+    use tokio_tungstenite::tungstenite::{Error, Message};
+
+    async fn next_json(ws: &mut (impl StreamExt<Item = Result<Message, Error>> + Unpin)) -> Value {
+        let text = ws.next().await.unwrap().unwrap().into_text().unwrap();
+        serde_json::from_str(&text).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_ws_server() {
+        assert!(ClientFilter::parse("filter=id%3A181&nodes=1-4+7").is_ok());
+        assert!(ClientFilter::parse("node=1")
+            .unwrap_err()
+            .contains("unknown parameter"));
+        assert!(ClientFilter::parse("nodes=200").is_err());
+
+        let server =
+            WsServer::start(&Handle::current(), "127.0.0.1:0".parse().unwrap(), 16).unwrap();
+        let url = format!("ws://{}/?nodes=1", server.local_addr());
+        let (mut ws, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+        let refused = format!("ws://{}/?nodes=0", server.local_addr());
+        assert!(tokio_tungstenite::connect_async(refused).await.is_err());
+
+        // Sent at once, the client can't keep up with its queue of 16.
+        let messages = server.messages();
+        for index in 0..100 {
+            let cob_id = if index % 2 == 0 { 0x181 } else { 0x182 };
+            let raw = RxMessage {
+                timestamp: Instant::now(),
+                cob_id,
+                data: [0x01, 0xAB, 0, 0, 0, 0, 0, 0],
+                dlc: 2,
+            };
+            messages
+                .send(Arc::new(MessageCached::new(index, raw)))
+                .unwrap();
+        }
+        let dropped = next_json(&mut ws).await;
+        assert_eq!(dropped["type"], "dropped");
+        assert_eq!(dropped["count"], 84);
+        for index in (84..100).step_by(2) {
+            let msg = next_json(&mut ws).await;
+            assert_eq!(msg["index"], index);
+            assert_eq!(msg["cob_id"], 0x181);
+            assert_eq!(msg["node"], 1);
+            assert_eq!(msg["class"], "tpdo1");
+            assert_eq!(msg["data"], "01AB");
+        }
+
+        let mut stats = server.stats();
+        stats.publish_if_due(Instant::now(), || BusStats::new().snapshot(Instant::now()));
+        let snapshot = next_json(&mut ws).await;
+        assert_eq!(snapshot["type"], "stats");
+        assert_eq!(snapshot["stats"]["total_messages"], 0);
+    }
+}
@@ -7,8 +7,15 @@ mod tests {
         interface::Connection,
         proto::nmt::{NmtCommand, NmtCommandSpecifier},
     };
-    use oze_canopen_viewer::driver::{self, Control};
-    use tokio::{sync::watch, time::sleep};
+    use oze_canopen_viewer::driver::{self, ConnectionStatus, Control};
+    use socketcan::{tokio::CanSocket, CanFrame, EmbeddedFrame, StandardId};
+    use tokio::{
+        sync::{mpsc, watch},
+        time::{sleep, timeout, Instant},
+    };
+
+    /// Frames of the burst, within the capacity of the data channel.
+    const BURST: u32 = 10_000;
 
     async fn send_test_messages() {
         let (interface, mut handles) = canopen::start(String::from("vcan0"), None);
@@ -23,20 +30,46 @@ mod tests {
         handles.close_and_join().await;
     }
 
-    #[tokio::test]
-    async fn test_driver_start() {
-        let initial_control = Control {
-            command: driver::ControlCommand::Process,
+    /// Sends `count` frames 0x181 numbered in their data, far above the rate of a 1 Mbit bus.
+    async fn send_burst(count: u32) {
+        let sock = CanSocket::open("vcan0").unwrap();
+        let id = StandardId::new(0x181).unwrap();
+        for i in 0..count {
+            let frame = CanFrame::new(id, &i.to_le_bytes()).unwrap();
+            while sock.write_frame(frame).await.is_err() {
+                sleep(Duration::from_millis(1)).await;
+            }
+            // Keep the receive buffer of the driver socket from overflowing.
+            if i % 64 == 63 {
+                sleep(Duration::from_millis(1)).await;
+            }
+        }
+    }
+
+    fn control(command: driver::ControlCommand) -> Control {
+        Control {
+            command,
             connection: Connection {
                 can_name: "vcan0".to_owned(),
                 bitrate: Some(100_000),
             },
-        };
+            kernel_filters: Vec::new(),
+            tx_rate: None,
+            gateway: None,
+            exit_nmt: None,
+            auto_start: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_driver_start() {
+        let initial_control = control(driver::ControlCommand::Process);
 
         let (state_snd, state_rcv) = watch::channel(driver::State::default());
+        let (data_snd, mut data_rcv) = mpsc::channel(driver::DATA_CHANNEL_CAPACITY);
         let (ctrl_snd, ctrl_rcv) = watch::channel(initial_control.clone());
-        let (_write_snd, write_rcv) = tokio::sync::mpsc::channel(100);
-        let drv = driver::Driver::new(state_snd, ctrl_rcv, write_rcv);
+        let (_write_snd, write_rcv) = mpsc::channel(100);
+        let drv = driver::Driver::new(state_snd, data_snd, ctrl_rcv, write_rcv);
         let driver_handle = drv.start_thread();
 
         sleep(Duration::from_millis(100)).await;
@@ -44,8 +77,12 @@ mod tests {
 
         send_test_messages().await;
 
-        assert!(!state_rcv.borrow().data.is_empty());
-        assert_eq!(state_rcv.borrow().data.back().unwrap().hex_str, "01 00");
+        let mut last = None;
+        while let Ok(msg) = data_rcv.try_recv() {
+            last = Some(msg);
+        }
+        assert_eq!(last.unwrap().hex_str, "01 00");
+        assert_eq!(state_rcv.borrow().dropped, 0);
 
         ctrl_snd
             .send(Control {
@@ -57,4 +94,90 @@ mod tests {
         driver_handle.await.unwrap();
         assert!(state_rcv.borrow().exit_signal);
     }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_driver_high_rate() {
+        let (state_snd, mut state_rcv) = watch::channel(driver::State::default());
+        let (data_snd, mut data_rcv) = mpsc::channel(driver::DATA_CHANNEL_CAPACITY);
+        let (ctrl_snd, ctrl_rcv) = watch::channel(control(driver::ControlCommand::Process));
+        let (_write_snd, write_rcv) = mpsc::channel(100);
+        let driver_handle = driver::Driver::new(state_snd, data_snd, ctrl_rcv, write_rcv).start_thread();
+        sleep(Duration::from_millis(200)).await;
+
+        // Every change the GUI could see, the state is published at a bounded rate.
+        let published = tokio::spawn(async move {
+            let mut count = 0u32;
+            while state_rcv.changed().await.is_ok() {
+                count += 1;
+                if state_rcv.borrow_and_update().exit_signal {
+                    break;
+                }
+            }
+            (count, state_rcv)
+        });
+        let start = Instant::now();
+        send_burst(BURST).await;
+        sleep(driver::STATE_PUBLISH_PERIOD * 5).await;
+
+        let mut received = Vec::new();
+        while let Ok(msg) = data_rcv.try_recv() {
+            if msg.msg.msg.cob_id == 0x181 {
+                received.push(msg);
+            }
+        }
+        assert_eq!(received.len(), BURST as usize);
+        for (i, msg) in (0..BURST).zip(&received) {
+            assert_eq!(msg.msg.msg.data[..4], i.to_le_bytes());
+        }
+        assert!(received.windows(2).all(|w| w[0].index < w[1].index));
+
+        ctrl_snd.send(control(driver::ControlCommand::Kill)).unwrap();
+        timeout(Duration::from_secs(1), driver_handle)
+            .await
+            .expect("the driver exits promptly")
+            .unwrap();
+        let elapsed = start.elapsed();
+        let (count, state_rcv) = published.await.unwrap();
+        let state = state_rcv.borrow();
+        assert!(state.exit_signal);
+        assert_eq!(state.dropped, 0);
+        assert_eq!(state.status, ConnectionStatus::Active);
+        let max = elapsed.as_millis() / driver::STATE_PUBLISH_PERIOD.as_millis() + 5;
+        assert!(u128::from(count) <= max, "{count} states published in {elapsed:?}");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_driver_burst_loss() {
+        // The GUI reads nothing during the burst, only the first `capacity` messages fit.
+        let capacity = 1000;
+        let (state_snd, state_rcv) = watch::channel(driver::State::default());
+        let (data_snd, mut data_rcv) = mpsc::channel(capacity);
+        let (ctrl_snd, ctrl_rcv) = watch::channel(control(driver::ControlCommand::Process));
+        let (_write_snd, write_rcv) = mpsc::channel(100);
+        let driver_handle = driver::Driver::new(state_snd, data_snd, ctrl_rcv, write_rcv).start_thread();
+        sleep(Duration::from_millis(200)).await;
+
+        send_burst(BURST).await;
+        sleep(driver::STATE_PUBLISH_PERIOD * 5).await;
+        let mut received = Vec::new();
+        while let Ok(msg) = data_rcv.try_recv() {
+            received.push(msg);
+        }
+        // The next message after the loss carries the marker.
+        send_burst(1).await;
+        sleep(driver::STATE_PUBLISH_PERIOD * 5).await;
+        let next = data_rcv.try_recv().unwrap();
+
+        let state = state_rcv.borrow().clone();
+        assert_eq!(received.len(), capacity);
+        let lost = u64::from(BURST) - capacity as u64;
+        assert_eq!(state.dropped, lost);
+        assert_eq!(next.index - received.last().unwrap().index - 1, lost);
+        let marker = state.sessions.back().unwrap();
+        assert_eq!(marker.index, next.index);
+        assert_eq!(marker.text, format!("--- {lost} messages lost ---"));
+
+        ctrl_snd.send(control(driver::ControlCommand::Kill)).unwrap();
+        driver_handle.await.unwrap();
+    }
 }